@@ -0,0 +1,31 @@
+// Benchmarks of the exact endgame solver by the number of empty cells, to choose when `EndgameExact` takes over.
+// Every position is drawn by `PositionGenerator` with that many cells empty, without a winner.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use quarto::{board::Board, position::PositionGenerator, random::SharedRng, solver::Solver};
+
+fn solve_endgame(c: &mut Criterion) {
+    let generator = PositionGenerator::new().with_rng(SharedRng::seeded(7));
+    let mut group = c.benchmark_group("solve endgame");
+    group.sample_size(10);
+    for empties in [5, 6, 7, 8, 9] {
+        let positions: Vec<Board> = (0..8)
+            .filter_map(|_| generator.board(16 - empties))
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(empties),
+            &positions,
+            |b, positions| {
+                b.iter(|| {
+                    for board in positions {
+                        Solver::new().piece_outcomes(board);
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, solve_endgame);
+criterion_main!(benches);
@@ -0,0 +1,168 @@
+// Benchmarks of the board layouts behind `BoardRepr`, to evaluate the attribute planes against the u128 cells.
+// The layouts are compared directly, and a search on `Board` measures the layout picked by the `plane-board` feature.
+// The winner check of the cells is compared with the loop over lines and attributes it replaced,
+// and the masks of the pieces and indices left with the vectors they save allocating.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use quarto::{
+    board::Board,
+    repr::{BoardRepr, CellRepr, PlaneRepr},
+    search::Searcher,
+};
+
+/// Random orders of the pieces and the indices for full games.
+fn games(count: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut rng = fastrand::Rng::with_seed(2778);
+    (0..count)
+        .map(|_| {
+            let (mut pieces, mut indices): (Vec<u8>, Vec<u8>) =
+                ((0..16).collect(), (0..16).collect());
+            rng.shuffle(&mut pieces);
+            rng.shuffle(&mut indices);
+            (pieces, indices)
+        })
+        .collect()
+}
+
+/// Place every piece of every game, checking for a winner and the pieces left after every placement.
+fn play<R: BoardRepr>(games: &[(Vec<u8>, Vec<u8>)]) -> usize {
+    let mut wins = 0;
+    for (pieces, indices) in games {
+        let mut repr = R::default();
+        for (&piece, &index) in pieces.iter().zip(indices) {
+            repr.place(piece, index);
+            let winner = (0..4).any(|line| repr.winning_row(line) || repr.winning_column(line))
+                || repr.winning_diagonal();
+            wins += usize::from(winner);
+            wins += (0..16).filter(|&p| !repr.is_used(p)).count() & 1;
+        }
+    }
+    wins
+}
+
+/// The winner check of the cells before the masks: every line, then every attribute bit, with a branch each.
+fn looped_winner(items: u128) -> bool {
+    let all = |mask: u128| items & mask == mask;
+    let line = |mask: u128| all(mask) && (4..8).any(|t| all(mask << t) || items & (mask << t) == 0);
+    let (row, column) = (0x01010101, 0x01000000_01000000_01000000_01000000);
+    let (down, up) = (
+        0x01000000_00010000_00000100_00000001,
+        0x01_00000100_00010000_01000000,
+    );
+    (0..4).any(|i| line(row << (32 * i)) || line(column << (8 * i))) || line(down) || line(up)
+}
+
+/// Every board along the games, after every placement.
+fn positions(games: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<Board> {
+    games
+        .into_iter()
+        .flat_map(|(pieces, indices)| {
+            let mut board = Board::new();
+            pieces.into_iter().zip(indices).map(move |(piece, index)| {
+                board.put_piece(piece, index).unwrap();
+                board
+            })
+        })
+        .collect()
+}
+
+fn compare_winner_checks(c: &mut Criterion) {
+    let boards = positions(games(100));
+    let mut group = c.benchmark_group("winner check");
+    group.bench_function("loops", |b| {
+        b.iter(|| {
+            let boards = black_box(&boards);
+            boards
+                .iter()
+                .filter(|board| looped_winner(board.items()))
+                .count()
+        })
+    });
+    group.bench_function("masks", |b| {
+        b.iter(|| {
+            let boards = black_box(&boards);
+            boards
+                .iter()
+                .filter(|board| CellRepr::from_u128(board.items()).has_line_winner())
+                .count()
+        })
+    });
+    group.bench_function("has_winner", |b| {
+        b.iter(|| {
+            black_box(&boards)
+                .iter()
+                .filter(|board| board.has_winner())
+                .count()
+        })
+    });
+    group.bench_function("has_winner_fast", |b| {
+        b.iter(|| {
+            let boards = black_box(&boards);
+            boards
+                .iter()
+                .filter(|board| board.has_winner_fast())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn compare_move_lists(c: &mut Criterion) {
+    let boards = positions(games(100));
+    let mut group = c.benchmark_group("moves left");
+    group.bench_function("vectors", |b| {
+        b.iter(|| {
+            black_box(&boards)
+                .iter()
+                .map(|board| board.valid_pieces().len() * board.empty_spaces().len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("masks", |b| {
+        b.iter(|| {
+            black_box(&boards)
+                .iter()
+                .map(|board| board.available_pieces().len() * board.empty_cells().len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+fn compare_layouts(c: &mut Criterion) {
+    let games = games(100);
+    let mut group = c.benchmark_group("play games");
+    group.bench_function("cells", |b| b.iter(|| play::<CellRepr>(black_box(&games))));
+    group.bench_function("planes", |b| {
+        b.iter(|| play::<PlaneRepr>(black_box(&games)))
+    });
+    group.finish();
+}
+
+fn search_board(c: &mut Criterion) {
+    let board = Board::new()
+        .apply_moves(&[(0, 0), (15, 5), (3, 10), (12, 15), (5, 3), (10, 6)])
+        .unwrap();
+    let mut group = c.benchmark_group("search board");
+    group.sample_size(10);
+    let layout = if cfg!(feature = "plane-board") {
+        "planes"
+    } else {
+        "cells"
+    };
+    group.bench_function(layout, |b| {
+        b.iter(|| Searcher::new().best_move(black_box(&board), 6, 3))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    compare_layouts,
+    compare_move_lists,
+    compare_winner_checks,
+    search_board
+);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+// Writes the C header of the `ffi` feature to the build directory, from the functions in src/ffi.rs,
+// and the gRPC server and client of the `grpc` feature. Without the features there is nothing to build.
+// The header is only written into the sources on request: `QUARTO_HEADER_DIR=include cargo build --features ffi`
+// refreshes include/quarto.h.
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-env-changed=QUARTO_HEADER_DIR");
+        let dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets the manifest directory");
+        let header = cbindgen::Builder::new()
+            .with_language(cbindgen::Language::C)
+            .with_include_guard("QUARTO_H")
+            .with_header(
+                "/* The C API of the quarto crate, generated by cbindgen from src/ffi.rs. */",
+            )
+            .with_src(format!("{}/src/ffi.rs", dir))
+            .generate()
+            .expect("the ffi module can be read by cbindgen");
+        let out = std::env::var("OUT_DIR").expect("cargo sets the output directory");
+        header.write_to_file(format!("{}/quarto.h", out));
+        if let Ok(include) = std::env::var("QUARTO_HEADER_DIR") {
+            header.write_to_file(format!("{}/quarto.h", include));
+        }
+    }
+    #[cfg(feature = "grpc")]
+    grpc();
+    #[cfg(not(feature = "ffi"))]
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Generate the service of proto/quarto.proto for the prost messages in src/grpc.rs, without protoc.
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type(format!("crate::grpc::{}", output))
+            .codec_path("tonic_prost::ProstCodec")
+            .build()
+    };
+    let service = Service::builder()
+        .name("Quarto")
+        .package("quarto")
+        .method(method(
+            "create_game",
+            "CreateGame",
+            "CreateGameRequest",
+            "Game",
+        ))
+        .method(method("get_game", "GetGame", "GameRequest", "Game"))
+        .method(method("play", "Play", "PlayRequest", "Game"))
+        .method(method("best_move", "BestMove", "GameRequest", "Move"))
+        .method(method("solve", "Solve", "GameRequest", "SolveResponse"))
+        .build();
+    Builder::new().compile(&[service]);
+}
@@ -0,0 +1,111 @@
+/* The C API of the quarto crate, generated by cbindgen from src/ffi.rs. */
+
+#ifndef QUARTO_H
+#define QUARTO_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Returned by `quarto_engine_choose_move` for a piece that is not a number from 0 to 15.
+ */
+#define QUARTO_INVALID_PIECE -2
+
+/**
+ * A game in progress: the board and the pieces placed on it.
+ */
+typedef struct QuartoBoard QuartoBoard;
+
+/**
+ * A computer player of the registry.
+ */
+typedef struct QuartoEngine QuartoEngine;
+
+/**
+ * Create an empty board, free it with `quarto_board_free`.
+ */
+struct QuartoBoard *quarto_board_new(void);
+
+/**
+ * Free a board of `quarto_board_new`.
+ *
+ * # Safety
+ * `board` is null or a board of `quarto_board_new` that was not freed yet.
+ */
+void quarto_board_free(struct QuartoBoard *board);
+
+/**
+ * Place `piece` on `index`: 0 if it was placed, -1 if that is not allowed or `board` is null.
+ *
+ * # Safety
+ * `board` is null or a live board of `quarto_board_new`.
+ */
+int32_t quarto_board_put_piece(struct QuartoBoard *board, uint8_t piece, uint8_t index);
+
+/**
+ * The piece on `index`, or -1 if the cell is empty.
+ *
+ * # Safety
+ * `board` is null or a live board of `quarto_board_new`.
+ */
+int32_t quarto_board_piece_at(const struct QuartoBoard *board, uint8_t index);
+
+/**
+ * Check if a line is won.
+ *
+ * # Safety
+ * `board` is null or a live board of `quarto_board_new`.
+ */
+bool quarto_board_has_winner(const struct QuartoBoard *board);
+
+/**
+ * Check if the game is won or the board is full.
+ *
+ * # Safety
+ * `board` is null or a live board of `quarto_board_new`.
+ */
+bool quarto_board_game_over(const struct QuartoBoard *board);
+
+/**
+ * Create the computer player `name` of the registry, like `smart` or `mcts`, searching `depth` turns
+ * or `iterations` playouts ahead. A `seed` other than 0 makes its random decisions repeat.
+ * Returns null for an unknown name, free the engine with `quarto_engine_free`.
+ *
+ * # Safety
+ * `name` is null or a string that ends with a 0 byte.
+ */
+struct QuartoEngine *quarto_engine_new(const char *name,
+                                       uint8_t depth,
+                                       uint32_t iterations,
+                                       uint64_t seed);
+
+/**
+ * Free an engine of `quarto_engine_new`.
+ *
+ * # Safety
+ * `engine` is null or an engine of `quarto_engine_new` that was not freed yet.
+ */
+void quarto_engine_free(struct QuartoEngine *engine);
+
+/**
+ * The piece the engine hands over to its opponent, or -1 if there is none.
+ *
+ * # Safety
+ * `engine` and `board` are null or live values of `quarto_engine_new` and `quarto_board_new`.
+ */
+int32_t quarto_engine_choose_piece(const struct QuartoEngine *engine,
+                                   const struct QuartoBoard *board);
+
+/**
+ * The cell the engine places `piece` on, -1 if there is none, or `QUARTO_INVALID_PIECE` for a piece over 15.
+ *
+ * # Safety
+ * `engine` and `board` are null or live values of `quarto_engine_new` and `quarto_board_new`.
+ */
+int32_t quarto_engine_choose_move(const struct QuartoEngine *engine,
+                                  const struct QuartoBoard *board,
+                                  uint8_t piece);
+
+#endif  /* QUARTO_H */
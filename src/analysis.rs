@@ -0,0 +1,334 @@
+// Post-game analysis, which goes over every decision of a recorded game and marks the mistakes.
+// Late positions are proven with the solver, earlier ones only get the one-ply checks of the harness.
+
+use std::fmt;
+
+use crate::{
+    board::Board,
+    notation::Cell,
+    printable::Piece,
+    record::GameRecord,
+    solver::{Outcome, Solver},
+};
+
+/// The kind of decision a mistake was made in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Handing over a piece.
+    Piece,
+    /// Placing the piece in hand.
+    Placement,
+}
+
+/// A decision that made the position worse for the player who made it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mistake {
+    /// The index of the turn in the record, from 0.
+    pub turn: usize,
+    pub player: usize,
+    pub decision: Decision,
+    /// The piece that was handed over, or the cell the piece was placed on.
+    pub played: u8,
+    /// A piece or cell that was better.
+    pub better: u8,
+    /// The outcome `better` keeps for the player, when it was proven.
+    pub could: Option<Outcome>,
+    /// The outcome after `played`, when it was proven.
+    pub got: Option<Outcome>,
+}
+
+impl fmt::Display for Mistake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome =
+            |outcome: Option<Outcome>| outcome.map_or(String::new(), |o| format!(" ({})", o));
+        match self.decision {
+            Decision::Piece => write!(
+                f,
+                "Move {}: player {} handed over {}{}, {} was better{}.",
+                self.turn + 1,
+                self.player + 1,
+                Piece::from_number(self.played).code(),
+                outcome(self.got),
+                Piece::from_number(self.better).code(),
+                outcome(self.could)
+            ),
+            Decision::Placement => write!(
+                f,
+                "Move {}: player {} placed on {}{}, {} was better{}.",
+                self.turn + 1,
+                self.player + 1,
+                cell(self.played),
+                outcome(self.got),
+                cell(self.better),
+                outcome(self.could)
+            ),
+        }
+    }
+}
+
+fn cell(index: u8) -> String {
+    Cell::new(index).map_or(index.to_string(), |c| c.to_string())
+}
+
+/// The mistakes found in a game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    pub mistakes: Vec<Mistake>,
+    /// The number of decisions that were proven with the solver.
+    pub proven: usize,
+    /// The number of decisions that were only checked one ply deep.
+    pub checked: usize,
+}
+
+impl Analysis {
+    /// The mistakes `player` made.
+    pub fn mistakes_of(&self, player: usize) -> impl Iterator<Item = &Mistake> {
+        self.mistakes.iter().filter(move |m| m.player == player)
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mistake in &self.mistakes {
+            writeln!(f, "{}", mistake)?;
+        }
+        if self.mistakes.is_empty() {
+            writeln!(f, "No mistakes found.")?;
+        }
+        for player in 0..2 {
+            writeln!(
+                f,
+                "Player {}: {} mistakes",
+                player + 1,
+                self.mistakes_of(player).count()
+            )?;
+        }
+        writeln!(
+            f,
+            "{} decisions proven, {} checked one ply deep",
+            self.proven, self.checked
+        )
+    }
+}
+
+/// Analyzes recorded games, proving the decisions on boards with few enough empty cells.
+#[derive(Debug)]
+pub struct Analyzer {
+    solver: Solver,
+    exact: usize,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer {
+            solver: Solver::new(),
+            exact: 8,
+        }
+    }
+}
+
+impl Analyzer {
+    /// Create an `Analyzer` that proves decisions once at most eight cells are empty.
+    pub fn new() -> Self {
+        Analyzer::default()
+    }
+
+    /// Prove decisions with `solver`, for example one with a memory limit.
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Prove the decisions on boards with at most `empty` empty cells, check the others one ply deep.
+    pub fn with_exact(mut self, empty: usize) -> Self {
+        self.exact = empty;
+        self
+    }
+
+    /// Go over every turn of `record` and collect the mistakes.
+    /// Fails if a turn of the record cannot be played.
+    pub fn analyze(&mut self, record: &GameRecord) -> Result<Analysis, String> {
+        let mut analysis = Analysis::default();
+        let mut board = Board::new().with_ruleset(record.ruleset());
+        for (number, turn) in record.turns().iter().enumerate() {
+            let exact = board.empty_spaces().len() <= self.exact;
+            let piece = if exact {
+                self.prove_piece(&board, turn.piece)
+            } else {
+                check_piece(&board, turn.piece)
+            };
+            let placement = if exact {
+                self.prove_placement(&board, turn.piece, turn.index)
+            } else {
+                check_placement(&board, turn.piece, turn.index)
+            };
+            let mistakes = [
+                (Decision::Piece, turn.giver, turn.piece, piece),
+                (Decision::Placement, turn.placer, turn.index, placement),
+            ];
+            for (decision, player, played, found) in mistakes {
+                if let Some((better, could, got)) = found {
+                    analysis.mistakes.push(Mistake {
+                        turn: number,
+                        player,
+                        decision,
+                        played,
+                        better,
+                        could,
+                        got,
+                    });
+                }
+            }
+            if exact {
+                analysis.proven += 2;
+            } else {
+                analysis.checked += 2;
+            }
+            board
+                .put_piece(turn.piece, turn.index)
+                .map_err(|e| format!("move {} is illegal: {}", number + 1, e))?;
+        }
+        Ok(analysis)
+    }
+
+    /// Compare handing over `given` to the best piece, as proven by the solver.
+    fn prove_piece(&mut self, board: &Board, given: u8) -> Option<Found> {
+        let outcomes = self.solver.piece_outcomes(board);
+        worse_than_best(&outcomes, given)
+    }
+
+    /// Compare placing `piece` on `index` to the best placement, as proven by the solver.
+    fn prove_placement(&mut self, board: &Board, piece: u8, index: u8) -> Option<Found> {
+        let outcomes = self.solver.placement_outcomes(board, piece);
+        worse_than_best(&outcomes, index)
+    }
+}
+
+/// A better choice, with the outcome it keeps and the outcome of the choice that was made.
+type Found = (u8, Option<Outcome>, Option<Outcome>);
+
+/// The best of `outcomes`, if it is better than the outcome of `played`.
+fn worse_than_best(outcomes: &[(u8, Outcome)], played: u8) -> Option<Found> {
+    let got = outcomes.iter().find(|(choice, _)| *choice == played)?.1;
+    let &(better, could) = outcomes
+        .iter()
+        .max_by_key(|(choice, outcome)| (*outcome, -i16::from(*choice)))?;
+    (could > got).then_some((better, Some(could), Some(got)))
+}
+
+/// Handing over `given` loses at once when a safe piece was still available.
+fn check_piece(board: &Board, given: u8) -> Option<Found> {
+    let safe = board.safe_pieces();
+    if safe.contains(&given) {
+        return None;
+    }
+    safe.first()
+        .map(|&better| (better, None, Some(Outcome::Loss)))
+}
+
+/// Placing `piece` on `index` misses a win when another cell completes a Quarto.
+fn check_placement(board: &Board, piece: u8, index: u8) -> Option<Found> {
+    let wins = board.winning_placements(piece);
+    if wins.is_empty() || wins.contains(&index) {
+        return None;
+    }
+    Some((wins[0], Some(Outcome::Win), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::GameResult, record::Turn};
+
+    use super::*;
+
+    fn record(moves: &[(u8, u8)]) -> GameRecord {
+        let mut record = GameRecord::new();
+        for (ply, &(piece, index)) in moves.iter().enumerate() {
+            record.push(Turn {
+                giver: ply % 2,
+                piece,
+                placer: 1 - ply % 2,
+                index,
+                quarto_called: false,
+            });
+        }
+        record.set_result(GameResult::Draw);
+        record
+    }
+
+    #[test]
+    fn test_one_ply_mistakes() {
+        // Three tall pieces on the top row: player 2 hands over another tall piece, player 1 does not take the win.
+        let analysis = Analyzer::new()
+            .analyze(&record(&[(8, 0), (9, 1), (10, 2), (11, 15)]))
+            .unwrap();
+        assert_eq!(analysis.checked, 8);
+        assert_eq!(
+            analysis.mistakes,
+            vec![
+                Mistake {
+                    turn: 3,
+                    player: 1,
+                    decision: Decision::Piece,
+                    played: 11,
+                    better: 4,
+                    could: None,
+                    got: Some(Outcome::Loss),
+                },
+                Mistake {
+                    turn: 3,
+                    player: 0,
+                    decision: Decision::Placement,
+                    played: 15,
+                    better: 3,
+                    could: Some(Outcome::Win),
+                    got: None,
+                },
+            ]
+        );
+        assert_eq!(analysis.mistakes_of(0).count(), 1);
+        assert!(analysis.to_string().contains("Move 4: player 1 placed on"));
+    }
+
+    #[test]
+    fn test_proven_mistakes_agree_with_solver() {
+        // Fill the board without handing over a winning piece or completing a line, as far as that goes.
+        let mut board = Board::new();
+        let mut moves: Vec<(u8, u8)> = Vec::new();
+        while let Some(&piece) = board.safe_pieces().first() {
+            let Some(index) = board.empty_spaces().into_iter().find(|&index| {
+                let mut next = board;
+                next.put_piece(piece, index).is_ok() && !next.has_winner()
+            }) else {
+                break;
+            };
+            board.put_piece(piece, index).unwrap();
+            moves.push((piece, index));
+        }
+        assert!(moves.len() > 11);
+        let analysis = Analyzer::new()
+            .with_exact(6)
+            .analyze(&record(&moves))
+            .unwrap();
+        assert_eq!(analysis.proven + analysis.checked, 2 * moves.len());
+        assert_eq!(analysis.checked, 20);
+        let mut solver = Solver::new();
+        let mut board = Board::new();
+        for (turn, &(piece, index)) in moves.iter().enumerate() {
+            if turn >= 10 {
+                let best = solver
+                    .piece_outcomes(&board)
+                    .into_iter()
+                    .map(|(_, o)| o)
+                    .max();
+                let given = solver.solve(&board, piece).flip();
+                let marked = analysis
+                    .mistakes
+                    .iter()
+                    .any(|m| m.turn == turn && m.decision == Decision::Piece);
+                assert_eq!(marked, best > Some(given));
+            }
+            board.put_piece(piece, index).unwrap();
+        }
+    }
+}
@@ -1,171 +1,552 @@
 // Author: @julianvansanten
 // A bitboard to store the Quarto board.
 
-use crate::printable::PrintableBoard;
+use std::{fmt, str::FromStr};
+
+pub use crate::rules::Ruleset;
+use crate::{
+    error::{BoardIssue, PlacementError, QuartoError},
+    line::{CELL_MASKS, DIAGONALS, INDEX_MASKS, LINES_THROUGH, Line, LineState, WinningLine},
+    notation::{Cell, Move},
+    printable::{Piece, PrintableBoard},
+    repr::{BoardRepr, DefaultRepr},
+};
 
 /// The bit size of a single piece.
 pub const PIECE_SIZE: u8 = 8;
-/// The bits set to check existence in the right-most column.
-/// Left-shift `COLUMN` by PIECE per column.
-const COLUMN: u128 =
-    0b1 + (0b1 << 4 * PIECE_SIZE) + (0b1 << 8 * PIECE_SIZE) + (0b1 << 12 * PIECE_SIZE);
-/// The bits set to check existence in the lowest row.
-/// Left-shift `ROW` by 4 * PIECE per row.
-const ROW: u128 = 0b1 + (0b1 << PIECE_SIZE) + (0b1 << 2 * PIECE_SIZE) + (0b1 << 3 * PIECE_SIZE);
-/// The bits set to check existence on the whole board.
-const BOARD_MASK: u128 =
-    COLUMN + (COLUMN << PIECE_SIZE) + (COLUMN << PIECE_SIZE * 2) + (COLUMN << PIECE_SIZE * 3);
-/// The bits set to check existence in the down diagonal.
-const DIAG_DOWN: u128 =
-    0b1 + (0b1 << 5 * PIECE_SIZE) + (0b1 << 10 * PIECE_SIZE) + (0b1 << 15 * PIECE_SIZE);
-/// The bits set to check existence in the up diagonal.
-const DIAG_UP: u128 = (0b1 << 3 * PIECE_SIZE)
-    + (0b1 << 6 * PIECE_SIZE)
-    + (0b1 << 9 * PIECE_SIZE)
-    + (0b1 << 12 * PIECE_SIZE);
-
-/// A Quarto board is stored as a `u128`.
+/// Mixed into the hash of a board with other than the classic rules, so the same pieces hash differently.
+const RULESET_KEY: u64 = 0x2a2a_5155_4152_544f;
+/// Random keys for every piece on every index, used to hash a board.
+/// Generated at compile time with SplitMix64 from a fixed seed, so hashes are stable between runs.
+const ZOBRIST: [[u64; 16]; 16] = zobrist_keys();
+
+/// Generate the `ZOBRIST` keys, indexed by board index and then piece.
+const fn zobrist_keys() -> [[u64; 16]; 16] {
+    let mut keys = [[0u64; 16]; 16];
+    let mut state: u64 = 0x5155_4152_544f_2121;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i / 16][i % 16] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// A Quarto board is exchanged as a `u128`.
 /// Each cell is 8 bits, so the entire board is 8 * 16 = 128.
 /// Each 8 bits represent a state of the cell: the leftmost 4 bits symbolize the 4 categories, the rightmost bit signals the existence of a piece.
+/// It is stored in the layout of `DefaultRepr`, which is the same `u128` unless the `plane-board` feature is enabled.
+/// The board knows its `Ruleset`, so everything that asks it for a winner follows the rules of the game.
+/// The `u128` does not include the ruleset, a board made from one plays by the classic rules.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Board {
-    items: u128,
+    repr: DefaultRepr,
+    ruleset: Ruleset,
+}
+
+/// What a placement did to the game, as found by `Board::put_piece_checked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementOutcome {
+    /// The piece completed a winning line.
+    Win,
+    /// The piece filled the board without completing a winning line.
+    Draw,
+    /// The game goes on.
+    Ongoing,
+}
+
+/// The set bits of a 16-bit mask, lowest first, such as the pieces left or the empty indices of a board.
+/// Search loops over these instead of the vectors of `Board::valid_pieces` and `Board::empty_spaces`, as they do not allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bits(pub u16);
+
+impl Iterator for Bits {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as u8;
+        // Clear the lowest set bit.
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Bits {}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+impl TryFrom<&[Option<Piece>]> for Board {
+    type Error = QuartoError;
+
+    /// Create a `Board` from a list of 16 cells in index order.
+    /// Fails if the list has another length, or if a piece occurs more than once.
+    fn try_from(cells: &[Option<Piece>]) -> Result<Self, Self::Error> {
+        if cells.len() != 16 {
+            return Err(QuartoError::WrongCellCount { len: cells.len() });
+        }
+        let mut board: Board = Board::new();
+        for (index, cell) in cells.iter().enumerate() {
+            if let Some(piece) = cell {
+                board.put_piece(piece.to_number(), index as u8)?;
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Write the board as the grid of `PrintableBoard`, with the attribute bits of every piece.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", PrintableBoard::new(self))
+    }
+}
+
+/// Serialized as the piece number on each of the 16 indices, or nothing for an empty index.
+/// This does not depend on the representation, so data stays readable with or without `plane-board`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let cells: Vec<Option<u8>> = (0..16).map(|index| self.piece_at(index)).collect();
+        cells.serialize(serializer)
+    }
+}
+
+/// Deserialized from the form of `Serialize`, rejecting boards with a piece on the board twice.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cells = Vec::<Option<u8>>::deserialize(deserializer)?;
+        let pieces = cells
+            .iter()
+            .map(|cell| match cell {
+                Some(number) if *number >= 16 => Err(serde::de::Error::custom(format!(
+                    "there is no piece {}",
+                    number
+                ))),
+                _ => Ok(cell.map(Piece::from_number)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Board::try_from(pieces.as_slice()).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Board {
+    type Err = String;
+
+    /// Read the grid that `Display` writes, or the compact form of `Board::compact`.
+    /// Either can end in the name of a ruleset, the board plays by the classic rules without one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens: Vec<&str> = s.split_whitespace().collect();
+        // Cells only use hex digits, a last word with any other letter names the ruleset.
+        let mut ruleset = Ruleset::Classic;
+        if let [_, .., last] = tokens[..]
+            && last
+                .chars()
+                .any(|c| c.is_alphabetic() && !c.is_ascii_hexdigit())
+        {
+            ruleset = last.parse()?;
+            tokens.pop();
+        }
+        let cells: Vec<Option<u8>> = if tokens.len() == 16 && tokens.iter().all(|t| t.len() == 4) {
+            tokens
+                .iter()
+                .map(|&token| match token {
+                    "----" => Ok(None),
+                    bits => u8::from_str_radix(bits, 2)
+                        .map(Some)
+                        .map_err(|_| format!("'{}' is not a cell like 0101 or ----", bits)),
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            tokens
+                .concat()
+                .chars()
+                .filter(|&c| c != '/')
+                .map(|c| match c {
+                    '.' => Ok(None),
+                    c => c
+                        .to_digit(16)
+                        .map(|piece| Some(piece as u8))
+                        .ok_or_else(|| format!("'{}' is not a piece from 0 to f or '.'", c)),
+                })
+                .collect::<Result<_, _>>()?
+        };
+        let cells: Vec<Option<Piece>> = cells
+            .into_iter()
+            .map(|cell| cell.map(Piece::from_number))
+            .collect();
+        Board::try_from(cells.as_slice())
+            .map(|board| board.with_ruleset(ruleset))
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Everything wrong with the cells of `items`, see `Board::validate`.
+fn issues(items: u128) -> Vec<BoardIssue> {
+    let mut issues = Vec::new();
+    let mut indices: [Vec<u8>; 16] = Default::default();
+    for index in 0..16 {
+        let cell = (items >> (PIECE_SIZE * (15 - index))) as u8;
+        if cell & 0b1110 != 0 {
+            issues.push(BoardIssue::StrayBits { index });
+        }
+        match (cell & 1, cell >> 4) {
+            (1, piece) => indices[piece as usize].push(index),
+            (_, 0) => {}
+            _ => issues.push(BoardIssue::AttributesWithoutPiece { index }),
+        }
+    }
+    for (piece, indices) in indices.into_iter().enumerate() {
+        if indices.len() > 1 {
+            issues.push(BoardIssue::DuplicatePiece {
+                piece: piece as u8,
+                indices,
+            });
+        }
+    }
+    issues
 }
 
 impl Board {
     /// Create a new empty board.
     pub fn new() -> Self {
-        Board { items: 0 }
+        Board {
+            repr: DefaultRepr::default(),
+            ruleset: Ruleset::Classic,
+        }
+    }
+
+    /// Play by `ruleset` on this board.
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    /// Get the rules the board plays by.
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    /// Create a `Board` directly from its internal `u128` representation.
+    /// This does not validate the board, so it can contain duplicate pieces or attribute bits without a piece.
+    /// With the `plane-board` feature, attribute bits without a piece are dropped.
+    /// Use `try_from_u128` for input that may be corrupt.
+    pub fn from_u128(items: u128) -> Self {
+        Board {
+            repr: DefaultRepr::from_u128(items),
+            ruleset: Ruleset::Classic,
+        }
+    }
+
+    /// Create a `Board` from its `u128` representation, or list everything `validate` finds wrong with it.
+    /// The bits are checked before they are stored, so no layout drops a problem unseen.
+    pub fn try_from_u128(items: u128) -> Result<Self, Vec<BoardIssue>> {
+        let issues = issues(items);
+        if issues.is_empty() {
+            Ok(Board::from_u128(items))
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Check that the board is consistent: no piece twice, and no bits set in a cell without a piece.
+    /// A board has 16 cells and there are 16 pieces, so it cannot hold more pieces than exist.
+    pub fn validate(&self) -> Result<(), Vec<BoardIssue>> {
+        let issues = issues(self.items());
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Check that the board is consistent and can come up in a game.
+    /// The players take turns placing, so any number of pieces can be on the board. A win can only be reached
+    /// with a last placement that made it: removing one of the pieces should leave a board without a winner.
+    pub fn check_reachable(&self) -> Result<(), Vec<BoardIssue>> {
+        self.validate()?;
+        let last_placement = |index| {
+            let mut before = *self;
+            before.remove_piece(index).is_some() && !before.has_winner()
+        };
+        if self.has_winner() && !(0..16).any(last_placement) {
+            return Err(vec![BoardIssue::WonBeforeLastPlacement]);
+        }
+        Ok(())
+    }
+
+    /// Check if the board can come up in a game, see `check_reachable`.
+    pub fn is_reachable(&self) -> bool {
+        self.check_reachable().is_ok()
     }
 
     /// Check if the board is empty.
     pub fn is_empty(&self) -> bool {
-        self.items == 0
+        self.repr == DefaultRepr::default()
     }
 
-    /// Create a `Board` from a `PrintableBoard`.
-    pub fn from_printable(pboard: &PrintableBoard) -> Result<Self, &'static str> {
-        let pboard_items = pboard.items();
-        if pboard_items.len() != 16 {
-            return Err("The PrintableBoard does not contain 16 elements!");
+    /// Apply a sequence of `(piece, index)` placements to a copy of the board, validating every one of them.
+    /// Fails on the first placement that is not allowed, or that comes after the game is already over.
+    pub fn apply_moves(&self, moves: &[(u8, u8)]) -> Result<Board, QuartoError> {
+        let mut board = *self;
+        for (ply, &(piece, index)) in moves.iter().enumerate() {
+            if board.game_over() {
+                return Err(QuartoError::GameOver { ply });
+            }
+            board
+                .put_piece(piece, index)
+                .map_err(|error| QuartoError::IllegalMove { ply, error })?;
         }
+        Ok(board)
+    }
 
-        let mut board: Board = Board::new();
-        for (i, option) in pboard_items.iter().enumerate() {
-            match option {
-                // Safely place the items on the board, return an `Err` if there is a duplicate.
-                Some(piece) => {
-                    if !board.put_piece(piece.to_number(), i as u8) {
-                        // TODO: add formatted string that tells why it failed.
-                        return Err("Unable to put item on board! Perhaps it already exists?");
-                    }
-                }
-                None => continue,
-            };
+    /// Write the board in one line for logs and fixtures: the rows top to bottom separated by `/`,
+    /// with every piece as a hex digit and `.` for an empty cell, like `5.../..../..../...a`.
+    /// A board that does not play by the classic rules ends in its ruleset, like `5.../..../..../...a advanced`.
+    pub fn compact(&self) -> String {
+        format!("{}{}", self.rows(), self.ruleset_suffix())
+    }
+
+    /// The rows of `compact`, without the ruleset.
+    fn rows(&self) -> String {
+        let rows: Vec<String> = (0..4)
+            .map(|row| {
+                (0..4)
+                    .map(|column| match self.piece_at(row * 4 + column) {
+                        Some(piece) => char::from_digit(piece as u32, 16).unwrap_or('?'),
+                        None => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.join("/")
+    }
+
+    /// The name of the ruleset after a space, or nothing for the classic rules.
+    fn ruleset_suffix(&self) -> String {
+        if self.ruleset.is_classic() {
+            String::new()
+        } else {
+            format!(" {}", self.ruleset)
         }
-        Ok(board)
+    }
+
+    /// Write a position in one line: the compact board, the piece in hand as a hex digit or `-`,
+    /// and `mover`, the player to place the piece in hand or to hand over the next piece.
+    /// For example `5.../..c./..../...a 3 1` has piece 3 in hand for the second player.
+    /// A board that does not play by the classic rules ends in its ruleset, like `5.../..c./..../...a 3 1 advanced`.
+    pub fn to_fen(&self, in_hand: Option<u8>, mover: usize) -> String {
+        let hand = in_hand.map_or('-', |piece| {
+            char::from_digit(piece as u32, 16).unwrap_or('?')
+        });
+        format!(
+            "{} {} {}{}",
+            self.rows(),
+            hand,
+            mover,
+            self.ruleset_suffix()
+        )
+    }
+
+    /// Read a position written by `to_fen`, as the board, the piece in hand and the player to move.
+    /// Only the built-in rulesets can be read, see `Ruleset::from_str`.
+    pub fn from_fen(fen: &str) -> Result<(Board, Option<u8>, usize), String> {
+        let (board, hand, mover, ruleset) = match fen.split_whitespace().collect::<Vec<_>>()[..] {
+            [board, hand, mover] => (board, hand, mover, Ruleset::Classic),
+            [board, hand, mover, ruleset] => (board, hand, mover, ruleset.parse()?),
+            _ => {
+                return Err(format!(
+                    "'{}' is not a position like 5.../..c./..../...a 3 1",
+                    fen.trim()
+                ));
+            }
+        };
+        let board = board.parse::<Board>()?.with_ruleset(ruleset);
+        let in_hand = match hand {
+            "-" => None,
+            hand => {
+                let piece = u8::from_str_radix(hand, 16)
+                    .map_err(|_| format!("'{}' is not a piece from 0 to f or '-'", hand))?;
+                if !board.valid_piece(piece) {
+                    return Err(format!("piece {} in hand is not available", piece));
+                }
+                Some(piece)
+            }
+        };
+        let mover = match mover {
+            "0" => 0,
+            "1" => 1,
+            _ => return Err(format!("the player to move is 0 or 1, got '{}'", mover)),
+        };
+        Ok((board, in_hand, mover))
     }
 
     /// Get a copy of the internal `u128` board structure.
     pub fn items(&self) -> u128 {
-        self.items
+        self.repr.to_u128()
     }
 
-    /// Check if the index on the board is empty.
-    pub fn index_empty(&self, index: u8) -> bool {
+    /// Get the piece on the given index, or `None` if the index is empty or out of range.
+    pub fn piece_at(&self, index: u8) -> Option<u8> {
         if index > 15 {
-            return false;
+            return None;
         }
-        let pos_mask: u128 = 0b1 << ((15 - index) * PIECE_SIZE);
-        self.items & pos_mask == 0
+        self.repr.piece_at(index)
+    }
+
+    /// Check if the index on the board is empty.
+    pub fn index_empty(&self, index: u8) -> bool {
+        index < 16 && !self.repr.is_occupied(index)
+    }
+
+    /// Check if every cell of a line of `Line::ADVANCED` is occupied.
+    fn full(&self, line: Line) -> bool {
+        line.position().is_some_and(|position| {
+            let mask = INDEX_MASKS[position];
+            self.repr.occupied_mask() & mask == mask
+        })
     }
 
     /// Check if a row on the board is full.
     /// The `row` value must lie between 0 and (incl.) 3.
     fn row(&self, row: u8) -> bool {
-        if row > 3 {
-            return false;
-        }
-        let row_mask = ROW << (4 * PIECE_SIZE * (3 - row));
-        self.items & row_mask == row_mask
+        row < 4 && self.full(Line::Row(row))
     }
 
     /// Check if a column on the board is full.
     /// The `column` value must lie between 0 and (incl.) 3.
     fn column(&self, column: u8) -> bool {
-        if column > 3 {
-            return false;
-        }
-        let col_mask = COLUMN << (PIECE_SIZE * (3 - column));
-        self.items & col_mask == col_mask
+        column < 4 && self.full(Line::Column(column))
     }
 
     /// Check if a row on the board is full and has blocks with one common characteristic.
     /// The `row` value must lie between 0 and (incl.) 3.
     pub fn winning_row(&self, row: u8) -> bool {
-        if !self.row(row) {
-            return false;
-        }
-        for t in 4..8 {
-            let row_mask = ROW << (4 * PIECE_SIZE * (3 - row) + t);
-            if self.items & row_mask == row_mask || self.items & row_mask == 0 {
-                return true;
-            }
-        }
-        false
+        self.row(row) && self.repr.winning_row(row)
     }
 
     /// Check if a column on the board is full and has blocks with one common characteristic.
     /// The `column` value must lie between 0 and (incl.) 3.
     pub fn winning_column(&self, column: u8) -> bool {
-        if !self.column(column) {
-            return false;
-        }
-        for t in 4..8 {
-            let col_mask = COLUMN << (PIECE_SIZE * (3 - column) + t);
-            if self.items & col_mask == col_mask || self.items & col_mask == 0 {
-                return true;
-            }
-        }
-        false
+        self.column(column) && self.repr.winning_column(column)
     }
 
     /// Check if a diagonal on the board is full and has blocks with one common characteristic.
     pub fn winning_diagonal(&self) -> bool {
-        for t in 4..8 {
-            let diag_up_mask = DIAG_UP << t;
-            let diag_down_mask = DIAG_DOWN << t;
-            let up_and = self.items & diag_up_mask;
-            let down_and = self.items & diag_down_mask;
-            if self.items & DIAG_UP == DIAG_UP && (up_and == diag_up_mask || up_and == 0) {
-                return true;
-            }
-            if self.items & DIAG_DOWN == DIAG_DOWN && (down_and == diag_down_mask || down_and == 0)
-            {
-                return true;
-            }
-        }
-        false
+        self.repr.winning_diagonal()
+    }
+
+    /// Check if a 2×2 square on the board is full and has blocks with one common characteristic.
+    /// The top left cell of the square is on column `x` and row `y`, which must lie between 0 and (incl.) 2.
+    /// This only decides the game with the advanced rules.
+    pub fn winning_square(&self, x: u8, y: u8) -> bool {
+        let Some(position) = Line::Square(x, y).position() else {
+            return false;
+        };
+        let items = self.items();
+        let square_mask = CELL_MASKS[position];
+        items & square_mask == square_mask
+            && (4..8).any(|t| {
+                let mask = square_mask << t;
+                items & mask == mask || items & mask == 0
+            })
     }
 
-    /// Check if the board has a winner.
-    /// Return true if there is a row/column/diagonal that is full with winning pieces.
+    /// Check if the board has a winner, as decided by the `WinRule` of its ruleset.
     pub fn has_winner(&self) -> bool {
-        // Check all rows and columns first
-        for i in 0..4 {
-            if self.winning_row(i) || self.winning_column(i) {
-                return true;
-            }
+        self.ruleset.rule().has_winner(self)
+    }
+
+    /// Like `has_winner`, but the classic rules check all lines with a few masks instead of asking the `WinRule`.
+    #[inline]
+    pub fn has_winner_fast(&self) -> bool {
+        match self.ruleset {
+            Ruleset::Classic => self.repr.has_line_winner(),
+            _ => self.has_winner(),
+        }
+    }
+
+    /// Check if there is a row/column/diagonal that is full with winning pieces, the classic rules.
+    pub fn has_line_winner(&self) -> bool {
+        self.repr.has_line_winner()
+    }
+
+    /// Check if a row, column or diagonal through `index` is full with winning pieces, the classic rules.
+    /// The other lines are not looked at, so this finds the win of the piece last placed on `index`.
+    pub fn has_line_winner_at(&self, index: u8) -> bool {
+        if index > 15 {
+            return false;
         }
-        // Finally, assume the result depends on the diagonals
-        self.winning_diagonal()
+        let on_diagonal = LINES_THROUGH[index as usize] & DIAGONALS != 0;
+        self.repr.winning_row(index / 4)
+            || self.repr.winning_column(index % 4)
+            || (on_diagonal && self.repr.winning_diagonal())
+    }
+
+    /// Check if a winning group of the ruleset through `index` is won, like `has_winner` for the last placement.
+    pub fn has_winner_at(&self, index: u8) -> bool {
+        self.ruleset.rule().has_winner_at(self, index)
+    }
+
+    /// The state of `line` on the board.
+    pub fn line_state(&self, line: Line) -> LineState {
+        LineState::new(line, line.indices().map(|index| self.piece_at(index)))
+    }
+
+    /// Iterate over the state of the lines of the ruleset, like the rows, the columns and then the diagonals.
+    pub fn lines(&self) -> impl Iterator<Item = LineState> + '_ {
+        self.ruleset
+            .lines()
+            .iter()
+            .map(|&line| self.line_state(line))
+    }
+
+    /// Return the full lines whose pieces share an attribute, with the attributes they share.
+    /// Empty if there is no winner, more than one line can win at the same time.
+    pub fn winning_lines(&self) -> Vec<WinningLine> {
+        self.lines().filter_map(|state| state.winning()).collect()
+    }
+
+    /// Return the empty indices where `piece` completes a Quarto, in increasing order.
+    pub fn winning_placements(&self, piece: u8) -> Vec<u8> {
+        let mut indices: Vec<u8> = self
+            .lines()
+            .filter(|state| state.completed_by(piece))
+            .filter_map(|state| state.first_empty())
+            .collect();
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    /// Return the remaining pieces that can be handed over without giving the opponent an immediate win.
+    pub fn safe_pieces(&self) -> Vec<u8> {
+        self.valid_pieces()
+            .into_iter()
+            .filter(|&piece| self.winning_placements(piece).is_empty())
+            .collect()
     }
 
     /// Check if the board is full with pieces.
     /// The board is full if all existence bits are set on the entire board.
     pub fn board_full(&self) -> bool {
-        // Build a bit mask from the COLUMN
-        self.items & BOARD_MASK == BOARD_MASK
+        self.repr.is_full()
     }
 
     /// Check if the game is over.
@@ -173,65 +554,140 @@ impl Board {
     pub fn game_over(&self) -> bool {
         self.has_winner() || self.board_full()
     }
-    
+
     /// Check if a given index is empty to place on the board.
     pub fn empty_index(&self, index: u8) -> bool {
-        index < 16 && self.items & (1 << PIECE_SIZE * (15 - index)) == 0
+        self.index_empty(index)
+    }
+
+    /// The single placement that turns this board into `other`.
+    /// Returns `None` if the boards are equal, or unrelated: `other` takes away or changes a piece, adds more than one,
+    /// or adds a piece that is already on this board.
+    pub fn diff(&self, other: &Board) -> Option<Move> {
+        let mut added = (0..16).filter(|&index| self.piece_at(index) != other.piece_at(index));
+        let (Some(index), None) = (added.next(), added.next()) else {
+            return None;
+        };
+        let piece = other.piece_at(index)?;
+        let mut next = *self;
+        next.put_piece(piece, index).ok()?;
+        let cell = Cell::new(index).filter(|_| next.items() == other.items())?;
+        Some(Move {
+            piece: Piece::from_number(piece),
+            cell,
+        })
     }
 
     /// Put a piece (given as a number from 0 to (incl.) 15) on the board at a given index.
-    /// Returns true if the piece was placed, false otherwise.
-    pub fn put_piece(&mut self, piece: u8, index: u8) -> bool {
+    /// Returns why the piece cannot be placed if it is not allowed, the board is unchanged in that case.
+    pub fn put_piece(&mut self, piece: u8, index: u8) -> Result<(), PlacementError> {
         // Cannot put a nonexisting piece on the board, or with an invalid index.
-        if !self.empty_index(index) || !self.valid_piece(piece) {
-            return false;
+        if index > 15 {
+            return Err(PlacementError::IndexOutOfRange { index });
+        }
+        if piece > 15 {
+            return Err(PlacementError::InvalidPiece { piece });
+        }
+        if !self.empty_index(index) {
+            return Err(PlacementError::OccupiedCell { index });
         }
-        let bit_index = 15 - index;
-        // Shift left the existence bit, then shift left the piece type (extra offset of 4 from the existence bit).
-        // Finally, add it to the board.
-        self.items +=
-            (1 << (PIECE_SIZE * bit_index)) + ((piece as u128) << (PIECE_SIZE * bit_index) + 4);
-        true
+        if !self.valid_piece(piece) {
+            return Err(PlacementError::PieceAlreadyUsed { piece });
+        }
+        self.repr.place(piece, index);
+        Ok(())
+    }
+
+    /// Put a piece on the board like `put_piece`, and tell if it won or drew the game.
+    /// Only the groups through `index` are checked, which is much faster than `has_winner` in a search.
+    /// A win elsewhere on the board before the placement is not seen, the game would have been over.
+    pub fn put_piece_checked(
+        &mut self,
+        piece: u8,
+        index: u8,
+    ) -> Result<PlacementOutcome, PlacementError> {
+        self.put_piece(piece, index)?;
+        Ok(if self.has_winner_at(index) {
+            PlacementOutcome::Win
+        } else if self.board_full() {
+            PlacementOutcome::Draw
+        } else {
+            PlacementOutcome::Ongoing
+        })
+    }
+
+    /// Remove the piece on the given index, undoing `put_piece`.
+    /// Returns the removed piece, or `None` if the index is empty or out of range.
+    pub fn remove_piece(&mut self, index: u8) -> Option<u8> {
+        let piece = self.piece_at(index)?;
+        self.repr.remove(index);
+        Some(piece)
     }
 
-    /// Check if a piece is valid to place on the board.
-    /// Loop over the pieces, if a piece exists, check if the values align with that of the piece number.
+    /// Check if a piece is valid to place on the board: it exists and is not on the board yet.
     pub fn valid_piece(&self, piece: u8) -> bool {
         // Pieces larger than 15 do not exist.
-        if piece > 15 {
-            return false;
-        }
-        for p in 0..16 {
-            let piece_mask = (piece as u128) << (PIECE_SIZE * p + 4);
-            if self.items & (1 << PIECE_SIZE * p) != 0
-                && (self.items & (0b1111 << PIECE_SIZE * p + 4)) ^ piece_mask == 0
-            {
-                return false;
-            }
-        }
-        true
+        piece < 16 && !self.repr.is_used(piece)
     }
 
     /// Return the indices that are empty.
     pub fn empty_spaces(&self) -> Vec<u8> {
-        let mut res: Vec<u8> = Vec::new();
-        for i in 0..16 {
-            if self.items & (1 << ((15 - i) * PIECE_SIZE)) == 0 {
-                res.push(i);
+        self.empty_cells().collect()
+    }
+
+    /// Return a list of valid pieces.
+    pub fn valid_pieces(&self) -> Vec<u8> {
+        self.available_pieces().collect()
+    }
+
+    /// The indices that hold a piece, bit `i` for index `i`.
+    pub fn occupied_cells_mask(&self) -> u16 {
+        self.repr.occupied_mask()
+    }
+
+    /// The pieces on the board, bit `p` for piece `p`.
+    pub fn used_pieces_mask(&self) -> u16 {
+        self.repr.used_mask()
+    }
+
+    /// Iterate over the empty indices, like `empty_spaces` without allocating.
+    pub fn empty_cells(&self) -> Bits {
+        Bits(!self.occupied_cells_mask())
+    }
+
+    /// Iterate over the pieces that are not on the board, like `valid_pieces` without allocating.
+    pub fn available_pieces(&self) -> Bits {
+        Bits(!self.used_pieces_mask())
+    }
+
+    /// Compute the Zobrist hash of the board: the XOR of a fixed random key for every placed piece.
+    /// Boards with the same pieces on the same indices hash the same, no matter the order the pieces were placed in.
+    /// A board with other rules mixes in a key of the ruleset, so tables do not confuse it with a classic board.
+    pub fn hash(&self) -> u64 {
+        let mut hash = self.ruleset_key();
+        for index in 0..16u8 {
+            if let Some(piece) = self.repr.piece_at(index) {
+                hash ^= ZOBRIST[index as usize][piece as usize];
             }
         }
-        res
+        hash
     }
 
-    /// Return a list of valid pieces (expensive!).
-    pub fn valid_pieces(&self) -> Vec<u8> {
-        let mut pieces: Vec<u8> = Vec::new();
-        for p in 0..16 {
-            if self.valid_piece(p) {
-                pieces.push(p);
+    /// The key of the ruleset that `hash` mixes in: 0 for the classic rules, and a hash of the name and lines of a custom rule.
+    /// The custom key is FNV-1a over the name and the indices of every line, so it is the same in every build.
+    pub(crate) fn ruleset_key(&self) -> u64 {
+        match self.ruleset {
+            Ruleset::Classic => 0,
+            Ruleset::Advanced => RULESET_KEY,
+            Ruleset::Custom(rule) => {
+                let lines = rule.lines().iter().flat_map(|line| line.indices());
+                let bytes = rule.name().bytes().chain([0xff]).chain(lines);
+                RULESET_KEY
+                    ^ bytes.fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+                    })
             }
         }
-        pieces
     }
 }
 
@@ -239,14 +695,139 @@ impl Board {
 mod tests {
     use std::panic;
 
-    use crate::printable::Piece;
+    use crate::{
+        line::Attribute,
+        printable::PrintableBoard,
+        repr::{CellRepr, PlaneRepr},
+        test_util::open_row,
+    };
 
     use super::*;
 
+    /// Generate one test per line, which checks every winning attribute on it with `check_line`.
+    macro_rules! exhaustive_line_tests {
+        ($($name:ident: $line:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    check_line($line);
+                }
+            )*
+        };
+    }
+
+    exhaustive_line_tests! {
+        test_exhaustive_row_0: Line::Row(0),
+        test_exhaustive_row_1: Line::Row(1),
+        test_exhaustive_row_2: Line::Row(2),
+        test_exhaustive_row_3: Line::Row(3),
+        test_exhaustive_column_0: Line::Column(0),
+        test_exhaustive_column_1: Line::Column(1),
+        test_exhaustive_column_2: Line::Column(2),
+        test_exhaustive_column_3: Line::Column(3),
+        test_exhaustive_diagonal_down: Line::DiagonalDown,
+        test_exhaustive_diagonal_up: Line::DiagonalUp,
+        test_exhaustive_square_top_left: Line::Square(0, 0),
+        test_exhaustive_square_center: Line::Square(1, 1),
+        test_exhaustive_square_bottom_right: Line::Square(2, 2),
+    }
+
+    /// Four pieces that share exactly `attribute` with `base`, and disagree on the other three attributes.
+    fn sharing_only(base: u8, attribute: Attribute) -> [u8; 4] {
+        let others: Vec<u8> = Attribute::ALL
+            .into_iter()
+            .filter(|&other| other != attribute)
+            .map(Attribute::bit)
+            .collect();
+        // Every column of these bits holds both values, so no other attribute is shared.
+        [0b000, 0b011, 0b101, 0b110].map(|flips: u8| {
+            (0..3)
+                .filter(|i| flips & (1 << i) != 0)
+                .fold(base, |piece, i| piece ^ others[i])
+        })
+    }
+
+    /// Check if `line` is won in `repr`.
+    fn repr_line_won<R: BoardRepr>(repr: R, line: Line) -> bool {
+        match line {
+            Line::Row(row) => repr.winning_row(row),
+            Line::Column(column) => repr.winning_column(column),
+            Line::DiagonalDown | Line::DiagonalUp => repr.winning_diagonal(),
+            Line::Square(..) | Line::Cells(..) => unreachable!("the layouts only check lines"),
+        }
+    }
+
+    /// Check if `line` is won on `board`, and that both layouts agree with `Board`.
+    fn line_won(board: &Board, line: Line) -> bool {
+        let won = match line {
+            Line::Row(row) => board.winning_row(row),
+            Line::Column(column) => board.winning_column(column),
+            Line::DiagonalDown | Line::DiagonalUp => board.winning_diagonal(),
+            Line::Square(x, y) => return board.winning_square(x, y),
+            Line::Cells(_) => unreachable!("only the lines of the built-in rules are checked"),
+        };
+        assert_eq!(repr_line_won(CellRepr::from_u128(board.items()), line), won);
+        assert_eq!(
+            repr_line_won(PlaneRepr::from_u128(board.items()), line),
+            won
+        );
+        won
+    }
+
+    /// Every piece as the base, combined with every attribute, gives a win on `line` that shares only that
+    /// attribute. Without the last piece, or with its attribute flipped, the line is a near-miss.
+    fn check_line(line: Line) {
+        let indices = line.indices();
+        let empty = match line {
+            Line::Square(..) => Board::new().with_ruleset(Ruleset::Advanced),
+            _ => Board::new(),
+        };
+        for base in 0..16 {
+            for attribute in Attribute::ALL {
+                let pieces = sharing_only(base, attribute);
+                let moves: Vec<(u8, u8)> = pieces.into_iter().zip(indices).collect();
+                let case = format!(
+                    "{} on {} from piece {}",
+                    attribute.describe(base & attribute.bit() != 0),
+                    line,
+                    base
+                );
+
+                let won = empty.apply_moves(&moves).expect(&case);
+                assert!(won.has_winner(), "{}", case);
+                assert!(line_won(&won, line), "{}", case);
+                assert_eq!(
+                    won.winning_lines(),
+                    vec![WinningLine {
+                        line,
+                        shared: vec![(attribute, base & attribute.bit() != 0)],
+                    }],
+                    "{}",
+                    case
+                );
+
+                let open = empty.apply_moves(&moves[..3]).expect(&case);
+                assert!(!open.has_winner(), "{}", case);
+                assert_eq!(
+                    open.winning_placements(pieces[3]),
+                    vec![indices[3]],
+                    "{}",
+                    case
+                );
+
+                let (last, index) = (pieces[3] ^ attribute.bit(), indices[3]);
+                let missed = open.apply_moves(&[(last, index)]).expect(&case);
+                assert!(!missed.has_winner(), "{}", case);
+                assert!(!line_won(&missed, line), "{}", case);
+                assert!(missed.winning_lines().is_empty(), "{}", case);
+            }
+        }
+    }
+
     #[test]
     fn test_new_board() {
         let board = Board::new();
-        assert_eq!(board.items, 0);
+        assert_eq!(board.items(), 0);
     }
 
     #[test]
@@ -254,7 +835,7 @@ mod tests {
         let board = Board::new();
         assert!(board.is_empty())
     }
-    
+
     #[test]
     fn test_empty_index_empty_board() {
         let board = Board::new();
@@ -262,12 +843,12 @@ mod tests {
             assert!(board.empty_index(i))
         }
     }
-    
+
     #[test]
     fn test_empty_index_nonempty_board() {
         let mut board = Board::new();
         let index = fastrand::u8(..16);
-        board.put_piece(0, index);
+        board.put_piece(0, index).unwrap();
         for i in 0..16 {
             if index == i {
                 assert!(!board.empty_index(i))
@@ -282,7 +863,7 @@ mod tests {
         let mut board = Board::new();
         let random_index = fastrand::u8(..16);
         let random_piece = fastrand::u8(..16);
-        board.put_piece(random_piece, random_index);
+        board.put_piece(random_piece, random_index).unwrap();
         assert!(!board.is_empty())
     }
 
@@ -295,7 +876,7 @@ mod tests {
     #[test]
     fn test_items_nonempty_board() {
         let mut board = Board::new();
-        board.put_piece(0, 15);
+        board.put_piece(0, 15).unwrap();
         assert_eq!(board.items(), 1)
     }
 
@@ -327,7 +908,7 @@ mod tests {
 
     #[test]
     fn test_is_empty_non_empty_board() {
-        let board: Board = Board { items: 1 };
+        let board: Board = Board::from_u128(1);
         assert!(!board.index_empty(15));
         for x in 0..15 {
             assert!(board.index_empty(x));
@@ -338,7 +919,7 @@ mod tests {
     fn test_row_first() {
         let items: u128 =
             (1 << (127 - 31)) + (1 << (127 - 23)) + (1 << (127 - 15)) + (1 << (127 - 7));
-        let board = Board { items };
+        let board = Board::from_u128(items);
         assert!(board.row(0));
         for row in 1..4 {
             assert!(!board.row(row))
@@ -349,7 +930,7 @@ mod tests {
     fn test_column_first() {
         let items: u128 =
             (1 << (127 - 7)) + (1 << (127 - 39)) + (1 << (127 - 71)) + (1 << (127 - 103));
-        let board = Board { items };
+        let board = Board::from_u128(items);
         assert!(board.column(0));
         for column in 1..4 {
             assert!(!board.column(column))
@@ -374,42 +955,10 @@ mod tests {
 
     #[test]
     fn test_winning_row_winning_row() {
-        let mut pboard_items: Vec<Option<Piece>> = Vec::new();
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        for _ in 0..12 {
-            pboard_items.push(None);
-        }
-        let pboard: PrintableBoard = match PrintableBoard::from_list(pboard_items) {
-            Some(board) => board,
-            None => panic!("Unable to construct printable board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Failed to construct board! {}", e),
-        };
+        // Four pieces with a hole and nothing else in common.
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 1), (10, 2), (9, 3)])
+            .expect("Failed to construct board!");
         assert!(board.winning_row(0));
         for i in 1..4 {
             assert!(!board.winning_row(i));
@@ -418,42 +967,9 @@ mod tests {
 
     #[test]
     fn test_winning_row_non_winning_row() {
-        let mut pboard_items: Vec<Option<Piece>> = Vec::new();
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        for _ in 0..12 {
-            pboard_items.push(None);
-        }
-        let pboard: PrintableBoard = match PrintableBoard::from_list(pboard_items) {
-            Some(board) => board,
-            None => panic!("Unable to construct printable board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Failed to construct board! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (4, 1), (2, 2), (1, 3)])
+            .expect("Failed to construct board!");
         for i in 0..4 {
             assert!(!board.winning_row(i));
         }
@@ -461,51 +977,9 @@ mod tests {
 
     #[test]
     fn test_winning_column_winning_column() {
-        let mut pboard_items: Vec<Option<Piece>> = Vec::new();
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        let pboard: PrintableBoard = match PrintableBoard::from_list(pboard_items) {
-            Some(board) => board,
-            None => panic!("Unable to construct printable board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Failed to construct board! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 4), (10, 8), (9, 12)])
+            .expect("Failed to construct board!");
         assert!(board.winning_column(0));
         for i in 1..4 {
             assert!(!board.winning_column(i));
@@ -514,51 +988,9 @@ mod tests {
 
     #[test]
     fn test_winning_column_non_winning_column() {
-        let mut pboard_items: Vec<Option<Piece>> = Vec::new();
-        pboard_items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        pboard_items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        for _ in 0..3 {
-            pboard_items.push(None);
-        }
-        let pboard: PrintableBoard = match PrintableBoard::from_list(pboard_items) {
-            Some(board) => board,
-            None => panic!("Unable to construct printable board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Failed to construct board! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (4, 4), (2, 8), (1, 12)])
+            .expect("Failed to construct board!");
         for i in 0..4 {
             assert!(!board.winning_column(i));
         }
@@ -572,123 +1004,107 @@ mod tests {
 
     #[test]
     fn test_winning_diagonal_non_winning() {
-        let mut items: Vec<Option<Piece>> = Vec::new();
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: false,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: false,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        let pboard: PrintableBoard = match PrintableBoard::from_list(items) {
-            Some(pb) => pb,
-            None => panic!("Unable to create the diagonal board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Unable to construct the board from printable! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (4, 5), (2, 10), (1, 15)])
+            .expect("Unable to construct the board!");
         assert!(!board.winning_diagonal())
     }
 
     #[test]
     fn test_winning_diagonal_winning() {
-        let mut items: Vec<Option<Piece>> = Vec::new();
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: true,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        for _ in 0..4 {
-            items.push(None);
-        }
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        let pboard: PrintableBoard = match PrintableBoard::from_list(items) {
-            Some(pb) => pb,
-            None => panic!("Unable to create the diagonal board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Unable to construct the board from printable! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 5), (10, 10), (9, 15)])
+            .expect("Unable to construct the board!");
         assert!(board.winning_diagonal())
     }
 
     #[test]
     fn test_put_invalid_piece() {
         let mut board: Board = Board::new();
-        assert!(!board.put_piece(16, 0));
+        assert_eq!(
+            board.put_piece(16, 0),
+            Err(PlacementError::InvalidPiece { piece: 16 })
+        );
         assert_eq!(board.items(), 0);
-        assert!(!board.put_piece(0, 16));
+        assert_eq!(
+            board.put_piece(0, 16),
+            Err(PlacementError::IndexOutOfRange { index: 16 })
+        );
         assert_eq!(board.items(), 0);
     }
 
+    /// Checking the lines of the last placement finds the same winner as checking the whole board.
+    #[test]
+    fn test_put_piece_checked_agrees_with_has_winner() {
+        let mut rng = fastrand::Rng::with_seed(2817);
+        for ruleset in [Ruleset::Classic, Ruleset::Advanced] {
+            for _ in 0..300 {
+                let mut board = Board::new().with_ruleset(ruleset);
+                let (mut pieces, mut cells): (Vec<u8>, Vec<u8>) =
+                    ((0..16).collect(), (0..16).collect());
+                rng.shuffle(&mut pieces);
+                rng.shuffle(&mut cells);
+                for (piece, index) in pieces.into_iter().zip(cells) {
+                    let outcome = board.put_piece_checked(piece, index).unwrap();
+                    let expected = if board.has_winner() {
+                        PlacementOutcome::Win
+                    } else if board.board_full() {
+                        PlacementOutcome::Draw
+                    } else {
+                        PlacementOutcome::Ongoing
+                    };
+                    assert_eq!(outcome, expected, "{} on {}", piece, index);
+                    if outcome != PlacementOutcome::Ongoing {
+                        break;
+                    }
+                }
+            }
+        }
+        let mut board = Board::new();
+        assert_eq!(
+            board.put_piece_checked(16, 0),
+            Err(PlacementError::InvalidPiece { piece: 16 })
+        );
+    }
+
+    /// The masks find the same winners as reading the state of every line, and leave other rulesets alone.
+    #[test]
+    fn test_has_winner_fast_agrees_with_lines() {
+        let mut rng = fastrand::Rng::with_seed(2818);
+        for _ in 0..500 {
+            let mut board = Board::new();
+            for index in 0..16 {
+                if rng.bool() {
+                    board.put_piece(rng.u8(0..16), index).ok();
+                }
+            }
+            let expected = Line::ALL
+                .iter()
+                .any(|&line| board.line_state(line).winning().is_some());
+            assert_eq!(board.has_winner_fast(), expected, "{:#x}", board.items());
+            let advanced = board.with_ruleset(Ruleset::Advanced);
+            assert_eq!(advanced.has_winner_fast(), advanced.has_winner());
+        }
+    }
+
     #[test]
     fn test_put_duplicate_piece() {
         let mut board: Board = Board::new();
         // First attempt to put piece 0 on the board.
-        assert!(board.put_piece(0, 0));
+        assert!(board.put_piece(0, 0).is_ok());
         // Then try to put piece 0 again, but now in a different spot.
-        assert!(!board.put_piece(0, 1));
+        assert_eq!(
+            board.put_piece(0, 1),
+            Err(PlacementError::PieceAlreadyUsed { piece: 0 })
+        );
     }
 
     #[test]
     fn test_put_valid_piece() {
         let mut board: Board = Board::new();
-        assert!(board.put_piece(1, 0));
+        assert!(board.put_piece(1, 0).is_ok());
         assert_ne!(board.items(), 0);
-        let pboard: PrintableBoard = PrintableBoard::from_board(board);
-        let items = pboard.items();
+        let items = PrintableBoard::new(&board).items();
         match items.first() {
             Some(option) => match option {
                 Some(piece) => assert_eq!(
@@ -705,14 +1121,14 @@ mod tests {
             None => panic!("Unable to get first item from the printable board!"),
         }
     }
-    
+
     #[test]
     fn test_put_piece_different_pieces_same_place() {
         let mut board: Board = Board::new();
         let empty = board.empty_spaces();
         let spot: usize = fastrand::usize(..empty.len());
-        assert!(board.put_piece(1, spot as u8));
-        assert!(!board.put_piece(2, spot as u8));
+        assert!(board.put_piece(1, spot as u8).is_ok());
+        assert!(board.put_piece(2, spot as u8).is_err());
     }
 
     #[test]
@@ -727,7 +1143,7 @@ mod tests {
         for i in 0..16 {
             items += 1 << (i * PIECE_SIZE);
         }
-        let board: Board = Board { items };
+        let board: Board = Board::from_u128(items);
         assert!(board.board_full());
     }
 
@@ -738,7 +1154,7 @@ mod tests {
         for i in 0..10 {
             items += 1 << (i * PIECE_SIZE);
         }
-        let board: Board = Board { items };
+        let board: Board = Board::from_u128(items);
         assert!(!board.board_full());
     }
 
@@ -750,47 +1166,137 @@ mod tests {
 
     #[test]
     fn test_has_winner_actual_winning() {
-        let mut items: Vec<Option<Piece>> = Vec::new();
         // Add 4 items in a row that have a hole and nothing else in common.
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: false,
-        }));
-        items.push(Some(Piece {
-            hole: true,
-            square: true,
-            high: false,
-            dark: false,
-        }));
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: true,
-            dark: false,
-        }));
-        items.push(Some(Piece {
-            hole: true,
-            square: false,
-            high: false,
-            dark: true,
-        }));
-        // Add empty spaces.
-        for _ in 0..12 {
-            items.push(None);
-        }
-        let pboard: PrintableBoard = match PrintableBoard::from_list(items) {
-            Some(pb) => pb,
-            None => panic!("Unable to create printable board!"),
-        };
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(e) => panic!("Unable to create board from printable! {}", e),
-        };
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 1), (10, 2), (9, 3)])
+            .expect("Unable to create board!");
         assert!(board.has_winner())
     }
 
+    #[test]
+    fn test_winning_lines() {
+        assert!(Board::new().winning_lines().is_empty());
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 5), (10, 10), (9, 15)])
+            .unwrap();
+        assert_eq!(
+            board.winning_lines(),
+            vec![WinningLine {
+                line: Line::DiagonalDown,
+                shared: vec![(crate::line::Attribute::Hole, true)]
+            }]
+        );
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (4, 1), (2, 2), (1, 3)])
+            .unwrap();
+        assert!(board.winning_lines().is_empty());
+    }
+
+    #[test]
+    fn test_lines() {
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 1), (10, 2), (7, 5)])
+            .unwrap();
+        let lines: Vec<LineState> = board.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0].line, Line::Row(0));
+        assert_eq!(lines[0].cells, [Some(8), Some(12), Some(10), None]);
+        assert_eq!(lines[0].empties(), 1);
+        assert!(lines[0].completed_by(9));
+        assert_eq!(lines[3].empties(), 4);
+        assert_eq!(lines[8].line, Line::DiagonalDown);
+        assert_eq!(lines[8].pieces(), 2);
+        assert!(!lines[8].is_open());
+    }
+
+    #[test]
+    fn test_winning_lines_agrees_with_has_winner() {
+        for _ in 0..200 {
+            let mut board = Board::new();
+            while !board.board_full() {
+                let pieces = board.valid_pieces();
+                let spaces = board.empty_spaces();
+                board
+                    .put_piece(
+                        pieces[fastrand::usize(..pieces.len())],
+                        spaces[fastrand::usize(..spaces.len())],
+                    )
+                    .unwrap();
+                assert_eq!(board.has_winner(), !board.winning_lines().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_winning_placements_open_row() {
+        assert_eq!(open_row().winning_placements(0b1001), vec![15]);
+        assert!(open_row().winning_placements(0b0001).is_empty());
+        assert!(Board::new().winning_placements(0).is_empty());
+    }
+
+    #[test]
+    fn test_safe_pieces_open_row() {
+        // Only dark pieces without a hole keep the row from sharing an attribute.
+        assert_eq!(
+            open_row().safe_pieces(),
+            vec![0b0001, 0b0011, 0b0101, 0b0111]
+        );
+        assert_eq!(Board::new().safe_pieces().len(), 16);
+    }
+
+    #[test]
+    fn test_winning_placements_agrees_with_placing() {
+        for board in crate::harness::random_positions(100, 14, &crate::random::SharedRng::seeded(1))
+        {
+            for piece in board.valid_pieces() {
+                let placed: Vec<u8> = board
+                    .empty_spaces()
+                    .into_iter()
+                    .filter(|&index| {
+                        let mut next = board;
+                        next.put_piece(piece, index).is_ok() && next.has_winner()
+                    })
+                    .collect();
+                assert_eq!(board.winning_placements(piece), placed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_moves_does_not_change_original() {
+        let board = Board::new();
+        let next = board.apply_moves(&[(3, 4), (5, 6)]).unwrap();
+        assert!(board.is_empty());
+        assert!(!next.empty_index(4));
+        assert!(!next.valid_piece(5));
+    }
+
+    #[test]
+    fn test_apply_moves_rejects_illegal_move() {
+        assert_eq!(
+            Board::new().apply_moves(&[(3, 4), (3, 5)]),
+            Err(QuartoError::IllegalMove {
+                ply: 1,
+                error: PlacementError::PieceAlreadyUsed { piece: 3 }
+            })
+        );
+        assert_eq!(
+            Board::new().apply_moves(&[(3, 4), (5, 4)]),
+            Err(QuartoError::IllegalMove {
+                ply: 1,
+                error: PlacementError::OccupiedCell { index: 4 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_apply_moves_rejects_moves_after_win() {
+        assert_eq!(
+            Board::new().apply_moves(&[(8, 0), (12, 1), (10, 2), (9, 3), (0, 4)]),
+            Err(QuartoError::GameOver { ply: 4 })
+        );
+    }
+
     #[test]
     fn test_game_over_empty_board() {
         let board: Board = Board::new();
@@ -800,23 +1306,23 @@ mod tests {
     #[test]
     fn test_game_over_winner() {
         let mut board: Board = Board::new();
-        board.put_piece(0, 0);
-        board.put_piece(2, 1);
-        board.put_piece(4, 2);
-        board.put_piece(6, 3);
+        board.put_piece(0, 0).unwrap();
+        board.put_piece(2, 1).unwrap();
+        board.put_piece(4, 2).unwrap();
+        board.put_piece(6, 3).unwrap();
         assert!(board.game_over())
     }
 
     #[test]
     fn test_game_over_non_winner() {
         let mut board: Board = Board::new();
-        board.put_piece(0, 0);
-        board.put_piece(2, 1);
-        board.put_piece(4, 2);
-        board.put_piece(15, 3);
+        board.put_piece(0, 0).unwrap();
+        board.put_piece(2, 1).unwrap();
+        board.put_piece(4, 2).unwrap();
+        board.put_piece(15, 3).unwrap();
         assert!(!board.game_over())
     }
-    
+
     #[test]
     fn test_random_board_progression() {
         let mut board: Board = Board::new();
@@ -829,7 +1335,7 @@ mod tests {
             let p = fastrand::usize(..pieces.len());
             let indices = board.empty_spaces();
             let s = fastrand::usize(..indices.len());
-            assert!(board.put_piece(pieces[p], indices[s]));
+            assert!(board.put_piece(pieces[p], indices[s]).is_ok());
             used_pieces.push(pieces[p]);
             used_indices.push(indices[s]);
         }
@@ -840,4 +1346,297 @@ mod tests {
             assert!(!board.index_empty(s));
         }
     }
+
+    #[test]
+    fn test_piece_at() {
+        let board = Board::new().apply_moves(&[(0, 3), (13, 9)]).unwrap();
+        assert_eq!(board.piece_at(3), Some(0));
+        assert_eq!(board.piece_at(9), Some(13));
+        assert_eq!(board.piece_at(4), None);
+        assert_eq!(board.piece_at(16), None);
+    }
+
+    #[test]
+    fn test_from_u128_round_trip() {
+        let board = Board::new().apply_moves(&[(5, 1), (6, 2)]).unwrap();
+        assert_eq!(Board::from_u128(board.items()), board);
+    }
+
+    #[test]
+    fn test_masks_match_lists() {
+        let board = Board::new()
+            .apply_moves(&[(5, 1), (6, 2), (15, 15)])
+            .unwrap();
+        assert_eq!(board.occupied_cells_mask(), 0b1000_0000_0000_0110);
+        assert_eq!(board.used_pieces_mask(), 0b1000_0000_0110_0000);
+        assert_eq!(board.empty_cells().len(), 13);
+        assert_eq!(
+            board.empty_cells().collect::<Vec<u8>>(),
+            board.empty_spaces()
+        );
+        let pieces: Vec<u8> = board.available_pieces().collect();
+        assert_eq!(
+            pieces,
+            (0..16)
+                .filter(|&p| board.valid_piece(p))
+                .collect::<Vec<u8>>()
+        );
+        assert_eq!(Bits(0).next(), None);
+        assert_eq!(Board::new().available_pieces().count(), 16);
+    }
+
+    #[test]
+    fn test_diff() {
+        let board = Board::new().apply_moves(&[(5, 1), (6, 2)]).unwrap();
+        let next = board.apply_moves(&[(9, 14)]).unwrap();
+        let placement = Move {
+            piece: Piece::from_number(9),
+            cell: Cell::new(14).unwrap(),
+        };
+        assert_eq!(board.diff(&next), Some(placement));
+        assert_eq!(board.diff(&board), None);
+        assert_eq!(next.diff(&board), None);
+        assert_eq!(board.diff(&next.apply_moves(&[(0, 0)]).unwrap()), None);
+        // Moving a piece, or placing one that is already on the board, is not a placement.
+        let moved = Board::new().apply_moves(&[(5, 3), (6, 2)]).unwrap();
+        assert_eq!(board.diff(&moved), None);
+        let mut twice = board;
+        twice.repr.place(5, 0);
+        assert_eq!(board.diff(&twice), None);
+    }
+
+    #[test]
+    fn test_validate_reports_every_issue() {
+        let board = Board::new().apply_moves(&[(5, 0), (6, 2)]).unwrap();
+        assert_eq!(board.validate(), Ok(()));
+        let cell = |index: u8, bits: u128| bits << (PIECE_SIZE * (15 - index));
+        let items = board.items() | cell(3, 0x51) | cell(7, 0x30) | cell(9, 0b0100);
+        let issues = Board::try_from_u128(items).unwrap_err();
+        assert_eq!(
+            issues,
+            vec![
+                BoardIssue::AttributesWithoutPiece { index: 7 },
+                BoardIssue::StrayBits { index: 9 },
+                BoardIssue::DuplicatePiece {
+                    piece: 5,
+                    indices: vec![0, 3]
+                },
+            ]
+        );
+        assert_eq!(issues[2].to_string(), "piece 5 is on indices 0, 3");
+    }
+
+    #[test]
+    fn test_reachable_wins() {
+        let one_row = Board::new()
+            .apply_moves(&[(8, 0), (9, 1), (10, 2), (0, 4), (11, 3)])
+            .unwrap();
+        assert!(one_row.has_winner() && one_row.is_reachable());
+        // A second row of holes next to it could only be completed after the game was over.
+        let mut two_rows = one_row;
+        for (piece, index) in [(12, 5), (13, 6), (14, 7), (15, 4)] {
+            two_rows.remove_piece(index);
+            two_rows.put_piece(piece, index).unwrap();
+        }
+        assert_eq!(
+            two_rows.check_reachable(),
+            Err(vec![BoardIssue::WonBeforeLastPlacement])
+        );
+        // Two winning lines through one cell are made by the same placement.
+        let cross = Board::new()
+            .apply_moves(&[
+                (8, 0),
+                (9, 1),
+                (10, 2),
+                (12, 7),
+                (13, 11),
+                (14, 15),
+                (11, 3),
+            ])
+            .unwrap();
+        assert!(cross.winning_row(0) && cross.winning_column(3) && cross.is_reachable());
+    }
+
+    #[test]
+    fn test_hash_empty_board() {
+        assert_eq!(Board::new().hash(), 0);
+    }
+
+    #[test]
+    fn test_hash_independent_of_order() {
+        let mut first = Board::new();
+        first.put_piece(3, 7).unwrap();
+        first.put_piece(9, 2).unwrap();
+        let mut second = Board::new();
+        second.put_piece(9, 2).unwrap();
+        second.put_piece(3, 7).unwrap();
+        assert_eq!(first.hash(), second.hash());
+    }
+
+    #[test]
+    fn test_hash_distinguishes_pieces_and_indices() {
+        let mut hashes: Vec<u64> = Vec::new();
+        for index in 0..16 {
+            for piece in 0..16 {
+                let mut board = Board::new();
+                board.put_piece(piece, index).unwrap();
+                hashes.push(board.hash());
+            }
+        }
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), 256);
+    }
+
+    #[test]
+    fn test_try_from_cells() {
+        let cells: Vec<Option<Piece>> = (0..16).map(|i| Some(Piece::from_number(i))).collect();
+        let board = Board::try_from(cells.as_slice()).unwrap();
+        assert_eq!(PrintableBoard::new(&board).items(), cells);
+    }
+
+    #[test]
+    fn test_try_from_wrong_length() {
+        assert_eq!(
+            Board::try_from(&[None; 15][..]),
+            Err(QuartoError::WrongCellCount { len: 15 })
+        );
+    }
+
+    #[test]
+    fn test_try_from_duplicate_piece() {
+        let mut cells = [None; 16];
+        cells[0] = Some(Piece::from_number(3));
+        cells[7] = Some(Piece::from_number(3));
+        assert_eq!(
+            Board::try_from(&cells[..]),
+            Err(QuartoError::Placement(PlacementError::PieceAlreadyUsed {
+                piece: 3
+            }))
+        );
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let board = Board::new()
+            .apply_moves(&[(5, 0), (10, 15), (12, 6)])
+            .unwrap();
+        assert_eq!(board.compact(), "5.../..c./..../...a");
+        assert_eq!("5.../..c./..../...a".parse(), Ok(board));
+        assert_eq!("5... ..c. .... ...a".parse(), Ok(board));
+        assert_eq!(board.to_string().parse(), Ok(board));
+        assert!(board.to_string().starts_with("0101 ---- ---- ----"));
+        assert_eq!("..../..../..../....".parse(), Ok(Board::new()));
+        let advanced = board.with_ruleset(Ruleset::Advanced);
+        assert_eq!(advanced.compact(), "5.../..c./..../...a advanced");
+        assert_eq!(advanced.compact().parse(), Ok(advanced));
+        assert_eq!("5... ..c. .... ...a advanced".parse(), Ok(advanced));
+        assert!("5.../..c./..../...a corners".parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let board = Board::new()
+            .apply_moves(&[(5, 0), (10, 15), (12, 6)])
+            .unwrap();
+        assert_eq!(board.to_fen(Some(3), 1), "5.../..c./..../...a 3 1");
+        assert_eq!(
+            Board::from_fen("5.../..c./..../...a 3 1"),
+            Ok((board, Some(3), 1))
+        );
+        assert_eq!(
+            Board::from_fen(&Board::new().to_fen(None, 0)),
+            Ok((Board::new(), None, 0))
+        );
+        assert!(Board::from_fen("5.../..c./..../...a 5 1").is_err());
+        assert!(Board::from_fen("5.../..c./..../...a 3 2").is_err());
+        assert!(Board::from_fen("5.../..c./..../...a").is_err());
+        let advanced = board.with_ruleset(Ruleset::Advanced);
+        assert_eq!(
+            advanced.to_fen(Some(3), 1),
+            "5.../..c./..../...a 3 1 advanced"
+        );
+        assert_eq!(
+            Board::from_fen(&advanced.to_fen(Some(3), 1)),
+            Ok((advanced, Some(3), 1))
+        );
+        assert!(Board::from_fen("5.../..c./..../...a 3 1 corners").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json() {
+        let board = Board::new().apply_moves(&[(5, 0), (12, 6)]).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(
+            json,
+            "[5,null,null,null,null,null,12,null,null,null,null,null,null,null,null,null]"
+        );
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+        assert!(
+            serde_json::from_str::<Board>(
+                "[5,5,null,null,null,null,null,null,null,null,null,null,null,null,null,null]"
+            )
+            .is_err()
+        );
+        assert!(
+            serde_json::from_str::<Board>(
+                "[16,null,null,null,null,null,null,null,null,null,null,null,null,null,null,null]"
+            )
+            .is_err()
+        );
+        assert!(serde_json::from_str::<Board>("[5]").is_err());
+        let piece = Piece::from_number(9);
+        let json = serde_json::to_string(&piece).unwrap();
+        assert_eq!(
+            json,
+            "{\"hole\":true,\"square\":false,\"high\":false,\"dark\":true}"
+        );
+        assert_eq!(serde_json::from_str::<Piece>(&json).unwrap(), piece);
+        let text = serde_json::to_string(&PrintableBoard::new(&board)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<String>(&text).unwrap(),
+            board.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(
+            "5.../...."
+                .parse::<Board>()
+                .unwrap_err()
+                .contains("16 cells")
+        );
+        assert!(
+            "5..5/..../..../...."
+                .parse::<Board>()
+                .unwrap_err()
+                .contains("already")
+        );
+        assert!(
+            "x.../..../..../...."
+                .parse::<Board>()
+                .unwrap_err()
+                .contains("'x'")
+        );
+    }
+
+    #[test]
+    fn test_remove_piece_undoes_put() {
+        let before = Board::new().apply_moves(&[(4, 2), (11, 9)]).unwrap();
+        let mut board = before;
+        assert!(board.put_piece(15, 15).is_ok());
+        assert_eq!(board.remove_piece(15), Some(15));
+        assert_eq!(board, before);
+        assert!(board.valid_piece(15));
+    }
+
+    #[test]
+    fn test_remove_piece_empty_index() {
+        let mut board = Board::new().apply_moves(&[(4, 2)]).unwrap();
+        assert_eq!(board.remove_piece(3), None);
+        assert_eq!(board.remove_piece(16), None);
+        assert_eq!(board.piece_at(2), Some(4));
+    }
 }
@@ -0,0 +1,191 @@
+// Browsing the games of a server: the players waiting for an opponent, the games in progress and the recent results.
+// The lobby cuts a listing into pages, which a client shows as a table with `join --list`.
+
+use std::{fmt, str::FromStr};
+
+/// Which games to list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameListKind {
+    /// Players waiting in the lobby for an opponent.
+    Open,
+    /// Games being played right now.
+    Ongoing,
+    /// Games that finished recently, the last one first.
+    Recent,
+}
+
+impl GameListKind {
+    /// Every kind, in the order a full listing shows them.
+    pub const ALL: [GameListKind; 3] = [
+        GameListKind::Open,
+        GameListKind::Ongoing,
+        GameListKind::Recent,
+    ];
+}
+
+impl fmt::Display for GameListKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameListKind::Open => write!(f, "open"),
+            GameListKind::Ongoing => write!(f, "ongoing"),
+            GameListKind::Recent => write!(f, "recent"),
+        }
+    }
+}
+
+impl FromStr for GameListKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(GameListKind::Open),
+            "ongoing" => Ok(GameListKind::Ongoing),
+            "recent" => Ok(GameListKind::Recent),
+            _ => Err(format!("expected open, ongoing or recent, got '{}'", s)),
+        }
+    }
+}
+
+/// A game in a listing, the names are one word like in the lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSummary {
+    /// The id to watch the game by, `None` for a player that waits for an opponent.
+    pub id: Option<u64>,
+    pub first: String,
+    /// The second player, `None` while the first waits for an opponent.
+    pub second: Option<String>,
+    /// What the server tells about the game: how long the player waits, the turn, or the result.
+    pub detail: String,
+}
+
+impl fmt::Display for GameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.id {
+            Some(id) => write!(f, "{}", id)?,
+            None => write!(f, "-")?,
+        }
+        write!(
+            f,
+            " {} {} {}",
+            self.first,
+            self.second.as_deref().unwrap_or("-"),
+            self.detail
+        )
+    }
+}
+
+impl FromStr for GameSummary {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a game, got '{}'", line);
+        let mut words = line.split_whitespace();
+        let (Some(id), Some(first), Some(second)) = (words.next(), words.next(), words.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(GameSummary {
+            id: match id {
+                "-" => None,
+                id => Some(id.parse().map_err(|_| invalid())?),
+            },
+            first: first.to_string(),
+            second: (second != "-").then(|| second.to_string()),
+            detail: words.collect::<Vec<&str>>().join(" "),
+        })
+    }
+}
+
+/// A page of a listing, pages are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub page: u32,
+    pub pages: u32,
+    pub games: Vec<GameSummary>,
+}
+
+impl Page {
+    /// Page `page` of `games`, `per_page` games to a page. A page past the last one is empty.
+    pub fn of(games: Vec<GameSummary>, page: u32, per_page: u32) -> Result<Self, String> {
+        if page == 0 || per_page == 0 {
+            return Err("pages count from 1 and hold at least one game".to_string());
+        }
+        let pages = games.len().div_ceil(per_page as usize).max(1) as u32;
+        let games = games
+            .into_iter()
+            .skip((page - 1) as usize * per_page as usize)
+            .take(per_page as usize)
+            .collect();
+        Ok(Page { page, pages, games })
+    }
+
+    /// Render the page as a table for the terminal.
+    pub fn to_table(&self, kind: GameListKind) -> String {
+        let detail = match kind {
+            GameListKind::Open => "Waiting",
+            GameListKind::Ongoing => "Turn",
+            GameListKind::Recent => "Result",
+        };
+        let mut out = format!(
+            "{:<10} {:<16} {:<16} {}\n",
+            "Game", "Player 1", "Player 2", detail
+        );
+        for game in &self.games {
+            out += &format!(
+                "{:<10} {:<16} {:<16} {}\n",
+                game.id.map_or("-".to_string(), |id| id.to_string()),
+                game.first,
+                game.second.as_deref().unwrap_or("(open)"),
+                game.detail
+            );
+        }
+        if self.games.is_empty() {
+            out += "No games.\n";
+        }
+        out += &format!("Page {} of {}\n", self.page, self.pages);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(id: u64) -> GameSummary {
+        GameSummary {
+            id: Some(id),
+            first: "alice".to_string(),
+            second: Some("bob".to_string()),
+            detail: "bob won".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_summary_round_trip() {
+        let open: GameSummary = "- alice - 2 min".parse().unwrap();
+        assert_eq!((open.id, open.second.as_deref()), (None, None));
+        assert_eq!(open.detail, "2 min");
+        assert_eq!(open.to_string().parse(), Ok(open));
+        assert_eq!(summary(12).to_string().parse(), Ok(summary(12)));
+        assert!("12 alice".parse::<GameSummary>().is_err());
+        assert!("x alice bob".parse::<GameSummary>().is_err());
+    }
+
+    #[test]
+    fn test_pages() {
+        let games: Vec<GameSummary> = (1..=5).map(summary).collect();
+        let page = Page::of(games.clone(), 2, 2).unwrap();
+        assert_eq!((page.page, page.pages), (2, 3));
+        assert_eq!(page.games, [summary(3), summary(4)]);
+        assert!(Page::of(games.clone(), 4, 2).unwrap().games.is_empty());
+        assert!(Page::of(games, 0, 2).is_err());
+        let table = page.to_table(GameListKind::Recent);
+        assert!(table.starts_with("Game"));
+        assert!(table.contains("Result"));
+        assert!(table.ends_with("Page 2 of 3\n"));
+        let empty = Page::of(vec![], 1, 20)
+            .unwrap()
+            .to_table(GameListKind::Open);
+        assert!(empty.ends_with("No games.\nPage 1 of 1\n"));
+    }
+}
@@ -0,0 +1,124 @@
+// A reference card that maps the piece numbers of the app to the pieces of a physical Quarto set.
+// Written as text for the terminal or as SVG to print, for players with a real board next to the app.
+
+use std::fmt::Write;
+
+use crate::printable::{Glyphs, Piece};
+
+/// The piece as it is called in a physical set, like `tall dark square hollow`.
+pub fn physical(piece: Piece) -> String {
+    format!(
+        "{} {} {} {}",
+        if piece.high { "tall" } else { "short" },
+        if piece.dark { "dark" } else { "light" },
+        if piece.square { "square" } else { "round" },
+        if piece.hole { "hollow" } else { "solid" }
+    )
+}
+
+/// The card as text: a line per piece with its number, glyph, code and physical description.
+pub fn to_text() -> String {
+    let glyphs = Glyphs::new();
+    let mut text = String::from("Piece  Glyph  Code  Physical piece\n");
+    for number in 0..16 {
+        let piece = Piece::from_number(number);
+        let _ = writeln!(
+            text,
+            "{:>5}  {:<5}  {}  {}",
+            number,
+            glyphs.render(piece),
+            piece.code(),
+            physical(piece)
+        );
+    }
+    text
+}
+
+/// The size of a cell of the SVG card in pixels.
+const CELL: u32 = 120;
+
+/// The card as an SVG image: the sixteen pieces seen from above in a 4x4 grid, labelled with number and code.
+/// Tall pieces are drawn larger, and a hollow piece has a hole in its top.
+pub fn to_svg() -> String {
+    let size = CELL * 4;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\" \
+         font-family=\"sans-serif\" font-size=\"13\" text-anchor=\"middle\">\n\
+         <rect width=\"{0}\" height=\"{0}\" fill=\"white\"/>\n",
+        size
+    );
+    for number in 0..16u32 {
+        let piece = Piece::from_number(number as u8);
+        let (x, y) = (
+            number % 4 * CELL + CELL / 2,
+            number / 4 * CELL + CELL / 2 - 10,
+        );
+        let radius = if piece.high { 34 } else { 24 };
+        let fill = if piece.dark { "#5a3a22" } else { "#e6c99a" };
+        let _ = if piece.square {
+            writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\" stroke=\"black\"/>",
+                x - radius,
+                y - radius,
+                radius * 2,
+                fill
+            )
+        } else {
+            writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"black\"/>",
+                x, y, radius, fill
+            )
+        };
+        if piece.hole {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"white\" stroke=\"black\"/>",
+                x,
+                y,
+                radius / 2
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\">{} {}</text>",
+            x,
+            number / 4 * CELL + CELL - 8,
+            number,
+            piece.code()
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_follows_code() {
+        for number in 0..16 {
+            let piece = Piece::from_number(number);
+            let initials: String = physical(piece)
+                .split(' ')
+                .map(|word| word.chars().next().unwrap().to_ascii_uppercase())
+                .collect();
+            assert_eq!(initials, piece.code());
+        }
+        assert_eq!(physical(Piece::from_number(15)), "tall dark square hollow");
+    }
+
+    #[test]
+    fn test_card_lists_every_piece() {
+        let text = to_text();
+        assert_eq!(text.lines().count(), 17);
+        assert!(text.contains("   15  \u{25a1}'     TDSH  tall dark square hollow\n"));
+        let svg = to_svg();
+        assert!(svg.starts_with("<svg "));
+        assert_eq!(svg.matches("<text ").count(), 16);
+        assert_eq!(svg.matches("<rect ").count(), 1 + 8);
+        assert!(svg.contains(">15 TDSH</text>"));
+    }
+}
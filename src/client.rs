@@ -0,0 +1,18 @@
+// The decisions a client makes in a game, as the REST and gRPC APIs of the server take them.
+// A `Session` applies them to its game, and refuses an action that does not fit the turn.
+
+/// A decision of the local player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Action {
+    /// Hand `piece` over to the opponent.
+    Give(u8),
+    /// Place the piece in hand on `index`.
+    Place(u8),
+    /// Call Quarto.
+    Quarto,
+}
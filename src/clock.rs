@@ -0,0 +1,181 @@
+// Time controls and chess-style clocks, with a time limit for the whole game and for every decision.
+// The clock of a player runs while they decide, a player that goes over a limit loses on time.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// The time limits of a game, both unlimited by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    /// The time each player has for all their decisions together.
+    pub per_game: Option<Duration>,
+    /// The time each player has for a single decision, handing over a piece or placing one.
+    pub per_move: Option<Duration>,
+}
+
+impl TimeControl {
+    /// No time limits.
+    pub fn new() -> Self {
+        TimeControl::default()
+    }
+
+    /// Give each player `time` for the whole game.
+    pub fn with_per_game(mut self, time: Duration) -> Self {
+        self.per_game = Some(time);
+        self
+    }
+
+    /// Give each player `time` for every decision.
+    pub fn with_per_move(mut self, time: Duration) -> Self {
+        self.per_move = Some(time);
+        self
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.per_game, self.per_move) {
+            (None, None) => write!(f, "unlimited"),
+            (Some(game), None) => write!(f, "{} per game", format_time(game)),
+            (None, Some(mv)) => write!(f, "{} per move", format_time(mv)),
+            (Some(game), Some(mv)) => write!(
+                f,
+                "{} per game, {} per move",
+                format_time(game),
+                format_time(mv)
+            ),
+        }
+    }
+}
+
+/// Write `time` as minutes and seconds, like `4:05`.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// The clocks of the players under a `TimeControl`.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    /// The time left in the game per player, `None` without a limit per game.
+    remaining: Vec<Option<Duration>>,
+    /// The player whose clock runs, and since when.
+    running: Option<(usize, Instant)>,
+    /// The player whose flag fell.
+    flagged: Option<usize>,
+}
+
+impl Clock {
+    /// Set the clocks of two players to the time per game of `control`.
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            remaining: vec![control.per_game; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    /// Keep a clock for each of `players` players, like the four of a team game.
+    pub fn with_players(mut self, players: usize) -> Self {
+        self.remaining = vec![self.control.per_game; players];
+        self
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// The time `player` has left in the game, `None` without a limit per game.
+    pub fn remaining(&self, player: usize) -> Option<Duration> {
+        let remaining = self.remaining[player]?;
+        Some(match self.running {
+            Some((running, since)) if running == player => {
+                remaining.saturating_sub(since.elapsed())
+            }
+            _ => remaining,
+        })
+    }
+
+    /// The time `player` has for their next decision, the smaller of the time left in the game and the time per move.
+    pub fn allowed(&self, player: usize) -> Option<Duration> {
+        match (self.remaining(player), self.control.per_move) {
+            (Some(game), Some(mv)) => Some(game.min(mv)),
+            (game, mv) => game.or(mv),
+        }
+    }
+
+    /// Start the clock of `player`, who is about to decide.
+    pub fn start(&mut self, player: usize) {
+        self.running = Some((player, Instant::now()));
+    }
+
+    /// Stop the running clock, and charge the time since it started.
+    /// Returns false if the player went over the time they were allowed, their flag fell.
+    pub fn stop(&mut self) -> bool {
+        let Some((player, since)) = self.running else {
+            return true;
+        };
+        self.charge(player, since.elapsed())
+    }
+
+    /// Charge `player` for a decision that took `elapsed`.
+    /// Returns false if the player went over the time they were allowed.
+    pub fn charge(&mut self, player: usize, elapsed: Duration) -> bool {
+        self.running = None;
+        let allowed = self.allowed(player);
+        if let Some(remaining) = self.remaining[player].as_mut() {
+            *remaining = remaining.saturating_sub(elapsed);
+        }
+        if allowed.is_some_and(|allowed| elapsed > allowed) {
+            self.flagged.get_or_insert(player);
+        }
+        self.flagged != Some(player)
+    }
+
+    /// The player whose flag fell, if any.
+    pub fn flagged(&self) -> Option<usize> {
+        self.flagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_game_and_per_move() {
+        let control = TimeControl::new()
+            .with_per_game(Duration::from_secs(60))
+            .with_per_move(Duration::from_secs(20));
+        assert_eq!(control.to_string(), "1:00 per game, 0:20 per move");
+        let mut clock = Clock::new(control);
+        assert_eq!(clock.allowed(0), Some(Duration::from_secs(20)));
+        for _ in 0..3 {
+            assert!(clock.charge(0, Duration::from_secs(15)));
+        }
+        assert_eq!(clock.remaining(0), Some(Duration::from_secs(15)));
+        assert_eq!(clock.allowed(0), Some(Duration::from_secs(15)));
+        assert_eq!(clock.remaining(1), Some(Duration::from_secs(60)));
+        assert!(!clock.charge(1, Duration::from_secs(21)));
+        assert_eq!(clock.flagged(), Some(1));
+        assert!(clock.charge(0, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_unlimited_and_running_clock() {
+        let mut clock = Clock::new(TimeControl::new());
+        assert_eq!(clock.allowed(0), None);
+        assert!(clock.charge(0, Duration::from_secs(3600)));
+        let mut clock = Clock::new(TimeControl::new().with_per_game(Duration::from_secs(60)));
+        clock.start(1);
+        assert!(clock.remaining(1).unwrap() <= Duration::from_secs(60));
+        assert_eq!(clock.remaining(0), Some(Duration::from_secs(60)));
+        assert!(clock.stop());
+        assert!(clock.stop());
+        assert_eq!(format_time(Duration::from_millis(245_900)), "4:05");
+    }
+}
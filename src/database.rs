@@ -0,0 +1,258 @@
+// A database of canonical positions, enumerated ply by ply from the empty board with the symmetry module.
+// Positions can be proven by the solver, which is only practical for the last plies of a game.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    board::Board,
+    persist::{self, DataKind, PersistError},
+    solver::{Outcome, Solver},
+    symmetry,
+};
+
+/// The canonical positions after a number of placements, all with piece 0 in hand.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ply {
+    /// The positions by their canonical board, with the outcome for the player to place if it was proven.
+    pub positions: BTreeMap<u128, Option<Outcome>>,
+    /// The number of placements from the previous ply that won the game.
+    pub finished: u64,
+}
+
+impl Ply {
+    /// Count the proven positions with `outcome`.
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.positions
+            .values()
+            .filter(|&&o| o == Some(outcome))
+            .count()
+    }
+}
+
+/// All canonical positions up to a number of plies, where a ply is placing a piece and handing over the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionDb {
+    plies: Vec<Ply>,
+}
+
+impl PositionDb {
+    /// Enumerate all canonical positions up to and including `plies` placements from the empty board.
+    /// The number of positions grows about forty times with every ply, four plies take a few seconds.
+    pub fn build(plies: u8) -> Self {
+        PositionDb::build_from(&Board::new(), 0, plies)
+    }
+
+    /// Enumerate all canonical positions up to and including `plies` placements after `board` with `piece` in hand.
+    pub fn build_from(board: &Board, piece: u8, plies: u8) -> Self {
+        let mut first = Ply::default();
+        first
+            .positions
+            .insert(symmetry::canonical(board, piece).items(), None);
+        let mut db = PositionDb { plies: vec![first] };
+        for _ in 0..plies.min(board.empty_spaces().len() as u8) {
+            let next = db.expand(db.plies.last().expect("there is always a first ply"));
+            db.plies.push(next);
+        }
+        db
+    }
+
+    /// Place the piece in hand of every position of `ply` everywhere, and hand over every remaining piece.
+    fn expand(&self, ply: &Ply) -> Ply {
+        let mut next = Ply::default();
+        for &items in ply.positions.keys() {
+            let board = Board::from_u128(items);
+            for index in board.empty_spaces() {
+                let mut placed = board;
+                if placed.put_piece(0, index).is_err() {
+                    continue;
+                }
+                if placed.has_winner() {
+                    next.finished += 1;
+                    continue;
+                }
+                for piece in placed.valid_pieces() {
+                    next.positions
+                        .insert(symmetry::canonical(&placed, piece).items(), None);
+                }
+            }
+        }
+        next
+    }
+
+    /// The plies of the database, starting at the empty board.
+    pub fn plies(&self) -> &[Ply] {
+        &self.plies
+    }
+
+    /// Prove the outcome of every position of the last ply that is not proven yet, with `solver`.
+    /// This is only practical when at most about eight cells are empty in the last ply.
+    /// Returns the number of positions the solver visited.
+    pub fn solve_last(&mut self, solver: &mut Solver) -> u64 {
+        if let Some(ply) = self.plies.last_mut() {
+            for (&items, outcome) in ply.positions.iter_mut() {
+                if outcome.is_none() {
+                    *outcome = Some(solver.solve(&Board::from_u128(items), 0));
+                }
+            }
+        }
+        solver.nodes()
+    }
+
+    /// Write the database in the stored format, with a versioned header.
+    /// Every ply starts with a `ply <number> <finished>` line, followed by a line for each position.
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        for (number, ply) in self.plies.iter().enumerate() {
+            body.push_str(&format!("ply {} {}\n", number, ply.finished));
+            for (items, outcome) in &ply.positions {
+                let outcome = outcome.map_or("-".to_string(), |o| o.to_string());
+                body.push_str(&format!("{:032x} {}\n", items, outcome));
+            }
+        }
+        persist::write(DataKind::PositionDb, &body)
+    }
+
+    /// Read a database written by `to_text`.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::PositionDb, data)?;
+        let mut plies: Vec<Ply> = Vec::new();
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: &str| PersistError::Malformed {
+                line,
+                message: message.to_string(),
+            };
+            match text.split_whitespace().collect::<Vec<&str>>()[..] {
+                [] => {}
+                ["ply", number, finished] => {
+                    if number.parse() != Ok(plies.len()) {
+                        return Err(malformed("expected the plies in order"));
+                    }
+                    plies.push(Ply {
+                        positions: BTreeMap::new(),
+                        finished: finished
+                            .parse()
+                            .map_err(|_| malformed("expected a number of finished games"))?,
+                    });
+                }
+                [items, outcome] => {
+                    let ply = plies
+                        .last_mut()
+                        .ok_or_else(|| malformed("expected a ply before the positions"))?;
+                    let items = u128::from_str_radix(items, 16)
+                        .map_err(|_| malformed("expected a board in hexadecimal"))?;
+                    let board = Board::try_from_u128(items).map_err(|issues| {
+                        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+                        malformed(&issues.join(", "))
+                    })?;
+                    let outcome = match outcome {
+                        "-" => None,
+                        "win" => Some(Outcome::Win),
+                        "draw" => Some(Outcome::Draw),
+                        "loss" => Some(Outcome::Loss),
+                        _ => return Err(malformed("expected win, draw, loss or -")),
+                    };
+                    ply.positions.insert(board.items(), outcome);
+                }
+                _ => return Err(malformed("expected a ply or a position")),
+            }
+        }
+        if plies.is_empty() {
+            return Err(PersistError::Malformed {
+                line: 2,
+                message: "expected at least one ply".to_string(),
+            });
+        }
+        Ok(PositionDb { plies })
+    }
+}
+
+impl fmt::Display for PositionDb {
+    /// A table with the number of positions, finished games and proven outcomes of every ply.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>4} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "Ply", "Positions", "Finished", "Wins", "Draws", "Losses"
+        )?;
+        for (number, ply) in self.plies.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                number,
+                ply.positions.len(),
+                ply.finished,
+                ply.count(Outcome::Win),
+                ply.count(Outcome::Draw),
+                ply.count(Outcome::Loss)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_plies() {
+        let db = PositionDb::build(1);
+        assert_eq!(db.plies().len(), 2);
+        assert_eq!(db.plies()[0].positions.len(), 1);
+        // A single piece on the board is in a corner, on an edge or in the middle up to symmetry.
+        let boards = db.plies()[1]
+            .positions
+            .keys()
+            .map(|&items| Board::from_u128(items));
+        assert!(boards.clone().all(|board| board.empty_spaces().len() == 15));
+        assert!(db.plies()[1].positions.len() < 16 * 15);
+        assert_eq!(db.plies()[1].finished, 0);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut db = PositionDb::build(2);
+        db.plies[2]
+            .positions
+            .values_mut()
+            .take(3)
+            .for_each(|o| *o = Some(Outcome::Draw));
+        assert_eq!(PositionDb::from_text(&db.to_text()), Ok(db));
+        assert!(PositionDb::from_text("quarto positions 1\n").is_err());
+        assert!(matches!(
+            PositionDb::from_text("quarto positions 1\nply 0 0\n3 -\n"),
+            Err(PersistError::Malformed { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_solve_last() {
+        // Three light pieces with a hole on the last row, and the dark piece with a hole in hand.
+        let mut db = PositionDb::build_from(&crate::test_util::open_row(), 0b1001, 0);
+        assert!(db.solve_last(&mut Solver::new()) > 0);
+        assert_eq!(db.plies()[0].count(Outcome::Win), 1);
+    }
+
+    #[test]
+    fn test_build_from_late_position() {
+        let board = Board::new()
+            .apply_moves(&[
+                (0, 0),
+                (15, 1),
+                (3, 2),
+                (12, 3),
+                (5, 4),
+                (10, 5),
+                (6, 6),
+                (9, 7),
+                (1, 9),
+            ])
+            .unwrap();
+        let mut db = PositionDb::build_from(&board, 2, 2);
+        let counts: Vec<usize> = db.plies().iter().map(|ply| ply.positions.len()).collect();
+        assert_eq!(counts, vec![1, 42, 1160]);
+        assert_eq!(db.plies()[2].finished, 20);
+        db.solve_last(&mut Solver::new());
+        assert!(db.plies()[2].positions.values().all(Option::is_some));
+    }
+}
@@ -0,0 +1,433 @@
+// Diagnostics of the environment the game runs in: the terminal, the data directory, the config and the network.
+// Every check ends in a status and, when something is off, advice on what to do about it.
+
+use std::{
+    env, fmt, fs,
+    io::{self, IsTerminal},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    history::HistoryDb,
+    profile::{self, Profile},
+};
+
+/// How a check turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    /// Works, but with less than the full experience.
+    Warning,
+    /// Something is broken and needs fixing.
+    Problem,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::Warning => write!(f, "warning"),
+            Status::Problem => write!(f, "problem"),
+        }
+    }
+}
+
+/// The outcome of a single check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    /// What was found.
+    pub detail: String,
+    /// What to do about it, for warnings and problems.
+    pub advice: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            advice: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, advice: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Warning,
+            detail: detail.into(),
+            advice: Some(advice.into()),
+        }
+    }
+
+    fn problem(name: &'static str, detail: impl Into<String>, advice: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Problem,
+            detail: detail.into(),
+            advice: Some(advice.into()),
+        }
+    }
+}
+
+/// The outcomes of all checks, printed as a line per check with the advice below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// The worst status of the checks.
+    pub fn status(&self) -> Status {
+        self.checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(Status::Ok)
+    }
+
+    /// The number of checks with `status`.
+    pub fn count(&self, status: Status) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.checks.iter() {
+            writeln!(
+                f,
+                "{:<8} {:<15} {}",
+                check.status.to_string(),
+                check.name,
+                check.detail
+            )?;
+            if let Some(advice) = &check.advice {
+                writeln!(f, "{:<8} {:<15} -> {}", "", "", advice)?;
+            }
+        }
+        writeln!(
+            f,
+            "{} warnings, {} problems.",
+            self.count(Status::Warning),
+            self.count(Status::Problem)
+        )
+    }
+}
+
+/// Runs the checks, on the profile of this user unless another one is given.
+#[derive(Debug, Clone)]
+pub struct Doctor {
+    profile: Option<PathBuf>,
+    server: Option<String>,
+    timeout: Duration,
+}
+
+impl Default for Doctor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Doctor {
+    /// Check the profile at `profile::default_path`, without a server.
+    pub fn new() -> Self {
+        Doctor {
+            profile: profile::default_path(),
+            server: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Check the profile at `path`, and the data directory it is in.
+    pub fn with_profile(mut self, path: PathBuf) -> Self {
+        self.profile = Some(path);
+        self
+    }
+
+    /// Also check that the server at `address`, as host:port, can be reached.
+    pub fn with_server(mut self, address: &str) -> Self {
+        self.server = Some(address.to_string());
+        self
+    }
+
+    /// Give up connecting to the server after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run every check.
+    pub fn run(&self) -> Report {
+        let lookup = |name: &str| env::var(name).ok();
+        let mut checks = vec![
+            check_unicode(lookup),
+            check_color(lookup, io::stdout().is_terminal()),
+            check_raw_mode(),
+        ];
+        match &self.profile {
+            Some(path) => checks.extend(check_data(path)),
+            None => checks.push(Check::problem(
+                "data directory",
+                "there is no home or config directory",
+                "set $HOME, $XDG_CONFIG_HOME or $QUARTO_PROFILE",
+            )),
+        }
+        if let Some(address) = &self.server {
+            checks.push(check_server(address, self.timeout));
+        }
+        Report { checks }
+    }
+}
+
+/// Check the directory of the profile, and the profile and the history that are read from it.
+fn check_data(profile: &Path) -> Vec<Check> {
+    let dir = profile.parent().unwrap_or(Path::new("."));
+    let mut checks = vec![check_directory(dir)];
+    checks.push(match Profile::load(profile) {
+        Ok(Some(loaded)) => Check::ok(
+            "profile",
+            format!("{} ({})", profile.display(), loaded.name),
+        ),
+        Ok(None) => Check::warning(
+            "profile",
+            format!("{} does not exist", profile.display()),
+            "run `quarto setup` to pick your name and preferences",
+        ),
+        Err(e) => Check::problem(
+            "profile",
+            format!("{}: {}", profile.display(), e),
+            "fix the file, or remove it and run `quarto setup`",
+        ),
+    });
+    let history = profile.with_file_name("history");
+    checks.push(match HistoryDb::load(&history) {
+        Ok(db) => Check::ok("history", format!("{} games", db.len())),
+        Err(e) => Check::problem(
+            "history",
+            format!("{}: {}", history.display(), e),
+            "move the file away, a new history is started without it",
+        ),
+    });
+    checks
+}
+
+/// Check that the locale of `lookup` uses UTF-8, which the glyphs of the pieces need.
+pub fn check_unicode(lookup: impl Fn(&str) -> Option<String>) -> Check {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| lookup(name).filter(|value| !value.is_empty()));
+    match locale {
+        Some(locale) if locale.to_uppercase().replace('-', "").contains("UTF8") => {
+            Check::ok("unicode", format!("UTF-8 locale ({})", locale))
+        }
+        Some(locale) => Check::warning(
+            "unicode",
+            format!("the locale {} is not UTF-8", locale),
+            "set LANG to a UTF-8 locale like en_US.UTF-8, or the pieces show as garbage",
+        ),
+        None => Check::warning(
+            "unicode",
+            "no locale is set",
+            "set LANG to a UTF-8 locale like en_US.UTF-8, or the pieces show as garbage",
+        ),
+    }
+}
+
+/// Check that colors are shown, as `profile::detect_color` decides.
+pub fn check_color(lookup: impl Fn(&str) -> Option<String>, terminal: bool) -> Check {
+    if !terminal {
+        return Check::warning(
+            "color",
+            "stdout is not a terminal",
+            "run the doctor in the terminal you play in",
+        );
+    }
+    if lookup("NO_COLOR").is_some() {
+        return Check::warning(
+            "color",
+            "NO_COLOR is set",
+            "unset NO_COLOR, or pass --color always",
+        );
+    }
+    match lookup("TERM") {
+        Some(term) if term != "dumb" => Check::ok("color", format!("TERM is {}", term)),
+        Some(_) => Check::warning(
+            "color",
+            "TERM is dumb",
+            "use a terminal with colors, or pass --color always",
+        ),
+        None => Check::warning(
+            "color",
+            "TERM is not set",
+            "set TERM, like xterm-256color, or pass --color always",
+        ),
+    }
+}
+
+/// Check that the terminal can be switched to raw mode, which the full-screen interface needs.
+#[cfg(feature = "tui")]
+fn check_raw_mode() -> Check {
+    use ratatui::crossterm::terminal;
+
+    if !io::stdin().is_terminal() {
+        return Check::warning(
+            "raw mode",
+            "stdin is not a terminal",
+            "play in a terminal for the full-screen interface",
+        );
+    }
+    match terminal::enable_raw_mode().and_then(|_| terminal::disable_raw_mode()) {
+        Ok(()) => Check::ok("raw mode", "the full-screen interface can be used"),
+        Err(e) => Check::problem(
+            "raw mode",
+            e.to_string(),
+            "use another terminal, the game falls back to the line interface",
+        ),
+    }
+}
+
+/// Without the `tui` feature there is no full-screen interface that needs raw mode.
+#[cfg(not(feature = "tui"))]
+fn check_raw_mode() -> Check {
+    Check::warning(
+        "raw mode",
+        "built without the full-screen interface",
+        "build with --features tui to play full-screen",
+    )
+}
+
+/// Check that `dir` exists and files can be written in it.
+fn check_directory(dir: &Path) -> Check {
+    if !dir.exists() {
+        return Check::warning(
+            "data directory",
+            format!("{} does not exist yet", dir.display()),
+            "it is created when the profile is first saved, run `quarto setup`",
+        );
+    }
+    let probe = dir.join(".doctor");
+    match fs::write(&probe, "").and_then(|_| fs::remove_file(&probe)) {
+        Ok(()) => Check::ok("data directory", format!("{} is writable", dir.display())),
+        Err(e) => Check::problem(
+            "data directory",
+            format!("cannot write in {}: {}", dir.display(), e),
+            "fix the permissions of the directory, or set $QUARTO_PROFILE to a path elsewhere",
+        ),
+    }
+}
+
+/// Check that `address` resolves and accepts a connection within `timeout`.
+fn check_server(address: &str, timeout: Duration) -> Check {
+    let addresses = match address.to_socket_addrs() {
+        Ok(addresses) => addresses.collect::<Vec<_>>(),
+        Err(e) => {
+            return Check::problem(
+                "server",
+                format!("cannot resolve {}: {}", address, e),
+                "check the address, it is written as host:port",
+            );
+        }
+    };
+    let mut error = None;
+    for socket in addresses.iter() {
+        match TcpStream::connect_timeout(socket, timeout) {
+            Ok(_) => return Check::ok("server", format!("{} is reachable", address)),
+            Err(e) => error = Some(e),
+        }
+    }
+    Check::problem(
+        "server",
+        match error {
+            Some(e) => format!("cannot connect to {}: {}", address, e),
+            None => format!("{} has no addresses", address),
+        },
+        "check that the server runs and that no firewall or proxy blocks the port",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    fn vars(pairs: &'static [(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        |name| {
+            pairs
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_terminal_checks() {
+        assert_eq!(
+            check_unicode(vars(&[("LANG", "en_US.utf8")])).status,
+            Status::Ok
+        );
+        assert_eq!(
+            check_unicode(vars(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")])).status,
+            Status::Warning
+        );
+        assert_eq!(check_unicode(vars(&[])).status, Status::Warning);
+        assert_eq!(
+            check_color(vars(&[("TERM", "xterm")]), true).status,
+            Status::Ok
+        );
+        let check = check_color(vars(&[("TERM", "xterm"), ("NO_COLOR", "")]), true);
+        assert_eq!(
+            check.advice.as_deref(),
+            Some("unset NO_COLOR, or pass --color always")
+        );
+        assert_eq!(
+            check_color(vars(&[("TERM", "xterm")]), false).status,
+            Status::Warning
+        );
+    }
+
+    #[test]
+    fn test_data_checks() {
+        let dir = env::temp_dir().join(format!("quarto-doctor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let profile = dir.join("profile");
+        Profile::default().save(&profile).unwrap();
+        fs::write(dir.join("history"), "quarto record 1\n").unwrap();
+        let checks = check_data(&profile);
+        fs::remove_dir_all(&dir).unwrap();
+        let statuses: Vec<_> = checks
+            .iter()
+            .map(|check| (check.name, check.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("data directory", Status::Ok),
+                ("profile", Status::Ok),
+                ("history", Status::Problem),
+            ]
+        );
+        let report = Report { checks };
+        assert_eq!(report.status(), Status::Problem);
+        assert!(report.to_string().ends_with("0 warnings, 1 problems.\n"));
+    }
+
+    #[test]
+    fn test_server_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let timeout = Duration::from_secs(1);
+        assert_eq!(check_server(&address, timeout).status, Status::Ok);
+        drop(listener);
+        assert_eq!(check_server(&address, timeout).status, Status::Problem);
+        assert_eq!(check_server("no port", timeout).status, Status::Problem);
+    }
+}
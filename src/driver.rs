@@ -0,0 +1,322 @@
+// A turn-by-turn driver of a game, for callers that get the decisions one at a time instead of from a `Player`.
+// Follows the same rules as the loop of `QuartoGame`, which a stress test checks by playing both side by side.
+
+use crate::{
+    board::Board,
+    error::{PlacementError, QuartoError},
+    game::GameResult,
+    player::Player,
+    record::{GameRecord, Turn},
+};
+
+/// The decision the game waits for, or its result once it is over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// `player` hands a piece to the opponent.
+    Give { player: usize },
+    /// `player` places the `piece` they were handed.
+    Place { player: usize, piece: u8 },
+    /// `player` can call Quarto on the board as it is now.
+    Call { player: usize },
+    /// The game is over.
+    Over(GameResult),
+}
+
+/// A decision of the player the game waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Give(u8),
+    Place(u8),
+    Call(bool),
+}
+
+/// Plays a game one decision at a time, with the rules of `QuartoGame::play` or `QuartoGame::play_without_call`.
+#[derive(Debug, Clone)]
+pub struct GameDriver {
+    board: Board,
+    record: GameRecord,
+    /// Whether the players are asked to call Quarto.
+    calls: bool,
+    step: Step,
+    /// The last placement, until its placer decided on calling Quarto.
+    placed: Option<Turn>,
+}
+
+impl GameDriver {
+    /// Start a game on an empty board without Quarto calls, the first player hands over the first piece.
+    pub fn new() -> Self {
+        GameDriver {
+            board: Board::new(),
+            record: GameRecord::new(),
+            calls: false,
+            step: Step::Give { player: 0 },
+            placed: None,
+        }
+    }
+
+    /// Ask the players to call Quarto after every placement, like `QuartoGame::play`.
+    pub fn with_calls(mut self) -> Self {
+        self.calls = true;
+        self
+    }
+
+    /// The decision the game waits for.
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The turns played so far, with the result once the game is over.
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// The result of the game, or `None` if it is not over yet.
+    pub fn result(&self) -> Option<GameResult> {
+        self.record.result()
+    }
+
+    /// Make the decision the game waits for, and return the next step.
+    /// An illegal decision is rejected and leaves the game as it was.
+    pub fn decide(&mut self, decision: Decision) -> Result<Step, QuartoError> {
+        match (self.step, decision) {
+            (Step::Over(_), _) => {
+                return Err(QuartoError::GameOver {
+                    ply: self.record.len(),
+                });
+            }
+            (Step::Give { player }, Decision::Give(piece)) => {
+                if piece > 15 {
+                    return Err(PlacementError::InvalidPiece { piece }.into());
+                }
+                if !self.board.valid_piece(piece) {
+                    return Err(PlacementError::PieceAlreadyUsed { piece }.into());
+                }
+                self.step = Step::Place {
+                    player: 1 - player,
+                    piece,
+                };
+            }
+            (Step::Place { player, piece }, Decision::Place(index)) => {
+                self.board.put_piece(piece, index)?;
+                let turn = Turn {
+                    giver: 1 - player,
+                    piece,
+                    placer: player,
+                    index,
+                    quarto_called: false,
+                };
+                if self.calls {
+                    self.placed = Some(turn);
+                    self.step = Step::Call { player };
+                } else {
+                    self.record.push(turn);
+                    self.next_turn(player);
+                }
+            }
+            (Step::Call { player }, Decision::Call(called)) => {
+                let winner = self.board.has_winner();
+                match self.placed.take() {
+                    Some(mut turn) => {
+                        turn.quarto_called = called;
+                        self.record.push(turn);
+                        if called && winner {
+                            self.finish(GameResult::Win(player));
+                        } else if winner {
+                            // The placer missed the Quarto, the opponent can still claim it.
+                            self.step = Step::Call { player: 1 - player };
+                        } else {
+                            self.next_turn(player);
+                        }
+                    }
+                    None if called => self.finish(GameResult::Win(player)),
+                    None => self.finish(GameResult::Draw),
+                }
+            }
+            _ => return Err(QuartoError::UnexpectedDecision),
+        }
+        Ok(self.step)
+    }
+
+    /// Stop the game with an error, for example when a player cannot decide.
+    pub fn abort(&mut self) -> GameResult {
+        self.placed = None;
+        self.finish(GameResult::Error);
+        GameResult::Error
+    }
+
+    /// Ask the player the game waits for to decide, and return the next step.
+    /// A player that does not decide or decides illegally ends the game with an error, like in `QuartoGame`.
+    /// If that player forfeited, the opponent wins instead.
+    pub fn advance(&mut self, players: [&dyn Player; 2]) -> Step {
+        let (player, decision) = match self.step {
+            Step::Over(_) => return self.step,
+            Step::Give { player } => (
+                player,
+                players[player].get_piece(&self.board).map(Decision::Give),
+            ),
+            Step::Place { player, piece } => (
+                player,
+                players[player]
+                    .get_move(&self.board, piece)
+                    .map(Decision::Place),
+            ),
+            Step::Call { player } => (
+                player,
+                Some(Decision::Call(players[player].quarto(&self.board))),
+            ),
+        };
+        match decision.map(|decision| self.decide(decision)) {
+            Some(Ok(step)) => step,
+            None if players[player].forfeited() => {
+                self.placed = None;
+                self.finish(GameResult::Win(1 - player));
+                self.step
+            }
+            _ => Step::Over(self.abort()),
+        }
+    }
+
+    /// Ask the players for every decision until the game is over, and return the result.
+    pub fn play(&mut self, players: [&dyn Player; 2]) -> GameResult {
+        loop {
+            if let Step::Over(result) = self.advance(players) {
+                return result;
+            }
+        }
+    }
+
+    /// After `placer` placed a piece without winning, end the game on a full board or let them hand over the next piece.
+    fn next_turn(&mut self, placer: usize) {
+        if self.board.has_winner() {
+            self.finish(GameResult::Win(placer));
+        } else if self.board.board_full() {
+            self.finish(GameResult::Draw);
+        } else {
+            self.step = Step::Give { player: placer };
+        }
+    }
+
+    fn finish(&mut self, result: GameResult) {
+        self.record.set_result(result);
+        self.step = Step::Over(result);
+    }
+}
+
+impl Default for GameDriver {
+    fn default() -> Self {
+        GameDriver::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        QuartoGame,
+        player::ComputerPlayer,
+        random::SharedRng,
+        strategy::{DeterministicStrategy, DumbStrategy, NaiveStrategy, SmartStrategy, Strategy},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_rejects_unexpected_decisions() {
+        let mut driver = GameDriver::new();
+        assert_eq!(
+            driver.decide(Decision::Place(0)),
+            Err(QuartoError::UnexpectedDecision)
+        );
+        assert_eq!(
+            driver.decide(Decision::Give(3)),
+            Ok(Step::Place {
+                player: 1,
+                piece: 3
+            })
+        );
+        assert_eq!(
+            driver.decide(Decision::Place(16)),
+            Err(PlacementError::IndexOutOfRange { index: 16 }.into())
+        );
+        assert_eq!(
+            driver.decide(Decision::Place(5)),
+            Ok(Step::Give { player: 1 })
+        );
+        assert_eq!(
+            driver.decide(Decision::Give(3)),
+            Err(PlacementError::PieceAlreadyUsed { piece: 3 }.into())
+        );
+        assert_eq!(driver.record().len(), 1);
+    }
+
+    #[test]
+    fn test_missed_quarto_claimed_by_opponent() {
+        let mut driver = GameDriver::new().with_calls();
+        for (piece, index) in [(8, 12), (12, 13), (10, 14)] {
+            driver.decide(Decision::Give(piece)).unwrap();
+            driver.decide(Decision::Place(index)).unwrap();
+            driver.decide(Decision::Call(false)).unwrap();
+        }
+        driver.decide(Decision::Give(9)).unwrap();
+        assert_eq!(
+            driver.decide(Decision::Place(15)),
+            Ok(Step::Call { player: 0 })
+        );
+        assert_eq!(
+            driver.decide(Decision::Call(false)),
+            Ok(Step::Call { player: 1 })
+        );
+        assert_eq!(
+            driver.decide(Decision::Call(true)),
+            Ok(Step::Over(GameResult::Win(1)))
+        );
+        assert_eq!(
+            driver.decide(Decision::Call(true)),
+            Err(QuartoError::GameOver { ply: 4 })
+        );
+    }
+
+    /// Build the two players of a stress game, with the strategies picked by `seed` and drawing from `rng`.
+    fn stress_players(seed: u64, rng: &SharedRng) -> [Box<dyn Player>; 2] {
+        fn player<S: Strategy + 'static>(strategy: S) -> Box<dyn Player> {
+            Box::new(ComputerPlayer::new(strategy))
+        }
+        // The search is slow in debug builds, so only a few games use it.
+        let pick = |choice: u64| match choice % 16 {
+            0 => player(SmartStrategy::new(1).with_rng(rng.clone())),
+            1 => player(DeterministicStrategy),
+            2..8 => player(NaiveStrategy::new().with_rng(rng.clone())),
+            _ => player(DumbStrategy::new().with_rng(rng.clone())),
+        };
+        [pick(seed), pick(seed / 3)]
+    }
+
+    /// Play thousands of seeded games through the loop of `QuartoGame` and through the driver, with and without
+    /// Quarto calls, and check that both produce the same turns and result.
+    #[test]
+    fn test_driver_matches_game_loop() {
+        for seed in 0..2000 {
+            let calls = seed % 2 == 0;
+            let [first, second] = stress_players(seed, &SharedRng::seeded(seed));
+            let mut game = QuartoGame::new(first, second);
+            let expected = if calls {
+                game.play()
+            } else {
+                game.play_without_call()
+            };
+
+            let [first, second] = stress_players(seed, &SharedRng::seeded(seed));
+            let mut driver = if calls {
+                GameDriver::new().with_calls()
+            } else {
+                GameDriver::new()
+            };
+            let result = driver.play([first.as_ref(), second.as_ref()]);
+            assert_eq!(result, expected, "seed {}", seed);
+            assert_eq!(driver.record(), game.history(), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,528 @@
+// The engine protocol: one JSON object per line on stdin and stdout, so GUIs and tournament managers can run an engine.
+// The `engine` command answers it with a player of the registry, and an `ExternalEnginePlayer` asks it of another program.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use crate::{board::Board, player::Player};
+
+/// A line the GUI sends to the engine, a JSON object with a `type`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// `{"type":"newgame"}`: forget the last game, answered with `ready`.
+    NewGame,
+    /// `{"type":"position","fen":"5.../..c./..../...a 3 1"}`: the position of `Board::to_fen` to decide on next.
+    Position {
+        board: Board,
+        piece: Option<u8>,
+        mover: usize,
+    },
+    /// `{"type":"go"}`: decide on the position, answered with `bestmove`.
+    Go,
+    /// `{"type":"quit"}`: stop the engine.
+    Quit,
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Request::NewGame => write!(f, "{{\"type\":\"newgame\"}}"),
+            Request::Position {
+                board,
+                piece,
+                mover,
+            } => write!(
+                f,
+                "{{\"type\":\"position\",\"fen\":{}}}",
+                quote(&board.to_fen(*piece, *mover))
+            ),
+            Request::Go => write!(f, "{{\"type\":\"go\"}}"),
+            Request::Quit => write!(f, "{{\"type\":\"quit\"}}"),
+        }
+    }
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields = parse_object(line)?;
+        match kind(&fields)? {
+            "newgame" => Ok(Request::NewGame),
+            "position" => {
+                let Some(Value::Text(fen)) = fields.get("fen") else {
+                    return Err("a position needs a fen".to_string());
+                };
+                let (board, piece, mover) = Board::from_fen(fen)?;
+                Ok(Request::Position {
+                    board,
+                    piece,
+                    mover,
+                })
+            }
+            "go" => Ok(Request::Go),
+            "quit" => Ok(Request::Quit),
+            other => Err(format!("unknown request type '{}'", other)),
+        }
+    }
+}
+
+/// A line the engine sends back, a JSON object with a `type`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// `{"type":"ready"}`: the engine is ready for a new game.
+    Ready,
+    /// `{"type":"bestmove","place":5,"give":7}`: the cell to place the piece in hand on, and the piece to hand over
+    /// after. `place` is null when no piece is in hand, `give` when the placement ends the game.
+    BestMove { place: Option<u8>, give: Option<u8> },
+    /// `{"type":"error","message":"..."}`: the request could not be answered.
+    Error(String),
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = |n: Option<u8>| n.map_or("null".to_string(), |n| n.to_string());
+        match self {
+            Response::Ready => write!(f, "{{\"type\":\"ready\"}}"),
+            Response::BestMove { place, give } => write!(
+                f,
+                "{{\"type\":\"bestmove\",\"place\":{},\"give\":{}}}",
+                number(*place),
+                number(*give)
+            ),
+            Response::Error(message) => {
+                write!(f, "{{\"type\":\"error\",\"message\":{}}}", quote(message))
+            }
+        }
+    }
+}
+
+impl FromStr for Response {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields = parse_object(line)?;
+        let number = |name: &str| match fields.get(name) {
+            Some(Value::Number(n)) => u8::try_from(*n)
+                .map(Some)
+                .map_err(|_| format!("{} {} is out of range", name, n)),
+            Some(Value::Null) | None => Ok(None),
+            Some(_) => Err(format!("{} is not a number", name)),
+        };
+        match kind(&fields)? {
+            "ready" => Ok(Response::Ready),
+            "bestmove" => Ok(Response::BestMove {
+                place: number("place")?,
+                give: number("give")?,
+            }),
+            "error" => match fields.get("message") {
+                Some(Value::Text(message)) => Ok(Response::Error(message.clone())),
+                _ => Ok(Response::Error(String::new())),
+            },
+            other => Err(format!("unknown response type '{}'", other)),
+        }
+    }
+}
+
+/// Answer the requests on `input` with the decisions of `player`, until `quit` or the end of the input.
+/// Lines that are not requests are answered with an error, so a GUI can tell what went wrong.
+pub fn run(player: &dyn Player, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+    let mut position = (Board::new(), None);
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match line.parse() {
+            Ok(Request::NewGame) => {
+                position = (Board::new(), None);
+                Response::Ready
+            }
+            Ok(Request::Position { board, piece, .. }) => {
+                position = (board, piece);
+                continue;
+            }
+            Ok(Request::Go) => decide(player, &position.0, position.1),
+            Ok(Request::Quit) => break,
+            Err(e) => Response::Error(e),
+        };
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// The placement of `piece` by `player` on `board`, and the piece it hands over after.
+fn decide(player: &dyn Player, board: &Board, piece: Option<u8>) -> Response {
+    if board.game_over() {
+        return Response::Error("the game is over".to_string());
+    }
+    let mut after = *board;
+    let place = match piece {
+        Some(piece) => match player.get_move(board, piece) {
+            Some(index) if after.put_piece(piece, index).is_ok() => Some(index),
+            _ => return Response::Error(format!("no placement for piece {}", piece)),
+        },
+        None => None,
+    };
+    let give = if after.game_over() {
+        None
+    } else {
+        player.get_piece(&after)
+    };
+    Response::BestMove { place, give }
+}
+
+/// The pipes to a running engine program.
+#[derive(Debug)]
+struct Process {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl Process {
+    /// Send `requests` and read the response to the last one.
+    fn ask(&mut self, requests: &[Request]) -> Option<Response> {
+        for request in requests {
+            writeln!(self.input, "{}", request).ok()?;
+        }
+        self.input.flush().ok()?;
+        let mut line = String::new();
+        if self.output.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        line.parse().ok()
+    }
+
+    /// Ask the engine to quit, and make sure it does.
+    fn stop(mut self) {
+        writeln!(self.input, "{}", Request::Quit).ok();
+        drop(self.input);
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// A player that runs an engine program speaking the engine protocol, like the `engine` command of this crate.
+/// An engine that stops or answers nonsense forfeits the game, wrap the player in a `Watchdog` to also time it.
+#[derive(Debug)]
+pub struct ExternalEnginePlayer {
+    /// The running engine, `None` once it failed.
+    process: Mutex<Option<Process>>,
+    /// The board after the last placement of the engine, and the piece it chose to hand over on it.
+    given: Mutex<Option<(Board, Option<u8>)>>,
+}
+
+impl ExternalEnginePlayer {
+    /// Start `program` with `args`, and wait until it is ready for a game.
+    pub fn spawn(program: &str, args: &[&str]) -> Result<Self, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Cannot start the engine {}: {}", program, e))?;
+        let (Some(input), Some(output)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Cannot talk to the engine {}", program));
+        };
+        let player = ExternalEnginePlayer {
+            process: Mutex::new(Some(Process {
+                child,
+                input,
+                output: BufReader::new(output),
+            })),
+            given: Mutex::new(None),
+        };
+        match player.ask(&[Request::NewGame]) {
+            Some(Response::Ready) => Ok(player),
+            _ => Err(format!("The engine {} did not get ready", program)),
+        }
+    }
+
+    /// Start the engine of a command line split on whitespace, like `quarto engine --depth 3`.
+    pub fn from_command(command: &str) -> Result<Self, String> {
+        match command.split_whitespace().collect::<Vec<_>>()[..] {
+            [program, ref args @ ..] => ExternalEnginePlayer::spawn(program, args),
+            [] => Err("The engine command is empty".to_string()),
+        }
+    }
+
+    /// Send `requests` and read the response to the last one. An engine that fails to answer is stopped.
+    fn ask(&self, requests: &[Request]) -> Option<Response> {
+        let mut process = self.process.lock().unwrap();
+        let response = process.as_mut().and_then(|process| process.ask(requests));
+        if !matches!(response, Some(Response::Ready | Response::BestMove { .. }))
+            && let Some(process) = process.take()
+        {
+            process.stop();
+        }
+        response
+    }
+
+    /// Ask the engine for its placement of `piece` on `board`, and the piece it hands over after.
+    fn best_move(&self, board: &Board, piece: Option<u8>) -> Option<(Option<u8>, Option<u8>)> {
+        let placed = 16 - board.empty_spaces().len();
+        let position = Request::Position {
+            board: *board,
+            piece,
+            mover: (placed + usize::from(piece.is_some())) % 2,
+        };
+        match self.ask(&[position, Request::Go])? {
+            Response::BestMove { place, give } => Some((place, give)),
+            _ => None,
+        }
+    }
+}
+
+impl Player for ExternalEnginePlayer {
+    /// The engine chose the piece with its last placement, it is only asked again for the first piece of a game.
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        match self.given.lock().unwrap().take() {
+            Some((after, give)) if after == *board => give,
+            _ => self.best_move(board, None)?.1,
+        }
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        let (place, give) = self.best_move(board, Some(piece))?;
+        let index = place?;
+        let mut after = *board;
+        after.put_piece(piece, index).ok()?;
+        *self.given.lock().unwrap() = Some((after, give));
+        Some(index)
+    }
+
+    /// The protocol has no call, the engine calls Quarto whenever a line is won.
+    fn quarto(&self, board: &Board) -> bool {
+        board.has_winner()
+    }
+
+    fn forfeited(&self) -> bool {
+        self.process.lock().unwrap().is_none()
+    }
+}
+
+impl Drop for ExternalEnginePlayer {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.get_mut().unwrap().take() {
+            process.stop();
+        }
+    }
+}
+
+/// A value of a field of a protocol line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Text(String),
+    Number(i64),
+    Bool(bool),
+    Null,
+}
+
+/// The `type` of a protocol line.
+fn kind(fields: &BTreeMap<String, Value>) -> Result<&str, String> {
+    match fields.get("type") {
+        Some(Value::Text(kind)) => Ok(kind),
+        _ => Err("the line has no type".to_string()),
+    }
+}
+
+/// Write `text` as a JSON string.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Read a JSON object of strings, integers, booleans and nulls, which is all the protocol sends.
+fn parse_object(line: &str) -> Result<BTreeMap<String, Value>, String> {
+    let invalid = || format!("'{}' is not a JSON object of the protocol", line.trim());
+    let mut chars = line.trim().chars().peekable();
+    let mut fields = BTreeMap::new();
+    let skip = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    let text = |chars: &mut std::iter::Peekable<std::str::Chars>| -> Option<String> {
+        let mut text = String::new();
+        loop {
+            match chars.next()? {
+                '"' => return Some(text),
+                '\\' => match chars.next()? {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'u' => {
+                        let code: String = (0..4).filter_map(|_| chars.next()).collect();
+                        text.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                    }
+                    c => text.push(c),
+                },
+                c => text.push(c),
+            }
+        }
+    };
+    if chars.next() != Some('{') {
+        return Err(invalid());
+    }
+    skip(&mut chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(fields);
+    }
+    loop {
+        skip(&mut chars);
+        if chars.next() != Some('"') {
+            return Err(invalid());
+        }
+        let name = text(&mut chars).ok_or_else(invalid)?;
+        skip(&mut chars);
+        if chars.next() != Some(':') {
+            return Err(invalid());
+        }
+        skip(&mut chars);
+        let value = match chars.peek() {
+            Some('"') => {
+                chars.next();
+                Value::Text(text(&mut chars).ok_or_else(invalid)?)
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '-') {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "null" => Value::Null,
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    number => Value::Number(number.parse().map_err(|_| invalid())?),
+                }
+            }
+            None => return Err(invalid()),
+        };
+        fields.insert(name, value);
+        skip(&mut chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') if chars.next().is_none() => return Ok(fields),
+            _ => return Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player::ComputerPlayer, strategy::SmartStrategy};
+
+    #[test]
+    fn test_messages_round_trip() {
+        let board = Board::new().apply_moves(&[(5, 0), (12, 6)]).unwrap();
+        let requests = [
+            Request::NewGame,
+            Request::Position {
+                board,
+                piece: Some(3),
+                mover: 1,
+            },
+            Request::Go,
+            Request::Quit,
+        ];
+        for request in requests {
+            assert_eq!(request.to_string().parse(), Ok(request));
+        }
+        let responses = [
+            Response::Ready,
+            Response::BestMove {
+                place: Some(5),
+                give: None,
+            },
+            Response::Error("a \"quoted\" \\ message".to_string()),
+        ];
+        for response in responses {
+            assert_eq!(response.to_string().parse(), Ok(response));
+        }
+        assert_eq!(
+            " { \"give\" : 7 , \"type\":\"bestmove\" } ".parse(),
+            Ok(Response::BestMove {
+                place: None,
+                give: Some(7)
+            })
+        );
+        assert!("{\"type\":\"go\"".parse::<Request>().is_err());
+        assert!(
+            "{\"type\":\"bestmove\",\"give\":300}"
+                .parse::<Response>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_engine_answers_requests() {
+        let player = ComputerPlayer::new(SmartStrategy::new(1));
+        let input = [
+            "{\"type\":\"newgame\"}",
+            "{\"type\":\"position\",\"fen\":\"89a./..../..../...0 b 1\"}",
+            "{\"type\":\"go\"}",
+            "go",
+            "{\"type\":\"quit\"}",
+            "{\"type\":\"go\"}",
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        run(&player, input.as_bytes(), &mut output).unwrap();
+        let responses: Vec<Response> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0], Response::Ready);
+        // Placing the tall piece on the last cell of the row wins, so nothing is handed over.
+        assert_eq!(
+            responses[1],
+            Response::BestMove {
+                place: Some(3),
+                give: None
+            }
+        );
+        assert!(matches!(responses[2], Response::Error(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_engine() {
+        // An engine that places on D1 and hands over piece 5, whatever the position.
+        let script = "read line; echo '{\"type\":\"ready\"}'; \
+            while read line; do case $line in *go*) echo '{\"type\":\"bestmove\",\"place\":3,\"give\":5}';; esac; done";
+        let player = ExternalEnginePlayer::spawn("sh", &["-c", script]).unwrap();
+        let board = Board::new().apply_moves(&[(8, 0), (9, 1)]).unwrap();
+        assert_eq!(player.get_move(&board, 10), Some(3));
+        let after = board.apply_moves(&[(10, 3)]).unwrap();
+        assert_eq!(player.get_piece(&after), Some(5));
+        assert_eq!(player.get_piece(&Board::new()), Some(5));
+        assert!(!player.forfeited());
+        // D1 is taken on this board, so the engine's answer is refused.
+        assert_eq!(player.get_move(&after, 11), None);
+
+        let silent =
+            ExternalEnginePlayer::spawn("sh", &["-c", "read line; echo '{\"type\":\"ready\"}'"])
+                .unwrap();
+        assert_eq!(silent.get_piece(&board), None);
+        assert!(silent.forfeited());
+        assert!(ExternalEnginePlayer::from_command("no-such-quarto-engine").is_err());
+    }
+}
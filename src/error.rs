@@ -0,0 +1,136 @@
+// Errors that can occur while playing or setting up a game of Quarto.
+
+use std::{error::Error, fmt};
+
+use crate::persist::PersistError;
+
+/// The reason a piece cannot be placed on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    /// There is already a piece on the index.
+    OccupiedCell { index: u8 },
+    /// The piece is already on the board.
+    PieceAlreadyUsed { piece: u8 },
+    /// The index is not between 0 and (incl.) 15.
+    IndexOutOfRange { index: u8 },
+    /// The piece is not between 0 and (incl.) 15.
+    InvalidPiece { piece: u8 },
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::OccupiedCell { index } => {
+                write!(f, "index {} is already occupied", index)
+            }
+            PlacementError::PieceAlreadyUsed { piece } => {
+                write!(f, "piece {} is already on the board", piece)
+            }
+            PlacementError::IndexOutOfRange { index } => {
+                write!(f, "index {} is not on the board", index)
+            }
+            PlacementError::InvalidPiece { piece } => write!(f, "piece {} does not exist", piece),
+        }
+    }
+}
+
+impl Error for PlacementError {}
+
+/// The crate-wide error type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuartoError {
+    /// A placement in a sequence of moves is not allowed.
+    IllegalMove { ply: usize, error: PlacementError },
+    /// A move in a sequence of moves comes after the game was already over.
+    GameOver { ply: usize },
+    /// A list of cells does not describe a board of 16 cells.
+    WrongCellCount { len: usize },
+    /// A piece cannot be placed on the board.
+    Placement(PlacementError),
+    /// Stored data cannot be read.
+    Persist(PersistError),
+    /// A decision was made that the game is not waiting for, like placing a piece before one was handed over.
+    UnexpectedDecision,
+}
+
+impl fmt::Display for QuartoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuartoError::IllegalMove { ply, error } => {
+                write!(f, "move {} is illegal: {}", ply, error)
+            }
+            QuartoError::GameOver { ply } => {
+                write!(f, "move {} is played after the game is over", ply)
+            }
+            QuartoError::WrongCellCount { len } => {
+                write!(f, "a board has 16 cells, got {}", len)
+            }
+            QuartoError::Placement(error) => write!(f, "{}", error),
+            QuartoError::Persist(error) => write!(f, "{}", error),
+            QuartoError::UnexpectedDecision => {
+                write!(f, "the game is not waiting for this decision")
+            }
+        }
+    }
+}
+
+impl Error for QuartoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QuartoError::IllegalMove { error, .. } | QuartoError::Placement(error) => Some(error),
+            QuartoError::Persist(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PlacementError> for QuartoError {
+    fn from(error: PlacementError) -> Self {
+        QuartoError::Placement(error)
+    }
+}
+
+impl From<PersistError> for QuartoError {
+    fn from(error: PersistError) -> Self {
+        QuartoError::Persist(error)
+    }
+}
+
+/// Something wrong with a board, as found by `Board::validate` and `Board::check_reachable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardIssue {
+    /// The piece is on more than one index.
+    DuplicatePiece { piece: u8, indices: Vec<u8> },
+    /// The cell has attribute bits, but not the bit that says it holds a piece.
+    AttributesWithoutPiece { index: u8 },
+    /// The cell has bits set that are not part of a piece.
+    StrayBits { index: u8 },
+    /// More than one line wins and no cell is on all of them, so the game would have ended before the last placement.
+    WonBeforeLastPlacement,
+}
+
+impl fmt::Display for BoardIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardIssue::DuplicatePiece { piece, indices } => {
+                let indices: Vec<String> = indices.iter().map(u8::to_string).collect();
+                write!(f, "piece {} is on indices {}", piece, indices.join(", "))
+            }
+            BoardIssue::AttributesWithoutPiece { index } => {
+                write!(f, "index {} has attributes but no piece", index)
+            }
+            BoardIssue::StrayBits { index } => {
+                write!(
+                    f,
+                    "index {} has bits set that are not part of a piece",
+                    index
+                )
+            }
+            BoardIssue::WonBeforeLastPlacement => {
+                write!(f, "the game was won before the last placement")
+            }
+        }
+    }
+}
+
+impl Error for BoardIssue {}
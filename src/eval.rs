@@ -0,0 +1,113 @@
+// Quick estimates of who is ahead, for the evaluation bar next to a watched board.
+// Plays out random games in which both sides take a win when they can and avoid handing over a winning piece.
+
+use crate::{board::Board, random::SharedRng};
+
+/// Play one game from `board` to the end and return the score for the first player: 1 for a win, 0.5 for a draw.
+/// `mover` places `in_hand` if there is a piece in hand, and otherwise hands over the next piece.
+fn playout(
+    mut board: Board,
+    mut in_hand: Option<u8>,
+    mut mover: usize,
+    rng: &mut fastrand::Rng,
+) -> f64 {
+    loop {
+        let piece = match in_hand.take() {
+            Some(piece) => piece,
+            None => {
+                let safe = board.safe_pieces();
+                let pieces = if safe.is_empty() {
+                    board.valid_pieces()
+                } else {
+                    safe
+                };
+                if pieces.is_empty() {
+                    return 0.5;
+                }
+                mover = 1 - mover;
+                pieces[rng.usize(..pieces.len())]
+            }
+        };
+        if !board.winning_placements(piece).is_empty() {
+            return if mover == 0 { 1.0 } else { 0.0 };
+        }
+        let spaces = board.empty_spaces();
+        if spaces.is_empty()
+            || board
+                .put_piece(piece, spaces[rng.usize(..spaces.len())])
+                .is_err()
+        {
+            return 0.5;
+        }
+        if board.board_full() {
+            return 0.5;
+        }
+    }
+}
+
+/// Estimate the expected score of the first player with `playouts` random games, from 0 (lost) to 1 (won).
+/// `mover` is the player to place `in_hand`, or to hand over a piece if nothing is in hand.
+/// A finished game, or no playouts at all, is scored as even. The random games draw from `rng`.
+pub fn estimate(
+    board: &Board,
+    in_hand: Option<u8>,
+    mover: usize,
+    playouts: u32,
+    rng: &SharedRng,
+) -> f64 {
+    if playouts == 0 || board.game_over() {
+        return 0.5;
+    }
+    let mut rng = rng.fork();
+    let total: f64 = (0..playouts)
+        .map(|_| playout(*board, in_hand, mover, &mut rng))
+        .sum();
+    total / playouts as f64
+}
+
+/// Render `score` as a bar of `width` characters, filled from the left for the first player.
+pub fn bar(score: f64, width: usize) -> String {
+    let filled = (score.clamp(0.0, 1.0) * width as f64).round() as usize;
+    format!(
+        "Player 1 [{}{}] Player 2  {:.0}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        score * 100.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::open_row;
+
+    #[test]
+    fn test_winning_piece_in_hand() {
+        assert_eq!(
+            estimate(&open_row(), Some(0b1001), 0, 50, &SharedRng::seeded(1)),
+            1.0
+        );
+        assert_eq!(
+            estimate(&open_row(), Some(0b1001), 1, 50, &SharedRng::seeded(1)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_empty_board_is_close() {
+        let score = estimate(&Board::new(), None, 0, 400, &SharedRng::seeded(1));
+        assert!((0.2..=0.8).contains(&score), "{}", score);
+    }
+
+    #[test]
+    fn test_no_playouts() {
+        assert_eq!(estimate(&open_row(), None, 0, 0, &SharedRng::new()), 0.5);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0.5, 10), "Player 1 [#####-----] Player 2  50%");
+        assert_eq!(bar(1.0, 4), "Player 1 [####] Player 2  100%");
+        assert_eq!(bar(0.0, 4), "Player 1 [----] Player 2  0%");
+    }
+}
@@ -0,0 +1,173 @@
+// Static evaluation of the positions where the search stops looking ahead.
+// Without an `Evaluator` every such position scores as even, a trained model can score them instead.
+
+use std::{fmt, str::FromStr};
+
+use crate::{board::Board, search::WIN};
+
+/// Scores positions at the end of a search, for example with a trained model.
+/// Scores are for the player to move, and must stay between `-WIN` and `WIN` so they never look like a proven result.
+pub trait Evaluator: Send + Sync {
+    /// Score `board` for the player that places `piece`.
+    fn evaluate(&self, board: &Board, piece: u8) -> i32;
+
+    /// Score `board` for the player that hands over the next piece: the best piece it can hand over, by default.
+    fn evaluate_give(&self, board: &Board) -> i32 {
+        board
+            .valid_pieces()
+            .into_iter()
+            .map(|piece| -self.evaluate(board, piece))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Weighs the threats on the board and the pieces that are safe to hand over, for weights tuned by self-play.
+/// The weights are written as `pairs,threats,safe`, like `2,-10,5`, which `quarto tune` prints and `--weights` reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreatEvaluator {
+    /// The weights of the open lines with two pieces, the open lines with three pieces, and the safe pieces.
+    pub weights: [f64; 3],
+}
+
+impl Default for ThreatEvaluator {
+    fn default() -> Self {
+        ThreatEvaluator {
+            weights: [2.0, -10.0, 5.0],
+        }
+    }
+}
+
+impl ThreatEvaluator {
+    /// Create an evaluator with the weights of `weights`.
+    pub fn new(weights: [f64; 3]) -> Self {
+        ThreatEvaluator { weights }
+    }
+}
+
+impl fmt::Display for ThreatEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [pairs, threats, safe] = self.weights;
+        write!(f, "{},{},{}", pairs, threats, safe)
+    }
+}
+
+impl FromStr for ThreatEvaluator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not three weights like 2,-10,5", s);
+        let weights: Vec<f64> = s
+            .split(',')
+            .map(|weight| weight.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        match weights[..] {
+            [pairs, threats, safe] => Ok(ThreatEvaluator::new([pairs, threats, safe])),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Evaluator for ThreatEvaluator {
+    /// The placer cannot win with the piece, or the search would have found it.
+    fn evaluate(&self, board: &Board, piece: u8) -> i32 {
+        let lines: Vec<_> = board.lines().filter(|line| line.is_open()).collect();
+        let pairs = lines.iter().filter(|line| line.pieces() == 2).count();
+        let threats = lines.iter().filter(|line| line.pieces() == 3).count();
+        let safe = board
+            .valid_pieces()
+            .into_iter()
+            .filter(|&other| other != piece && !lines.iter().any(|line| line.completed_by(other)))
+            .count();
+        let [pairs_weight, threats_weight, safe_weight] = self.weights;
+        (pairs_weight * pairs as f64 + threats_weight * threats as f64 + safe_weight * safe as f64)
+            .round() as i32
+    }
+}
+
+/// Evaluators are shared in options that are printed for debugging, without showing their model.
+impl fmt::Debug for dyn Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Evaluator")
+    }
+}
+
+/// Keep a score of an evaluator below the score of a proven win or loss.
+pub(crate) fn bounded(score: i32) -> i32 {
+    score.clamp(-(WIN - 1), WIN - 1)
+}
+
+/// The features of a position for a model: for every cell whether it holds a piece and its four attributes,
+/// then the four attributes of the piece in hand, 84 values of 0 or 1.
+pub fn features(board: &Board, piece: u8) -> [f32; 84] {
+    let mut features = [0.0; 84];
+    let bits = |piece: u8| (0..4).map(move |bit| f32::from((piece >> (3 - bit)) & 1));
+    for index in 0..16u8 {
+        if let Some(placed) = board.piece_at(index) {
+            let cell = &mut features[index as usize * 5..index as usize * 5 + 5];
+            cell[0] = 1.0;
+            for (feature, bit) in cell[1..].iter_mut().zip(bits(placed)) {
+                *feature = bit;
+            }
+        }
+    }
+    for (feature, bit) in features[80..].iter_mut().zip(bits(piece)) {
+        *feature = bit;
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::Searcher;
+
+    use super::*;
+
+    /// Prefers positions with the piece in hand of more attributes, so it only decides between even placements.
+    struct Tall;
+
+    impl Evaluator for Tall {
+        fn evaluate(&self, _: &Board, piece: u8) -> i32 {
+            piece.count_ones() as i32 * 100
+        }
+    }
+
+    #[test]
+    fn test_evaluator_scores_leaves() {
+        let board = Board::new().apply_moves(&[(0, 0), (5, 5)]).unwrap();
+        let plain = Searcher::new().piece_scores(&board, 0);
+        assert!(plain.iter().all(|&(_, score)| score == 0));
+        let scores = Searcher::new()
+            .with_evaluator(&Tall)
+            .piece_scores(&board, 0);
+        // Handing over a piece gives the opponent its score.
+        for (piece, score) in scores {
+            assert_eq!(score, -(piece.count_ones() as i32) * 100);
+        }
+        assert_eq!(bounded(10 * WIN), WIN - 1);
+        let (piece, _) = Searcher::new()
+            .with_evaluator(&Tall)
+            .best_piece(&board, 0)
+            .unwrap();
+        assert_eq!(piece, 1);
+    }
+
+    #[test]
+    fn test_weights_round_trip() {
+        let evaluator = ThreatEvaluator::new([1.5, -12.25, 4.0]);
+        assert_eq!(evaluator.to_string(), "1.5,-12.25,4");
+        assert_eq!(evaluator.to_string().parse(), Ok(evaluator));
+        assert!("1,2".parse::<ThreatEvaluator>().is_err());
+        assert!("1,two,3".parse::<ThreatEvaluator>().is_err());
+    }
+
+    #[test]
+    fn test_features() {
+        let board = Board::new().apply_moves(&[(0b1010, 1)]).unwrap();
+        let features = features(&board, 0b0001);
+        assert_eq!(features[5..10], [1.0, 1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(features[80..], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(features.iter().sum::<f32>(), 4.0);
+    }
+}
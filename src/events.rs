@@ -0,0 +1,114 @@
+// A broadcast bus for the events of a game.
+// The game loop publishes what happens, and every interested part (stats, logging, spectators, dashboards) subscribes on its own.
+
+use std::sync::{
+    Arc, Mutex, MutexGuard,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::game::GameResult;
+
+/// Something that happened in a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    /// A new game started on an empty board.
+    GameStarted,
+    /// `player` handed `piece` to the opponent.
+    PieceGiven { player: usize, piece: u8 },
+    /// `player` placed `piece` on `index`.
+    PiecePlaced { player: usize, piece: u8, index: u8 },
+    /// `player` called Quarto, `correct` tells if there really was a winning line.
+    QuartoCalled { player: usize, correct: bool },
+    /// The game is over.
+    GameEnded { result: GameResult },
+}
+
+/// A bus that delivers every published `GameEvent` to all subscribers.
+/// Clones share the same subscribers, so a clone can be handed to the game while another is kept to subscribe later.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<GameEvent>>>>,
+}
+
+impl EventBus {
+    /// Create a new bus without subscribers.
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+
+    /// Subscribe to all events published from now on.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<GameEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().push(sender);
+        receiver
+    }
+
+    /// Send `event` to every subscriber, forgetting the ones that hung up.
+    pub fn publish(&self, event: GameEvent) {
+        self.lock()
+            .retain(|subscriber| subscriber.send(event).is_ok());
+    }
+
+    /// The number of subscribers that were still listening at the last publish.
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// A subscriber that panicked while holding the lock does not stop the others from getting events.
+    fn lock(&self) -> MutexGuard<'_, Vec<Sender<GameEvent>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_every_subscriber_gets_events() {
+        let bus = EventBus::new();
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+        bus.publish(GameEvent::GameStarted);
+        assert_eq!(first.try_recv(), Ok(GameEvent::GameStarted));
+        assert_eq!(second.try_recv(), Ok(GameEvent::GameStarted));
+    }
+
+    #[test]
+    fn test_dropped_subscriber_is_removed() {
+        let bus = EventBus::new();
+        let kept = bus.subscribe();
+        drop(bus.subscribe());
+        assert_eq!(bus.subscriber_count(), 2);
+        bus.publish(GameEvent::GameStarted);
+        assert_eq!(bus.subscriber_count(), 1);
+        assert!(kept.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_subscriber_on_other_thread() {
+        let bus = EventBus::new();
+        let receiver = bus.subscribe();
+        let publisher = bus.clone();
+        thread::spawn(move || {
+            publisher.publish(GameEvent::PieceGiven {
+                player: 0,
+                piece: 3,
+            })
+        })
+        .join()
+        .unwrap();
+        assert_eq!(
+            receiver.recv(),
+            Ok(GameEvent::PieceGiven {
+                player: 0,
+                piece: 3
+            })
+        );
+    }
+}
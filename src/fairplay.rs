@@ -0,0 +1,316 @@
+// Fair-play review of rated games: how often a player picked the engine's best decision at a fixed depth.
+// Accuracy that a person is very unlikely to reach, or long streaks of best decisions, flag the game for a moderator.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::Board,
+    persist::{self, DataKind, PersistError},
+    record::GameRecord,
+    search::Searcher,
+};
+
+/// The depth of the comparison and the thresholds to flag a game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FairPlayConfig {
+    /// The number of turns the engine looks ahead to find the best decisions.
+    pub depth: u8,
+    /// Games with fewer informative decisions of a player are never flagged for accuracy.
+    pub min_decisions: u32,
+    /// The share of best decisions from which a player can be flagged.
+    pub min_match_rate: f64,
+    /// How many standard deviations the matches must lie above what random decisions would match.
+    pub min_z_score: f64,
+    /// A streak of this many best decisions in a row is flagged on its own.
+    pub max_streak: u32,
+}
+
+impl Default for FairPlayConfig {
+    fn default() -> Self {
+        FairPlayConfig {
+            depth: 2,
+            min_decisions: 8,
+            min_match_rate: 0.9,
+            min_z_score: 3.0,
+            max_streak: 12,
+        }
+    }
+}
+
+/// How the decisions of one player compare to the best decisions of the engine.
+/// Only informative decisions count: with a single option, or with every option equally good, nothing is learned.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Accuracy {
+    pub decisions: u32,
+    /// Decisions that were as good as the best one.
+    pub matches: u32,
+    /// The matches a player deciding at random would expect.
+    pub expected: f64,
+    /// The variance of the matches of a player deciding at random.
+    pub variance: f64,
+    /// The most best decisions in a row.
+    pub longest_streak: u32,
+}
+
+impl Accuracy {
+    /// The share of informative decisions that matched the best one.
+    pub fn match_rate(&self) -> f64 {
+        if self.decisions == 0 {
+            return 0.0;
+        }
+        self.matches as f64 / self.decisions as f64
+    }
+
+    /// How many standard deviations the matches lie above those of random decisions.
+    pub fn z_score(&self) -> f64 {
+        if self.variance <= 0.0 {
+            return 0.0;
+        }
+        (self.matches as f64 - self.expected) / self.variance.sqrt()
+    }
+
+    /// Count a decision where `chosen` scored as listed in `scores`.
+    fn add(&mut self, scores: &[(u8, i32)], chosen: u8, streak: &mut u32) {
+        let best = scores.iter().map(|&(_, score)| score).max();
+        let worst = scores.iter().map(|&(_, score)| score).min();
+        if scores.len() < 2 || best == worst {
+            return;
+        }
+        let best_count = scores.iter().filter(|&&(_, s)| Some(s) == best).count();
+        let p = best_count as f64 / scores.len() as f64;
+        self.decisions += 1;
+        self.expected += p;
+        self.variance += p * (1.0 - p);
+        if scores.iter().any(|&(d, s)| d == chosen && Some(s) == best) {
+            self.matches += 1;
+            *streak += 1;
+            self.longest_streak = self.longest_streak.max(*streak);
+        } else {
+            *streak = 0;
+        }
+    }
+}
+
+/// Compare the decisions of `player` in `record` with the best decisions at `depth`.
+/// A game that cannot be replayed is compared up to the first turn that cannot be played.
+pub fn accuracy(record: &GameRecord, player: usize, depth: u8) -> Accuracy {
+    let mut accuracy = Accuracy::default();
+    let mut streak = 0;
+    let mut board = Board::new();
+    let mut searcher = Searcher::new();
+    for turn in record.turns() {
+        if turn.giver == player {
+            let scores = searcher.piece_scores(&board, depth);
+            accuracy.add(&scores, turn.piece, &mut streak);
+        }
+        if turn.placer == player {
+            let scores = searcher.move_scores(&board, turn.piece, depth);
+            accuracy.add(&scores, turn.index, &mut streak);
+        }
+        if board.put_piece(turn.piece, turn.index).is_err() {
+            break;
+        }
+    }
+    accuracy
+}
+
+/// Why a player was flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagReason {
+    /// The share of best decisions is far above what is likely.
+    Accuracy,
+    /// Too many best decisions in a row.
+    Streak,
+}
+
+impl fmt::Display for FlagReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagReason::Accuracy => write!(f, "accuracy"),
+            FlagReason::Streak => write!(f, "streak"),
+        }
+    }
+}
+
+/// The fair-play review of a game, kept next to its record for moderators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Review {
+    /// The seats that were reviewed, with their accuracy.
+    pub players: Vec<(usize, Accuracy)>,
+    /// The seats flagged for review, with the reason.
+    pub flags: Vec<(usize, FlagReason)>,
+}
+
+impl Review {
+    /// Review the decisions of the players on `seats`, usually the people in a rated game.
+    pub fn new(record: &GameRecord, seats: &[usize], config: &FairPlayConfig) -> Self {
+        let mut players = Vec::new();
+        let mut flags = Vec::new();
+        for &seat in seats {
+            let accuracy = accuracy(record, seat, config.depth);
+            if accuracy.decisions >= config.min_decisions
+                && accuracy.match_rate() >= config.min_match_rate
+                && accuracy.z_score() >= config.min_z_score
+            {
+                flags.push((seat, FlagReason::Accuracy));
+            }
+            if accuracy.longest_streak >= config.max_streak {
+                flags.push((seat, FlagReason::Streak));
+            }
+            players.push((seat, accuracy));
+        }
+        Review { players, flags }
+    }
+
+    /// Check if a moderator should look at the game.
+    pub fn is_flagged(&self) -> bool {
+        !self.flags.is_empty()
+    }
+
+    /// Write the review with a header, like `player 0 14 13 6.5 2.1 9` per seat and `flag 0 accuracy` per flag.
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        for (seat, a) in self.players.iter() {
+            body += &format!(
+                "player {} {} {} {} {} {}\n",
+                seat, a.decisions, a.matches, a.expected, a.variance, a.longest_streak
+            );
+        }
+        for (seat, reason) in self.flags.iter() {
+            body += &format!("flag {} {}\n", seat, reason);
+        }
+        persist::write(DataKind::Review, &body)
+    }
+
+    /// Read a review written by `to_text`.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::Review, data)?;
+        let mut review = Review {
+            players: Vec::new(),
+            flags: Vec::new(),
+        };
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: &str| PersistError::Malformed {
+                line,
+                message: message.to_string(),
+            };
+            match text.split_whitespace().collect::<Vec<_>>()[..] {
+                [] => {}
+                [
+                    "player",
+                    seat,
+                    decisions,
+                    matches,
+                    expected,
+                    variance,
+                    streak,
+                ] => {
+                    let number = || malformed("a player has six numbers");
+                    let accuracy = Accuracy {
+                        decisions: decisions.parse().map_err(|_| number())?,
+                        matches: matches.parse().map_err(|_| number())?,
+                        expected: expected.parse().map_err(|_| number())?,
+                        variance: variance.parse().map_err(|_| number())?,
+                        longest_streak: streak.parse().map_err(|_| number())?,
+                    };
+                    review
+                        .players
+                        .push((seat.parse().map_err(|_| number())?, accuracy));
+                }
+                ["flag", seat, reason] => {
+                    let reason = match reason {
+                        "accuracy" => FlagReason::Accuracy,
+                        "streak" => FlagReason::Streak,
+                        _ => return Err(malformed("a flag is for accuracy or a streak")),
+                    };
+                    let seat = seat.parse().map_err(|_| malformed("a seat is 0 or 1"))?;
+                    review.flags.push((seat, reason));
+                }
+                _ => return Err(malformed("expected a player or a flag")),
+            }
+        }
+        Ok(review)
+    }
+}
+
+/// The file the review of the record in `record` is kept in, next to it.
+pub fn review_path(record: &Path) -> PathBuf {
+    record.with_extension("review")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        QuartoGame,
+        player::ComputerPlayer,
+        random::SharedRng,
+        strategy::{NaiveStrategy, SmartStrategy},
+    };
+
+    use super::*;
+
+    /// A game between an engine at depth 2 and a player that decides at random.
+    fn engine_against_random(seed: u64) -> GameRecord {
+        let rng = SharedRng::seeded(seed);
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(SmartStrategy::new(2).with_rng(rng.clone())),
+            ComputerPlayer::new(NaiveStrategy::new().with_rng(rng)),
+        );
+        game.play_without_call();
+        game.history().clone()
+    }
+
+    #[test]
+    fn test_engine_matches_itself() {
+        let record = engine_against_random(7);
+        let engine = accuracy(&record, 0, 2);
+        let random = accuracy(&record, 1, 2);
+        assert_eq!(engine.matches, engine.decisions);
+        assert_eq!(engine.longest_streak, engine.decisions);
+        assert!(engine.match_rate() > random.match_rate());
+    }
+
+    #[test]
+    fn test_flags_engine_only() {
+        let config = FairPlayConfig {
+            min_decisions: 2,
+            min_z_score: 1.0,
+            max_streak: 100,
+            ..FairPlayConfig::default()
+        };
+        let flagged = (0..8)
+            .map(|seed| Review::new(&engine_against_random(seed), &[0, 1], &config))
+            .filter(|review| review.is_flagged())
+            .collect::<Vec<_>>();
+        assert!(!flagged.is_empty());
+        for review in flagged {
+            assert_eq!(review.flags, vec![(0, FlagReason::Accuracy)]);
+        }
+    }
+
+    #[test]
+    fn test_review_round_trip() {
+        let review = Review {
+            players: vec![(
+                1,
+                Accuracy {
+                    decisions: 14,
+                    matches: 13,
+                    expected: 6.25,
+                    variance: 2.1875,
+                    longest_streak: 9,
+                },
+            )],
+            flags: vec![(1, FlagReason::Accuracy), (1, FlagReason::Streak)],
+        };
+        assert_eq!(Review::from_text(&review.to_text()), Ok(review));
+        assert!(Review::from_text("quarto review 1\nflag 0 luck\n").is_err());
+        assert_eq!(
+            review_path(Path::new("games/42.txt")),
+            PathBuf::from("games/42.review")
+        );
+    }
+}
@@ -0,0 +1,235 @@
+// A C API over the board and the computer players, so GUIs in other languages can link the shared library.
+// Only built with the `ffi` feature, which also writes the header to the build directory, see build.rs.
+// Build the shared library with `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+// No panic unwinds into C: a function that panics returns its error value instead.
+
+use std::{
+    ffi::{CStr, c_char},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{
+    board::Board,
+    player::Player,
+    random::SharedRng,
+    registry::{PlayerOptions, Registry},
+};
+
+/// A game in progress: the board and the pieces placed on it.
+pub struct QuartoBoard {
+    board: Board,
+}
+
+/// A computer player of the registry.
+pub struct QuartoEngine {
+    player: Box<dyn Player>,
+}
+
+/// Returned by `quarto_engine_choose_move` for a piece that is not a number from 0 to 15.
+pub const QUARTO_INVALID_PIECE: i32 = -2;
+
+/// Run `body`, and return `failed` if it panics instead of unwinding into the caller.
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(failed)
+}
+
+/// Create an empty board, free it with `quarto_board_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn quarto_board_new() -> *mut QuartoBoard {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(QuartoBoard {
+            board: Board::new(),
+        }))
+    })
+}
+
+/// Free a board of `quarto_board_new`.
+///
+/// # Safety
+/// `board` is null or a board of `quarto_board_new` that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_board_free(board: *mut QuartoBoard) {
+    guard((), || {
+        if !board.is_null() {
+            drop(unsafe { Box::from_raw(board) });
+        }
+    })
+}
+
+/// Place `piece` on `index`: 0 if it was placed, -1 if that is not allowed or `board` is null.
+///
+/// # Safety
+/// `board` is null or a live board of `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_board_put_piece(
+    board: *mut QuartoBoard,
+    piece: u8,
+    index: u8,
+) -> i32 {
+    guard(-1, || {
+        match unsafe { board.as_mut() }.map(|board| board.board.put_piece(piece, index)) {
+            Some(Ok(())) => 0,
+            _ => -1,
+        }
+    })
+}
+
+/// The piece on `index`, or -1 if the cell is empty.
+///
+/// # Safety
+/// `board` is null or a live board of `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_board_piece_at(board: *const QuartoBoard, index: u8) -> i32 {
+    guard(-1, || {
+        unsafe { board.as_ref() }
+            .and_then(|board| board.board.piece_at(index))
+            .map_or(-1, i32::from)
+    })
+}
+
+/// Check if a line is won.
+///
+/// # Safety
+/// `board` is null or a live board of `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_board_has_winner(board: *const QuartoBoard) -> bool {
+    guard(false, || {
+        unsafe { board.as_ref() }.is_some_and(|board| board.board.has_winner())
+    })
+}
+
+/// Check if the game is won or the board is full.
+///
+/// # Safety
+/// `board` is null or a live board of `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_board_game_over(board: *const QuartoBoard) -> bool {
+    guard(false, || {
+        unsafe { board.as_ref() }.is_some_and(|board| board.board.game_over())
+    })
+}
+
+/// Create the computer player `name` of the registry, like `smart` or `mcts`, searching `depth` turns
+/// or `iterations` playouts ahead. A `seed` other than 0 makes its random decisions repeat.
+/// Returns null for an unknown name, free the engine with `quarto_engine_free`.
+///
+/// # Safety
+/// `name` is null or a string that ends with a 0 byte.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_engine_new(
+    name: *const c_char,
+    depth: u8,
+    iterations: u32,
+    seed: u64,
+) -> *mut QuartoEngine {
+    guard(ptr::null_mut(), || {
+        if name.is_null() {
+            return ptr::null_mut();
+        }
+        let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+            return ptr::null_mut();
+        };
+        let options = PlayerOptions {
+            depth,
+            iterations,
+            rng: match seed {
+                0 => SharedRng::new(),
+                seed => SharedRng::seeded(seed),
+            },
+            ..PlayerOptions::default()
+        };
+        match Registry::default().create_computer(name, &options) {
+            Ok(player) => Box::into_raw(Box::new(QuartoEngine { player })),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Free an engine of `quarto_engine_new`.
+///
+/// # Safety
+/// `engine` is null or an engine of `quarto_engine_new` that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_engine_free(engine: *mut QuartoEngine) {
+    guard((), || {
+        if !engine.is_null() {
+            drop(unsafe { Box::from_raw(engine) });
+        }
+    })
+}
+
+/// The piece the engine hands over to its opponent, or -1 if there is none.
+///
+/// # Safety
+/// `engine` and `board` are null or live values of `quarto_engine_new` and `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_engine_choose_piece(
+    engine: *const QuartoEngine,
+    board: *const QuartoBoard,
+) -> i32 {
+    guard(-1, || match unsafe { (engine.as_ref(), board.as_ref()) } {
+        (Some(engine), Some(board)) => engine.player.get_piece(&board.board).map_or(-1, i32::from),
+        _ => -1,
+    })
+}
+
+/// The cell the engine places `piece` on, -1 if there is none, or `QUARTO_INVALID_PIECE` for a piece over 15.
+///
+/// # Safety
+/// `engine` and `board` are null or live values of `quarto_engine_new` and `quarto_board_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quarto_engine_choose_move(
+    engine: *const QuartoEngine,
+    board: *const QuartoBoard,
+    piece: u8,
+) -> i32 {
+    if piece > 15 {
+        return QUARTO_INVALID_PIECE;
+    }
+    guard(-1, || match unsafe { (engine.as_ref(), board.as_ref()) } {
+        (Some(engine), Some(board)) => engine
+            .player
+            .get_move(&board.board, piece)
+            .map_or(-1, i32::from),
+        _ => -1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_wins_through_the_c_api() {
+        unsafe {
+            let board = quarto_board_new();
+            for (piece, index) in [(8, 0), (9, 1), (10, 2), (0, 15)] {
+                assert_eq!(quarto_board_put_piece(board, piece, index), 0);
+            }
+            assert_eq!(quarto_board_put_piece(board, 8, 3), -1);
+            assert_eq!(quarto_board_piece_at(board, 1), 9);
+            assert_eq!(quarto_board_piece_at(board, 3), -1);
+            let engine = quarto_engine_new(c"smart".as_ptr(), 1, 100, 2806);
+            assert!(!engine.is_null());
+            let index = quarto_engine_choose_move(engine, board, 11);
+            assert_eq!(index, 3);
+            assert_eq!(quarto_board_put_piece(board, 11, index as u8), 0);
+            assert!(quarto_board_has_winner(board));
+            assert!(quarto_engine_new(c"human".as_ptr(), 1, 100, 0).is_null());
+            assert_eq!(quarto_engine_choose_piece(engine, ptr::null()), -1);
+            assert_eq!(
+                quarto_engine_choose_move(engine, board, 16),
+                QUARTO_INVALID_PIECE
+            );
+            quarto_engine_free(engine);
+            quarto_board_free(board);
+        }
+    }
+
+    #[test]
+    fn test_guard_stops_a_panic() {
+        assert_eq!(guard(-1, || 0), 0);
+        assert_eq!(guard(-1, || panic!("a bug in the engine")), -1);
+    }
+}
@@ -0,0 +1,90 @@
+// The fog training variant, where the attributes of the pieces on the board are hidden.
+// Every turn the player can inspect a few lines to see their pieces, so the rest has to be remembered.
+
+use crate::line::Line;
+
+/// Which cells of the board a player sees, and how many inspections are left this turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fog {
+    per_turn: u8,
+    left: u8,
+    /// A bit for every visible index, bit `i` for index `i`.
+    visible: u16,
+}
+
+impl Fog {
+    /// Create a fog that allows `per_turn` inspections every turn.
+    pub fn new(per_turn: u8) -> Self {
+        Fog {
+            per_turn,
+            left: per_turn,
+            visible: 0,
+        }
+    }
+
+    /// Hide the board again and restore the inspections, at the start of a turn.
+    pub fn new_turn(&mut self) {
+        self.left = self.per_turn;
+        self.visible = 0;
+    }
+
+    /// The number of inspections left this turn.
+    pub fn left(&self) -> u8 {
+        self.left
+    }
+
+    /// Reveal the cells of `line` for the rest of the turn.
+    pub fn inspect(&mut self, line: Line) -> Result<(), String> {
+        if self.left == 0 {
+            return Err("No inspections left this turn.".to_string());
+        }
+        self.left -= 1;
+        self.visible |= line.index_mask();
+        Ok(())
+    }
+
+    /// The hidden indices, bit `i` for index `i`.
+    pub fn hidden(&self) -> u16 {
+        !self.visible
+    }
+}
+
+/// Parse an inspection: `r0` to `r3` for a row, `c0` to `c3` for a column, `dd` or `du` for a diagonal.
+pub fn parse_inspection(text: &str) -> Option<Line> {
+    let number = |digit: &str| digit.parse::<u8>().ok().filter(|&n| n < 4);
+    match text.split_at_checked(1)? {
+        ("r", row) => number(row).map(Line::Row),
+        ("c", column) => number(column).map(Line::Column),
+        ("d", "d") => Some(Line::DiagonalDown),
+        ("d", "u") => Some(Line::DiagonalUp),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspections_per_turn() {
+        let mut fog = Fog::new(2);
+        assert_eq!(fog.hidden(), u16::MAX);
+        fog.inspect(Line::Row(0)).unwrap();
+        fog.inspect(Line::Column(0)).unwrap();
+        assert_eq!(fog.hidden(), !0b0001_0001_0001_1111);
+        assert!(fog.inspect(Line::DiagonalUp).is_err());
+        fog.new_turn();
+        assert_eq!(fog.left(), 2);
+        assert_eq!(fog.hidden(), u16::MAX);
+    }
+
+    #[test]
+    fn test_parse_inspection() {
+        assert_eq!(parse_inspection("r2"), Some(Line::Row(2)));
+        assert_eq!(parse_inspection("c0"), Some(Line::Column(0)));
+        assert_eq!(parse_inspection("du"), Some(Line::DiagonalUp));
+        assert_eq!(parse_inspection("r4"), None);
+        assert_eq!(parse_inspection("7"), None);
+        assert_eq!(parse_inspection(""), None);
+    }
+}
@@ -1,16 +1,71 @@
-use crate::{board::Board, player::Player};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::{Board, Ruleset},
+    cancel::CancelToken,
+    clock::{Clock, TimeControl},
+    error::QuartoError,
+    events::{EventBus, GameEvent},
+    handicap::{Handicap, RandomPieces},
+    observer::GameObserver,
+    persist::{self, DataKind, PersistError},
+    player::Player,
+    record::{GameRecord, Turn},
+    rules::WinRule,
+};
+
+/// The most players a game seats, the two teams of two of `QuartoGame::teams`.
+pub const MAX_SEATS: usize = 4;
 
 pub struct QuartoGame {
-    players: [Box<dyn Player>; 2],
+    /// The players in the order of play, two or four of them.
+    /// In a team game the first team sits on the even seats, so a piece always goes to the next opponent.
+    players: Vec<Box<dyn Player>>,
+    /// The names the players were made from, like the names of the registry, so `load` can make them again.
+    names: Vec<String>,
     current: usize,
     board: Board,
+    /// Stops the game between two decisions when cancelled, for example when the user quits.
+    cancel: CancelToken,
+    /// Every turn played so far.
+    record: GameRecord,
+    /// Turns that were taken back with `undo`, the last one is redone first.
+    undone: Vec<Turn>,
+    /// Receives everything that happens in the game, for whoever subscribed to it.
+    events: EventBus,
+    /// Hooks that are called right after something happens in the game.
+    observers: Vec<Box<dyn GameObserver>>,
+    /// Where the game is saved after every placement, removed again when the game ends.
+    autosave: Option<PathBuf>,
+    /// The first error while autosaving, the game goes on without it.
+    autosave_error: Option<io::Error>,
+    /// The clocks of the players, in a game with a time control.
+    clock: Option<Clock>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameResult {
     Error,
     Draw,
     Win(usize),
+    /// The flag of this player fell, they went over the time of the `TimeControl`.
+    Timeout(usize),
+}
+
+impl GameResult {
+    /// The team that won: the players on the even seats are team 0, the players on the odd seats team 1.
+    /// With two players the team is the seat of the winner.
+    pub fn winning_team(self) -> Option<usize> {
+        match self {
+            GameResult::Win(player) => Some(player % 2),
+            GameResult::Timeout(player) => Some((player + 1) % 2),
+            GameResult::Draw | GameResult::Error => None,
+        }
+    }
 }
 
 impl QuartoGame {
@@ -21,51 +76,506 @@ impl QuartoGame {
         P1: Player + 'static,
         P2: Player + 'static,
     {
+        Self::seated(vec![Box::new(player1), Box::new(player2)])
+    }
+
+    /// Build a game of two against two, with the players in the order of play.
+    /// The first and third player form a team against the second and fourth, teammates share the win.
+    /// Every player hands their piece to the next player, who is always an opponent.
+    pub fn teams(players: [Box<dyn Player>; 4]) -> Self {
+        Self::seated(players.into())
+    }
+
+    fn seated(players: Vec<Box<dyn Player>>) -> Self {
         Self {
-            players: [Box::new(player1), Box::new(player2)],
+            names: vec![String::new(); players.len()],
+            players,
             current: 0,
             board: Board::new(),
+            cancel: CancelToken::new(),
+            record: GameRecord::new(),
+            undone: Vec::new(),
+            events: EventBus::new(),
+            observers: Vec::new(),
+            autosave: None,
+            autosave_error: None,
+            clock: None,
+        }
+    }
+
+    /// Stop the game when `cancel` is cancelled.
+    /// Give the same token to the strategies of the players, so a running search stops as well.
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Start the game from an opening: placements played in turn, with the first player handing over the first piece.
+    /// Fails if the opening contains an illegal placement, or placements after the game is over.
+    pub fn with_opening(self, moves: &[(u8, u8)]) -> Result<Self, QuartoError> {
+        self.opened(moves, 0)
+    }
+
+    /// Play `moves` in turn, with the player on seat `first` handing over the first piece.
+    fn opened(mut self, moves: &[(u8, u8)], first: usize) -> Result<Self, QuartoError> {
+        self.board = self.board.apply_moves(moves)?;
+        let seats = self.players.len();
+        for (i, &(piece, index)) in moves.iter().enumerate() {
+            self.record.push(Turn {
+                giver: (first + i) % seats,
+                piece,
+                placer: (first + i + 1) % seats,
+                index,
+                quarto_called: false,
+            });
+        }
+        // The player that placed the last piece hands over the next one.
+        self.current = (first + moves.len()) % seats;
+        Ok(self)
+    }
+
+    /// Give the game a `handicap`, to even out a game between a weaker and a stronger player.
+    /// Fails if the placed pieces of the handicap are not a legal opening, a seat that is not in the game is not restricted.
+    pub fn with_handicap(mut self, handicap: Handicap) -> Result<Self, QuartoError> {
+        let seats = self.players.len();
+        match handicap {
+            Handicap::Placed { weaker, moves } => {
+                // Start the opening so the last piece is placed by the weaker player.
+                let first = (weaker + seats - moves.len() % seats) % seats;
+                self.opened(&moves, first)
+            }
+            Handicap::RandomPieces {
+                stronger,
+                every,
+                rng,
+            } => {
+                if stronger < seats {
+                    let player = self.players.remove(stronger);
+                    let restricted = RandomPieces::new(player, every, rng);
+                    self.players.insert(stronger, Box::new(restricted));
+                }
+                Ok(self)
+            }
+        }
+    }
+
+    /// Play by `ruleset`, for example the advanced rules where 2×2 squares win as well.
+    /// The players see the ruleset on the board they are given, so strategies follow it.
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.board = self.board.with_ruleset(ruleset);
+        self.record = self.record.with_ruleset(ruleset);
+        self
+    }
+
+    /// Play by a custom `rule`, which decides the winning groups of cells.
+    /// A record of the game names the rule, but only games with a built-in ruleset can be loaded again.
+    pub fn with_win_rule(self, rule: &'static dyn WinRule) -> Self {
+        self.with_ruleset(Ruleset::Custom(rule))
+    }
+
+    /// Name the players, so a saved game knows which players to make when it is loaded.
+    pub fn with_names(self, first: &str, second: &str) -> Self {
+        self.with_seat_names(&[first, second])
+    }
+
+    /// Name the player on every seat, in the order of play, like `with_names` for a team game.
+    pub fn with_seat_names(mut self, names: &[&str]) -> Self {
+        for (name, given) in self.names.iter_mut().zip(names) {
+            *name = given.to_string();
+        }
+        self
+    }
+
+    /// Save the game to `path` after every placement, and remove the file when the game ends.
+    /// A game that crashes or is killed leaves its last position behind, to resume it with `load`.
+    pub fn with_autosave(mut self, path: PathBuf) -> Self {
+        self.autosave = Some(path);
+        self
+    }
+
+    /// Play with the time limits of `control`, a player that goes over them loses on time.
+    pub fn with_clock(mut self, control: TimeControl) -> Self {
+        self.clock = Some(Clock::new(control).with_players(self.players.len()));
+        self
+    }
+
+    /// Get the clocks of the players, in a game with a time control.
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Get the first error while autosaving, if any.
+    pub fn autosave_error(&self) -> Option<&io::Error> {
+        self.autosave_error.as_ref()
+    }
+
+    /// Publish the events of the game on `events`.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Call the hooks of `observer` for everything that happens in the game.
+    pub fn with_observer<O: GameObserver + 'static>(mut self, observer: O) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Get the bus the events of the game are published on, to subscribe to them.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Get the record of the turns played so far, with the result once the game is over.
+    pub fn history(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// Check if the board is full or has a Quarto, a game that was quit or cancelled before that can be resumed.
+    pub fn is_over(&self) -> bool {
+        self.board.game_over()
+    }
+
+    /// Write the game in the stored format: the names of the players, the player on turn and the turns so far.
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        for (seat, name) in self.names.iter().enumerate() {
+            body += &format!("player {} {}\n", seat, name);
+        }
+        body += &format!("current {}\n", self.current);
+        self.record.write_lines(&mut body);
+        persist::write(DataKind::SavedGame, &body)
+    }
+
+    /// Read a game written by `to_text`, with the players that `make` makes from their names.
+    /// The turns are played again, and the result of a game that was quit before it was over is dropped.
+    pub fn from_text(
+        data: &str,
+        mut make: impl FnMut(&str) -> Result<Box<dyn Player>, String>,
+    ) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::SavedGame, data)?;
+        let mut names: [Option<(usize, String)>; 4] = Default::default();
+        let mut current = (1, 0);
+        let mut saved = GameRecord::new();
+        let mut board = Board::new();
+        // The header is the first line of the file.
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: &str| PersistError::Malformed {
+                line,
+                message: message.to_string(),
+            };
+            let words: Vec<&str> = text.split_whitespace().collect();
+            match words.as_slice() {
+                ["player", seat @ ("0" | "1" | "2" | "3"), name @ ..] => {
+                    names[seat.parse::<usize>().unwrap()] = Some((line, name.join(" ")));
+                }
+                ["player", ..] => return Err(malformed("a player is 0 to 3 with a name")),
+                ["current", seat] => {
+                    let seat = seat
+                        .parse()
+                        .map_err(|_| malformed("the current player is a seat number"))?;
+                    current = (line, seat);
+                }
+                words => {
+                    // The number of players is only known at the end, a team game has four.
+                    saved.read_line(words, MAX_SEATS).map_err(malformed)?;
+                    if let (["turn", ..], Some(turn)) = (words, saved.turns().last()) {
+                        board
+                            .put_piece(turn.piece, turn.index)
+                            .map_err(|e| malformed(&e.to_string()))?;
+                    }
+                }
+            }
+        }
+        let seats: Vec<(usize, String)> = match names {
+            [Some(first), Some(second), None, None] => vec![first, second],
+            [Some(first), Some(second), Some(third), Some(fourth)] => {
+                vec![first, second, third, fourth]
+            }
+            _ => {
+                return Err(PersistError::Malformed {
+                    line: 1,
+                    message: "two or four players need a name".to_string(),
+                });
+            }
+        };
+        let (line, seat) = current;
+        if seat >= seats.len() {
+            return Err(PersistError::Malformed {
+                line,
+                message: format!("the current player is a seat from 0 to {}", seats.len() - 1),
+            });
+        }
+        let players = seats
+            .iter()
+            .map(|(line, name)| {
+                make(name).map_err(|message| PersistError::Malformed {
+                    line: *line,
+                    message,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let names: Vec<&str> = seats.iter().map(|(_, name)| name.as_str()).collect();
+        let mut game = QuartoGame::seated(players)
+            .with_seat_names(&names)
+            .with_ruleset(saved.ruleset());
+        let board = board.with_ruleset(saved.ruleset());
+        for turn in saved.turns() {
+            game.record.push(*turn);
+        }
+        if let Some(result) = saved.result().filter(|_| board.game_over()) {
+            game.record.set_result(result);
+        }
+        game.board = board;
+        game.current = seat;
+        Ok(game)
+    }
+
+    /// Save the game to `path`, to resume it later with `load`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+
+    /// Load a game saved with `save`, with the players that `make` makes from their names.
+    pub fn load(
+        path: &Path,
+        make: impl FnMut(&str) -> Result<Box<dyn Player>, String>,
+    ) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        QuartoGame::from_text(&data, make)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Take back the last turn: the piece goes back into the pool and the player that gave it is on turn again.
+    /// Returns the turn that was taken back, or `None` if no turn was played.
+    pub fn undo(&mut self) -> Option<Turn> {
+        let turn = self.record.pop()?;
+        self.board.remove_piece(turn.index);
+        self.current = turn.giver;
+        self.undone.push(turn);
+        Some(turn)
+    }
+
+    /// Play the last turn that was taken back with `undo` again.
+    /// Returns the turn that was replayed, or `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<Turn> {
+        let turn = self.undone.pop()?;
+        if self.board.put_piece(turn.piece, turn.index).is_err() {
+            self.undone.push(turn);
+            return None;
+        }
+        self.current = turn.placer;
+        self.record.push(turn);
+        if self.board.has_winner() {
+            self.record.set_result(GameResult::Win(turn.placer));
+        } else if self.board.board_full() {
+            self.record.set_result(GameResult::Draw);
         }
+        Some(turn)
+    }
+
+    /// Publish `event` on the bus and call the matching hook of every observer.
+    fn emit(&self, event: GameEvent) {
+        self.events.publish(event);
+        for observer in self.observers.iter() {
+            match event {
+                GameEvent::GameStarted => {}
+                GameEvent::PieceGiven { player, piece } => {
+                    observer.on_piece_given(player, piece, &self.board)
+                }
+                GameEvent::PiecePlaced {
+                    player,
+                    piece,
+                    index,
+                } => observer.on_piece_placed(player, piece, index, &self.board),
+                GameEvent::QuartoCalled { player, correct } => {
+                    observer.on_quarto_called(player, correct, &self.board)
+                }
+                GameEvent::GameEnded { result } => observer.on_game_end(result, &self.record),
+            }
+        }
+    }
+
+    /// Write the autosave, keeping the first error.
+    fn autosave(&mut self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        if let Err(e) = self.save(path) {
+            self.autosave_error.get_or_insert(e);
+        }
+    }
+
+    /// Store the result in the record and return it.
+    /// The game ended as it should, so the autosave is not needed anymore.
+    fn finish(&mut self, result: GameResult) -> GameResult {
+        if let Some(path) = &self.autosave
+            && let Err(e) = fs::remove_file(path)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            self.autosave_error.get_or_insert(e);
+        }
+        self.record.set_result(result);
+        self.emit(GameEvent::GameEnded { result });
+        result
+    }
+
+    /// The seat after `player`, who is always an opponent of `player`.
+    fn next(&self, player: usize) -> usize {
+        (player + 1) % self.players.len()
     }
 
     /// Advance the game to the next player.
     fn next_player(&mut self) {
-        self.current = 1 - self.current;
+        self.current = self.next(self.current);
     }
 
     /// Play the `QuartoGame` once, without asking players to call Quarto.
-    /// Return the winner, `Draw` if it is a draw, and `Error` if the game ended pre-emptively due to an error.
+    /// Return the winner, `Draw` if it is a draw, and `Error` if the game ended pre-emptively due to an error or cancellation.
     pub fn play_without_call(&mut self) -> GameResult {
+        self.run(false)
+    }
+
+    /// Play the `QuartoGame` once, asking the player that placed a piece if they call Quarto.
+    /// A correct call wins the game, a wrong call is ignored.
+    /// When the placer misses a Quarto the opponent can still claim it, a Quarto nobody claims ends the game in a draw.
+    pub fn play(&mut self) -> GameResult {
+        self.run(true)
+    }
+
+    /// End the game because `player` did not decide: a win for the next opponent if `player` forfeited, an error otherwise.
+    /// A player that forfeited because their time ran out, like a hung player in a `Watchdog`, loses on time.
+    fn abort(&mut self, player: usize) -> GameResult {
+        if self.players[player].forfeited() {
+            if !self.stop_clock() {
+                return self.finish(GameResult::Timeout(player));
+            }
+            return self.finish(GameResult::Win(self.next(player)));
+        }
+        self.finish(GameResult::Error)
+    }
+
+    /// Tell `player` their time and start their clock, in a game with a clock, and show the clocks to the observers.
+    fn start_clock(&mut self, player: usize) {
+        if let Some(clock) = &mut self.clock {
+            if let Some(time) = clock.allowed(player) {
+                self.players[player].time_left(time);
+            }
+            for observer in &self.observers {
+                observer.on_clock(clock);
+            }
+            clock.start(player);
+        }
+    }
+
+    /// Stop the running clock, returns false if the flag of the player fell.
+    fn stop_clock(&mut self) -> bool {
+        self.clock.as_mut().is_none_or(Clock::stop)
+    }
+
+    /// The game loop, `calls` decides if the players are asked to call Quarto.
+    fn run(&mut self, calls: bool) -> GameResult {
+        self.emit(GameEvent::GameStarted);
         while !self.board.game_over() {
+            if self.cancel.is_cancelled() {
+                return self.finish(GameResult::Error);
+            }
+            let giver = self.current;
+            self.start_clock(giver);
             let piece: u8 = match self.players[self.current].get_piece(&self.board) {
                 Some(p) => p,
-                None => return GameResult::Error,
+                None => return self.abort(self.current),
             };
-            self.next_player();
-            let player_move = match self.players[self.current].get_move(&self.board, piece) {
+            if !self.stop_clock() {
+                return self.finish(GameResult::Timeout(giver));
+            }
+            self.emit(GameEvent::PieceGiven {
+                player: giver,
+                piece,
+            });
+            if self.cancel.is_cancelled() {
+                return self.finish(GameResult::Error);
+            }
+            // The giver stays on turn until the piece is placed, so a game that stops here is resumed with this turn.
+            let placer = self.next(giver);
+            self.start_clock(placer);
+            let player_move = match self.players[placer].get_move(&self.board, piece) {
                 Some(m) => m,
-                None => return GameResult::Error,
+                None => return self.abort(placer),
             };
-            self.board.put_piece(piece, player_move);
+            if !self.stop_clock() {
+                return self.finish(GameResult::Timeout(placer));
+            }
+            if self.board.put_piece(piece, player_move).is_err() {
+                return self.finish(GameResult::Error);
+            }
+            self.next_player();
+            self.emit(GameEvent::PiecePlaced {
+                player: self.current,
+                piece,
+                index: player_move,
+            });
+            let quarto_called = calls && self.players[self.current].quarto(&self.board);
+            // A new turn replaces the turns that were taken back.
+            self.undone.clear();
+            self.record.push(Turn {
+                giver,
+                piece,
+                placer: self.current,
+                index: player_move,
+                quarto_called,
+            });
+            self.autosave();
+            if !calls {
+                continue;
+            }
+            let winner = self.board.has_winner();
+            if quarto_called {
+                self.emit(GameEvent::QuartoCalled {
+                    player: self.current,
+                    correct: winner,
+                });
+                if winner {
+                    return self.finish(GameResult::Win(self.current));
+                }
+            } else if winner {
+                let opponent = self.next(self.current);
+                if self.players[opponent].quarto(&self.board) {
+                    self.emit(GameEvent::QuartoCalled {
+                        player: opponent,
+                        correct: true,
+                    });
+                    return self.finish(GameResult::Win(opponent));
+                }
+                return self.finish(GameResult::Draw);
+            }
         }
         if self.board.has_winner() {
-            return GameResult::Win(self.current);
+            return self.finish(GameResult::Win(self.current));
         }
-        GameResult::Draw
+        self.finish(GameResult::Draw)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::player::{ComputerPlayer};
-    use crate::strategy::{DumbStrategy, DeterministicStrategy};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::player::ComputerPlayer;
+    use crate::strategy::{DeterministicStrategy, DumbStrategy, SmartStrategy};
 
     use super::*;
 
     #[test]
     fn test_new_game_empty_board() {
-        let player1 = ComputerPlayer::new(DumbStrategy);
-        let player2 = ComputerPlayer::new(DumbStrategy);
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
         let game = QuartoGame::new(player1, player2);
         assert!(game.board.is_empty());
         assert_eq!(game.current, 0)
@@ -73,8 +583,8 @@ mod tests {
 
     #[test]
     fn test_play_game_without_call_with_dumb_bots() {
-        let player1 = ComputerPlayer::new(DumbStrategy);
-        let player2 = ComputerPlayer::new(DumbStrategy);
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
         let mut game = QuartoGame::new(player1, player2);
         let res = game.play_without_call();
         assert_ne!(res, GameResult::Error);
@@ -88,4 +598,412 @@ mod tests {
         let res = game.play_without_call();
         assert_ne!(res, GameResult::Error);
     }
+
+    #[test]
+    fn test_cancelled_game_stops() {
+        let cancel = CancelToken::new();
+        let player1 = ComputerPlayer::new(SmartStrategy::new(6).with_cancel(cancel.clone()));
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
+        let mut game = QuartoGame::new(player1, player2).with_cancel(cancel.clone());
+        cancel.cancel();
+        assert_eq!(game.play_without_call(), GameResult::Error);
+        assert!(game.board.is_empty());
+    }
+
+    #[test]
+    fn test_history_matches_board() {
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
+        let mut game = QuartoGame::new(player1, player2);
+        let res = game.play_without_call();
+        let history = game.history();
+        assert_eq!(history.result(), Some(res));
+        assert_eq!(history.final_board(), Some(game.board));
+        for (i, turn) in history.turns().iter().enumerate() {
+            // The first player hands over the first piece, after which the players alternate.
+            assert_eq!(turn.giver, i % 2);
+            assert_eq!(turn.placer, 1 - i % 2);
+        }
+        if let GameResult::Win(winner) = res {
+            assert_eq!(history.turns().last().map(|t| t.placer), Some(winner));
+        }
+    }
+
+    #[test]
+    fn test_events_follow_history() {
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
+        let mut game = QuartoGame::new(player1, player2);
+        let events = game.events().subscribe();
+        let res = game.play_without_call();
+        let events: Vec<GameEvent> = events.try_iter().collect();
+        assert_eq!(events.first(), Some(&GameEvent::GameStarted));
+        assert_eq!(events.last(), Some(&GameEvent::GameEnded { result: res }));
+        let placed: Vec<(usize, u8, u8)> = events
+            .iter()
+            .filter_map(|e| match *e {
+                GameEvent::PiecePlaced {
+                    player,
+                    piece,
+                    index,
+                } => Some((player, piece, index)),
+                _ => None,
+            })
+            .collect();
+        let turns: Vec<(usize, u8, u8)> = game
+            .history()
+            .turns()
+            .iter()
+            .map(|t| (t.placer, t.piece, t.index))
+            .collect();
+        assert_eq!(placed, turns);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
+        let mut game = QuartoGame::new(player1, player2);
+        let res = game.play_without_call();
+        let board = game.board;
+        let turns = game.history().len();
+        let last = game.undo().unwrap();
+        assert_eq!(game.history().len(), turns - 1);
+        assert_eq!(game.history().result(), None);
+        assert!(game.board.index_empty(last.index));
+        assert_eq!(game.current, last.giver);
+        assert_eq!(game.redo(), Some(last));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.board, board);
+        assert_eq!(game.history().result(), Some(res));
+    }
+
+    #[test]
+    fn test_undo_everything() {
+        let player1 = ComputerPlayer::new(DeterministicStrategy);
+        let player2 = ComputerPlayer::new(DeterministicStrategy);
+        let mut game = QuartoGame::new(player1, player2);
+        game.play_without_call();
+        while game.undo().is_some() {}
+        assert!(game.board.is_empty());
+        assert_eq!(game.current, 0);
+        // Playing again after taking everything back gives the same game.
+        game.play_without_call();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.history().final_board(), Some(game.board));
+    }
+
+    #[test]
+    fn test_with_opening() {
+        let player1 = ComputerPlayer::new(DeterministicStrategy);
+        let player2 = ComputerPlayer::new(DeterministicStrategy);
+        let mut game = QuartoGame::new(player1, player2)
+            .with_opening(&[(5, 0), (9, 10), (2, 3)])
+            .unwrap();
+        assert_eq!(game.current, 1);
+        assert_eq!(game.history().len(), 3);
+        game.play_without_call();
+        let first = game.history().turns()[3];
+        assert_eq!((first.giver, first.placer), (1, 0));
+        assert_eq!(game.history().final_board(), Some(game.board));
+    }
+
+    #[test]
+    fn test_teams() {
+        let player = || -> Box<dyn Player> { Box::new(ComputerPlayer::new(DeterministicStrategy)) };
+        let mut game = QuartoGame::teams([player(), player(), player(), player()])
+            .with_seat_names(&["a", "b", "c", "d"]);
+        let result = game.play();
+        for (i, turn) in game.history().turns().iter().enumerate() {
+            assert_eq!((turn.giver, turn.placer), (i % 4, (i + 1) % 4));
+        }
+        let last = *game.history().turns().last().unwrap();
+        match result {
+            GameResult::Win(winner) => {
+                assert_eq!(winner, last.placer);
+                assert_eq!(result.winning_team(), Some(last.placer % 2));
+            }
+            _ => assert_eq!(result.winning_team(), None),
+        }
+
+        let mut game = QuartoGame::teams([player(), player(), player(), player()])
+            .with_seat_names(&["a", "b", "c", "d"])
+            .with_opening(&[(0, 0), (1, 1), (2, 2)])
+            .unwrap();
+        assert_eq!(game.current, 3);
+        game.record.set_result(GameResult::Error);
+        let text = game.to_text();
+        assert!(text.contains("player 3 d\ncurrent 3\n"));
+        let make = |_: &str| -> Result<Box<dyn Player>, String> { Ok(player()) };
+        let resumed = QuartoGame::from_text(&text, make).unwrap();
+        assert_eq!(resumed.players.len(), 4);
+        assert_eq!(resumed.names, ["a", "b", "c", "d"]);
+        assert_eq!(resumed.current, 3);
+        let three = text.replace("player 3 d\n", "");
+        assert!(QuartoGame::from_text(&three, make).is_err());
+    }
+
+    #[test]
+    fn test_advanced_ruleset() {
+        // Four hole pieces in the top left square, but on no row, column or diagonal.
+        let opening = [(8, 0), (12, 1), (10, 4), (9, 5), (0, 15)];
+        let new_game = || {
+            QuartoGame::new(
+                ComputerPlayer::new(DeterministicStrategy),
+                ComputerPlayer::new(DeterministicStrategy),
+            )
+            .with_names("deterministic", "deterministic")
+        };
+        assert!(new_game().with_opening(&opening).is_ok());
+        assert!(matches!(
+            new_game()
+                .with_ruleset(Ruleset::Advanced)
+                .with_opening(&opening),
+            Err(QuartoError::GameOver { ply: 4 })
+        ));
+        let game = new_game()
+            .with_ruleset(Ruleset::Advanced)
+            .with_opening(&opening[..3])
+            .unwrap();
+        let make = |_: &str| -> Result<Box<dyn Player>, String> {
+            Ok(Box::new(ComputerPlayer::new(DeterministicStrategy)))
+        };
+        let resumed = QuartoGame::from_text(&game.to_text(), make).unwrap();
+        assert_eq!(resumed.board.ruleset(), Ruleset::Advanced);
+        assert_eq!(resumed.history().ruleset(), Ruleset::Advanced);
+    }
+
+    #[test]
+    fn test_save_and_resume() {
+        let make = |name: &str| -> Result<Box<dyn Player>, String> {
+            match name {
+                "deterministic" => Ok(Box::new(ComputerPlayer::new(DeterministicStrategy))),
+                _ => Err(format!("Unknown player '{}'", name)),
+            }
+        };
+        let mut finished = QuartoGame::new(
+            make("deterministic").unwrap(),
+            make("deterministic").unwrap(),
+        );
+        let result = finished.play();
+        let mut game = QuartoGame::new(
+            make("deterministic").unwrap(),
+            make("deterministic").unwrap(),
+        )
+        .with_names("deterministic", "deterministic")
+        .with_opening(
+            &finished
+                .history()
+                .turns()
+                .iter()
+                .take(3)
+                .map(|t| (t.piece, t.index))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        game.record.set_result(GameResult::Error);
+        let text = game.to_text();
+        assert!(text.starts_with(
+            "quarto saved-game 1\nplayer 0 deterministic\nplayer 1 deterministic\ncurrent 1\n"
+        ));
+        let mut resumed = QuartoGame::from_text(&text, make).unwrap();
+        assert!(!resumed.is_over());
+        assert_eq!(resumed.history().result(), None);
+        assert_eq!(resumed.play(), result);
+        assert_eq!(resumed.history().turns(), finished.history().turns());
+        let unknown = text.replace("player 1 deterministic", "player 1 nobody");
+        assert_eq!(
+            QuartoGame::from_text(&unknown, make).err(),
+            Some(PersistError::Malformed {
+                line: 3,
+                message: "Unknown player 'nobody'".to_string()
+            })
+        );
+        assert!(QuartoGame::from_text(&text.replace("current 1", "current 2"), make).is_err());
+    }
+
+    #[test]
+    fn test_autosave_after_every_placement() {
+        let path = std::env::temp_dir().join(format!("quarto-autosave-{}", std::process::id()));
+        let saved: Arc<Mutex<Vec<usize>>> = Arc::default();
+        struct Watch(PathBuf, Arc<Mutex<Vec<usize>>>);
+        impl GameObserver for Watch {
+            fn on_piece_placed(&self, _: usize, _: u8, _: u8, _: &Board) {
+                let data = fs::read_to_string(&self.0).unwrap_or_default();
+                self.1.lock().unwrap().push(data.matches("turn").count());
+            }
+        }
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(DeterministicStrategy),
+            ComputerPlayer::new(DeterministicStrategy),
+        )
+        .with_observer(Watch(path.clone(), saved.clone()))
+        .with_autosave(path.clone());
+        game.play();
+        // The observer hears of a placement before it is saved, so the file holds the turns before it.
+        let turns = game.history().len();
+        assert_eq!(*saved.lock().unwrap(), (0..turns).collect::<Vec<_>>());
+        assert!(!path.exists());
+        assert!(game.autosave_error().is_none());
+    }
+
+    #[test]
+    fn test_flag_falls() {
+        use crate::strategy::Strategy;
+
+        struct Slow(Arc<Mutex<Vec<Duration>>>);
+        impl Player for Slow {
+            fn get_piece(&self, board: &Board) -> Option<u8> {
+                DeterministicStrategy.get_piece(board)
+            }
+            fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+                std::thread::sleep(Duration::from_millis(30));
+                DeterministicStrategy.get_move(board, piece)
+            }
+            fn quarto(&self, _: &Board) -> bool {
+                false
+            }
+            fn time_left(&self, time: Duration) {
+                self.0.lock().unwrap().push(time);
+            }
+        }
+        let told: Arc<Mutex<Vec<Duration>>> = Arc::default();
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(DeterministicStrategy),
+            Slow(told.clone()),
+        )
+        .with_clock(TimeControl::new().with_per_game(Duration::from_millis(50)));
+        assert_eq!(game.play(), GameResult::Timeout(1));
+        // The second player places the pieces of the odd turns, and loses on time during the second placement.
+        assert_eq!(game.history().len(), 2);
+        let told = told.lock().unwrap();
+        assert_eq!(told[0], Duration::from_millis(50));
+        assert!(told.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(game.clock().unwrap().flagged(), Some(1));
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(DeterministicStrategy),
+            ComputerPlayer::new(DeterministicStrategy),
+        )
+        .with_clock(TimeControl::new().with_per_move(Duration::from_secs(60)));
+        assert!(matches!(game.play(), GameResult::Win(_) | GameResult::Draw));
+    }
+
+    #[test]
+    fn test_with_illegal_opening() {
+        let player1 = ComputerPlayer::new(DumbStrategy::new());
+        let player2 = ComputerPlayer::new(DumbStrategy::new());
+        assert!(
+            QuartoGame::new(player1, player2)
+                .with_opening(&[(5, 0), (5, 1)])
+                .is_err()
+        );
+    }
+
+    /// An observer that writes down every hook call.
+    #[derive(Default)]
+    struct LogObserver {
+        log: Mutex<Vec<String>>,
+    }
+
+    impl GameObserver for LogObserver {
+        fn on_piece_given(&self, player: usize, piece: u8, _: &Board) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("give {} {}", player, piece));
+        }
+
+        fn on_piece_placed(&self, player: usize, piece: u8, index: u8, board: &Board) {
+            assert_eq!(board.piece_at(index), Some(piece));
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("place {} {}", player, index));
+        }
+
+        fn on_quarto_called(&self, player: usize, correct: bool, _: &Board) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("quarto {} {}", player, correct));
+        }
+
+        fn on_game_end(&self, result: GameResult, record: &GameRecord) {
+            assert_eq!(record.result(), Some(result));
+            self.log.lock().unwrap().push(format!("end {:?}", result));
+        }
+    }
+
+    #[test]
+    fn test_observer_follows_game() {
+        let observer = Arc::new(LogObserver::default());
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(SmartStrategy::new(1)),
+            ComputerPlayer::new(SmartStrategy::new(1)),
+        )
+        .with_observer(observer.clone());
+        let res = game.play();
+        let log = observer.log.lock().unwrap();
+        let turns = game.history().len();
+        assert_eq!(log.iter().filter(|l| l.starts_with("give")).count(), turns);
+        assert_eq!(log.iter().filter(|l| l.starts_with("place")).count(), turns);
+        assert_eq!(log.last(), Some(&format!("end {:?}", res)));
+        if let GameResult::Win(winner) = res {
+            // Smart players always call a Quarto they made.
+            assert_eq!(log[log.len() - 2], format!("quarto {} true", winner));
+            assert!(game.history().turns().last().unwrap().quarto_called);
+        }
+    }
+
+    /// A player that always gives `piece`, places on `index`, and calls Quarto if `calls` is set.
+    struct ScriptedPlayer {
+        piece: u8,
+        index: u8,
+        calls: bool,
+    }
+
+    impl Player for ScriptedPlayer {
+        fn get_piece(&self, _: &Board) -> Option<u8> {
+            Some(self.piece)
+        }
+
+        fn get_move(&self, _: &Board, _: u8) -> Option<u8> {
+            Some(self.index)
+        }
+
+        fn quarto(&self, _: &Board) -> bool {
+            self.calls
+        }
+    }
+
+    /// A game where the first player hands over piece 9, which the second player places on the open end of the last row.
+    fn missed_quarto(claimed: bool) -> GameResult {
+        let giver = ScriptedPlayer {
+            piece: 9,
+            index: 0,
+            calls: claimed,
+        };
+        let placer = ScriptedPlayer {
+            piece: 0,
+            index: 15,
+            calls: false,
+        };
+        let mut game = QuartoGame::new(giver, placer)
+            .with_opening(&[(8, 12), (12, 13), (10, 14), (0, 0)])
+            .unwrap();
+        let res = game.play();
+        assert!(game.board.has_winner());
+        assert!(!game.history().turns()[4].quarto_called);
+        res
+    }
+
+    #[test]
+    fn test_missed_quarto_is_claimed_by_opponent() {
+        assert_eq!(missed_quarto(true), GameResult::Win(0));
+    }
+
+    #[test]
+    fn test_unclaimed_quarto_is_draw() {
+        assert_eq!(missed_quarto(false), GameResult::Draw);
+    }
 }
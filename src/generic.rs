@@ -0,0 +1,213 @@
+// Experimental: a board of any size with any number of attributes, to try out variants of Quarto.
+// Only built with the `experimental` feature. Nothing in the crate plays on it: the game, the players, the search
+// and the solver all use the `u128` of `Board`, which only fits four by four cells with four attributes.
+// It checks the rules and nothing more, and its interface can change in any release.
+
+use crate::{board::Board, error::PlacementError};
+
+/// A board of `N` by `N` cells for the `2^ATTRS` pieces with `ATTRS` attributes.
+/// A full row, column or diagonal of `N` pieces that share an attribute wins.
+/// Pieces and indices are numbered like on `Board`, so there can be at most 15 by 15 cells and 8 attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericBoard<const N: usize = 4, const ATTRS: usize = 4> {
+    /// The piece on every cell, row by row.
+    cells: [[Option<u8>; N]; N],
+    /// Bit `p` is set when piece `p` is on the board.
+    used: [u64; 4],
+}
+
+impl<const N: usize, const ATTRS: usize> Default for GenericBoard<N, ATTRS> {
+    fn default() -> Self {
+        GenericBoard::new()
+    }
+}
+
+impl<const N: usize, const ATTRS: usize> GenericBoard<N, ATTRS> {
+    /// The number of cells.
+    pub const CELLS: usize = N * N;
+    /// The number of pieces, one for every combination of attributes.
+    pub const PIECES: usize = 1 << ATTRS;
+
+    /// Create a new empty board.
+    /// Fails to compile for a size or a number of attributes the numbering cannot hold.
+    pub fn new() -> Self {
+        const { assert!(N >= 2 && N <= 15 && ATTRS >= 1 && ATTRS <= 8) };
+        GenericBoard {
+            cells: [[None; N]; N],
+            used: [0; 4],
+        }
+    }
+
+    /// Get the piece on the given index, or `None` if the index is empty or out of range.
+    pub fn piece_at(&self, index: u8) -> Option<u8> {
+        let index = index as usize;
+        if index >= Self::CELLS {
+            return None;
+        }
+        self.cells[index / N][index % N]
+    }
+
+    /// Check if a piece exists and is not on the board yet.
+    pub fn valid_piece(&self, piece: u8) -> bool {
+        (piece as usize) < Self::PIECES && self.used[piece as usize / 64] & (1 << (piece % 64)) == 0
+    }
+
+    /// Return the indices that are empty.
+    pub fn empty_spaces(&self) -> Vec<u8> {
+        (0..Self::CELLS as u8)
+            .filter(|&index| self.piece_at(index).is_none())
+            .collect()
+    }
+
+    /// Return the pieces that are not on the board yet.
+    pub fn valid_pieces(&self) -> Vec<u8> {
+        (0..Self::PIECES)
+            .map(|piece| piece as u8)
+            .filter(|&piece| self.valid_piece(piece))
+            .collect()
+    }
+
+    /// Put `piece` on the board at `index`.
+    /// Returns why the piece cannot be placed if it is not allowed, the board is unchanged in that case.
+    pub fn put_piece(&mut self, piece: u8, index: u8) -> Result<(), PlacementError> {
+        if index as usize >= Self::CELLS {
+            return Err(PlacementError::IndexOutOfRange { index });
+        }
+        if piece as usize >= Self::PIECES {
+            return Err(PlacementError::InvalidPiece { piece });
+        }
+        if self.piece_at(index).is_some() {
+            return Err(PlacementError::OccupiedCell { index });
+        }
+        if !self.valid_piece(piece) {
+            return Err(PlacementError::PieceAlreadyUsed { piece });
+        }
+        self.cells[index as usize / N][index as usize % N] = Some(piece);
+        self.used[piece as usize / 64] |= 1 << (piece % 64);
+        Ok(())
+    }
+
+    /// The indices of every line: the rows, the columns and then the two diagonals.
+    pub fn lines() -> impl Iterator<Item = Vec<u8>> {
+        let rows = (0..N).map(|row| (0..N).map(|column| (row * N + column) as u8).collect());
+        let columns = (0..N).map(|column| (0..N).map(|row| (row * N + column) as u8).collect());
+        let down = (0..N).map(|i| (i * N + i) as u8).collect();
+        let up = (0..N).map(|i| ((N - 1 - i) * N + i) as u8).collect();
+        rows.chain(columns).chain([down, up])
+    }
+
+    /// The attribute bits every piece on `line` has, and the ones no piece has.
+    fn common(&self, line: &[u8]) -> (u8, u8) {
+        let all = (Self::PIECES - 1) as u8;
+        line.iter()
+            .filter_map(|&index| self.piece_at(index))
+            .fold((all, all), |(set, unset), piece| {
+                (set & piece, unset & !piece & all)
+            })
+    }
+
+    /// Check if `line` is full and its pieces share an attribute.
+    fn line_wins(&self, line: &[u8]) -> bool {
+        let (set, unset) = self.common(line);
+        line.iter().all(|&index| self.piece_at(index).is_some()) && set | unset != 0
+    }
+
+    /// Check if the board has a winner.
+    pub fn has_winner(&self) -> bool {
+        Self::lines().any(|line| self.line_wins(&line))
+    }
+
+    /// Check if every cell holds a piece.
+    pub fn board_full(&self) -> bool {
+        self.cells.iter().flatten().all(Option::is_some)
+    }
+
+    /// Check if the game is over: a line wins, the board is full, or no piece is left to hand over.
+    pub fn game_over(&self) -> bool {
+        self.has_winner() || self.board_full() || self.valid_pieces().is_empty()
+    }
+
+    /// Return the empty indices where `piece` completes a Quarto, in increasing order.
+    pub fn winning_placements(&self, piece: u8) -> Vec<u8> {
+        if !self.valid_piece(piece) {
+            return Vec::new();
+        }
+        self.empty_spaces()
+            .into_iter()
+            .filter(|&index| {
+                let mut next = *self;
+                next.put_piece(piece, index).is_ok() && next.has_winner()
+            })
+            .collect()
+    }
+}
+
+/// The standard board, to compare a variant with the standard game.
+impl From<&Board> for GenericBoard {
+    fn from(board: &Board) -> Self {
+        let mut generic = GenericBoard::new();
+        for index in 0..16 {
+            if let Some(piece) = board.piece_at(index) {
+                generic
+                    .put_piece(piece, index)
+                    .expect("a board holds every piece once");
+            }
+        }
+        generic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play random games on both boards, and check that they agree after every placement.
+    #[test]
+    fn test_agrees_with_board() {
+        let mut rng = fastrand::Rng::with_seed(2795);
+        for _ in 0..200 {
+            let mut board = Board::new();
+            let mut generic: GenericBoard = GenericBoard::new();
+            while !board.game_over() {
+                let pieces = board.valid_pieces();
+                let spaces = board.empty_spaces();
+                let piece = pieces[rng.usize(..pieces.len())];
+                assert_eq!(
+                    generic.winning_placements(piece),
+                    board.winning_placements(piece)
+                );
+                let index = spaces[rng.usize(..spaces.len())];
+                board.put_piece(piece, index).unwrap();
+                generic.put_piece(piece, index).unwrap();
+                assert_eq!(generic.has_winner(), board.has_winner());
+                assert_eq!(generic.game_over(), board.game_over());
+                assert_eq!(generic, GenericBoard::from(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn test_larger_board() {
+        // Five by five cells with five attributes: five pieces with the top bit on the up diagonal win.
+        let mut board: GenericBoard<5, 5> = GenericBoard::new();
+        assert_eq!(GenericBoard::<5, 5>::PIECES, 32);
+        assert_eq!(GenericBoard::<5, 5>::lines().count(), 12);
+        for (piece, index) in [(16, 20), (17, 16), (18, 12), (19, 8)] {
+            board.put_piece(piece, index).unwrap();
+        }
+        assert!(!board.has_winner());
+        assert_eq!(board.winning_placements(31), vec![4]);
+        assert_eq!(board.winning_placements(15), Vec::<u8>::new());
+        assert_eq!(
+            board.put_piece(32, 0),
+            Err(PlacementError::InvalidPiece { piece: 32 })
+        );
+        assert_eq!(
+            board.put_piece(0, 25),
+            Err(PlacementError::IndexOutOfRange { index: 25 })
+        );
+        board.put_piece(31, 4).unwrap();
+        assert!(board.game_over());
+        assert!(!board.valid_piece(31));
+    }
+}
@@ -0,0 +1,330 @@
+// A gRPC service to play against the engine, mirroring the REST API of `serve` on the same `Sessions`.
+// Only built with the `grpc` feature. The messages are prost types written by hand after proto/quarto.proto,
+// and build.rs generates the server and the client from them, so no protoc is needed.
+
+use std::{io, net::SocketAddr, sync::Arc};
+
+use tonic::{Request, Response, Status};
+
+use crate::{
+    board::Board,
+    client::Action,
+    game::GameResult,
+    session::{self, Sessions},
+};
+
+include!(concat!(env!("OUT_DIR"), "/quarto.Quarto.rs"));
+
+pub use quarto_client::QuartoClient;
+pub use quarto_server::{Quarto, QuartoServer};
+
+/// Start a game against a computer player of the registry, `smart` when the engine is left empty.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateGameRequest {
+    #[prost(string, tag = "1")]
+    pub engine: String,
+}
+
+/// Ask about the game with `id`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GameRequest {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+}
+
+/// Play a move in the game with `id`, or let the engine play when there is none.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PlayRequest {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(message, optional, tag = "2")]
+    pub action: Option<Move>,
+}
+
+/// Hand a piece over, or place the piece in hand.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Move {
+    #[prost(oneof = "Kind", tags = "1, 2")]
+    pub kind: Option<Kind>,
+}
+
+/// What a `Move` does.
+#[derive(Clone, Copy, PartialEq, prost::Oneof)]
+pub enum Kind {
+    #[prost(uint32, tag = "1")]
+    Give(u32),
+    #[prost(uint32, tag = "2")]
+    Place(u32),
+}
+
+/// The state of a game, like the `Session` of the REST API.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Game {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    /// The piece on each of the 16 cells, 16 for an empty cell.
+    #[prost(uint32, repeated, tag = "2")]
+    pub cells: Vec<u32>,
+    /// The piece that was handed over and waits to be placed.
+    #[prost(uint32, optional, tag = "3")]
+    pub piece: Option<u32>,
+    #[prost(uint32, tag = "4")]
+    pub turn: u32,
+    /// Empty while the game goes on, then `win 0`, `timeout 1`, `draw` or `error`.
+    #[prost(string, tag = "5")]
+    pub result: String,
+    #[prost(string, tag = "6")]
+    pub engine: String,
+}
+
+/// The proven outcome of every move of the player to act.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub outcomes: Vec<MoveOutcome>,
+}
+
+/// A move with its outcome for the player that makes it, `win`, `draw` or `loss`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MoveOutcome {
+    #[prost(message, optional, tag = "1")]
+    pub action: Option<Move>,
+    #[prost(string, tag = "2")]
+    pub outcome: String,
+}
+
+impl From<Action> for Move {
+    fn from(action: Action) -> Self {
+        let kind = match action {
+            Action::Give(piece) => Some(Kind::Give(piece.into())),
+            Action::Place(index) => Some(Kind::Place(index.into())),
+            Action::Quarto => None,
+        };
+        Move { kind }
+    }
+}
+
+impl TryFrom<Move> for Action {
+    type Error = String;
+
+    fn try_from(action: Move) -> Result<Self, Self::Error> {
+        let number = |number: u32| {
+            u8::try_from(number)
+                .ok()
+                .filter(|&number| number < 16)
+                .ok_or(format!("{} is not a piece or a cell", number))
+        };
+        match action.kind {
+            Some(Kind::Give(piece)) => Ok(Action::Give(number(piece)?)),
+            Some(Kind::Place(index)) => Ok(Action::Place(number(index)?)),
+            None => Err("The move gives nor places".to_string()),
+        }
+    }
+}
+
+/// Write the cells of `board` as piece numbers, 16 for an empty cell.
+fn cells(board: &Board) -> Vec<u32> {
+    (0..16)
+        .map(|index| board.piece_at(index).map_or(16, u32::from))
+        .collect()
+}
+
+impl From<session::Session> for Game {
+    fn from(session: session::Session) -> Self {
+        let result = match session.result {
+            None => String::new(),
+            Some(GameResult::Win(seat)) => format!("win {}", seat),
+            Some(GameResult::Timeout(seat)) => format!("timeout {}", seat),
+            Some(GameResult::Draw) => "draw".to_string(),
+            Some(GameResult::Error) => "error".to_string(),
+        };
+        Game {
+            id: session.id,
+            cells: cells(&session.board),
+            piece: session.piece.map(u32::from),
+            turn: session.turn as u32,
+            result,
+            engine: session.engine,
+        }
+    }
+}
+
+/// Answers the requests with the games of `Sessions`, which may be shared with the REST API.
+#[derive(Debug, Clone)]
+pub struct Service {
+    sessions: Arc<Sessions>,
+}
+
+impl Service {
+    /// Answer with the games of `sessions`.
+    pub fn new(sessions: Arc<Sessions>) -> Self {
+        Service { sessions }
+    }
+
+    /// Run `answer` on the games where blocking is allowed, since the engine thinks for a while.
+    /// A game that does not exist is not found, any other error is a request that cannot be done now.
+    async fn answer<T: Send + 'static>(
+        &self,
+        id: u64,
+        answer: impl FnOnce(&Sessions) -> Result<T, String> + Send + 'static,
+    ) -> Result<Response<T>, Status> {
+        let sessions = self.sessions.clone();
+        if sessions.get(id).is_none() {
+            return Err(Status::not_found(format!("There is no game {}", id)));
+        }
+        tokio::task::spawn_blocking(move || answer(&sessions))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(Response::new)
+            .map_err(Status::failed_precondition)
+    }
+}
+
+#[tonic::async_trait]
+impl Quarto for Service {
+    async fn create_game(
+        &self,
+        request: Request<CreateGameRequest>,
+    ) -> Result<Response<Game>, Status> {
+        let engine = match request.into_inner().engine {
+            engine if engine.is_empty() => "smart".to_string(),
+            engine => engine,
+        };
+        self.sessions
+            .create(&engine)
+            .map(|session| Response::new(session.into()))
+            .map_err(Status::invalid_argument)
+    }
+
+    async fn get_game(&self, request: Request<GameRequest>) -> Result<Response<Game>, Status> {
+        let id = request.into_inner().id;
+        self.answer(id, move |sessions| {
+            sessions
+                .get(id)
+                .map(Game::from)
+                .ok_or(format!("There is no game {}", id))
+        })
+        .await
+    }
+
+    async fn play(&self, request: Request<PlayRequest>) -> Result<Response<Game>, Status> {
+        let PlayRequest { id, action } = request.into_inner();
+        let action = action
+            .map(Action::try_from)
+            .transpose()
+            .map_err(Status::invalid_argument)?;
+        self.answer(id, move |sessions| {
+            match action {
+                Some(action) => sessions.act(id, action),
+                None => sessions.play_engine(id),
+            }
+            .map(Game::from)
+        })
+        .await
+    }
+
+    async fn best_move(&self, request: Request<GameRequest>) -> Result<Response<Move>, Status> {
+        let id = request.into_inner().id;
+        self.answer(id, move |sessions| sessions.best_action(id).map(Move::from))
+            .await
+    }
+
+    async fn solve(
+        &self,
+        request: Request<GameRequest>,
+    ) -> Result<Response<SolveResponse>, Status> {
+        let id = request.into_inner().id;
+        self.answer(id, move |sessions| {
+            let outcomes = sessions
+                .solve(id)?
+                .into_iter()
+                .map(|(action, outcome)| MoveOutcome {
+                    action: Some(action.into()),
+                    outcome: outcome.to_string(),
+                });
+            Ok(SolveResponse {
+                outcomes: outcomes.collect(),
+            })
+        })
+        .await
+    }
+}
+
+/// Answer the gRPC requests to `address`, like `127.0.0.1:50051`, with the games of `sessions`.
+pub fn serve(address: &str, sessions: Arc<Sessions>) -> io::Result<()> {
+    let address: SocketAddr = address
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    tokio::runtime::Runtime::new()?.block_on(async {
+        tonic::transport::Server::builder()
+            .add_service(QuartoServer::new(Service::new(sessions)))
+            .serve(address)
+            .await
+            .map_err(io::Error::other)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::PlayerOptions;
+
+    #[test]
+    fn test_service_mirrors_rest() {
+        let sessions = Sessions::new().with_options(PlayerOptions {
+            depth: 1,
+            ..PlayerOptions::default()
+        });
+        let service = Service::new(Arc::new(sessions));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let game = service
+                .create_game(Request::new(CreateGameRequest::default()))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!((game.engine.as_str(), game.cells.len()), ("smart", 16));
+            let play = |action: Option<Action>| PlayRequest {
+                id: game.id,
+                action: action.map(Move::from),
+            };
+            for (piece, index) in [(8, 0), (9, 1), (10, 2)] {
+                service
+                    .play(Request::new(play(Some(Action::Give(piece)))))
+                    .await
+                    .unwrap();
+                service
+                    .play(Request::new(play(Some(Action::Place(index)))))
+                    .await
+                    .unwrap();
+            }
+            let game = service
+                .play(Request::new(play(Some(Action::Give(11)))))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!((game.piece, game.cells[2]), (Some(11), 10));
+            let best = service
+                .best_move(Request::new(GameRequest { id: game.id }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(best, Move::from(Action::Place(3)));
+            let error = service
+                .solve(Request::new(GameRequest { id: game.id }))
+                .await
+                .unwrap_err();
+            assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+            let game = service
+                .play(Request::new(play(None)))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(game.result, "win 0");
+            let missing = service.get_game(Request::new(GameRequest { id: 99 })).await;
+            assert_eq!(missing.unwrap_err().code(), tonic::Code::NotFound);
+            let bad = service.play(Request::new(play(Some(Action::Quarto)))).await;
+            assert_eq!(bad.unwrap_err().code(), tonic::Code::InvalidArgument);
+        });
+    }
+}
@@ -0,0 +1,122 @@
+// Handicaps for games between players of different strength, so clubs can make mismatched games interesting.
+// The weaker player can start from a position of their choice, or the stronger player has to hand over random pieces.
+
+use std::{cell::Cell, time::Duration};
+
+use crate::{board::Board, player::Player, random::SharedRng};
+
+/// A handicap of a game, given to `QuartoGame::with_handicap`.
+#[derive(Debug, Clone)]
+pub enum Handicap {
+    /// The game starts with `moves` already on the board, played in turn so the `weaker` player hands over the next piece.
+    Placed { weaker: usize, moves: Vec<(u8, u8)> },
+    /// The `stronger` player hands over a random piece instead of their own choice on every `every`th piece, like every other one for 2.
+    RandomPieces {
+        stronger: usize,
+        every: usize,
+        rng: SharedRng,
+    },
+}
+
+/// Wraps a `Player` that hands over a random piece on every `every`th piece it gives.
+pub struct RandomPieces {
+    player: Box<dyn Player>,
+    every: usize,
+    rng: SharedRng,
+    /// The number of pieces handed over so far.
+    given: Cell<usize>,
+}
+
+impl RandomPieces {
+    /// Restrict `player` to a random piece on every `every`th piece, 0 never restricts it.
+    pub fn new(player: Box<dyn Player>, every: usize, rng: SharedRng) -> Self {
+        RandomPieces {
+            player,
+            every,
+            rng,
+            given: Cell::new(0),
+        }
+    }
+}
+
+impl Player for RandomPieces {
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        let given = self.given.get() + 1;
+        self.given.set(given);
+        if self.every > 0 && given.is_multiple_of(self.every) {
+            return self.rng.choose(&board.valid_pieces());
+        }
+        self.player.get_piece(board)
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        self.player.get_move(board, piece)
+    }
+
+    fn quarto(&self, board: &Board) -> bool {
+        self.player.quarto(board)
+    }
+
+    fn forfeited(&self) -> bool {
+        self.player.forfeited()
+    }
+
+    fn time_left(&self, time: Duration) {
+        self.player.time_left(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        QuartoGame,
+        player::ComputerPlayer,
+        strategy::{DeterministicStrategy, Strategy},
+    };
+
+    use super::*;
+
+    fn deterministic() -> ComputerPlayer<DeterministicStrategy> {
+        ComputerPlayer::new(DeterministicStrategy)
+    }
+
+    #[test]
+    fn test_random_pieces_every_other_turn() {
+        let mut game = QuartoGame::new(deterministic(), deterministic())
+            .with_handicap(Handicap::RandomPieces {
+                stronger: 0,
+                every: 2,
+                rng: SharedRng::seeded(2798),
+            })
+            .unwrap();
+        game.play_without_call();
+        let mut board = Board::new();
+        let mut random = 0;
+        for (turn, &entry) in game.history().turns().iter().enumerate() {
+            let chosen = DeterministicStrategy.get_piece(&board);
+            if entry.giver == 0 && turn % 4 == 2 {
+                random += usize::from(chosen != Some(entry.piece));
+            } else {
+                assert_eq!(chosen, Some(entry.piece));
+            }
+            board.put_piece(entry.piece, entry.index).unwrap();
+        }
+        assert!(random > 0);
+    }
+
+    #[test]
+    fn test_placed_pieces() {
+        let moves = [(0, 0), (15, 5), (3, 10)];
+        let mut game = QuartoGame::new(deterministic(), deterministic())
+            .with_handicap(Handicap::Placed {
+                weaker: 0,
+                moves: moves.to_vec(),
+            })
+            .unwrap();
+        let givers: Vec<usize> = game.history().turns().iter().map(|t| t.giver).collect();
+        assert_eq!(givers, [1, 0, 1]);
+        game.play_without_call();
+        let next = game.history().turns()[3];
+        assert_eq!((next.giver, next.placer), (0, 1));
+    }
+}
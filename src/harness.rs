@@ -0,0 +1,155 @@
+// Correctness harness for strategies.
+// Checks a `Strategy` against a corpus of positions for the blunders that can be decided with a one-ply lookahead.
+
+use std::fmt;
+
+use crate::{board::Board, position::PositionGenerator, random::SharedRng, strategy::Strategy};
+
+/// A mistake a `Strategy` made on a position from the corpus.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Blunder {
+    /// The strategy could complete a Quarto with `piece`, but placed it on `chosen` instead.
+    MissedWin {
+        board: Board,
+        piece: u8,
+        chosen: Option<u8>,
+    },
+    /// The strategy handed over `given`, which lets the opponent win, while a safe piece was still available.
+    UnsafePiece { board: Board, given: Option<u8> },
+}
+
+impl fmt::Display for Blunder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Blunder::MissedWin {
+                board,
+                piece,
+                chosen,
+            } => write!(
+                f,
+                "missed a win with piece {} on board {:#034x}, placed it on {:?}",
+                piece,
+                board.items(),
+                chosen
+            ),
+            Blunder::UnsafePiece { board, given } => write!(
+                f,
+                "handed over winning piece {:?} on board {:#034x} while a safe piece existed",
+                given,
+                board.items()
+            ),
+        }
+    }
+}
+
+/// Find the first blunder `strategy` makes on the given positions.
+/// Positions that are already over are skipped.
+pub fn find_blunder<S: Strategy>(strategy: &S, positions: &[Board]) -> Option<Blunder> {
+    for board in positions.iter().filter(|b| !b.game_over()) {
+        for piece in board.valid_pieces() {
+            let wins = board.winning_placements(piece);
+            if wins.is_empty() {
+                continue;
+            }
+            let chosen = strategy.get_move(board, piece);
+            if !chosen.is_some_and(|index| wins.contains(&index)) {
+                return Some(Blunder::MissedWin {
+                    board: *board,
+                    piece,
+                    chosen,
+                });
+            }
+        }
+        let safe = board.safe_pieces();
+        if safe.is_empty() {
+            continue;
+        }
+        let given = strategy.get_piece(board);
+        if !given.is_some_and(|piece| safe.contains(&piece)) {
+            return Some(Blunder::UnsafePiece {
+                board: *board,
+                given,
+            });
+        }
+    }
+    None
+}
+
+/// Panic if `strategy` misses an immediate win or hands over a winning piece on any of the positions.
+/// Any strategy that calls itself smart should pass this on a decent corpus.
+pub fn assert_never_blunders<S: Strategy>(strategy: &S, positions: &[Board]) {
+    if let Some(blunder) = find_blunder(strategy, positions) {
+        panic!("Strategy blundered: {}", blunder);
+    }
+}
+
+/// Generate `count` random positions that are not over yet, each with at most `max_pieces` pieces on the board.
+/// The positions are drawn from `rng`, so a seed gives the same corpus.
+pub fn random_positions(count: usize, max_pieces: u8, rng: &SharedRng) -> Vec<Board> {
+    let generator = PositionGenerator::new().with_rng(rng.clone());
+    let mut positions: Vec<Board> = Vec::new();
+    while positions.len() < count {
+        let pieces = rng.usize(..=usize::from(max_pieces.min(15)));
+        positions.extend(generator.board(pieces as u8));
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{strategy::DeterministicStrategy, test_util::open_row};
+
+    use super::*;
+
+    /// A strategy that plays the oracle's answers directly, so it never blunders.
+    struct OracleStrategy;
+
+    impl Strategy for OracleStrategy {
+        fn get_piece(&self, board: &Board) -> Option<u8> {
+            board
+                .safe_pieces()
+                .first()
+                .copied()
+                .or(board.valid_pieces().first().copied())
+        }
+
+        fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+            board
+                .winning_placements(piece)
+                .first()
+                .copied()
+                .or(board.empty_spaces().first().copied())
+        }
+
+        fn quarto(&self, board: &Board) -> bool {
+            board.has_winner()
+        }
+    }
+
+    #[test]
+    fn test_deterministic_misses_win() {
+        let blunder = find_blunder(&DeterministicStrategy, &[open_row()]);
+        assert!(matches!(blunder, Some(Blunder::MissedWin { .. })));
+    }
+
+    #[test]
+    #[should_panic(expected = "Strategy blundered")]
+    fn test_assert_never_blunders_panics() {
+        assert_never_blunders(&DeterministicStrategy, &[open_row()]);
+    }
+
+    #[test]
+    fn test_oracle_never_blunders() {
+        assert_never_blunders(
+            &OracleStrategy,
+            &random_positions(50, 10, &SharedRng::seeded(1)),
+        );
+    }
+
+    #[test]
+    fn test_random_positions_not_over() {
+        for board in random_positions(50, 15, &SharedRng::seeded(1)) {
+            assert!(!board.game_over());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+// The local database of finished games, kept next to the profile.
+// Filled by `quarto import` from game archives of other tools, and checked by `quarto doctor`.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    game::MAX_SEATS,
+    persist::{self, DataKind, PersistError},
+    profile,
+    record::GameRecord,
+};
+
+/// Finished games, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryDb {
+    games: Vec<GameRecord>,
+}
+
+impl HistoryDb {
+    /// Create an empty database.
+    pub fn new() -> Self {
+        HistoryDb::default()
+    }
+
+    /// Add a game to the end of the database.
+    pub fn add(&mut self, record: GameRecord) {
+        self.games.push(record);
+    }
+
+    pub fn games(&self) -> &[GameRecord] {
+        &self.games
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Write the database with a header, every game starts with a `game` line followed by the lines of its record.
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        for record in self.games.iter() {
+            body += "game\n";
+            record.write_lines(&mut body);
+        }
+        persist::write(DataKind::HistoryDb, &body)
+    }
+
+    /// Read a database written by `to_text`.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::HistoryDb, data)?;
+        let mut db = HistoryDb::new();
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: &str| PersistError::Malformed {
+                line,
+                message: message.to_string(),
+            };
+            let words: Vec<&str> = text.split_whitespace().collect();
+            match (words.as_slice(), db.games.last_mut()) {
+                (["game"], _) => db.games.push(GameRecord::new()),
+                ([], _) => {}
+                (_, Some(record)) => record.read_line(&words, MAX_SEATS).map_err(malformed)?,
+                (_, None) => return Err(malformed("a game starts with a game line")),
+            }
+        }
+        Ok(db)
+    }
+
+    /// Load the database from `path`, or an empty database if the file does not exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(data) => HistoryDb::from_text(&data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HistoryDb::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Save the database to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+/// Where the history is kept: `history` next to the profile.
+pub fn default_path() -> Option<PathBuf> {
+    profile::default_path().map(|path| path.with_file_name("history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GameResult, record::Turn};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut db = HistoryDb::new();
+        let mut record = GameRecord::new();
+        record.push(Turn {
+            giver: 0,
+            piece: 5,
+            placer: 1,
+            index: 9,
+            quarto_called: false,
+        });
+        record.set_result(GameResult::Draw);
+        db.add(record);
+        db.add(GameRecord::new());
+        assert_eq!(HistoryDb::from_text(&db.to_text()), Ok(db));
+        assert!(HistoryDb::from_text("quarto history 1\nresult draw\n").is_err());
+    }
+}
@@ -0,0 +1,349 @@
+// Bulk import of game archives, CSV move lists, spectator transcripts and QGN, into the local history database.
+// Every game is replayed to validate it, games that cannot be played are rejected with a reason instead of stopping the import.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::{
+    board::Board,
+    events::GameEvent,
+    game::{GameResult, MAX_SEATS},
+    history::HistoryDb,
+    notation,
+    qgn::{self, QgnGame},
+    record::{GameRecord, Turn},
+    spectate::SpectatorMessage,
+};
+
+/// The formats an archive can be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A header line with a `moves` column and an optional `result` column, then a game per line.
+    /// Moves are `piece:cell` separated by spaces, like `3:5` or `TDSH:B3`.
+    /// A result is `1-0`, `0-1`, `1/2-1/2`, `first`, `second` or `draw`.
+    Csv,
+    /// Logs of the spectator protocol, a game from `start` to `end`.
+    Transcript,
+    /// Games in QGN, see `qgn::QgnGame`.
+    Qgn,
+}
+
+impl ArchiveFormat {
+    /// Guess the format from the extension of `path`: `.csv` is CSV, `.qgn` is QGN, everything else a transcript.
+    pub fn guess(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => ArchiveFormat::Csv,
+            Some(extension) if extension.eq_ignore_ascii_case("qgn") => ArchiveFormat::Qgn,
+            _ => ArchiveFormat::Transcript,
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveFormat::Csv => write!(f, "csv"),
+            ArchiveFormat::Transcript => write!(f, "transcript"),
+            ArchiveFormat::Qgn => write!(f, "qgn"),
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ArchiveFormat::Csv),
+            "transcript" => Ok(ArchiveFormat::Transcript),
+            "qgn" => Ok(ArchiveFormat::Qgn),
+            _ => Err(format!("expected csv, transcript or qgn, got '{}'", s)),
+        }
+    }
+}
+
+/// What an import added, and the games it left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// The number of each rejected game in the archive, counting from 1, with the reason.
+    pub rejected: Vec<(usize, String)>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Imported {} games, rejected {}.",
+            self.imported,
+            self.rejected.len()
+        )?;
+        for (game, reason) in self.rejected.iter() {
+            writeln!(f, "  game {}: {}", game, reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Import the games of `data` into `db`, and report what was imported and rejected.
+pub fn import(data: &str, format: ArchiveFormat, db: &mut HistoryDb) -> ImportReport {
+    let games = match format {
+        ArchiveFormat::Csv => read_csv(data),
+        ArchiveFormat::Transcript => read_transcripts(data),
+        ArchiveFormat::Qgn => qgn::parse_all(data)
+            .into_iter()
+            .map(|game| game.map(QgnGame::into_record))
+            .collect(),
+    };
+    let mut report = ImportReport::default();
+    for (number, game) in (1..).zip(games) {
+        match game.and_then(validate) {
+            Ok(record) => {
+                db.add(record);
+                report.imported += 1;
+            }
+            Err(reason) => report.rejected.push((number, reason)),
+        }
+    }
+    report
+}
+
+/// Replay `record` to check every turn, and fill in or check the result.
+/// A game without a result gets the result of its last position, a game that is not over is rejected.
+/// A win must go to the player that placed the last piece, a record cannot tell a Quarto claimed by the opponent from a wrong result.
+fn validate(mut record: GameRecord) -> Result<GameRecord, String> {
+    let mut board = Board::new().with_ruleset(record.ruleset());
+    for (ply, turn) in record.turns().iter().enumerate() {
+        if board.game_over() {
+            return Err(format!("move {} is played after the game is over", ply + 1));
+        }
+        if turn.giver % 2 == turn.placer % 2 || turn.giver.max(turn.placer) >= MAX_SEATS {
+            return Err(format!("move {} is not between two opponents", ply + 1));
+        }
+        board
+            .put_piece(turn.piece, turn.index)
+            .map_err(|e| format!("move {} is illegal: {}", ply + 1, e))?;
+    }
+    let last = record.turns().last().copied();
+    let consistent = match (record.result(), last) {
+        (None, Some(last)) if board.has_winner() => {
+            record.set_result(GameResult::Win(last.placer));
+            true
+        }
+        (None, _) if board.board_full() => {
+            record.set_result(GameResult::Draw);
+            true
+        }
+        (None, _) => return Err("the game is not finished".to_string()),
+        (Some(GameResult::Win(winner)), last) => {
+            board.has_winner() && last.is_some_and(|last| last.placer == winner)
+        }
+        (Some(GameResult::Draw), _) => board.game_over(),
+        (Some(GameResult::Timeout(player)), _) => player < MAX_SEATS && !board.game_over(),
+        (Some(GameResult::Error), _) => false,
+    };
+    if !consistent {
+        return Err(format!(
+            "the result {:?} does not match the final board",
+            record.result()
+        ));
+    }
+    Ok(record)
+}
+
+/// Read the games of a CSV archive, a game per line after the header.
+fn read_csv(data: &str) -> Vec<Result<GameRecord, String>> {
+    let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+    let split = |line: &str| -> Vec<String> {
+        line.split(',')
+            .map(|cell| cell.trim().trim_matches('"').trim().to_lowercase())
+            .collect()
+    };
+    let header = lines.next().map(split).unwrap_or_default();
+    let Some(moves) = header.iter().position(|column| column == "moves") else {
+        return vec![Err("the header has no moves column".to_string())];
+    };
+    let result = header.iter().position(|column| column == "result");
+    lines
+        .map(|line| {
+            let cells = split(line);
+            let mut record = GameRecord::new();
+            let tokens = cells.get(moves).map_or("", |cell| cell.as_str());
+            for (ply, token) in tokens.split_whitespace().enumerate() {
+                let (piece, index) = token
+                    .split_once(':')
+                    .and_then(|(piece, cell)| {
+                        Some((notation::parse_piece(piece)?, notation::parse_index(cell)?))
+                    })
+                    .ok_or_else(|| format!("'{}' is not a move like 3:5 or TDSH:B3", token))?;
+                record.push(Turn {
+                    giver: ply % 2,
+                    piece,
+                    placer: 1 - ply % 2,
+                    index,
+                    quarto_called: false,
+                });
+            }
+            if let Some(cell) = result.and_then(|column| cells.get(column)) {
+                match cell.as_str() {
+                    "" => {}
+                    "1-0" | "first" => record.set_result(GameResult::Win(0)),
+                    "0-1" | "second" => record.set_result(GameResult::Win(1)),
+                    "1/2-1/2" | "draw" => record.set_result(GameResult::Draw),
+                    other => return Err(format!("'{}' is not a result", other)),
+                }
+            }
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Read the games of spectator transcripts, every game starts at a `start` line.
+/// Lines before the first `start` and clock lines are skipped.
+fn read_transcripts(data: &str) -> Vec<Result<GameRecord, String>> {
+    let mut games: Vec<Result<GameRecord, String>> = Vec::new();
+    let mut given: Option<(usize, u8)> = None;
+    for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let message = line.parse::<SpectatorMessage>();
+        if message == Ok(SpectatorMessage::Event(GameEvent::GameStarted)) {
+            games.push(Ok(GameRecord::new()));
+            given = None;
+            continue;
+        }
+        let Some(Ok(record)) = games.last_mut() else {
+            continue;
+        };
+        if record.result().is_some() {
+            continue;
+        }
+        let event = match message {
+            Ok(SpectatorMessage::Event(event)) => event,
+            Ok(SpectatorMessage::Clock(_)) => continue,
+            Err(e) => {
+                *games.last_mut().unwrap() = Err(e);
+                continue;
+            }
+        };
+        match event {
+            GameEvent::GameStarted => {}
+            GameEvent::PieceGiven { player, piece } => given = Some((player, piece)),
+            GameEvent::PiecePlaced {
+                player,
+                piece,
+                index,
+            } => {
+                let giver = match given.take() {
+                    Some((giver, given)) if given == piece => giver,
+                    // Without the piece given, assume the previous seat gave it.
+                    _ => player.checked_sub(1).unwrap_or(1),
+                };
+                record.push(Turn {
+                    giver,
+                    piece,
+                    placer: player,
+                    index,
+                    quarto_called: false,
+                });
+            }
+            GameEvent::QuartoCalled { player, .. } => {
+                // Only a call by the placer belongs to the turn, the result tells what a claim by the opponent did.
+                if let Some(mut turn) = record.pop() {
+                    turn.quarto_called |= turn.placer == player;
+                    record.push(turn);
+                }
+            }
+            GameEvent::GameEnded { result } => record.set_result(result),
+        }
+    }
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_csv() {
+        let data = "id,moves,result\n\
+                    1,0:0 1:1 2:2 3:3,1-0\n\
+                    2,\"SLRS:A1 SLRH:B1 SLSS:C1\",\n\
+                    3,0:0 0:1,draw\n\
+                    4,8:0 12:1 10:2 9:3,1-0\n\
+                    5,8:0 12:1 10:2 9:3,\n\
+                    6,8:0 12:1 10:2 9:3,second\n";
+        let mut db = HistoryDb::new();
+        let report = import(data, ArchiveFormat::Csv, &mut db);
+        assert_eq!(report.imported, 3);
+        assert_eq!(db.len(), 3);
+        let rejected: Vec<usize> = report.rejected.iter().map(|&(game, _)| game).collect();
+        assert_eq!(rejected, vec![2, 3, 6]);
+        assert!(report.rejected[0].1.contains("not finished"));
+        assert!(report.rejected[1].1.contains("move 2 is illegal"));
+        assert!(report.rejected[2].1.contains("does not match"));
+        assert_eq!(db.games()[1].result(), Some(GameResult::Win(0)));
+        assert_eq!(db.games()[2].result(), Some(GameResult::Win(0)));
+        assert!(
+            report
+                .to_string()
+                .starts_with("Imported 3 games, rejected 3.")
+        );
+    }
+
+    #[test]
+    fn test_import_transcripts() {
+        let data = "start\ngive 0 8\nplace 1 8 0\ngive 1 12\nplace 0 12 1\nclock 100 200\n\
+                    give 0 10\nplace 1 10 2\ngive 1 9\nplace 0 9 3\nquarto 0 1\nend win 0\n\
+                    start\ngive 0 8\nplace 1 8 0\nnonsense\nend draw\n\
+                    start\ngive 0 8\nplace 1 8 0\nend win 1\n";
+        let mut db = HistoryDb::new();
+        let report = import(data, ArchiveFormat::Transcript, &mut db);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.rejected.len(), 2);
+        assert!(report.rejected[0].1.contains("nonsense"));
+        let record = &db.games()[0];
+        assert_eq!(record.len(), 4);
+        assert!(record.turns()[3].quarto_called);
+        assert_eq!(record.turns()[1].giver, 1);
+        assert_eq!(record.result(), Some(GameResult::Win(0)));
+    }
+
+    #[test]
+    fn test_import_team_transcripts() {
+        let data = "start
+give 0 8
+place 1 8 0
+give 1 12
+place 2 12 1
+                    give 2 10
+place 3 10 2
+give 3 9
+place 0 9 3
+end win 0
+                    start
+give 0 8
+place 2 8 0
+end draw
+";
+        let mut db = HistoryDb::new();
+        let report = import(data, ArchiveFormat::Transcript, &mut db);
+        assert_eq!(report.imported, 1);
+        assert!(report.rejected[0].1.contains("not between two opponents"));
+        assert_eq!(db.games()[0].turns()[2].giver, 2);
+        assert_eq!(HistoryDb::from_text(&db.to_text()).unwrap(), db);
+    }
+
+    #[test]
+    fn test_guess_format() {
+        assert_eq!(
+            ArchiveFormat::guess(Path::new("games.CSV")),
+            ArchiveFormat::Csv
+        );
+        assert_eq!(
+            ArchiveFormat::guess(Path::new("server.log")),
+            ArchiveFormat::Transcript
+        );
+        assert_eq!(
+            ArchiveFormat::guess(Path::new("club.qgn")),
+            ArchiveFormat::Qgn
+        );
+    }
+}
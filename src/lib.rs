@@ -0,0 +1,121 @@
+//! A Quarto engine: a bitboard, players and strategies, and a game loop to play them against each other.
+//!
+//! ```
+//! use quarto::{ComputerPlayer, GameResult, QuartoGame, strategy::SmartStrategy};
+//!
+//! let mut game = QuartoGame::new(
+//!     ComputerPlayer::new(SmartStrategy::new(1)),
+//!     ComputerPlayer::new(SmartStrategy::new(1)),
+//! );
+//! let result = game.play_without_call();
+//! assert_ne!(result, GameResult::Error);
+//! assert_eq!(game.history().result(), Some(result));
+//! ```
+//!
+//! # Stability
+//!
+//! The types in [`prelude`] and the re-exports at the root of the crate are the stable surface, for bots and tools
+//! built on the crate: they only change with a new major version. The other modules are public so the binary,
+//! the benchmarks and experiments can reach them, but they can change in any release.
+//!
+//! ```
+//! use quarto::prelude::*;
+//!
+//! let mut driver = GameDriver::new();
+//! driver.decide(Decision::Give("TDSH".parse::<Piece>().unwrap().to_number())).unwrap();
+//! let cell: Cell = "B3".parse().unwrap();
+//! assert_eq!(driver.decide(Decision::Place(cell.index())), Ok(Step::Give { player: 1 }));
+//! assert_eq!(driver.board().piece_at(cell.index()), Some(15));
+//! ```
+
+pub mod analysis;
+pub mod board;
+#[cfg(feature = "network")]
+pub mod browse;
+pub mod cancel;
+pub mod card;
+pub mod client;
+pub mod clock;
+pub mod database;
+pub mod doctor;
+pub mod driver;
+pub mod engine;
+pub mod error;
+pub mod eval;
+pub mod evaluator;
+pub mod events;
+pub mod fairplay;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fog;
+pub mod game;
+#[cfg(feature = "experimental")]
+pub mod generic;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod handicap;
+pub mod harness;
+pub mod history;
+pub mod import;
+pub mod line;
+#[cfg(feature = "network")]
+pub mod lobby;
+pub mod mcts;
+pub mod memory;
+#[cfg(feature = "network")]
+pub mod network;
+pub mod notation;
+pub mod observer;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod perft;
+pub mod persist;
+pub mod playback;
+pub mod player;
+pub mod position;
+pub mod prelude;
+pub mod printable;
+pub mod profile;
+pub mod puzzle;
+pub mod qgn;
+pub mod random;
+pub mod rating;
+pub mod record;
+pub mod registry;
+pub mod replay;
+pub mod repr;
+pub mod rules;
+pub mod search;
+pub mod season;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod session;
+pub mod simulate;
+pub mod solver;
+pub mod spectate;
+pub mod strategy;
+pub mod symmetry;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod tournament;
+pub mod trace;
+pub mod transposition;
+pub mod tune;
+pub mod ui;
+pub mod usage;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watchdog;
+
+pub use board::{Board, PlacementOutcome};
+pub use driver::GameDriver;
+pub use error::{BoardIssue, PlacementError, QuartoError};
+pub use game::{GameResult, QuartoGame};
+pub use player::{ComputerPlayer, HumanPlayer, Player};
+pub use printable::Piece;
+pub use record::{GameRecord, Turn};
+pub use strategy::Strategy;
@@ -0,0 +1,401 @@
+// The lines of the board and the attributes their pieces can share, including the 2×2 squares of the advanced rules.
+// Used to tell which line ended the game and why, and by heuristics that look at the lines still open.
+// The masks of the lines are precomputed in tables, for the layouts and for anyone else that checks lines with bits.
+
+use std::fmt;
+
+use crate::board::PIECE_SIZE;
+
+/// The existence bits of the cells of every line of `Line::ADVANCED`, in the `u128` layout of `Board::items`.
+/// Shift a mask left by 4 to 7 for the attribute bits of the cells.
+pub const CELL_MASKS: [u128; 19] = cell_masks();
+/// The indices of every line of `Line::ADVANCED`, bit `i` for index `i`.
+pub const INDEX_MASKS: [u16; 19] = index_masks();
+/// The lines through every index, bit `l` for `Line::ADVANCED[l]`.
+pub const LINES_THROUGH: [u32; 16] = lines_through();
+/// The bits of `LINES_THROUGH` for the rows, the columns and the diagonals, the lines of `Line::ALL`.
+pub const CLASSIC_LINES: u32 = 0b11_1111_1111;
+/// The bits of `LINES_THROUGH` for the diagonals.
+pub const DIAGONALS: u32 = 0b11_0000_0000;
+
+/// The existence bit of `index` in the `u128` layout of `Board::items`, index 0 in the most significant byte.
+pub const fn cell_bit(index: u8) -> u128 {
+    1 << (PIECE_SIZE * (15 - index))
+}
+
+/// Generate `CELL_MASKS`.
+const fn cell_masks() -> [u128; 19] {
+    let mut masks = [0; 19];
+    let mut line = 0;
+    while line < 19 {
+        masks[line] = Line::ADVANCED[line].cell_mask();
+        line += 1;
+    }
+    masks
+}
+
+/// Generate `INDEX_MASKS`.
+const fn index_masks() -> [u16; 19] {
+    let mut masks = [0; 19];
+    let mut line = 0;
+    while line < 19 {
+        masks[line] = Line::ADVANCED[line].index_mask();
+        line += 1;
+    }
+    masks
+}
+
+/// Generate `LINES_THROUGH` from `INDEX_MASKS`.
+const fn lines_through() -> [u32; 16] {
+    let masks = index_masks();
+    let mut through = [0; 16];
+    let mut line = 0;
+    while line < 19 {
+        let mut index = 0;
+        while index < 16 {
+            if masks[line] & (1 << index) != 0 {
+                through[index] |= 1 << line;
+            }
+            index += 1;
+        }
+        line += 1;
+    }
+    through
+}
+
+/// A row, column, diagonal, 2×2 square or other group of four cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Line {
+    /// A row from 0 (top) to 3 (bottom).
+    Row(u8),
+    /// A column from 0 (left) to 3 (right).
+    Column(u8),
+    /// The diagonal from the top left to the bottom right.
+    DiagonalDown,
+    /// The diagonal from the bottom left to the top right.
+    DiagonalUp,
+    /// The 2×2 square with its top left cell on column `x` and row `y`, both from 0 to 2.
+    Square(u8, u8),
+    /// Any four cells, for the custom rules of a `CellRule`.
+    Cells([u8; 4]),
+}
+
+impl Line {
+    /// All ten lines: the rows, the columns and then the diagonals.
+    pub const ALL: [Line; 10] = [
+        Line::Row(0),
+        Line::Row(1),
+        Line::Row(2),
+        Line::Row(3),
+        Line::Column(0),
+        Line::Column(1),
+        Line::Column(2),
+        Line::Column(3),
+        Line::DiagonalDown,
+        Line::DiagonalUp,
+    ];
+
+    /// The ten lines and then the nine squares, which all win with the advanced rules.
+    pub const ADVANCED: [Line; 19] = [
+        Line::Row(0),
+        Line::Row(1),
+        Line::Row(2),
+        Line::Row(3),
+        Line::Column(0),
+        Line::Column(1),
+        Line::Column(2),
+        Line::Column(3),
+        Line::DiagonalDown,
+        Line::DiagonalUp,
+        Line::Square(0, 0),
+        Line::Square(1, 0),
+        Line::Square(2, 0),
+        Line::Square(0, 1),
+        Line::Square(1, 1),
+        Line::Square(2, 1),
+        Line::Square(0, 2),
+        Line::Square(1, 2),
+        Line::Square(2, 2),
+    ];
+
+    /// The board indices of the cells on the line.
+    pub const fn indices(self) -> [u8; 4] {
+        match self {
+            Line::Row(row) => [row * 4, row * 4 + 1, row * 4 + 2, row * 4 + 3],
+            Line::Column(column) => [column, column + 4, column + 8, column + 12],
+            Line::DiagonalDown => [0, 5, 10, 15],
+            Line::DiagonalUp => [12, 9, 6, 3],
+            Line::Square(x, y) => {
+                let corner = y * 4 + x;
+                [corner, corner + 1, corner + 4, corner + 5]
+            }
+            Line::Cells(cells) => cells,
+        }
+    }
+
+    /// The position of the line in `Line::ADVANCED`, and so in `CELL_MASKS`, `INDEX_MASKS` and `LINES_THROUGH`.
+    /// Only the lines of the built-in rules have one, `None` for `Line::Cells` and for rows, columns or squares off the board.
+    pub const fn position(self) -> Option<usize> {
+        match self {
+            Line::Row(row) if row < 4 => Some(row as usize),
+            Line::Column(column) if column < 4 => Some(4 + column as usize),
+            Line::DiagonalDown => Some(8),
+            Line::DiagonalUp => Some(9),
+            Line::Square(x, y) if x < 3 && y < 3 => Some(10 + 3 * y as usize + x as usize),
+            _ => None,
+        }
+    }
+
+    /// The existence bits of the cells on the line, in the `u128` layout of `Board::items`.
+    pub const fn cell_mask(self) -> u128 {
+        let [a, b, c, d] = self.indices();
+        cell_bit(a) | cell_bit(b) | cell_bit(c) | cell_bit(d)
+    }
+
+    /// The indices on the line, bit `i` for index `i`.
+    pub const fn index_mask(self) -> u16 {
+        let [a, b, c, d] = self.indices();
+        (1 << a) | (1 << b) | (1 << c) | (1 << d)
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Row(row) => write!(f, "row {}", row),
+            Line::Column(column) => write!(f, "column {}", column),
+            Line::DiagonalDown => write!(f, "down diagonal"),
+            Line::DiagonalUp => write!(f, "up diagonal"),
+            Line::Square(x, y) => write!(f, "square at column {} row {}", x, y),
+            Line::Cells([a, b, c, d]) => write!(f, "cells {}, {}, {} and {}", a, b, c, d),
+        }
+    }
+}
+
+/// One of the four attributes of a piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Hole,
+    Square,
+    High,
+    Dark,
+}
+
+impl Attribute {
+    /// All attributes, from the highest bit of a piece number to the lowest.
+    pub const ALL: [Attribute; 4] = [
+        Attribute::Hole,
+        Attribute::Square,
+        Attribute::High,
+        Attribute::Dark,
+    ];
+
+    /// The bit of the attribute in a piece number.
+    pub fn bit(self) -> u8 {
+        match self {
+            Attribute::Hole => 8,
+            Attribute::Square => 4,
+            Attribute::High => 2,
+            Attribute::Dark => 1,
+        }
+    }
+
+    /// The word for a piece with or without the attribute.
+    pub fn describe(self, present: bool) -> &'static str {
+        match (self, present) {
+            (Attribute::Hole, true) => "hole",
+            (Attribute::Hole, false) => "solid",
+            (Attribute::Square, true) => "square",
+            (Attribute::Square, false) => "round",
+            (Attribute::High, true) => "high",
+            (Attribute::High, false) => "low",
+            (Attribute::Dark, true) => "dark",
+            (Attribute::Dark, false) => "light",
+        }
+    }
+}
+
+/// The pieces on a line, and what they still have in common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineState {
+    pub line: Line,
+    /// The piece on each cell of the line, in the order of `Line::indices`.
+    pub cells: [Option<u8>; 4],
+    /// The attribute bits every piece on the line has.
+    pub common_set: u8,
+    /// The attribute bits no piece on the line has.
+    pub common_unset: u8,
+}
+
+impl LineState {
+    /// Collect the state of `line` from the pieces on its cells.
+    pub fn new(line: Line, cells: [Option<u8>; 4]) -> Self {
+        let pieces = cells.iter().flatten();
+        LineState {
+            line,
+            cells,
+            common_set: pieces.clone().fold(0xf, |acc, &p| acc & p),
+            common_unset: pieces.fold(0xf, |acc, &p| acc & !p),
+        }
+    }
+
+    /// The number of pieces on the line.
+    pub fn pieces(&self) -> usize {
+        self.cells.iter().flatten().count()
+    }
+
+    /// The number of empty cells on the line.
+    pub fn empties(&self) -> usize {
+        4 - self.pieces()
+    }
+
+    /// The attributes all pieces on the line agree on, and whether they all have or all lack them.
+    /// An attribute is listed once, as present, on an empty line.
+    pub fn shared(&self) -> Vec<(Attribute, bool)> {
+        Attribute::ALL
+            .into_iter()
+            .filter_map(|a| {
+                if self.common_set & a.bit() != 0 {
+                    Some((a, true))
+                } else if self.common_unset & a.bit() != 0 {
+                    Some((a, false))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Check if the line can still be won, because its pieces have an attribute in common.
+    pub fn is_open(&self) -> bool {
+        self.common_set | self.common_unset != 0
+    }
+
+    /// Check if placing `piece` on the last empty cell of the line wins.
+    pub fn completed_by(&self, piece: u8) -> bool {
+        self.empties() == 1 && (self.common_set & piece) | (self.common_unset & !piece) != 0
+    }
+
+    /// The index of the first empty cell on the line, if any.
+    pub fn first_empty(&self) -> Option<u8> {
+        self.line
+            .indices()
+            .into_iter()
+            .zip(self.cells)
+            .find_map(|(index, cell)| cell.is_none().then_some(index))
+    }
+
+    /// The winning line this is, if the line is full and its pieces share an attribute.
+    pub fn winning(&self) -> Option<WinningLine> {
+        (self.empties() == 0 && self.is_open()).then(|| WinningLine {
+            line: self.line,
+            shared: self.shared(),
+        })
+    }
+}
+
+/// A full line whose pieces share at least one attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinningLine {
+    pub line: Line,
+    /// The shared attributes, and whether all pieces have them or all pieces lack them.
+    pub shared: Vec<(Attribute, bool)>,
+}
+
+impl fmt::Display for WinningLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: Vec<&str> = self
+            .shared
+            .iter()
+            .map(|&(attribute, present)| attribute.describe(present))
+            .collect();
+        write!(f, "{}: all {}", self.line, words.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_cover_the_board() {
+        let mut counts = [0; 16];
+        for line in Line::ALL {
+            for index in line.indices() {
+                counts[index as usize] += 1;
+            }
+        }
+        // Every cell is on a row and a column, the diagonals pass eight of them.
+        assert_eq!(counts.iter().sum::<i32>(), 40);
+        assert_eq!(counts[0], 3);
+        assert_eq!(counts[1], 2);
+        assert_eq!(Line::Column(2).indices(), [2, 6, 10, 14]);
+        assert_eq!(Line::Square(2, 1).indices(), [6, 7, 10, 11]);
+    }
+
+    #[test]
+    fn test_mask_tables() {
+        assert_eq!(INDEX_MASKS[0], 0b1111);
+        assert_eq!(INDEX_MASKS[9], 0b0001_0010_0100_1000);
+        assert_eq!(CELL_MASKS[Line::Row(3).position().unwrap()], 0x01010101);
+        assert_eq!(
+            CELL_MASKS[Line::Square(0, 1).position().unwrap()],
+            cell_bit(4) | cell_bit(5) | cell_bit(8) | cell_bit(9)
+        );
+        for (position, line) in Line::ADVANCED.into_iter().enumerate() {
+            assert_eq!(line.position(), Some(position));
+        }
+        assert_eq!(Line::Row(4).position(), None);
+        assert_eq!(Line::Square(3, 0).position(), None);
+        assert_eq!(Line::Cells([0, 1, 2, 3]).position(), None);
+        // Index 5 is on row 1, column 1, the down diagonal and the four squares around it.
+        assert_eq!(LINES_THROUGH[5] & CLASSIC_LINES, 0b01_0010_0010);
+        assert_eq!((LINES_THROUGH[5] >> 10).count_ones(), 4);
+        assert_eq!(LINES_THROUGH[3] & DIAGONALS, 0b10_0000_0000);
+    }
+
+    fn full(line: Line, pieces: [u8; 4]) -> LineState {
+        LineState::new(line, pieces.map(Some))
+    }
+
+    #[test]
+    fn test_winning() {
+        let line = full(Line::Row(0), [8, 12, 10, 9]).winning().unwrap();
+        assert_eq!(line.shared, vec![(Attribute::Hole, true)]);
+        assert_eq!(line.to_string(), "row 0: all hole");
+        let line = full(Line::DiagonalUp, [0, 2, 4, 6]).winning().unwrap();
+        assert_eq!(
+            line.shared,
+            vec![(Attribute::Hole, false), (Attribute::Dark, false)]
+        );
+        assert_eq!(line.to_string(), "up diagonal: all solid, light");
+        assert_eq!(full(Line::Row(0), [8, 4, 2, 1]).winning(), None);
+        assert_eq!(
+            LineState::new(Line::Row(0), [Some(8), Some(12), Some(10), None]).winning(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_partial_line() {
+        let empty = LineState::new(Line::Column(1), [None; 4]);
+        assert_eq!(empty.empties(), 4);
+        assert!(empty.is_open());
+        assert_eq!(empty.shared().len(), 4);
+        assert_eq!(empty.first_empty(), Some(1));
+
+        let state = LineState::new(Line::Column(1), [Some(8), None, Some(10), Some(9)]);
+        assert_eq!(state.pieces(), 3);
+        assert_eq!(
+            state.shared(),
+            vec![(Attribute::Hole, true), (Attribute::Square, false)]
+        );
+        assert_eq!(state.first_empty(), Some(5));
+        assert!(state.completed_by(12));
+        assert!(!state.completed_by(4));
+
+        let blocked = LineState::new(Line::Row(2), [Some(15), Some(0), None, None]);
+        assert!(!blocked.is_open());
+        assert!(!blocked.completed_by(3));
+    }
+}
@@ -0,0 +1,1067 @@
+// The lobby of a server that hosts games: clients enter it under a name, and leave it paired into a game.
+// A player challenges a waiting player, who accepts by challenging back, asks for a game against a bot,
+// or resumes the session of a game in progress after losing the connection. Anyone can watch a game in progress,
+// and hears what its players say to each other. Games are only kept in memory, a restart of the server ends them.
+// The games between two players are rated in the seasons of the lobby, which anyone can ask the standings of,
+// and get a fair-play review that flags implausibly accurate decisions for a moderator.
+// Anyone can browse the waiting players, the games in progress and the recent results, a page at a time.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        mpsc::{self, Sender},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "tls")]
+use rustls::ServerConfig;
+
+use crate::{
+    browse::{GameListKind, GameSummary, Page},
+    clock::TimeControl,
+    fairplay::{FairPlayConfig, Review},
+    game::GameResult,
+    network::{
+        self, Chat, Connection, Heartbeat, HostedGame, Link, NetMessage, Outbox, Relay, Seat,
+        Server,
+    },
+    player::Player,
+    record::GameRecord,
+    registry::{PlayerOptions, Registry},
+    season::{SeasonConfig, Seasons, Standings},
+};
+
+/// How long a waiting player is read before the lobby checks if someone accepted their challenge.
+const WAIT: Duration = Duration::from_millis(50);
+
+/// How long the server waits for a player that lost the connection to resume the session.
+const GRACE: Duration = Duration::from_secs(60);
+
+/// How long a client tries to resume its session after losing the connection.
+const RECONNECT: Duration = Duration::from_secs(30);
+
+/// How many finished games the lobby lists as recent.
+const RECENT: usize = 100;
+
+/// A player in the lobby, with the way to hand them the name and connection of the opponent that accepted
+/// their challenge.
+struct Waiting {
+    connection: Arc<Connection>,
+    paired: Sender<(String, Arc<Connection>)>,
+    since: Instant,
+}
+
+/// The players in the lobby by name, the challenges between them as (challenger, challenged),
+/// the links of the clients in the games in progress by session, those games by id with their relay,
+/// and the games that finished recently with their result, the last one first.
+#[derive(Default)]
+struct State {
+    waiting: BTreeMap<String, Waiting>,
+    challenges: BTreeSet<(String, String)>,
+    sessions: BTreeMap<u64, Arc<Link>>,
+    games: BTreeMap<u64, (HostedGame, Arc<Relay>)>,
+    finished: VecDeque<(HostedGame, GameResult)>,
+    last_game: u64,
+}
+
+impl State {
+    /// Take `name` out of the lobby, with the challenges from and to them.
+    fn leave(&mut self, name: &str) -> Option<Waiting> {
+        self.challenges.retain(|(a, b)| a != name && b != name);
+        self.waiting.remove(name)
+    }
+
+    /// The names of the waiting players other than `name`.
+    fn others(&self, name: Option<&str>) -> Vec<String> {
+        self.waiting
+            .keys()
+            .filter(|other| Some(other.as_str()) != name)
+            .cloned()
+            .collect()
+    }
+
+    /// The games of a listing of `kind`: the players that wait longest first, the games in progress by id,
+    /// and the last finished game first.
+    fn browse(&self, kind: GameListKind) -> Vec<GameSummary> {
+        match kind {
+            GameListKind::Open => {
+                let mut waiting: Vec<(&String, &Waiting)> = self.waiting.iter().collect();
+                waiting.sort_by_key(|(_, waiting)| waiting.since);
+                waiting
+                    .into_iter()
+                    .map(|(name, waiting)| GameSummary {
+                        id: None,
+                        first: name.clone(),
+                        second: None,
+                        detail: waited(waiting.since.elapsed()),
+                    })
+                    .collect()
+            }
+            GameListKind::Ongoing => self
+                .games
+                .values()
+                .map(|(game, relay)| summary(game, relay.turn().to_string()))
+                .collect(),
+            GameListKind::Recent => self
+                .finished
+                .iter()
+                .map(|(game, result)| {
+                    let detail = match *result {
+                        GameResult::Win(seat) => format!("{} won", game.players[seat % 2]),
+                        GameResult::Timeout(seat) => {
+                            format!("{} ran out of time", game.players[seat % 2])
+                        }
+                        GameResult::Draw => "draw".to_string(),
+                        GameResult::Error => "error".to_string(),
+                    };
+                    summary(game, detail)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How long a player waits, in the minutes or the seconds for the first minute.
+fn waited(time: Duration) -> String {
+    match time.as_secs() {
+        seconds if seconds < 60 => format!("{} s", seconds),
+        seconds => format!("{} min", seconds / 60),
+    }
+}
+
+/// The summary of a hosted game for a listing.
+fn summary(game: &HostedGame, detail: String) -> GameSummary {
+    GameSummary {
+        id: Some(game.id),
+        first: game.players[0].clone(),
+        second: Some(game.players[1].clone()),
+        detail,
+    }
+}
+
+/// What the lobby does after a message of a client.
+enum Next {
+    Stay,
+    /// Play the game between the players of these names on this thread, the first seat hands over the first piece.
+    Play([Seat; 2], [String; 2]),
+    /// The connection went to a game on another thread: the challenger that was accepted, or a resumed game.
+    Handed,
+    /// The client went away.
+    Leave,
+}
+
+/// Hears the seasons after a rated game, with the record and the fair-play review of the game.
+type RatedHandler = Box<dyn Fn(&Seasons, &GameRecord, &Review) + Send + Sync>;
+
+/// The lobby of a server, where clients find an opponent for a game that is played on the server.
+pub struct Lobby {
+    state: Mutex<State>,
+    /// The options of the computer players for the bot games.
+    options: PlayerOptions,
+    /// How long a player that lost the connection has to resume the session.
+    grace: Duration,
+    /// How often the players of a game are pinged, and how long a silent one is waited for.
+    heartbeat: Heartbeat,
+    /// The time limits of the games, unlimited by default.
+    control: TimeControl,
+    /// The ratings of the players, by season.
+    seasons: Mutex<Seasons>,
+    /// Hears the seasons after every rated game, to save them.
+    rated: Option<RatedHandler>,
+    /// The thresholds of the fair-play review of the rated games.
+    fair_play: FairPlayConfig,
+    /// The certificate to accept clients over TLS with, or `None` for plain WebSockets.
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<ServerConfig>>,
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lobby {
+    /// Create an empty lobby, with the default options for the bots, a grace period of a minute,
+    /// and seasons that start from scratch.
+    pub fn new() -> Self {
+        Lobby {
+            state: Mutex::default(),
+            options: PlayerOptions::default(),
+            grace: GRACE,
+            heartbeat: Heartbeat::default(),
+            control: TimeControl::new(),
+            seasons: Mutex::new(Seasons::new(SeasonConfig::default(), now())),
+            rated: None,
+            fair_play: FairPlayConfig::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Make the bots with `options`, like a deeper search.
+    pub fn with_options(mut self, options: PlayerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Give a player that lost the connection `grace` to resume the session, before they forfeit.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Ping the players of a game as `heartbeat` says, a player that stops answering is treated like one
+    /// that lost the connection.
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Time the players of every game by `control`, a player who goes over loses on time.
+    pub fn with_time_control(mut self, control: TimeControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// Rate the games in `seasons`, like the seasons saved by an earlier run of the server.
+    pub fn with_seasons(mut self, seasons: Seasons) -> Self {
+        self.seasons = Mutex::new(seasons);
+        self
+    }
+
+    /// Review the decisions of the players in rated games with `config`.
+    pub fn with_fair_play(mut self, config: FairPlayConfig) -> Self {
+        self.fair_play = config;
+        self
+    }
+
+    /// Call `rated` after every rated game with the seasons, the record of the game and its fair-play review,
+    /// like to save them.
+    pub fn on_rated(
+        mut self,
+        rated: impl Fn(&Seasons, &GameRecord, &Review) + Send + Sync + 'static,
+    ) -> Self {
+        self.rated = Some(Box::new(rated));
+        self
+    }
+
+    /// Rate the game of `record` between the players `names` in the season it ends in, and review it.
+    fn rate(&self, names: &[String; 2], result: GameResult, record: &GameRecord) {
+        if result == GameResult::Error {
+            return;
+        }
+        let review = Review::new(record, &[0, 1], &self.fair_play);
+        let mut seasons = self.seasons.lock().unwrap_or_else(|e| e.into_inner());
+        seasons.record_result(&names[0], &names[1], result, now());
+        if let Some(rated) = &self.rated {
+            rated(&seasons, record, &review);
+        }
+    }
+
+    /// The standings of `season`, or of the current season with `None`.
+    pub fn standings(&self, season: Option<u32>) -> Option<Standings> {
+        let mut seasons = self.seasons.lock().unwrap_or_else(|e| e.into_inner());
+        seasons.rollover(now());
+        seasons.standings(season)
+    }
+
+    /// Accept the clients over TLS with the certificate of `config`.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, config: Arc<ServerConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// A poisoned lock still holds a usable lobby.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The names of the players waiting for an opponent.
+    pub fn waiting(&self) -> Vec<String> {
+        self.lock().others(None)
+    }
+
+    /// The games in progress, by id.
+    pub fn playing(&self) -> Vec<HostedGame> {
+        self.lock()
+            .games
+            .values()
+            .map(|(game, _)| game.clone())
+            .collect()
+    }
+
+    /// Welcome the clients that connect to `listener` in the lobby, every client on its own thread.
+    pub fn host(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let lobby = self.clone();
+            thread::spawn(move || {
+                if let Ok(connection) = stream.and_then(|stream| lobby.accept(stream)) {
+                    lobby.enter(Arc::new(connection));
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Accept the WebSocket of a client on `stream`, over TLS if the lobby has a certificate.
+    fn accept(&self, stream: TcpStream) -> io::Result<Connection> {
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            return Connection::accept_tls(stream, config.clone());
+        }
+        Connection::accept(stream)
+    }
+
+    /// Serve a client in the lobby until it is paired into a game, and play that game if this thread hosts it.
+    pub fn enter(&self, connection: Arc<Connection>) -> Option<GameResult> {
+        let (paired, opponent) = mpsc::channel::<(String, _)>();
+        let mut name: Option<String> = None;
+        let next = loop {
+            if let Ok((other, opponent)) = opponent.try_recv() {
+                // The challenge was accepted, the challenger hands over the first piece.
+                let me = name.clone().unwrap_or_default();
+                break Next::Play(
+                    [
+                        self.link(0, connection.clone(), &me),
+                        self.link(1, opponent, &other),
+                    ],
+                    [me, other],
+                );
+            }
+            let next = match connection.receive(Some(WAIT)) {
+                Ok(None) => continue,
+                Ok(Some(message)) => self.handle(message, &mut name, &connection, &paired),
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(e.to_string()),
+                Err(_) => break Next::Leave,
+            };
+            match next {
+                Ok(Next::Stay) => {}
+                Ok(next) => break next,
+                Err(e) => {
+                    if connection.send(&NetMessage::Error(e)).is_err() {
+                        break Next::Leave;
+                    }
+                }
+            }
+        };
+        match next {
+            Next::Play(seats, players) => {
+                let sessions: Vec<u64> = seats
+                    .iter()
+                    .filter_map(|seat| match seat {
+                        Seat::Remote(link) => Some(link.session),
+                        Seat::Computer(_) => None,
+                    })
+                    .collect();
+                // Only the games between two players are rated, not those against a bot.
+                let rated = sessions.len() == 2;
+                let relay = Arc::new(Relay::new());
+                let id = {
+                    let mut state = self.lock();
+                    state.last_game += 1;
+                    let id = state.last_game;
+                    let game = HostedGame {
+                        id,
+                        players: players.clone(),
+                    };
+                    state.games.insert(id, (game, relay.clone()));
+                    id
+                };
+                let (result, record) = network::play_hosted(seats, self.grace, self.control, relay);
+                {
+                    let mut state = self.lock();
+                    for session in sessions {
+                        state.sessions.remove(&session);
+                    }
+                    if let Some((game, _)) = state.games.remove(&id) {
+                        state.finished.push_front((game, result));
+                        state.finished.truncate(RECENT);
+                    }
+                }
+                if rated {
+                    self.rate(&players, result, &record);
+                }
+                Some(result)
+            }
+            Next::Stay | Next::Handed => None,
+            Next::Leave => {
+                if let Some(name) = name {
+                    self.lock().leave(&name);
+                }
+                None
+            }
+        }
+    }
+
+    /// Link `connection` of the player `name` to `seat` of a game, under a session it can resume.
+    fn link(&self, seat: usize, connection: Arc<Connection>, name: &str) -> Seat {
+        let link = Link::new(seat, connection)
+            .with_name(name)
+            .with_heartbeat(self.heartbeat);
+        let link = Arc::new(link);
+        self.lock().sessions.insert(link.session, link.clone());
+        Seat::Remote(link)
+    }
+
+    /// Answer a message of the client `name`, who has no name until they said hello.
+    fn handle(
+        &self,
+        message: NetMessage,
+        name: &mut Option<String>,
+        connection: &Arc<Connection>,
+        paired: &Sender<(String, Arc<Connection>)>,
+    ) -> Result<Next, String> {
+        let mut state = self.lock();
+        let (message, me) = match (message, name.as_deref()) {
+            (NetMessage::List, me) => {
+                send(connection, &NetMessage::Waiting(state.others(me)))?;
+                let games = state.games.values().map(|(game, _)| game.clone());
+                return send(connection, &NetMessage::Playing(games.collect()));
+            }
+            (NetMessage::Ping, _) => return send(connection, &NetMessage::Pong),
+            (NetMessage::Pong, _) => return Ok(Next::Stay),
+            (
+                NetMessage::Browse {
+                    kind,
+                    page,
+                    per_page,
+                },
+                _,
+            ) => {
+                let page = Page::of(state.browse(kind), page, per_page)?;
+                for game in page.games {
+                    send(connection, &NetMessage::Game(game))?;
+                }
+                return send(
+                    connection,
+                    &NetMessage::Paged {
+                        page: page.page,
+                        pages: page.pages,
+                    },
+                );
+            }
+            (NetMessage::Standings(season), _) => {
+                // The current season always has standings, only a past one can be missing.
+                let standings = self
+                    .standings(season)
+                    .ok_or(format!("there is no season {}", season.unwrap_or_default()))?;
+                return send(connection, &NetMessage::Season(standings));
+            }
+            (NetMessage::Watch(id), me) => {
+                let (_, relay) = state
+                    .games
+                    .get(&id)
+                    .ok_or(format!("there is no game {} in progress", id))?;
+                relay.watch(connection.clone()).map_err(|e| e.to_string())?;
+                // A spectator leaves the lobby, only the relay writes to them from now on.
+                if let Some(me) = me {
+                    state.leave(me);
+                }
+                return Ok(Next::Handed);
+            }
+            (NetMessage::Hello(_), Some(me)) => {
+                return Err(format!("you are already in the lobby as {}", me));
+            }
+            (NetMessage::Hello(new), None) => {
+                if state.waiting.contains_key(&new) {
+                    return Err(format!("the name {} is taken", new));
+                }
+                state.waiting.insert(
+                    new.clone(),
+                    Waiting {
+                        connection: connection.clone(),
+                        paired: paired.clone(),
+                        since: Instant::now(),
+                    },
+                );
+                let others = NetMessage::Waiting(state.others(Some(&new)));
+                *name = Some(new);
+                return send(connection, &others);
+            }
+            (NetMessage::Resume(session), None) => {
+                let link = state
+                    .sessions
+                    .get(&session)
+                    .ok_or(format!("there is no game with session {:016x}", session))?;
+                link.replace(connection.clone())
+                    .map_err(|e| e.to_string())?;
+                return Ok(Next::Handed);
+            }
+            (_, None) => return Err("say hello with your name first".to_string()),
+            (message, Some(me)) => (message, me.to_string()),
+        };
+        match message {
+            NetMessage::Challenge(other) => {
+                if other == me || !state.waiting.contains_key(&other) {
+                    return Err(format!("{} is not waiting for an opponent", other));
+                }
+                if state.challenges.contains(&(other.clone(), me.clone())) {
+                    // Accepted: the challenger hosts the game on their thread.
+                    let challenger = state.leave(&other).expect("the challenger is waiting");
+                    state.leave(&me);
+                    challenger.paired.send((me, connection.clone())).ok();
+                    return Ok(Next::Handed);
+                }
+                let challenged = state.waiting[&other].connection.clone();
+                state.challenges.insert((me.clone(), other));
+                send(&challenged, &NetMessage::Challenged(me)).ok();
+                Ok(Next::Stay)
+            }
+            NetMessage::Bot(bot) => {
+                let player = Registry::default().create_computer(&bot, &self.options)?;
+                state.leave(&me);
+                drop(state);
+                Ok(Next::Play(
+                    [
+                        self.link(0, connection.clone(), &me),
+                        Seat::Computer(player),
+                    ],
+                    [me, bot],
+                ))
+            }
+            message => Err(format!("{} cannot be done in the lobby", message)),
+        }
+    }
+}
+
+/// The time in seconds since the Unix epoch, that seasons are dated in.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+/// Send `message` and stay in the lobby.
+fn send(connection: &Connection, message: &NetMessage) -> Result<Next, String> {
+    connection.send(message).map_err(|e| e.to_string())?;
+    Ok(Next::Stay)
+}
+
+/// The opponent a client looks for in the lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Seek {
+    /// Wait, and accept the first challenge.
+    Anyone,
+    /// Challenge a waiting player.
+    Challenge(String),
+    /// Play against a computer player of the registry.
+    Bot(String),
+    /// Take the seat of a game in progress again, by its session.
+    Resume(u64),
+}
+
+/// The players waiting in the lobby of `server`, and the games in progress.
+pub fn list(server: &Server) -> io::Result<(Vec<String>, Vec<HostedGame>)> {
+    let connection = server.connect()?;
+    connection.send(&NetMessage::List)?;
+    let mut waiting = None;
+    let answer = loop {
+        match connection.receive(None)? {
+            Some(NetMessage::Waiting(names)) => waiting = Some(names),
+            Some(NetMessage::Playing(games)) => break Ok((waiting.unwrap_or_default(), games)),
+            Some(NetMessage::Error(e)) => break Err(io::Error::other(e)),
+            _ => {}
+        }
+    };
+    connection.close();
+    answer
+}
+
+/// Page `page` of the games of `kind` on `server`, `per_page` games to a page.
+pub fn browse(server: &Server, kind: GameListKind, page: u32, per_page: u32) -> io::Result<Page> {
+    let connection = server.connect()?;
+    connection.send(&NetMessage::Browse {
+        kind,
+        page,
+        per_page,
+    })?;
+    let mut games = Vec::new();
+    let answer = loop {
+        match connection.receive(None)? {
+            Some(NetMessage::Game(game)) => games.push(game),
+            Some(NetMessage::Paged { page, pages }) => break Ok(Page { page, pages, games }),
+            Some(NetMessage::Error(e)) => break Err(io::Error::other(e)),
+            _ => {}
+        }
+    };
+    connection.close();
+    answer
+}
+
+/// The standings of `season` on `server`, or of its current season with `None`.
+pub fn standings(server: &Server, season: Option<u32>) -> io::Result<Standings> {
+    let connection = server.connect()?;
+    connection.send(&NetMessage::Standings(season))?;
+    let answer = loop {
+        match connection.receive(None)? {
+            Some(NetMessage::Season(standings)) => break Ok(standings),
+            Some(NetMessage::Error(e)) => break Err(io::Error::other(e)),
+            _ => {}
+        }
+    };
+    connection.close();
+    answer
+}
+
+/// Watch game `id` on `server` without playing, from the moves so far until it ends.
+/// `show` hears every message of the server. Returns the result of the game.
+pub fn watch(
+    server: &Server,
+    id: u64,
+    mut show: impl FnMut(&NetMessage),
+) -> io::Result<GameResult> {
+    let connection = server.connect()?;
+    connection.send(&NetMessage::Watch(id))?;
+    loop {
+        let Some(message) = connection.receive(None)? else {
+            continue;
+        };
+        show(&message);
+        match message {
+            NetMessage::Over(result) => return Ok(result),
+            NetMessage::Error(e) => {
+                connection.close();
+                return Err(io::Error::other(e));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Wait on `connection` for the welcome to a game, and return the session. A client that seeks anyone
+/// accepts the first challenge, an error of the server ends the wait.
+fn welcome(
+    connection: &Connection,
+    seek: &Seek,
+    show: &mut impl FnMut(&NetMessage),
+) -> io::Result<u64> {
+    loop {
+        let Some(message) = connection.receive(None)? else {
+            continue;
+        };
+        show(&message);
+        match message {
+            NetMessage::Welcome { session, .. } => return Ok(session),
+            NetMessage::Ping => connection.send(&NetMessage::Pong)?,
+            NetMessage::Challenged(other) if *seek == Seek::Anyone => {
+                connection.send(&NetMessage::Challenge(other))?;
+            }
+            NetMessage::Error(e) => {
+                connection.close();
+                return Err(io::Error::other(e));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Resume `session` on a new connection to `server`, trying again until `deadline`.
+/// A refusal of the server, like a session that is over, is not tried again.
+fn reconnect(
+    server: &Server,
+    session: u64,
+    deadline: Instant,
+    show: &mut impl FnMut(&NetMessage),
+) -> io::Result<Connection> {
+    loop {
+        let attempt = server.connect().and_then(|connection| {
+            connection.send(&NetMessage::Resume(session))?;
+            Ok(connection)
+        });
+        match attempt {
+            Ok(connection) => {
+                welcome(&connection, &Seek::Resume(session), show)?;
+                return Ok(connection);
+            }
+            Err(e) if Instant::now() >= deadline => return Err(e),
+            Err(_) => thread::sleep(WAIT * 10),
+        }
+    }
+}
+
+/// Enter the lobby of `server` as `name`, find the opponent of `seek`, and play the game
+/// with `player` deciding for this side, chatting on `chat`. `show` hears every other message of the server.
+/// A lost connection is resumed for a while, so the game goes on from the position it was in,
+/// and the decisions the server may not have taken are sent again.
+pub fn join(
+    server: &Server,
+    name: &str,
+    seek: &Seek,
+    player: &dyn Player,
+    chat: &Chat,
+    mut show: impl FnMut(&NetMessage),
+) -> io::Result<GameResult> {
+    let mut connection = Arc::new(server.connect()?);
+    match seek {
+        Seek::Resume(session) => connection.send(&NetMessage::Resume(*session))?,
+        _ => connection.send(&NetMessage::Hello(name.to_string()))?,
+    }
+    match seek {
+        Seek::Anyone | Seek::Resume(_) => {}
+        Seek::Challenge(other) => connection.send(&NetMessage::Challenge(other.clone()))?,
+        Seek::Bot(bot) => connection.send(&NetMessage::Bot(bot.clone()))?,
+    }
+    let session = welcome(&connection, seek, &mut show)?;
+    let mut outbox = Outbox::new();
+    loop {
+        match network::play(&connection, player, chat, &mut outbox, &mut show) {
+            Err(e) if e.kind() != io::ErrorKind::Interrupted => {
+                let deadline = Instant::now() + RECONNECT;
+                connection = Arc::new(reconnect(server, session, deadline, &mut show)?);
+                // A resend that fails loses the new connection too, which the game finds out.
+                outbox.resend(&connection).ok();
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, player::ComputerPlayer, strategy::DeterministicStrategy};
+
+    /// A computer player that wishes good luck when it hands over its first piece.
+    struct Chatty {
+        chat: Chat,
+        player: ComputerPlayer<DeterministicStrategy>,
+    }
+
+    impl Player for Chatty {
+        fn get_piece(&self, board: &Board) -> Option<u8> {
+            if board.empty_spaces().len() == 16 {
+                self.chat.send_chat("good  luck\n").unwrap();
+            }
+            self.player.get_piece(board)
+        }
+
+        fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+            self.player.get_move(board, piece)
+        }
+
+        fn quarto(&self, board: &Board) -> bool {
+            self.player.quarto(board)
+        }
+    }
+
+    /// A lobby with quick bots.
+    fn lobby() -> Lobby {
+        Lobby::new().with_options(PlayerOptions {
+            depth: 1,
+            ..PlayerOptions::default()
+        })
+    }
+
+    /// Host `lobby` on a free port of localhost, and return its address.
+    fn host_lobby(lobby: Lobby) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || Arc::new(lobby).host(listener));
+        address
+    }
+
+    /// Host a lobby on a free port of localhost, and return the server.
+    fn host() -> Server {
+        Server::new(&host_lobby(lobby()))
+    }
+
+    #[test]
+    fn test_challenge_in_the_lobby() {
+        let (sender, rated) = mpsc::channel();
+        let lobby = lobby().on_rated(move |_, record, review| {
+            sender.send((record.clone(), review.clone())).unwrap()
+        });
+        let address = Server::new(&host_lobby(lobby));
+        let alice = {
+            let address = address.clone();
+            thread::spawn(move || {
+                let player = ComputerPlayer::new(DeterministicStrategy);
+                join(
+                    &address,
+                    "alice",
+                    &Seek::Anyone,
+                    &player,
+                    &Chat::new(),
+                    |_| {},
+                )
+                .unwrap()
+            })
+        };
+        while list(&address).unwrap().0 != ["alice"] {
+            thread::sleep(WAIT);
+        }
+        let open = browse(&address, GameListKind::Open, 1, 20).unwrap();
+        assert_eq!(open.games.len(), 1);
+        assert_eq!(
+            (open.games[0].first.as_str(), open.games[0].second.as_ref()),
+            ("alice", None)
+        );
+        assert!(browse(&address, GameListKind::Open, 0, 20).is_err());
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let taken = join(
+            &address,
+            "alice",
+            &Seek::Bot("smart".to_string()),
+            &player,
+            &Chat::new(),
+            |_| {},
+        );
+        assert!(taken.unwrap_err().to_string().contains("taken"));
+        let mut seen = Vec::new();
+        let challenge = Seek::Challenge("alice".to_string());
+        let result = join(
+            &address,
+            "bob",
+            &challenge,
+            &player,
+            &Chat::new(),
+            |message| seen.push(message.clone()),
+        )
+        .unwrap();
+        assert_eq!(result, alice.join().unwrap());
+        assert_ne!(result, GameResult::Error);
+        // Alice accepted the challenge of bob, who hands over the first piece.
+        assert!(
+            seen.iter()
+                .any(|message| matches!(message, NetMessage::Welcome { seat: 0, .. }))
+        );
+        assert_eq!(list(&address).unwrap(), (vec![], vec![]));
+        // The game between alice and bob is reviewed for fair play, both players, and rated.
+        let (record, review) = rated.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(record.result(), Some(result));
+        assert_eq!(review.players.len(), 2);
+        assert!(rated.try_recv().is_err());
+        // The game is listed among the recent ones, with its result.
+        let recent = browse(&address, GameListKind::Recent, 1, 20).unwrap();
+        assert_eq!((recent.page, recent.pages), (1, 1));
+        assert_eq!(recent.games.len(), 1);
+        assert_eq!(recent.games[0].first, "bob");
+        assert!(recent.to_table(GameListKind::Recent).contains("alice"));
+        let current = standings(&address, None).unwrap();
+        let players: Vec<(&str, u32)> = current
+            .players
+            .iter()
+            .map(|(name, rating)| (name.as_str(), rating.games))
+            .collect();
+        assert_eq!(players.len(), 2);
+        assert!(players.contains(&("alice", 1)) && players.contains(&("bob", 1)));
+        let past = current.season - 1;
+        let error = standings(&address, Some(past)).unwrap_err();
+        assert_eq!(error.to_string(), format!("there is no season {}", past));
+    }
+
+    #[test]
+    fn test_bot_game() {
+        let address = host();
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let bot = |name: &str| Seek::Bot(name.to_string());
+        let chat = Chat::new();
+        assert!(join(&address, "carol", &bot("human"), &player, &chat, |_| {}).is_err());
+        let mut seen = Vec::new();
+        let result = join(&address, "dave", &bot("smart"), &player, &chat, |message| {
+            seen.push(message.clone())
+        })
+        .unwrap();
+        assert_ne!(result, GameResult::Error);
+        assert!(
+            seen.iter()
+                .any(|message| matches!(message, NetMessage::Welcome { seat: 0, .. }))
+        );
+        assert_eq!(seen.last(), Some(&NetMessage::Over(result)));
+    }
+
+    #[test]
+    fn test_watch_a_game_in_progress() {
+        let control = TimeControl::new().with_per_game(Duration::from_secs(600));
+        let address = Server::new(&host_lobby(lobby().with_time_control(control)));
+        let connection = address.connect().unwrap();
+        connection
+            .send(&NetMessage::Hello("frank".to_string()))
+            .unwrap();
+        connection
+            .send(&NetMessage::Bot("smart".to_string()))
+            .unwrap();
+        welcome(&connection, &Seek::Anyone, &mut |_| {}).unwrap();
+        let game = HostedGame {
+            id: 1,
+            players: ["frank".to_string(), "smart".to_string()],
+        };
+        assert_eq!(list(&address).unwrap(), (vec![], vec![game]));
+        let ongoing = browse(&address, GameListKind::Ongoing, 1, 20).unwrap();
+        assert_eq!(ongoing.games.len(), 1);
+        assert_eq!(
+            (ongoing.games[0].id, ongoing.games[0].detail.as_str()),
+            (Some(1), "1")
+        );
+        // Hand over the first piece, and wait until the server relays it.
+        while !matches!(connection.receive(None), Ok(Some(NetMessage::AskPiece(_)))) {}
+        connection
+            .send(&NetMessage::Give { ply: 0, piece: 0 })
+            .unwrap();
+        while !matches!(connection.receive(None), Ok(Some(NetMessage::Given { .. }))) {}
+        let (sender, seen) = mpsc::channel();
+        let spectator = thread::spawn(move || {
+            watch(&address, 1, |message| sender.send(message.clone()).unwrap()).unwrap()
+        });
+        // The spectator that comes in late gets the moves so far first.
+        assert_eq!(seen.recv(), Ok(NetMessage::Given { seat: 0, piece: 0 }));
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let mut outbox = Outbox::new();
+        let result = network::play(
+            &Arc::new(connection),
+            &player,
+            &Chat::new(),
+            &mut outbox,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(spectator.join().unwrap(), result);
+        let seen: Vec<NetMessage> = seen.iter().collect();
+        assert_eq!(seen.last(), Some(&NetMessage::Over(result)));
+        assert!(seen.iter().all(|message| message.spectator().is_some()));
+        // The spectator follows the clocks too, the game has a time control.
+        assert!(
+            seen.iter()
+                .any(|message| matches!(message, NetMessage::Clock(_)))
+        );
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_bot_game_over_tls() {
+        use crate::tls::{self, ClientTls};
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert, key) = (certified.cert.pem(), certified.signing_key.serialize_pem());
+        let config = tls::server_config_from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        let address = host_lobby(lobby().with_tls(config));
+        let address = address.replace("127.0.0.1", "localhost");
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let bot = Seek::Bot("smart".to_string());
+        // A client that does not trust the certificate cannot connect, nor one that does not speak TLS.
+        let untrusted = Server::new(&address).with_tls(ClientTls::new());
+        assert!(join(&untrusted, "frank", &bot, &player, &Chat::new(), |_| {}).is_err());
+        assert!(list(&Server::new(&address)).is_err());
+        // A client that pins another certificate cannot connect either.
+        let pin = tls::fingerprint(certified.cert.der());
+        let mut wrong = pin;
+        wrong[0] ^= 1;
+        let pinned =
+            |pin| Server::new(&address).with_tls(ClientTls::new().with_pinned_fingerprint(pin));
+        assert!(join(&pinned(wrong), "frank", &bot, &player, &Chat::new(), |_| {}).is_err());
+        let trusted = ClientTls::new().with_root_pem(cert.as_bytes()).unwrap();
+        let server = Server::new(&address).with_tls(trusted);
+        let result = join(&server, "frank", &bot, &player, &Chat::new(), |_| {}).unwrap();
+        assert_ne!(result, GameResult::Error);
+        let result = join(&pinned(pin), "grace", &bot, &player, &Chat::new(), |_| {}).unwrap();
+        assert_ne!(result, GameResult::Error);
+    }
+
+    #[test]
+    fn test_resume_after_a_lost_connection() {
+        let address = host();
+        let connection = address.connect().unwrap();
+        connection
+            .send(&NetMessage::Hello("erin".to_string()))
+            .unwrap();
+        connection
+            .send(&NetMessage::Bot("smart".to_string()))
+            .unwrap();
+        let session = welcome(&connection, &Seek::Anyone, &mut |_| {}).unwrap();
+        // The connection drops before the first piece is handed over.
+        connection.close();
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let mut seen = Vec::new();
+        let chat = Chat::new();
+        let result = join(
+            &address,
+            "",
+            &Seek::Resume(session),
+            &player,
+            &chat,
+            |message| seen.push(message.clone()),
+        )
+        .unwrap();
+        assert_ne!(result, GameResult::Error);
+        assert_eq!(seen[0], NetMessage::Welcome { seat: 0, session });
+        assert!(
+            seen.iter()
+                .any(|message| matches!(message, NetMessage::AskPiece(_)))
+        );
+        // The session is over with the game.
+        assert!(join(&address, "", &Seek::Resume(session), &player, &chat, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_resend_after_a_lost_connection() {
+        let address = host();
+        let connection = address.connect().unwrap();
+        connection
+            .send(&NetMessage::Hello("grace".to_string()))
+            .unwrap();
+        connection
+            .send(&NetMessage::Bot("smart".to_string()))
+            .unwrap();
+        let session = welcome(&connection, &Seek::Anyone, &mut |_| {}).unwrap();
+        while !matches!(connection.receive(None), Ok(Some(NetMessage::AskPiece(_)))) {}
+        // The connection drops just as the first piece is handed over, the decision stays in the outbox.
+        connection.close();
+        let mut outbox = Outbox::new();
+        let give = NetMessage::Give { ply: 0, piece: 5 };
+        assert!(outbox.send(&connection, give).is_err());
+        let deadline = Instant::now() + RECONNECT;
+        let connection = Arc::new(reconnect(&address, session, deadline, &mut |_| {}).unwrap());
+        outbox.resend(&connection).unwrap();
+        // The question asked again is answered from the outbox too, the server ignores the second answer.
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let mut seen = Vec::new();
+        let result = network::play(&connection, &player, &Chat::new(), &mut outbox, |message| {
+            seen.push(message.clone())
+        })
+        .unwrap();
+        assert_ne!(result, GameResult::Error);
+        assert!(seen.contains(&NetMessage::Given { seat: 0, piece: 5 }));
+        assert!(
+            !seen
+                .iter()
+                .any(|message| matches!(message, NetMessage::Error(_)))
+        );
+    }
+
+    #[test]
+    fn test_chat_during_a_game() {
+        let address = host();
+        let (sender, heard) = mpsc::channel();
+        let alice = {
+            let address = address.clone();
+            thread::spawn(move || {
+                let chat = Chat::new();
+                chat.on_chat(move |from, text| sender.send(format!("{}: {}", from, text)).unwrap());
+                let player = ComputerPlayer::new(DeterministicStrategy);
+                join(&address, "alice", &Seek::Anyone, &player, &chat, |_| {}).unwrap()
+            })
+        };
+        while list(&address).unwrap().0 != ["alice"] {
+            thread::sleep(WAIT);
+        }
+        let chat = Chat::new();
+        assert!(chat.send_chat("too early").is_err());
+        let player = Chatty {
+            chat: chat.clone(),
+            player: ComputerPlayer::new(DeterministicStrategy),
+        };
+        let challenge = Seek::Challenge("alice".to_string());
+        let result = join(&address, "bob", &challenge, &player, &chat, |_| {}).unwrap();
+        assert_eq!(result, alice.join().unwrap());
+        // Alice hears bob under his name, on one line.
+        assert_eq!(heard.try_iter().collect::<Vec<_>>(), ["bob: good luck"]);
+        assert!(chat.send_chat("too late").is_err());
+    }
+}
@@ -1,10 +1,1613 @@
-pub mod board;
-pub mod printable;
-pub mod player;
-pub mod game;
-pub mod ui;
-pub mod strategy;
-
-fn main() {
-    println!("Hello, world!");
+// The quarto command line tool, a thin layer over the library.
+
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    panic,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::{Args, Parser, Subcommand, builder::PossibleValuesParser};
+#[cfg(feature = "network")]
+use quarto::browse::GameListKind;
+use quarto::{
+    Board, GameResult, Player, QuartoGame,
+    analysis::Analyzer,
+    board::Ruleset,
+    card,
+    clock::TimeControl,
+    database::PositionDb,
+    doctor::{self, Doctor},
+    engine,
+    evaluator::{Evaluator, ThreatEvaluator},
+    events::EventBus,
+    history::{self, HistoryDb},
+    import::{self, ArchiveFormat},
+    memory::{self, MemoryBudget},
+    observer::LastGame,
+    perft,
+    playback::{self, Playback},
+    printable::{ColorMode, PrintableBoard},
+    profile::{self, Profile},
+    puzzle::{self, Puzzle, PuzzleGenerator},
+    qgn::{self, QgnExport},
+    random::SharedRng,
+    record::GameRecord,
+    registry::{PlayerOptions, Registry},
+    replay::{self, Replay},
+    simulate::{self, SimulationStats},
+    solver::Solver,
+    spectate::{SpectatorMessage, WatchView},
+    strategy::{Difficulty, DifficultyController, DifficultyFeedback},
+    transposition::{ProofTable, Replacement, TranspositionTable},
+    tune::{Parameter, TuneTarget, Tuner},
+    ui::wizard,
+    usage::Mode,
+    watchdog::Watchdog,
+};
+
+#[derive(Parser)]
+#[command(name = "quarto", about = "Play and analyse games of Quarto")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    play: PlayArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mark the moves of a saved game where a player handed over a losing piece or missed a win.
+    Analyze(AnalyzeArgs),
+    /// Enumerate all canonical positions up to a number of plies, and print statistics.
+    BuildDb(BuildDbArgs),
+    /// Print a reference card of the piece numbers and the pieces of a physical set.
+    Card(CardArgs),
+    /// Watch two computer players, with controls to pause, step, change the speed and jump to the end.
+    Demo(DemoArgs),
+    /// Check the terminal, the data directory, the profile, the history and a server, with advice for what is off.
+    Doctor(DoctorArgs),
+    /// Run a computer player as an engine, answering one JSON request per line on stdin and stdout.
+    Engine(EngineArgs),
+    /// Host games over WebSockets, with a lobby where players challenge each other or ask for a bot.
+    /// The games are kept in memory only, they end when the host stops.
+    #[cfg(feature = "network")]
+    Host(HostArgs),
+    /// Import game archives from other tools into the history, and list the games that were rejected.
+    Import(ImportArgs),
+    /// Join a game on a server that hosts games, as a human or a computer player, or list and watch its games.
+    #[cfg(feature = "network")]
+    Join(JoinArgs),
+    /// Count every sequence of moves to a depth, to check move generation and win detection and to time them.
+    Perft(PerftArgs),
+    /// Play games between two players (the default).
+    Play(PlayArgs),
+    /// Search random positions for puzzles of forced wins and print them, or solve them with --play.
+    Puzzles(PuzzlesArgs),
+    /// Step through a saved game move by move, full-screen with the tui feature.
+    Replay(ReplayArgs),
+    /// Serve a REST API over HTTP to play games against the engine, with JSON bodies.
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+    /// Answer the questions of the first-run wizard again, and save the profile.
+    Setup,
+    /// Play many games between two strategies and report statistics.
+    Simulate(SimulateArgs),
+    /// Prove the outcome of every placement of a piece.
+    Solve(SolveArgs),
+    /// Show the ranked players of a season on a server.
+    #[cfg(feature = "network")]
+    Standings(StandingsArgs),
+    /// Export a summary of your games from your profile. It is only shared when you send it yourself.
+    Stats(StatsArgs),
+    /// Show the memory budget of the caches, set in MiB with $QUARTO_MEMORY, and the memory in use.
+    Status,
+    /// Tune the parameters of a strategy by playing perturbed copies of them against each other.
+    Tune(TuneArgs),
+}
+
+/// Accept the names of the players in the registry.
+fn player_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(Registry::default().names())
+}
+
+/// Options of the computer strategies.
+#[derive(Args, Clone)]
+struct StrategyArgs {
+    /// The number of turns the smart strategy looks ahead.
+    #[arg(long, default_value_t = 2)]
+    depth: u8,
+    /// The number of playouts of the MCTS strategy per decision.
+    #[arg(long, default_value_t = 5000)]
+    iterations: u32,
+    /// Spread the search of the smart strategy over all cores.
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    parallel: bool,
+    /// The command line of the external player, a program that speaks the protocol of the engine command.
+    #[arg(long)]
+    engine: Option<String>,
+    /// Score the positions where the smart strategy stops searching with this ONNX model.
+    #[cfg(feature = "onnx")]
+    #[arg(long, value_parser = load_model)]
+    model: Option<Arc<dyn Evaluator>>,
+    /// Score the positions where the smart strategy stops searching with these threat weights, as printed by tune.
+    #[arg(long, allow_hyphen_values = true)]
+    weights: Option<ThreatEvaluator>,
+}
+
+/// Load the ONNX model of `--model`.
+#[cfg(feature = "onnx")]
+fn load_model(path: &str) -> Result<Arc<dyn Evaluator>, String> {
+    Ok(Arc::new(quarto::onnx::OnnxEvaluator::load(Path::new(
+        path,
+    ))?))
+}
+
+#[derive(Args)]
+struct PerftArgs {
+    /// The number of moves, a piece handed over and placed, to count.
+    #[arg(long, default_value_t = 3)]
+    depth: u8,
+    /// Start from the pieces on the board, as piece:index, instead of the empty board.
+    placements: Vec<String>,
+    /// Also print the count after every first move.
+    #[arg(long)]
+    divide: bool,
+}
+
+#[derive(Args)]
+struct PlayArgs {
+    /// The first player, who hands over the first piece.
+    #[arg(long, default_value = "human", value_parser = player_names())]
+    p1: String,
+    /// The second player, by default the computer at the difficulty of your profile.
+    #[arg(long, value_parser = player_names())]
+    p2: Option<String>,
+    /// The third player, the teammate of the first: with --p4 two teams of two play against each other.
+    #[arg(long, value_parser = player_names(), requires = "p4")]
+    p3: Option<String>,
+    /// The fourth player, the teammate of the second.
+    #[arg(long, value_parser = player_names(), requires = "p3")]
+    p4: Option<String>,
+    /// The difficulty of the computer opponent: easy, medium, hard or expert, instead of the one in your profile.
+    #[arg(long)]
+    difficulty: Option<Difficulty>,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+    /// The number of games to play.
+    #[arg(long, default_value_t = 1)]
+    games: u32,
+    /// Seed the random generator, so the same games are played again.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Play by the classic rules, or the advanced rules where four pieces in a 2×2 square that share an attribute win as well.
+    #[arg(long, default_value_t = Ruleset::Classic)]
+    ruleset: Ruleset,
+    /// Play the fog variant: hide the pieces on the board from human players, who can inspect this many lines per turn.
+    #[arg(long)]
+    fog: Option<u8>,
+    /// Color the board: auto, always or never, instead of the choice in your profile.
+    #[arg(long)]
+    color: Option<ColorMode>,
+    /// Describe the pieces as in a physical set, to play along on a real board.
+    #[arg(long)]
+    physical: bool,
+    /// Append every finished game in QGN to this file.
+    #[arg(long)]
+    qgn: Option<PathBuf>,
+    /// Give each player this many seconds for the whole game, a player who goes over loses on time.
+    #[arg(long)]
+    game_time: Option<u64>,
+    /// Give each player this many seconds for every decision, a player who goes over loses on time.
+    #[arg(long)]
+    move_time: Option<u64>,
+    /// Save a game that is quit before it is over to this file.
+    #[arg(long, conflicts_with = "games")]
+    save: Option<PathBuf>,
+    /// Resume the game saved in this file, and save it there again when it is quit.
+    #[arg(long, conflicts_with_all = ["games", "p1", "p2", "difficulty"])]
+    resume: Option<PathBuf>,
+    /// Abandon a computer player that takes longer than this many seconds for a decision, it forfeits the game.
+    #[arg(long, default_value_t = 60)]
+    watchdog: u64,
+    /// Let the computer opponent on the second seat blunder more after it wins and less after it loses, over the games.
+    #[arg(long, conflicts_with_all = ["p3", "resume"])]
+    adaptive: bool,
+}
+
+#[derive(Args)]
+struct CardArgs {
+    /// Write the card as an SVG image to this file, to print it.
+    #[arg(long)]
+    svg: Option<PathBuf>,
+}
+
+#[cfg(feature = "network")]
+#[derive(Args)]
+struct HostArgs {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    address: String,
+    /// Accept the clients over TLS with this PEM certificate.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "key")]
+    cert: Option<PathBuf>,
+    /// The PEM private key of the certificate.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "cert")]
+    key: Option<PathBuf>,
+    /// Rate the games between players in the seasons of this file, created when it does not exist.
+    #[arg(long)]
+    seasons: Option<PathBuf>,
+    /// Keep the record of every rated game in this directory, with its fair-play review next to it.
+    #[arg(long)]
+    games: Option<PathBuf>,
+    /// Give each player this many seconds for the whole game, a player who goes over loses on time.
+    #[arg(long)]
+    game_time: Option<u64>,
+    /// Give each player this many seconds for every decision, a player who goes over loses on time.
+    #[arg(long)]
+    move_time: Option<u64>,
+    /// The options of the bots.
+    #[command(flatten)]
+    strategy: StrategyArgs,
+}
+
+/// How to reach a server that hosts games.
+#[cfg(feature = "network")]
+#[derive(Args)]
+struct ServerArgs {
+    /// The address of the server, as host:port.
+    #[arg(long)]
+    server: String,
+    /// Connect over TLS, checking the certificate of the server against its host.
+    #[cfg(feature = "tls")]
+    #[arg(long)]
+    tls: bool,
+    /// Also trust this PEM certificate, like the self-signed one of the host.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls")]
+    cert: Option<PathBuf>,
+    /// Only trust the server whose certificate has this SHA-256 fingerprint, in hex with or without colons.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls", conflicts_with = "cert", value_parser = quarto::tls::parse_fingerprint)]
+    pin: Option<quarto::tls::Fingerprint>,
+}
+
+#[cfg(feature = "network")]
+impl ServerArgs {
+    /// The server, reached over TLS with `--tls`, trusting only the pinned certificate with `--pin`.
+    fn server(&self) -> Result<quarto::network::Server, String> {
+        #[allow(unused_mut)]
+        let mut server = quarto::network::Server::new(&self.server);
+        #[cfg(feature = "tls")]
+        if self.tls {
+            let mut tls = quarto::tls::ClientTls::new();
+            if let Some(path) = &self.cert {
+                tls = fs::read(path)
+                    .and_then(|pem| tls.with_root_pem(&pem))
+                    .map_err(|e| {
+                        format!("Cannot load the certificate {}: {}", path.display(), e)
+                    })?;
+            }
+            if let Some(pin) = self.pin {
+                tls = tls.with_pinned_fingerprint(pin);
+            }
+            server = server.with_tls(tls);
+        }
+        Ok(server)
+    }
+}
+
+#[cfg(feature = "network")]
+#[derive(Args)]
+struct JoinArgs {
+    #[command(flatten)]
+    server: ServerArgs,
+    /// Your name in the lobby, one word.
+    #[arg(long, required_unless_present_any = ["list", "resume", "watch"])]
+    name: Option<String>,
+    /// Challenge a player waiting in the lobby, instead of waiting for a challenge.
+    #[arg(long, conflicts_with = "bot")]
+    challenge: Option<String>,
+    /// Play against a computer player of the server, like smart.
+    #[arg(long)]
+    bot: Option<String>,
+    /// Only list the players waiting in the lobby, the games being played and the recent results, as tables.
+    #[arg(long, conflicts_with_all = ["challenge", "bot"])]
+    list: bool,
+    /// List only these games: open, ongoing or recent.
+    #[arg(long, requires = "list")]
+    kind: Option<GameListKind>,
+    /// The page of the listing to show, starting at 1.
+    #[arg(long, default_value_t = 1, requires = "list")]
+    page: u32,
+    /// The number of games per page.
+    #[arg(long, default_value_t = 20, requires = "list")]
+    per_page: u32,
+    /// Take your seat again in a game in progress, by the session the server gave when it started.
+    #[arg(long, value_parser = parse_session, conflicts_with_all = ["challenge", "bot", "list"])]
+    resume: Option<u64>,
+    /// Watch the game in progress with this id without playing, listed with --list.
+    #[arg(long, conflicts_with_all = ["challenge", "bot", "list", "resume"])]
+    watch: Option<u64>,
+    /// The player that decides for this side.
+    #[arg(long, default_value = "human", value_parser = player_names())]
+    player: String,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// The archives to import.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// The format of the archives, guessed from the extension when left out.
+    #[arg(long)]
+    format: Option<ArchiveFormat>,
+    /// The history to import into, instead of the one next to the profile.
+    #[arg(long)]
+    db: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// A saved game record, or a QGN file.
+    file: PathBuf,
+    /// The game to analyze of a QGN file with several games, starting at 1.
+    #[arg(long, default_value_t = 1)]
+    game: usize,
+    /// Prove the moves once at most this many cells are empty, earlier moves are only checked one move deep.
+    #[arg(long, default_value_t = 8)]
+    exact: usize,
+}
+
+#[derive(Args)]
+struct PuzzlesArgs {
+    /// The number of puzzles to find.
+    #[arg(long, default_value_t = 5)]
+    count: usize,
+    /// Find wins in this many placements of both players, an odd number.
+    #[arg(long, default_value_t = 3)]
+    plies: usize,
+    /// Try positions with at most this many empty cells.
+    #[arg(long, default_value_t = 8)]
+    empty: usize,
+    /// The number of random positions to try for every puzzle.
+    #[arg(long, default_value_t = 1000)]
+    tries: usize,
+    /// Seed the random generator, so the same puzzles are found again.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Solve the puzzles instead of printing them, full-screen with the tui feature.
+    #[arg(long)]
+    play: bool,
+    /// Read the puzzles from a file with one on every line, instead of searching for them.
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// A saved game record, or a QGN file.
+    file: PathBuf,
+    /// The game to show of a QGN file with several games, starting at 1.
+    #[arg(long, default_value_t = 1)]
+    game: usize,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Args)]
+struct ServeArgs {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Serve over HTTPS with this PEM certificate.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "key")]
+    cert: Option<PathBuf>,
+    /// The PEM private key of the certificate.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "cert")]
+    key: Option<PathBuf>,
+    /// Also serve the gRPC API on this address, like 127.0.0.1:50051, with the same games.
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc: Option<String>,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    /// The first player, who hands over the first piece.
+    #[arg(long, default_value = "smart", value_parser = player_names())]
+    p1: String,
+    /// The second player.
+    #[arg(long, default_value = "dumb", value_parser = player_names())]
+    p2: String,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+    /// The number of games to play.
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+    /// Seed the random generator, so the same games are played again.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Also write the statistics as CSV to this file.
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    /// Also write the statistics as JSON to this file.
+    #[arg(long)]
+    json: Option<PathBuf>,
+    /// Append every game in QGN to this file, to replay them afterwards.
+    #[arg(long)]
+    qgn: Option<PathBuf>,
+    /// Abandon a computer player that takes longer than this many seconds for a decision, it forfeits the game.
+    #[arg(long, default_value_t = 60)]
+    watchdog: u64,
+    /// Spread the games over this many threads, one per core by default.
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+#[cfg(feature = "network")]
+#[derive(Args)]
+struct StandingsArgs {
+    #[command(flatten)]
+    server: ServerArgs,
+    /// The season to show, by default the current one.
+    #[arg(long)]
+    season: Option<u32>,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Write the summary to this file instead of printing it.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DemoArgs {
+    /// The first player, who hands over the first piece.
+    #[arg(long, default_value = "smart", value_parser = player_names())]
+    p1: String,
+    /// The second player.
+    #[arg(long, default_value = "mcts", value_parser = player_names())]
+    p2: String,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+    /// The time between plies in milliseconds.
+    #[arg(long, default_value_t = 800)]
+    delay: u64,
+    /// The number of random games behind the evaluation bar, 0 hides the bar.
+    #[arg(long, default_value_t = 300)]
+    eval: u32,
+    /// Seed the random generator, so the same game is played again.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Args)]
+struct DoctorArgs {
+    /// Also check that the server at this address, as host:port, can be reached.
+    #[arg(long)]
+    server: Option<String>,
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    /// The piece in hand.
+    #[arg(required_unless_present = "fen")]
+    piece: Option<u8>,
+    /// The pieces on the board, as piece:index.
+    placements: Vec<String>,
+    /// Solve a position written like 5.../..c./..../...a 3 1, instead of the piece and the placements.
+    #[arg(long, conflicts_with_all = ["piece", "placements"])]
+    fen: Option<String>,
+    /// The memory of the table of proven positions in MiB, by default its share of $QUARTO_MEMORY.
+    #[arg(long)]
+    memory: Option<usize>,
+}
+
+#[derive(Args)]
+struct BuildDbArgs {
+    /// The number of placements to enumerate, every ply multiplies the positions by about forty.
+    #[arg(long, default_value_t = 4)]
+    plies: u8,
+    /// The piece in hand at the start.
+    #[arg(long, default_value_t = 0)]
+    piece: u8,
+    /// Start from the pieces on the board, as piece:index, instead of the empty board.
+    placements: Vec<String>,
+    /// Prove the outcome of every position of the last ply, only practical close to the end of the game.
+    #[arg(long)]
+    solve: bool,
+    /// Write the database to this file.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct EngineArgs {
+    /// The computer player that decides.
+    #[arg(long, default_value = "smart", value_parser = player_names())]
+    player: String,
+    #[command(flatten)]
+    strategy: StrategyArgs,
+    /// Seed the random generator, so the same decisions are made again.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Args)]
+struct TuneArgs {
+    /// What to tune: mcts for the exploration constant, eval for the weights of the threat evaluation.
+    #[arg(long, default_value = "eval")]
+    target: TuneTarget,
+    /// The number of steps of the tuning.
+    #[arg(long, default_value_t = 20)]
+    steps: u32,
+    /// The number of pairs of games of every step, one with each player first.
+    #[arg(long, default_value_t = 10)]
+    pairs: u32,
+    /// The number of turns the smart strategy looks ahead, for the eval target.
+    #[arg(long, default_value_t = 1)]
+    depth: u8,
+    /// The number of playouts per decision, for the mcts target.
+    #[arg(long, default_value_t = 500)]
+    iterations: u32,
+    /// Seed the random generator, so the same tuning is run again.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+impl StrategyArgs {
+    /// The options for the players of the registry, drawing from `rng`.
+    fn options(&self, rng: &SharedRng) -> PlayerOptions {
+        PlayerOptions {
+            depth: self.depth,
+            iterations: self.iterations,
+            #[cfg(feature = "parallel")]
+            parallel: self.parallel,
+            rng: rng.clone(),
+            evaluator: self.evaluator(),
+            engine: self.engine.clone(),
+            ..PlayerOptions::default()
+        }
+    }
+
+    /// The evaluator of --model, or else of --weights.
+    fn evaluator(&self) -> Option<Arc<dyn Evaluator>> {
+        #[cfg(feature = "onnx")]
+        if let Some(model) = &self.model {
+            return Some(model.clone());
+        }
+        self.weights
+            .map(|weights| Arc::new(weights) as Arc<dyn Evaluator>)
+    }
+}
+
+/// Check if the player named `name` is a person at this terminal.
+fn is_interactive(name: &str) -> bool {
+    Registry::default()
+        .get(name)
+        .is_some_and(|entry| entry.interactive)
+}
+
+/// Build the computer opponent for a difficulty picked in the profile or on the command line,
+/// with the random generator and table of `options`, which forfeits when a decision takes longer than `timeout`.
+/// The opponent adapts to the results recorded in `controller`, if there is one.
+fn make_opponent(
+    difficulty: Difficulty,
+    options: &PlayerOptions,
+    timeout: Duration,
+    controller: Option<Arc<std::sync::Mutex<DifficultyController>>>,
+) -> Box<dyn Player> {
+    let player = match controller {
+        Some(controller) => {
+            difficulty.adaptive_player(&options.rng, options.table.clone(), controller)
+        }
+        None => difficulty.player(&options.rng, options.table.clone()),
+    };
+    Box::new(Watchdog::new(player).with_timeout(timeout))
+}
+
+/// The transposition table of the computer players, in its share of the memory budget.
+fn search_table() -> Result<Arc<std::sync::Mutex<TranspositionTable>>, String> {
+    let table = MemoryBudget::from_env()?.table(Replacement::DepthPreferred);
+    Ok(Arc::new(std::sync::Mutex::new(table)))
+}
+
+/// Load the profile, running the wizard on the first run in a terminal.
+/// Falls back to the default profile if there is no place to keep it or it cannot be read.
+fn load_profile() -> Profile {
+    let Some(path) = profile::default_path() else {
+        return Profile::default();
+    };
+    match Profile::load(&path) {
+        Ok(Some(profile)) => profile,
+        Ok(None) if io::stdin().is_terminal() => setup(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Profile::default()
+        }),
+        Ok(None) => Profile::default(),
+        Err(e) => {
+            eprintln!("Cannot read the profile {}: {}", path.display(), e);
+            Profile::default()
+        }
+    }
+}
+
+/// Run the first-run wizard and save the profile to `path`, keeping the usage statistics of an earlier profile.
+fn setup(path: &Path) -> Result<Profile, String> {
+    let mut profile = wizard::run(io::stdin().lock(), io::stdout(), profile::detect_color())
+        .map_err(|e| format!("Cannot run the setup: {}", e))?;
+    if let Ok(Some(earlier)) = Profile::load(path) {
+        profile.usage = earlier.usage;
+    }
+    profile
+        .save(path)
+        .map_err(|e| format!("Cannot write the profile {}: {}", path.display(), e))?;
+    println!("Saved your profile to {}.", path.display());
+    Ok(profile)
+}
+
+/// Play the games, and print the winner of a single game or a summary of many.
+fn play(args: &PlayArgs) -> Result<(), String> {
+    let profile = load_profile();
+    let color = args.color.map_or(
+        profile.color && io::stdout().is_terminal(),
+        ColorMode::enabled,
+    );
+    let last = Arc::new(LastGame::new());
+    let name = |player: usize, kind: Option<&str>| match kind {
+        Some(kind) if is_interactive(kind) => profile.name.clone(),
+        _ => format!("Player {}", player),
+    };
+    let export = args.qgn.clone().map(|path| {
+        Arc::new(
+            QgnExport::new(path)
+                .with_tag("Event", "Casual game")
+                .with_tag("First", &name(1, Some(&args.p1)))
+                .with_tag("Second", &name(2, args.p2.as_deref())),
+        )
+    });
+    let difficulty = args.difficulty.unwrap_or(profile.difficulty);
+    // The computer opponent is named after its difficulty, which no player of the registry is named after.
+    let second = args.p2.clone().unwrap_or_else(|| difficulty.to_string());
+    let timeout = Duration::from_secs(args.watchdog);
+    let table = search_table()?;
+    let adaptive = match second.parse::<Difficulty>() {
+        Ok(_) if args.adaptive => Some(Arc::new(std::sync::Mutex::new(
+            DifficultyController::default(),
+        ))),
+        Err(_) if args.adaptive => {
+            return Err(format!(
+                "Only a computer opponent adapts, not {}: leave out --p2 or give a difficulty",
+                second
+            ));
+        }
+        _ => None,
+    };
+    let options = |rng: &SharedRng| PlayerOptions {
+        fog: args.fog,
+        color,
+        physical: args.physical,
+        table: Some(table.clone()),
+        ..args.strategy.options(rng)
+    };
+    let make = |name: &str, rng: &SharedRng| match name.parse() {
+        Ok(difficulty) => Ok(make_opponent(difficulty, &options(rng), timeout, None)),
+        Err(_) => Registry::default().create_guarded(name, &options(rng), timeout),
+    };
+    let make_second = |rng: &SharedRng| match (second.parse(), &adaptive) {
+        (Ok(difficulty), Some(controller)) => Ok(make_opponent(
+            difficulty,
+            &options(rng),
+            timeout,
+            Some(controller.clone()),
+        )),
+        _ => make(&second, rng),
+    };
+    let new_game = |rng: &SharedRng| -> Result<QuartoGame, String> {
+        let game = match (&args.p3, &args.p4) {
+            (Some(third), Some(fourth)) => QuartoGame::teams([
+                make(&args.p1, rng)?,
+                make(&second, rng)?,
+                make(third, rng)?,
+                make(fourth, rng)?,
+            ])
+            .with_seat_names(&[&args.p1, &second, third, fourth]),
+            _ => QuartoGame::new(make(&args.p1, rng)?, make_second(rng)?)
+                .with_names(&args.p1, &second),
+        };
+        Ok(game.with_ruleset(args.ruleset))
+    };
+    let mut control = TimeControl::new();
+    if let Some(seconds) = args.game_time {
+        control = control.with_per_game(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.move_time {
+        control = control.with_per_move(Duration::from_secs(seconds));
+    }
+    let observe = |game: QuartoGame| {
+        let mut game = game.with_observer(last.clone());
+        if let Some(controller) = &adaptive {
+            game = game.with_observer(DifficultyFeedback::new(controller.clone(), 0));
+        }
+        let game = if control == TimeControl::new() {
+            game
+        } else {
+            game.with_clock(control)
+        };
+        match &export {
+            Some(export) => game.with_observer(export.clone()),
+            None => game,
+        }
+    };
+    let seats = [
+        Some(args.p1.as_str()),
+        args.p2.as_deref(),
+        args.p3.as_deref(),
+        args.p4.as_deref(),
+    ];
+    let people = seats.iter().flatten().any(|&kind| is_interactive(kind));
+    let autosave =
+        profile::autosave_path().filter(|_| profile.autosave && people && args.games == 1);
+    let resume = match (&args.resume, &autosave) {
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(path)) if path.exists() && io::stdin().is_terminal() => {
+            let resume = wizard::ask_resume(io::stdin().lock(), io::stdout())
+                .map_err(|e| format!("Cannot ask to resume the last game: {}", e))?;
+            if !resume {
+                let _ = fs::remove_file(path);
+            }
+            resume.then(|| path.clone())
+        }
+        _ => None,
+    };
+    let save = args.save.as_ref().or(args.resume.as_ref());
+    let stats = match (&resume, save, &autosave) {
+        (None, None, None) => {
+            simulate::simulate(args.games, args.seed, |rng| new_game(rng).map(&observe))?
+        }
+        _ => {
+            let rng = args.seed.map_or_else(SharedRng::new, SharedRng::seeded);
+            let game = match &resume {
+                Some(resume) => QuartoGame::load(resume, |name| make(name, &rng))
+                    .map_err(|e| format!("Cannot resume {}: {}", resume.display(), e))?,
+                None => new_game(&rng)?,
+            };
+            let mut game = observe(game);
+            if let Some(path) = &autosave {
+                game = game.with_autosave(path.clone());
+                report_crash(path.clone());
+            }
+            let mut stats = SimulationStats::default();
+            let start = Instant::now();
+            let result = game.play_without_call();
+            stats.add(result, game.history().len(), start.elapsed());
+            if let (Some(path), Some(e)) = (&autosave, game.autosave_error()) {
+                eprintln!("Cannot autosave the game to {}: {}", path.display(), e);
+            }
+            if let Some(path) = save.filter(|_| !game.is_over()) {
+                game.save(path)
+                    .map_err(|e| format!("Cannot save the game to {}: {}", path.display(), e))?;
+                println!(
+                    "\nSaved the game to {}, resume it with --resume.",
+                    path.display()
+                );
+                return Ok(());
+            }
+            stats
+        }
+    };
+    record_usage(args, difficulty, &stats);
+    if let (Some(path), Some(e)) = (&args.qgn, export.and_then(|export| export.take_error())) {
+        eprintln!("Cannot write the games to {}: {}", path.display(), e);
+    }
+    if args.games == 1
+        && let Some((result, record)) = last.take()
+    {
+        if let Some(board) = record.final_board() {
+            println!("\n{}\n", PrintableBoard::new(&board).with_color(color));
+            for line in board.winning_lines() {
+                println!("Quarto on {}", line);
+            }
+        }
+        if let GameResult::Timeout(seat) = result {
+            println!("{} ran out of time.", name(seat + 1, seats[seat]));
+        }
+    }
+    // In a team game the players on the first and third seat play against those on the second and fourth.
+    let team = |team: usize| match &args.p3 {
+        Some(_) => format!(
+            "{} and {} win!",
+            name(team + 1, seats[team]),
+            name(team + 3, seats[team + 2])
+        ),
+        None => format!("{} wins!", name(team + 1, seats[team])),
+    };
+    if args.games != 1 {
+        print!("{}", stats.to_table());
+        if let Some(controller) = &adaptive {
+            let rate = controller
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .error_rate();
+            println!(
+                "The computer ended up blundering {:.0}% of its decisions.",
+                rate * 100.0
+            );
+        }
+    } else if stats.wins == 1 {
+        println!("{}", team(0));
+    } else if stats.losses == 1 {
+        println!("{}", team(1));
+    } else if stats.draws == 1 {
+        println!("It is a draw.");
+    } else {
+        return Err("The game ended with an error".to_string());
+    }
+    Ok(())
+}
+
+/// Tell after a panic that the game was autosaved at `path`, and is offered when quarto starts again.
+fn report_crash(path: PathBuf) {
+    let report = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        report(info);
+        if path.exists() {
+            eprintln!(
+                "The game crashed, but it was saved after the last move. Start quarto again to resume it."
+            );
+        }
+    }));
+}
+
+/// Count the games in the usage statistics of the profile, if a person played and has a saved profile.
+fn record_usage(args: &PlayArgs, difficulty: Difficulty, stats: &SimulationStats) {
+    let people = [Some(args.p1.as_str()), args.p2.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|name| is_interactive(name))
+        .count();
+    let mode = match (people, args.fog) {
+        (0, _) => return,
+        (_, Some(_)) => Mode::Fog,
+        (1, None) => Mode::Computer,
+        (_, None) => Mode::Local,
+    };
+    let versus = (args.p2.is_none() && is_interactive(&args.p1)).then_some(difficulty);
+    let Some(path) = profile::default_path() else {
+        return;
+    };
+    if let Ok(Some(mut profile)) = Profile::load(&path) {
+        profile.usage.record(mode, versus, stats);
+        if let Err(e) = profile.save(&path) {
+            eprintln!("Cannot save the statistics to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Print the reference card of the pieces, or write it as SVG to `svg`.
+fn card(svg: Option<&Path>) -> Result<(), String> {
+    match svg {
+        Some(path) => fs::write(path, card::to_svg())
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", card::to_text());
+            Ok(())
+        }
+    }
+}
+
+/// Import the archives into the history, and report per archive.
+fn import(args: &ImportArgs) -> Result<(), String> {
+    let path = args
+        .db
+        .clone()
+        .or_else(history::default_path)
+        .ok_or("There is no config directory to keep the history in".to_string())?;
+    let mut db =
+        HistoryDb::load(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    for file in args.files.iter() {
+        let data = fs::read_to_string(file)
+            .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+        let format = args.format.unwrap_or_else(|| ArchiveFormat::guess(file));
+        let report = import::import(&data, format, &mut db);
+        print!("{}: {}", file.display(), report);
+    }
+    db.save(&path)
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Print the usage statistics of the profile as a summary to share, or write it to `output`.
+fn stats(output: Option<&Path>) -> Result<(), String> {
+    let path = profile::default_path()
+        .ok_or("There is no config directory to keep the profile in".to_string())?;
+    let profile = Profile::load(&path)
+        .map_err(|e| format!("Cannot read the profile {}: {}", path.display(), e))?
+        .unwrap_or_default();
+    let report = profile.usage.report(&profile.name);
+    match output {
+        Some(output) => fs::write(output, report)
+            .map_err(|e| format!("Cannot write {}: {}", output.display(), e)),
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Play a batch of games between two strategies, and report the statistics.
+fn simulate(args: &SimulateArgs) -> Result<(), String> {
+    let export = args.qgn.clone().map(|path| {
+        Arc::new(
+            QgnExport::new(path)
+                .with_tag("Event", "Simulation")
+                .with_tag("First", &args.p1)
+                .with_tag("Second", &args.p2),
+        )
+    });
+    let timeout = Duration::from_secs(args.watchdog);
+    let make_game = |rng: &SharedRng| {
+        let options = args.strategy.options(rng);
+        let make = |name: &str| Registry::default().create_guarded(name, &options, timeout);
+        let game = QuartoGame::new(make(&args.p1)?, make(&args.p2)?);
+        Ok(match &export {
+            Some(export) => game.with_observer(export.clone()),
+            None => game,
+        })
+    };
+    #[cfg(feature = "parallel")]
+    let stats = simulate::simulate_parallel(args.games, args.seed, args.threads, make_game)?;
+    #[cfg(not(feature = "parallel"))]
+    let stats = simulate::simulate(args.games, args.seed, make_game)?;
+    if let (Some(path), Some(e)) = (&args.qgn, export.and_then(|export| export.take_error())) {
+        eprintln!("Cannot write the games to {}: {}", path.display(), e);
+    }
+    println!("{} against {}", args.p1, args.p2);
+    print!("{}", stats.to_table());
+    if let Some(path) = &args.csv {
+        fs::write(path, stats.to_csv())
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.json {
+        fs::write(path, stats.to_json() + "\n")
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Read a saved game record, or game number `game` of a QGN file.
+fn read_record(path: &Path, game: usize) -> Result<GameRecord, String> {
+    let data =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    match GameRecord::from_text(&data) {
+        Ok(record) => Ok(record),
+        Err(_) => Ok(qgn::parse_all(&data)
+            .into_iter()
+            .nth(game.saturating_sub(1))
+            .ok_or(format!("{} has no game {}", path.display(), game))?
+            .map_err(|e| format!("Cannot read game {}: {}", game, e))?
+            .into_record()),
+    }
+}
+
+/// Print the mistakes of a saved game.
+fn analyze(args: &AnalyzeArgs) -> Result<(), String> {
+    let record = read_record(&args.file, args.game)?;
+    let mut analyzer = Analyzer::new()
+        .with_solver(MemoryBudget::from_env()?.solver())
+        .with_exact(args.exact);
+    print!("{}", analyzer.analyze(&record)?);
+    Ok(())
+}
+
+/// Find or read puzzles, and print them or let the player solve them.
+fn puzzles(args: &PuzzlesArgs) -> Result<(), String> {
+    let puzzles: Vec<Puzzle> = match &args.file {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse())
+            .collect::<Result<_, _>>()?,
+        None => {
+            let mut generator = PuzzleGenerator::new()
+                .with_solver(MemoryBudget::from_env()?.solver())
+                .with_rng(args.seed.map_or_else(SharedRng::new, SharedRng::seeded))
+                .with_plies(args.plies)
+                .with_empty(args.empty);
+            (0..args.count)
+                .map_while(|_| generator.generate(args.tries))
+                .collect()
+        }
+    };
+    if puzzles.is_empty() {
+        return Err("No puzzles found, try more --tries or --empty cells".to_string());
+    }
+    if !args.play {
+        for puzzle in &puzzles {
+            println!("{}", puzzle);
+        }
+        return Ok(());
+    }
+    #[cfg(feature = "tui")]
+    if io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+        && let Ok(interface) = quarto::ui::tui::TuiInterface::stdio()
+    {
+        let solved = quarto::ui::tui::puzzles(&interface, &puzzles);
+        drop(interface);
+        println!("Solved {} of {} puzzles.", solved, puzzles.len());
+        return Ok(());
+    }
+    let solved = puzzle::run_text(&puzzles, io::stdin().lock(), &mut io::stdout())
+        .map_err(|e| e.to_string())?;
+    println!("Solved {} of {} puzzles.", solved, puzzles.len());
+    Ok(())
+}
+
+/// Load a game record, or the chosen game of a QGN file, and step through it.
+fn replay(args: &ReplayArgs) -> Result<(), String> {
+    let mut replay = Replay::new(read_record(&args.file, args.game)?)?;
+    #[cfg(feature = "tui")]
+    if io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+        && let Ok(mut terminal) = ratatui::try_init()
+    {
+        let shown = quarto::ui::tui::replay(&mut terminal, quarto::ui::tui::Keys, &mut replay);
+        ratatui::restore();
+        return shown.map_err(|e| e.to_string());
+    }
+    replay::run_text(&mut replay, io::stdin().lock(), &mut io::stdout()).map_err(|e| e.to_string())
+}
+
+/// Play a game between two computer players on another thread, and show it with playback controls from stdin.
+fn demo(args: &DemoArgs) -> Result<(), String> {
+    if is_interactive(&args.p1) || is_interactive(&args.p2) {
+        return Err("A demo is played between two computer players".to_string());
+    }
+    let bus = EventBus::new();
+    let events = bus.subscribe();
+    let rng = args.seed.map_or_else(SharedRng::new, SharedRng::seeded);
+    // The evaluation bar gets its own generator, so it does not change the decisions of the players.
+    let evaluation = SharedRng::seeded(rng.usize(..) as u64);
+    let options = args.strategy.options(&rng);
+    let (p1, p2) = (args.p1.clone(), args.p2.clone());
+    // The players cannot be sent to another thread, so they are created on the thread of the game.
+    let (ready, started) = mpsc::channel();
+    thread::spawn(move || {
+        let registry = Registry::default();
+        let players = registry
+            .create(&p1, &options)
+            .and_then(|first| Ok((first, registry.create(&p2, &options)?)));
+        match players {
+            Ok((first, second)) => {
+                let _ = ready.send(Ok(()));
+                QuartoGame::new(first, second).with_events(bus).play();
+            }
+            Err(e) => {
+                let _ = ready.send(Err(e));
+            }
+        }
+    });
+    started
+        .recv()
+        .map_err(|_| "The demo stopped before it started".to_string())??;
+    let controls = playback::read_controls(io::BufReader::new(io::stdin()));
+    playback::drive(
+        events
+            .into_iter()
+            .map(|event| Ok(SpectatorMessage::Event(event))),
+        &controls,
+        &mut io::stdout(),
+        &mut WatchView::new()
+            .with_evaluation(args.eval)
+            .with_rng(evaluation),
+        &mut Playback::new(Duration::from_millis(args.delay)),
+    )
+    .map_err(|e| format!("Cannot show the demo: {}", e))
+}
+
+/// Parse a list of `piece:index` placements into a board.
+fn parse_board(moves: &[String]) -> Result<Board, String> {
+    let mut placements: Vec<(u8, u8)> = Vec::new();
+    for m in moves {
+        let (piece, index) = m
+            .split_once(':')
+            .ok_or(format!("Expected a placement as piece:index, got '{}'", m))?;
+        let piece: u8 = piece
+            .parse()
+            .map_err(|_| format!("Invalid piece in '{}'", m))?;
+        let index: u8 = index
+            .parse()
+            .map_err(|_| format!("Invalid index in '{}'", m))?;
+        placements.push((piece, index));
+    }
+    Board::new()
+        .apply_moves(&placements)
+        .map_err(|e| e.to_string())
+}
+
+/// Count the move sequences from the position given on the command line, and how fast they were counted.
+fn perft(args: &PerftArgs) -> Result<(), String> {
+    let board = parse_board(&args.placements)?;
+    let start = Instant::now();
+    if args.divide {
+        for (piece, index, count) in perft::divide(&board, args.depth) {
+            println!("{:>2}:{:<2} {}", piece, index, count);
+        }
+    }
+    let nodes = perft::perft(&board, args.depth);
+    let elapsed = start.elapsed();
+    println!("Nodes: {}", nodes);
+    println!("Time: {:.2?}", elapsed);
+    println!(
+        "Nodes per second: {:.0}",
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+    Ok(())
+}
+
+/// Solve the position given on the command line: the piece in hand and the placements on the board.
+fn solve(args: &SolveArgs) -> Result<(), String> {
+    let (board, piece) = match &args.fen {
+        Some(fen) => match Board::from_fen(fen)? {
+            (board, Some(piece), _) => (board, piece),
+            (_, None, _) => return Err("The position has no piece in hand to solve".to_string()),
+        },
+        None => (
+            parse_board(&args.placements)?,
+            args.piece.unwrap_or_default(),
+        ),
+    };
+    if !board.valid_piece(piece) {
+        return Err(format!("Piece {} is not available", piece));
+    }
+    if board.game_over() {
+        return Err("The game is already over".to_string());
+    }
+    let mut solver = match args.memory {
+        Some(mib) => Solver::new()
+            .with_table(ProofTable::from_mib(mib, Replacement::DepthPreferred).with_two_tiers()),
+        None => MemoryBudget::from_env()?.solver(),
+    };
+    for (index, outcome) in solver.placement_outcomes(&board, piece) {
+        println!("{:>2}: {}", index, outcome);
+    }
+    println!("Outcome: {}", solver.solve(&board, piece));
+    println!("Positions: {}", solver.known_positions());
+    Ok(())
+}
+
+fn build_db(args: &BuildDbArgs) -> Result<(), String> {
+    let board = parse_board(&args.placements)?;
+    if !board.valid_piece(args.piece) {
+        return Err(format!("Piece {} is not available", args.piece));
+    }
+    if board.game_over() {
+        return Err("The game is already over".to_string());
+    }
+    let mut db = PositionDb::build_from(&board, args.piece, args.plies);
+    if args.solve {
+        let nodes = db.solve_last(&mut MemoryBudget::from_env()?.solver());
+        println!("Solver nodes: {}", nodes);
+    }
+    print!("{}", db);
+    if let Some(path) = &args.output {
+        fs::write(path, db.to_text())
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Run the checks of the environment, failing if one found a problem.
+fn doctor(args: &DoctorArgs) -> Result<(), String> {
+    let doctor = match &args.server {
+        Some(server) => Doctor::new().with_server(server),
+        None => Doctor::new(),
+    };
+    let report = doctor.run();
+    print!("{}", report);
+    match report.count(doctor::Status::Problem) {
+        0 => Ok(()),
+        problems => Err(format!("Found {} problems", problems)),
+    }
+}
+
+/// Run a computer player as an engine on stdin and stdout, until the input ends.
+fn engine(args: &EngineArgs) -> Result<(), String> {
+    let rng = args.seed.map_or_else(SharedRng::new, SharedRng::seeded);
+    let options = PlayerOptions {
+        table: Some(search_table()?),
+        ..args.strategy.options(&rng)
+    };
+    let player = Registry::default().create_computer(&args.player, &options)?;
+    engine::run(player.as_ref(), io::stdin().lock(), &mut io::stdout())
+        .map_err(|e| format!("The engine stopped: {}", e))
+}
+
+/// Read a session of the server, in hex.
+#[cfg(feature = "network")]
+fn parse_session(hex: &str) -> Result<u64, String> {
+    u64::from_str_radix(hex, 16).map_err(|_| format!("'{}' is not a session", hex))
+}
+
+/// Host games in a lobby, over TLS when a certificate is given.
+#[cfg(feature = "network")]
+fn host(args: &HostArgs) -> Result<(), String> {
+    let mut control = TimeControl::new();
+    if let Some(seconds) = args.game_time {
+        control = control.with_per_game(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.move_time {
+        control = control.with_per_move(Duration::from_secs(seconds));
+    }
+    let mut lobby = quarto::lobby::Lobby::new()
+        .with_options(args.strategy.options(&SharedRng::new()))
+        .with_time_control(control);
+    if let Some(path) = args.seasons.as_ref().filter(|path| path.exists()) {
+        let seasons = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| quarto::season::Seasons::from_text(&data).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Cannot load the seasons {}: {}", path.display(), e))?;
+        lobby = lobby.with_seasons(seasons);
+    }
+    if let Some(games) = &args.games {
+        fs::create_dir_all(games)
+            .map_err(|e| format!("Cannot create {}: {}", games.display(), e))?;
+    }
+    let (seasons, games) = (args.seasons.clone(), args.games.clone());
+    // The records are numbered from the start of the server, so a restart does not overwrite them.
+    let started = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    let count = std::sync::atomic::AtomicU64::new(0);
+    lobby = lobby.on_rated(move |ratings, record, review| {
+        if let Some(path) = &seasons
+            && let Err(e) = fs::write(path, ratings.to_text())
+        {
+            eprintln!("Cannot save the seasons {}: {}", path.display(), e);
+        }
+        if let Some(games) = &games {
+            let number = count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            let path = games.join(format!("{}-{}.record", started, number));
+            let saved = fs::write(&path, record.to_text())
+                .and_then(|_| fs::write(quarto::fairplay::review_path(&path), review.to_text()));
+            match saved {
+                Err(e) => eprintln!("Cannot save the game {}: {}", path.display(), e),
+                Ok(()) if review.is_flagged() => {
+                    println!("Flagged {} for review", path.display())
+                }
+                Ok(()) => {}
+            }
+        }
+    });
+    #[allow(unused_mut)]
+    let mut scheme = "ws";
+    #[cfg(feature = "tls")]
+    if let (Some(cert), Some(key)) = (&args.cert, &args.key) {
+        let config = quarto::tls::server_config(cert, key)
+            .map_err(|e| format!("Cannot load the certificate {}: {}", cert.display(), e))?;
+        lobby = lobby.with_tls(config);
+        scheme = "wss";
+    }
+    std::net::TcpListener::bind(&args.address)
+        .and_then(|listener| {
+            println!("Hosting games on {}://{}", scheme, args.address);
+            Arc::new(lobby).host(listener)
+        })
+        .map_err(|e| format!("Cannot host on {}: {}", args.address, e))
+}
+
+/// Play a game on a server, and tell what the opponent does.
+#[cfg(feature = "network")]
+fn join(args: &JoinArgs) -> Result<(), String> {
+    use quarto::{
+        lobby::{self, Seek},
+        network::{self, Chat, NetMessage},
+    };
+
+    let server = args.server.server()?;
+    if args.list {
+        let kinds = args
+            .kind
+            .map_or(GameListKind::ALL.to_vec(), |kind| vec![kind]);
+        for (number, kind) in kinds.into_iter().enumerate() {
+            let page = lobby::browse(&server, kind, args.page, args.per_page)
+                .map_err(|e| format!("Cannot list the {} games of {}: {}", kind, server, e))?;
+            if number > 0 {
+                println!();
+            }
+            match kind {
+                GameListKind::Open => println!("Waiting for an opponent:"),
+                GameListKind::Ongoing => println!("Being played:"),
+                GameListKind::Recent => println!("Finished recently:"),
+            }
+            print!("{}", page.to_table(kind));
+        }
+        return Ok(());
+    }
+    if let Some(id) = args.watch {
+        /// What the spectator hears: a message of the server, a key of the viewer, or the end of the game.
+        enum Watched {
+            Message(NetMessage),
+            Key(playback::Control),
+            Done(io::Result<GameResult>),
+        }
+        let (sender, watched) = mpsc::channel();
+        let keys = sender.clone();
+        let controls = playback::read_controls(io::BufReader::new(io::stdin()));
+        thread::spawn(move || {
+            for control in controls {
+                if keys.send(Watched::Key(control)).is_err() {
+                    break;
+                }
+            }
+        });
+        let watching = server.clone();
+        thread::spawn(move || {
+            let done = lobby::watch(&watching, id, |message| {
+                sender.send(Watched::Message(message.clone())).ok();
+            });
+            sender.send(Watched::Done(done)).ok();
+        });
+        println!("Type f and enter to turn the board around.");
+        let mut view = WatchView::new();
+        for watched in watched {
+            match watched {
+                Watched::Message(NetMessage::Chat { from, text }) => {
+                    let (from, text) = (
+                        network::strip_controls(&from),
+                        network::strip_controls(&text),
+                    );
+                    println!("{}: {}", from, text);
+                }
+                Watched::Message(message) => {
+                    if let Some(message) = message.spectator() {
+                        view.apply(message);
+                        println!("{}", view.render());
+                    }
+                }
+                Watched::Key(playback::Control::Flip) => {
+                    view.flip();
+                    println!("{}", view.render());
+                }
+                // The other controls are for a playback, a live game goes at the pace of its players.
+                Watched::Key(_) => {}
+                Watched::Done(done) => {
+                    done.map_err(|e| format!("Cannot watch game {} on {}: {}", id, server, e))?;
+                    break;
+                }
+            }
+        }
+        return Ok(());
+    }
+    let seek = match (&args.challenge, &args.bot, args.resume) {
+        (Some(name), _, _) => Seek::Challenge(name.clone()),
+        (_, Some(bot), _) => Seek::Bot(bot.clone()),
+        (_, _, Some(session)) => Seek::Resume(session),
+        _ => Seek::Anyone,
+    };
+    let options = args.strategy.options(&SharedRng::new());
+    let chat = Chat::new();
+    // A human in the full-screen interface chats in its sidebar, anyone else only hears the chat.
+    #[cfg(feature = "tui")]
+    let chatting = (args.player == "human" && options.fog.is_none())
+        .then(|| chatting_human(&chat))
+        .flatten();
+    #[cfg(not(feature = "tui"))]
+    let chatting = None;
+    let player = match chatting {
+        Some(player) => player,
+        None => {
+            chat.on_chat(|from, text| println!("{}: {}", from, text));
+            Registry::default().create(&args.player, &options)?
+        }
+    };
+    let name = args.name.as_deref().unwrap_or_default();
+    let mut seat = 0;
+    let show = |message: &NetMessage| match *message {
+        NetMessage::Waiting(ref names) if names.is_empty() => {
+            println!("Entered the lobby, nobody else is waiting");
+        }
+        NetMessage::Waiting(ref names) => {
+            println!("Entered the lobby, waiting: {}", names.join(", "));
+        }
+        NetMessage::Challenged(ref other) => println!("{} challenged you", other),
+        NetMessage::Welcome {
+            seat: number,
+            session,
+        } => {
+            seat = number;
+            println!(
+                "You are player {}, take your seat again with --resume {:016x}",
+                seat + 1,
+                session
+            );
+        }
+        NetMessage::Given { seat: from, piece } if from != seat => {
+            println!("The opponent handed over piece {}", piece);
+        }
+        NetMessage::Placed {
+            seat: from,
+            piece,
+            index,
+        } if from != seat => println!("The opponent placed piece {} on {}", piece, index),
+        NetMessage::Error(ref text) => println!("The server refused: {}", text),
+        _ => {}
+    };
+    let result = lobby::join(&server, name, &seek, player.as_ref(), &chat, show)
+        .map_err(|e| format!("Cannot play on {}: {}", server, e))?;
+    match result {
+        GameResult::Win(winner) if winner == seat => println!("You won!"),
+        GameResult::Win(_) => println!("You lost."),
+        GameResult::Timeout(loser) if loser == seat => println!("You ran out of time."),
+        GameResult::Timeout(_) => println!("The opponent ran out of time."),
+        GameResult::Draw => println!("It's a draw."),
+        GameResult::Error => println!("The game ended in an error."),
+    }
+    Ok(())
+}
+
+/// A human in the full-screen interface with the lines of `chat` in a sidebar, when both ends of the terminal are one.
+#[cfg(all(feature = "tui", feature = "network"))]
+fn chatting_human(chat: &quarto::network::Chat) -> Option<Box<dyn Player>> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+    let interface = quarto::ui::tui::TuiInterface::stdio().ok()?;
+    let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let heard = lines.clone();
+    chat.on_chat(move |from, text| {
+        heard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(format!("{}: {}", from, text));
+    });
+    let chat = chat.clone();
+    let interface = interface.with_chat(lines, move |text| {
+        chat.send_chat(text).map_err(|e| e.to_string())
+    });
+    Some(Box::new(quarto::player::HumanPlayer::new(interface)))
+}
+
+/// Print the memory budget, what every cache takes when it is built within it, and the memory this process uses.
+fn status() -> Result<(), String> {
+    let budget = MemoryBudget::from_env()?;
+    print!("{}", budget);
+    // Build the caches like a game does, the trace buffer only grows as games are kept.
+    let table = budget.table(Replacement::DepthPreferred);
+    let solver = budget.solver();
+    let traces = budget.trace_buffer();
+    println!("In use");
+    for (cache, bytes) in [
+        ("Transposition table", table.memory_usage()),
+        ("Solver cache", solver.memory_usage()),
+        ("Trace buffer", traces.memory_usage()),
+    ] {
+        println!("{:<20}{:>12}", cache, memory::format_mib(bytes));
+    }
+    if let Some(bytes) = memory::resident_memory() {
+        println!("{:<20}{:>12}", "Resident memory", memory::format_mib(bytes));
+    }
+    Ok(())
+}
+
+fn tune(args: &TuneArgs) -> Result<(), String> {
+    let mut tuner = Tuner::new(args.target.parameters())
+        .with_pairs(args.pairs)
+        .with_rng(args.seed.map_or_else(SharedRng::new, SharedRng::seeded));
+    let format = |parameters: &[Parameter]| {
+        let values: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+        values.join(", ")
+    };
+    tuner.tune(
+        args.steps,
+        |values, rng| args.target.player(values, rng, args.depth, args.iterations),
+        |step, parameters, score| {
+            println!(
+                "Step {:>3}: score {:.2}, {}",
+                step,
+                score,
+                format(parameters)
+            )
+        },
+    );
+    println!("Best: {}", format(tuner.parameters()));
+    if args.target == TuneTarget::Eval {
+        let values: Vec<f64> = tuner.parameters().iter().map(|p| p.value).collect();
+        let weights = ThreatEvaluator::new([values[0], values[1], values[2]]);
+        println!("Play with them with --weights {}", weights);
+    }
+    Ok(())
+}
+
+/// Serve the REST API, over HTTPS when a certificate is given, and the gRPC API next to it.
+#[cfg(feature = "serve")]
+fn serve(args: &ServeArgs) -> Result<(), String> {
+    let sessions = Arc::new(
+        quarto::session::Sessions::new().with_options(args.strategy.options(&SharedRng::new())),
+    );
+    #[cfg(feature = "grpc")]
+    if let Some(address) = args.grpc.clone() {
+        let sessions = sessions.clone();
+        println!("Serving gRPC on {}", address);
+        std::thread::spawn(move || {
+            if let Err(e) = quarto::grpc::serve(&address, sessions) {
+                eprintln!("Cannot serve gRPC on {}: {}", address, e);
+            }
+        });
+    }
+    #[cfg(feature = "tls")]
+    if let (Some(cert), Some(key)) = (&args.cert, &args.key) {
+        println!("Serving games on https://{}", args.address);
+        return quarto::serve::serve_tls(&args.address, cert, key, sessions)
+            .map_err(|e| format!("Cannot serve on {}: {}", args.address, e));
+    }
+    println!("Serving games on http://{}", args.address);
+    quarto::serve::serve(&args.address, sessions)
+        .map_err(|e| format!("Cannot serve on {}: {}", args.address, e))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Some(Command::Analyze(args)) => analyze(args),
+        Some(Command::BuildDb(args)) => build_db(args),
+        Some(Command::Card(args)) => card(args.svg.as_deref()),
+        Some(Command::Demo(args)) => demo(args),
+        Some(Command::Doctor(args)) => doctor(args),
+        Some(Command::Engine(args)) => engine(args),
+        #[cfg(feature = "network")]
+        Some(Command::Host(args)) => host(args),
+        Some(Command::Import(args)) => import(args),
+        #[cfg(feature = "network")]
+        Some(Command::Join(args)) => join(args),
+        Some(Command::Perft(args)) => perft(args),
+        Some(Command::Play(args)) => play(args),
+        Some(Command::Puzzles(args)) => puzzles(args),
+        Some(Command::Replay(args)) => replay(args),
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(args)) => serve(args),
+        Some(Command::Setup) => profile::default_path()
+            .ok_or("There is no config directory to keep the profile in".to_string())
+            .and_then(|path| setup(&path))
+            .map(|_| ()),
+        Some(Command::Simulate(args)) => simulate(args),
+        Some(Command::Solve(args)) => solve(args),
+        #[cfg(feature = "network")]
+        Some(Command::Standings(args)) => args
+            .server
+            .server()
+            .and_then(|server| {
+                quarto::lobby::standings(&server, args.season).map_err(|e| e.to_string())
+            })
+            .map(|standings| print!("{}", standings))
+            .map_err(|e| format!("Cannot get the standings: {}", e)),
+        Some(Command::Stats(args)) => stats(args.output.as_deref()),
+        Some(Command::Status) => status(),
+        Some(Command::Tune(args)) => tune(args),
+        None => play(&cli.play),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,402 @@
+// Monte Carlo Tree Search over the two decisions of a Quarto turn.
+// Playouts are played with random moves on a copy of the bitboard, which is cheap enough to run thousands of them per move.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    board::{Board, PlacementOutcome},
+    cancel::CancelToken,
+};
+
+/// How long a search is allowed to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MctsBudget {
+    /// Run a fixed number of playouts, at least one.
+    Iterations(u32),
+    /// Run playouts until the time is up.
+    Time(Duration),
+}
+
+/// A decision in a Quarto turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Place the piece in hand on an index.
+    Place(u8),
+    /// Hand a piece over to the opponent.
+    Give(u8),
+}
+
+/// The position in a node: the board and the piece in hand, if the next decision is a placement.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    board: Board,
+    piece: Option<u8>,
+    /// The player that makes the next decision, 0 is the player at the root.
+    actor: u8,
+}
+
+impl State {
+    /// All decisions that can be made from this state.
+    fn actions(&self) -> Vec<Action> {
+        match self.piece {
+            Some(_) => self
+                .board
+                .empty_spaces()
+                .into_iter()
+                .map(Action::Place)
+                .collect(),
+            None => self
+                .board
+                .valid_pieces()
+                .into_iter()
+                .map(Action::Give)
+                .collect(),
+        }
+    }
+
+    /// Apply a decision, returning the new state and the winner if the game ended.
+    /// A game ending in a draw has `Some(None)` as result.
+    fn apply(&self, action: Action) -> (State, Option<Option<u8>>) {
+        match (action, self.piece) {
+            (Action::Place(index), Some(piece)) => {
+                let mut board = self.board;
+                let Ok(outcome) = board.put_piece_checked(piece, index) else {
+                    return (*self, Some(None));
+                };
+                let next = State {
+                    board,
+                    piece: None,
+                    actor: self.actor,
+                };
+                match outcome {
+                    PlacementOutcome::Win => (next, Some(Some(self.actor))),
+                    PlacementOutcome::Draw => (next, Some(None)),
+                    PlacementOutcome::Ongoing => (next, None),
+                }
+            }
+            (Action::Give(piece), _) => (
+                State {
+                    board: self.board,
+                    piece: Some(piece),
+                    actor: 1 - self.actor,
+                },
+                None,
+            ),
+            // Placing without a piece in hand cannot happen, the actions are generated from the state.
+            (Action::Place(_), None) => (*self, Some(None)),
+        }
+    }
+
+    /// Play random decisions until the game ends, and return the winner (`None` for a draw).
+    fn playout(&self, rng: &mut fastrand::Rng) -> Option<u8> {
+        let mut state = *self;
+        loop {
+            let actions = state.actions();
+            if actions.is_empty() {
+                return None;
+            }
+            let (next, result) = state.apply(actions[rng.usize(..actions.len())]);
+            if let Some(winner) = result {
+                return winner;
+            }
+            state = next;
+        }
+    }
+}
+
+/// A node in the search tree, stored in an arena.
+struct Node {
+    parent: Option<usize>,
+    action: Option<Action>,
+    state: State,
+    /// The result of the game if this node ends it.
+    result: Option<Option<u8>>,
+    children: Vec<usize>,
+    untried: Vec<Action>,
+    visits: u32,
+    /// The total reward for the player that made the decision leading to this node.
+    reward: f64,
+}
+
+impl Node {
+    fn new(
+        parent: Option<usize>,
+        action: Option<Action>,
+        state: State,
+        result: Option<Option<u8>>,
+    ) -> Self {
+        let untried = if result.is_some() {
+            Vec::new()
+        } else {
+            state.actions()
+        };
+        Node {
+            parent,
+            action,
+            state,
+            result,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+
+    /// The player that made the decision leading to this node.
+    fn mover(&self) -> u8 {
+        match self.action {
+            // A placement is made by the player that is still to hand over a piece.
+            Some(Action::Place(_)) => self.state.actor,
+            // Handing over a piece passes the turn, the root is treated the same.
+            Some(Action::Give(_)) | None => 1 - self.state.actor,
+        }
+    }
+}
+
+/// A search tree, rooted at the position the strategy has to decide on.
+struct Tree {
+    nodes: Vec<Node>,
+    exploration: f64,
+    /// Picks the nodes to expand and plays out the random games.
+    rng: fastrand::Rng,
+}
+
+impl Tree {
+    fn new(root: State, exploration: f64, rng: fastrand::Rng) -> Self {
+        Tree {
+            nodes: vec![Node::new(None, None, root, None)],
+            exploration,
+            rng,
+        }
+    }
+
+    /// Pick the child of `node` with the highest upper confidence bound.
+    fn select_child(&self, node: usize) -> usize {
+        let ln_visits = (self.nodes[node].visits.max(1) as f64).ln();
+        let score = |child: usize| {
+            let c = &self.nodes[child];
+            let visits = c.visits.max(1) as f64;
+            c.reward / visits + self.exploration * (ln_visits / visits).sqrt()
+        };
+        let mut best = self.nodes[node].children[0];
+        let mut best_score = score(best);
+        for &child in self.nodes[node].children.iter().skip(1) {
+            let child_score = score(child);
+            if child_score > best_score {
+                best = child;
+                best_score = child_score;
+            }
+        }
+        best
+    }
+
+    /// Run a single iteration: selection, expansion, playout, and backpropagation.
+    fn iterate(&mut self) {
+        let mut node = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select_child(node);
+        }
+        if !self.nodes[node].untried.is_empty() {
+            let untried = &mut self.nodes[node].untried;
+            let action = untried.swap_remove(self.rng.usize(..untried.len()));
+            let (state, result) = self.nodes[node].state.apply(action);
+            self.nodes
+                .push(Node::new(Some(node), Some(action), state, result));
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+        }
+        let winner = match self.nodes[node].result {
+            Some(result) => result,
+            None => self.nodes[node].state.playout(&mut self.rng),
+        };
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let n = &mut self.nodes[index];
+            n.visits += 1;
+            n.reward += match winner {
+                Some(w) if w == n.mover() => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            current = n.parent;
+        }
+    }
+
+    /// Run iterations within the budget, then return the most visited decision at the root.
+    /// Stops early when `cancel` is cancelled, but always runs at least one iteration.
+    fn search(&mut self, budget: MctsBudget, cancel: &CancelToken) -> Option<Action> {
+        match budget {
+            MctsBudget::Iterations(n) => {
+                for i in 0..n.max(1) {
+                    if i > 0 && cancel.is_cancelled() {
+                        break;
+                    }
+                    self.iterate();
+                }
+            }
+            MctsBudget::Time(limit) => {
+                let start = Instant::now();
+                self.iterate();
+                while start.elapsed() < limit && !cancel.is_cancelled() {
+                    self.iterate();
+                }
+            }
+        }
+        self.nodes[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+            .and_then(|&child| self.nodes[child].action)
+    }
+}
+
+/// Find the index to place `piece` on, using Monte Carlo Tree Search.
+/// The same `rng` and budget in iterations give the same decision.
+pub fn best_move(
+    board: &Board,
+    piece: u8,
+    budget: MctsBudget,
+    exploration: f64,
+    cancel: &CancelToken,
+    rng: fastrand::Rng,
+) -> Option<u8> {
+    let root = State {
+        board: *board,
+        piece: Some(piece),
+        actor: 0,
+    };
+    match Tree::new(root, exploration, rng).search(budget, cancel) {
+        Some(Action::Place(index)) => Some(index),
+        _ => None,
+    }
+}
+
+/// Find the piece to hand over, using Monte Carlo Tree Search.
+pub fn best_piece(
+    board: &Board,
+    budget: MctsBudget,
+    exploration: f64,
+    cancel: &CancelToken,
+    rng: fastrand::Rng,
+) -> Option<u8> {
+    let root = State {
+        board: *board,
+        piece: None,
+        actor: 0,
+    };
+    match Tree::new(root, exploration, rng).search(budget, cancel) {
+        Some(Action::Give(piece)) => Some(piece),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::open_row;
+
+    #[test]
+    fn test_best_move_finds_win() {
+        let index = best_move(
+            &open_row(),
+            0b1001,
+            MctsBudget::Iterations(500),
+            1.4,
+            &CancelToken::new(),
+            fastrand::Rng::new(),
+        );
+        assert_eq!(index, Some(15));
+        let index = best_move(
+            &open_row(),
+            0b1001,
+            MctsBudget::Iterations(0),
+            1.4,
+            &CancelToken::new(),
+            fastrand::Rng::new(),
+        );
+        assert!(index.is_some());
+    }
+
+    #[test]
+    fn test_best_piece_avoids_losing_piece() {
+        let piece = best_piece(
+            &open_row(),
+            MctsBudget::Iterations(2000),
+            1.4,
+            &CancelToken::new(),
+            fastrand::Rng::new(),
+        )
+        .unwrap();
+        assert_eq!(piece & 0b1001, 0b0001);
+    }
+
+    #[test]
+    fn test_same_seed_same_decision() {
+        let search = || {
+            best_piece(
+                &Board::new(),
+                MctsBudget::Iterations(300),
+                1.4,
+                &CancelToken::new(),
+                fastrand::Rng::with_seed(5),
+            )
+        };
+        assert_eq!(search(), search());
+    }
+
+    #[test]
+    fn test_time_budget_returns() {
+        let budget = MctsBudget::Time(Duration::from_millis(20));
+        assert!(
+            best_move(
+                &Board::new(),
+                0,
+                budget,
+                1.4,
+                &CancelToken::new(),
+                fastrand::Rng::new()
+            )
+            .is_some()
+        );
+    }
+
+    #[test]
+    fn test_cancelled_search_stops() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let start = Instant::now();
+        let budget = MctsBudget::Time(Duration::from_secs(10));
+        assert!(best_piece(&Board::new(), budget, 1.4, &cancel, fastrand::Rng::new()).is_some());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_full_board() {
+        let mut board = Board::new();
+        for i in 0..16 {
+            board.put_piece(i, i).unwrap();
+        }
+        assert_eq!(
+            best_move(
+                &board,
+                0,
+                MctsBudget::Iterations(10),
+                1.4,
+                &CancelToken::new(),
+                fastrand::Rng::new()
+            ),
+            None
+        );
+        assert_eq!(
+            best_piece(
+                &board,
+                MctsBudget::Iterations(10),
+                1.4,
+                &CancelToken::new(),
+                fastrand::Rng::new()
+            ),
+            None
+        );
+    }
+}
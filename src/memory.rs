@@ -0,0 +1,180 @@
+// A memory budget for the caches of the engine, split over the transposition table, the solver cache and the trace buffer.
+// Every cache gets a fixed share, so the engine stays within the budget on small machines and servers.
+
+use std::{env, fmt, fs};
+
+use crate::{
+    solver::Solver,
+    trace::TraceBuffer,
+    transposition::{ProofTable, Replacement, TranspositionTable},
+};
+
+const MIB: usize = 1 << 20;
+
+/// How much memory the caches may use together, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudget {
+    total: usize,
+}
+
+impl MemoryBudget {
+    /// The budget without configuration.
+    pub const DEFAULT_MIB: usize = 256;
+
+    /// Create a budget of `bytes` for all caches together.
+    pub fn new(bytes: usize) -> Self {
+        MemoryBudget { total: bytes }
+    }
+
+    /// Create a budget of `mib` mebibytes.
+    pub fn from_mib(mib: usize) -> Self {
+        MemoryBudget::new(mib.saturating_mul(MIB))
+    }
+
+    /// Read the budget in mebibytes from `$QUARTO_MEMORY`, or use the default budget.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("QUARTO_MEMORY") {
+            Ok(mib) => mib
+                .trim()
+                .parse()
+                .map(MemoryBudget::from_mib)
+                .map_err(|_| format!("QUARTO_MEMORY should be a number of MiB, got '{}'", mib)),
+            Err(_) => Ok(MemoryBudget::default()),
+        }
+    }
+
+    /// The budget of all caches together, in bytes.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The share of the transposition table: half of the budget.
+    pub fn table_bytes(&self) -> usize {
+        self.total / 2
+    }
+
+    /// The share of the solver cache: three eighths of the budget.
+    pub fn solver_bytes(&self) -> usize {
+        self.total / 8 * 3
+    }
+
+    /// The share of the trace buffer: an eighth of the budget.
+    pub fn trace_bytes(&self) -> usize {
+        self.total / 8
+    }
+
+    /// A transposition table that fits in its share.
+    pub fn table(&self, policy: Replacement) -> TranspositionTable {
+        TranspositionTable::new(self.table_bytes() / TranspositionTable::ENTRY_SIZE, policy)
+    }
+
+    /// The number of positions the solver table holds.
+    pub fn solver_positions(&self) -> usize {
+        ProofTable::rounded_capacity(self.solver_bytes() / ProofTable::ENTRY_SIZE)
+    }
+
+    /// A solver whose table fits in its share, keeping deep proofs and recent ones in two tiers.
+    pub fn solver(&self) -> Solver {
+        let table = ProofTable::new(self.solver_positions(), Replacement::DepthPreferred);
+        Solver::new().with_table(table.with_two_tiers())
+    }
+
+    /// The number of full games the trace buffer may hold.
+    pub fn trace_games(&self) -> usize {
+        self.trace_bytes() / TraceBuffer::GAME_SIZE
+    }
+
+    /// A trace buffer that fits in its share.
+    pub fn trace_buffer(&self) -> TraceBuffer {
+        TraceBuffer::new(self.trace_games())
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget::from_mib(MemoryBudget::DEFAULT_MIB)
+    }
+}
+
+/// The resident memory of this process in bytes, where the system tells it.
+pub fn resident_memory() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    // The system counts the resident pages in KiB, whatever the size of a page.
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?;
+    match line.split_whitespace().collect::<Vec<&str>>()[..] {
+        [kib, "kB"] => kib.parse::<usize>().ok().map(|kib| kib * 1024),
+        _ => None,
+    }
+}
+
+/// Format `bytes` in mebibytes with one decimal.
+pub fn format_mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / MIB as f64)
+}
+
+impl fmt::Display for MemoryBudget {
+    /// A table with the share of every cache, and how much it holds in that share.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots = TranspositionTable::rounded_capacity(
+            self.table_bytes() / TranspositionTable::ENTRY_SIZE,
+        );
+        writeln!(f, "{:<20}{:>12}", "Memory budget", format_mib(self.total))?;
+        writeln!(
+            f,
+            "{:<20}{:>12}  {} entries",
+            "Transposition table",
+            format_mib(self.table_bytes()),
+            slots
+        )?;
+        writeln!(
+            f,
+            "{:<20}{:>12}  {} positions",
+            "Solver cache",
+            format_mib(self.solver_bytes()),
+            self.solver_positions()
+        )?;
+        writeln!(
+            f,
+            "{:<20}{:>12}  {} games",
+            "Trace buffer",
+            format_mib(self.trace_bytes()),
+            self.trace_games()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shares_fit_budget() {
+        let budget = MemoryBudget::from_mib(64);
+        assert!(
+            budget.table_bytes() + budget.solver_bytes() + budget.trace_bytes() <= budget.total()
+        );
+        assert!(budget.table(Replacement::Always).memory_usage() <= budget.table_bytes());
+        assert!(budget.trace_games() > 0);
+    }
+
+    #[test]
+    fn test_tiny_budget() {
+        let budget = MemoryBudget::new(0);
+        assert_eq!(budget.table(Replacement::Always).capacity(), 1);
+        assert_eq!(budget.solver_positions(), 0);
+        assert_eq!(budget.trace_buffer().len(), 0);
+    }
+
+    #[test]
+    fn test_readout() {
+        let readout = MemoryBudget::from_mib(8).to_string();
+        assert!(readout.starts_with("Memory budget"));
+        assert!(readout.contains("8.0 MiB"));
+        assert!(readout.contains("4.0 MiB"));
+        assert_eq!(readout.lines().count(), 4);
+        #[cfg(target_os = "linux")]
+        assert!(resident_memory().is_some_and(|bytes| bytes > 0));
+    }
+}
@@ -0,0 +1,1538 @@
+// Games between players on different machines, hosted by a server that relays the moves over WebSockets.
+// Every message is one line of text, pieces are the byte of `Piece::to_u8` in hex and a board is the bytes of its cells.
+// The players can chat during the game, what they say is read on its own thread so it arrives while the other thinks.
+// The server pings the players of a game, a client that stops answering is treated like one that lost the connection.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Read, Write},
+    net::TcpStream,
+    str::FromStr,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tls")]
+use rustls::ServerConfig;
+use tungstenite::{Message, WebSocket};
+
+#[cfg(feature = "tls")]
+use crate::tls::{self, ClientTls};
+
+use crate::{
+    board::Board,
+    browse::{GameListKind, GameSummary},
+    clock::{Clock, TimeControl},
+    events::GameEvent,
+    game::{GameResult, QuartoGame},
+    observer::GameObserver,
+    player::Player,
+    printable::Piece,
+    rating::Rating,
+    record::GameRecord,
+    season::Standings,
+    spectate::SpectatorMessage,
+};
+
+/// How long a read waits before the lock on the connection is released, so messages can be sent meanwhile.
+const POLL: Duration = Duration::from_millis(20);
+
+/// How often the server pings the players of a game, and how long it waits to hear from them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Send a ping after this much time without sending one.
+    pub interval: Duration,
+    /// The client is gone after this much time without a message, a pong or anything else.
+    pub timeout: Duration,
+}
+
+impl Heartbeat {
+    /// Ping every `interval`, `timeout` is raised to `interval` if it is smaller.
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Heartbeat {
+            interval,
+            timeout: timeout.max(interval),
+        }
+    }
+}
+
+impl Default for Heartbeat {
+    /// A ping every 5 seconds, and a timeout after three missed answers.
+    fn default() -> Self {
+        Heartbeat::new(Duration::from_secs(5), Duration::from_secs(15))
+    }
+}
+
+/// A message of the network protocol, from the server to a client or back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetMessage {
+    /// Server: you play on `seat`, and resume with `session` after losing the connection, `welcome 0 <session>`.
+    Welcome { seat: usize, session: u64 },
+    /// Server: hand a piece over to the opponent, `ask-piece <board>`.
+    AskPiece(Board),
+    /// Server: place `piece` on the board, `ask-move 81 <board>`.
+    AskMove(u8, Board),
+    /// Server: the player on `seat` handed over `piece`, `given 0 81`.
+    Given { seat: usize, piece: u8 },
+    /// Server: the player on `seat` placed `piece` on `index`, `placed 1 81 5`.
+    Placed { seat: usize, piece: u8, index: u8 },
+    /// Server: the time each player is allowed for their next decision, sent when a clock starts, `clock 65000 4000`.
+    Clock([Duration; 2]),
+    /// Server: the game is over, `over win 0`, `over timeout 1`, `over draw` or `over error`.
+    Over(GameResult),
+    /// Client: the piece to hand over as the decision of `ply`, the number of decisions before it, `give 4 81`.
+    Give { ply: usize, piece: u8 },
+    /// Client: the cell to place the piece on as the decision of `ply`, `place 5 7`.
+    Place { ply: usize, index: u8 },
+    /// Either side: a message that could not be handled, `error <text>`.
+    Error(String),
+    /// Client: enter the lobby under a name of one word, `hello alice`.
+    Hello(String),
+    /// Client: ask for the players waiting in the lobby, `list`.
+    List,
+    /// Server: the players waiting in the lobby, `waiting alice bob`.
+    Waiting(Vec<String>),
+    /// Client: challenge a waiting player, or accept their challenge, `challenge bob`.
+    Challenge(String),
+    /// Server: a waiting player challenged you, `challenged alice`.
+    Challenged(String),
+    /// Client: play against a computer player of the registry, `bot smart`.
+    Bot(String),
+    /// Client: take the seat of `session` again on a new connection, `resume <session>`.
+    Resume(u64),
+    /// Server: the games in progress, `playing 3 alice bob 4 dave smart`.
+    Playing(Vec<HostedGame>),
+    /// Client: follow game `id` without playing, from the moves so far to the end, `watch 3`.
+    Watch(u64),
+    /// Client: say something to everyone who follows the game, `say good luck`.
+    Say(String),
+    /// Server: a player of the game said something, `chat alice good luck`.
+    Chat { from: String, text: String },
+    /// Client: ask for the standings of a season, or of the current one with `None`, `standings current`.
+    Standings(Option<u32>),
+    /// Server: the rated players of a season with its dates, `season 1 <start> <end> alice 1516 1 bob 1484 1`.
+    Season(Standings),
+    /// Client: ask for a page of a listing of the games, `browse recent 1 20` for the first 20 recent games.
+    Browse {
+        kind: GameListKind,
+        page: u32,
+        per_page: u32,
+    },
+    /// Server: a game of the page asked for, `game 3 alice bob 7`, or `game - carol - 2 min` for a waiting player.
+    Game(GameSummary),
+    /// Server: the page asked for is complete, the games came before, `page 1 3`.
+    Paged { page: u32, pages: u32 },
+    /// Either side: check that the other side is still there, `ping`.
+    Ping,
+    /// Either side: the answer to a ping, `pong`.
+    Pong,
+}
+
+/// A game in progress on a server, that can be watched by its id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostedGame {
+    pub id: u64,
+    /// The names of the players in the order of play, a bot by its name in the registry.
+    pub players: [String; 2],
+}
+
+impl fmt::Display for HostedGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} - {}", self.id, self.players[0], self.players[1])
+    }
+}
+
+impl NetMessage {
+    /// The event of the game a message of the server tells, to follow the game like a spectator.
+    pub fn event(&self) -> Option<GameEvent> {
+        match *self {
+            NetMessage::Given { seat, piece } => Some(GameEvent::PieceGiven {
+                player: seat,
+                piece,
+            }),
+            NetMessage::Placed { seat, piece, index } => Some(GameEvent::PiecePlaced {
+                player: seat,
+                piece,
+                index,
+            }),
+            NetMessage::Over(result) => Some(GameEvent::GameEnded { result }),
+            _ => None,
+        }
+    }
+
+    /// What a spectator follows of a message of the server: an event of the game, or the clocks.
+    pub fn spectator(&self) -> Option<SpectatorMessage> {
+        match *self {
+            NetMessage::Clock(clocks) => Some(SpectatorMessage::Clock(clocks)),
+            _ => self.event().map(SpectatorMessage::Event),
+        }
+    }
+
+    /// The ply of a question of the server, or of the decision of a client that answers it.
+    pub fn ply(&self) -> Option<usize> {
+        match *self {
+            NetMessage::AskPiece(board) => Some(ply(&board, false)),
+            NetMessage::AskMove(_, board) => Some(ply(&board, true)),
+            NetMessage::Give { ply, .. } | NetMessage::Place { ply, .. } => Some(ply),
+            _ => None,
+        }
+    }
+}
+
+/// The ply of the decision on `board`: two decisions for every piece on it, and one more to place the next piece.
+fn ply(board: &Board, placing: bool) -> usize {
+    2 * board.occupied_cells_mask().count_ones() as usize + placing as usize
+}
+
+/// Write a piece number as the hex byte of the protocol.
+fn piece_hex(piece: u8) -> String {
+    format!("{:02x}", Piece::from_number(piece).to_u8())
+}
+
+/// Read a piece number from the hex byte of the protocol.
+fn parse_piece(hex: &str) -> Option<u8> {
+    let byte = u8::from_str_radix(hex, 16).ok()?;
+    Piece::from_u8(byte).map(|piece| piece.to_number())
+}
+
+/// Write the cells of a board as hex bytes, 00 for an empty cell.
+fn board_hex(board: &Board) -> String {
+    (0..16)
+        .map(|index| board.piece_at(index).map_or("00".to_string(), piece_hex))
+        .collect()
+}
+
+/// Read a board from the hex bytes of its cells.
+fn parse_board(hex: &str) -> Option<Board> {
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut board = Board::new();
+    for index in 0..16u8 {
+        let cell = hex.get(index as usize * 2..index as usize * 2 + 2)?;
+        if cell != "00" {
+            board.put_piece(parse_piece(cell)?, index).ok()?;
+        }
+    }
+    Some(board)
+}
+
+impl fmt::Display for NetMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetMessage::Welcome { seat, session } => write!(f, "welcome {} {:016x}", seat, session),
+            NetMessage::AskPiece(board) => write!(f, "ask-piece {}", board_hex(board)),
+            NetMessage::AskMove(piece, board) => {
+                write!(f, "ask-move {} {}", piece_hex(*piece), board_hex(board))
+            }
+            NetMessage::Given { seat, piece } => write!(f, "given {} {}", seat, piece_hex(*piece)),
+            NetMessage::Placed { seat, piece, index } => {
+                write!(f, "placed {} {} {}", seat, piece_hex(*piece), index)
+            }
+            NetMessage::Clock([first, second]) => {
+                write!(f, "clock {} {}", first.as_millis(), second.as_millis())
+            }
+            NetMessage::Over(result) => match result {
+                GameResult::Win(seat) => write!(f, "over win {}", seat),
+                GameResult::Timeout(seat) => write!(f, "over timeout {}", seat),
+                GameResult::Draw => write!(f, "over draw"),
+                GameResult::Error => write!(f, "over error"),
+            },
+            NetMessage::Give { ply, piece } => write!(f, "give {} {}", ply, piece_hex(*piece)),
+            NetMessage::Place { ply, index } => write!(f, "place {} {}", ply, index),
+            NetMessage::Error(text) => write!(f, "error {}", text),
+            NetMessage::Hello(name) => write!(f, "hello {}", name),
+            NetMessage::List => write!(f, "list"),
+            NetMessage::Waiting(names) if names.is_empty() => write!(f, "waiting"),
+            NetMessage::Waiting(names) => write!(f, "waiting {}", names.join(" ")),
+            NetMessage::Challenge(name) => write!(f, "challenge {}", name),
+            NetMessage::Challenged(name) => write!(f, "challenged {}", name),
+            NetMessage::Bot(name) => write!(f, "bot {}", name),
+            NetMessage::Resume(session) => write!(f, "resume {:016x}", session),
+            NetMessage::Playing(games) => {
+                write!(f, "playing")?;
+                for game in games {
+                    write!(f, " {} {} {}", game.id, game.players[0], game.players[1])?;
+                }
+                Ok(())
+            }
+            NetMessage::Watch(id) => write!(f, "watch {}", id),
+            NetMessage::Say(text) => write!(f, "say {}", text),
+            NetMessage::Chat { from, text } => write!(f, "chat {} {}", from, text),
+            NetMessage::Standings(None) => write!(f, "standings current"),
+            NetMessage::Standings(Some(season)) => write!(f, "standings {}", season),
+            NetMessage::Season(standings) => {
+                let Standings {
+                    season, start, end, ..
+                } = standings;
+                write!(f, "season {} {} {}", season, start, end)?;
+                for (name, rating) in &standings.players {
+                    write!(f, " {} {} {}", name, rating.elo, rating.games)?;
+                }
+                Ok(())
+            }
+            NetMessage::Browse {
+                kind,
+                page,
+                per_page,
+            } => write!(f, "browse {} {} {}", kind, page, per_page),
+            NetMessage::Game(game) => write!(f, "game {}", game),
+            NetMessage::Paged { page, pages } => write!(f, "page {} {}", page, pages),
+            NetMessage::Ping => write!(f, "ping"),
+            NetMessage::Pong => write!(f, "pong"),
+        }
+    }
+}
+
+impl FromStr for NetMessage {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a message of the protocol", line.trim());
+        if let Some(text) = line.strip_prefix("error ") {
+            return Ok(NetMessage::Error(text.to_string()));
+        }
+        if let Some(text) = line.strip_prefix("say ") {
+            return Ok(NetMessage::Say(text.to_string()));
+        }
+        if let Some(game) = line.strip_prefix("game ") {
+            return game.parse().map(NetMessage::Game).map_err(|_| invalid());
+        }
+        if let Some((from, text)) = line
+            .strip_prefix("chat ")
+            .and_then(|rest| rest.split_once(' '))
+        {
+            return Ok(NetMessage::Chat {
+                from: from.to_string(),
+                text: text.to_string(),
+            });
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| word.parse().map_err(|_| invalid());
+        let index = |word: &str| {
+            word.parse::<u8>()
+                .ok()
+                .filter(|&index| index < 16)
+                .ok_or_else(invalid)
+        };
+        let piece = |word: &str| parse_piece(word).ok_or_else(invalid);
+        let board = |word: &str| parse_board(word).ok_or_else(invalid);
+        let session = |word: &str| u64::from_str_radix(word, 16).map_err(|_| invalid());
+        match words[..] {
+            ["welcome", seat, id] => Ok(NetMessage::Welcome {
+                seat: number(seat)?,
+                session: session(id)?,
+            }),
+            ["ask-piece", cells] => Ok(NetMessage::AskPiece(board(cells)?)),
+            ["ask-move", hex, cells] => Ok(NetMessage::AskMove(piece(hex)?, board(cells)?)),
+            ["given", seat, hex] => Ok(NetMessage::Given {
+                seat: number(seat)?,
+                piece: piece(hex)?,
+            }),
+            ["placed", seat, hex, cell] => Ok(NetMessage::Placed {
+                seat: number(seat)?,
+                piece: piece(hex)?,
+                index: index(cell)?,
+            }),
+            ["clock", first, second] => {
+                let time = |word: &str| {
+                    word.parse()
+                        .map(Duration::from_millis)
+                        .map_err(|_| invalid())
+                };
+                Ok(NetMessage::Clock([time(first)?, time(second)?]))
+            }
+            ["over", "win", seat] => Ok(NetMessage::Over(GameResult::Win(number(seat)?))),
+            ["over", "timeout", seat] => Ok(NetMessage::Over(GameResult::Timeout(number(seat)?))),
+            ["over", "draw"] => Ok(NetMessage::Over(GameResult::Draw)),
+            ["over", "error"] => Ok(NetMessage::Over(GameResult::Error)),
+            ["give", ply, hex] => Ok(NetMessage::Give {
+                ply: number(ply)?,
+                piece: piece(hex)?,
+            }),
+            ["place", ply, cell] => Ok(NetMessage::Place {
+                ply: number(ply)?,
+                index: index(cell)?,
+            }),
+            ["hello", name] => Ok(NetMessage::Hello(name.to_string())),
+            ["list"] => Ok(NetMessage::List),
+            ["browse", kind, page, per_page] => Ok(NetMessage::Browse {
+                kind: kind.parse().map_err(|_| invalid())?,
+                page: number(page)? as u32,
+                per_page: number(per_page)? as u32,
+            }),
+            ["page", page, pages] => Ok(NetMessage::Paged {
+                page: number(page)? as u32,
+                pages: number(pages)? as u32,
+            }),
+            ["ping"] => Ok(NetMessage::Ping),
+            ["pong"] => Ok(NetMessage::Pong),
+            ["waiting", ref names @ ..] => Ok(NetMessage::Waiting(
+                names.iter().map(|name| name.to_string()).collect(),
+            )),
+            ["challenge", name] => Ok(NetMessage::Challenge(name.to_string())),
+            ["challenged", name] => Ok(NetMessage::Challenged(name.to_string())),
+            ["bot", name] => Ok(NetMessage::Bot(name.to_string())),
+            ["resume", id] => Ok(NetMessage::Resume(session(id)?)),
+            ["playing", ref games @ ..] if games.len() % 3 == 0 => games
+                .chunks(3)
+                .map(|game| {
+                    Ok(HostedGame {
+                        id: number(game[0])? as u64,
+                        players: [game[1].to_string(), game[2].to_string()],
+                    })
+                })
+                .collect::<Result<_, String>>()
+                .map(NetMessage::Playing),
+            ["watch", id] => Ok(NetMessage::Watch(number(id)? as u64)),
+            ["standings", "current"] => Ok(NetMessage::Standings(None)),
+            ["standings", season] => Ok(NetMessage::Standings(Some(number(season)? as u32))),
+            ["season", season, start, end, ref players @ ..] if players.len() % 3 == 0 => {
+                let time = |word: &str| word.parse().map_err(|_| invalid());
+                Ok(NetMessage::Season(Standings {
+                    season: number(season)? as u32,
+                    start: time(start)?,
+                    end: time(end)?,
+                    players: players
+                        .chunks(3)
+                        .map(|player| {
+                            let elo = player[1].parse().map_err(|_| invalid())?;
+                            let games = number(player[2])? as u32;
+                            Ok((player[0].to_string(), Rating { elo, games }))
+                        })
+                        .collect::<Result<_, String>>()?,
+                }))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The stream under a WebSocket: plain TCP, or TLS on either side with the `tls` feature.
+#[derive(Debug)]
+pub enum Stream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Client(Box<tls::ClientStream>),
+    #[cfg(feature = "tls")]
+    Server(Box<tls::ServerStream>),
+}
+
+impl Stream {
+    /// The TCP connection under the stream.
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            Stream::Client(stream) => &stream.sock,
+            #[cfg(feature = "tls")]
+            Stream::Server(stream) => &stream.sock,
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Client(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Server(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Client(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Server(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Client(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Server(stream) => stream.flush(),
+        }
+    }
+}
+
+/// The address of a server that hosts games, and how a client connects to it.
+#[derive(Debug, Clone)]
+pub struct Server {
+    address: String,
+    #[cfg(feature = "tls")]
+    tls: Option<ClientTls>,
+}
+
+impl Server {
+    /// The server at `address`, like `localhost:7878`, over plain WebSockets.
+    pub fn new(address: &str) -> Self {
+        Server {
+            address: address.to_string(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Connect over TLS, trusting the server as `tls` decides. The certificate is checked against the host of the address.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: ClientTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// The address of the server.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Open a new connection to the server.
+    pub fn connect(&self) -> io::Result<Connection> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return Connection::connect_tls(&self.address, tls);
+        }
+        Connection::connect(&self.address)
+    }
+}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)
+    }
+}
+
+/// A WebSocket connection that can send while another thread waits for a message on it.
+#[derive(Debug)]
+pub struct Connection {
+    socket: Mutex<WebSocket<Stream>>,
+}
+
+impl Connection {
+    /// Accept the WebSocket handshake of a client on `stream`.
+    pub fn accept(stream: TcpStream) -> io::Result<Self> {
+        Connection::handshake(Stream::Plain(stream))
+    }
+
+    /// Accept the TLS and then the WebSocket handshake of a client on `stream`.
+    #[cfg(feature = "tls")]
+    pub fn accept_tls(stream: TcpStream, config: Arc<ServerConfig>) -> io::Result<Self> {
+        Connection::handshake(Stream::Server(Box::new(tls::accept(stream, config)?)))
+    }
+
+    /// Accept the WebSocket handshake of a client on `stream`, encrypted or not.
+    fn handshake(stream: Stream) -> io::Result<Self> {
+        let socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+        Connection::polled(socket)
+    }
+
+    /// Connect to the server at `address`, like `localhost:7878`.
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let stream = Stream::Plain(TcpStream::connect(address)?);
+        let (socket, _) = tungstenite::client::client(format!("ws://{}/", address), stream)
+            .map_err(io::Error::other)?;
+        Connection::polled(socket)
+    }
+
+    /// Connect to the server at `address` over TLS, checking its certificate against the host of the address.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(address: &str, tls: &ClientTls) -> io::Result<Self> {
+        let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let stream = Stream::Client(Box::new(tls.connect(address, host)?));
+        let (socket, _) = tungstenite::client::client(format!("wss://{}/", address), stream)
+            .map_err(io::Error::other)?;
+        Connection::polled(socket)
+    }
+
+    /// Make reads give up after `POLL`, so a waiting read does not hold the lock for long.
+    fn polled(socket: WebSocket<Stream>) -> io::Result<Self> {
+        socket.get_ref().tcp().set_read_timeout(Some(POLL))?;
+        Ok(Connection {
+            socket: Mutex::new(socket),
+        })
+    }
+
+    /// A poisoned lock still holds a usable socket.
+    fn lock(&self) -> MutexGuard<'_, WebSocket<Stream>> {
+        self.socket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send `message`.
+    pub fn send(&self, message: &NetMessage) -> io::Result<()> {
+        self.lock()
+            .send(Message::text(message.to_string()))
+            .map_err(io::Error::other)
+    }
+
+    /// Wait for the next message, up to `timeout` if there is one. Returns `None` when the time is up.
+    /// A message that is not of the protocol is an error of kind `InvalidData`, the connection stays usable.
+    pub fn receive(&self, timeout: Option<Duration>) -> io::Result<Option<NetMessage>> {
+        let start = Instant::now();
+        loop {
+            match self.lock().read() {
+                Ok(Message::Text(text)) => {
+                    return text
+                        .parse()
+                        .map(Some)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+                Ok(Message::Close(_)) => return Err(io::ErrorKind::ConnectionAborted.into()),
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => return Err(io::Error::other(e)),
+            }
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return Ok(None);
+            }
+            // Let a sender take the lock before reading again.
+            thread::yield_now();
+        }
+    }
+
+    /// Close the connection, telling the other side.
+    pub fn close(&self) {
+        let mut socket = self.lock();
+        socket.close(None).ok();
+        socket.flush().ok();
+    }
+}
+
+/// A new session id from the random source of the operating system.
+/// The id is all it takes to take over a seat, so it must not be predictable like `fastrand`.
+fn new_session() -> u64 {
+    let mut bytes = [0; 8];
+    getrandom::fill(&mut bytes).expect("the operating system has a secure random source");
+    u64::from_le_bytes(bytes)
+}
+
+/// The connection to the client on a seat of a hosted game, known by a random session id.
+/// A client that lost its connection resumes the session on a new one, which replaces the old one.
+/// Sessions live in the memory of the server, they cannot be resumed after it restarts.
+#[derive(Debug)]
+pub struct Link {
+    pub seat: usize,
+    pub session: u64,
+    /// The name the player chats under.
+    pub name: String,
+    connection: Mutex<Arc<Connection>>,
+    replaced: Condvar,
+    /// Set after the game, when the session can no longer be resumed.
+    over: AtomicBool,
+    heartbeat: Heartbeat,
+}
+
+impl Link {
+    /// Link `seat` to `connection`, under a new session id. The player chats as player 1 or 2.
+    pub fn new(seat: usize, connection: Arc<Connection>) -> Self {
+        Link {
+            seat,
+            session: new_session(),
+            name: format!("player{}", seat + 1),
+            connection: Mutex::new(connection),
+            replaced: Condvar::new(),
+            over: AtomicBool::new(false),
+            heartbeat: Heartbeat::default(),
+        }
+    }
+
+    /// Chat as `name`, one word like in the lobby.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Ping the client as `heartbeat` says, instead of every 5 seconds.
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// A poisoned lock still holds a usable connection.
+    fn lock(&self) -> MutexGuard<'_, Arc<Connection>> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The connection to the client now.
+    pub fn connection(&self) -> Arc<Connection> {
+        self.lock().clone()
+    }
+
+    /// Continue the session on `connection`, the welcome is sent again. A game that is over cannot be resumed.
+    pub fn replace(&self, connection: Arc<Connection>) -> io::Result<()> {
+        let mut current = self.lock();
+        if self.over.load(Ordering::Relaxed) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the game of the session is over",
+            ));
+        }
+        connection.send(&NetMessage::Welcome {
+            seat: self.seat,
+            session: self.session,
+        })?;
+        std::mem::replace(&mut *current, connection).close();
+        self.replaced.notify_all();
+        Ok(())
+    }
+
+    /// Whether the game of the session is over.
+    fn over(&self) -> bool {
+        self.over.load(Ordering::Relaxed)
+    }
+
+    /// End the session after the game, and disconnect the client.
+    fn finish(&self) {
+        let connection = self.lock();
+        self.over.store(true, Ordering::Relaxed);
+        connection.close();
+    }
+
+    /// Wait up to `grace` for a connection other than `old`, and tell if there is one.
+    fn wait_replaced(&self, old: &Arc<Connection>, grace: Duration) -> bool {
+        let (connection, _) = self
+            .replaced
+            .wait_timeout_while(self.lock(), grace, |connection| {
+                Arc::ptr_eq(connection, old)
+            })
+            .unwrap_or_else(|e| e.into_inner());
+        !Arc::ptr_eq(&connection, old)
+    }
+}
+
+/// A message read on a connection of a link, with the connection it came from.
+type Answer = (Arc<Connection>, io::Result<NetMessage>);
+
+/// Read the connections of `link` until the game is over, passing what the player says on to `relay`
+/// and sending the other messages to `answers`. A lost connection is followed by the one that replaces it.
+/// The client is pinged on the heartbeat of the link, a connection that stays silent past its timeout is closed and lost.
+fn listen(link: &Link, relay: &Relay, answers: &Sender<Answer>) {
+    loop {
+        let connection = link.connection();
+        let (mut heard, mut pinged) = (Instant::now(), Instant::now());
+        loop {
+            if pinged.elapsed() >= link.heartbeat.interval {
+                connection.send(&NetMessage::Ping).ok();
+                pinged = Instant::now();
+            }
+            let received = connection.receive(Some(POLL * 5));
+            if let Ok(Some(_)) = received {
+                heard = Instant::now();
+            }
+            let answer = match received {
+                Ok(Some(NetMessage::Say(text))) => {
+                    relay.chat(&link.name, &text);
+                    continue;
+                }
+                Ok(Some(NetMessage::Ping)) => {
+                    connection.send(&NetMessage::Pong).ok();
+                    continue;
+                }
+                Ok(Some(NetMessage::Pong)) => continue,
+                Ok(Some(message)) => Ok(message),
+                Ok(None) if link.over() => return,
+                Ok(None) if !Arc::ptr_eq(&link.connection(), &connection) => break,
+                Ok(None) if heard.elapsed() >= link.heartbeat.timeout => {
+                    connection.close();
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the client stopped answering",
+                    ))
+                }
+                Ok(None) => continue,
+                Err(e) => Err(e),
+            };
+            let lost = matches!(&answer, Err(e) if e.kind() != io::ErrorKind::InvalidData);
+            if answers.send((connection.clone(), answer)).is_err() {
+                return;
+            }
+            if lost {
+                break;
+            }
+        }
+        while !link.wait_replaced(&connection, POLL * 5) {
+            if link.over() {
+                return;
+            }
+        }
+    }
+}
+
+/// A player on another machine, asked for its decisions over its link to the server.
+/// Answers that are not allowed are refused and asked again. A player that loses the connection, or stops
+/// answering the pings, forfeits the game, unless it resumes the session within the grace period and the question
+/// is asked again.
+#[derive(Debug)]
+pub struct RemotePlayer {
+    link: Arc<Link>,
+    grace: Duration,
+    gone: AtomicBool,
+    answers: Receiver<Answer>,
+}
+
+impl RemotePlayer {
+    /// Ask the player on the other side of `link`, who forfeits as soon as the connection is lost.
+    /// What the player says is relayed on `relay` as soon as it arrives.
+    pub fn new(link: Arc<Link>, relay: Arc<Relay>) -> Self {
+        let (sender, answers) = mpsc::channel();
+        let listened = link.clone();
+        thread::spawn(move || listen(&listened, &relay, &sender));
+        RemotePlayer {
+            link,
+            grace: Duration::ZERO,
+            gone: AtomicBool::new(false),
+            answers,
+        }
+    }
+
+    /// Wait up to `grace` for a player that lost the connection to resume the session.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Send `question` on `connection` and wait for an answer that `accept` takes, refusing the others.
+    /// A decision of an earlier ply was taken already and sent again after a reconnect, it is ignored.
+    /// A connection that is replaced meanwhile is an error of kind `ConnectionAborted`.
+    fn ask_on<T>(
+        &self,
+        connection: &Arc<Connection>,
+        question: &NetMessage,
+        accept: &impl Fn(NetMessage) -> Result<T, String>,
+    ) -> io::Result<T> {
+        connection.send(question)?;
+        loop {
+            let refusal = match self.answers.recv_timeout(POLL * 5) {
+                // A message of a connection that was replaced is too late.
+                Ok((from, _)) if !Arc::ptr_eq(&from, connection) => continue,
+                Ok((_, Ok(message))) => match message.ply().zip(question.ply()) {
+                    Some((sent, asked)) if sent < asked => continue,
+                    Some((sent, asked)) if sent > asked => {
+                        format!(
+                            "ply {} is not asked yet, the question is of ply {}",
+                            sent, asked
+                        )
+                    }
+                    _ => match accept(message) {
+                        Ok(value) => return Ok(value),
+                        Err(refusal) => refusal,
+                    },
+                },
+                Ok((_, Err(e))) if e.kind() == io::ErrorKind::InvalidData => e.to_string(),
+                Ok((_, Err(e))) => return Err(e),
+                Err(RecvTimeoutError::Timeout)
+                    if Arc::ptr_eq(&self.link.connection(), connection) =>
+                {
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::ErrorKind::ConnectionAborted.into());
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(io::ErrorKind::BrokenPipe.into()),
+            };
+            connection.send(&NetMessage::Error(refusal))?;
+        }
+    }
+
+    /// Ask `question` until an answer is accepted, on the connection of the session now.
+    fn ask<T>(
+        &self,
+        question: NetMessage,
+        accept: impl Fn(NetMessage) -> Result<T, String>,
+    ) -> Option<T> {
+        while !self.gone.load(Ordering::Relaxed) {
+            let connection = self.link.connection();
+            match self.ask_on(&connection, &question, &accept) {
+                Ok(answer) => return Some(answer),
+                Err(_) if self.link.wait_replaced(&connection, self.grace) => {}
+                Err(_) => self.gone.store(true, Ordering::Relaxed),
+            }
+        }
+        None
+    }
+}
+
+impl Player for RemotePlayer {
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        self.ask(NetMessage::AskPiece(*board), |message| match message {
+            NetMessage::Give { piece, .. } if board.valid_pieces().contains(&piece) => Ok(piece),
+            NetMessage::Give { piece, .. } => {
+                Err(format!("piece {} is not available", piece_hex(piece)))
+            }
+            other => Err(format!("expected give, got {}", other)),
+        })
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        self.ask(
+            NetMessage::AskMove(piece, *board),
+            |message| match message {
+                NetMessage::Place { index, .. } if board.empty_index(index) => Ok(index),
+                NetMessage::Place { index, .. } => Err(format!("cell {} is not empty", index)),
+                other => Err(format!("expected place, got {}", other)),
+            },
+        )
+    }
+
+    /// The server calls Quarto for the remote player, whenever a line is won.
+    fn quarto(&self, board: &Board) -> bool {
+        board.has_winner()
+    }
+
+    fn forfeited(&self) -> bool {
+        self.gone.load(Ordering::Relaxed)
+    }
+}
+
+/// The clients that follow a hosted game, and the moves so far and the last clocks for the spectators that come in late.
+#[derive(Debug, Default)]
+struct Audience {
+    links: Vec<Arc<Link>>,
+    spectators: Vec<Arc<Connection>>,
+    history: Vec<NetMessage>,
+    clock: Option<NetMessage>,
+}
+
+impl Audience {
+    /// Send `message` to every player and spectator. A player without a connection misses it,
+    /// a spectator without one is let go.
+    fn tell(&mut self, message: &NetMessage) {
+        for link in &self.links {
+            link.connection().send(message).ok();
+        }
+        self.spectators
+            .retain(|spectator| spectator.send(message).is_ok());
+    }
+}
+
+/// Drop the control characters of `text` from a peer, so escape sequences cannot take over the terminal it is printed on.
+pub fn strip_controls(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Sends every move of a hosted game to the players and the spectators that follow it, and what the players say.
+#[derive(Debug, Default)]
+pub struct Relay {
+    audience: Mutex<Audience>,
+}
+
+impl Relay {
+    /// Relay the moves to nobody yet.
+    pub fn new() -> Self {
+        Relay::default()
+    }
+
+    /// A poisoned lock still holds a usable audience.
+    fn lock(&self) -> MutexGuard<'_, Audience> {
+        self.audience.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Relay the moves to the player of `link`.
+    pub fn seat(&self, link: Arc<Link>) {
+        self.lock().links.push(link);
+    }
+
+    /// Relay the moves so far and the moves to come to the spectator on `connection`, who cannot play.
+    /// The clocks are relayed as they were when the last one started.
+    pub fn watch(&self, connection: Arc<Connection>) -> io::Result<()> {
+        let mut audience = self.lock();
+        for message in audience.history.iter().chain(&audience.clock) {
+            connection.send(message)?;
+        }
+        audience.spectators.push(connection);
+        Ok(())
+    }
+
+    /// Tell everyone what `from` said, without control characters. The chat is not replayed to spectators that come in late.
+    pub fn chat(&self, from: &str, text: &str) {
+        self.lock().tell(&NetMessage::Chat {
+            from: strip_controls(from),
+            text: strip_controls(text),
+        });
+    }
+
+    /// The turn the game is in, counted from 1, a turn for every piece placed.
+    pub fn turn(&self) -> usize {
+        let history = &self.lock().history;
+        1 + history
+            .iter()
+            .filter(|message| matches!(message, NetMessage::Placed { .. }))
+            .count()
+    }
+
+    /// Tell everyone `message`, and keep it for the spectators to come.
+    fn broadcast(&self, message: NetMessage) {
+        let mut audience = self.lock();
+        audience.tell(&message);
+        audience.history.push(message);
+    }
+}
+
+impl GameObserver for Relay {
+    fn on_piece_given(&self, player: usize, piece: u8, _board: &Board) {
+        self.broadcast(NetMessage::Given {
+            seat: player,
+            piece,
+        });
+    }
+
+    fn on_piece_placed(&self, player: usize, piece: u8, index: u8, _board: &Board) {
+        self.broadcast(NetMessage::Placed {
+            seat: player,
+            piece,
+            index,
+        });
+    }
+
+    /// The clocks are told to everyone, only the last ones are kept for the spectators to come.
+    fn on_clock(&self, clock: &Clock) {
+        if let (Some(first), Some(second)) = (clock.allowed(0), clock.allowed(1)) {
+            let message = NetMessage::Clock([first, second]);
+            let mut audience = self.lock();
+            audience.tell(&message);
+            audience.clock = Some(message);
+        }
+    }
+
+    fn on_game_end(&self, result: GameResult, _record: &GameRecord) {
+        self.broadcast(NetMessage::Over(result));
+        for spectator in self.lock().spectators.drain(..) {
+            spectator.close();
+        }
+    }
+}
+
+/// A seat of a hosted game: a client on a link, or a computer player on the server.
+pub enum Seat {
+    Remote(Arc<Link>),
+    Computer(Box<dyn Player>),
+}
+
+impl Seat {
+    /// The player that decides for this seat, and the link that follows the game if there is one.
+    fn into_player(
+        self,
+        grace: Duration,
+        relay: &Arc<Relay>,
+    ) -> (Box<dyn Player>, Option<Arc<Link>>) {
+        match self {
+            Seat::Remote(link) => (
+                Box::new(RemotePlayer::new(link.clone(), relay.clone()).with_grace(grace)),
+                Some(link),
+            ),
+            Seat::Computer(player) => (player, None),
+        }
+    }
+}
+
+/// Play a game between `seats` on the server, the first seat hands over the first piece.
+/// The clients are welcomed to their seat, and follow the moves on `relay` with its spectators.
+/// They are disconnected after the game, a client that loses the connection has `grace` to resume its session.
+/// The players are timed by `control`, the clocks are relayed whenever one starts.
+/// Returns the result with the record of the game.
+pub fn play_hosted(
+    seats: [Seat; 2],
+    grace: Duration,
+    control: TimeControl,
+    relay: Arc<Relay>,
+) -> (GameResult, GameRecord) {
+    let [(first, a), (second, b)] = seats.map(|seat| seat.into_player(grace, &relay));
+    let links: Vec<Arc<Link>> = [a, b].into_iter().flatten().collect();
+    for link in &links {
+        link.connection()
+            .send(&NetMessage::Welcome {
+                seat: link.seat,
+                session: link.session,
+            })
+            .ok();
+        relay.seat(link.clone());
+    }
+    let mut game = QuartoGame::new(first, second)
+        .with_clock(control)
+        .with_observer(relay);
+    let result = game.play();
+    for link in &links {
+        link.finish();
+    }
+    (result, game.history().clone())
+}
+
+/// Hears what is said in a game, with who said it.
+type ChatHandler = Box<dyn Fn(&str, &str) + Send>;
+
+/// The chat of a client in its game: sends what the player says, and hands what is said to the handlers
+/// as soon as it arrives, even while the player thinks. Clones share the chat.
+#[derive(Clone, Default)]
+pub struct Chat {
+    connection: Arc<Mutex<Option<Arc<Connection>>>>,
+    handlers: Arc<Mutex<Vec<ChatHandler>>>,
+}
+
+impl Chat {
+    /// A chat outside of a game, until it is played with.
+    pub fn new() -> Self {
+        Chat::default()
+    }
+
+    /// Say `text` to the opponent and the spectators, on one line. Fails outside of a game.
+    pub fn send_chat(&self, text: &str) -> io::Result<()> {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "there is nothing to say",
+            ));
+        }
+        match &*self.connection.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(connection) => connection.send(&NetMessage::Say(text)),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "there is no game to chat in",
+            )),
+        }
+    }
+
+    /// Call `handler` with the name of the player and what they said, for everything said from now on.
+    pub fn on_chat(&self, handler: impl Fn(&str, &str) + Send + 'static) {
+        self.handlers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(handler));
+    }
+
+    /// Chat on `connection`, or nowhere.
+    fn attach(&self, connection: Option<Arc<Connection>>) {
+        *self.connection.lock().unwrap_or_else(|e| e.into_inner()) = connection;
+    }
+
+    /// Hand what `from` said to the handlers, without control characters whatever the server let through.
+    fn hear(&self, from: &str, text: &str) {
+        let (from, text) = (strip_controls(from), strip_controls(text));
+        for handler in self
+            .handlers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            handler(&from, &text);
+        }
+    }
+}
+
+/// The decisions of a client by ply, kept until a question of a later ply shows the server took them.
+/// After a reconnect they are sent again, the server ignores those it already took.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    pending: BTreeMap<usize, NetMessage>,
+}
+
+impl Outbox {
+    /// An outbox without decisions.
+    pub fn new() -> Self {
+        Outbox::default()
+    }
+
+    /// Send the decision `answer` on `connection`, and keep it even if the connection is lost.
+    pub fn send(&mut self, connection: &Connection, answer: NetMessage) -> io::Result<()> {
+        let sent = connection.send(&answer);
+        if let Some(ply) = answer.ply() {
+            self.pending.insert(ply, answer);
+        }
+        sent
+    }
+
+    /// The server asks about `ply`, so it took every decision before it.
+    fn ack(&mut self, ply: usize) {
+        self.pending = self.pending.split_off(&ply);
+    }
+
+    /// The decision made for `ply` already, when the server asks about it again.
+    fn answered(&self, ply: usize) -> Option<NetMessage> {
+        self.pending.get(&ply).cloned()
+    }
+
+    /// Send the decisions the server may not have taken again, after resuming the session on `connection`.
+    pub fn resend(&self, connection: &Connection) -> io::Result<()> {
+        self.pending
+            .values()
+            .try_for_each(|answer| connection.send(answer))
+    }
+}
+
+/// Play the game of `connection` after the server welcomed it, with `player` deciding for this side.
+/// `show` hears every message of the server to show the game, except the chat, which goes to `chat`, and the pings,
+/// which are answered while the player thinks. The decisions go through `outbox`, which answers a question
+/// that is asked again. Returns the result of the game.
+pub fn play(
+    connection: &Arc<Connection>,
+    player: &dyn Player,
+    chat: &Chat,
+    outbox: &mut Outbox,
+    show: impl FnMut(&NetMessage),
+) -> io::Result<GameResult> {
+    let done = &AtomicBool::new(false);
+    chat.attach(Some(connection.clone()));
+    let result = thread::scope(|scope| {
+        let (sender, messages) = mpsc::channel();
+        scope.spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                let message = match connection.receive(Some(POLL * 5)) {
+                    Ok(Some(NetMessage::Chat { from, text })) => {
+                        chat.hear(&from, &text);
+                        continue;
+                    }
+                    Ok(Some(NetMessage::Ping)) => {
+                        connection.send(&NetMessage::Pong).ok();
+                        continue;
+                    }
+                    Ok(Some(message)) => Ok(message),
+                    Ok(None) => continue,
+                    Err(e) => Err(e),
+                };
+                let last = matches!(message, Ok(NetMessage::Over(_)) | Err(_));
+                if sender.send(message).is_err() || last {
+                    return;
+                }
+            }
+        });
+        let result = answer(connection, player, outbox, &messages, show);
+        done.store(true, Ordering::Relaxed);
+        result
+    });
+    chat.attach(None);
+    result
+}
+
+/// The board of the last question, and the placement that follows the answer: the piece handed over or placed,
+/// and where it was placed.
+type Expected = (Board, u8, Option<u8>);
+
+/// Check that `board` follows from the last question: the board is asked about again, or the expected placement was made.
+fn follows(board: &Board, expected: Option<Expected>) -> bool {
+    let Some((before, piece, index)) = expected else {
+        return true;
+    };
+    match before.diff(board) {
+        Some(placement) => {
+            placement.piece.to_number() == piece
+                && index.is_none_or(|index| placement.cell.index() == index)
+        }
+        None => *board == before,
+    }
+}
+
+/// Answer the questions of the server among `messages` with the decisions of `player`, until the game is over.
+/// Every board the server asks about should follow from the one before, a server that sends another one is left.
+fn answer(
+    connection: &Connection,
+    player: &dyn Player,
+    outbox: &mut Outbox,
+    messages: &Receiver<io::Result<NetMessage>>,
+    mut show: impl FnMut(&NetMessage),
+) -> io::Result<GameResult> {
+    let mut expected: Option<Expected> = None;
+    loop {
+        let message = messages
+            .recv()
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionAborted))??;
+        show(&message);
+        if let NetMessage::AskPiece(board) | NetMessage::AskMove(_, board) = &message
+            && !follows(board, expected)
+        {
+            connection.close();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the server sent a board that does not follow from the last move",
+            ));
+        }
+        let (board, in_hand) = match message {
+            NetMessage::AskPiece(board) => (board, None),
+            NetMessage::AskMove(piece, board) => (board, Some(piece)),
+            NetMessage::Over(result) => {
+                connection.close();
+                return Ok(result);
+            }
+            _ => continue,
+        };
+        let ply = ply(&board, in_hand.is_some());
+        outbox.ack(ply);
+        // A question asked again after a reconnect gets the decision made before.
+        let answer = match (outbox.answered(ply), in_hand) {
+            (Some(answer), _) => Some(answer),
+            (None, None) => player
+                .get_piece(&board)
+                .map(|piece| NetMessage::Give { ply, piece }),
+            (None, Some(piece)) => player
+                .get_move(&board, piece)
+                .map(|index| NetMessage::Place { ply, index }),
+        };
+        let Some(answer) = answer else {
+            connection.close();
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "the player did not decide",
+            ));
+        };
+        expected = match (&answer, in_hand) {
+            (NetMessage::Give { piece, .. }, _) => Some((board, *piece, None)),
+            (NetMessage::Place { index, .. }, Some(piece)) => Some((board, piece, Some(*index))),
+            _ => None,
+        };
+        outbox.send(connection, answer)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        player::ComputerPlayer,
+        strategy::{DeterministicStrategy, SmartStrategy},
+    };
+
+    #[test]
+    fn test_message_round_trip() {
+        let board = Board::new().apply_moves(&[(8, 0), (5, 15)]).unwrap();
+        let messages = [
+            NetMessage::Welcome {
+                seat: 1,
+                session: 0x2813,
+            },
+            NetMessage::Resume(u64::MAX),
+            NetMessage::AskPiece(board),
+            NetMessage::AskMove(15, board),
+            NetMessage::Given { seat: 0, piece: 3 },
+            NetMessage::Placed {
+                seat: 1,
+                piece: 0,
+                index: 12,
+            },
+            NetMessage::Over(GameResult::Win(1)),
+            NetMessage::Over(GameResult::Draw),
+            NetMessage::Clock([Duration::from_millis(65_000), Duration::from_millis(4_321)]),
+            NetMessage::Give { ply: 0, piece: 9 },
+            NetMessage::Place { ply: 31, index: 7 },
+            NetMessage::Error("cell 3 is not empty".to_string()),
+            NetMessage::Hello("alice".to_string()),
+            NetMessage::Waiting(vec![]),
+            NetMessage::Waiting(vec!["alice".to_string(), "bob".to_string()]),
+            NetMessage::Bot("smart".to_string()),
+            NetMessage::Playing(vec![HostedGame {
+                id: 3,
+                players: ["alice".to_string(), "smart".to_string()],
+            }]),
+            NetMessage::Watch(3),
+            NetMessage::Say("good luck".to_string()),
+            NetMessage::Chat {
+                from: "alice".to_string(),
+                text: "thanks, you too".to_string(),
+            },
+            NetMessage::Standings(None),
+            NetMessage::Standings(Some(2)),
+            NetMessage::Season(Standings {
+                season: 2,
+                start: 1_767_225_600,
+                end: 1_769_817_600,
+                players: vec![(
+                    "bob".to_string(),
+                    Rating {
+                        elo: 1515.9,
+                        games: 1,
+                    },
+                )],
+            }),
+            NetMessage::Browse {
+                kind: GameListKind::Recent,
+                page: 2,
+                per_page: 20,
+            },
+            NetMessage::Game(GameSummary {
+                id: None,
+                first: "carol".to_string(),
+                second: None,
+                detail: "2 min".to_string(),
+            }),
+            NetMessage::Paged { page: 2, pages: 3 },
+            NetMessage::Ping,
+            NetMessage::Pong,
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+        // Pieces travel as the byte of the protocol, hole piece 8 is 0x81.
+        assert_eq!(
+            NetMessage::Give { ply: 4, piece: 8 }.to_string(),
+            "give 4 81"
+        );
+        // The board of a question tells its ply.
+        assert_eq!(NetMessage::AskPiece(board).ply(), Some(4));
+        assert_eq!(NetMessage::AskMove(15, board).ply(), Some(5));
+        assert!(
+            NetMessage::AskPiece(board)
+                .to_string()
+                .starts_with("ask-piece 8100")
+        );
+        assert!("give 0 80".parse::<NetMessage>().is_err());
+        assert!("give 81".parse::<NetMessage>().is_err());
+        assert!("place 1".parse::<NetMessage>().is_err());
+        // 259 is not cut down to cell 3.
+        assert!("place 1 259".parse::<NetMessage>().is_err());
+        assert!("place 1 16".parse::<NetMessage>().is_err());
+        assert!("placed 0 81 259".parse::<NetMessage>().is_err());
+    }
+
+    #[test]
+    fn test_chat_strips_control_characters() {
+        let chat = Chat::new();
+        let heard = Arc::new(Mutex::new(Vec::new()));
+        let lines = heard.clone();
+        chat.on_chat(move |from, text| lines.lock().unwrap().push(format!("{}: {}", from, text)));
+        chat.hear("mallory\x1b[2J", "hi\x1b]0;owned\x07 there\r");
+        assert_eq!(*heard.lock().unwrap(), ["mallory[2J: hi]0;owned there"]);
+        assert_eq!(strip_controls("ok \u{9b}31m"), "ok 31m");
+    }
+
+    #[test]
+    fn test_hosted_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            // The clients are pinged all game long, and answer while they think.
+            let heartbeat = Heartbeat::new(POLL * 2, Duration::from_secs(2));
+            let accept = |seat| -> io::Result<Seat> {
+                let connection = Connection::accept(listener.accept()?.0)?;
+                let link = Link::new(seat, Arc::new(connection)).with_heartbeat(heartbeat);
+                Ok(Seat::Remote(Arc::new(link)))
+            };
+            let seats = [accept(0).unwrap(), accept(1).unwrap()];
+            let control = TimeControl::new().with_per_game(Duration::from_secs(60));
+            play_hosted(seats, Duration::ZERO, control, Arc::new(Relay::new()))
+        });
+        let first = {
+            let connection = Arc::new(Connection::connect(&address).unwrap());
+            thread::spawn(move || {
+                let mut seen = Vec::new();
+                let player = ComputerPlayer::new(DeterministicStrategy);
+                let chat = Chat::new();
+                let result = play(&connection, &player, &chat, &mut Outbox::new(), |message| {
+                    seen.push(message.clone())
+                });
+                (result.unwrap(), seen)
+            })
+        };
+        let connection = Arc::new(Connection::connect(&address).unwrap());
+        let player = ComputerPlayer::new(SmartStrategy::new(1));
+        let result = play(
+            &connection,
+            &player,
+            &Chat::new(),
+            &mut Outbox::new(),
+            |_| {},
+        )
+        .unwrap();
+        let (first_result, seen) = first.join().unwrap();
+        assert_eq!(result, first_result);
+        let (hosted, record) = server.join().unwrap();
+        assert_eq!(hosted, result);
+        assert_eq!(record.result(), Some(result));
+        assert_ne!(result, GameResult::Error);
+        assert!(matches!(seen[0], NetMessage::Welcome { seat: 0, .. }));
+        assert!(seen.contains(&NetMessage::Given { seat: 0, piece: 0 }));
+        assert_eq!(seen.last(), Some(&NetMessage::Over(result)));
+        assert!(!seen.contains(&NetMessage::Ping));
+        // The clocks are relayed as they start, the first player has the whole game to hand over the first piece.
+        let clocks: Vec<[Duration; 2]> = seen
+            .iter()
+            .filter_map(|message| match message {
+                NetMessage::Clock(clocks) => Some(*clocks),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(clocks[0], [Duration::from_secs(60); 2]);
+        assert!(clocks.len() >= 4);
+    }
+
+    #[test]
+    fn test_silent_client_forfeits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let connection = Connection::accept(listener.accept().unwrap().0).unwrap();
+            let heartbeat = Heartbeat::new(POLL * 2, POLL * 10);
+            let link = Link::new(0, Arc::new(connection)).with_heartbeat(heartbeat);
+            let computer = ComputerPlayer::new(DeterministicStrategy);
+            let seats = [
+                Seat::Remote(Arc::new(link)),
+                Seat::Computer(Box::new(computer)),
+            ];
+            play_hosted(
+                seats,
+                Duration::ZERO,
+                TimeControl::new(),
+                Arc::new(Relay::new()),
+            )
+        });
+        // The client reads what the server sends, but never answers, not even a ping.
+        let connection = Connection::connect(&address).unwrap();
+        let mut heard = Vec::new();
+        while let Ok(message) = connection.receive(None) {
+            heard.extend(message);
+        }
+        let (result, record) = server.join().unwrap();
+        assert_eq!(result, GameResult::Win(1));
+        assert!(record.turns().is_empty());
+        assert!(heard.contains(&NetMessage::Ping));
+        assert!(matches!(heard[0], NetMessage::Welcome { seat: 0, .. }));
+    }
+
+    #[test]
+    fn test_client_rejects_unrelated_board() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || -> io::Result<Option<NetMessage>> {
+            let connection = Connection::accept(listener.accept()?.0)?;
+            connection.send(&NetMessage::AskPiece(Board::new()))?;
+            connection.receive(None)?;
+            // The piece handed over was 0, but the board shows another one placed.
+            let board = Board::new().apply_moves(&[(5, 3)]).unwrap();
+            connection.send(&NetMessage::AskMove(1, board))?;
+            connection.receive(None)
+        });
+        let connection = Arc::new(Connection::connect(&address).unwrap());
+        let player = ComputerPlayer::new(DeterministicStrategy);
+        let error = play(
+            &connection,
+            &player,
+            &Chat::new(),
+            &mut Outbox::new(),
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!matches!(
+            server.join().unwrap(),
+            Ok(Some(NetMessage::Place { .. }))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+// A short notation for cells, pieces and moves, so people can type `B3` and `TDSH` instead of indices and bits.
+// Cells go from `A1` in the top left to `D4` in the bottom right, with the letter for the column and the digit for the row.
+
+use std::{fmt, str::FromStr};
+
+use crate::printable::Piece;
+
+/// A cell of the board in the notation `A1`–`D4`, which holds the board index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell(u8);
+
+impl Cell {
+    /// The cell on board index `index`, if it is on the board.
+    pub fn new(index: u8) -> Option<Self> {
+        (index < 16).then_some(Cell(index))
+    }
+
+    /// The board index of the cell.
+    pub fn index(self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'A' + self.0 % 4) as char, self.0 / 4 + 1)
+    }
+}
+
+impl FromStr for Cell {
+    type Err = String;
+
+    /// Parse a column `A`–`D` followed by a row `1`–`4`, in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().as_bytes() {
+            &[column, row] => {
+                let column = column.to_ascii_uppercase().wrapping_sub(b'A');
+                let row = row.wrapping_sub(b'1');
+                if column < 4 && row < 4 {
+                    Ok(Cell(row * 4 + column))
+                } else {
+                    Err(format!("'{}' is not a cell from A1 to D4", s.trim()))
+                }
+            }
+            _ => Err(format!("'{}' is not a cell from A1 to D4", s.trim())),
+        }
+    }
+}
+
+/// The letters of the code of a piece, in order, as (with the attribute, without it).
+const CODE: [(char, char); 4] = [('T', 'S'), ('D', 'L'), ('S', 'R'), ('H', 'S')];
+
+impl Piece {
+    /// The code of the piece: tall or short, dark or light, square or round, and hollow or solid.
+    /// For example `TDSH` is a tall dark square piece with a hole.
+    pub fn code(&self) -> String {
+        [self.high, self.dark, self.square, self.hole]
+            .into_iter()
+            .zip(CODE)
+            .map(|(present, (with, without))| if present { with } else { without })
+            .collect()
+    }
+}
+
+impl FromStr for Piece {
+    type Err = String;
+
+    /// Parse the code of `Piece::code`, in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letters: Vec<char> = s.trim().chars().map(|c| c.to_ascii_uppercase()).collect();
+        if letters.len() != 4 {
+            return Err(format!("'{}' is not a piece code like TDSH", s.trim()));
+        }
+        let mut attributes = [false; 4];
+        for (i, (&letter, (with, without))) in letters.iter().zip(CODE).enumerate() {
+            attributes[i] = match letter {
+                l if l == with => true,
+                l if l == without => false,
+                _ => {
+                    return Err(format!(
+                        "letter {} of '{}' must be {} or {}",
+                        i + 1,
+                        s.trim(),
+                        with,
+                        without
+                    ));
+                }
+            };
+        }
+        let [high, dark, square, hole] = attributes;
+        Ok(Piece::new(hole, square, high, dark))
+    }
+}
+
+/// A placement: `piece` goes on `cell`, written as `TDSH B3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub piece: Piece,
+    pub cell: Cell,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.piece.code(), self.cell)
+    }
+}
+
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [piece, cell] => Ok(Move {
+                piece: piece.parse()?,
+                cell: cell.parse()?,
+            }),
+            _ => Err(format!("'{}' is not a move like TDSH B3", s.trim())),
+        }
+    }
+}
+
+/// Read a board index from a cell like `B3`, or from the index itself.
+pub fn parse_index(s: &str) -> Option<u8> {
+    s.parse::<Cell>()
+        .map(Cell::index)
+        .ok()
+        .or_else(|| s.trim().parse().ok())
+}
+
+/// Read a piece number from a code like `TDSH`, or from the number itself.
+pub fn parse_piece(s: &str) -> Option<u8> {
+    s.parse::<Piece>()
+        .map(|piece| piece.to_number())
+        .ok()
+        .or_else(|| s.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells() {
+        assert_eq!("A1".parse(), Ok(Cell(0)));
+        assert_eq!("b3".parse(), Ok(Cell(9)));
+        assert_eq!("D4".parse(), Ok(Cell(15)));
+        for index in 0..16 {
+            let cell = Cell::new(index).unwrap();
+            assert_eq!(cell.to_string().parse(), Ok(cell));
+        }
+        for wrong in ["E1", "A0", "A5", "A", "A10", ""] {
+            assert!(wrong.parse::<Cell>().is_err(), "{}", wrong);
+        }
+        assert_eq!(Cell::new(16), None);
+    }
+
+    #[test]
+    fn test_piece_codes() {
+        assert_eq!("TDSH".parse(), Ok(Piece::new(true, true, true, true)));
+        assert_eq!(Piece::from_number(0).code(), "SLRS");
+        for number in 0..16 {
+            let piece = Piece::from_number(number);
+            assert_eq!(piece.code().to_lowercase().parse(), Ok(piece));
+        }
+        assert!("TDS".parse::<Piece>().is_err());
+        assert!("TXSH".parse::<Piece>().unwrap_err().contains("D or L"));
+    }
+
+    #[test]
+    fn test_moves() {
+        let placement: Move = "tdsh b3".parse().unwrap();
+        assert_eq!(placement.piece.to_number(), 15);
+        assert_eq!(placement.cell.index(), 9);
+        assert_eq!(placement.to_string(), "TDSH B3");
+        assert!("TDSH".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn test_parse_numbers_or_notation() {
+        assert_eq!(parse_index("C2"), Some(6));
+        assert_eq!(parse_index("6"), Some(6));
+        assert_eq!(parse_piece("SLRS"), Some(0));
+        assert_eq!(parse_piece("12"), Some(12));
+        assert_eq!(parse_piece("x"), None);
+    }
+}
@@ -0,0 +1,92 @@
+// Hooks to follow a game as it is played.
+// Observers are called by the game loop right after something happens, so loggers and interfaces do not need their own loop.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{board::Board, clock::Clock, game::GameResult, record::GameRecord};
+
+/// Follows a `QuartoGame`, every hook does nothing unless it is implemented.
+/// Hooks take `&self` like the other traits of the crate, use interior mutability to keep state.
+pub trait GameObserver {
+    /// `player` handed `piece` to the opponent.
+    fn on_piece_given(&self, _player: usize, _piece: u8, _board: &Board) {}
+
+    /// `player` placed `piece` on `index`, `board` already has the piece on it.
+    fn on_piece_placed(&self, _player: usize, _piece: u8, _index: u8, _board: &Board) {}
+
+    /// `player` called Quarto, `correct` tells if there really was a winning line.
+    fn on_quarto_called(&self, _player: usize, _correct: bool, _board: &Board) {}
+
+    /// The clock of a player started, in a game with a time control, `clock` tells the time everyone has.
+    fn on_clock(&self, _clock: &Clock) {}
+
+    /// The game is over.
+    fn on_game_end(&self, _result: GameResult, _record: &GameRecord) {}
+}
+
+/// A shared observer, so the caller can keep a handle to read what it collected.
+impl<O: GameObserver + ?Sized> GameObserver for Arc<O> {
+    fn on_piece_given(&self, player: usize, piece: u8, board: &Board) {
+        (**self).on_piece_given(player, piece, board)
+    }
+
+    fn on_piece_placed(&self, player: usize, piece: u8, index: u8, board: &Board) {
+        (**self).on_piece_placed(player, piece, index, board)
+    }
+
+    fn on_quarto_called(&self, player: usize, correct: bool, board: &Board) {
+        (**self).on_quarto_called(player, correct, board)
+    }
+
+    fn on_clock(&self, clock: &Clock) {
+        (**self).on_clock(clock)
+    }
+
+    fn on_game_end(&self, result: GameResult, record: &GameRecord) {
+        (**self).on_game_end(result, record)
+    }
+}
+
+/// Keeps the record of the last game that ended, to show its final board afterwards.
+#[derive(Debug, Default)]
+pub struct LastGame(Mutex<Option<(GameResult, GameRecord)>>);
+
+impl LastGame {
+    /// Create an observer that has not seen a game end yet.
+    pub fn new() -> Self {
+        LastGame(Mutex::new(None))
+    }
+
+    /// Take the result and record of the last game, leaving nothing behind.
+    pub fn take(&self) -> Option<(GameResult, GameRecord)> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+impl GameObserver for LastGame {
+    fn on_game_end(&self, result: GameResult, record: &GameRecord) {
+        *self.0.lock().unwrap() = Some((result, record.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{QuartoGame, player::ComputerPlayer, strategy::DeterministicStrategy};
+
+    use super::*;
+
+    #[test]
+    fn test_last_game() {
+        let last = Arc::new(LastGame::new());
+        let result = QuartoGame::new(
+            Box::new(ComputerPlayer::new(DeterministicStrategy)),
+            Box::new(ComputerPlayer::new(DeterministicStrategy)),
+        )
+        .with_observer(last.clone())
+        .play();
+        let (seen, record) = last.take().unwrap();
+        assert_eq!(seen, result);
+        assert!(record.final_board().is_some());
+        assert!(last.take().is_none());
+    }
+}
@@ -0,0 +1,113 @@
+// An `Evaluator` backed by an ONNX model, so an evaluation trained elsewhere can drop into the search.
+// Only built with the `onnx` feature, the model runs on the CPU with tract.
+
+use std::{fs::File, io::Read, path::Path};
+
+use tract_onnx::prelude::*;
+
+use crate::{
+    board::Board,
+    evaluator::{self, Evaluator},
+};
+
+/// Scores positions with an ONNX model that takes the 84 `evaluator::features` of a position as a `[1, 84]` float tensor.
+/// The first value of its first output is the score for the player to move, from -1 for lost to 1 for won.
+pub struct OnnxEvaluator {
+    model: TypedRunnableModel<TypedModel>,
+    /// The search score of an output of 1.
+    scale: f32,
+}
+
+impl OnnxEvaluator {
+    /// Load the model in the ONNX file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut file =
+            File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        OnnxEvaluator::read(&mut file)
+    }
+
+    /// Read an ONNX model from `reader`.
+    /// An output of 1 scores half a proven win, so a proven result always counts for more.
+    pub fn read(reader: &mut dyn Read) -> Result<Self, String> {
+        let model = tract_onnx::onnx()
+            .model_for_read(reader)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, 84]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| format!("Cannot load the model: {}", e))?;
+        Ok(OnnxEvaluator {
+            model,
+            scale: crate::search::WIN as f32 / 2.0,
+        })
+    }
+
+    /// Score an output of 1 as `scale`, at most just below a proven win.
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Run the model on the features of a position, `None` if it fails.
+    fn run(&self, board: &Board, piece: u8) -> Option<f32> {
+        let input = tract_ndarray::Array2::from_shape_vec(
+            (1, 84),
+            evaluator::features(board, piece).to_vec(),
+        )
+        .ok()?;
+        let outputs = self.model.run(tvec!(input.into_tensor().into())).ok()?;
+        outputs.first()?.as_slice::<f32>().ok()?.first().copied()
+    }
+}
+
+impl Evaluator for OnnxEvaluator {
+    /// A model that fails to run scores the position as even.
+    fn evaluate(&self, board: &Board, piece: u8) -> i32 {
+        self.run(board, piece)
+            .map_or(0, |value| (value * self.scale).round() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the protobuf field `number` holding `bytes`.
+    fn field(number: u8, bytes: &[u8]) -> Vec<u8> {
+        let mut out = vec![number << 3 | 2, bytes.len() as u8];
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    /// Write the varint field `number`, for values below 128.
+    fn varint(number: u8, value: u8) -> Vec<u8> {
+        vec![number << 3, value]
+    }
+
+    /// A model that sums its 84 inputs.
+    fn sum_model() -> Vec<u8> {
+        let dim = |size: u8| field(1, &varint(1, size));
+        let shape = [dim(1), dim(84)].concat();
+        let tensor = [varint(1, 1), field(2, &shape)].concat();
+        let input = [field(1, b"x"), field(2, &field(1, &tensor))].concat();
+        let node = [field(1, b"x"), field(2, b"y"), field(4, b"ReduceSum")].concat();
+        let graph = [
+            field(1, &node),
+            field(2, b"sum"),
+            field(11, &input),
+            field(12, &field(1, b"y")),
+        ]
+        .concat();
+        [varint(1, 7), field(7, &graph), field(8, &varint(2, 13))].concat()
+    }
+
+    #[test]
+    fn test_model_scores_features() {
+        let evaluator = OnnxEvaluator::read(&mut sum_model().as_slice())
+            .unwrap()
+            .with_scale(10.0);
+        // A placed piece with two attributes and a piece in hand with one: 1 + 2 + 1.
+        let board = Board::new().apply_moves(&[(0b0101, 3)]).unwrap();
+        assert_eq!(evaluator.evaluate(&board, 0b1000), 40);
+        assert!(OnnxEvaluator::load(Path::new("no such model.onnx")).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+// A search that splits the root over threads with rayon, behind the `parallel` feature.
+// Every root decision is searched by its own `Searcher` on a copy of the board, so the threads share nothing but the cancel token.
+
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    board::Board,
+    cancel::CancelToken,
+    search::{INFINITY, Searcher, WIN},
+};
+
+/// Pick the decision with the highest score, the lowest decision among equal scores, like the sequential search.
+fn best_of(scores: BTreeMap<u8, i32>) -> Option<(u8, i32)> {
+    scores
+        .into_iter()
+        .fold(None, |best, (decision, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((decision, score)),
+        })
+}
+
+/// Find the best index to place `piece` on, together with its score.
+/// Every pair of a placement and the piece handed over after it is searched on its own thread.
+/// Returns `None` if the board has no empty spaces.
+pub fn best_move(board: &Board, piece: u8, depth: u8, cancel: &CancelToken) -> Option<(u8, i32)> {
+    // A placement that ends the game, or is not searched deeper, has no piece to hand over.
+    let mut pairs: Vec<(u8, Board, Option<u8>)> = Vec::new();
+    for index in board.empty_spaces() {
+        let mut next = *board;
+        if next.put_piece(piece, index).is_err() {
+            continue;
+        }
+        if next.has_winner_fast() || next.board_full() || depth == 0 {
+            pairs.push((index, next, None));
+        } else {
+            pairs.extend(
+                next.valid_pieces()
+                    .into_iter()
+                    .map(|p| (index, next, Some(p))),
+            );
+        }
+    }
+    let scores: Vec<(u8, i32)> = pairs
+        .into_par_iter()
+        .map(|(index, next, give)| {
+            let score = match give {
+                None if next.has_winner_fast() => WIN + depth as i32 + 1,
+                None => 0,
+                Some(p) => -Searcher::new().with_cancel(cancel).place_value(
+                    &next,
+                    p,
+                    depth - 1,
+                    -INFINITY,
+                    INFINITY,
+                ),
+            };
+            (index, score)
+        })
+        .collect();
+    let mut moves: BTreeMap<u8, i32> = BTreeMap::new();
+    for (index, score) in scores {
+        let best = moves.entry(index).or_insert(score);
+        *best = (*best).max(score);
+    }
+    best_of(moves)
+}
+
+/// Find the best piece to hand over on `board`, together with its score for the player handing it over.
+/// Every piece is searched on its own thread.
+/// Returns `None` if there are no pieces left.
+pub fn best_piece(board: &Board, depth: u8, cancel: &CancelToken) -> Option<(u8, i32)> {
+    let scores: BTreeMap<u8, i32> = board
+        .valid_pieces()
+        .into_par_iter()
+        .map(|piece| {
+            let mut searcher = Searcher::new().with_cancel(cancel);
+            (
+                piece,
+                -searcher.place_value(board, piece, depth, -INFINITY, INFINITY),
+            )
+        })
+        .collect();
+    best_of(scores)
+}
+
+/// Run `search` for every depth up to and including `depth`, stopping early when a forced win is found.
+/// Depth 0 is always completed, so when cancelled the result of the last completed depth is returned.
+fn deepen<F>(depth: u8, cancel: &CancelToken, search: F) -> Option<(u8, i32)>
+where
+    F: Fn(u8) -> Option<(u8, i32)>,
+{
+    let mut best = None;
+    for d in 0..=depth {
+        let result = search(d);
+        if d > 0 && cancel.is_cancelled() {
+            return best;
+        }
+        best = result;
+        if best?.1 >= WIN {
+            break;
+        }
+    }
+    best
+}
+
+/// Find the best index to place `piece` on with iterative deepening.
+pub fn deepen_move(board: &Board, piece: u8, depth: u8, cancel: &CancelToken) -> Option<(u8, i32)> {
+    deepen(depth, cancel, |d| best_move(board, piece, d, cancel))
+}
+
+/// Find the best piece to hand over with iterative deepening.
+pub fn deepen_piece(board: &Board, depth: u8, cancel: &CancelToken) -> Option<(u8, i32)> {
+    deepen(depth, cancel, |d| best_piece(board, d, cancel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{harness, random::SharedRng, search};
+
+    #[test]
+    fn test_same_decisions_as_sequential() {
+        let cancel = CancelToken::new();
+        for board in harness::random_positions(20, 10, &SharedRng::seeded(1)) {
+            for depth in 0..2 {
+                assert_eq!(
+                    best_piece(&board, depth, &cancel),
+                    search::best_piece(&board, depth)
+                );
+                if let Some(&piece) = board.valid_pieces().first() {
+                    assert_eq!(
+                        best_move(&board, piece, depth, &cancel),
+                        search::best_move(&board, piece, depth)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_board() {
+        let mut board = Board::new();
+        for i in 0..16 {
+            board.put_piece(i, i).unwrap();
+        }
+        let cancel = CancelToken::new();
+        assert_eq!(best_move(&board, 0, 2, &cancel), None);
+        assert_eq!(best_piece(&board, 2, &cancel), None);
+    }
+
+    #[test]
+    fn test_cancelled_search_keeps_completed_depth() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let board = Board::new().apply_moves(&[(0, 0), (15, 5)]).unwrap();
+        assert!(deepen_piece(&board, 3, &cancel).is_some());
+        let (piece, _) = deepen_piece(&crate::test_util::open_row(), 3, &cancel).unwrap();
+        assert_eq!(piece & 0b1001, 0b0001);
+    }
+}
@@ -0,0 +1,78 @@
+// Perft: count every legal sequence of moves from a position to a fixed depth.
+// The counts check move generation and win detection against known numbers, and time the core of the engine.
+
+use crate::board::{Board, PlacementOutcome};
+
+/// Count the sequences of `depth` moves from `board`, where a move hands over a piece and places it.
+/// A move that wins or fills the board ends its sequence early, and still counts as one.
+pub fn perft(board: &Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let (pieces, spaces) = (board.available_pieces(), board.empty_cells());
+    // Every last move ends a sequence, whatever it does.
+    if depth == 1 {
+        return (pieces.len() * spaces.len()) as u64;
+    }
+    let mut nodes = 0;
+    for piece in pieces {
+        for index in spaces {
+            let mut next = *board;
+            nodes += match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Ongoing) => perft(&next, depth - 1),
+                Ok(_) => 1,
+                Err(_) => 0,
+            };
+        }
+    }
+    nodes
+}
+
+/// Split the count of `perft` over the first moves, as (piece, index, count), to find where two counts differ.
+pub fn divide(board: &Board, depth: u8) -> Vec<(u8, u8, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let mut moves = Vec::new();
+    for piece in board.available_pieces() {
+        for index in board.empty_cells() {
+            let mut next = *board;
+            let count = match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Ongoing) => perft(&next, depth - 1),
+                Ok(_) => 1,
+                Err(_) => continue,
+            };
+            moves.push((piece, index, count));
+        }
+    }
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_counts() {
+        let board = Board::new();
+        assert_eq!(perft(&board, 0), 1);
+        assert_eq!(perft(&board, 1), 256);
+        assert_eq!(perft(&board, 2), 256 * 225);
+        let total: u64 = divide(&board, 2).iter().map(|&(_, _, count)| count).sum();
+        assert_eq!(total, perft(&board, 2));
+    }
+
+    #[test]
+    fn test_wins_end_sequences() {
+        // Three round holes on the top row: a hole or a round piece on index 3 wins and ends the sequence there.
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (9, 1), (10, 2)])
+            .unwrap();
+        let wins = (0..16)
+            .filter(|&p| board.valid_piece(p) && (p & 8 != 0 || p & 4 == 0))
+            .count() as u64;
+        assert_eq!(wins, 9);
+        // Every other move is followed by the 12 pieces left on the 12 cells left.
+        assert_eq!(perft(&board, 2), wins + (13 * 13 - wins) * 12 * 12);
+    }
+}
@@ -0,0 +1,289 @@
+// Versioned headers and migrations for everything this crate stores on disk.
+// Every file starts with a header naming its kind and version, so old files can be upgraded step by step when a format changes.
+
+use std::{error::Error, fmt};
+
+/// The kinds of data that are stored on disk, each with its own format and version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    /// A single played game, see `GameRecord`.
+    GameRecord,
+    /// A database of finished games.
+    HistoryDb,
+    /// Player profiles and preferences.
+    Profile,
+    /// Ratings of strategies over tournament runs.
+    Ratings,
+    /// Canonical positions by ply, see `PositionDb`.
+    PositionDb,
+    /// Ratings of players per season, see `Seasons`.
+    Seasons,
+    /// The fair-play review of a rated game, see `Review`.
+    Review,
+    /// A game in progress with the names of its players, see `QuartoGame::save`.
+    SavedGame,
+}
+
+impl DataKind {
+    const ALL: [DataKind; 8] = [
+        DataKind::GameRecord,
+        DataKind::HistoryDb,
+        DataKind::Profile,
+        DataKind::Ratings,
+        DataKind::PositionDb,
+        DataKind::Seasons,
+        DataKind::Review,
+        DataKind::SavedGame,
+    ];
+
+    /// The name of the kind in a header.
+    pub fn tag(self) -> &'static str {
+        match self {
+            DataKind::GameRecord => "record",
+            DataKind::HistoryDb => "history",
+            DataKind::Profile => "profile",
+            DataKind::Ratings => "ratings",
+            DataKind::PositionDb => "positions",
+            DataKind::Seasons => "seasons",
+            DataKind::Review => "review",
+            DataKind::SavedGame => "saved-game",
+        }
+    }
+
+    /// Find the kind with the given header name.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        DataKind::ALL.into_iter().find(|kind| kind.tag() == tag)
+    }
+
+    /// The version this crate writes for the kind.
+    /// Bump it together with adding a migration from the previous version to `MIGRATIONS`.
+    pub fn current_version(self) -> u32 {
+        match self {
+            DataKind::GameRecord
+            | DataKind::HistoryDb
+            | DataKind::Profile
+            | DataKind::Ratings
+            | DataKind::PositionDb
+            | DataKind::Seasons
+            | DataKind::Review
+            | DataKind::SavedGame => 1,
+        }
+    }
+}
+
+/// An error while reading stored data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PersistError {
+    /// The data does not start with a `quarto <kind> <version>` header, and the kind has no version without one.
+    MissingHeader,
+    /// The header names another kind of data than expected.
+    WrongKind { expected: DataKind, found: String },
+    /// The data was written by a newer version of the crate, or by a version without a migration.
+    UnsupportedVersion { kind: DataKind, version: u32 },
+    /// A line of the body could not be read.
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::MissingHeader => write!(f, "missing quarto header"),
+            PersistError::WrongKind { expected, found } => {
+                write!(f, "expected {} data, found {}", expected.tag(), found)
+            }
+            PersistError::UnsupportedVersion { kind, version } => write!(
+                f,
+                "{} version {} is not supported, this version reads up to {}",
+                kind.tag(),
+                version,
+                kind.current_version()
+            ),
+            PersistError::Malformed { line, message } => {
+                write!(f, "line {} is malformed: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for PersistError {}
+
+/// The first line of every stored file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub kind: DataKind,
+    pub version: u32,
+}
+
+impl Header {
+    /// The header for data of `kind` written by this version of the crate.
+    pub fn current(kind: DataKind) -> Self {
+        Header {
+            kind,
+            version: kind.current_version(),
+        }
+    }
+
+    /// Read a header line like `quarto record 1`.
+    pub fn parse(line: &str) -> Result<Self, PersistError> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("quarto") {
+            return Err(PersistError::MissingHeader);
+        }
+        let tag = words.next().ok_or(PersistError::MissingHeader)?;
+        let kind = DataKind::from_tag(tag).ok_or_else(|| PersistError::Malformed {
+            line: 1,
+            message: format!("unknown kind {}", tag),
+        })?;
+        let version = words
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or(PersistError::MissingHeader)?;
+        Ok(Header { kind, version })
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quarto {} {}", self.kind.tag(), self.version)
+    }
+}
+
+/// Upgrades the body of `kind` data from version `from` to version `from + 1`.
+pub(crate) struct Migration {
+    kind: DataKind,
+    from: u32,
+    migrate: fn(&str) -> Result<String, PersistError>,
+}
+
+/// All migrations, applied in order until the data reaches the current version.
+/// Data without a header is version 0 of its kind, if the kind has a migration from 0.
+static MIGRATIONS: &[Migration] = &[Migration {
+    kind: DataKind::GameRecord,
+    from: 0,
+    migrate: crate::record::migrate_v0,
+}];
+
+/// Prepend the current header for `kind` to `body`.
+pub fn write(kind: DataKind, body: &str) -> String {
+    format!("{}\n{}", Header::current(kind), body)
+}
+
+/// Check the header of `data`, and migrate the body to the current version of `kind`.
+pub fn read(kind: DataKind, data: &str) -> Result<String, PersistError> {
+    let (first, body) = data.split_once('\n').unwrap_or((data, ""));
+    let header = match Header::parse(first) {
+        Ok(header) => header,
+        Err(PersistError::MissingHeader)
+            if has_migration(kind, 0) && !first.starts_with("quarto") =>
+        {
+            return migrate(kind, 0, data);
+        }
+        Err(PersistError::Malformed { .. }) => {
+            return Err(PersistError::WrongKind {
+                expected: kind,
+                found: first.split_whitespace().nth(1).unwrap_or("").to_string(),
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    if header.kind != kind {
+        return Err(PersistError::WrongKind {
+            expected: kind,
+            found: header.kind.tag().to_string(),
+        });
+    }
+    migrate(kind, header.version, body)
+}
+
+/// Apply the migrations for `kind` to a body of the given version.
+fn migrate(kind: DataKind, mut version: u32, body: &str) -> Result<String, PersistError> {
+    let unsupported = PersistError::UnsupportedVersion { kind, version };
+    if version > kind.current_version() {
+        return Err(unsupported);
+    }
+    let mut body = body.to_string();
+    while version < kind.current_version() {
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| m.kind == kind && m.from == version)
+            .ok_or(unsupported.clone())?;
+        body = (step.migrate)(&body)?;
+        version += 1;
+    }
+    Ok(body)
+}
+
+/// Whether there is a migration for `kind` from version `from`.
+fn has_migration(kind: DataKind, from: u32) -> bool {
+    MIGRATIONS.iter().any(|m| m.kind == kind && m.from == from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_roundtrip() {
+        for kind in DataKind::ALL {
+            let header = Header::current(kind);
+            assert_eq!(Header::parse(&header.to_string()), Ok(header));
+        }
+    }
+
+    #[test]
+    fn test_every_old_version_has_migration() {
+        for kind in DataKind::ALL {
+            // Version 0 is data without a header, which only some kinds can read.
+            let first = if has_migration(kind, 0) { 0 } else { 1 };
+            for from in first..kind.current_version() {
+                assert!(
+                    has_migration(kind, from),
+                    "no migration for {} version {}",
+                    kind.tag(),
+                    from
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_current_version() {
+        let data = write(DataKind::Profile, "name test\n");
+        assert_eq!(read(DataKind::Profile, &data).unwrap(), "name test\n");
+    }
+
+    #[test]
+    fn test_read_missing_header() {
+        assert_eq!(
+            read(DataKind::Profile, "name test\n"),
+            Err(PersistError::MissingHeader)
+        );
+        assert_eq!(
+            read(DataKind::Profile, "quarto profile 0\nname test\n"),
+            Err(PersistError::UnsupportedVersion {
+                kind: DataKind::Profile,
+                version: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_wrong_kind() {
+        let data = write(DataKind::Profile, "");
+        assert!(matches!(
+            read(DataKind::Ratings, &data),
+            Err(PersistError::WrongKind { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_newer_version() {
+        assert_eq!(
+            read(DataKind::Review, "quarto review 7\n"),
+            Err(PersistError::UnsupportedVersion {
+                kind: DataKind::Review,
+                version: 7
+            })
+        );
+    }
+}
@@ -0,0 +1,319 @@
+// Playback controls for watching games in the terminal: pause, step one ply, change the speed and jump to the end.
+// The plies are shown on a timer that listens for controls while it waits, instead of sleeping between moves.
+
+use std::{
+    io::{self, BufRead, Write},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    events::GameEvent,
+    spectate::{SpectatorMessage, WatchView},
+};
+
+/// The slowest playback, slowing down further keeps this delay.
+const MAX_DELAY: Duration = Duration::from_secs(10);
+/// The delay after slowing down a playback that shows every ply at once.
+const MIN_DELAY: Duration = Duration::from_millis(50);
+
+/// A command of the viewer, typed as a single letter and enter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// Pause, or resume when paused (`p`).
+    Pause,
+    /// Show the next ply and pause (`s`).
+    Step,
+    /// Halve the delay between plies (`+`).
+    Faster,
+    /// Double the delay between plies (`-`).
+    Slower,
+    /// Show the rest of the game without waiting (`e`).
+    End,
+    /// Turn the board around (`f`).
+    Flip,
+}
+
+impl FromStr for Control {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "p" => Ok(Control::Pause),
+            "s" => Ok(Control::Step),
+            "+" => Ok(Control::Faster),
+            "-" => Ok(Control::Slower),
+            "e" => Ok(Control::End),
+            "f" => Ok(Control::Flip),
+            _ => Err(format!("expected p, s, +, -, e or f, got '{}'", s)),
+        }
+    }
+}
+
+/// The timer of a playback: when the next ply is due.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playback {
+    delay: Duration,
+    paused: bool,
+    /// Set by `Control::Step` until the next ply is shown.
+    step: bool,
+    to_end: bool,
+    /// When the last ply was shown.
+    last: Option<Instant>,
+}
+
+impl Playback {
+    /// Create a running playback that shows a ply every `delay`.
+    pub fn new(delay: Duration) -> Self {
+        Playback {
+            delay: delay.min(MAX_DELAY),
+            paused: false,
+            step: false,
+            to_end: false,
+            last: None,
+        }
+    }
+
+    /// The delay between plies.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Check if the playback waits for the viewer.
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Change the playback by a control of the viewer, `Control::Flip` does not affect the timer.
+    pub fn apply(&mut self, control: Control) {
+        match control {
+            Control::Pause => self.paused = !self.paused,
+            Control::Step => {
+                self.paused = true;
+                self.step = true;
+            }
+            Control::Faster => self.delay /= 2,
+            Control::Slower => self.delay = (self.delay * 2).clamp(MIN_DELAY, MAX_DELAY),
+            Control::End => self.to_end = true,
+            Control::Flip => {}
+        }
+    }
+
+    /// How long to wait at `now` before the next ply is due, or `None` to wait for a control.
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        if self.to_end || self.step {
+            return Some(Duration::ZERO);
+        }
+        if self.paused {
+            return None;
+        }
+        Some(match self.last {
+            Some(last) => (last + self.delay).saturating_duration_since(now),
+            None => Duration::ZERO,
+        })
+    }
+
+    /// Note that a ply was shown at `now`.
+    pub fn played(&mut self, now: Instant) {
+        self.step = false;
+        self.last = Some(now);
+    }
+
+    /// A line telling the state of the playback and the controls.
+    fn status(&self) -> String {
+        let state = if self.to_end {
+            "to the end".to_string()
+        } else if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{}ms per ply", self.delay.as_millis())
+        };
+        format!(
+            "[{}] p pause, s step, + faster, - slower, e end, f flip\n",
+            state
+        )
+    }
+}
+
+/// Check if a message is a ply, which waits for the timer, other messages are shown right away.
+fn is_ply(message: &SpectatorMessage) -> bool {
+    matches!(
+        message,
+        SpectatorMessage::Event(GameEvent::PieceGiven { .. } | GameEvent::PiecePlaced { .. })
+    )
+}
+
+/// Read controls from `input` on another thread, lines that are no control are ignored.
+pub fn read_controls<R: BufRead + Send + 'static>(input: R) -> Receiver<Control> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in input.lines().map_while(Result::ok) {
+            if let Ok(control) = line.trim().parse()
+                && sender.send(control).is_err()
+            {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Clear the screen and draw the view and the playback state from the top.
+fn draw<W: Write>(output: &mut W, view: &WatchView, playback: &Playback) -> io::Result<()> {
+    write!(
+        output,
+        "\x1b[2J\x1b[H{}{}",
+        view.render(),
+        playback.status()
+    )?;
+    output.flush()
+}
+
+/// Wait until the next ply is due, handling the controls that arrive in the meantime.
+fn wait_for_ply<W: Write>(
+    controls: &Receiver<Control>,
+    output: &mut W,
+    view: &mut WatchView,
+    playback: &mut Playback,
+) -> io::Result<()> {
+    loop {
+        let control = match playback.wait(Instant::now()) {
+            Some(Duration::ZERO) => return Ok(()),
+            Some(timeout) => match controls.recv_timeout(timeout) {
+                Ok(control) => control,
+                Err(RecvTimeoutError::Timeout) => return Ok(()),
+                // Without controls the timer can only run out.
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(timeout);
+                    return Ok(());
+                }
+            },
+            None => match controls.recv() {
+                Ok(control) => control,
+                // Nobody can resume anymore, so play on.
+                Err(_) => Control::Pause,
+            },
+        };
+        if control == Control::Flip {
+            view.flip();
+        }
+        playback.apply(control);
+        draw(output, view, playback)?;
+    }
+}
+
+/// Show `messages` in `view` on `output`, one ply at a time on the timer of `playback`, until the game ends.
+pub fn drive<I, W>(
+    messages: I,
+    controls: &Receiver<Control>,
+    output: &mut W,
+    view: &mut WatchView,
+    playback: &mut Playback,
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = io::Result<SpectatorMessage>>,
+    W: Write,
+{
+    for message in messages {
+        let message = message?;
+        let ply = is_ply(&message);
+        if ply {
+            wait_for_ply(controls, output, view, playback)?;
+        }
+        view.apply(message);
+        if ply {
+            playback.played(Instant::now());
+        }
+        draw(output, view, playback)?;
+        if view.finished() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Vec<io::Result<SpectatorMessage>> {
+        ["start", "give 0 3", "place 1 3 5", "give 1 8", "end draw"]
+            .iter()
+            .map(|line| Ok(line.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_control_from_str() {
+        assert_eq!("p".parse(), Ok(Control::Pause));
+        assert_eq!("+".parse(), Ok(Control::Faster));
+        assert!("x".parse::<Control>().is_err());
+    }
+
+    #[test]
+    fn test_timer() {
+        let start = Instant::now();
+        let mut playback = Playback::new(Duration::from_millis(400));
+        assert_eq!(playback.wait(start), Some(Duration::ZERO));
+        playback.played(start);
+        assert_eq!(
+            playback.wait(start + Duration::from_millis(100)),
+            Some(Duration::from_millis(300))
+        );
+        playback.apply(Control::Pause);
+        assert_eq!(playback.wait(start), None);
+        playback.apply(Control::Step);
+        assert_eq!(playback.wait(start), Some(Duration::ZERO));
+        playback.played(start);
+        assert!(playback.paused());
+        assert_eq!(playback.wait(start), None);
+        playback.apply(Control::End);
+        assert_eq!(playback.wait(start), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_speed_bounds() {
+        let mut playback = Playback::new(Duration::ZERO);
+        playback.apply(Control::Faster);
+        assert_eq!(playback.delay(), Duration::ZERO);
+        playback.apply(Control::Slower);
+        assert_eq!(playback.delay(), MIN_DELAY);
+        for _ in 0..20 {
+            playback.apply(Control::Slower);
+        }
+        assert_eq!(playback.delay(), MAX_DELAY);
+        playback.apply(Control::Faster);
+        assert_eq!(playback.delay(), MAX_DELAY / 2);
+    }
+
+    #[test]
+    fn test_jump_to_end() {
+        let (sender, controls) = mpsc::channel();
+        sender.send(Control::End).unwrap();
+        let mut playback = Playback::new(MAX_DELAY);
+        let mut view = WatchView::new();
+        let mut output = Vec::new();
+        let start = Instant::now();
+        drive(game(), &controls, &mut output, &mut view, &mut playback).unwrap();
+        assert!(view.finished());
+        assert!(start.elapsed() < MAX_DELAY);
+    }
+
+    #[test]
+    fn test_step_while_paused() {
+        let (sender, controls) = mpsc::channel();
+        let mut playback = Playback::new(Duration::ZERO);
+        playback.apply(Control::Pause);
+        for control in [Control::Step, Control::Flip, Control::Step, Control::Pause] {
+            sender.send(control).unwrap();
+        }
+        drop(sender);
+        let mut view = WatchView::new();
+        let mut output = Vec::new();
+        drive(game(), &controls, &mut output, &mut view, &mut playback).unwrap();
+        assert!(view.finished());
+        assert!(String::from_utf8(output).unwrap().contains("[paused]"));
+    }
+}
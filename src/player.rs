@@ -2,6 +2,8 @@
 // Players that can play the Quarto game.
 // Uses the `Board` to determine the moves.
 
+use std::time::Duration;
+
 use crate::{board::Board, strategy::Strategy, ui::PlayerInterface};
 
 /// An abstraction of a `Player` that can play Quarto.
@@ -15,11 +17,20 @@ pub trait Player {
 
     /// Ask the player if they wish to call Quarto.
     fn quarto(&self, board: &Board) -> bool;
+
+    /// Check if the player gave up the game, like a player that stopped answering.
+    /// When such a player does not decide, the opponent wins instead of the game ending with an error.
+    fn forfeited(&self) -> bool {
+        false
+    }
+
+    /// Hear the time this player has for the decision it is asked next, in a game with a clock.
+    fn time_left(&self, _time: Duration) {}
 }
 
 pub struct HumanPlayer<I: PlayerInterface> {
-    // A `HumanPlayer` needs an interface that can ask questions and get responses.
-    interface: I
+    /// A `HumanPlayer` needs an interface that can ask questions and get responses.
+    interface: I,
 }
 pub struct ComputerPlayer<T: Strategy> {
     /// A `ComputerPlayer` uses a `Strategy` to determine its decisions.
@@ -41,17 +52,22 @@ impl<T: Strategy> ComputerPlayer<T> {
 }
 
 impl<I: PlayerInterface> Player for HumanPlayer<I> {
-    
     /// Ask the player for the piece to play.
     /// Validate the piece and ask (via the interface) for a new piece if it is wrong.
     fn get_piece(&self, board: &Board) -> Option<u8> {
-        let valid_pieces = board.valid_pieces();
-        if valid_pieces.is_empty() {
-            return None
+        if board.valid_pieces().is_empty() {
+            return None;
         }
+        self.interface.show_board(board);
         let mut piece = self.interface.prompt_for_piece(board);
         while !board.valid_piece(piece) {
-            // TODO: warn the user via the interface
+            if self.interface.closed() {
+                return None;
+            }
+            self.interface.warn_player(&format!(
+                "Piece {} is not available, pick another one!",
+                piece
+            ));
             piece = self.interface.prompt_for_piece(board);
         }
         Some(piece)
@@ -60,24 +76,31 @@ impl<I: PlayerInterface> Player for HumanPlayer<I> {
     /// Ask the player for the move to make, based on a given piece.
     /// Validate the move and ask (via the interface) for a new move if it is wrong.
     fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
-        let empty_spaces = board.empty_spaces();
-        if empty_spaces.is_empty() {
-            return None
+        if board.empty_spaces().is_empty() {
+            return None;
         }
-        let mut get_move = self.interface.prompt_for_move(board, piece);
-        while !board.empty_index(get_move) {
-            // TODO: warn the user via the interface
-            get_move = self.interface.prompt_for_move(board, piece);
+        self.interface.show_board(board);
+        let mut index = self.interface.prompt_for_move(board, piece);
+        while !board.empty_index(index) {
+            if self.interface.closed() {
+                return None;
+            }
+            self.interface
+                .warn_player(&format!("Space {} is not empty, pick another one!", index));
+            index = self.interface.prompt_for_move(board, piece);
         }
-        Some(self.interface.prompt_for_move(board, piece))
+        Some(index)
     }
 
     /// Ask the user via the interface if they wish to call Quarto.
     fn quarto(&self, board: &Board) -> bool {
         self.interface.ask_quarto(board)
     }
-}
 
+    fn time_left(&self, time: Duration) {
+        self.interface.show_clock(time);
+    }
+}
 
 /// Use the `Strategy` `T` to determine the moves.
 impl<T: Strategy> Player for ComputerPlayer<T> {
@@ -94,28 +117,150 @@ impl<T: Strategy> Player for ComputerPlayer<T> {
     }
 }
 
+/// A boxed player plays like the player inside, so players picked at runtime can be used in a `QuartoGame`.
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        (**self).get_piece(board)
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        (**self).get_move(board, piece)
+    }
+
+    fn quarto(&self, board: &Board) -> bool {
+        (**self).quarto(board)
+    }
+
+    fn forfeited(&self) -> bool {
+        (**self).forfeited()
+    }
+
+    fn time_left(&self, time: Duration) {
+        (**self).time_left(time)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::strategy::{DumbStrategy, NaiveStrategy};
 
     use super::*;
+    use std::cell::RefCell;
     use std::panic;
 
+    /// An interface that replays scripted answers and remembers the warnings it received.
+    struct ScriptedInterface {
+        pieces: RefCell<Vec<u8>>,
+        moves: RefCell<Vec<u8>>,
+        warnings: RefCell<usize>,
+        /// Set when a prompt finds the script empty.
+        closed: RefCell<bool>,
+    }
+
+    impl ScriptedInterface {
+        /// Take the next scripted answer, or close the interface when there is none left.
+        fn next(&self, script: &RefCell<Vec<u8>>) -> u8 {
+            let mut script = script.borrow_mut();
+            if script.is_empty() {
+                *self.closed.borrow_mut() = true;
+                return u8::MAX;
+            }
+            script.remove(0)
+        }
+
+        fn new(pieces: Vec<u8>, moves: Vec<u8>) -> Self {
+            ScriptedInterface {
+                pieces: RefCell::new(pieces),
+                moves: RefCell::new(moves),
+                warnings: RefCell::new(0),
+                closed: RefCell::new(false),
+            }
+        }
+    }
+
+    impl PlayerInterface for ScriptedInterface {
+        fn show_board(&self, _: &Board) {}
+
+        fn prompt_for_piece(&self, _: &Board) -> u8 {
+            self.next(&self.pieces)
+        }
+
+        fn prompt_for_move(&self, _: &Board, _: u8) -> u8 {
+            self.next(&self.moves)
+        }
+
+        fn ask_quarto(&self, board: &Board) -> bool {
+            board.has_winner()
+        }
+
+        fn warn_player(&self, _: &str) {
+            *self.warnings.borrow_mut() += 1;
+        }
+
+        fn closed(&self) -> bool {
+            *self.closed.borrow()
+        }
+    }
+
+    #[test]
+    fn test_human_get_piece_valid() {
+        let player = HumanPlayer::new(ScriptedInterface::new(vec![3], vec![]));
+        assert_eq!(player.get_piece(&Board::new()), Some(3));
+        assert_eq!(*player.interface.warnings.borrow(), 0);
+    }
+
+    #[test]
+    fn test_human_get_piece_reprompts_on_used_piece() {
+        let mut board = Board::new();
+        board.put_piece(3, 0).unwrap();
+        let player = HumanPlayer::new(ScriptedInterface::new(vec![3, 16, 4], vec![]));
+        assert_eq!(player.get_piece(&board), Some(4));
+        assert_eq!(*player.interface.warnings.borrow(), 2);
+    }
+
+    #[test]
+    fn test_human_get_move_reprompts_on_occupied_space() {
+        let mut board = Board::new();
+        board.put_piece(3, 5).unwrap();
+        let player = HumanPlayer::new(ScriptedInterface::new(vec![], vec![5, 20, 6]));
+        assert_eq!(player.get_move(&board, 0), Some(6));
+        assert_eq!(*player.interface.warnings.borrow(), 2);
+    }
+
+    #[test]
+    fn test_human_stops_when_closed() {
+        let mut board = Board::new();
+        board.put_piece(3, 5).unwrap();
+        let player = HumanPlayer::new(ScriptedInterface::new(vec![3], vec![5]));
+        assert_eq!(player.get_piece(&board), None);
+        assert_eq!(player.get_move(&board, 0), None);
+    }
+
+    #[test]
+    fn test_human_full_board() {
+        let mut board: Board = Board::new();
+        for i in 0..16 {
+            board.put_piece(i, i).unwrap();
+        }
+        let player = HumanPlayer::new(ScriptedInterface::new(vec![], vec![]));
+        assert_eq!(player.get_piece(&board), None);
+        assert_eq!(player.get_move(&board, 0), None);
+    }
+
     #[test]
     fn test_get_move_from_dumb_full_board() {
         let mut board: Board = Board::new();
         for i in 0..16 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
-        match player.get_move(&board, 0) {
-            Some(n) => panic!(
+        if let Some(n) = player.get_move(&board, 0) {
+            panic!(
                 "Strategy came back with number {}, while there is no valid space!",
                 n
-            ),
-            None => (),
+            )
         }
     }
 
@@ -123,17 +268,16 @@ mod tests {
     fn test_get_piece_from_dumb_full_board() {
         let mut board: Board = Board::new();
         for i in 0..16 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
-        match player.get_piece(&board) {
-            Some(n) => panic!(
+        if let Some(n) = player.get_piece(&board) {
+            panic!(
                 "Strategy came back with number {}, while there is no valid space!",
                 n
-            ),
-            None => (),
+            )
         }
     }
 
@@ -141,17 +285,16 @@ mod tests {
     fn test_get_move_from_naive_full_board() {
         let mut board: Board = Board::new();
         for i in 0..16 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
-        match player.get_move(&board, 0) {
-            Some(n) => panic!(
+        if let Some(n) = player.get_move(&board, 0) {
+            panic!(
                 "Strategy came back with number {}, while there is no valid space!",
                 n
-            ),
-            None => (),
+            )
         }
     }
 
@@ -159,17 +302,16 @@ mod tests {
     fn test_get_piece_from_naive_full_board() {
         let mut board: Board = Board::new();
         for i in 0..16 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
-        match player.get_piece(&board) {
-            Some(n) => panic!(
+        if let Some(n) = player.get_piece(&board) {
+            panic!(
                 "Strategy came back with number {}, while there is no valid space!",
                 n
-            ),
-            None => (),
+            )
         }
     }
 
@@ -177,10 +319,10 @@ mod tests {
     fn test_get_move_from_dumb_nearly_full_board() {
         let mut board: Board = Board::new();
         for i in 0..15 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
         match player.get_move(&board, 0) {
             Some(n) => assert_eq!(n, 15),
@@ -192,10 +334,10 @@ mod tests {
     fn test_get_piece_from_dumb_nearly_full_board() {
         let mut board: Board = Board::new();
         for i in 0..15 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
         match player.get_piece(&board) {
             Some(n) => assert_eq!(n, 15),
@@ -207,10 +349,10 @@ mod tests {
     fn test_get_move_from_naive_nearly_full_board() {
         let mut board: Board = Board::new();
         for i in 0..15 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
         match player.get_move(&board, 0) {
             Some(n) => assert_eq!(n, 15),
@@ -222,10 +364,10 @@ mod tests {
     fn test_get_piece_from_naive_nearly_full_board() {
         let mut board: Board = Board::new();
         for i in 0..15 {
-            board.put_piece(i, i);
+            board.put_piece(i, i).unwrap();
         }
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
         match player.get_piece(&board) {
             Some(n) => assert_eq!(n, 15),
@@ -237,7 +379,7 @@ mod tests {
     fn test_get_move_from_dumb_empty_board() {
         let board: Board = Board::new();
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
         match player.get_move(&board, 0) {
             Some(m) => assert!(m < 16),
@@ -249,7 +391,7 @@ mod tests {
     fn test_get_piece_from_dumb_empty_board() {
         let board: Board = Board::new();
         let player = ComputerPlayer {
-            strategy: DumbStrategy,
+            strategy: DumbStrategy::new(),
         };
         match player.get_piece(&board) {
             Some(m) => assert!(m < 16),
@@ -261,7 +403,7 @@ mod tests {
     fn test_get_move_from_naive_empty_board() {
         let board: Board = Board::new();
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
         match player.get_move(&board, 0) {
             Some(m) => assert!(m < 16),
@@ -273,7 +415,7 @@ mod tests {
     fn test_get_piece_from_naive_empty_board() {
         let board: Board = Board::new();
         let player = ComputerPlayer {
-            strategy: NaiveStrategy,
+            strategy: NaiveStrategy::new(),
         };
         match player.get_piece(&board) {
             Some(m) => assert!(m < 16),
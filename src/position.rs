@@ -0,0 +1,95 @@
+// Random positions that can come up in a game, with a chosen number of pieces on the board.
+// Used to test strategies on many realistic boards, to look for puzzles, and to benchmark the solver.
+
+use crate::{
+    board::{Board, PlacementOutcome, Ruleset},
+    random::SharedRng,
+};
+
+/// How often a random game is started over before giving up on a number of pieces.
+const TRIES: usize = 100;
+
+/// Draws random positions by playing random placements that do not end the game.
+#[derive(Debug, Clone, Default)]
+pub struct PositionGenerator {
+    rng: SharedRng,
+    ruleset: Ruleset,
+}
+
+impl PositionGenerator {
+    /// Create a generator of classic positions, drawn from a generator seeded by the thread.
+    pub fn new() -> Self {
+        PositionGenerator::default()
+    }
+
+    /// Draw the positions from `rng`, so a seed gives the same positions.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Draw positions of `ruleset`, whose wins are avoided as well.
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    /// A board with `pieces` pieces and no winner, 16 for a drawn game.
+    /// Returns `None` for more than 16 pieces, or if every random game ended before that many were placed.
+    pub fn board(&self, pieces: u8) -> Option<Board> {
+        if pieces > 16 {
+            return None;
+        }
+        (0..TRIES).find_map(|_| self.play(pieces))
+    }
+
+    /// A board with `pieces` pieces and no winner, and a random piece that is handed over to place next.
+    /// Returns `None` when there is no such board, or no piece left to hand over.
+    pub fn position(&self, pieces: u8) -> Option<(Board, u8)> {
+        let board = self.board(pieces)?;
+        let piece = self.rng.choose(&board.valid_pieces())?;
+        Some((board, piece))
+    }
+
+    /// Play random placements that do not win until `pieces` are on the board, or `None` if none is left.
+    fn play(&self, pieces: u8) -> Option<Board> {
+        let mut board = Board::new().with_ruleset(self.ruleset);
+        for _ in 0..pieces {
+            let mut moves: Vec<Board> = Vec::new();
+            for piece in board.valid_pieces() {
+                for index in board.empty_spaces() {
+                    let mut next = board;
+                    if matches!(
+                        next.put_piece_checked(piece, index),
+                        Ok(PlacementOutcome::Ongoing | PlacementOutcome::Draw)
+                    ) {
+                        moves.push(next);
+                    }
+                }
+            }
+            board = self.rng.choose(&moves)?;
+        }
+        Some(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_are_reachable() {
+        let generator = PositionGenerator::new().with_rng(SharedRng::seeded(2824));
+        for pieces in 0..=16 {
+            let board = generator.board(pieces).unwrap();
+            assert_eq!(16 - board.empty_spaces().len(), pieces as usize);
+            assert!(!board.has_winner() && board.is_reachable());
+        }
+        assert_eq!(generator.board(17), None);
+        assert!(generator.position(16).is_none());
+        let (board, piece) = generator.position(9).unwrap();
+        assert!(board.valid_piece(piece));
+        let seeded = || PositionGenerator::new().with_rng(SharedRng::seeded(7));
+        assert_eq!(seeded().board(12), seeded().board(12));
+    }
+}
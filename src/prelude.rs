@@ -0,0 +1,15 @@
+// The types a typical user of the library needs, to import with `use quarto::prelude::*`.
+// Everything here is part of the stable surface of the crate, see the crate documentation.
+
+pub use crate::{
+    board::Board,
+    driver::{Decision, GameDriver, Step},
+    error::{PlacementError, QuartoError},
+    game::{GameResult, QuartoGame},
+    notation::{Cell, Move},
+    player::{ComputerPlayer, HumanPlayer, Player},
+    printable::Piece,
+    random::SharedRng,
+    record::{GameRecord, Turn},
+    strategy::Strategy,
+};
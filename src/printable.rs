@@ -1,56 +1,238 @@
 // Author: @julianvansanten
-// An easy to debug board with a list of pieces.
-// This implementation uses a lot of memory (reads/writes), so there is only a way to go from this board to the bitboard.
-
-use crate::board::{Board, PIECE_SIZE};
-/// Representation for the board that is easier to print.
-/// Uses `Some(Piece)`s to store each piece, is easier to print but way slower to operate on.
-/// If there is no Piece on a location, we store a `None`.
-#[derive(Debug, PartialEq, Eq)]
-pub struct PrintableBoard {
-    items: Vec<Option<Piece>>,
-}
-
-impl PrintableBoard {
-    /// Wrap a `Vec<Option`Piece>>` in a new PrintableBoard.
-    /// Returns `Some(PrintableBoard)` if the list contains exactly 16 entries, otherwise None.
-    pub fn from_list(items: Vec<Option<Piece>>) -> Option<Self> {
-        // Boards must have 16 spaces.
-        if items.len() != 16 {
-            return None;
+// Human readable views of the bitboard.
+// A `PrintableBoard` only borrows a `Board` and formats it, so it can never disagree with the board it shows.
+
+use std::{fmt, str::FromStr};
+
+use crate::{board::Board, profile};
+
+/// When to color the board in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Use colors if stdout is a terminal that shows them.
+    #[default]
+    Auto,
+    Always,
+    /// Never use colors, for terminals without them or output to a file.
+    Never,
+}
+
+impl ColorMode {
+    /// Check if colors should be used.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => profile::detect_color(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorMode::Auto => write!(f, "auto"),
+            ColorMode::Always => write!(f, "always"),
+            ColorMode::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("expected auto, always or never, got '{}'", s)),
         }
-        Some(PrintableBoard { items })
     }
+}
+
+/// Wrap `text` in the ANSI color of a dark or light piece, reversed when it is on a winning line.
+fn paint(text: &str, dark: bool, winning: bool) -> String {
+    let color = if dark { 34 } else { 33 };
+    let reverse = if winning { "7;" } else { "" };
+    format!("\x1b[{}{}m{}\x1b[0m", reverse, color, text)
+}
 
-    /// Create a `PrintableBoard` from a `Board`.
-    /// Take the `u128`, shift over the bits, AND it with 255 and turn it into a `u8`.
-    pub fn from_board(board: Board) -> Self {
-        let mut items: Vec<Option<Piece>> = Vec::new();
-        // Reverse the for loop to start at the first (leftmost) 8 bits
-        for shift in (0..16).rev() {
-            let u8piece: u8 = (board.items() >> (PIECE_SIZE * shift) & 255) as u8;
-            items.push(Piece::from_u8(u8piece));
+/// How a `PrintableBoard` lays out the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// A 4x4 grid with the attribute bits of every piece, `----` for an empty cell.
+    #[default]
+    Grid,
+    /// All 16 cells on a single line, in index order.
+    List,
+    /// One line per cell, with the attributes of the piece written out.
+    Verbose,
+    /// A 4x4 grid with a compact glyph for every piece, see `Glyphs`.
+    Glyphs,
+}
+
+/// A view on a `Board` that is easier to print.
+/// Formatting is done on the fly from the bitboard, so the view stays cheap to create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintableBoard<'a> {
+    board: &'a Board,
+    format: Format,
+    /// The indices whose piece is shown as unknown, bit `i` for index `i`.
+    hidden: u16,
+    /// Color the pieces and highlight the winning lines with ANSI escapes.
+    color: bool,
+}
+
+impl<'a> PrintableBoard<'a> {
+    /// Create a view on `board` with the default grid format.
+    pub fn new(board: &'a Board) -> Self {
+        PrintableBoard {
+            board,
+            format: Format::default(),
+            hidden: 0,
+            color: false,
         }
-        PrintableBoard { items }
     }
 
-    /// Create a deep copy of the items in the board.
+    /// Use `format` to lay out the board.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Hide the attributes of the pieces on the indices in `hidden`, bit `i` for index `i`.
+    /// A hidden cell still shows whether it holds a piece.
+    pub fn with_hidden(mut self, hidden: u16) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Color dark and light pieces with ANSI escapes, and reverse the pieces on a winning line.
+    /// The verbose format stays plain text.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The indices on a winning line, bit `i` for index `i`, if the board is colored.
+    fn winning_cells(&self) -> u16 {
+        if !self.color {
+            return 0;
+        }
+        self.board
+            .winning_lines()
+            .iter()
+            .fold(0, |cells, winning| cells | winning.line.index_mask())
+    }
+
+    /// Check if the piece on `index` is hidden.
+    fn is_hidden(&self, index: usize) -> bool {
+        self.hidden & (1 << index) != 0
+    }
+
+    /// The pieces on the board, in index order.
     pub fn items(&self) -> Vec<Option<Piece>> {
-        let mut res: Vec<Option<Piece>> = Vec::new();
-        for option in self.items.iter() {
-            res.push(match option {
-                // Must be a `clone` to create a copy of the piece!
-                Some(p) => Some(p.clone()),
-                None => None,
-            });
+        (0..16)
+            .map(|index| self.board.piece_at(index).map(Piece::from_number))
+            .collect()
+    }
+
+    /// Write a cell in the current format, `winning` holds the indices to highlight.
+    fn cell(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        index: usize,
+        piece: Option<Piece>,
+        winning: u16,
+    ) -> fmt::Result {
+        let glyphs = self.format == Format::Glyphs;
+        match piece {
+            Some(_) if self.is_hidden(index) && glyphs => write!(f, "? "),
+            Some(_) if self.is_hidden(index) => write!(f, "????"),
+            Some(piece) => {
+                let text = match (glyphs, self.color) {
+                    (true, true) => Glyphs::shape(piece).to_string(),
+                    (true, false) => return write!(f, "{}", Glyphs::new().render(piece)),
+                    (false, _) => format!("{:04b}", piece.to_number()),
+                };
+                if self.color {
+                    write!(
+                        f,
+                        "{}",
+                        paint(&text, piece.dark, winning & (1 << index) != 0)
+                    )?;
+                } else {
+                    write!(f, "{}", text)?;
+                }
+                if glyphs {
+                    write!(f, " ")?;
+                }
+                Ok(())
+            }
+            None if glyphs => write!(f, "\u{b7} "),
+            None => write!(f, "----"),
+        }
+    }
+}
+
+/// Serialized as the text it prints, a view borrows its board so it cannot be deserialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PrintableBoard<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for PrintableBoard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self.items();
+        let winning = self.winning_cells();
+        match self.format {
+            Format::Grid | Format::Glyphs => {
+                for (row, cells) in items.chunks(4).enumerate() {
+                    if row > 0 {
+                        writeln!(f)?;
+                    }
+                    for (column, &piece) in cells.iter().enumerate() {
+                        if column > 0 {
+                            write!(f, " ")?;
+                        }
+                        self.cell(f, row * 4 + column, piece, winning)?;
+                    }
+                }
+                Ok(())
+            }
+            Format::List => {
+                for (index, &piece) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    self.cell(f, index, piece, winning)?;
+                }
+                Ok(())
+            }
+            Format::Verbose => {
+                for (index, piece) in items.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    match piece {
+                        Some(_) if self.is_hidden(index) => write!(f, "{:>2}: hidden", index)?,
+                        Some(piece) => write!(f, "{:>2}: {}", index, piece)?,
+                        None => write!(f, "{:>2}: empty", index)?,
+                    }
+                }
+                Ok(())
+            }
         }
-        res
     }
 }
 
 /// A Piece on the board that can be printed, but is not necessarily used in the Board structure (slow).
 /// There are 16 Pieces in Quarto, with each piece having a hole/no hole, being square/round, being high/low, and dark/light.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     // This order is coherent with the order of the networking protocol.
     pub hole: bool,   // fill
@@ -76,12 +258,22 @@ impl Piece {
         if input & 1u8 == 0 {
             return None;
         }
-        Some(Piece {
-            hole: input & (1 << 7) == (1 << 7),
-            square: input & (1 << 6) == (1 << 6),
-            high: input & (1 << 5) == (1 << 5),
-            dark: input & (1 << 4) == (1 << 4),
-        })
+        Some(Piece::from_number(input >> 4))
+    }
+
+    /// Create a Piece from its number between 0 and (incl.) 15, higher bits are ignored.
+    pub fn from_number(number: u8) -> Self {
+        Piece {
+            hole: number & 0b1000 != 0,
+            square: number & 0b0100 != 0,
+            high: number & 0b0010 != 0,
+            dark: number & 0b0001 != 0,
+        }
+    }
+
+    /// Convert the `Piece` to the byte of the networking protocol, the inverse of `from_u8`.
+    pub fn to_u8(&self) -> u8 {
+        (self.to_number() << 4) | 1
     }
 
     /// Convert the `Piece` to a number between 0 and (incl.) 15.
@@ -96,6 +288,64 @@ impl Piece {
     }
 }
 
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            if self.hole { "hole" } else { "solid" },
+            if self.square { "square" } else { "round" },
+            if self.high { "high" } else { "low" },
+            if self.dark { "dark" } else { "light" }
+        )
+    }
+}
+
+/// Draws a `Piece` as a glyph two columns wide, instead of writing out its attributes.
+/// The shape is round or square, hollow for a hole, and small for a low piece: ● • ○ ◦ ■ ▪ □ ▫.
+/// A dark piece is marked with `'` after its glyph, or colored blue with ANSI colors, where light pieces are yellow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Glyphs {
+    color: bool,
+}
+
+impl Glyphs {
+    /// Create a renderer that marks dark pieces without colors.
+    pub fn new() -> Self {
+        Glyphs::default()
+    }
+
+    /// Color the pieces with ANSI escapes when `color` is set.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The glyph for the shape, hole and height of `piece`, without its color.
+    pub fn shape(piece: Piece) -> char {
+        match (piece.square, piece.hole, piece.high) {
+            (false, false, true) => '●',
+            (false, false, false) => '•',
+            (false, true, true) => '○',
+            (false, true, false) => '◦',
+            (true, false, true) => '■',
+            (true, false, false) => '▪',
+            (true, true, true) => '□',
+            (true, true, false) => '▫',
+        }
+    }
+
+    /// Draw `piece` in two columns.
+    pub fn render(&self, piece: Piece) -> String {
+        let shape = Glyphs::shape(piece);
+        match (self.color, piece.dark) {
+            (true, dark) => format!("{} ", paint(&shape.to_string(), dark, false)),
+            (false, true) => format!("{}'", shape),
+            (false, false) => format!("{} ", shape),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::panic;
@@ -147,31 +397,127 @@ mod tests {
     }
 
     #[test]
-    fn test_board_conversion_arb_length() {
-        assert_eq!(PrintableBoard::from_list(Vec::new()), None);
+    fn test_from_number_roundtrip() {
+        for number in 0..16 {
+            assert_eq!(Piece::from_number(number).to_number(), number);
+            let piece = Piece::from_number(number);
+            assert_eq!(Piece::from_u8(piece.to_u8()), Some(piece));
+        }
+    }
+
+    #[test]
+    fn test_items_follow_board() {
+        let board = Board::new().apply_moves(&[(5, 0), (10, 15)]).unwrap();
+        let items = PrintableBoard::new(&board).items();
+        assert_eq!(items.len(), 16);
+        assert_eq!(items[0], Some(Piece::from_number(5)));
+        assert_eq!(items[15], Some(Piece::from_number(10)));
+        assert_eq!(items.iter().filter(|p| p.is_some()).count(), 2);
+    }
+
+    #[test]
+    fn test_grid_format() {
+        let board = Board::new().apply_moves(&[(5, 0), (10, 15)]).unwrap();
+        assert_eq!(
+            PrintableBoard::new(&board).to_string(),
+            "0101 ---- ---- ----\n\
+             ---- ---- ---- ----\n\
+             ---- ---- ---- ----\n\
+             ---- ---- ---- 1010"
+        );
+    }
+
+    #[test]
+    fn test_list_format() {
+        let board = Board::new().apply_moves(&[(15, 1)]).unwrap();
+        let list = PrintableBoard::new(&board)
+            .with_format(Format::List)
+            .to_string();
+        assert!(list.starts_with("---- 1111 ----"));
+        assert_eq!(list.split(' ').count(), 16);
+    }
+
+    #[test]
+    fn test_verbose_format() {
+        let board = Board::new().apply_moves(&[(0b1001, 2)]).unwrap();
+        let verbose = PrintableBoard::new(&board)
+            .with_format(Format::Verbose)
+            .to_string();
+        let lines: Vec<&str> = verbose.lines().collect();
+        assert_eq!(lines.len(), 16);
+        assert_eq!(lines[0], " 0: empty");
+        assert_eq!(lines[2], " 2: hole round low dark");
     }
 
     #[test]
-    fn test_board_conversion_correct_list() {
-        let mut pieces: Vec<Option<Piece>> = Vec::new();
-        for i in 0..16 {
-            let piece: Option<Piece> = Piece::from_u8((i << 4) + 1);
-            pieces.push(piece);
+    fn test_glyphs_are_distinct() {
+        let glyphs: Vec<String> = (0..16)
+            .map(|number| Glyphs::new().render(Piece::from_number(number)))
+            .collect();
+        for glyph in glyphs.iter() {
+            assert_eq!(glyph.chars().count(), 2);
+            assert_eq!(glyphs.iter().filter(|&g| g == glyph).count(), 1);
         }
+        // Solid round high dark, and hole square low light.
+        assert_eq!(glyphs[0b0011], "●'");
+        assert_eq!(glyphs[0b1100], "▫ ");
+    }
 
-        let pboard: PrintableBoard = match PrintableBoard::from_list(pieces) {
-            Some(pboard) => pboard,
-            None => panic!("PrintableBoard not correctly initialized!"),
-        };
+    #[test]
+    fn test_glyph_format() {
+        let board = Board::new()
+            .apply_moves(&[(0b0011, 0), (0b0010, 15)])
+            .unwrap();
+        let printable = PrintableBoard::new(&board).with_format(Format::Glyphs);
+        let text = printable.to_string();
+        assert!(text.starts_with("●' ·  ·  · \n"));
+        assert!(text.ends_with("·  ● "));
+        assert!(printable.with_hidden(1).to_string().starts_with("?  "));
+        let colored = printable.with_color(true).to_string();
+        assert!(colored.starts_with("\x1b[34m●\x1b[0m "));
+        assert!(colored.contains("\x1b[33m"));
+    }
 
-        let board: Board = match Board::from_printable(&pboard) {
-            Ok(b) => b,
-            Err(_) => panic!("Board conversion failed!"),
-        };
+    #[test]
+    fn test_winning_line_highlight() {
+        // Four pieces without a hole on the first column.
+        let board = Board::new()
+            .apply_moves(&[
+                (0b0000, 0),
+                (0b1000, 1),
+                (0b0111, 4),
+                (0b0101, 8),
+                (0b0011, 12),
+            ])
+            .unwrap();
+        let colored = PrintableBoard::new(&board).with_color(true).to_string();
+        assert_eq!(colored.matches("\x1b[7;").count(), 4);
+        assert!(colored.starts_with("\x1b[7;33m0000\x1b[0m \x1b[33m1000\x1b[0m ----"));
+        let glyphs = PrintableBoard::new(&board)
+            .with_format(Format::Glyphs)
+            .with_color(true)
+            .to_string();
+        assert_eq!(glyphs.matches("\x1b[7;").count(), 4);
+        assert!(!PrintableBoard::new(&board).to_string().contains('\x1b'));
+    }
 
-        match Board::from_printable(&PrintableBoard::from_board(board)) {
-            Ok(board2) => assert_eq!(board, board2),
-            Err(_) => panic!("Double conversion failed!"),
-        };
+    #[test]
+    fn test_color_mode() {
+        for mode in [ColorMode::Auto, ColorMode::Always, ColorMode::Never] {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        assert!(ColorMode::Always.enabled());
+        assert!(!ColorMode::Never.enabled());
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn test_hidden_cells() {
+        let board = Board::new().apply_moves(&[(5, 0), (10, 15)]).unwrap();
+        let hidden = PrintableBoard::new(&board).with_hidden(1);
+        assert!(hidden.to_string().starts_with("???? ---- ---- ----"));
+        assert!(hidden.to_string().ends_with("---- 1010"));
+        let verbose = hidden.with_format(Format::Verbose).to_string();
+        assert!(verbose.starts_with(" 0: hidden\n 1: empty"));
     }
 }
@@ -0,0 +1,193 @@
+// The player's profile: their name and preferences, kept in a config file between runs.
+// Written by the first-run wizard, see `ui::wizard`.
+
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    persist::{self, DataKind, PersistError},
+    strategy::Difficulty,
+    usage::Usage,
+};
+
+/// The name and preferences of the player at this computer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// The strength of the computer opponent when none is picked.
+    pub difficulty: Difficulty,
+    /// Use colors in the terminal.
+    pub color: bool,
+    /// Save a game in progress after every move.
+    pub autosave: bool,
+    /// Keep finished games in the history.
+    pub history: bool,
+    /// Statistics over the games played at this computer, which are never sent anywhere.
+    pub usage: Usage,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            name: "Player".to_string(),
+            difficulty: Difficulty::default(),
+            color: false,
+            autosave: true,
+            history: true,
+            usage: Usage::default(),
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+impl Profile {
+    /// Write the profile in the stored format, the settings followed by the usage statistics.
+    pub fn to_text(&self) -> String {
+        let mut body = format!(
+            "name {}\ndifficulty {}\ncolor {}\nautosave {}\nhistory {}\n",
+            self.name,
+            self.difficulty,
+            yes_no(self.color),
+            yes_no(self.autosave),
+            yes_no(self.history)
+        );
+        self.usage.write(&mut body);
+        persist::write(DataKind::Profile, &body)
+    }
+
+    /// Read a profile written by `to_text`, settings that are missing keep their default.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::Profile, data)?;
+        let mut profile = Profile::default();
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: String| PersistError::Malformed { line, message };
+            let flag = |value: &str| match value {
+                "yes" => Ok(true),
+                "no" => Ok(false),
+                _ => Err(malformed(format!("expected yes or no, got '{}'", value))),
+            };
+            match text.split_once(' ').unwrap_or((text, "")) {
+                ("", "") => {}
+                ("name", name) if !name.trim().is_empty() => profile.name = name.trim().to_string(),
+                ("difficulty", value) => profile.difficulty = value.parse().map_err(malformed)?,
+                ("color", value) => profile.color = flag(value)?,
+                ("autosave", value) => profile.autosave = flag(value)?,
+                ("history", value) => profile.history = flag(value)?,
+                (key, value) => {
+                    if !profile.usage.read(key, value).map_err(malformed)? {
+                        return Err(malformed(format!("unknown setting '{}'", text)));
+                    }
+                }
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Load the profile at `path`, or `None` if there is no file yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(data) => Profile::from_text(&data)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Save the profile to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+/// Where the profile is kept: `$QUARTO_PROFILE`, or `quarto/profile` in the user's config directory.
+/// `None` if there is no home directory to put it in.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("QUARTO_PROFILE") {
+        return Some(PathBuf::from(path));
+    }
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("quarto").join("profile"))
+}
+
+/// Where a game in progress is autosaved: `autosave` next to the profile.
+pub fn autosave_path() -> Option<PathBuf> {
+    default_path().map(|path| path.with_file_name("autosave"))
+}
+
+/// Guess if the terminal shows colors: stdout is a terminal, `NO_COLOR` is not set and `TERM` is not `dumb`.
+pub fn detect_color() -> bool {
+    io::stdout().is_terminal()
+        && env::var_os("NO_COLOR").is_none()
+        && env::var("TERM").is_ok_and(|term| term != "dumb")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::usage::{Mode, Record};
+
+    use super::*;
+
+    #[test]
+    fn test_text_roundtrip() {
+        let mut profile = Profile {
+            name: "Ada Lovelace".to_string(),
+            difficulty: Difficulty::Hard,
+            color: true,
+            autosave: false,
+            history: true,
+            usage: Usage::default(),
+        };
+        assert_eq!(Profile::from_text(&profile.to_text()), Ok(profile.clone()));
+        profile.usage.games.insert(Mode::Computer, 12);
+        profile.usage.versus.insert(
+            Difficulty::Easy,
+            Record {
+                wins: 7,
+                draws: 1,
+                losses: 4,
+            },
+        );
+        profile.usage.time_played = Duration::from_secs(1800);
+        assert_eq!(Profile::from_text(&profile.to_text()), Ok(profile));
+    }
+
+    #[test]
+    fn test_from_text_defaults_and_errors() {
+        let data = persist::write(DataKind::Profile, "name test\n");
+        let profile = Profile::from_text(&data).unwrap();
+        assert_eq!(profile.name, "test");
+        assert_eq!(profile.difficulty, Difficulty::Medium);
+        let data = persist::write(DataKind::Profile, "name test\ncolor maybe\n");
+        assert!(matches!(
+            Profile::from_text(&data),
+            Err(PersistError::Malformed { line: 3, .. })
+        ));
+        let data = persist::write(DataKind::Profile, "volume 11\n");
+        assert!(Profile::from_text(&data).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = env::temp_dir().join(format!("quarto-profile-{}", std::process::id()));
+        let path = dir.join("nested").join("profile");
+        assert_eq!(Profile::load(&path).unwrap(), None);
+        let profile = Profile::default();
+        profile.save(&path).unwrap();
+        assert_eq!(Profile::load(&path).unwrap(), Some(profile));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+// Puzzles of forced wins: positions where only one placement of the piece in hand wins in a number of plies.
+// Random reachable positions are searched for them, and the solver proves that no other placement wins at all.
+
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use crate::{
+    board::Board,
+    notation::{self, Cell},
+    position::PositionGenerator,
+    printable::{Piece, PrintableBoard},
+    random::SharedRng,
+    solver::{Outcome, Solver},
+};
+
+/// A position where the player to move wins by placing the piece in hand on the one `solution` cell.
+/// The win takes `plies` placements of both players, the last one by the player to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Puzzle {
+    pub board: Board,
+    pub piece: u8,
+    /// The player to place the piece in hand.
+    pub mover: usize,
+    pub plies: usize,
+    pub solution: u8,
+}
+
+impl Puzzle {
+    /// Check if placing the piece on `index` solves the puzzle.
+    pub fn is_solution(&self, index: u8) -> bool {
+        index == self.solution
+    }
+
+    /// The task of the puzzle, like `Place TDSH to win in 3 plies.`.
+    pub fn describe(&self) -> String {
+        format!(
+            "Place {} to win in {} plies.",
+            Piece::from_number(self.piece).code(),
+            self.plies
+        )
+    }
+
+    /// Tell if the placement on `index` solved the puzzle, or which cell did.
+    pub fn verdict(&self, index: u8) -> String {
+        match Cell::new(self.solution) {
+            _ if self.is_solution(index) => "Solved!".to_string(),
+            Some(cell) => format!("Not quite, {} wins.", cell),
+            None => "Not quite.".to_string(),
+        }
+    }
+}
+
+/// Written as the position in the notation of `Board::to_fen`, the number of plies and the solution,
+/// like `5.../..c./..../...a 3 1; win in 3; B3`.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}; win in {}; {}",
+            self.board.to_fen(Some(self.piece), self.mover),
+            self.plies,
+            Cell::new(self.solution).ok_or(fmt::Error)?
+        )
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [fen, plies, solution] = s.split(';').map(str::trim).collect::<Vec<_>>()[..] else {
+            return Err(format!(
+                "'{}' is not a puzzle like 5.../..c./..../...a 3 1; win in 3; B3",
+                s.trim()
+            ));
+        };
+        let (board, piece, mover) = Board::from_fen(fen)?;
+        let piece = piece.ok_or("a puzzle has a piece in hand")?;
+        let plies = plies
+            .strip_prefix("win in ")
+            .and_then(|plies| plies.parse().ok())
+            .ok_or(format!("'{}' is not like win in 3", plies))?;
+        let solution = solution.parse::<Cell>()?.index();
+        if !board.empty_index(solution) {
+            return Err(format!("the solution {} is not empty", solution));
+        }
+        Ok(Puzzle {
+            board,
+            piece,
+            mover,
+            plies,
+            solution,
+        })
+    }
+}
+
+/// The placements of `piece` on `board` that win within `plies` placements, whatever the opponent does.
+pub fn forced_wins(board: &Board, piece: u8, plies: usize) -> Vec<u8> {
+    board
+        .empty_spaces()
+        .into_iter()
+        .filter(|&index| forces_win(board, piece, index, plies))
+        .collect()
+}
+
+/// Check if placing `piece` on `index` wins at once, or lets the placer hand over a piece after which every
+/// placement and every piece handed back still wins within the plies that are left.
+fn forces_win(board: &Board, piece: u8, index: u8, plies: usize) -> bool {
+    let mut next = *board;
+    if next.put_piece(piece, index).is_err() {
+        return false;
+    }
+    if next.has_winner() {
+        return true;
+    }
+    if plies < 3 || next.game_over() {
+        return false;
+    }
+    next.valid_pieces().into_iter().any(|given| {
+        next.empty_spaces().into_iter().all(|reply| {
+            let mut after = next;
+            after.put_piece(given, reply).is_ok()
+                && !after.game_over()
+                && after
+                    .valid_pieces()
+                    .into_iter()
+                    .all(|back| !forced_wins(&after, back, plies - 2).is_empty())
+        })
+    })
+}
+
+/// Show every puzzle on `output` and read the placements from `input`, until `q` or the end of the input.
+/// Returns the number of puzzles solved.
+pub fn run_text(
+    puzzles: &[Puzzle],
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<usize> {
+    let mut lines = input.lines();
+    let mut solved = 0;
+    for puzzle in puzzles {
+        writeln!(output, "\n{}\n", PrintableBoard::new(&puzzle.board))?;
+        writeln!(output, "{}", puzzle.describe())?;
+        let index = loop {
+            write!(output, "Your placement, like B3, or q to quit: ")?;
+            output.flush()?;
+            let Some(line) = lines.next().transpose()? else {
+                return Ok(solved);
+            };
+            match line.trim() {
+                "q" => return Ok(solved),
+                line => match notation::parse_index(line) {
+                    Some(index) if puzzle.board.empty_index(index) => break index,
+                    _ => writeln!(output, "'{}' is not an empty cell", line)?,
+                },
+            }
+        };
+        solved += usize::from(puzzle.is_solution(index));
+        writeln!(output, "{}", puzzle.verdict(index))?;
+    }
+    Ok(solved)
+}
+
+/// Searches random positions for puzzles.
+#[derive(Debug)]
+pub struct PuzzleGenerator {
+    solver: Solver,
+    rng: SharedRng,
+    plies: usize,
+    /// The most empty cells of a position that is tried.
+    empty: usize,
+}
+
+impl Default for PuzzleGenerator {
+    fn default() -> Self {
+        PuzzleGenerator {
+            solver: Solver::new(),
+            rng: SharedRng::new(),
+            plies: 3,
+            empty: 8,
+        }
+    }
+}
+
+impl PuzzleGenerator {
+    /// Create a generator of puzzles that win in three plies, on boards with at most eight empty cells.
+    pub fn new() -> Self {
+        PuzzleGenerator::default()
+    }
+
+    /// Prove the puzzles with `solver`, for example one with a memory limit.
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Draw the positions from `rng`, so a seed gives the same puzzles.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Look for wins in `plies` placements, an odd number since the player to move places the last piece.
+    pub fn with_plies(mut self, plies: usize) -> Self {
+        self.plies = plies;
+        self
+    }
+
+    /// Try positions with at most `empty` empty cells, more take the solver longer.
+    pub fn with_empty(mut self, empty: usize) -> Self {
+        self.empty = empty;
+        self
+    }
+
+    /// Try up to `tries` random positions, and return the first puzzle found.
+    pub fn generate(&mut self, tries: usize) -> Option<Puzzle> {
+        (0..tries).find_map(|_| {
+            let (board, mover) = self.random_position()?;
+            let piece = self.rng.choose(&board.valid_pieces())?;
+            self.check(&board, piece, mover)
+        })
+    }
+
+    /// Play random placements that do not win, until few enough cells are empty.
+    /// Returns the position and the player to place the next piece, or `None` if the random game ended early.
+    fn random_position(&self) -> Option<(Board, usize)> {
+        let empty = self
+            .rng
+            .usize(self.plies.clamp(1, 16)..=self.empty.clamp(1, 15));
+        let board = PositionGenerator::new()
+            .with_rng(self.rng.clone())
+            .board(16 - empty as u8)?;
+        // The first player hands over the first piece, so the second player places it.
+        Some((board, (16 - empty + 1) % 2))
+    }
+
+    /// The puzzle of placing `piece` on `board`, if exactly one placement wins and it takes all the plies.
+    fn check(&mut self, board: &Board, piece: u8, mover: usize) -> Option<Puzzle> {
+        let [solution] = forced_wins(board, piece, self.plies)[..] else {
+            return None;
+        };
+        if self.plies >= 3 && forces_win(board, piece, solution, self.plies - 2) {
+            return None;
+        }
+        let wins = self
+            .solver
+            .placement_outcomes(board, piece)
+            .into_iter()
+            .filter(|&(_, outcome)| outcome == Outcome::Win)
+            .count();
+        (wins == 1).then_some(Puzzle {
+            board: *board,
+            piece,
+            mover,
+            plies: self.plies,
+            solution,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Ruleset;
+
+    use super::*;
+
+    #[test]
+    fn test_generated_puzzles_are_unique() {
+        let mut generator = PuzzleGenerator::new()
+            .with_rng(SharedRng::seeded(2799))
+            .with_empty(6);
+        let puzzle = generator.generate(500).expect("a puzzle in 500 tries");
+        assert_eq!(puzzle.plies, 3);
+        assert_eq!(
+            forced_wins(&puzzle.board, puzzle.piece, 3),
+            vec![puzzle.solution]
+        );
+        assert!(forced_wins(&puzzle.board, puzzle.piece, 1).is_empty());
+        let outcomes = Solver::new().placement_outcomes(&puzzle.board, puzzle.piece);
+        for (index, outcome) in outcomes {
+            assert_eq!(outcome == Outcome::Win, puzzle.is_solution(index));
+        }
+        assert_eq!(puzzle.to_string().parse::<Puzzle>(), Ok(puzzle));
+    }
+
+    #[test]
+    fn test_win_in_one() {
+        // Three tall pieces on the top row, and a tall piece in hand.
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (9, 1), (10, 2), (0, 15)])
+            .unwrap();
+        assert_eq!(forced_wins(&board, 11, 1), vec![3]);
+        let puzzle: Puzzle = "89a./..../..../...0 b 1; win in 1; D1".parse().unwrap();
+        assert_eq!(
+            (puzzle.board, puzzle.piece, puzzle.solution),
+            (board, 11, 3)
+        );
+        assert!(
+            "89a./..../..../...0 b 1; win in 1"
+                .parse::<Puzzle>()
+                .is_err()
+        );
+        let advanced = Puzzle {
+            board: board.with_ruleset(Ruleset::Advanced),
+            ..puzzle
+        };
+        assert_eq!(advanced.to_string().parse(), Ok(advanced));
+        let mut output = Vec::new();
+        let solved = run_text(&[puzzle, puzzle], "A2\nD1\nq\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(solved, 1);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Not quite, D1 wins."));
+        assert!(output.contains("Solved!"));
+    }
+}
@@ -0,0 +1,450 @@
+// Quarto Game Notation (QGN): a text format for complete games modelled on PGN, to share games and keep them in files.
+// Tags in square brackets describe the game, followed by the numbered moves in the notation of `notation` and the result.
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    game::GameResult,
+    notation::{self, Cell},
+    observer::GameObserver,
+    printable::Piece,
+    record::{GameRecord, Turn},
+    usage,
+};
+
+/// The tags every game starts with, in this order.
+const ROSTER: [&str; 5] = ["Event", "Date", "First", "Second", "Result"];
+
+/// The longest line of moves that is written.
+const LINE_WIDTH: usize = 79;
+
+/// A game in QGN: its tags and its record.
+/// The first player hands over the first piece, after that the players take turns.
+/// QGN knows two sides: a team game is written as a game of its teams, and reads back as a game of two players.
+///
+/// ```text
+/// [Event "Casual game"]
+/// [Date "2026-10-16"]
+/// [First "Julian"]
+/// [Second "Computer"]
+/// [Result "0-1"]
+///
+/// 1. TDSH A1 2. SLRS B2 3. TLRH C3 4. SDSS D4# 0-1
+/// ```
+///
+/// A `#` after a cell marks a called Quarto, and text in braces is a comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QgnGame {
+    tags: Vec<(String, String)>,
+    record: GameRecord,
+}
+
+impl QgnGame {
+    /// Describe `record` with the tags of the roster: an unknown event and players, today's date and the result.
+    pub fn new(record: GameRecord) -> Self {
+        let today = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let values = [
+            "?".to_string(),
+            usage::format_date(today),
+            "?".to_string(),
+            "?".to_string(),
+            result_token(record.result()).to_string(),
+        ];
+        QgnGame {
+            tags: ROSTER
+                .iter()
+                .map(|name| name.to_string())
+                .zip(values)
+                .collect(),
+            record,
+        }
+    }
+
+    /// Set the tag `name` to `value`, replacing the value it had.
+    pub fn with_tag(mut self, name: &str, value: &str) -> Self {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+        self
+    }
+
+    /// The value of the tag `name`, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    pub fn into_record(self) -> GameRecord {
+        self.record
+    }
+}
+
+/// The token of a result in the moves and the `Result` tag.
+/// A loss on time is scored as a win of the opponent, which is what QGN can tell, and a team game as a game of the teams.
+fn result_token(result: Option<GameResult>) -> &'static str {
+    match result.map(|result| (result, result.winning_team())) {
+        Some((_, Some(0))) => "1-0",
+        Some((_, Some(_))) => "0-1",
+        Some((GameResult::Draw, None)) => "1/2-1/2",
+        Some((_, None)) => "error",
+        None => "*",
+    }
+}
+
+/// The result of a token of `result_token`, with `Some(None)` for a game without a result.
+fn parse_result(token: &str) -> Option<Option<GameResult>> {
+    match token {
+        "1-0" => Some(Some(GameResult::Win(0))),
+        "0-1" => Some(Some(GameResult::Win(1))),
+        "1/2-1/2" => Some(Some(GameResult::Draw)),
+        "error" => Some(Some(GameResult::Error)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+impl fmt::Display for QgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.tags.iter() {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f)?;
+        let mut line = String::new();
+        let moves = self.record.turns().iter().enumerate().map(|(ply, turn)| {
+            let cell = Cell::new(turn.index).map_or(turn.index.to_string(), |c| c.to_string());
+            format!(
+                "{}. {} {}{}",
+                ply + 1,
+                Piece::from_number(turn.piece).code(),
+                cell,
+                if turn.quarto_called { "#" } else { "" }
+            )
+        });
+        for token in moves.chain([result_token(self.record.result()).to_string()]) {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+impl FromStr for QgnGame {
+    type Err = String;
+
+    /// Parse a single game, the tags of the roster may be missing.
+    /// Piece numbers and board indices are accepted as well as the notation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut movetext = String::new();
+        for line in s.lines().map(str::trim) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (name, value) = tag
+                    .strip_suffix(']')
+                    .and_then(|tag| tag.split_once(' '))
+                    .and_then(|(name, value)| {
+                        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+                        Some((name, value.replace("\\\"", "\"").replace("\\\\", "\\")))
+                    })
+                    .ok_or_else(|| format!("'{}' is not a tag like [Event \"name\"]", line))?;
+                tags.push((name.to_string(), value));
+            } else {
+                movetext.push_str(line);
+                movetext.push(' ');
+            }
+        }
+        let mut record = GameRecord::new();
+        let mut piece: Option<u8> = None;
+        let mut result = None;
+        let mut words = without_comments(&movetext)?;
+        words.retain(|word| !is_move_number(word));
+        for word in words {
+            if result.is_some() {
+                return Err(format!("'{}' comes after the result", word));
+            }
+            if let Some(token) = parse_result(&word) {
+                result = Some(token);
+                continue;
+            }
+            match piece.take() {
+                None => {
+                    piece = Some(
+                        notation::parse_piece(&word)
+                            .filter(|&piece| piece < 16)
+                            .ok_or_else(|| format!("'{}' is not a piece like TDSH", word))?,
+                    );
+                }
+                Some(piece) => {
+                    let (cell, called) = match word.strip_suffix('#') {
+                        Some(cell) => (cell, true),
+                        None => (word.as_str(), false),
+                    };
+                    let index = notation::parse_index(cell)
+                        .filter(|&index| index < 16)
+                        .ok_or_else(|| format!("'{}' is not a cell like B3", word))?;
+                    let ply = record.len();
+                    record.push(Turn {
+                        giver: ply % 2,
+                        piece,
+                        placer: 1 - ply % 2,
+                        index,
+                        quarto_called: called,
+                    });
+                }
+            }
+        }
+        if let Some(piece) = piece {
+            return Err(format!("piece {} is not placed", piece));
+        }
+        let tagged = tags
+            .iter()
+            .find(|(name, _)| name == "Result")
+            .map(|(_, value)| parse_result(value).ok_or(format!("'{}' is not a result", value)))
+            .transpose()?;
+        match (result, tagged) {
+            (Some(moves), Some(tag)) if moves != tag => {
+                return Err("the result of the moves differs from the Result tag".to_string());
+            }
+            (Some(Some(result)), _) | (None, Some(Some(result))) => record.set_result(result),
+            _ => {}
+        }
+        Ok(QgnGame { tags, record })
+    }
+}
+
+/// Split `movetext` into words, leaving out comments in braces.
+fn without_comments(movetext: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut rest = movetext;
+    while let Some(start) = rest.find('{') {
+        words.extend(rest[..start].split_whitespace().map(str::to_string));
+        let end = rest[start..]
+            .find('}')
+            .ok_or("a comment is not closed with }".to_string())?;
+        rest = &rest[start + end + 1..];
+    }
+    words.extend(rest.split_whitespace().map(str::to_string));
+    Ok(words)
+}
+
+/// Check if `word` is a move number like `12.`.
+fn is_move_number(word: &str) -> bool {
+    word.strip_suffix('.')
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Parse every game in `text`, where each game starts at its tags.
+/// Games are parsed on their own, so a broken game does not hide the ones after it.
+pub fn parse_all(text: &str) -> Vec<Result<QgnGame, String>> {
+    let mut games: Vec<String> = Vec::new();
+    let mut in_moves = true;
+    for line in text.lines() {
+        let is_tag = line.trim_start().starts_with('[');
+        if is_tag && in_moves {
+            games.push(String::new());
+        }
+        if !line.trim().is_empty() {
+            in_moves = !is_tag;
+            if let Some(game) = games.last_mut() {
+                game.push_str(line);
+                game.push('\n');
+            } else {
+                games.push(format!("{}\n", line));
+            }
+        }
+    }
+    games.iter().map(|game| game.parse()).collect()
+}
+
+/// Appends every game that ends to a QGN file, with the same tags for every game.
+/// Games that end at the same time on other threads are appended one after the other.
+#[derive(Debug)]
+pub struct QgnExport {
+    path: PathBuf,
+    tags: Vec<(String, String)>,
+    error: Mutex<Option<io::Error>>,
+    /// Held while a game is appended, so games of other threads do not end up in between.
+    writing: Mutex<()>,
+}
+
+impl QgnExport {
+    /// Create an observer that appends to `path`, creating it if needed.
+    pub fn new(path: PathBuf) -> Self {
+        QgnExport {
+            path,
+            tags: Vec::new(),
+            error: Mutex::new(None),
+            writing: Mutex::new(()),
+        }
+    }
+
+    /// Give every exported game the tag `name` with `value`.
+    pub fn with_tag(mut self, name: &str, value: &str) -> Self {
+        self.tags.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Take the first error writing a game, since observers cannot return one.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.lock().unwrap().take()
+    }
+}
+
+impl GameObserver for QgnExport {
+    fn on_game_end(&self, _result: GameResult, record: &GameRecord) {
+        let game = self
+            .tags
+            .iter()
+            .fold(QgnGame::new(record.clone()), |game, (name, value)| {
+                game.with_tag(name, value)
+            });
+        let text = format!("{}\n", game);
+        let _writing = self.writing.lock().unwrap_or_else(|e| e.into_inner());
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        if let Err(e) = written {
+            self.error.lock().unwrap().get_or_insert(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        QuartoGame,
+        player::ComputerPlayer,
+        random::SharedRng,
+        strategy::{DumbStrategy, SmartStrategy},
+    };
+
+    use super::*;
+
+    fn played(seed: u64) -> GameRecord {
+        let rng = SharedRng::seeded(seed);
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(SmartStrategy::new(1).with_rng(rng.clone())),
+            ComputerPlayer::new(DumbStrategy::new().with_rng(rng)),
+        );
+        game.play();
+        game.history().clone()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for seed in 0..10 {
+            let game = QgnGame::new(played(seed))
+                .with_tag("First", "Ada \"the engine\"")
+                .with_tag("Round", "3");
+            let text = game.to_string();
+            assert!(text.lines().all(|line| line.len() <= LINE_WIDTH));
+            assert_eq!(text.parse(), Ok(game));
+        }
+        let game = QgnGame::new(GameRecord::new());
+        assert_eq!(game.tag("Result"), Some("*"));
+        assert_eq!(
+            &game.tags()[..2]
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>(),
+            &ROSTER[..2]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let game: QgnGame = "[Event \"Club night\"]\n[Result \"1-0\"]\n\n\
+                             1. TDSH A1 {a quiet start} 2. 0 b2\n3. TLRH C3 4. SDSS D4# 1-0\n"
+            .parse()
+            .unwrap();
+        assert_eq!(game.tag("Event"), Some("Club night"));
+        let record = game.record();
+        assert_eq!(record.len(), 4);
+        assert_eq!((record.turns()[1].piece, record.turns()[1].index), (0, 5));
+        assert_eq!(record.turns()[1].giver, 1);
+        assert!(record.turns()[3].quarto_called);
+        assert_eq!(record.result(), Some(GameResult::Win(0)));
+        let errors = [
+            "1. TDSH",
+            "1. TDSH E5",
+            "1. TXSH A1",
+            "[Result \"0-1\"]\n1. TDSH A1 1-0",
+            "1. TDSH A1 * 2. SLRS B2",
+            "1. TDSH {open A1",
+            "[Result 1-0]",
+        ];
+        for text in errors {
+            assert!(text.parse::<QgnGame>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_all_and_export() {
+        let path = env::temp_dir().join(format!("quarto-qgn-{}.qgn", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let export = QgnExport::new(path.clone()).with_tag("Event", "Test");
+        for seed in 0..3 {
+            export.on_game_end(GameResult::Draw, &played(seed));
+        }
+        assert!(export.take_error().is_none());
+        let mut text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        text.push_str("[Event \"Broken\"]\n1. TDSH\n");
+        let games = parse_all(&text);
+        assert_eq!(games.len(), 4);
+        for (seed, game) in (0..3).zip(games.iter()) {
+            let game = game.as_ref().unwrap();
+            assert_eq!(game.tag("Event"), Some("Test"));
+            assert_eq!(game.record(), &played(seed));
+        }
+        assert!(games[3].is_err());
+        // Games that end on several threads at once are appended whole.
+        let export = QgnExport::new(path.clone());
+        std::thread::scope(|scope| {
+            for seed in 0..4 {
+                let export = &export;
+                scope.spawn(move || {
+                    for _ in 0..5 {
+                        export.on_game_end(GameResult::Draw, &played(seed));
+                    }
+                });
+            }
+        });
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let games = parse_all(&text);
+        assert_eq!(games.len(), 20);
+        assert!(games.iter().all(|game| game.is_ok()));
+    }
+}
@@ -0,0 +1,94 @@
+// A random generator that is handed to strategies, so a seed reproduces the same games.
+// Clones draw from the same sequence, so both players of a game and the wrappers around a strategy share one seed.
+
+use std::{
+    ops::RangeBounds,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// A seedable random generator that can be shared between strategies and threads.
+#[derive(Debug, Clone)]
+pub struct SharedRng(Arc<Mutex<fastrand::Rng>>);
+
+impl SharedRng {
+    /// Create a generator seeded from the global generator of this thread.
+    /// After `fastrand::seed`, this is reproducible as well.
+    pub fn new() -> Self {
+        SharedRng::from_rng(fastrand::Rng::new())
+    }
+
+    /// Create a generator that always produces the same sequence for `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        SharedRng::from_rng(fastrand::Rng::with_seed(seed))
+    }
+
+    fn from_rng(rng: fastrand::Rng) -> Self {
+        SharedRng(Arc::new(Mutex::new(rng)))
+    }
+
+    /// Lock the generator, a poisoned lock still holds a usable generator.
+    fn lock(&self) -> MutexGuard<'_, fastrand::Rng> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A random number in `range`.
+    pub fn usize(&self, range: impl RangeBounds<usize>) -> usize {
+        self.lock().usize(range)
+    }
+
+    /// A random number between 0 and 1.
+    pub fn f64(&self) -> f64 {
+        self.lock().f64()
+    }
+
+    /// Pick a random item of `items`, or `None` if there are none.
+    pub fn choose<T: Copy>(&self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(items[self.usize(..items.len())])
+    }
+
+    /// Split off an independent generator for a single search, seeded from this one.
+    /// This avoids taking the lock for every random number in a hot loop.
+    pub fn fork(&self) -> fastrand::Rng {
+        self.lock().fork()
+    }
+}
+
+impl Default for SharedRng {
+    fn default() -> Self {
+        SharedRng::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_repeats_sequence() {
+        let first = SharedRng::seeded(42);
+        let second = SharedRng::seeded(42);
+        let draw = |rng: &SharedRng| (0..10).map(|_| rng.usize(..100)).collect::<Vec<usize>>();
+        assert_eq!(draw(&first), draw(&second));
+    }
+
+    #[test]
+    fn test_clones_share_sequence() {
+        let rng = SharedRng::seeded(7);
+        let clone = rng.clone();
+        let expected = {
+            let other = SharedRng::seeded(7);
+            (other.usize(..1000), other.usize(..1000))
+        };
+        assert_eq!((rng.usize(..1000), clone.usize(..1000)), expected);
+    }
+
+    #[test]
+    fn test_choose() {
+        let rng = SharedRng::seeded(1);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&[3]), Some(3));
+    }
+}
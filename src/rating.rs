@@ -0,0 +1,215 @@
+// Elo ratings of strategies, updated from match results.
+// The ratings are stored between tournament runs, so changes to a strategy show up as a change in its rating.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    game::GameResult,
+    persist::{self, DataKind, PersistError},
+    tournament::PairedReport,
+};
+
+/// The rating a strategy starts with.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// The rating of a single strategy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    /// The number of rated games played.
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            elo: INITIAL_RATING,
+            games: 0,
+        }
+    }
+}
+
+/// The expected score of a player rated `rating` against a player rated `opponent`.
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+/// Elo ratings of strategies, by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ratings {
+    ratings: BTreeMap<String, Rating>,
+    /// How much a single game can change a rating.
+    k_factor: f64,
+}
+
+impl Default for Ratings {
+    fn default() -> Self {
+        Ratings {
+            ratings: BTreeMap::new(),
+            k_factor: 32.0,
+        }
+    }
+}
+
+impl Ratings {
+    /// Create an empty set of ratings with a K-factor of 32.
+    pub fn new() -> Self {
+        Ratings::default()
+    }
+
+    /// Use `k_factor` as the largest change of a rating in a single game.
+    pub fn with_k_factor(mut self, k_factor: f64) -> Self {
+        self.k_factor = k_factor;
+        self
+    }
+
+    /// The rating of `name`, or the initial rating if it has not played yet.
+    pub fn get(&self, name: &str) -> Rating {
+        self.ratings.get(name).copied().unwrap_or_default()
+    }
+
+    /// Set the rating of `name`, for example to restore stored ratings.
+    pub fn insert(&mut self, name: &str, rating: Rating) {
+        self.ratings.insert(name.to_string(), rating);
+    }
+
+    /// Check if nobody has a rating yet.
+    pub fn is_empty(&self) -> bool {
+        self.ratings.is_empty()
+    }
+
+    /// Update the ratings with a game between `a` and `b`, where `a` scored `score` (1 for a win, 0.5 for a draw, 0 for a loss).
+    pub fn record(&mut self, a: &str, b: &str, score: f64) {
+        let (rating_a, rating_b) = (self.get(a), self.get(b));
+        let change = self.k_factor * (score - expected_score(rating_a.elo, rating_b.elo));
+        self.ratings.insert(
+            a.to_string(),
+            Rating {
+                elo: rating_a.elo + change,
+                games: rating_a.games + 1,
+            },
+        );
+        self.ratings.insert(
+            b.to_string(),
+            Rating {
+                elo: rating_b.elo - change,
+                games: rating_b.games + 1,
+            },
+        );
+    }
+
+    /// Update the ratings with a game result where `first` was the first player, or the first team of a team game.
+    /// Games that ended with an error are not rated.
+    pub fn record_result(&mut self, first: &str, second: &str, result: GameResult) {
+        let score = match (result, result.winning_team()) {
+            (_, Some(0)) => 1.0,
+            (_, Some(_)) => 0.0,
+            (GameResult::Draw, None) => 0.5,
+            (_, None) => return,
+        };
+        self.record(first, second, score);
+    }
+
+    /// Update the ratings with all games of a paired match between `a` and `b`.
+    pub fn record_report(&mut self, a: &str, b: &str, report: &PairedReport) {
+        for pair in report.pairs.iter() {
+            self.record_result(a, b, pair.first);
+            self.record_result(b, a, pair.second);
+        }
+    }
+
+    /// All ratings, highest first.
+    pub fn leaderboard(&self) -> Vec<(&str, Rating)> {
+        let mut board: Vec<(&str, Rating)> = self
+            .ratings
+            .iter()
+            .map(|(name, rating)| (name.as_str(), *rating))
+            .collect();
+        board.sort_by(|a, b| b.1.elo.total_cmp(&a.1.elo));
+        board
+    }
+
+    /// Write the ratings in the stored format, with a versioned header.
+    /// Names cannot contain whitespace.
+    pub fn to_text(&self) -> String {
+        let body: String = self
+            .ratings
+            .iter()
+            .map(|(name, rating)| format!("{} {} {}\n", name, rating.elo, rating.games))
+            .collect();
+        persist::write(DataKind::Ratings, &body)
+    }
+
+    /// Read ratings written by `to_text`.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::Ratings, data)?;
+        let mut ratings = Ratings::new();
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = || PersistError::Malformed {
+                line,
+                message: "expected a name, a rating and a number of games".to_string(),
+            };
+            match text.split_whitespace().collect::<Vec<&str>>()[..] {
+                [] => {}
+                [name, elo, games] => {
+                    let rating = Rating {
+                        elo: elo.parse().map_err(|_| malformed())?,
+                        games: games.parse().map_err(|_| malformed())?,
+                    };
+                    ratings.insert(name, rating);
+                }
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(ratings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        player::ComputerPlayer,
+        random::SharedRng,
+        strategy::{DumbStrategy, SmartStrategy},
+        tournament,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_expected_score() {
+        assert_eq!(expected_score(1500.0, 1500.0), 0.5);
+        assert!((expected_score(1900.0, 1500.0) - 0.909).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_record_is_zero_sum() {
+        let mut ratings = Ratings::new();
+        ratings.record("smart", "dumb", 1.0);
+        assert_eq!(ratings.get("smart").elo, 1516.0);
+        assert_eq!(ratings.get("dumb").elo, 1484.0);
+        ratings.record_result("smart", "dumb", GameResult::Error);
+        assert_eq!(ratings.get("smart").games, 1);
+    }
+
+    #[test]
+    fn test_stronger_strategy_rises() {
+        let report = tournament::play_paired(
+            || ComputerPlayer::new(SmartStrategy::new(1)),
+            || ComputerPlayer::new(DumbStrategy::new()),
+            &tournament::balanced_openings(5, 2, &SharedRng::seeded(1)),
+        );
+        let mut ratings = Ratings::new();
+        ratings.record_report("smart", "dumb", &report);
+        assert_eq!(ratings.get("smart").games, 10);
+        assert_eq!(ratings.leaderboard()[0].0, "smart");
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let mut ratings = Ratings::new();
+        ratings.record("mcts", "naive", 0.5);
+        ratings.record("mcts", "smart", 0.0);
+        assert_eq!(Ratings::from_text(&ratings.to_text()), Ok(ratings));
+    }
+}
@@ -0,0 +1,348 @@
+// A record of a played game: every turn, and the result once the game is over.
+// This is what interfaces, replays, and post-game analysis work with.
+
+use crate::{
+    board::{Board, Ruleset},
+    game::{GameResult, MAX_SEATS},
+    persist::{self, DataKind, PersistError},
+};
+
+/// A single turn: one player hands over a piece, the other player places it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Turn {
+    /// The player that chose the piece.
+    pub giver: usize,
+    /// The piece that was handed over.
+    pub piece: u8,
+    /// The player that placed the piece.
+    pub placer: usize,
+    /// The index the piece was placed on.
+    pub index: u8,
+    /// Whether the placer called Quarto after placing the piece.
+    pub quarto_called: bool,
+}
+
+/// All turns of a game in order, and the result once it is known.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    turns: Vec<Turn>,
+    result: Option<GameResult>,
+    /// The rules the game was played by, records without one are classic games.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Ruleset::is_classic")
+    )]
+    ruleset: Ruleset,
+}
+
+impl GameRecord {
+    /// Create an empty record for a new game.
+    pub fn new() -> Self {
+        GameRecord::default()
+    }
+
+    /// Record a game played by `ruleset`.
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    /// The rules the game was played by.
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    /// Add a turn to the end of the record.
+    pub fn push(&mut self, turn: Turn) {
+        self.turns.push(turn);
+    }
+
+    /// Remove the last turn, for example to take it back.
+    /// The game is no longer over after that, so the result is cleared as well.
+    pub fn pop(&mut self) -> Option<Turn> {
+        let turn = self.turns.pop()?;
+        self.result = None;
+        Some(turn)
+    }
+
+    /// All turns played so far, in order.
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// The number of turns played.
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// Check if no turns were played yet.
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// The result of the game, or `None` if it is not over yet.
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    /// Store the result of the game.
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = Some(result);
+    }
+
+    /// Rebuild the board after the first `turns` turns.
+    /// Returns `None` if a recorded turn cannot be played on the board.
+    pub fn board_after(&self, turns: usize) -> Option<Board> {
+        let mut board = Board::new().with_ruleset(self.ruleset);
+        for turn in self.turns.iter().take(turns) {
+            board.put_piece(turn.piece, turn.index).ok()?;
+        }
+        Some(board)
+    }
+
+    /// Rebuild the final board of the record.
+    pub fn final_board(&self) -> Option<Board> {
+        self.board_after(self.turns.len())
+    }
+}
+
+impl GameRecord {
+    /// Write the record in the current stored format, with a versioned header.
+    pub fn to_text(&self) -> String {
+        let mut body = String::new();
+        self.write_lines(&mut body);
+        persist::write(DataKind::GameRecord, &body)
+    }
+
+    /// Append the ruleset of an advanced game, the turns and the result to `body`, a line each.
+    pub(crate) fn write_lines(&self, body: &mut String) {
+        if !self.ruleset.is_classic() {
+            *body += &format!("ruleset {}\n", self.ruleset);
+        }
+        for turn in self.turns.iter() {
+            *body += &format!(
+                "turn {} {} {} {} {}\n",
+                turn.giver, turn.piece, turn.placer, turn.index, turn.quarto_called as u8
+            );
+        }
+        match self.result {
+            Some(GameResult::Win(winner)) => *body += &format!("result win {}\n", winner),
+            Some(GameResult::Draw) => *body += "result draw\n",
+            Some(GameResult::Error) => *body += "result error\n",
+            Some(GameResult::Timeout(player)) => *body += &format!("result timeout {}\n", player),
+            None => {}
+        }
+    }
+
+    /// Read a line written by `write_lines`, split in `words`, of a game between `seats` players.
+    /// Returns the reason if the line is malformed.
+    pub(crate) fn read_line(&mut self, words: &[&str], seats: usize) -> Result<(), &'static str> {
+        let seat = |word: &str| word.parse().ok().filter(|&seat: &usize| seat < seats);
+        match words {
+            [] => {}
+            ["turn", numbers @ ..] => {
+                let numbers: Vec<u8> = numbers
+                    .iter()
+                    .map(|n| n.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "turn numbers must be small integers")?;
+                let [giver, piece, placer, index, quarto] = numbers[..] else {
+                    return Err("a turn has five numbers");
+                };
+                if giver as usize >= seats || placer as usize >= seats {
+                    return Err("the players of a turn must be seats of the game");
+                }
+                self.push(Turn {
+                    giver: giver as usize,
+                    piece,
+                    placer: placer as usize,
+                    index,
+                    quarto_called: quarto != 0,
+                });
+            }
+            ["ruleset", ruleset] => {
+                self.ruleset = ruleset.parse().map_err(|_| "unknown ruleset")?;
+            }
+            ["result", "draw"] => self.set_result(GameResult::Draw),
+            ["result", "error"] => self.set_result(GameResult::Error),
+            ["result", "win", winner] => self.set_result(GameResult::Win(
+                seat(winner).ok_or("the winner must be a seat of the game")?,
+            )),
+            ["result", "timeout", player] => self.set_result(GameResult::Timeout(
+                seat(player).ok_or("the player must be a seat of the game")?,
+            )),
+            _ => return Err("expected a ruleset, a turn or a result"),
+        }
+        Ok(())
+    }
+
+    /// Read a record written by `to_text`, migrating it first if it was written in an older format.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::GameRecord, data)?;
+        let mut record = GameRecord::new();
+        // The header is the first line of the file.
+        for (line, text) in (2..).zip(body.lines()) {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            record
+                .read_line(&words, MAX_SEATS)
+                .map_err(|message| PersistError::Malformed {
+                    line,
+                    message: message.to_string(),
+                })?;
+        }
+        Ok(record)
+    }
+}
+
+/// Upgrade a record from before the header, version 0, to version 1.
+/// The lines were the same, so the words of each line are only joined by single spaces again and the blank lines dropped.
+pub(crate) fn migrate_v0(body: &str) -> Result<String, PersistError> {
+    let mut migrated = String::new();
+    for (line, text) in (1..).zip(body.lines()) {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.first() {
+            None => {}
+            Some(&("ruleset" | "turn" | "result")) => migrated += &format!("{}\n", words.join(" ")),
+            Some(_) => {
+                return Err(PersistError::Malformed {
+                    line,
+                    message: "expected a ruleset, a turn or a result".to_string(),
+                });
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(piece: u8, index: u8) -> Turn {
+        Turn {
+            giver: 0,
+            piece,
+            placer: 1,
+            index,
+            quarto_called: false,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json() {
+        let mut record = GameRecord::new();
+        record.push(turn(3, 5));
+        record.set_result(GameResult::Win(1));
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            "{\"turns\":[{\"giver\":0,\"piece\":3,\"placer\":1,\"index\":5,\"quarto_called\":false}],\"result\":{\"Win\":1}}"
+        );
+        assert_eq!(serde_json::from_str::<GameRecord>(&json).unwrap(), record);
+    }
+
+    #[test]
+    fn test_new_record_empty() {
+        let record = GameRecord::new();
+        assert!(record.is_empty());
+        assert_eq!(record.result(), None);
+        assert_eq!(record.final_board(), Some(Board::new()));
+    }
+
+    #[test]
+    fn test_board_after() {
+        let mut record = GameRecord::new();
+        record.push(turn(3, 5));
+        record.push(turn(4, 6));
+        assert_eq!(record.len(), 2);
+        assert_eq!(
+            record.board_after(1),
+            Some(Board::new().apply_moves(&[(3, 5)]).unwrap())
+        );
+        assert_eq!(
+            record.final_board(),
+            Some(Board::new().apply_moves(&[(3, 5), (4, 6)]).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_board_after_invalid_record() {
+        let mut record = GameRecord::new();
+        record.push(turn(3, 5));
+        record.push(turn(3, 6));
+        assert_eq!(record.final_board(), None);
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let mut record = GameRecord::new();
+        record.push(turn(3, 5));
+        record.push(Turn {
+            quarto_called: true,
+            ..turn(4, 6)
+        });
+        record.set_result(GameResult::Win(1));
+        assert_eq!(GameRecord::from_text(&record.to_text()), Ok(record));
+    }
+
+    #[test]
+    fn test_advanced_roundtrip() {
+        let mut record = GameRecord::new().with_ruleset(Ruleset::Advanced);
+        for (piece, index) in [(8, 0), (12, 1), (10, 4), (9, 5)] {
+            record.push(turn(piece, index));
+        }
+        record.set_result(GameResult::Win(1));
+        let text = record.to_text();
+        assert!(text.contains("ruleset advanced\n"));
+        let read = GameRecord::from_text(&text).unwrap();
+        assert_eq!(read, record);
+        assert!(read.final_board().unwrap().has_winner());
+        assert!(
+            !GameRecord::from_text(&text.replace("ruleset advanced\n", ""))
+                .unwrap()
+                .final_board()
+                .unwrap()
+                .has_winner()
+        );
+    }
+
+    #[test]
+    fn test_load_v1_fixture() {
+        let record = GameRecord::from_text(include_str!("../fixtures/record_v1.txt")).unwrap();
+        assert_eq!(record.len(), 4);
+        assert_eq!(record.result(), Some(GameResult::Win(0)));
+        assert!(record.turns()[3].quarto_called);
+        assert!(record.final_board().unwrap().has_winner());
+    }
+
+    #[test]
+    fn test_migrate_v0_fixture() {
+        let data = include_str!("../fixtures/record_v0.txt");
+        assert!(!data.starts_with("quarto"));
+        let record = GameRecord::from_text(data).unwrap();
+        let v1 = GameRecord::from_text(include_str!("../fixtures/record_v1.txt")).unwrap();
+        assert_eq!(record, v1);
+        let text = record.to_text();
+        assert_eq!(text, include_str!("../fixtures/record_v1.txt"));
+        assert_eq!(GameRecord::from_text(&text), Ok(record));
+        assert!(matches!(
+            GameRecord::from_text("turn 0 8 1 12 0\nmove 3\n"),
+            Err(PersistError::Malformed { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_turn() {
+        assert!(matches!(
+            GameRecord::from_text("quarto record 1\nturn 0 1 1\n"),
+            Err(PersistError::Malformed { line: 2, .. })
+        ));
+        assert!(matches!(
+            GameRecord::from_text("quarto record 1\nturn 0 1 1 5 0\nresult win 4\n"),
+            Err(PersistError::Malformed { line: 3, .. })
+        ));
+    }
+}
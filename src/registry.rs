@@ -0,0 +1,377 @@
+// A registry of the players that can be created by name, for command line flags, config files and the lobby.
+// Every entry has a constructor that builds the player from one shared set of options.
+
+#[cfg(feature = "tui")]
+use std::io::{self, IsTerminal};
+
+#[cfg(feature = "tui")]
+use crate::ui::tui::TuiInterface;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    engine::ExternalEnginePlayer,
+    evaluator::Evaluator,
+    fog::Fog,
+    mcts::MctsBudget,
+    player::{ComputerPlayer, HumanPlayer, Player},
+    random::SharedRng,
+    strategy::{DeterministicStrategy, DumbStrategy, MctsStrategy, NaiveStrategy, SmartStrategy},
+    transposition::TranspositionTable,
+    ui::terminal::TerminalInterface,
+    watchdog::Watchdog,
+};
+
+/// The options a constructor can use, each player takes the ones that apply to it.
+#[derive(Debug, Clone)]
+pub struct PlayerOptions {
+    /// The number of turns the smart strategy looks ahead.
+    pub depth: u8,
+    /// The number of playouts of the MCTS strategy per decision.
+    pub iterations: u32,
+    /// Spread the search of the smart strategy over all cores.
+    #[cfg(feature = "parallel")]
+    pub parallel: bool,
+    /// Play the fog variant as a human, with this many inspections per turn.
+    pub fog: Option<u8>,
+    /// Color the board for a human in the terminal.
+    pub color: bool,
+    /// Describe the pieces to a human as the pieces of a physical set.
+    pub physical: bool,
+    /// Where the random decisions are drawn from.
+    pub rng: SharedRng,
+    /// Scores the positions where the smart strategy stops searching, like a trained model.
+    pub evaluator: Option<Arc<dyn Evaluator>>,
+    /// The command line of the engine program of the external player, which cannot be created without one.
+    pub engine: Option<String>,
+    /// The transposition table of the smart strategy, shared by the players built with these options.
+    pub table: Option<Arc<Mutex<TranspositionTable>>>,
+}
+
+impl Default for PlayerOptions {
+    fn default() -> Self {
+        PlayerOptions {
+            depth: 2,
+            iterations: 5000,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            fog: None,
+            color: false,
+            physical: false,
+            rng: SharedRng::new(),
+            evaluator: None,
+            engine: None,
+            table: None,
+        }
+    }
+}
+
+/// Builds a player from the options, or tells why it cannot.
+pub type Constructor = fn(&PlayerOptions) -> Result<Box<dyn Player>, String>;
+
+/// A player that can be created by name.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub name: &'static str,
+    /// A short description for help texts.
+    pub description: &'static str,
+    /// Whether the player is a person at this terminal, rather than a computer.
+    pub interactive: bool,
+    pub constructor: Constructor,
+}
+
+/// The players that can be created by name, in the order they were registered.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Registry {
+    /// Create a registry without any players.
+    pub fn new() -> Self {
+        Registry {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add `entry`, replacing an entry with the same name.
+    pub fn register(&mut self, entry: Entry) {
+        match self.entries.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Add `entry` and return the registry, to chain registrations.
+    pub fn with(mut self, entry: Entry) -> Self {
+        self.register(entry);
+        self
+    }
+
+    /// The names of all players.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|e| e.name).collect()
+    }
+
+    /// All entries, in the order they were registered.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Find the entry named `name`.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Find the entry named `name`, or tell which names there are.
+    fn entry(&self, name: &str) -> Result<&Entry, String> {
+        self.get(name).ok_or_else(|| {
+            format!(
+                "Unknown player '{}', expected one of {}",
+                name,
+                self.names().join(", ")
+            )
+        })
+    }
+
+    /// Create the player named `name` with `options`.
+    pub fn create(&self, name: &str, options: &PlayerOptions) -> Result<Box<dyn Player>, String> {
+        (self.entry(name)?.constructor)(options)
+    }
+
+    /// Create the player named `name` like `create`, a computer player behind a `Watchdog` with `timeout` per call.
+    /// A computer player that hangs forfeits the game, a person at this terminal is never hurried.
+    pub fn create_guarded(
+        &self,
+        name: &str,
+        options: &PlayerOptions,
+        timeout: Duration,
+    ) -> Result<Box<dyn Player>, String> {
+        let entry = self.entry(name)?;
+        if entry.interactive {
+            return (entry.constructor)(options);
+        }
+        let (constructor, options) = (entry.constructor, options.clone());
+        let watchdog = Watchdog::spawn(move || constructor(&options))?;
+        Ok(Box::new(watchdog.with_timeout(timeout)))
+    }
+
+    /// Create the computer player named `name`, refusing the players that are a person at this terminal.
+    pub fn create_computer(
+        &self,
+        name: &str,
+        options: &PlayerOptions,
+    ) -> Result<Box<dyn Player>, String> {
+        if self.get(name).is_some_and(|entry| entry.interactive) {
+            return Err(format!("'{}' is not a computer player", name));
+        }
+        self.create(name, options)
+    }
+}
+
+impl Default for Registry {
+    /// The players of this crate: a human at the terminal and every strategy.
+    fn default() -> Self {
+        Registry::new()
+            .with(Entry {
+                name: "human",
+                description: "a person at this terminal, full-screen with the tui feature",
+                interactive: true,
+                constructor: |options| {
+                    #[cfg(feature = "tui")]
+                    if options.fog.is_none()
+                        && io::stdin().is_terminal()
+                        && io::stdout().is_terminal()
+                        && let Ok(interface) = TuiInterface::stdio()
+                    {
+                        return Ok(Box::new(HumanPlayer::new(interface)));
+                    }
+                    let interface = TerminalInterface::stdio()
+                        .with_color(options.color)
+                        .with_physical(options.physical);
+                    Ok(match options.fog {
+                        Some(inspections) => {
+                            Box::new(HumanPlayer::new(interface.with_fog(Fog::new(inspections))))
+                        }
+                        None => Box::new(HumanPlayer::new(interface)),
+                    })
+                },
+            })
+            .with(Entry {
+                name: "dumb",
+                description: "random decisions, sometimes forgets to call Quarto",
+                interactive: false,
+                constructor: |options| {
+                    Ok(Box::new(ComputerPlayer::new(
+                        DumbStrategy::new().with_rng(options.rng.clone()),
+                    )))
+                },
+            })
+            .with(Entry {
+                name: "naive",
+                description: "random decisions",
+                interactive: false,
+                constructor: |options| {
+                    Ok(Box::new(ComputerPlayer::new(
+                        NaiveStrategy::new().with_rng(options.rng.clone()),
+                    )))
+                },
+            })
+            .with(Entry {
+                name: "deterministic",
+                description: "always the first piece and the first empty cell",
+                interactive: false,
+                constructor: |_| Ok(Box::new(ComputerPlayer::new(DeterministicStrategy))),
+            })
+            .with(Entry {
+                name: "smart",
+                description: "searches a number of turns ahead",
+                interactive: false,
+                constructor: |options| {
+                    let strategy = SmartStrategy::new(options.depth).with_rng(options.rng.clone());
+                    let strategy = match &options.evaluator {
+                        Some(evaluator) => strategy.with_evaluator(evaluator.clone()),
+                        None => strategy,
+                    };
+                    let strategy = match &options.table {
+                        Some(table) => strategy.with_table(table.clone()),
+                        None => strategy,
+                    };
+                    #[cfg(feature = "parallel")]
+                    let strategy = if options.parallel {
+                        strategy.with_parallel()
+                    } else {
+                        strategy
+                    };
+                    Ok(Box::new(ComputerPlayer::new(strategy)))
+                },
+            })
+            .with(Entry {
+                name: "mcts",
+                description: "Monte Carlo tree search",
+                interactive: false,
+                constructor: |options| {
+                    Ok(Box::new(ComputerPlayer::new(
+                        MctsStrategy::new(MctsBudget::Iterations(options.iterations))
+                            .with_rng(options.rng.clone()),
+                    )))
+                },
+            })
+            .with(Entry {
+                name: "external",
+                description: "an engine program on the protocol of the engine command",
+                interactive: false,
+                constructor: |options| {
+                    let command = options.engine.as_deref().ok_or(
+                        "The external player needs the command line of an engine, set with --engine",
+                    )?;
+                    Ok(Box::new(ExternalEnginePlayer::from_command(command)?))
+                },
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GameResult, QuartoGame};
+
+    use super::*;
+
+    #[test]
+    fn test_default_names() {
+        assert_eq!(
+            Registry::default().names(),
+            vec![
+                "human",
+                "dumb",
+                "naive",
+                "deterministic",
+                "smart",
+                "mcts",
+                "external"
+            ]
+        );
+        assert!(Registry::default().get("human").unwrap().interactive);
+    }
+
+    #[test]
+    fn test_create_computer_players() {
+        let registry = Registry::default();
+        let options = PlayerOptions {
+            depth: 1,
+            iterations: 50,
+            ..PlayerOptions::default()
+        };
+        for entry in registry
+            .entries()
+            .iter()
+            .filter(|e| !e.interactive && e.name != "external")
+        {
+            let mut game = QuartoGame::new(
+                registry.create(entry.name, &options).unwrap(),
+                registry.create("dumb", &options).unwrap(),
+            );
+            assert_ne!(
+                game.play_without_call(),
+                GameResult::Error,
+                "{}",
+                entry.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_guarded() {
+        let registry = Registry::default();
+        let options = PlayerOptions::default();
+        let timeout = Duration::from_secs(5);
+        let mut game = QuartoGame::new(
+            registry.create_guarded("smart", &options, timeout).unwrap(),
+            registry.create_guarded("dumb", &options, timeout).unwrap(),
+        );
+        assert_ne!(game.play_without_call(), GameResult::Error);
+        assert!(
+            registry
+                .create_guarded("grandmaster", &options, timeout)
+                .is_err()
+        );
+        assert!(registry.create("external", &options).is_err());
+        let options = PlayerOptions {
+            engine: Some("quarto-engine-that-does-not-exist".to_string()),
+            ..options
+        };
+        assert!(
+            registry
+                .create_guarded("external", &options, timeout)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unknown_name() {
+        let error = Registry::default()
+            .create("grandmaster", &PlayerOptions::default())
+            .err()
+            .unwrap();
+        assert!(error.contains("grandmaster"));
+        assert!(error.contains("smart"));
+    }
+
+    #[test]
+    fn test_register_replaces() {
+        let mut registry = Registry::default();
+        registry.register(Entry {
+            name: "smart",
+            description: "a shallow search",
+            interactive: false,
+            constructor: |_| Ok(Box::new(ComputerPlayer::new(SmartStrategy::new(1)))),
+        });
+        assert_eq!(registry.names().len(), 7);
+        assert_eq!(
+            registry.get("smart").unwrap().description,
+            "a shallow search"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+// Step through a saved game move by move, to review it after it was played, like the blunders of a simulated game.
+// The navigation is shared by the full-screen viewer of the `tui` feature and the line-based viewer below.
+
+use std::{
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use crate::{
+    board::Board,
+    card,
+    game::GameResult,
+    notation::Cell,
+    printable::{Piece, PrintableBoard},
+    record::{GameRecord, Turn},
+};
+
+/// A record and the move that is shown, from 0 for the empty board up to the number of turns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    record: GameRecord,
+    /// The board after every move, starting with the empty board.
+    boards: Vec<Board>,
+    ply: usize,
+}
+
+impl Replay {
+    /// Start at the empty board of `record`.
+    /// Fails if a turn of the record cannot be played.
+    pub fn new(record: GameRecord) -> Result<Self, String> {
+        let mut boards = vec![Board::new().with_ruleset(record.ruleset())];
+        for (ply, turn) in record.turns().iter().enumerate() {
+            let mut board = boards[ply];
+            board
+                .put_piece(turn.piece, turn.index)
+                .map_err(|e| format!("move {} is illegal: {}", ply + 1, e))?;
+            boards.push(board);
+        }
+        Ok(Replay {
+            record,
+            boards,
+            ply: 0,
+        })
+    }
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// The number of moves that were played before the board that is shown.
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// The number of moves in the game.
+    pub fn len(&self) -> usize {
+        self.boards.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The board after the move that is shown.
+    pub fn board(&self) -> &Board {
+        &self.boards[self.ply]
+    }
+
+    /// The move that led to the board that is shown, `None` at the start.
+    pub fn turn(&self) -> Option<&Turn> {
+        self.ply.checked_sub(1).map(|ply| &self.record.turns()[ply])
+    }
+
+    /// Show the next move, returns false at the end of the game.
+    pub fn forward(&mut self) -> bool {
+        let moved = self.ply < self.len();
+        self.jump(self.ply + 1);
+        moved
+    }
+
+    /// Show the previous move, returns false at the start of the game.
+    pub fn back(&mut self) -> bool {
+        let moved = self.ply > 0;
+        self.jump(self.ply.saturating_sub(1));
+        moved
+    }
+
+    /// Show the board after `ply` moves, or after the last move if the game is shorter.
+    pub fn jump(&mut self, ply: usize) {
+        self.ply = ply.min(self.len());
+    }
+
+    /// Tell what happened in the move that is shown, with the result after the last move.
+    pub fn describe(&self) -> String {
+        let Some(turn) = self.turn() else {
+            return format!("Start of the game, {} moves.", self.len());
+        };
+        let piece = Piece::from_number(turn.piece);
+        let cell = Cell::new(turn.index).map_or(turn.index.to_string(), |c| c.to_string());
+        let mut text = format!(
+            "Move {}/{}: player {} handed over {} ({}), player {} placed it on {}.",
+            self.ply,
+            self.len(),
+            turn.giver + 1,
+            piece.code(),
+            card::physical(piece),
+            turn.placer + 1,
+            cell
+        );
+        if turn.quarto_called {
+            text.push_str(" Quarto!");
+        }
+        if self.ply == self.len() {
+            match self.record.result() {
+                Some(GameResult::Win(player)) => {
+                    text.push_str(&format!(" Player {} wins.", player + 1))
+                }
+                Some(GameResult::Draw) => text.push_str(" It is a draw."),
+                Some(GameResult::Error) => text.push_str(" The game ended with an error."),
+                Some(GameResult::Timeout(player)) => {
+                    text.push_str(&format!(" Player {} ran out of time.", player + 1))
+                }
+                None => {}
+            }
+        }
+        text
+    }
+}
+
+/// A command of the line-based viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayCommand {
+    /// Show the next move (`n` or just enter).
+    Next,
+    /// Show the previous move (`p`).
+    Previous,
+    /// Show the board after this many moves (a number).
+    Jump(usize),
+    /// Show the last move (`e`).
+    End,
+    /// Stop the viewer (`q`).
+    Quit,
+}
+
+impl FromStr for ReplayCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" | "n" => Ok(ReplayCommand::Next),
+            "p" => Ok(ReplayCommand::Previous),
+            "e" => Ok(ReplayCommand::End),
+            "q" => Ok(ReplayCommand::Quit),
+            number => number
+                .parse()
+                .map(ReplayCommand::Jump)
+                .map_err(|_| format!("expected n, p, e, q or a move number, got '{}'", number)),
+        }
+    }
+}
+
+/// Show `replay` on `output` and follow the commands on `input`, until `q` or the end of the input.
+pub fn run_text(
+    replay: &mut Replay,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        writeln!(output, "\n{}\n", PrintableBoard::new(replay.board()))?;
+        writeln!(output, "{}", replay.describe())?;
+        write!(
+            output,
+            "[enter] next, p previous, e end, a number to jump, q quit: "
+        )?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        match line.parse() {
+            Ok(ReplayCommand::Next) => {
+                replay.forward();
+            }
+            Ok(ReplayCommand::Previous) => {
+                replay.back();
+            }
+            Ok(ReplayCommand::Jump(ply)) => replay.jump(ply),
+            Ok(ReplayCommand::End) => replay.jump(replay.len()),
+            Ok(ReplayCommand::Quit) => return Ok(()),
+            Err(e) => writeln!(output, "{}", e)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> GameRecord {
+        let mut record = GameRecord::new();
+        for (ply, (piece, index)) in [(15, 9), (0, 0), (3, 5)].into_iter().enumerate() {
+            record.push(Turn {
+                giver: ply % 2,
+                piece,
+                placer: 1 - ply % 2,
+                index,
+                quarto_called: false,
+            });
+        }
+        record.set_result(GameResult::Draw);
+        record
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut replay = Replay::new(record()).unwrap();
+        assert_eq!(replay.len(), 3);
+        assert_eq!(replay.describe(), "Start of the game, 3 moves.");
+        assert!(!replay.back());
+        assert!(replay.forward());
+        assert_eq!(replay.board().piece_at(9), Some(15));
+        assert_eq!(
+            replay.describe(),
+            "Move 1/3: player 1 handed over TDSH (tall dark square hollow), player 2 placed it on B3."
+        );
+        replay.jump(10);
+        assert_eq!(replay.ply(), 3);
+        assert!(!replay.forward());
+        assert!(replay.describe().ends_with("on B2. It is a draw."));
+        assert!(replay.back());
+        assert_eq!(replay.board().piece_at(5), None);
+        let mut broken = record();
+        broken.push(Turn {
+            giver: 1,
+            piece: 15,
+            placer: 0,
+            index: 1,
+            quarto_called: false,
+        });
+        assert!(Replay::new(broken).is_err());
+    }
+
+    #[test]
+    fn test_text_viewer() {
+        let mut replay = Replay::new(record()).unwrap();
+        let mut output = Vec::new();
+        run_text(&mut replay, "\n\n2\np\nx\nq\nn\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(replay.ply(), 1);
+        assert!(output.contains("Move 2/3"));
+        assert!(output.contains("expected n, p, e, q or a move number, got 'x'"));
+        assert_eq!("12".parse(), Ok(ReplayCommand::Jump(12)));
+    }
+}
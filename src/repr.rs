@@ -0,0 +1,346 @@
+// Storage layouts for the board, behind the `BoardRepr` trait so they can be swapped and compared.
+// `Board` uses `CellRepr` by default and `PlaneRepr` with the `plane-board` feature, its public API stays the same.
+
+use std::fmt::Debug;
+
+use crate::{
+    board::PIECE_SIZE,
+    line::{INDEX_MASKS, Line, cell_bit},
+};
+
+/// The layout `Board` stores its pieces in, picked with the `plane-board` feature.
+#[cfg(not(feature = "plane-board"))]
+pub type DefaultRepr = CellRepr;
+/// The layout `Board` stores its pieces in, picked with the `plane-board` feature.
+#[cfg(feature = "plane-board")]
+pub type DefaultRepr = PlaneRepr;
+
+/// The storage of the pieces on a board.
+/// Indices are below 16 and pieces below 16, `Board` checks that before calling in.
+pub trait BoardRepr: Debug + Copy + Eq + Default {
+    /// Read the cell layout of `Board::from_u128`.
+    fn from_u128(items: u128) -> Self;
+    /// Write the cell layout of `Board::items`.
+    fn to_u128(&self) -> u128;
+    /// The piece on `index`, if there is one.
+    fn piece_at(&self, index: u8) -> Option<u8>;
+    /// Check if `index` holds a piece.
+    fn is_occupied(&self, index: u8) -> bool;
+    /// Check if `piece` is on the board.
+    fn is_used(&self, piece: u8) -> bool;
+    /// Put `piece` on the empty `index`.
+    fn place(&mut self, piece: u8, index: u8);
+    /// Clear `index`.
+    fn remove(&mut self, index: u8);
+    /// Check if every index holds a piece.
+    fn is_full(&self) -> bool;
+    /// The indices that hold a piece, bit `i` for index `i`.
+    fn occupied_mask(&self) -> u16;
+    /// The pieces on the board, bit `p` for piece `p`.
+    fn used_mask(&self) -> u16;
+    /// Check if `row` is full with pieces that share an attribute.
+    fn winning_row(&self, row: u8) -> bool;
+    /// Check if `column` is full with pieces that share an attribute.
+    fn winning_column(&self, column: u8) -> bool;
+    /// Check if a diagonal is full with pieces that share an attribute.
+    fn winning_diagonal(&self) -> bool;
+
+    /// Check if any row, column or diagonal is full with pieces that share an attribute.
+    fn has_line_winner(&self) -> bool {
+        (0..4).any(|i| self.winning_row(i) || self.winning_column(i)) || self.winning_diagonal()
+    }
+}
+
+/// The bits set to check existence on the whole board, the cells of all four rows.
+const BOARD_MASK: u128 = Line::Row(0).cell_mask()
+    | Line::Row(1).cell_mask()
+    | Line::Row(2).cell_mask()
+    | Line::Row(3).cell_mask();
+/// The last cell of every row, the right-most column, where `fold` leaves a row.
+const ROW_ENDS: u128 = Line::Column(3).cell_mask() * 0xff;
+/// The bottom row, where `fold` leaves a column.
+const COLUMN_ENDS: u128 = Line::Row(3).cell_mask() * 0xff;
+/// The cell of index 15, where `fold` leaves the down diagonal.
+const DOWN_END: u128 = cell_bit(15) * 0xff;
+/// The cell of index 12, where `fold` leaves the up diagonal.
+const UP_END: u128 = cell_bit(12) * 0xff;
+
+/// AND every four cells `stride` bits apart into the lowest of them.
+/// The other cells are left with garbage, so mask the result with the ends of the lines.
+fn fold(spread: u128, stride: u8) -> u128 {
+    let and = spread & (spread >> stride);
+    and & (and >> (2 * stride))
+}
+
+/// A `u128` with 8 bits per cell, index 0 in the most significant byte.
+/// The leftmost 4 bits of a cell are the attributes of the piece, the rightmost bit signals the existence of a piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellRepr(u128);
+
+impl CellRepr {
+    /// Spread every piece over its cell: the attributes on the high nibble and their complement on the low one.
+    /// An empty cell is zero, so four cells are full and share an attribute exactly when their AND is not zero.
+    fn spread(&self) -> u128 {
+        let exists = (self.0 & BOARD_MASK) * 0x0f;
+        (self.0 & (exists << 4)) | (!(self.0 >> 4) & exists)
+    }
+}
+
+impl BoardRepr for CellRepr {
+    fn from_u128(items: u128) -> Self {
+        CellRepr(items)
+    }
+
+    fn to_u128(&self) -> u128 {
+        self.0
+    }
+
+    fn piece_at(&self, index: u8) -> Option<u8> {
+        let cell = (self.0 >> (PIECE_SIZE * (15 - index))) & 0xff;
+        if cell & 1 == 0 {
+            return None;
+        }
+        Some((cell >> 4) as u8)
+    }
+
+    fn is_occupied(&self, index: u8) -> bool {
+        self.0 & (1 << (PIECE_SIZE * (15 - index))) != 0
+    }
+
+    /// Loop over the cells, and compare the attributes of every piece with `piece`.
+    fn is_used(&self, piece: u8) -> bool {
+        (0..16).any(|p| {
+            let piece_mask = (piece as u128) << (PIECE_SIZE * p + 4);
+            self.0 & (1 << (PIECE_SIZE * p)) != 0
+                && (self.0 & (0b1111 << (PIECE_SIZE * p + 4))) ^ piece_mask == 0
+        })
+    }
+
+    /// Shift left the existence bit, then shift left the piece type (extra offset of 4 from the existence bit).
+    fn place(&mut self, piece: u8, index: u8) {
+        let bit_index = 15 - index;
+        self.0 +=
+            (1 << (PIECE_SIZE * bit_index)) + ((piece as u128) << ((PIECE_SIZE * bit_index) + 4));
+    }
+
+    fn remove(&mut self, index: u8) {
+        self.0 &= !(0xff << (PIECE_SIZE * (15 - index)));
+    }
+
+    fn is_full(&self) -> bool {
+        self.0 & BOARD_MASK == BOARD_MASK
+    }
+
+    /// Gather the existence bits of each half of the board into a byte with one multiplication.
+    fn occupied_mask(&self) -> u16 {
+        let exists = self.0 & BOARD_MASK;
+        let gather = |half: u64| (half.wrapping_mul(0x8040_2010_0804_0201) >> 56) as u16;
+        gather((exists >> 64) as u64) | gather(exists as u64) << 8
+    }
+
+    /// Set the bit of the attribute nibble of every occupied cell.
+    fn used_mask(&self) -> u16 {
+        let mut occupied = self.occupied_mask();
+        let mut mask = 0;
+        while occupied != 0 {
+            let index = occupied.trailing_zeros() as u8;
+            mask |= 1 << ((self.0 >> (PIECE_SIZE * (15 - index) + 4)) & 0xf);
+            occupied &= occupied - 1;
+        }
+        mask
+    }
+
+    fn winning_row(&self, row: u8) -> bool {
+        fold(self.spread(), PIECE_SIZE) & (cell_bit(4 * row + 3) * 0xff) != 0
+    }
+
+    fn winning_column(&self, column: u8) -> bool {
+        fold(self.spread(), 4 * PIECE_SIZE) & (cell_bit(12 + column) * 0xff) != 0
+    }
+
+    fn winning_diagonal(&self) -> bool {
+        let spread = self.spread();
+        (fold(spread, 5 * PIECE_SIZE) & DOWN_END) | (fold(spread, 3 * PIECE_SIZE) & UP_END) != 0
+    }
+
+    /// Fold the rows, the columns and the diagonals at once, without a branch per line or attribute.
+    #[inline]
+    fn has_line_winner(&self) -> bool {
+        let spread = self.spread();
+        (fold(spread, PIECE_SIZE) & ROW_ENDS)
+            | (fold(spread, 4 * PIECE_SIZE) & COLUMN_ENDS)
+            | (fold(spread, 5 * PIECE_SIZE) & DOWN_END)
+            | (fold(spread, 3 * PIECE_SIZE) & UP_END)
+            != 0
+    }
+}
+
+/// One 16-bit plane per attribute, with bit `i` set if the piece on index `i` has that attribute.
+/// A line wins when it is full and a plane has all or none of its bits set, which takes a few masks per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PlaneRepr {
+    /// The indices that hold a piece.
+    occupied: u16,
+    /// The indices whose piece has attribute bit `b`, in `planes[b]`.
+    planes: [u16; 4],
+    /// The pieces on the board, bit `p` for piece `p`.
+    used: u16,
+}
+
+impl PlaneRepr {
+    /// Check if the indices in `mask` are full with pieces that share an attribute.
+    fn line_wins(&self, mask: u16) -> bool {
+        self.occupied & mask == mask
+            && self
+                .planes
+                .iter()
+                .any(|plane| plane & mask == mask || plane & mask == 0)
+    }
+
+    /// Check if `line` is on the board and wins.
+    fn winning_line(&self, line: Line) -> bool {
+        line.position()
+            .is_some_and(|position| self.line_wins(INDEX_MASKS[position]))
+    }
+}
+
+impl BoardRepr for PlaneRepr {
+    /// Pieces that occur twice in `items` are kept on the board, but only counted once as used.
+    fn from_u128(items: u128) -> Self {
+        let mut repr = PlaneRepr::default();
+        for index in 0..16 {
+            if let Some(piece) = CellRepr(items).piece_at(index) {
+                repr.place(piece, index);
+            }
+        }
+        repr
+    }
+
+    fn to_u128(&self) -> u128 {
+        let mut cells = CellRepr::default();
+        for index in 0..16 {
+            if let Some(piece) = self.piece_at(index) {
+                cells.place(piece, index);
+            }
+        }
+        cells.0
+    }
+
+    fn piece_at(&self, index: u8) -> Option<u8> {
+        if !self.is_occupied(index) {
+            return None;
+        }
+        Some(
+            self.planes
+                .iter()
+                .enumerate()
+                .map(|(bit, plane)| (((plane >> index) & 1) as u8) << bit)
+                .sum(),
+        )
+    }
+
+    fn is_occupied(&self, index: u8) -> bool {
+        self.occupied & (1 << index) != 0
+    }
+
+    fn is_used(&self, piece: u8) -> bool {
+        self.used & (1 << piece) != 0
+    }
+
+    fn place(&mut self, piece: u8, index: u8) {
+        self.occupied |= 1 << index;
+        for (bit, plane) in self.planes.iter_mut().enumerate() {
+            *plane |= (((piece >> bit) & 1) as u16) << index;
+        }
+        self.used |= 1 << piece;
+    }
+
+    fn remove(&mut self, index: u8) {
+        if let Some(piece) = self.piece_at(index) {
+            self.used &= !(1 << piece);
+        }
+        self.occupied &= !(1 << index);
+        for plane in self.planes.iter_mut() {
+            *plane &= !(1 << index);
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.occupied == u16::MAX
+    }
+
+    fn occupied_mask(&self) -> u16 {
+        self.occupied
+    }
+
+    fn used_mask(&self) -> u16 {
+        self.used
+    }
+
+    fn winning_row(&self, row: u8) -> bool {
+        self.winning_line(Line::Row(row))
+    }
+
+    fn winning_column(&self, column: u8) -> bool {
+        self.winning_line(Line::Column(column))
+    }
+
+    fn winning_diagonal(&self) -> bool {
+        self.winning_line(Line::DiagonalDown) || self.winning_line(Line::DiagonalUp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play random games in both layouts, and check that they agree after every placement.
+    #[test]
+    fn test_layouts_agree() {
+        let mut rng = fastrand::Rng::with_seed(2778);
+        for _ in 0..200 {
+            let (mut cells, mut planes) = (CellRepr::default(), PlaneRepr::default());
+            let mut pieces: Vec<u8> = (0..16).collect();
+            let mut indices: Vec<u8> = (0..16).collect();
+            rng.shuffle(&mut pieces);
+            rng.shuffle(&mut indices);
+            for (&piece, &index) in pieces.iter().zip(&indices) {
+                cells.place(piece, index);
+                planes.place(piece, index);
+                assert_eq!(PlaneRepr::from_u128(cells.to_u128()), planes);
+                assert_eq!(planes.to_u128(), cells.to_u128());
+                assert_eq!(planes.is_used(piece), cells.is_used(piece));
+                assert_eq!(planes.is_full(), cells.is_full());
+                assert_eq!(planes.occupied_mask(), cells.occupied_mask());
+                assert_eq!(planes.used_mask(), cells.used_mask());
+                for line in 0..4 {
+                    assert_eq!(planes.winning_row(line), cells.winning_row(line));
+                    assert_eq!(planes.winning_column(line), cells.winning_column(line));
+                }
+                assert_eq!(planes.winning_diagonal(), cells.winning_diagonal());
+                assert_eq!(planes.has_line_winner(), cells.has_line_winner());
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_masks_ignore_stray_bits() {
+        let mut cells = CellRepr::default();
+        cells.place(0, 0);
+        cells.place(0b1111, 15);
+        cells.place(0b0110, 8);
+        let cells = CellRepr::from_u128(cells.to_u128() | (0x0e << (PIECE_SIZE * 4)));
+        assert_eq!(cells.occupied_mask(), 0b1000_0001_0000_0001);
+        assert_eq!(cells.used_mask(), 0b1000_0000_0100_0001);
+    }
+
+    #[test]
+    fn test_plane_remove() {
+        let mut planes = PlaneRepr::default();
+        planes.place(0b1010, 6);
+        assert_eq!(planes.piece_at(6), Some(0b1010));
+        assert!(planes.is_used(0b1010));
+        planes.remove(6);
+        assert_eq!(planes, PlaneRepr::default());
+    }
+}
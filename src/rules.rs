@@ -0,0 +1,259 @@
+// The rules that decide when a board has a winner, behind the `WinRule` trait.
+// A variant with other winning groups of cells implements the trait instead of changing `Board`.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::{board::Board, line::Line};
+
+/// Decides which groups of four cells win when their pieces share an attribute.
+pub trait WinRule: fmt::Debug + Send + Sync {
+    /// The name of the rule, like `classic`.
+    fn name(&self) -> &str;
+
+    /// The groups of cells that win, which strategies look at to find wins and threats.
+    fn lines(&self) -> &[Line];
+
+    /// Check if `board` has a winner: a full group of `lines` whose pieces share an attribute.
+    fn has_winner(&self, board: &Board) -> bool {
+        self.lines()
+            .iter()
+            .any(|&line| board.line_state(line).winning().is_some())
+    }
+
+    /// Check if a full group of `lines` through `index` has pieces that share an attribute.
+    fn has_winner_at(&self, board: &Board, index: u8) -> bool {
+        self.lines().iter().any(|&line| {
+            line.indices().contains(&index) && board.line_state(line).winning().is_some()
+        })
+    }
+}
+
+/// The rows, columns and diagonals win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Classic;
+
+impl WinRule for Classic {
+    fn name(&self) -> &str {
+        "classic"
+    }
+
+    fn lines(&self) -> &[Line] {
+        &Line::ALL
+    }
+
+    fn has_winner(&self, board: &Board) -> bool {
+        board.has_line_winner()
+    }
+
+    fn has_winner_at(&self, board: &Board, index: u8) -> bool {
+        board.has_line_winner_at(index)
+    }
+}
+
+/// The official advanced rules: the rows, columns and diagonals, and the nine 2×2 squares win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Advanced;
+
+impl WinRule for Advanced {
+    fn name(&self) -> &str {
+        "advanced"
+    }
+
+    fn lines(&self) -> &[Line] {
+        &Line::ADVANCED
+    }
+
+    fn has_winner(&self, board: &Board) -> bool {
+        board.has_line_winner() || (0..3).any(|y| (0..3).any(|x| board.winning_square(x, y)))
+    }
+
+    /// The squares through `index` have their top left corner on its cell or one column or row before it.
+    fn has_winner_at(&self, board: &Board, index: u8) -> bool {
+        let (x, y) = (index % 4, index / 4);
+        board.has_line_winner_at(index)
+            || (x.saturating_sub(1)..=x.min(2))
+                .any(|x| (y.saturating_sub(1)..=y.min(2)).any(|y| board.winning_square(x, y)))
+    }
+}
+
+/// A rule of any groups of four cells, for variants like only the squares, or the corners.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellRule {
+    name: String,
+    lines: Vec<Line>,
+}
+
+impl CellRule {
+    /// Win with the groups of four indices in `quadruples`.
+    pub fn new(name: &str, quadruples: &[[u8; 4]]) -> Self {
+        CellRule {
+            name: name.to_string(),
+            lines: quadruples.iter().map(|&cells| Line::Cells(cells)).collect(),
+        }
+    }
+
+    /// Win with `lines`, for example the squares without the rows and columns.
+    pub fn from_lines(name: &str, lines: &[Line]) -> Self {
+        CellRule {
+            name: name.to_string(),
+            lines: lines.to_vec(),
+        }
+    }
+}
+
+impl WinRule for CellRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+}
+
+/// The rules a board plays by: one of the built-in rules or a custom `WinRule`.
+/// Boards are copied a lot, so a custom rule lives for the whole program, like in a `static` or a leaked `Box`.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Ruleset {
+    /// Only the rows, columns and diagonals win.
+    #[default]
+    Classic,
+    /// The official advanced rules, where four pieces in any 2×2 square that share an attribute win as well.
+    Advanced,
+    /// The groups of cells of a custom rule win.
+    Custom(&'static dyn WinRule),
+}
+
+impl Ruleset {
+    /// Check if these are the classic rules.
+    pub fn is_classic(&self) -> bool {
+        matches!(self, Ruleset::Classic)
+    }
+
+    /// The rule that decides the winner.
+    pub fn rule(self) -> &'static dyn WinRule {
+        match self {
+            Ruleset::Classic => &Classic,
+            Ruleset::Advanced => &Advanced,
+            Ruleset::Custom(rule) => rule,
+        }
+    }
+
+    /// The lines that win with these rules.
+    pub fn lines(self) -> &'static [Line] {
+        self.rule().lines()
+    }
+}
+
+/// Custom rules are the same when they have the same name and lines.
+impl PartialEq for Ruleset {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Ruleset::Classic, Ruleset::Classic) | (Ruleset::Advanced, Ruleset::Advanced) => true,
+            (Ruleset::Custom(a), Ruleset::Custom(b)) => {
+                a.name() == b.name() && a.lines() == b.lines()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Ruleset {}
+
+impl Hash for Ruleset {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let Ruleset::Custom(rule) = self {
+            rule.name().hash(state);
+            rule.lines().hash(state);
+        }
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rule().name())
+    }
+}
+
+/// Only the built-in rules can be read, a custom rule has to be given by the program that defines it.
+impl FromStr for Ruleset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Ruleset::Classic),
+            "advanced" => Ok(Ruleset::Advanced),
+            _ => Err(format!("'{}' is not a ruleset, use classic or advanced", s)),
+        }
+    }
+}
+
+/// Serialized as its name, like `Display`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Ruleset {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserialized from the name of a built-in rule.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Ruleset {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_rule() {
+        // Only the four corners win.
+        static CORNERS: std::sync::OnceLock<CellRule> = std::sync::OnceLock::new();
+        let corners = CORNERS.get_or_init(|| CellRule::new("corners", &[[0, 3, 12, 15]]));
+        let ruleset = Ruleset::Custom(corners);
+        assert_eq!(ruleset.to_string(), "corners");
+        assert_eq!(ruleset, Ruleset::Custom(corners));
+        assert_ne!(ruleset, Ruleset::Classic);
+        let board = Board::new().with_ruleset(ruleset);
+        let row = board
+            .apply_moves(&[(8, 0), (9, 1), (10, 2), (11, 3)])
+            .unwrap();
+        assert!(!row.has_winner());
+        let corners = board.apply_moves(&[(8, 0), (9, 3), (10, 12)]).unwrap();
+        assert_eq!(corners.winning_placements(11), vec![15]);
+        assert!(corners.apply_moves(&[(11, 15)]).unwrap().has_winner());
+        assert_eq!(corners.lines().count(), 1);
+        // The key is stable between builds, so stored hashes of custom boards stay valid.
+        assert_eq!(board.ruleset_key(), 0x7d25_1152_12e6_7cad);
+    }
+
+    /// The fast checks of the built-in rules give the same winner as checking their lines one by one.
+    #[test]
+    fn test_built_in_rules_agree_with_their_lines() {
+        let mut rng = fastrand::Rng::with_seed(2797);
+        for ruleset in [Ruleset::Classic, Ruleset::Advanced] {
+            let same = CellRule::from_lines("same", ruleset.lines());
+            for _ in 0..500 {
+                let mut board = Board::new().with_ruleset(ruleset);
+                let (mut pieces, mut cells): (Vec<u8>, Vec<u8>) =
+                    ((0..16).collect(), (0..16).collect());
+                rng.shuffle(&mut pieces);
+                rng.shuffle(&mut cells);
+                for (piece, index) in pieces.into_iter().zip(cells).take(rng.usize(4..=16)) {
+                    board.put_piece(piece, index).unwrap();
+                }
+                assert_eq!(ruleset.rule().has_winner(&board), same.has_winner(&board));
+            }
+        }
+    }
+}
@@ -0,0 +1,550 @@
+// Negamax search with alpha-beta pruning over the two decisions of a Quarto turn.
+// A turn consists of placing the piece that was handed over, followed by choosing the piece for the opponent.
+
+use std::{fmt, sync::mpsc::Sender};
+
+use crate::{
+    board::{Board, PlacementOutcome},
+    cancel::CancelToken,
+    evaluator::{self, Evaluator},
+    transposition::{self, Bound, TranspositionTable},
+};
+
+/// The score of a position that is won for the player to move.
+/// Wins found with more remaining depth (so sooner) score higher.
+pub const WIN: i32 = 1_000;
+/// A bound that is larger than any score the search can return.
+pub(crate) const INFINITY: i32 = i32::MAX - 1;
+
+/// A snapshot of a running search, reported after every completed depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchProgress {
+    /// The depth that was just completed.
+    pub depth: u8,
+    /// The best decision found so far: an index for a placement, a piece for handing over.
+    pub best: u8,
+    /// The score of the best decision.
+    pub score: i32,
+    /// The number of nodes visited since the search started.
+    pub nodes: u64,
+}
+
+impl fmt::Display for SearchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "depth {} best {} score {} nodes {}",
+            self.depth, self.best, self.score, self.nodes
+        )
+    }
+}
+
+/// A placement with the piece to hand over after it, as ranked by `Searcher::rank_moves`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RankedMove {
+    pub index: u8,
+    /// The best piece to hand over afterwards, `None` if the placement ends the game.
+    pub give: Option<u8>,
+    /// The score of the placement for the player that places the piece.
+    pub score: i32,
+}
+
+/// Anything that wants to follow a search while it runs, like a thinking indicator or an analysis panel.
+pub trait ProgressReporter: Send + Sync {
+    /// Called every time the search completes a depth.
+    fn on_iteration(&self, progress: SearchProgress);
+}
+
+/// Send the progress over a channel, so it can be consumed on another thread.
+impl ProgressReporter for Sender<SearchProgress> {
+    fn on_iteration(&self, progress: SearchProgress) {
+        // The receiving side may have stopped listening, the search does not care.
+        let _ = self.send(progress);
+    }
+}
+
+/// The state of a single search: the number of visited nodes, who to report progress to, the table of known positions,
+/// and the token to stop early.
+#[derive(Default)]
+pub struct Searcher<'a> {
+    nodes: u64,
+    reporter: Option<&'a dyn ProgressReporter>,
+    table: Option<&'a mut TranspositionTable>,
+    cancel: Option<&'a CancelToken>,
+    evaluator: Option<&'a dyn Evaluator>,
+}
+
+impl<'a> Searcher<'a> {
+    /// Create a new `Searcher` that does not report its progress.
+    pub fn new() -> Self {
+        Searcher {
+            nodes: 0,
+            reporter: None,
+            table: None,
+            cancel: None,
+            evaluator: None,
+        }
+    }
+
+    /// Stop searching as soon as `cancel` is cancelled.
+    /// Results of a cancelled search are meaningless, except for the depths that were completed before.
+    pub fn with_cancel(mut self, cancel: &'a CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Check if the search was asked to stop.
+    pub fn cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.is_cancelled())
+    }
+
+    /// Remember and reuse the scores of positions in `table`.
+    pub fn with_table(mut self, table: &'a mut TranspositionTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Score the positions where the search stops with `evaluator`, instead of as even.
+    /// A table should only be shared between searches with the same evaluator.
+    pub fn with_evaluator(mut self, evaluator: &'a dyn Evaluator) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+
+    /// Report the progress of iterative deepening to `reporter`.
+    pub fn with_reporter(mut self, reporter: &'a dyn ProgressReporter) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// The number of nodes visited so far.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Score the decision of placing `piece` on `board`, from the view of the player that places it.
+    /// `depth` is the number of full turns (placement and handing over a piece) to look ahead after this placement.
+    /// An immediate win is always found, even at depth 0.
+    pub fn place_value(
+        &mut self,
+        board: &Board,
+        piece: u8,
+        depth: u8,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> i32 {
+        // Depth 0 only looks for immediate wins, which is quick enough to finish even when cancelled.
+        if depth > 0 && self.cancelled() {
+            return 0;
+        }
+        let key = transposition::key(board, piece);
+        if depth > 0
+            && let Some(entry) = self.table.as_mut().and_then(|t| t.probe(key))
+            && entry.depth >= depth
+        {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => alpha = alpha.max(entry.score),
+                Bound::Upper => beta = beta.min(entry.score),
+            }
+            if alpha >= beta {
+                return entry.score;
+            }
+        }
+        let original_alpha = alpha;
+        let spaces = board.empty_cells();
+        // Look for a win first, it can never be improved upon.
+        for index in spaces {
+            let mut next = *board;
+            self.nodes += 1;
+            if next.put_piece_checked(piece, index) == Ok(PlacementOutcome::Win) {
+                return WIN + depth as i32;
+            }
+        }
+        if depth == 0 {
+            return self
+                .evaluator
+                .map_or(0, |e| evaluator::bounded(e.evaluate(board, piece)));
+        }
+        let mut best = -INFINITY;
+        for index in spaces {
+            let mut next = *board;
+            if next.put_piece(piece, index).is_err() {
+                continue;
+            }
+            let value = if next.board_full() {
+                0
+            } else {
+                self.give_value(&next, depth - 1, alpha, beta)
+            };
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        if self.cancelled() {
+            return best;
+        }
+        if let Some(table) = self.table.as_mut() {
+            let bound = if best <= original_alpha {
+                Bound::Upper
+            } else if best >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.store(key, depth, best, bound);
+        }
+        best
+    }
+
+    /// Score the decision of handing over a piece on `board`, from the view of the player that hands it over.
+    pub fn give_value(&mut self, board: &Board, depth: u8, mut alpha: i32, beta: i32) -> i32 {
+        let mut best = -INFINITY;
+        for piece in board.available_pieces() {
+            let value = -self.place_value(board, piece, depth, -beta, -alpha);
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Score a placement where the search stops, for the player that placed the piece and hands over the next one.
+    fn give_leaf(&self, board: &Board) -> i32 {
+        self.evaluator
+            .map_or(0, |e| evaluator::bounded(e.evaluate_give(board)))
+    }
+
+    /// Find the best index to place `piece` on, together with its score.
+    /// Returns `None` if the board has no empty spaces.
+    pub fn best_move(&mut self, board: &Board, piece: u8, depth: u8) -> Option<(u8, i32)> {
+        let mut best: Option<(u8, i32)> = None;
+        for index in board.empty_spaces() {
+            let mut next = *board;
+            self.nodes += 1;
+            let value = match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Win) => WIN + depth as i32 + 1,
+                Ok(PlacementOutcome::Draw) => 0,
+                Ok(PlacementOutcome::Ongoing) if depth == 0 => self.give_leaf(&next),
+                Ok(PlacementOutcome::Ongoing) => {
+                    let alpha = best.map_or(-INFINITY, |(_, v)| v);
+                    self.give_value(&next, depth - 1, alpha, INFINITY)
+                }
+                Err(_) => continue,
+            };
+            if best.is_none_or(|(_, v)| value > v) {
+                best = Some((index, value));
+            }
+        }
+        best
+    }
+
+    /// Find the best piece to hand over on `board`, together with its score for the player handing it over.
+    /// Returns `None` if there are no pieces left.
+    pub fn best_piece(&mut self, board: &Board, depth: u8) -> Option<(u8, i32)> {
+        let mut best: Option<(u8, i32)> = None;
+        for piece in board.valid_pieces() {
+            let alpha = best.map_or(-INFINITY, |(_, v)| v);
+            let value = -self.place_value(board, piece, depth, -INFINITY, -alpha);
+            if best.is_none_or(|(_, v)| value > v) {
+                best = Some((piece, value));
+            }
+        }
+        best
+    }
+
+    /// Score every index to place `piece` on, in order of the index.
+    /// Unlike `best_move`, every score is exact, so worse placements can be compared with each other.
+    pub fn move_scores(&mut self, board: &Board, piece: u8, depth: u8) -> Vec<(u8, i32)> {
+        let mut scores = Vec::new();
+        for index in board.empty_spaces() {
+            let mut next = *board;
+            self.nodes += 1;
+            let value = match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Win) => WIN + depth as i32 + 1,
+                Ok(PlacementOutcome::Draw) => 0,
+                Ok(PlacementOutcome::Ongoing) if depth == 0 => self.give_leaf(&next),
+                Ok(PlacementOutcome::Ongoing) => {
+                    self.give_value(&next, depth - 1, -INFINITY, INFINITY)
+                }
+                Err(_) => continue,
+            };
+            scores.push((index, value));
+        }
+        scores
+    }
+
+    /// Score every piece to hand over on `board` for the player handing it over, in order of the piece.
+    /// Unlike `best_piece`, every score is exact.
+    pub fn piece_scores(&mut self, board: &Board, depth: u8) -> Vec<(u8, i32)> {
+        board
+            .valid_pieces()
+            .into_iter()
+            .map(|piece| {
+                (
+                    piece,
+                    -self.place_value(board, piece, depth, -INFINITY, INFINITY),
+                )
+            })
+            .collect()
+    }
+
+    /// Score every placement of `piece` together with the best piece to hand over after it, best first.
+    /// Placements with the same score keep the order of the index.
+    /// At depth 0 the placements are not searched further, the piece is the first one that does not lose at once.
+    pub fn rank_moves(&mut self, board: &Board, piece: u8, depth: u8) -> Vec<RankedMove> {
+        let mut ranked = Vec::new();
+        for index in board.empty_spaces() {
+            let mut next = *board;
+            self.nodes += 1;
+            let (give, score) = match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Win) => (None, WIN + depth as i32 + 1),
+                Ok(PlacementOutcome::Draw) => (None, 0),
+                Ok(PlacementOutcome::Ongoing) if depth == 0 => {
+                    (next.safe_pieces().first().copied(), self.give_leaf(&next))
+                }
+                Ok(PlacementOutcome::Ongoing) => {
+                    let best = self
+                        .rank_pieces(&next, depth - 1)
+                        .first()
+                        .copied()
+                        .expect("a board that is not full has a piece left");
+                    (Some(best.0), best.1)
+                }
+                Err(_) => continue,
+            };
+            ranked.push(RankedMove { index, give, score });
+        }
+        ranked.sort_by_key(|ranked| -ranked.score);
+        ranked
+    }
+
+    /// Score every piece to hand over on `board` like `piece_scores`, best first.
+    pub fn rank_pieces(&mut self, board: &Board, depth: u8) -> Vec<(u8, i32)> {
+        let mut ranked = self.piece_scores(board, depth);
+        ranked.sort_by_key(|&(_, score)| -score);
+        ranked
+    }
+
+    /// Run `search` for every depth up to and including `depth`, reporting after each one.
+    /// Stops early when a forced win is found.
+    /// Depth 0 is always completed, so when cancelled the result of the last completed depth is returned.
+    fn deepen<F>(&mut self, depth: u8, mut search: F) -> Option<(u8, i32)>
+    where
+        F: FnMut(&mut Self, u8) -> Option<(u8, i32)>,
+    {
+        let mut best = None;
+        for d in 0..=depth {
+            let result = search(self, d);
+            if d > 0 && self.cancelled() {
+                return best;
+            }
+            best = result;
+            let (decision, score) = best?;
+            if let Some(reporter) = self.reporter {
+                reporter.on_iteration(SearchProgress {
+                    depth: d,
+                    best: decision,
+                    score,
+                    nodes: self.nodes,
+                });
+            }
+            if score >= WIN {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Find the best index to place `piece` on with iterative deepening.
+    pub fn deepen_move(&mut self, board: &Board, piece: u8, depth: u8) -> Option<(u8, i32)> {
+        self.deepen(depth, |s, d| s.best_move(board, piece, d))
+    }
+
+    /// Find the best piece to hand over with iterative deepening.
+    pub fn deepen_piece(&mut self, board: &Board, depth: u8) -> Option<(u8, i32)> {
+        self.deepen(depth, |s, d| s.best_piece(board, d))
+    }
+}
+
+/// Find the best index to place `piece` on, together with its score.
+/// Returns `None` if the board has no empty spaces.
+pub fn best_move(board: &Board, piece: u8, depth: u8) -> Option<(u8, i32)> {
+    Searcher::new().best_move(board, piece, depth)
+}
+
+/// Find the best piece to hand over on `board`, together with its score for the player handing it over.
+/// Returns `None` if there are no pieces left.
+pub fn best_piece(board: &Board, depth: u8) -> Option<(u8, i32)> {
+    Searcher::new().best_piece(board, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::test_util::open_row;
+
+    #[test]
+    fn test_best_move_takes_win() {
+        assert_eq!(best_move(&open_row(), 0b1001, 1).map(|(m, _)| m), Some(15));
+    }
+
+    #[test]
+    fn test_best_piece_is_safe() {
+        let (piece, value) = best_piece(&open_row(), 1).unwrap();
+        // Only dark pieces without a hole are safe.
+        assert_eq!(piece & 0b1001, 0b0001);
+        assert!(value > -WIN);
+    }
+
+    #[test]
+    fn test_scores_agree_with_best() {
+        let board = open_row();
+        let mut searcher = Searcher::new();
+        let moves = searcher.move_scores(&board, 0b1001, 1);
+        assert_eq!(moves.len(), board.empty_spaces().len());
+        let best = moves.iter().max_by_key(|&&(_, v)| v).map(|&(_, v)| v);
+        assert_eq!(best, best_move(&board, 0b1001, 1).map(|(_, v)| v));
+        let pieces = searcher.piece_scores(&board, 1);
+        assert_eq!(pieces.len(), board.valid_pieces().len());
+        let best = pieces.iter().max_by_key(|&&(_, v)| v).map(|&(_, v)| v);
+        assert_eq!(best, best_piece(&board, 1).map(|(_, v)| v));
+    }
+
+    #[test]
+    fn test_rank_moves() {
+        let board = open_row();
+        let mut searcher = Searcher::new();
+        let ranked = searcher.rank_moves(&board, 0b1001, 1);
+        assert_eq!(
+            ranked[0],
+            RankedMove {
+                index: 15,
+                give: None,
+                score: WIN + 2
+            }
+        );
+        let mut scores = searcher.move_scores(&board, 0b1001, 1);
+        scores.sort_by_key(|&(_, score)| -score);
+        let ranked: Vec<(u8, i32)> = ranked.iter().map(|r| (r.index, r.score)).collect();
+        assert_eq!(ranked, scores);
+        // A piece that cannot win is placed, and the piece handed over after it does not lose at once.
+        let ranked = searcher.rank_moves(&board, 0b0001, 1);
+        assert!(ranked.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        let mut next = board;
+        next.put_piece(0b0001, ranked[0].index).unwrap();
+        assert!(next.winning_placements(ranked[0].give.unwrap()).is_empty());
+        let pieces = searcher.rank_pieces(&board, 1);
+        assert_eq!(pieces[0].1, best_piece(&board, 1).unwrap().1);
+    }
+
+    #[test]
+    fn test_place_value_immediate_win_at_depth_zero() {
+        let value = Searcher::new().place_value(&open_row(), 0b1001, 0, -INFINITY, INFINITY);
+        assert!(value >= WIN);
+    }
+
+    #[test]
+    fn test_give_value_no_safe_piece_loses() {
+        // Fill the board so that every remaining piece completes the open row.
+        let mut board = open_row();
+        for (piece, index) in [(0b0001, 0), (0b0011, 1), (0b0101, 4), (0b0111, 6)] {
+            assert!(board.put_piece(piece, index).is_ok());
+        }
+        assert!(Searcher::new().give_value(&board, 1, -INFINITY, INFINITY) <= -WIN);
+    }
+
+    #[test]
+    fn test_best_move_full_board() {
+        let mut board = Board::new();
+        for i in 0..16 {
+            board.put_piece(i, i).unwrap();
+        }
+        assert_eq!(best_move(&board, 0, 2), None);
+        assert_eq!(best_piece(&board, 2), None);
+    }
+
+    #[test]
+    fn test_table_does_not_change_result() {
+        let mut board = Board::new();
+        for (piece, index) in [(0, 0), (5, 5), (10, 10), (3, 12), (9, 3), (6, 9)] {
+            board.put_piece(piece, index).unwrap();
+        }
+        let mut table = TranspositionTable::new(1 << 12, transposition::Replacement::Always);
+        let mut with_table = Searcher::new().with_table(&mut table);
+        let first = with_table.best_piece(&board, 2).map(|(_, v)| v);
+        let second = with_table.best_piece(&board, 2).map(|(_, v)| v);
+        let nodes_with_table = with_table.nodes();
+        let mut plain = Searcher::new();
+        let expected = plain.best_piece(&board, 2).map(|(_, v)| v);
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+        assert!(nodes_with_table < 2 * plain.nodes());
+        assert!(!table.is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_search_returns_quickly() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut searcher = Searcher::new().with_cancel(&cancel);
+        let best = searcher.deepen_piece(&Board::new(), 5);
+        assert!(best.is_some());
+        assert!(searcher.nodes() < 1_000);
+        let (piece, _) = searcher.deepen_piece(&open_row(), 5).unwrap();
+        assert_eq!(piece & 0b1001, 0b0001);
+        let (index, _) = searcher.deepen_move(&open_row(), 0b1001, 5).unwrap();
+        assert_eq!(index, 15);
+    }
+
+    #[test]
+    fn test_cancel_from_other_thread() {
+        let cancel = CancelToken::new();
+        let remote = cancel.clone();
+        let start = std::time::Instant::now();
+        let best = std::thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                Searcher::new()
+                    .with_cancel(&cancel)
+                    .deepen_piece(&Board::new(), 6)
+            });
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            remote.cancel();
+            handle.join().unwrap()
+        });
+        assert!(best.is_some());
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_deepen_reports_every_depth() {
+        let (tx, rx) = mpsc::channel();
+        let mut searcher = Searcher::new().with_reporter(&tx);
+        let best = searcher.deepen_piece(&open_row(), 1);
+        let reports: Vec<SearchProgress> = rx.try_iter().collect();
+        assert_eq!(
+            reports.iter().map(|p| p.depth).collect::<Vec<u8>>(),
+            vec![0, 1]
+        );
+        assert_eq!(best.map(|(p, _)| p), Some(reports[1].best));
+        assert!(reports[0].nodes <= reports[1].nodes);
+        assert_eq!(reports[1].nodes, searcher.nodes());
+    }
+
+    #[test]
+    fn test_deepen_stops_at_win() {
+        let (tx, rx) = mpsc::channel();
+        let best = Searcher::new()
+            .with_reporter(&tx)
+            .deepen_move(&open_row(), 0b1001, 3);
+        assert_eq!(best.map(|(m, _)| m), Some(15));
+        assert_eq!(rx.try_iter().count(), 1);
+    }
+}
@@ -0,0 +1,314 @@
+// Seasonal ladders: ratings that start over every season, with the standings of past seasons kept in an archive.
+// A lobby hosts the seasons: it rates the games between its players and answers the `standings` message of a client.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    game::GameResult,
+    persist::{self, DataKind, PersistError},
+    rating::{Rating, Ratings},
+    usage,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// When the first season starts and how long every season lasts, as set in the server config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeasonConfig {
+    /// The start of the first season, in seconds since the Unix epoch.
+    pub start: u64,
+    /// The length of a season in days.
+    pub length_days: u32,
+}
+
+impl Default for SeasonConfig {
+    /// Seasons of thirteen weeks, the first one starting on 2026-01-01.
+    fn default() -> Self {
+        SeasonConfig {
+            start: 1_767_225_600,
+            length_days: 91,
+        }
+    }
+}
+
+impl SeasonConfig {
+    /// Create a config with seasons of `length_days` days from `start`, a season lasts at least a day.
+    pub fn new(start: u64, length_days: u32) -> Self {
+        SeasonConfig {
+            start,
+            length_days: length_days.max(1),
+        }
+    }
+
+    fn length(&self) -> u64 {
+        u64::from(self.length_days.max(1)) * DAY
+    }
+
+    /// The season at `time`, seasons are numbered from 1 and times before the first season fall in it.
+    pub fn season_at(&self, time: u64) -> u32 {
+        (time.saturating_sub(self.start) / self.length()) as u32 + 1
+    }
+
+    /// The start of `season`, in seconds since the Unix epoch.
+    pub fn start_of(&self, season: u32) -> u64 {
+        self.start + u64::from(season.saturating_sub(1)) * self.length()
+    }
+
+    /// The end of `season`, which is the start of the next one.
+    pub fn end_of(&self, season: u32) -> u64 {
+        self.start_of(season) + self.length()
+    }
+}
+
+/// The ratings of the current season and the archived ratings of all past seasons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seasons {
+    config: SeasonConfig,
+    current: u32,
+    ratings: Ratings,
+    archive: BTreeMap<u32, Ratings>,
+}
+
+impl Seasons {
+    /// Start the ladder in the season at `now`.
+    pub fn new(config: SeasonConfig, now: u64) -> Self {
+        Seasons {
+            config,
+            current: config.season_at(now),
+            ratings: Ratings::new(),
+            archive: BTreeMap::new(),
+        }
+    }
+
+    /// The config the seasons roll over by.
+    pub fn config(&self) -> SeasonConfig {
+        self.config
+    }
+
+    /// The number of the current season.
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// The ratings of the current season.
+    pub fn ratings(&self) -> &Ratings {
+        &self.ratings
+    }
+
+    /// The ratings of a past season, or `None` if it is not archived.
+    pub fn archived(&self, season: u32) -> Option<&Ratings> {
+        self.archive.get(&season)
+    }
+
+    /// Archive the current season and reset the ratings if a new season started at `now`.
+    /// Returns `true` if the season rolled over.
+    pub fn rollover(&mut self, now: u64) -> bool {
+        let season = self.config.season_at(now);
+        if season <= self.current {
+            return false;
+        }
+        let finished = std::mem::replace(&mut self.ratings, Ratings::new());
+        if !finished.is_empty() {
+            self.archive.insert(self.current, finished);
+        }
+        self.current = season;
+        true
+    }
+
+    /// Rate a game played at `now`, in the season it was played.
+    pub fn record_result(&mut self, first: &str, second: &str, result: GameResult, now: u64) {
+        self.rollover(now);
+        self.ratings.record_result(first, second, result);
+    }
+
+    /// The standings of `season`, or of the current season with `None`.
+    pub fn standings(&self, season: Option<u32>) -> Option<Standings> {
+        let season = season.unwrap_or(self.current);
+        let ratings = if season == self.current {
+            &self.ratings
+        } else {
+            self.archive.get(&season)?
+        };
+        Some(Standings {
+            season,
+            start: self.config.start_of(season),
+            end: self.config.end_of(season),
+            players: ratings
+                .leaderboard()
+                .into_iter()
+                .map(|(name, rating)| (name.to_string(), rating))
+                .collect(),
+        })
+    }
+
+    /// Write the seasons in the stored format, with a versioned header.
+    /// The config comes first, then every archived season and the current season with their ratings.
+    pub fn to_text(&self) -> String {
+        let mut body = format!("config {} {}\n", self.config.start, self.config.length_days);
+        let seasons = self
+            .archive
+            .iter()
+            .map(|(&season, ratings)| ("season", season, ratings))
+            .chain([("current", self.current, &self.ratings)]);
+        for (marker, season, ratings) in seasons {
+            body.push_str(&format!("{} {}\n", marker, season));
+            for (name, rating) in ratings.leaderboard() {
+                body.push_str(&format!("{} {} {}\n", name, rating.elo, rating.games));
+            }
+        }
+        persist::write(DataKind::Seasons, &body)
+    }
+
+    /// Read seasons written by `to_text`.
+    pub fn from_text(data: &str) -> Result<Self, PersistError> {
+        let body = persist::read(DataKind::Seasons, data)?;
+        let mut config = None;
+        let mut seasons: Vec<(bool, u32, Ratings)> = Vec::new();
+        for (line, text) in (2..).zip(body.lines()) {
+            let malformed = |message: &str| PersistError::Malformed {
+                line,
+                message: message.to_string(),
+            };
+            let number = |text: &str| text.parse().map_err(|_| malformed("expected a number"));
+            match text.split_whitespace().collect::<Vec<&str>>()[..] {
+                [] => {}
+                ["config", start, length_days] => {
+                    config = Some(SeasonConfig::new(
+                        start
+                            .parse()
+                            .map_err(|_| malformed("expected a start time"))?,
+                        number(length_days)?,
+                    ));
+                }
+                [marker @ ("season" | "current"), season] => {
+                    seasons.push((marker == "current", number(season)?, Ratings::new()));
+                }
+                [name, elo, games] => {
+                    let (_, _, ratings) = seasons
+                        .last_mut()
+                        .ok_or_else(|| malformed("expected a season before the ratings"))?;
+                    let rating = Rating {
+                        elo: elo.parse().map_err(|_| malformed("expected a rating"))?,
+                        games: number(games)?,
+                    };
+                    ratings.insert(name, rating);
+                }
+                _ => return Err(malformed("expected a config, a season or a rating")),
+            }
+        }
+        let missing = |message: &str| PersistError::Malformed {
+            line: 2,
+            message: message.to_string(),
+        };
+        let config = config.ok_or_else(|| missing("expected a config"))?;
+        let current = seasons
+            .iter()
+            .position(|(current, _, _)| *current)
+            .ok_or_else(|| missing("expected a current season"))?;
+        let (_, current, ratings) = seasons.remove(current);
+        Ok(Seasons {
+            config,
+            current,
+            ratings,
+            archive: seasons
+                .into_iter()
+                .map(|(_, season, ratings)| (season, ratings))
+                .collect(),
+        })
+    }
+}
+
+/// The players of a season ranked by rating, as the server sends them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standings {
+    pub season: u32,
+    /// The start of the season, in seconds since the Unix epoch.
+    pub start: u64,
+    /// The end of the season, in seconds since the Unix epoch.
+    pub end: u64,
+    /// The players with their rating, highest first.
+    pub players: Vec<(String, Rating)>,
+}
+
+impl fmt::Display for Standings {
+    /// A table of the players by rank, under the dates of the season.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Season {}, {} to {}",
+            self.season,
+            usage::format_date(self.start),
+            usage::format_date(self.end.saturating_sub(1))
+        )?;
+        writeln!(
+            f,
+            "{:>4}  {:<16} {:>8} {:>6}",
+            "Rank", "Player", "Rating", "Games"
+        )?;
+        for (rank, (name, rating)) in self.players.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:<16} {:>8.0} {:>6}",
+                rank + 1,
+                name,
+                rating.elo,
+                rating.games
+            )?;
+        }
+        if self.players.is_empty() {
+            writeln!(f, "No rated games yet.")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_767_225_600;
+
+    fn config() -> SeasonConfig {
+        SeasonConfig::new(START, 30)
+    }
+
+    #[test]
+    fn test_season_dates() {
+        let config = config();
+        assert_eq!(config.season_at(0), 1);
+        assert_eq!(config.season_at(START + 30 * DAY - 1), 1);
+        assert_eq!(config.season_at(START + 30 * DAY), 2);
+        assert_eq!(config.start_of(2), START + 30 * DAY);
+        assert_eq!(config.end_of(1), config.start_of(2));
+    }
+
+    #[test]
+    fn test_rollover_archives_and_resets() {
+        let mut seasons = Seasons::new(config(), START);
+        seasons.record_result("alice", "bob", GameResult::Win(0), START + DAY);
+        assert_eq!(seasons.current(), 1);
+        assert!(!seasons.rollover(START + 2 * DAY));
+        seasons.record_result("bob", "carol", GameResult::Draw, START + 45 * DAY);
+        assert_eq!(seasons.current(), 2);
+        assert_eq!(seasons.archived(1).unwrap().get("alice").elo, 1516.0);
+        assert_eq!(seasons.ratings().get("alice").games, 0);
+        assert_eq!(seasons.ratings().get("bob").games, 1);
+        // A season without games is not archived.
+        seasons.rollover(START + 100 * DAY);
+        assert_eq!(seasons.current(), 4);
+        assert!(seasons.standings(Some(2)).is_some());
+        assert!(seasons.standings(Some(3)).is_none());
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut seasons = Seasons::new(config(), START);
+        seasons.record_result("alice", "bob", GameResult::Win(1), START);
+        seasons.record_result("alice", "bob", GameResult::Draw, START + 31 * DAY);
+        let table = seasons.standings(Some(1)).unwrap().to_string();
+        assert!(table.starts_with("Season 1, 2026-01-01 to 2026-01-30"));
+        assert_eq!(Seasons::from_text(&seasons.to_text()), Ok(seasons));
+        let data = persist::write(DataKind::Seasons, "config 0 30\nseason 1\n");
+        assert!(Seasons::from_text(&data).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+// A REST API over HTTP to play against the engine, with JSON bodies of the serde types of `Session` and `Action`.
+// Only built with the `serve` feature, the games live in `Sessions` and every request is answered on its own thread.
+// With the `tls` feature the API can be served over HTTPS instead: rustls decrypts the connections in front of
+// a plain server on the loopback interface, so the crate links one version of rustls.
+
+use std::{io, sync::Arc, thread};
+
+use tiny_http::{Header, Response, Server};
+
+use crate::{client::Action, session::Sessions};
+
+/// The body of `POST /games`, every field may be left out.
+#[derive(Debug, serde::Deserialize)]
+struct CreateGame {
+    /// The computer player of the registry for the engine requests.
+    #[serde(default = "default_engine")]
+    engine: String,
+}
+
+fn default_engine() -> String {
+    "smart".to_string()
+}
+
+/// The body of an error response, like `{"error":"There is no game 3"}`.
+fn error(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Answer `method` on `path` with the JSON `body`, and return the status code and the JSON body of the response.
+///
+/// - `POST /games` with `{"engine":"smart"}` starts a game, answered with the `Session`.
+/// - `GET /games/<id>` is the `Session` of a game.
+/// - `POST /games/<id>/moves` with an `Action` like `{"give":3}` or `{"place":5}` plays it.
+/// - `GET /games/<id>/bestmove` is the `Action` the engine would take.
+/// - `GET /games/<id>/solve` is the proven outcome of every action, like `[{"action":{"place":5},"outcome":"win"}]`,
+///   near the end of a game.
+/// - `POST /games/<id>/engine` lets the engine take its action.
+pub fn route(sessions: &Sessions, method: &str, path: &str, body: &str) -> (u16, String) {
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let json = |result: Result<String, serde_json::Error>| match result {
+        Ok(json) => (200, json),
+        Err(e) => (500, error(&e.to_string())),
+    };
+    if let ("POST", ["games"]) = (method, &parts[..]) {
+        let create = match body.trim() {
+            "" => Ok(CreateGame {
+                engine: default_engine(),
+            }),
+            body => serde_json::from_str::<CreateGame>(body),
+        };
+        return match create.map_err(|e| e.to_string()) {
+            Ok(create) => match sessions.create(&create.engine) {
+                Ok(session) => match json(serde_json::to_string(&session)) {
+                    (200, body) => (201, body),
+                    failed => failed,
+                },
+                Err(e) => (400, error(&e)),
+            },
+            Err(e) => (400, error(&e)),
+        };
+    }
+    let (id, rest) = match &parts[..] {
+        ["games", id, rest @ ..] => match id.parse::<u64>() {
+            Ok(id) if sessions.get(id).is_some() => (id, rest),
+            _ => return (404, error(&format!("There is no game {}", id))),
+        },
+        _ => return (404, error(&format!("There is nothing at {}", path))),
+    };
+    let answer = match (method, rest) {
+        ("GET", []) => return json(serde_json::to_string(&sessions.get(id))),
+        ("POST", ["moves"]) => match serde_json::from_str::<Action>(body) {
+            Ok(action) => sessions.act(id, action),
+            Err(e) => return (400, error(&e.to_string())),
+        },
+        ("GET", ["bestmove"]) => {
+            return match sessions.best_action(id) {
+                Ok(action) => json(serde_json::to_string(&action)),
+                Err(e) => (409, error(&e)),
+            };
+        }
+        ("GET", ["solve"]) => {
+            return match sessions.solve(id) {
+                Ok(outcomes) => json(serde_json::to_string(
+                    &outcomes
+                        .into_iter()
+                        .map(|(action, outcome)| {
+                            serde_json::json!({ "action": action, "outcome": outcome.to_string() })
+                        })
+                        .collect::<Vec<_>>(),
+                )),
+                Err(e) => (409, error(&e)),
+            };
+        }
+        ("POST", ["engine"]) => sessions.play_engine(id),
+        _ => return (405, error(&format!("{} {} is not allowed", method, path))),
+    };
+    match answer {
+        Ok(session) => json(serde_json::to_string(&session)),
+        Err(e) => (409, error(&e)),
+    }
+}
+
+/// Answer the requests to `address`, like `127.0.0.1:8080`, with the games of `sessions`.
+pub fn serve(address: &str, sessions: Arc<Sessions>) -> io::Result<()> {
+    let server = Server::http(address).map_err(io::Error::other)?;
+    run(&server, sessions);
+    Ok(())
+}
+
+/// Answer the requests to `address` over HTTPS, with the PEM certificate and key at `cert` and `key`.
+#[cfg(feature = "tls")]
+pub fn serve_tls(
+    address: &str,
+    cert: &std::path::Path,
+    key: &std::path::Path,
+    sessions: Arc<Sessions>,
+) -> io::Result<()> {
+    let config = crate::tls::server_config(cert, key)?;
+    https(std::net::TcpListener::bind(address)?, config, sessions)
+}
+
+/// Answer the requests to `listener` over HTTPS with the certificate of `config`.
+/// Every connection is decrypted on its own thread and passed on to a plain server on the loopback interface.
+#[cfg(feature = "tls")]
+fn https(
+    listener: std::net::TcpListener,
+    config: Arc<rustls::ServerConfig>,
+    sessions: Arc<Sessions>,
+) -> io::Result<()> {
+    let server = Server::http("127.0.0.1:0").map_err(io::Error::other)?;
+    let plain = server
+        .server_addr()
+        .to_ip()
+        .expect("the plain server listens on TCP");
+    thread::spawn(move || run(&server, sessions));
+    for stream in listener.incoming() {
+        let config = config.clone();
+        thread::spawn(move || {
+            stream
+                .and_then(|stream| crate::tls::accept(stream, config))
+                .and_then(|client| relay(client, plain))
+                .ok();
+        });
+    }
+    Ok(())
+}
+
+/// How long a side of a relayed connection is read before the other side gets its turn.
+#[cfg(feature = "tls")]
+const POLL: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Copy the bytes of `client` to a new connection to the plain server at `plain` and back, until either side closes.
+#[cfg(feature = "tls")]
+fn relay(mut client: crate::tls::ServerStream, plain: std::net::SocketAddr) -> io::Result<()> {
+    use std::io::{Read, Write};
+
+    let mut server = std::net::TcpStream::connect(plain)?;
+    client.sock.set_read_timeout(Some(POLL))?;
+    server.set_read_timeout(Some(POLL))?;
+    let waiting = |e: &io::Error| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        )
+    };
+    let mut buffer = [0; 8192];
+    loop {
+        match client.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => server.write_all(&buffer[..read])?,
+            Err(e) if waiting(&e) => {}
+            Err(e) => return Err(e),
+        }
+        match server.read(&mut buffer) {
+            Ok(0) => {
+                client.conn.send_close_notify();
+                return client.flush();
+            }
+            Ok(read) => {
+                client.write_all(&buffer[..read])?;
+                client.flush()?;
+            }
+            Err(e) if waiting(&e) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Answer the requests of `server` until it stops.
+fn run(server: &Server, sessions: Arc<Sessions>) {
+    for mut request in server.incoming_requests() {
+        let sessions = sessions.clone();
+        thread::spawn(move || {
+            let mut body = String::new();
+            let (status, json) = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => route(&sessions, request.method().as_str(), request.url(), &body),
+                Err(e) => (400, error(&e.to_string())),
+            };
+            let header = Header::from_bytes("Content-Type", "application/json")
+                .expect("the content type is a valid header");
+            request
+                .respond(
+                    Response::from_string(json)
+                        .with_status_code(status)
+                        .with_header(header),
+                )
+                .ok();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{registry::PlayerOptions, session::Session};
+
+    #[test]
+    fn test_routes() {
+        let sessions = Sessions::new().with_options(PlayerOptions {
+            depth: 1,
+            ..PlayerOptions::default()
+        });
+        let (status, body) = route(&sessions, "POST", "/games", "");
+        assert_eq!(status, 201);
+        let session: Session = serde_json::from_str(&body).unwrap();
+        assert_eq!(session.engine, "smart");
+        let path = format!("/games/{}", session.id);
+        assert_eq!(
+            route(
+                &sessions,
+                "POST",
+                &format!("{}/moves", path),
+                "{\"give\":8}"
+            )
+            .0,
+            200
+        );
+        let (status, body) = route(&sessions, "GET", &format!("{}/bestmove", path), "");
+        assert_eq!(status, 200);
+        assert!(body.starts_with("{\"place\":"));
+        assert_eq!(
+            route(&sessions, "GET", &format!("{}/solve", path), "").0,
+            409
+        );
+        let (status, body) = route(&sessions, "POST", &format!("{}/engine", path), "");
+        assert_eq!(status, 200);
+        let session: Session = serde_json::from_str(&body).unwrap();
+        assert_eq!((session.piece, session.turn), (None, 1));
+        assert_eq!(
+            route(
+                &sessions,
+                "POST",
+                &format!("{}/moves", path),
+                "{\"place\":0}"
+            )
+            .0,
+            409
+        );
+        assert_eq!(route(&sessions, "GET", "/games/99", "").0, 404);
+        assert_eq!(
+            route(&sessions, "POST", "/games", "{\"engine\":\"human\"}").0,
+            400
+        );
+        assert_eq!(route(&sessions, "DELETE", &path, "").0, 405);
+    }
+
+    #[test]
+    fn test_serve_over_http() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || run(&server, Arc::new(Sessions::new())));
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /games HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+        assert!(response.contains("application/json"));
+        assert!(response.contains("\"engine\":\"smart\""));
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_serve_over_https() {
+        use crate::tls::ClientTls;
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert, key) = (certified.cert.pem(), certified.signing_key.serialize_pem());
+        let config = crate::tls::server_config_from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || https(listener, config, Arc::new(Sessions::new())));
+        let tls = ClientTls::new().with_root_pem(cert.as_bytes()).unwrap();
+        let mut stream = tls.connect(&address.to_string(), "localhost").unwrap();
+        write!(
+            stream,
+            "POST /games HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+    }
+}
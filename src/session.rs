@@ -0,0 +1,248 @@
+// Games played by requests instead of by `Player` calls, for the servers that let clients play against the engine.
+// The players submit their actions in turn, and can ask a computer player of the registry to decide for them.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    board::Board,
+    client::Action,
+    game::GameResult,
+    player::Player,
+    registry::{PlayerOptions, Registry},
+    solver::{Outcome, Solver},
+};
+
+/// The most empty cells of a position that is solved on request, a larger game tree takes too long to answer.
+const SOLVE_EMPTY: usize = 8;
+
+/// The state of a game played by requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    pub id: u64,
+    pub board: Board,
+    /// The piece that was handed over and waits to be placed.
+    pub piece: Option<u8>,
+    /// The player to act: to hand over a piece when there is none in hand, to place it otherwise.
+    pub turn: usize,
+    pub result: Option<GameResult>,
+    /// The name of the computer player of the registry that decides for the engine requests.
+    pub engine: String,
+}
+
+impl Session {
+    /// Start a game on an empty board, where the first player hands over the first piece.
+    pub fn new(id: u64, engine: &str) -> Self {
+        Session {
+            id,
+            board: Board::new(),
+            piece: None,
+            turn: 0,
+            result: None,
+            engine: engine.to_string(),
+        }
+    }
+
+    /// Apply the action of the player to act. Quarto is called for the players, when a placement wins.
+    pub fn apply(&mut self, action: Action) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("The game is over".to_string());
+        }
+        match (action, self.piece) {
+            (Action::Give(piece), None) => {
+                if !self.board.valid_pieces().contains(&piece) {
+                    return Err(format!("Piece {} cannot be handed over", piece));
+                }
+                self.piece = Some(piece);
+                self.turn = 1 - self.turn;
+            }
+            (Action::Place(index), Some(piece)) => {
+                self.board
+                    .put_piece(piece, index)
+                    .map_err(|e| e.to_string())?;
+                self.piece = None;
+                if self.board.has_winner() {
+                    self.result = Some(GameResult::Win(self.turn));
+                } else if self.board.empty_spaces().is_empty() {
+                    self.result = Some(GameResult::Draw);
+                }
+            }
+            (Action::Give(_), Some(_)) => {
+                return Err("The piece in hand is placed first".to_string());
+            }
+            (Action::Place(_), None) => return Err("There is no piece to place".to_string()),
+            (Action::Quarto, _) => {
+                return Err("Quarto is called with the winning placement".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The action `player` takes for the player to act.
+    pub fn decide(&self, player: &dyn Player) -> Result<Action, String> {
+        if self.result.is_some() {
+            return Err("The game is over".to_string());
+        }
+        let action = match self.piece {
+            Some(piece) => player.get_move(&self.board, piece).map(Action::Place),
+            None => player.get_piece(&self.board).map(Action::Give),
+        };
+        action.ok_or_else(|| format!("The {} player did not decide", self.engine))
+    }
+}
+
+/// The games of a server by id, shared between the threads that answer the requests.
+#[derive(Debug, Default)]
+pub struct Sessions {
+    games: Mutex<BTreeMap<u64, Session>>,
+    /// The options of the computer players that decide for the engine requests.
+    options: PlayerOptions,
+}
+
+impl Sessions {
+    /// Create an empty set of games, with the default options for the computer players.
+    pub fn new() -> Self {
+        Sessions::default()
+    }
+
+    /// Make the computer players with `options`, like a deeper search.
+    pub fn with_options(mut self, options: PlayerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// A poisoned lock still holds usable games.
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u64, Session>> {
+        self.games.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start a game, where the computer player `engine` of the registry decides for the engine requests.
+    pub fn create(&self, engine: &str) -> Result<Session, String> {
+        Registry::default().create_computer(engine, &self.options)?;
+        let mut games = self.lock();
+        let id = games.keys().next_back().map_or(1, |id| id + 1);
+        let session = Session::new(id, engine);
+        games.insert(id, session.clone());
+        Ok(session)
+    }
+
+    /// The game with `id`, if there is one.
+    pub fn get(&self, id: u64) -> Option<Session> {
+        self.lock().get(&id).cloned()
+    }
+
+    /// Apply `action` to the game with `id`, and return the game after it.
+    pub fn act(&self, id: u64, action: Action) -> Result<Session, String> {
+        let mut games = self.lock();
+        let session = games
+            .get_mut(&id)
+            .ok_or(format!("There is no game {}", id))?;
+        session.apply(action)?;
+        Ok(session.clone())
+    }
+
+    /// The action the engine of the game with `id` takes for the player to act.
+    /// The engine thinks without holding the lock, so the other games go on meanwhile.
+    pub fn best_action(&self, id: u64) -> Result<Action, String> {
+        let session = self.get(id).ok_or(format!("There is no game {}", id))?;
+        let engine = Registry::default().create_computer(&session.engine, &self.options)?;
+        session.decide(engine.as_ref())
+    }
+
+    /// Prove the outcome of every action of the player to act in the game with `id`, for that player.
+    pub fn solve(&self, id: u64) -> Result<Vec<(Action, Outcome)>, String> {
+        let session = self.get(id).ok_or(format!("There is no game {}", id))?;
+        if session.result.is_some() {
+            return Err("The game is over".to_string());
+        }
+        let empty = session.board.empty_spaces().len();
+        if empty > SOLVE_EMPTY {
+            return Err(format!(
+                "A position with {} empty cells is too big to solve, at most {} are",
+                empty, SOLVE_EMPTY
+            ));
+        }
+        let mut solver = Solver::new();
+        Ok(match session.piece {
+            Some(piece) => solver
+                .placement_outcomes(&session.board, piece)
+                .into_iter()
+                .map(|(index, outcome)| (Action::Place(index), outcome))
+                .collect(),
+            None => solver
+                .piece_outcomes(&session.board)
+                .into_iter()
+                .map(|(piece, outcome)| (Action::Give(piece), outcome))
+                .collect(),
+        })
+    }
+
+    /// Let the engine of the game with `id` act for the player to act, and return the game after it.
+    pub fn play_engine(&self, id: u64) -> Result<Session, String> {
+        let action = self.best_action(id)?;
+        self.act(id, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_turns() {
+        let mut session = Session::new(1, "smart");
+        assert!(session.apply(Action::Place(0)).is_err());
+        session.apply(Action::Give(8)).unwrap();
+        assert_eq!((session.piece, session.turn), (Some(8), 1));
+        assert!(session.apply(Action::Give(9)).is_err());
+        session.apply(Action::Place(0)).unwrap();
+        assert!(session.apply(Action::Give(8)).is_err());
+        for (piece, index) in [(9, 1), (10, 2), (11, 3)] {
+            session.apply(Action::Give(piece)).unwrap();
+            session.apply(Action::Place(index)).unwrap();
+        }
+        // The player that placed the fourth hole piece on the row won.
+        assert_eq!(session.result, Some(GameResult::Win(0)));
+        assert!(session.apply(Action::Give(0)).is_err());
+    }
+
+    #[test]
+    fn test_engine_plays_sessions() {
+        let sessions = Sessions::new().with_options(PlayerOptions {
+            depth: 1,
+            ..PlayerOptions::default()
+        });
+        assert!(sessions.create("human").is_err());
+        let id = sessions.create("smart").unwrap().id;
+        assert_eq!(sessions.create("dumb").unwrap().id, id + 1);
+        for (piece, index) in [(8, 0), (9, 1), (10, 2)] {
+            sessions.act(id, Action::Give(piece)).unwrap();
+            sessions.act(id, Action::Place(index)).unwrap();
+        }
+        sessions.act(id, Action::Give(11)).unwrap();
+        assert_eq!(sessions.best_action(id), Ok(Action::Place(3)));
+        assert!(sessions.solve(id).unwrap_err().contains("too big"));
+        let session = sessions.play_engine(id).unwrap();
+        assert_eq!(session.result, Some(GameResult::Win(0)));
+        assert!(sessions.act(id + 5, Action::Give(0)).is_err());
+    }
+
+    #[test]
+    fn test_solve_late_game() {
+        let sessions = Sessions::new();
+        let id = sessions.create("smart").unwrap().id;
+        let moves = [(0, 0), (15, 1), (3, 2), (12, 3), (5, 4), (10, 5), (6, 6)];
+        for (piece, index) in moves.into_iter().chain([(9, 7), (1, 9), (14, 10), (7, 11)]) {
+            sessions.act(id, Action::Give(piece)).unwrap();
+            sessions.act(id, Action::Place(index)).unwrap();
+        }
+        let outcomes = sessions.solve(id).unwrap();
+        let pieces: Vec<Action> = outcomes.iter().map(|&(action, _)| action).collect();
+        assert_eq!(pieces, [2, 4, 8, 11, 13].map(Action::Give));
+        sessions.act(id, Action::Give(2)).unwrap();
+        assert_eq!(sessions.solve(id).unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,287 @@
+// Batch simulation of games between two players, with statistics over the results.
+// Used to compare strategies, so the report can be printed as a table or written as CSV or JSON.
+// With the `parallel` feature, the games of a batch can be spread over a thread pool and their statistics merged.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    game::{GameResult, QuartoGame},
+    random::SharedRng,
+};
+
+/// Statistics over a batch of games, from the view of the first player, or their team in a team game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimulationStats {
+    pub games: u32,
+    /// Games won by the first player.
+    pub wins: u32,
+    pub draws: u32,
+    /// Games won by the second player.
+    pub losses: u32,
+    /// Games that ended with an error.
+    pub errors: u32,
+    /// The number of turns over all games.
+    pub turns: u64,
+    /// The time spent playing all games, added up over the games.
+    pub elapsed: Duration,
+    /// The time from the start to the end of the batch, shorter than `elapsed` when games run in parallel.
+    pub wall_time: Duration,
+}
+
+impl SimulationStats {
+    /// Add the result of a game that took `turns` turns and `elapsed` time.
+    pub fn add(&mut self, result: GameResult, turns: usize, elapsed: Duration) {
+        self.games += 1;
+        match (result, result.winning_team()) {
+            (_, Some(0)) => self.wins += 1,
+            (_, Some(_)) => self.losses += 1,
+            (GameResult::Draw, None) => self.draws += 1,
+            (_, None) => self.errors += 1,
+        }
+        self.turns += turns as u64;
+        self.elapsed += elapsed;
+        self.wall_time += elapsed;
+    }
+
+    /// Add the statistics of another batch, like one played on another thread at the same time.
+    /// The wall time is the longer of the two, as the batches overlap.
+    pub fn merge(&mut self, other: &SimulationStats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+        self.errors += other.errors;
+        self.turns += other.turns;
+        self.elapsed += other.elapsed;
+        self.wall_time = self.wall_time.max(other.wall_time);
+    }
+
+    /// The average number of turns per game.
+    pub fn average_turns(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        self.turns as f64 / self.games as f64
+    }
+
+    /// The average time per game.
+    pub fn average_time(&self) -> Duration {
+        if self.games == 0 {
+            return Duration::ZERO;
+        }
+        self.elapsed / self.games
+    }
+
+    /// Format the statistics as a table for the terminal.
+    pub fn to_table(&self) -> String {
+        let rows = [
+            ("Games", self.games.to_string()),
+            ("Wins", self.wins.to_string()),
+            ("Draws", self.draws.to_string()),
+            ("Losses", self.losses.to_string()),
+            ("Errors", self.errors.to_string()),
+            ("Average turns", format!("{:.2}", self.average_turns())),
+            ("Average time", format!("{:.2?}", self.average_time())),
+            ("Total time", format!("{:.2?}", self.wall_time)),
+        ];
+        rows.iter()
+            .map(|(name, value)| format!("{:<14}{:>12}\n", name, value))
+            .collect()
+    }
+
+    /// Format the statistics as CSV, a header line and a line with the values.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "games,wins,draws,losses,errors,average_turns,average_seconds,total_seconds\n{},{},{},{},{},{:.4},{:.6},{:.6}\n",
+            self.games,
+            self.wins,
+            self.draws,
+            self.losses,
+            self.errors,
+            self.average_turns(),
+            self.average_time().as_secs_f64(),
+            self.wall_time.as_secs_f64()
+        )
+    }
+
+    /// Format the statistics as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"games\":{},\"wins\":{},\"draws\":{},\"losses\":{},\"errors\":{},\"average_turns\":{:.4},\"average_seconds\":{:.6},\"total_seconds\":{:.6}}}",
+            self.games,
+            self.wins,
+            self.draws,
+            self.losses,
+            self.errors,
+            self.average_turns(),
+            self.average_time().as_secs_f64(),
+            self.wall_time.as_secs_f64()
+        )
+    }
+}
+
+/// Play `games` games built by `make_game`, and collect their statistics.
+/// `make_game` gets the random generator to hand to the players of the game.
+/// With a seed, game `i` gets a generator seeded with `seed + i`, so a batch can be repeated.
+/// Stops at the first game that `make_game` cannot build, with its error.
+pub fn simulate<F>(games: u32, seed: Option<u64>, make_game: F) -> Result<SimulationStats, String>
+where
+    F: Fn(&SharedRng) -> Result<QuartoGame, String>,
+{
+    let mut stats = SimulationStats::default();
+    let start = Instant::now();
+    for game in 0..games {
+        play(&mut stats, game, seed, &make_game)?;
+    }
+    stats.wall_time = start.elapsed();
+    Ok(stats)
+}
+
+/// Play `games` games like `simulate`, spread over a pool of `threads` threads, or one per core without a number.
+/// Every game gets its own generator, with a seed the same one as in `simulate`, so the statistics are the same too.
+#[cfg(feature = "parallel")]
+pub fn simulate_parallel<F>(
+    games: u32,
+    seed: Option<u64>,
+    threads: Option<usize>,
+    make_game: F,
+) -> Result<SimulationStats, String>
+where
+    F: Fn(&SharedRng) -> Result<QuartoGame, String> + Sync,
+{
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .map_err(|e| format!("Cannot start the threads: {}", e))?;
+    let start = Instant::now();
+    let mut stats = pool.install(|| {
+        (0..games)
+            .into_par_iter()
+            .try_fold(SimulationStats::default, |mut stats, game| {
+                play(&mut stats, game, seed, &make_game).map(|_| stats)
+            })
+            .try_reduce(SimulationStats::default, |mut stats, other| {
+                stats.merge(&other);
+                Ok(stats)
+            })
+    })?;
+    stats.wall_time = start.elapsed();
+    Ok(stats)
+}
+
+/// Play game number `game` of a batch built by `make_game`, and add it to `stats`.
+fn play<F>(
+    stats: &mut SimulationStats,
+    game: u32,
+    seed: Option<u64>,
+    make_game: &F,
+) -> Result<(), String>
+where
+    F: Fn(&SharedRng) -> Result<QuartoGame, String>,
+{
+    let rng = match seed {
+        Some(seed) => SharedRng::seeded(seed.wrapping_add(game as u64)),
+        None => SharedRng::new(),
+    };
+    let mut quarto = make_game(&rng)?;
+    let start = Instant::now();
+    let result = quarto.play_without_call();
+    stats.add(result, quarto.history().len(), start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        player::ComputerPlayer,
+        strategy::{DumbStrategy, SmartStrategy},
+    };
+
+    use super::*;
+
+    fn dumb_game(rng: &SharedRng) -> Result<QuartoGame, String> {
+        Ok(QuartoGame::new(
+            ComputerPlayer::new(DumbStrategy::new().with_rng(rng.clone())),
+            ComputerPlayer::new(DumbStrategy::new().with_rng(rng.clone())),
+        ))
+    }
+
+    #[test]
+    fn test_counts_add_up() {
+        let stats = simulate(30, Some(1), dumb_game).unwrap();
+        assert_eq!(stats.games, 30);
+        assert_eq!(stats.wins + stats.draws + stats.losses + stats.errors, 30);
+        assert_eq!(stats.errors, 0);
+        // A game takes at least the four turns needed to fill a line.
+        assert!(stats.average_turns() >= 4.0);
+    }
+
+    #[test]
+    fn test_seed_repeats_batch() {
+        let first = simulate(10, Some(7), dumb_game).unwrap();
+        let second = simulate(10, Some(7), dumb_game).unwrap();
+        assert_eq!(
+            (first.wins, first.draws, first.losses, first.turns),
+            (second.wins, second.draws, second.losses, second.turns)
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let sequential = simulate(200, Some(11), dumb_game).unwrap();
+        let parallel = simulate_parallel(200, Some(11), Some(4), dumb_game).unwrap();
+        assert_eq!(
+            (
+                parallel.games,
+                parallel.wins,
+                parallel.draws,
+                parallel.turns
+            ),
+            (
+                sequential.games,
+                sequential.wins,
+                sequential.draws,
+                sequential.turns
+            )
+        );
+    }
+
+    #[test]
+    fn test_smart_beats_dumb() {
+        let stats = simulate(10, Some(3), |rng| {
+            Ok(QuartoGame::new(
+                ComputerPlayer::new(SmartStrategy::new(1)),
+                ComputerPlayer::new(DumbStrategy::new().with_rng(rng.clone())),
+            ))
+        })
+        .unwrap();
+        assert!(stats.wins > stats.losses);
+        assert!(simulate(10, Some(3), |_| Err("no players".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        let mut stats = SimulationStats::default();
+        stats.add(GameResult::Win(0), 8, Duration::from_millis(2));
+        stats.add(GameResult::Draw, 16, Duration::from_millis(4));
+        assert!(stats.to_table().contains("Average turns        12.00"));
+        let csv = stats.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("2,1,1,0,0,12.0000,0.003000,0.006000"));
+        assert!(
+            stats
+                .to_json()
+                .starts_with("{\"games\":2,\"wins\":1,\"draws\":1,")
+        );
+        // Batches that ran at the same time took as long as the longest one.
+        let mut other = SimulationStats::default();
+        other.add(GameResult::Win(1), 8, Duration::from_millis(5));
+        stats.merge(&other);
+        assert_eq!(stats.elapsed, Duration::from_millis(11));
+        assert_eq!(stats.wall_time, Duration::from_millis(6));
+    }
+}
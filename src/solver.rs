@@ -0,0 +1,367 @@
+// An exact solver for Quarto positions.
+// Searches the whole game tree with win/draw/loss values, remembering positions by their canonical form.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    board::{Board, PlacementOutcome},
+    symmetry,
+    transposition::ProofTable,
+};
+
+/// The game-theoretic value of a position, for the player that has to place the piece in hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    fn from_value(value: i8) -> Self {
+        match value {
+            v if v > 0 => Outcome::Win,
+            0 => Outcome::Draw,
+            _ => Outcome::Loss,
+        }
+    }
+
+    /// The outcome for the other player.
+    pub fn flip(self) -> Self {
+        match self {
+            Outcome::Win => Outcome::Loss,
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Loss => Outcome::Win,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Win => write!(f, "win"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss => write!(f, "loss"),
+        }
+    }
+}
+
+/// A solver that keeps the values of the positions it has proven, so related positions are solved faster.
+#[derive(Debug, Default)]
+pub struct Solver {
+    memo: HashMap<u128, i8>,
+    nodes: u64,
+    /// The most positions to remember, the cache is emptied when it is full.
+    limit: Option<usize>,
+    /// A fixed-size table that remembers the positions instead of the cache.
+    table: Option<ProofTable>,
+}
+
+impl Solver {
+    /// The memory of a remembered position in bytes, roughly.
+    pub const POSITION_SIZE: usize = std::mem::size_of::<(u128, i8)>() + 1;
+
+    /// Create a new `Solver` without any known positions.
+    pub fn new() -> Self {
+        Solver::default()
+    }
+
+    /// Remember at most `positions` positions, forgetting all of them when the cache is full.
+    pub fn with_limit(mut self, positions: usize) -> Self {
+        self.limit = Some(positions);
+        self
+    }
+
+    /// Remember the positions in `table` instead of a cache, which never grows beyond its size.
+    pub fn with_table(mut self, table: ProofTable) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// The memory the cache takes in bytes, roughly.
+    pub fn memory_usage(&self) -> usize {
+        match &self.table {
+            Some(table) => table.memory_usage(),
+            None => self.memo.capacity() * Self::POSITION_SIZE,
+        }
+    }
+
+    /// The number of positions visited so far.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// The number of distinct canonical positions that were proven.
+    pub fn known_positions(&self) -> usize {
+        match &self.table {
+            Some(table) => table.len(),
+            None => self.memo.len(),
+        }
+    }
+
+    /// Prove the value of placing `piece` on `board`, for the player that places it.
+    pub fn solve(&mut self, board: &Board, piece: u8) -> Outcome {
+        Outcome::from_value(self.place_value(board, piece))
+    }
+
+    /// Prove the value of every placement of `piece` on `board`, in order of the indices.
+    pub fn placement_outcomes(&mut self, board: &Board, piece: u8) -> Vec<(u8, Outcome)> {
+        let mut outcomes: Vec<(u8, Outcome)> = Vec::new();
+        for index in board.empty_spaces() {
+            let mut next = *board;
+            let value = match next.put_piece_checked(piece, index) {
+                Ok(PlacementOutcome::Win) => 1,
+                Ok(PlacementOutcome::Draw) => 0,
+                Ok(PlacementOutcome::Ongoing) => self.give_value(&next),
+                Err(_) => continue,
+            };
+            outcomes.push((index, Outcome::from_value(value)));
+        }
+        outcomes
+    }
+
+    /// Prove the value of handing over each remaining piece on `board`, for the player that hands it over.
+    pub fn piece_outcomes(&mut self, board: &Board) -> Vec<(u8, Outcome)> {
+        board
+            .valid_pieces()
+            .into_iter()
+            .map(|piece| (piece, self.solve(board, piece).flip()))
+            .collect()
+    }
+
+    /// The value of placing `piece` on `board`: 1 for a win, 0 for a draw, -1 for a loss.
+    fn place_value(&mut self, board: &Board, piece: u8) -> i8 {
+        self.nodes += 1;
+        let spaces = board.empty_cells();
+        for index in spaces {
+            let mut next = *board;
+            if next.put_piece_checked(piece, index) == Ok(PlacementOutcome::Win) {
+                return 1;
+            }
+        }
+        let key = key(board, piece);
+        let known = match &self.table {
+            Some(table) => table.probe(key),
+            None => self.memo.get(&key).copied(),
+        };
+        if let Some(value) = known {
+            return value;
+        }
+        let mut best = -1;
+        for index in spaces {
+            let mut next = *board;
+            if next.put_piece(piece, index).is_err() {
+                continue;
+            }
+            let value = if next.board_full() {
+                0
+            } else {
+                self.give_value(&next)
+            };
+            best = best.max(value);
+            // Nothing beats a win, so the other placements do not need to be searched.
+            if best == 1 {
+                break;
+            }
+        }
+        if let Some(table) = self.table.as_mut() {
+            table.store(key, spaces.len() as u8, best);
+            return best;
+        }
+        if self.limit.is_some_and(|limit| self.memo.len() >= limit) {
+            self.memo = HashMap::new();
+        }
+        if self.limit != Some(0) {
+            self.memo.insert(key, best);
+        }
+        best
+    }
+
+    /// The value of handing over a piece on `board`, for the player that hands it over.
+    fn give_value(&mut self, board: &Board) -> i8 {
+        let mut best = -1;
+        for piece in board.available_pieces() {
+            best = best.max(-self.place_value(board, piece));
+            if best == 1 {
+                break;
+            }
+        }
+        best
+    }
+}
+
+/// Prove the value of placing `piece` on `board`, for the player that places it.
+pub fn solve(board: &Board, piece: u8) -> Outcome {
+    Solver::new().solve(board, piece)
+}
+
+/// The canonical form of `board` with `piece` in hand, as the key of the memo and the table.
+/// The unused bits 1 to 3 of every cell hold 48 bits of the key of the ruleset, to keep the positions of rulesets apart.
+fn key(board: &Board, piece: u8) -> u128 {
+    let ruleset = board.ruleset_key();
+    (0..16).fold(symmetry::canonical(board, piece).items(), |key, cell| {
+        key | u128::from((ruleset >> (3 * cell)) & 0b111) << (8 * cell + 1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::*;
+    use crate::{board::Ruleset, rules::CellRule, transposition::Replacement};
+
+    /// A position with eleven pieces on the board and no winner yet.
+    fn late_game() -> Board {
+        Board::new()
+            .apply_moves(&[
+                (0, 0),
+                (15, 1),
+                (3, 2),
+                (12, 3),
+                (5, 4),
+                (10, 5),
+                (6, 6),
+                (9, 7),
+                (1, 9),
+                (14, 10),
+                (7, 11),
+            ])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_limit_keeps_outcome() {
+        let board = late_game();
+        let mut limited = Solver::new().with_limit(10);
+        assert_eq!(limited.solve(&board, 2), solve(&board, 2));
+        assert!(limited.known_positions() <= 10);
+        let mut none = Solver::new().with_limit(0);
+        assert_eq!(none.solve(&board, 2), solve(&board, 2));
+        assert_eq!(none.known_positions(), 0);
+    }
+
+    #[test]
+    fn test_table_keeps_outcome() {
+        let board = late_game();
+        for table in [
+            ProofTable::new(8, Replacement::DepthPreferred),
+            ProofTable::new(8, Replacement::Always).with_two_tiers(),
+        ] {
+            let mut solver = Solver::new().with_table(table);
+            assert_eq!(solver.solve(&board, 2), solve(&board, 2));
+            assert!(solver.known_positions() <= 8);
+            assert_eq!(solver.memory_usage(), 8 * ProofTable::ENTRY_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_keys_keep_rulesets_apart() {
+        static CORNERS: OnceLock<CellRule> = OnceLock::new();
+        static CENTER: OnceLock<CellRule> = OnceLock::new();
+        let corners = CORNERS.get_or_init(|| CellRule::new("custom", &[[0, 3, 12, 15]]));
+        let center = CENTER.get_or_init(|| CellRule::new("custom", &[[5, 6, 9, 10]]));
+        let board = late_game();
+        let keys: Vec<u128> = [
+            Ruleset::Classic,
+            Ruleset::Advanced,
+            Ruleset::Custom(corners),
+            Ruleset::Custom(center),
+        ]
+        .into_iter()
+        .map(|ruleset| key(&board.with_ruleset(ruleset), 2))
+        .collect();
+        assert_eq!(keys[0], symmetry::canonical(&board, 2).items());
+        for (i, a) in keys.iter().enumerate() {
+            assert!(keys[i + 1..].iter().all(|b| a != b));
+        }
+    }
+
+    #[test]
+    fn test_immediate_win() {
+        let board = Board::new()
+            .apply_moves(&[(8, 12), (12, 13), (10, 14)])
+            .unwrap();
+        assert_eq!(solve(&board, 9), Outcome::Win);
+    }
+
+    #[test]
+    fn test_last_piece() {
+        let mut board = Board::new();
+        // Fill the board without a winner, leaving the last index open for the last piece.
+        let moves = [
+            (0, 0),
+            (15, 1),
+            (3, 2),
+            (12, 3),
+            (5, 4),
+            (10, 5),
+            (6, 6),
+            (9, 7),
+            (1, 8),
+            (14, 9),
+            (13, 10),
+            (2, 11),
+            (11, 12),
+            (4, 13),
+            (8, 14),
+        ];
+        for (piece, index) in moves {
+            assert!(board.put_piece(piece, index).is_ok());
+        }
+        assert!(!board.has_winner());
+        let mut last = board;
+        last.put_piece(7, 15).unwrap();
+        let expected = if last.has_winner() {
+            Outcome::Win
+        } else {
+            Outcome::Draw
+        };
+        assert_eq!(solve(&board, 7), expected);
+    }
+
+    #[test]
+    fn test_symmetric_positions_same_outcome() {
+        let board = late_game();
+        assert!(!board.game_over());
+        let mut solver = Solver::new();
+        for piece in board.valid_pieces() {
+            let outcome = solver.solve(&board, piece);
+            for permutation in symmetry::symmetries().iter().take(4) {
+                let other = symmetry::transform(&board, permutation);
+                assert_eq!(Solver::new().solve(&other, piece), outcome);
+            }
+        }
+    }
+
+    #[test]
+    fn test_placement_outcomes_consistent_with_solve() {
+        let board = late_game();
+        let mut solver = Solver::new();
+        for piece in board.valid_pieces() {
+            let best = solver
+                .placement_outcomes(&board, piece)
+                .into_iter()
+                .map(|(_, o)| o)
+                .max()
+                .unwrap();
+            assert_eq!(best, solver.solve(&board, piece));
+        }
+    }
+
+    #[test]
+    fn test_memo_is_used() {
+        let board = late_game();
+        let pieces = board.valid_pieces();
+        let mut solver = Solver::new();
+        for &piece in pieces.iter() {
+            solver.solve(&board, piece);
+        }
+        assert!(solver.known_positions() > 0);
+        let nodes = solver.nodes();
+        for &piece in pieces.iter() {
+            solver.solve(&board, piece);
+        }
+        assert_eq!(solver.nodes() - nodes, pieces.len() as u64);
+    }
+}
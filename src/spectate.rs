@@ -0,0 +1,322 @@
+// The messages a spectator follows a game by, and a terminal view that shows the game as it is played.
+// The view follows the events of a demo, and the games watched on a server with `join --watch`.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::{
+    board::Board, clock::format_time, eval, events::GameEvent, game::GameResult,
+    printable::PrintableBoard, random::SharedRng, symmetry,
+};
+
+/// A line of the spectator stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectatorMessage {
+    /// Something happened in the game.
+    Event(GameEvent),
+    /// The time each player has left.
+    Clock([Duration; 2]),
+}
+
+impl fmt::Display for SpectatorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectatorMessage::Event(GameEvent::GameStarted) => write!(f, "start"),
+            SpectatorMessage::Event(GameEvent::PieceGiven { player, piece }) => {
+                write!(f, "give {} {}", player, piece)
+            }
+            SpectatorMessage::Event(GameEvent::PiecePlaced {
+                player,
+                piece,
+                index,
+            }) => write!(f, "place {} {} {}", player, piece, index),
+            SpectatorMessage::Event(GameEvent::QuartoCalled { player, correct }) => {
+                write!(f, "quarto {} {}", player, *correct as u8)
+            }
+            SpectatorMessage::Event(GameEvent::GameEnded { result }) => match result {
+                GameResult::Win(winner) => write!(f, "end win {}", winner),
+                GameResult::Draw => write!(f, "end draw"),
+                GameResult::Error => write!(f, "end error"),
+                GameResult::Timeout(player) => write!(f, "end timeout {}", player),
+            },
+            SpectatorMessage::Clock([first, second]) => {
+                write!(f, "clock {} {}", first.as_millis(), second.as_millis())
+            }
+        }
+    }
+}
+
+impl FromStr for SpectatorMessage {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| -> Result<u64, String> {
+            word.parse()
+                .map_err(|_| format!("'{}' is not a number in '{}'", word, line))
+        };
+        // Pieces and indices are below 16, a larger number is not cut down to one.
+        let small = |word: &str| -> Result<u8, String> {
+            word.parse::<u8>()
+                .ok()
+                .filter(|&number| number < 16)
+                .ok_or_else(|| format!("'{}' is not a piece or index in '{}'", word, line))
+        };
+        let event = match words.as_slice() {
+            ["start"] => GameEvent::GameStarted,
+            ["give", player, piece] => GameEvent::PieceGiven {
+                player: number(player)? as usize,
+                piece: small(piece)?,
+            },
+            ["place", player, piece, index] => GameEvent::PiecePlaced {
+                player: number(player)? as usize,
+                piece: small(piece)?,
+                index: small(index)?,
+            },
+            ["quarto", player, correct] => GameEvent::QuartoCalled {
+                player: number(player)? as usize,
+                correct: number(correct)? != 0,
+            },
+            ["end", "win", winner] => GameEvent::GameEnded {
+                result: GameResult::Win(number(winner)? as usize),
+            },
+            ["end", "draw"] => GameEvent::GameEnded {
+                result: GameResult::Draw,
+            },
+            ["end", "error"] => GameEvent::GameEnded {
+                result: GameResult::Error,
+            },
+            ["end", "timeout", player] => GameEvent::GameEnded {
+                result: GameResult::Timeout(number(player)? as usize),
+            },
+            ["clock", first, second] => {
+                return Ok(SpectatorMessage::Clock([
+                    Duration::from_millis(number(first)?),
+                    Duration::from_millis(number(second)?),
+                ]));
+            }
+            _ => return Err(format!("unknown message '{}'", line)),
+        };
+        Ok(SpectatorMessage::Event(event))
+    }
+}
+
+/// The state of a watched game, as far as the spectator has seen it.
+#[derive(Debug, Clone, Default)]
+pub struct WatchView {
+    board: Board,
+    /// The piece that was handed over and still has to be placed.
+    in_hand: Option<u8>,
+    clocks: Option<[Duration; 2]>,
+    result: Option<GameResult>,
+    /// Show the board rotated by half a turn, as seen from the other side of the table.
+    flipped: bool,
+    /// The player to place the piece in hand, or to hand over the next piece.
+    to_move: usize,
+    /// The number of playouts for the evaluation bar, which is hidden at 0.
+    playouts: u32,
+    /// The expected score of the first player after the last ply.
+    score: Option<f64>,
+    /// Where the random games of the evaluation bar are drawn from.
+    rng: SharedRng,
+}
+
+impl WatchView {
+    /// Create a view on an empty board.
+    pub fn new() -> Self {
+        WatchView::default()
+    }
+
+    /// Show an evaluation bar, estimated with `playouts` random games after every ply.
+    pub fn with_evaluation(mut self, playouts: u32) -> Self {
+        self.playouts = playouts;
+        self
+    }
+
+    /// Draw the random games of the evaluation bar from `rng`, so a seed shows the same estimates.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Update the view with a message from the stream.
+    pub fn apply(&mut self, message: SpectatorMessage) {
+        match message {
+            SpectatorMessage::Event(GameEvent::GameStarted) => {
+                *self = WatchView {
+                    flipped: self.flipped,
+                    playouts: self.playouts,
+                    rng: self.rng.clone(),
+                    ..WatchView::default()
+                };
+                self.evaluate();
+            }
+            SpectatorMessage::Event(GameEvent::PieceGiven { player, piece }) => {
+                self.in_hand = Some(piece);
+                self.to_move = 1 - player;
+                self.evaluate();
+            }
+            SpectatorMessage::Event(GameEvent::PiecePlaced {
+                player,
+                piece,
+                index,
+            }) => {
+                // A spectator cannot do anything about an illegal placement, it just shows what it can.
+                let _ = self.board.put_piece(piece, index);
+                self.in_hand = None;
+                self.to_move = player;
+                self.evaluate();
+            }
+            SpectatorMessage::Event(GameEvent::QuartoCalled { .. }) => {}
+            SpectatorMessage::Event(GameEvent::GameEnded { result }) => self.result = Some(result),
+            SpectatorMessage::Clock(clocks) => self.clocks = Some(clocks),
+        }
+    }
+
+    /// Estimate the score for the evaluation bar, if it is shown.
+    fn evaluate(&mut self) {
+        if self.playouts > 0 {
+            self.score = Some(eval::estimate(
+                &self.board,
+                self.in_hand,
+                self.to_move,
+                self.playouts,
+                &self.rng,
+            ));
+        }
+    }
+
+    /// Turn the board around.
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    /// Check if the game is over.
+    pub fn finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Render the board, the piece in hand, the clocks and the result.
+    pub fn render(&self) -> String {
+        let board = if self.flipped {
+            let half_turn: symmetry::Permutation = core::array::from_fn(|i| 15 - i as u8);
+            symmetry::transform(&self.board, &half_turn)
+        } else {
+            self.board
+        };
+        let mut out = format!("{}\n", PrintableBoard::new(&board));
+        if let Some(piece) = self.in_hand {
+            out += &format!("In hand: {:04b}\n", piece);
+        }
+        if let Some(score) = self.score.filter(|_| !self.finished()) {
+            out += &format!("{}\n", eval::bar(score, 20));
+        }
+        if let Some([first, second]) = self.clocks {
+            out += &format!("Clocks: {} | {}\n", format_time(first), format_time(second));
+        }
+        match self.result {
+            Some(GameResult::Win(winner)) => {
+                out += &format!("Player {} wins!\n", winner + 1);
+                for line in self.board.winning_lines() {
+                    out += &format!("Quarto on {}\n", line);
+                }
+            }
+            Some(GameResult::Draw) => out += "It is a draw.\n",
+            Some(GameResult::Error) => out += "The game ended with an error.\n",
+            Some(GameResult::Timeout(player)) => {
+                out += &format!("Player {} ran out of time.\n", player + 1)
+            }
+            None => {}
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip() {
+        let messages = [
+            SpectatorMessage::Event(GameEvent::GameStarted),
+            SpectatorMessage::Event(GameEvent::PieceGiven {
+                player: 1,
+                piece: 9,
+            }),
+            SpectatorMessage::Event(GameEvent::PiecePlaced {
+                player: 0,
+                piece: 9,
+                index: 15,
+            }),
+            SpectatorMessage::Event(GameEvent::QuartoCalled {
+                player: 0,
+                correct: true,
+            }),
+            SpectatorMessage::Event(GameEvent::GameEnded {
+                result: GameResult::Win(0),
+            }),
+            SpectatorMessage::Event(GameEvent::GameEnded {
+                result: GameResult::Timeout(1),
+            }),
+            SpectatorMessage::Clock([Duration::from_millis(1500), Duration::from_secs(61)]),
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+        assert!("move 1 2".parse::<SpectatorMessage>().is_err());
+        assert!("place 0 9 259".parse::<SpectatorMessage>().is_err());
+        assert!("give 1 16".parse::<SpectatorMessage>().is_err());
+    }
+
+    #[test]
+    fn test_flip_turns_board_around() {
+        let mut view = WatchView::new();
+        view.apply("place 1 15 0".parse().unwrap());
+        assert!(view.render().starts_with("1111 ----"));
+        view.flip();
+        let flipped = view.render();
+        assert!(flipped.lines().nth(3).unwrap().ends_with("---- 1111"));
+    }
+
+    #[test]
+    fn test_render_clocks_and_result() {
+        let mut view = WatchView::new();
+        view.apply("give 0 5".parse().unwrap());
+        view.apply("clock 65000 4000".parse().unwrap());
+        assert!(view.render().contains("In hand: 0101"));
+        assert!(view.render().contains("Clocks: 1:05 | 0:04"));
+        view.apply("end draw".parse().unwrap());
+        assert!(view.finished());
+        assert!(view.render().ends_with("It is a draw.\n"));
+    }
+
+    #[test]
+    fn test_evaluation_bar() {
+        let mut view = WatchView::new().with_evaluation(50);
+        for line in ["start", "place 0 8 12", "place 0 12 13", "place 0 10 14"] {
+            view.apply(line.parse().unwrap());
+        }
+        view.apply("give 0 9".parse().unwrap());
+        assert!(
+            view.render()
+                .contains("Player 1 [--------------------] Player 2  0%")
+        );
+        view.apply("place 1 9 15".parse().unwrap());
+        view.apply("end win 1".parse().unwrap());
+        assert!(!view.render().contains("Player 1 ["));
+        assert!(!WatchView::new().render().contains("Player 1 ["));
+    }
+
+    #[test]
+    fn test_render_announces_winning_line() {
+        let mut view = WatchView::new();
+        for (piece, index) in [(8, 0), (12, 1), (10, 2), (9, 3)] {
+            view.apply(format!("place 0 {} {}", piece, index).parse().unwrap());
+        }
+        view.apply("end win 0".parse().unwrap());
+        assert!(
+            view.render()
+                .ends_with("Player 1 wins!\nQuarto on row 0: all hole\n")
+        );
+    }
+}
@@ -1,4 +1,25 @@
-use crate::board::Board;
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::{
+    board::Board,
+    cancel::CancelToken,
+    evaluator::Evaluator,
+    game::GameResult,
+    mcts::{self, MctsBudget},
+    observer::GameObserver,
+    player::{ComputerPlayer, Player},
+    random::SharedRng,
+    record::GameRecord,
+    search::{self, ProgressReporter, RankedMove, Searcher},
+    solver::{Outcome, Solver},
+    transposition::TranspositionTable,
+};
 
 /// A `Strategy` determines how the `ComputerPlayer` determines thw piece for the opponents, and its own moves.
 /// It also allows a different implementation for calling Quarto.
@@ -14,63 +35,105 @@ pub trait Strategy {
     fn quarto(&self, board: &Board) -> bool;
 }
 
-
-pub struct DumbStrategy;
-pub struct NaiveStrategy;
-pub struct SmartStrategy;
+/// A `Strategy` that decides at random, and sometimes forgets to call Quarto.
+#[derive(Debug, Clone, Default)]
+pub struct DumbStrategy {
+    rng: SharedRng,
+}
+/// A `Strategy` that decides at random.
+#[derive(Debug, Clone, Default)]
+pub struct NaiveStrategy {
+    rng: SharedRng,
+}
+/// A `Strategy` that searches ahead with negamax and alpha-beta pruning.
+pub struct SmartStrategy {
+    /// The number of full turns (placing a piece and handing over the next one) to look ahead.
+    depth: u8,
+    /// Who to report the progress of the search to.
+    reporter: Option<Box<dyn ProgressReporter>>,
+    /// The transposition table, which can be shared with other strategies.
+    table: Option<Arc<Mutex<TranspositionTable>>>,
+    /// Scores the positions where the search stops, which are even without one.
+    evaluator: Option<Arc<dyn Evaluator>>,
+    /// Stops a running search when cancelled.
+    cancel: CancelToken,
+    /// How freely to pick worse decisions, 0 always picks the best one.
+    temperature: f64,
+    /// The chance of a random decision instead of a searched one.
+    blunder: f64,
+    /// Draws the random decisions and the picks at a temperature.
+    rng: SharedRng,
+    /// Split the root of every search over threads, without the reporter and the table.
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+}
 pub struct DeterministicStrategy;
 
+/// A `Strategy` that uses Monte Carlo Tree Search with random playouts.
+pub struct MctsStrategy {
+    budget: MctsBudget,
+    /// The exploration constant of the upper confidence bound.
+    exploration: f64,
+    /// Stops a running search when cancelled.
+    cancel: CancelToken,
+    /// Seeds the random playouts of every search.
+    rng: SharedRng,
+}
+
+impl DumbStrategy {
+    /// Create a new `DumbStrategy` with its own random generator.
+    pub fn new() -> Self {
+        DumbStrategy::default()
+    }
+
+    /// Draw the decisions from `rng`, for example a seeded one.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+}
+
 impl Strategy for DumbStrategy {
     /// Select a random piece for the opponent.
     fn get_piece(&self, board: &Board) -> Option<u8> {
-        let valid_pieces = board.valid_pieces();
-        if valid_pieces.is_empty() {
-            return None;
-        }
-        let i = fastrand::usize(..valid_pieces.len());
-        Some(valid_pieces[i])
+        self.rng.choose(&board.valid_pieces())
     }
 
     /// Select a random place to put the piece on.
     /// This implementation just ignores what piece to place now.
     fn get_move(&self, board: &Board, _: u8) -> Option<u8> {
-        let empty_spaces = board.empty_spaces();
-        if empty_spaces.is_empty() {
-            return None;
-        }
-        let i = fastrand::usize(..empty_spaces.len());
-        Some(empty_spaces[i])
+        self.rng.choose(&board.empty_spaces())
     }
 
     /// Be dumb and do not call Quarto on 1/10 of the winning moments.
     fn quarto(&self, board: &Board) -> bool {
-        if board.has_winner() && fastrand::usize(0..10) != 0 {
-            return true;
-        }
-        false
+        board.has_winner() && self.rng.usize(0..10) != 0
+    }
+}
+
+impl NaiveStrategy {
+    /// Create a new `NaiveStrategy` with its own random generator.
+    pub fn new() -> Self {
+        NaiveStrategy::default()
+    }
+
+    /// Draw the decisions from `rng`, for example a seeded one.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
     }
 }
 
 impl Strategy for NaiveStrategy {
     /// Select a random piece for the opponent.
     fn get_piece(&self, board: &Board) -> Option<u8> {
-        let valid_pieces = board.valid_pieces();
-        if valid_pieces.is_empty() {
-            return None;
-        }
-        let i = fastrand::usize(..valid_pieces.len());
-        Some(valid_pieces[i])
+        self.rng.choose(&board.valid_pieces())
     }
 
     /// Select a random place to put the piece on.
     /// This implementation just ignores what piece to place now.
     fn get_move(&self, board: &Board, _: u8) -> Option<u8> {
-        let empty_spaces = board.empty_spaces();
-        if empty_spaces.is_empty() {
-            return None;
-        }
-        let i = fastrand::usize(..empty_spaces.len());
-        Some(empty_spaces[i])
+        self.rng.choose(&board.empty_spaces())
     }
 
     /// Always call Quarto when the board has a winner.
@@ -79,17 +142,196 @@ impl Strategy for NaiveStrategy {
     }
 }
 
+impl SmartStrategy {
+    /// Create a new `SmartStrategy` that looks `depth` turns ahead.
+    /// A depth of 1 already avoids handing over winning pieces, every extra turn multiplies the work by roughly 200.
+    pub fn new(depth: u8) -> Self {
+        SmartStrategy {
+            depth,
+            reporter: None,
+            table: None,
+            evaluator: None,
+            cancel: CancelToken::new(),
+            temperature: 0.0,
+            blunder: 0.0,
+            rng: SharedRng::new(),
+            #[cfg(feature = "parallel")]
+            parallel: false,
+        }
+    }
+
+    /// Pick decisions at random, weighted by their score, instead of always the best one.
+    /// At a temperature of 1 a decision that is a won game worse is picked about e times less often.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature.max(0.0);
+        self
+    }
+
+    /// Make a random decision with chance `probability`, without searching.
+    pub fn with_blunder_probability(mut self, probability: f64) -> Self {
+        self.blunder = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Draw the random decisions from `rng`, for example a seeded one.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Search the decisions at the root on all cores.
+    /// A parallel search does not use the reporter, the table or the evaluator.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Stop searching when `cancel` is cancelled, the best decision found so far is played.
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Get a token that stops the searches of this strategy.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Reuse the scores of positions that were already searched, stored in `table`.
+    /// The table may be shared with other strategies, the search holds the lock while it runs.
+    pub fn with_table(mut self, table: Arc<Mutex<TranspositionTable>>) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Score the positions at the end of every search with `evaluator`, like a trained model.
+    /// The evaluator may be shared with other strategies, a shared table should use the same one.
+    pub fn with_evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+
+    /// Report the progress of every search to `reporter`, for example a channel to a thinking indicator.
+    pub fn with_reporter<R: ProgressReporter + 'static>(mut self, reporter: R) -> Self {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    /// Run `search` with a `Searcher` that uses the reporter, evaluator and table of this strategy, if any.
+    /// A poisoned table is not used.
+    fn search<T, F>(&self, search: F) -> T
+    where
+        F: FnOnce(&mut Searcher) -> T,
+    {
+        let mut searcher = Searcher::new().with_cancel(&self.cancel);
+        if let Some(reporter) = &self.reporter {
+            searcher = searcher.with_reporter(reporter.as_ref());
+        }
+        if let Some(evaluator) = &self.evaluator {
+            searcher = searcher.with_evaluator(evaluator.as_ref());
+        }
+        let mut guard = self.table.as_ref().and_then(|t| t.lock().ok());
+        if let Some(table) = guard.as_mut() {
+            searcher = searcher.with_table(table);
+        }
+        search(&mut searcher)
+    }
+
+    /// Rank every placement of `piece` with the piece to hand over after it, best first, for hints and analysis.
+    /// Searches as deep as this strategy, with its table, and without temperature or blunders.
+    pub fn rank_moves(&self, board: &Board, piece: u8) -> Vec<RankedMove> {
+        self.search(|s| s.rank_moves(board, piece, self.depth))
+    }
+
+    /// Rank every piece to hand over on `board`, best first, like `rank_moves`.
+    pub fn rank_pieces(&self, board: &Board) -> Vec<(u8, i32)> {
+        self.search(|s| s.rank_pieces(board, self.depth))
+    }
+
+    /// The number of turns this strategy looks ahead.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The temperature used to pick between decisions.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// The chance of a random decision.
+    pub fn blunder_probability(&self) -> f64 {
+        self.blunder
+    }
+
+    /// Decide if this decision should be a random one.
+    fn blunder(&self) -> bool {
+        self.blunder > 0.0 && self.rng.f64() < self.blunder
+    }
+
+    /// Pick one of the scored decisions at random, with the weights of a softmax over the scores at the temperature.
+    fn sample(&self, scores: &[(u8, i32)]) -> Option<u8> {
+        let best = scores.iter().map(|&(_, score)| score).max()?;
+        let weights: Vec<f64> = scores
+            .iter()
+            .map(|&(_, score)| {
+                (f64::from(score - best) / (self.temperature * f64::from(search::WIN))).exp()
+            })
+            .collect();
+        let mut roll = self.rng.f64() * weights.iter().sum::<f64>();
+        for (&(decision, _), weight) in scores.iter().zip(weights) {
+            if roll < weight {
+                return Some(decision);
+            }
+            roll -= weight;
+        }
+        scores.last().map(|&(decision, _)| decision)
+    }
+}
+
+impl Default for SmartStrategy {
+    fn default() -> Self {
+        SmartStrategy::new(2)
+    }
+}
+
 impl Strategy for SmartStrategy {
+    /// Select the piece that leaves the opponent with the worst position.
     fn get_piece(&self, board: &Board) -> Option<u8> {
-        todo!("SmartStrategy not yet implemented!")
+        if self.blunder() {
+            return self.rng.choose(&board.valid_pieces());
+        }
+        if self.temperature > 0.0 {
+            return self.sample(&self.search(|s| s.piece_scores(board, self.depth)));
+        }
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            return parallel::deepen_piece(board, self.depth, &self.cancel).map(|(piece, _)| piece);
+        }
+        self.search(|s| s.deepen_piece(board, self.depth))
+            .map(|(piece, _)| piece)
     }
 
+    /// Select the place with the best score for the given piece.
     fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
-        todo!("SmartStrategy not yet implemented!")
+        if self.blunder() {
+            return self.rng.choose(&board.empty_spaces());
+        }
+        if self.temperature > 0.0 {
+            return self.sample(&self.search(|s| s.move_scores(board, piece, self.depth)));
+        }
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            return parallel::deepen_move(board, piece, self.depth, &self.cancel)
+                .map(|(index, _)| index);
+        }
+        self.search(|s| s.deepen_move(board, piece, self.depth))
+            .map(|(index, _)| index)
     }
 
+    /// Always call Quarto when the board has a winner.
     fn quarto(&self, board: &Board) -> bool {
-        todo!("SmartStrategy not yet implemented!")
+        board.has_winner()
     }
 }
 
@@ -117,4 +359,744 @@ impl Strategy for DeterministicStrategy {
     fn quarto(&self, board: &Board) -> bool {
         board.has_winner()
     }
-}
\ No newline at end of file
+}
+
+impl MctsStrategy {
+    /// Create a new `MctsStrategy` that searches within `budget` for every decision.
+    pub fn new(budget: MctsBudget) -> Self {
+        MctsStrategy {
+            budget,
+            exploration: std::f64::consts::SQRT_2,
+            cancel: CancelToken::new(),
+            rng: SharedRng::new(),
+        }
+    }
+
+    /// Seed the playouts from `rng`, for example a seeded one.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Stop searching when `cancel` is cancelled, the most visited decision so far is played.
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Get a token that stops the searches of this strategy.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Set the exploration constant, higher values try out less promising decisions more often.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+}
+
+impl Default for MctsStrategy {
+    fn default() -> Self {
+        MctsStrategy::new(MctsBudget::Iterations(5_000))
+    }
+}
+
+impl Strategy for MctsStrategy {
+    /// Select the piece the search hands over most often.
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        mcts::best_piece(
+            board,
+            self.budget,
+            self.exploration,
+            &self.cancel,
+            self.rng.fork(),
+        )
+    }
+
+    /// Take an immediate win, otherwise select the place the search visits most often.
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        if let Some((index, value)) = search::best_move(board, piece, 0)
+            && value >= search::WIN
+        {
+            return Some(index);
+        }
+        mcts::best_move(
+            board,
+            piece,
+            self.budget,
+            self.exploration,
+            &self.cancel,
+            self.rng.fork(),
+        )
+    }
+
+    /// Always call Quarto when the board has a winner.
+    fn quarto(&self, board: &Board) -> bool {
+        board.has_winner()
+    }
+}
+
+/// Adjusts the error rate of an `AdaptiveStrategy` between games, based on the results of the human opponent.
+/// Every human win makes the opponent play better, every human loss makes it blunder more, so games stay close to a 50% win rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyController {
+    error_rate: f64,
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl DifficultyController {
+    /// Create a new controller starting at `error_rate`, adjusting in steps of `step` and never leaving `min..=max`.
+    /// The bounds are clamped to `0.0..=1.0`.
+    pub fn new(error_rate: f64, min: f64, max: f64, step: f64) -> Self {
+        let min = min.clamp(0.0, 1.0);
+        let max = max.clamp(min, 1.0);
+        DifficultyController {
+            error_rate: error_rate.clamp(min, max),
+            min,
+            max,
+            step: step.abs(),
+        }
+    }
+
+    /// The chance that the opponent plays a random move instead of its own choice.
+    pub fn error_rate(&self) -> f64 {
+        self.error_rate
+    }
+
+    /// Record the result of a finished game, where the human played on seat `human`.
+    /// Draws and errors leave the difficulty as it is, running out of time is a loss, and a win of a teammate is a win.
+    pub fn record_result(&mut self, result: &GameResult, human: usize) {
+        let Some(team) = result.winning_team() else {
+            return;
+        };
+        let delta = if team == human % 2 {
+            -self.step
+        } else {
+            self.step
+        };
+        self.error_rate = (self.error_rate + delta).clamp(self.min, self.max);
+    }
+}
+
+impl Default for DifficultyController {
+    /// Start halfway and move in steps of 10%.
+    fn default() -> Self {
+        DifficultyController::new(0.5, 0.0, 1.0, 0.1)
+    }
+}
+
+/// Records the result of every game it observes in a shared `DifficultyController`, for the human on seat `human`.
+#[derive(Debug, Clone)]
+pub struct DifficultyFeedback {
+    controller: Arc<Mutex<DifficultyController>>,
+    human: usize,
+}
+
+impl DifficultyFeedback {
+    /// Adjust `controller` to the results of the player on seat `human`.
+    pub fn new(controller: Arc<Mutex<DifficultyController>>, human: usize) -> Self {
+        DifficultyFeedback { controller, human }
+    }
+}
+
+impl GameObserver for DifficultyFeedback {
+    fn on_game_end(&self, result: GameResult, _record: &GameRecord) {
+        self.controller
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record_result(&result, self.human);
+    }
+}
+
+/// How strong a computer opponent should play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    /// All difficulties, from easy to expert.
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    /// Build the search that plays at this difficulty.
+    /// Easy and medium look one turn ahead but make mistakes, hard and expert never pick a worse decision they can see.
+    pub fn strategy(self) -> SmartStrategy {
+        match self {
+            Difficulty::Easy => SmartStrategy::new(1)
+                .with_temperature(0.5)
+                .with_blunder_probability(0.3),
+            Difficulty::Medium => SmartStrategy::new(1)
+                .with_temperature(0.1)
+                .with_blunder_probability(0.05),
+            Difficulty::Hard => SmartStrategy::new(2),
+            Difficulty::Expert => SmartStrategy::new(4),
+        }
+    }
+
+    /// Build the computer player at this difficulty, deciding with `rng` and searching with `table` if any.
+    /// The expert also solves the endgame exactly, so it never misses a forced win or draw there.
+    /// Earlier in the game it only searches four turns ahead, it is not a perfect player.
+    pub fn player(
+        self,
+        rng: &SharedRng,
+        table: Option<Arc<Mutex<TranspositionTable>>>,
+    ) -> Box<dyn Player + Send> {
+        self.build(rng, table, None)
+    }
+
+    /// Build the computer player at this difficulty that also blunders as often as `controller` says,
+    /// to adapt to a human over a series of games.
+    pub fn adaptive_player(
+        self,
+        rng: &SharedRng,
+        table: Option<Arc<Mutex<TranspositionTable>>>,
+        controller: Arc<Mutex<DifficultyController>>,
+    ) -> Box<dyn Player + Send> {
+        self.build(rng, table, Some(controller))
+    }
+
+    /// Build the computer player, adaptive with a controller.
+    fn build(
+        self,
+        rng: &SharedRng,
+        table: Option<Arc<Mutex<TranspositionTable>>>,
+        controller: Option<Arc<Mutex<DifficultyController>>>,
+    ) -> Box<dyn Player + Send> {
+        let strategy = self.strategy().with_rng(rng.clone());
+        let strategy = match table {
+            Some(table) => strategy.with_table(table),
+            None => strategy,
+        };
+        match self {
+            Difficulty::Expert => computer(EndgameExact::new(strategy), controller, rng),
+            _ => computer(strategy, controller, rng),
+        }
+    }
+}
+
+/// A computer player of `strategy`, that blunders with the error rate of `controller` if there is one.
+fn computer<S: Strategy + Send + 'static>(
+    strategy: S,
+    controller: Option<Arc<Mutex<DifficultyController>>>,
+    rng: &SharedRng,
+) -> Box<dyn Player + Send> {
+    match controller {
+        Some(controller) => Box::new(ComputerPlayer::new(
+            AdaptiveStrategy::new(strategy, DifficultyController::default())
+                .with_controller(controller)
+                .with_rng(rng.clone()),
+        )),
+        None => Box::new(ComputerPlayer::new(strategy)),
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+            Difficulty::Expert => write!(f, "expert"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            // Profiles written before the rename still name the expert perfect.
+            "expert" | "perfect" => Ok(Difficulty::Expert),
+            _ => Err(format!(
+                "expected easy, medium, hard or expert, got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// A `Strategy` that wraps another one, but plays a random piece or move with the error rate of its `DifficultyController`.
+pub struct AdaptiveStrategy<S: Strategy> {
+    inner: S,
+    controller: Arc<Mutex<DifficultyController>>,
+    rng: SharedRng,
+}
+
+impl<S: Strategy> AdaptiveStrategy<S> {
+    /// Create a new `AdaptiveStrategy` around `inner`, controlled by `controller`.
+    pub fn new(inner: S, controller: DifficultyController) -> Self {
+        AdaptiveStrategy {
+            inner,
+            controller: Arc::new(Mutex::new(controller)),
+            rng: SharedRng::new(),
+        }
+    }
+
+    /// Draw the blunders from `rng`, for example a seeded one.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Share `controller` with others, like the opponents of a series of games that adapts to the human.
+    pub fn with_controller(mut self, controller: Arc<Mutex<DifficultyController>>) -> Self {
+        self.controller = controller;
+        self
+    }
+
+    /// Get a handle to the controller, so results can be recorded while the strategy is owned by a player.
+    pub fn controller(&self) -> Arc<Mutex<DifficultyController>> {
+        Arc::clone(&self.controller)
+    }
+
+    /// Decide if this decision should be a deliberate mistake.
+    fn blunder(&self) -> bool {
+        let error_rate = match self.controller.lock() {
+            Ok(controller) => controller.error_rate(),
+            Err(_) => return false,
+        };
+        self.rng.f64() < error_rate
+    }
+}
+
+impl<S: Strategy> Strategy for AdaptiveStrategy<S> {
+    /// Select a random piece on a blunder, otherwise ask the inner strategy.
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        if self.blunder() {
+            return self.rng.choose(&board.valid_pieces());
+        }
+        self.inner.get_piece(board)
+    }
+
+    /// Select a random place on a blunder, otherwise ask the inner strategy.
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        if self.blunder() {
+            return self.rng.choose(&board.empty_spaces());
+        }
+        self.inner.get_move(board, piece)
+    }
+
+    fn quarto(&self, board: &Board) -> bool {
+        self.inner.quarto(board)
+    }
+}
+
+/// A `Strategy` that hands over pieces with one strategy and places them with another.
+/// This isolates which half of the decisions a heuristic improves.
+pub struct SplitStrategy<G: Strategy, P: Strategy> {
+    give: G,
+    place: P,
+}
+
+impl<G: Strategy, P: Strategy> SplitStrategy<G, P> {
+    /// Create a new `SplitStrategy` that gives pieces with `give` and places them with `place`.
+    pub fn new(give: G, place: P) -> Self {
+        SplitStrategy { give, place }
+    }
+}
+
+impl<G: Strategy, P: Strategy> Strategy for SplitStrategy<G, P> {
+    /// Ask the strategy that gives pieces.
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        self.give.get_piece(board)
+    }
+
+    /// Ask the strategy that places pieces.
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        self.place.get_move(board, piece)
+    }
+
+    /// Quarto is called right after placing, so the placing strategy decides.
+    fn quarto(&self, board: &Board) -> bool {
+        self.place.quarto(board)
+    }
+}
+
+/// A `Strategy` that wraps another one, but solves the game exactly once few enough cells are empty.
+/// Late in the game the exact search is cheap, and it never misses a forced win or draw.
+pub struct EndgameExact<S: Strategy> {
+    inner: S,
+    /// Solve exactly when at most this many cells are empty.
+    empties: u8,
+    /// Kept between decisions, so later positions of the same endgame are already proven.
+    solver: Mutex<Solver>,
+}
+
+impl<S: Strategy> EndgameExact<S> {
+    /// Solve exactly from this many empty cells on, which takes a few milliseconds, one more empty cell takes about a hundred times longer.
+    pub const DEFAULT_EMPTIES: u8 = 7;
+
+    /// Create a new `EndgameExact` around `inner`, solving from `DEFAULT_EMPTIES` empty cells on.
+    pub fn new(inner: S) -> Self {
+        EndgameExact {
+            inner,
+            empties: Self::DEFAULT_EMPTIES,
+            solver: Mutex::new(Solver::new()),
+        }
+    }
+
+    /// Solve exactly when at most `empties` cells are empty.
+    pub fn with_empties(mut self, empties: u8) -> Self {
+        self.empties = empties;
+        self
+    }
+
+    /// Solve with `solver`, for example one whose cache fits in a `MemoryBudget`.
+    pub fn with_solver(mut self, solver: Solver) -> Self {
+        self.solver = Mutex::new(solver);
+        self
+    }
+
+    /// Check if `board` is late enough to be solved exactly.
+    fn solves(&self, board: &Board) -> bool {
+        board.empty_spaces().len() <= self.empties as usize
+    }
+
+    /// Pick the decision with the best outcome, the first one among equal outcomes.
+    fn best(outcomes: Vec<(u8, Outcome)>) -> Option<u8> {
+        outcomes
+            .into_iter()
+            .rev()
+            .max_by_key(|&(_, outcome)| outcome)
+            .map(|(decision, _)| decision)
+    }
+}
+
+impl<S: Strategy> Strategy for EndgameExact<S> {
+    /// Select the piece with the best proven outcome in the endgame, otherwise ask the inner strategy.
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        if self.solves(board)
+            && let Ok(mut solver) = self.solver.lock()
+        {
+            return Self::best(solver.piece_outcomes(board));
+        }
+        self.inner.get_piece(board)
+    }
+
+    /// Select the place with the best proven outcome in the endgame, otherwise ask the inner strategy.
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        if self.solves(board)
+            && let Ok(mut solver) = self.solver.lock()
+        {
+            return Self::best(solver.placement_outcomes(board, piece));
+        }
+        self.inner.get_move(board, piece)
+    }
+
+    fn quarto(&self, board: &Board) -> bool {
+        self.inner.quarto(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Ruleset,
+        harness::{assert_never_blunders, random_positions},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_smart_takes_square_win() {
+        let board = Board::new()
+            .with_ruleset(Ruleset::Advanced)
+            .apply_moves(&[(8, 0), (12, 1), (10, 4)])
+            .unwrap();
+        let strategy = SmartStrategy::new(1);
+        assert_eq!(strategy.get_move(&board, 9), Some(5));
+        assert_eq!(strategy.get_piece(&board).map(|piece| piece & 8), Some(0));
+        let ranked = strategy.rank_moves(&board, 9);
+        assert_eq!((ranked[0].index, ranked[0].give), (5, None));
+        assert_eq!(ranked.len(), 13);
+        assert_eq!(strategy.rank_pieces(&board)[0].0 & 8, 0);
+    }
+
+    #[test]
+    fn test_smart_never_blunders() {
+        assert_never_blunders(
+            &SmartStrategy::new(1),
+            &random_positions(20, 12, &SharedRng::seeded(1)),
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_smart_never_blunders() {
+        assert_never_blunders(
+            &SmartStrategy::new(1).with_parallel(),
+            &random_positions(20, 12, &SharedRng::seeded(1)),
+        );
+    }
+
+    #[test]
+    fn test_split_asks_each_half() {
+        let strategy = SplitStrategy::new(DeterministicStrategy, SmartStrategy::new(1));
+        for board in random_positions(10, 12, &SharedRng::seeded(1)) {
+            assert_eq!(
+                strategy.get_piece(&board),
+                DeterministicStrategy.get_piece(&board)
+            );
+            if let Some(&piece) = board.valid_pieces().first() {
+                assert_eq!(
+                    strategy.get_move(&board, piece),
+                    SmartStrategy::new(1).get_move(&board, piece)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cold_smart_never_blunders() {
+        let strategy = SmartStrategy::new(1).with_temperature(0.01);
+        assert_never_blunders(&strategy, &random_positions(20, 12, &SharedRng::seeded(1)));
+    }
+
+    #[test]
+    fn test_hot_smart_varies() {
+        let strategy = SmartStrategy::new(0).with_temperature(100.0);
+        let pieces: std::collections::HashSet<Option<u8>> =
+            (0..50).map(|_| strategy.get_piece(&Board::new())).collect();
+        assert!(pieces.len() > 1);
+    }
+
+    #[test]
+    fn test_always_blunders() {
+        let strategy = SmartStrategy::new(1).with_blunder_probability(1.0);
+        let board = crate::test_util::open_row();
+        let moves: std::collections::HashSet<Option<u8>> =
+            (0..50).map(|_| strategy.get_move(&board, 0b1001)).collect();
+        assert!(moves.len() > 1);
+    }
+
+    #[test]
+    fn test_difficulty_round_trip() {
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+        }
+        assert!("impossible".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn test_difficulty_strategies() {
+        assert!(Difficulty::Easy.strategy().blunder_probability() > 0.0);
+        assert_eq!(Difficulty::Hard.strategy().temperature(), 0.0);
+        assert!(Difficulty::Expert.strategy().depth() > Difficulty::Hard.strategy().depth());
+        assert_eq!("perfect".parse(), Ok(Difficulty::Expert));
+        // With seven empty cells the expert hands over a piece with the best proven outcome.
+        let board = Board::new()
+            .apply_moves(&[
+                (0, 0),
+                (15, 5),
+                (3, 10),
+                (12, 15),
+                (5, 1),
+                (10, 6),
+                (6, 11),
+                (9, 12),
+                (1, 3),
+            ])
+            .unwrap();
+        let piece = Difficulty::Expert
+            .player(&SharedRng::seeded(1), None)
+            .get_piece(&board);
+        let outcomes = Solver::new().piece_outcomes(&board);
+        let best = outcomes.iter().map(|&(_, outcome)| outcome).max();
+        assert!(outcomes.contains(&(piece.unwrap(), best.unwrap())));
+    }
+
+    #[test]
+    fn test_endgame_exact_finds_proven_outcome() {
+        let strategy = EndgameExact::new(DumbStrategy::new()).with_empties(6);
+        for _ in 0..10 {
+            let mut board = Board::new();
+            while board.empty_spaces().len() > 6 && !board.game_over() {
+                let piece = DumbStrategy::new().get_piece(&board).unwrap();
+                let index = DumbStrategy::new().get_move(&board, piece).unwrap();
+                board.put_piece(piece, index).unwrap();
+            }
+            if board.game_over() {
+                continue;
+            }
+            let mut solver = Solver::new();
+            if let Some(piece) = strategy.get_piece(&board) {
+                let outcomes = solver.piece_outcomes(&board);
+                let best = outcomes.iter().map(|&(_, o)| o).max();
+                assert_eq!(
+                    outcomes.iter().find(|&&(p, _)| p == piece).map(|&(_, o)| o),
+                    best
+                );
+                let index = strategy.get_move(&board, piece).unwrap();
+                let outcomes = solver.placement_outcomes(&board, piece);
+                let best = outcomes.iter().map(|&(_, o)| o).max();
+                assert_eq!(
+                    outcomes.iter().find(|&&(i, _)| i == index).map(|&(_, o)| o),
+                    best
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_endgame_exact_uses_inner_early() {
+        let strategy = EndgameExact::new(DeterministicStrategy).with_empties(4);
+        assert_eq!(strategy.get_piece(&Board::new()), Some(0));
+        assert_eq!(strategy.get_move(&Board::new(), 0), Some(0));
+    }
+
+    #[test]
+    fn test_mcts_plays_full_game() {
+        let strategy = MctsStrategy::new(MctsBudget::Iterations(200));
+        let mut board = Board::new();
+        while !board.game_over() {
+            let piece = strategy.get_piece(&board).unwrap();
+            let index = strategy.get_move(&board, piece).unwrap();
+            assert!(board.put_piece(piece, index).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_smart_reports_progress() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let strategy = SmartStrategy::new(1).with_reporter(tx);
+        let mut board = Board::new();
+        for (piece, index) in [(0, 0), (5, 5), (10, 10), (3, 12)] {
+            board.put_piece(piece, index).unwrap();
+        }
+        let piece = strategy.get_piece(&board);
+        let last = rx.try_iter().last().unwrap();
+        assert_eq!(last.depth, 1);
+        assert_eq!(piece, Some(last.best));
+    }
+
+    #[test]
+    fn test_smart_with_shared_table_never_blunders() {
+        let table = Arc::new(Mutex::new(TranspositionTable::new(
+            1 << 12,
+            crate::transposition::Replacement::DepthPreferred,
+        )));
+        let first = SmartStrategy::new(1).with_table(Arc::clone(&table));
+        let second = SmartStrategy::new(2).with_table(Arc::clone(&table));
+        let positions = random_positions(10, 12, &SharedRng::seeded(1));
+        assert_never_blunders(&first, &positions);
+        assert_never_blunders(&second, &positions);
+        assert!(!table.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_smart_full_board() {
+        let mut board = Board::new();
+        for i in 0..16 {
+            board.put_piece(i, i).unwrap();
+        }
+        let strategy = SmartStrategy::default();
+        assert_eq!(strategy.get_piece(&board), None);
+        assert_eq!(strategy.get_move(&board, 0), None);
+    }
+
+    #[test]
+    fn test_controller_human_win_makes_harder() {
+        let mut controller = DifficultyController::new(0.5, 0.0, 1.0, 0.1);
+        controller.record_result(&GameResult::Win(0), 0);
+        assert!((controller.error_rate() - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_controller_human_loss_makes_easier() {
+        let mut controller = DifficultyController::new(0.5, 0.0, 1.0, 0.1);
+        controller.record_result(&GameResult::Win(1), 0);
+        assert!((controller.error_rate() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_controller_teammate_win_makes_harder() {
+        let mut controller = DifficultyController::new(0.5, 0.0, 1.0, 0.1);
+        controller.record_result(&GameResult::Win(2), 0);
+        controller.record_result(&GameResult::Timeout(3), 0);
+        assert!((controller.error_rate() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_controller_draw_and_error_unchanged() {
+        let mut controller = DifficultyController::new(0.5, 0.0, 1.0, 0.1);
+        controller.record_result(&GameResult::Draw, 0);
+        controller.record_result(&GameResult::Error, 0);
+        assert_eq!(controller.error_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_controller_bounded() {
+        let mut controller = DifficultyController::new(0.5, 0.2, 0.8, 0.25);
+        for _ in 0..10 {
+            controller.record_result(&GameResult::Win(0), 0);
+        }
+        assert_eq!(controller.error_rate(), 0.2);
+        for _ in 0..10 {
+            controller.record_result(&GameResult::Win(1), 0);
+        }
+        assert_eq!(controller.error_rate(), 0.8);
+    }
+
+    #[test]
+    fn test_controller_clamps_construction() {
+        let controller = DifficultyController::new(2.0, -1.0, 3.0, 0.1);
+        assert_eq!(controller.error_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_feedback_adapts_the_opponent_after_every_game() {
+        let controller = Arc::new(Mutex::new(DifficultyController::default()));
+        let mut expected = DifficultyController::default();
+        for seed in 0..3 {
+            let rng = SharedRng::seeded(seed);
+            let opponent = Difficulty::Medium.adaptive_player(&rng, None, controller.clone());
+            let result = crate::QuartoGame::new(
+                Box::new(ComputerPlayer::new(DumbStrategy::new().with_rng(rng))),
+                opponent,
+            )
+            .with_observer(DifficultyFeedback::new(controller.clone(), 0))
+            .play();
+            expected.record_result(&result, 0);
+            assert_eq!(*controller.lock().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_without_errors_follows_inner() {
+        let strategy = AdaptiveStrategy::new(
+            DeterministicStrategy,
+            DifficultyController::new(0.0, 0.0, 0.0, 0.1),
+        );
+        let board = Board::new();
+        assert_eq!(strategy.get_piece(&board), Some(0));
+        assert_eq!(strategy.get_move(&board, 0), Some(0));
+    }
+
+    #[test]
+    fn test_adaptive_controller_shared() {
+        let strategy =
+            AdaptiveStrategy::new(DeterministicStrategy, DifficultyController::default());
+        strategy
+            .controller()
+            .lock()
+            .unwrap()
+            .record_result(&GameResult::Win(0), 0);
+        assert!((strategy.controller.lock().unwrap().error_rate() - 0.4).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,209 @@
+// Symmetries of the Quarto board.
+// Some permutations of the indices map every row, column, and diagonal onto another line, so they do not change the value of a position.
+// Together with flipping an attribute of every piece, they allow positions to be reduced to a canonical form.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::{
+    board::{Board, PIECE_SIZE, Ruleset},
+    line::{INDEX_MASKS, Line},
+};
+
+/// A permutation of the 16 indices: the piece on index `i` moves to index `p[i]`.
+pub type Permutation = [u8; 16];
+
+/// Check if `permutation` maps every line of `Line::ALL` onto one of them.
+/// A permutation maps different lines onto different cells, so they are all hit once.
+fn preserves_lines(permutation: &Permutation) -> bool {
+    let lines = &INDEX_MASKS[..Line::ALL.len()];
+    Line::ALL.iter().all(|line| {
+        let mapped = line
+            .indices()
+            .iter()
+            .fold(0u16, |mask, &i| mask | 1 << permutation[i as usize]);
+        lines.contains(&mapped)
+    })
+}
+
+/// The 32 permutations of the indices that preserve the lines of the board, starting with the identity.
+/// These are generated from permuting rows and columns in the same way, mirroring, and transposing.
+pub fn symmetries() -> &'static [Permutation] {
+    static SYMMETRIES: OnceLock<Vec<Permutation>> = OnceLock::new();
+    SYMMETRIES.get_or_init(|| {
+        let mut result: Vec<Permutation> = Vec::new();
+        let mut orders: Vec<[u8; 4]> = Vec::new();
+        for a in 0..4u8 {
+            for b in 0..4u8 {
+                for c in 0..4u8 {
+                    for d in 0..4u8 {
+                        let order = [a, b, c, d];
+                        if (0..4u8).all(|x| order.contains(&x)) {
+                            orders.push(order);
+                        }
+                    }
+                }
+            }
+        }
+        for order in orders {
+            for mirror in [false, true] {
+                for transpose in [false, true] {
+                    let mut permutation = [0u8; 16];
+                    for index in 0..16u8 {
+                        let row = order[(index / 4) as usize];
+                        let mut column = order[(index % 4) as usize];
+                        if mirror {
+                            column = 3 - column;
+                        }
+                        let (row, column) = if transpose {
+                            (column, row)
+                        } else {
+                            (row, column)
+                        };
+                        permutation[index as usize] = row * 4 + column;
+                    }
+                    if preserves_lines(&permutation) && !result.contains(&permutation) {
+                        result.push(permutation);
+                    }
+                }
+            }
+        }
+        result
+    })
+}
+
+/// The symmetries that map every line of `lines` onto one of them.
+fn preserving(lines: &[Line]) -> Vec<Permutation> {
+    let groups: Vec<[u8; 4]> = lines
+        .iter()
+        .map(|line| {
+            let mut indices = line.indices();
+            indices.sort();
+            indices
+        })
+        .collect();
+    symmetries()
+        .iter()
+        .filter(|permutation| {
+            groups.iter().all(|group| {
+                let mut mapped = group.map(|i| permutation[i as usize]);
+                mapped.sort();
+                groups.contains(&mapped)
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// The symmetries that also map the lines of `ruleset` onto each other.
+/// With the advanced rules only the rotations and reflections of the board are left.
+/// A custom rule keeps the symmetries that preserve its lines, which are worked out once per rule.
+pub fn symmetries_for(ruleset: Ruleset) -> &'static [Permutation] {
+    static ADVANCED: OnceLock<Vec<Permutation>> = OnceLock::new();
+    static CUSTOM: OnceLock<Mutex<HashMap<Ruleset, &'static [Permutation]>>> = OnceLock::new();
+    match ruleset {
+        Ruleset::Classic => symmetries(),
+        Ruleset::Advanced => ADVANCED.get_or_init(|| preserving(&Line::ADVANCED)),
+        // Custom rules live for the whole program, so their symmetries may as well.
+        Ruleset::Custom(rule) => CUSTOM
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(ruleset)
+            .or_insert_with(|| preserving(rule.lines()).leak()),
+    }
+}
+
+/// Move every piece on `board` according to `permutation`.
+pub fn transform(board: &Board, permutation: &Permutation) -> Board {
+    let items = board.items();
+    let mut result: u128 = 0;
+    for index in 0..16u8 {
+        let cell = (items >> (PIECE_SIZE * (15 - index))) & 0xff;
+        result |= cell << (PIECE_SIZE * (15 - permutation[index as usize]));
+    }
+    Board::from_u128(result).with_ruleset(board.ruleset())
+}
+
+/// Flip the attributes in `mask` of every piece on `board`.
+/// Flipping an attribute for all pieces (including the one in hand) does not change the value of a position.
+pub fn flip_attributes(board: &Board, mask: u8) -> Board {
+    let items = board.items();
+    let mut result = items;
+    for index in 0..16u8 {
+        let shift = PIECE_SIZE * (15 - index);
+        if (items >> shift) & 1 == 1 {
+            result ^= ((mask & 0b1111) as u128) << (shift + 4);
+        }
+    }
+    Board::from_u128(result).with_ruleset(board.ruleset())
+}
+
+/// The canonical form of a position where `piece` has to be placed on `board`.
+/// Attributes are flipped so the piece in hand becomes piece 0, then the smallest board over all symmetries is taken.
+/// Positions with the same canonical form have the same value, only symmetries that keep the squares count with the advanced rules.
+pub fn canonical(board: &Board, piece: u8) -> Board {
+    let flipped = flip_attributes(board, piece);
+    symmetries_for(board.ruleset())
+        .iter()
+        .map(|p| transform(&flipped, p))
+        .min_by_key(|b| b.items())
+        .unwrap_or(flipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetry_count() {
+        assert_eq!(symmetries().len(), 32);
+        assert_eq!(symmetries()[0], core::array::from_fn(|i| i as u8));
+    }
+
+    #[test]
+    fn test_advanced_symmetries_keep_squares() {
+        assert_eq!(symmetries_for(Ruleset::Advanced).len(), 8);
+        let board = Board::new()
+            .with_ruleset(Ruleset::Advanced)
+            .apply_moves(&[(8, 5), (12, 6), (10, 9), (9, 10)])
+            .unwrap();
+        assert!(board.has_winner());
+        for permutation in symmetries_for(Ruleset::Advanced) {
+            let other = transform(&board, permutation);
+            assert_eq!(other.ruleset(), Ruleset::Advanced);
+            assert!(other.has_winner());
+        }
+    }
+
+    #[test]
+    fn test_transform_preserves_winner() {
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 5), (10, 10), (9, 15)])
+            .unwrap();
+        for permutation in symmetries() {
+            assert!(transform(&board, permutation).has_winner());
+        }
+    }
+
+    #[test]
+    fn test_flip_attributes_preserves_winner() {
+        let board = Board::new()
+            .apply_moves(&[(8, 0), (12, 1), (10, 2), (9, 3)])
+            .unwrap();
+        for mask in 0..16 {
+            assert!(flip_attributes(&board, mask).has_winner());
+        }
+    }
+
+    #[test]
+    fn test_canonical_same_for_symmetric_positions() {
+        let board = Board::new().apply_moves(&[(3, 0), (7, 6)]).unwrap();
+        for permutation in symmetries() {
+            let other = transform(&board, permutation);
+            assert_eq!(canonical(&board, 5), canonical(&other, 5));
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// Positions shared by the tests of several modules.
+
+use crate::Board;
+
+/// Three light pieces with a hole on the last row, the last cell of the row is still empty.
+/// The dark piece with a hole, `0b1001`, wins on index 15, and `0b0001` cannot.
+pub(crate) fn open_row() -> Board {
+    Board::new()
+        .apply_moves(&[(0b1000, 12), (0b1100, 13), (0b1010, 14)])
+        .unwrap()
+}
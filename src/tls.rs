@@ -0,0 +1,296 @@
+// Encrypted connections for the game server and its clients, built on rustls.
+// The server loads its certificate from PEM files, a client trusts the usual roots or pins the server's certificate.
+
+use std::{fs, io, net::TcpStream, path::Path, sync::Arc};
+
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, ServerConfig,
+    ServerConnection, SignatureScheme, StreamOwned,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{self, CryptoProvider},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject},
+};
+
+/// A TLS connection from a client to the server.
+pub type ClientStream = StreamOwned<ClientConnection, TcpStream>;
+/// A TLS connection accepted by the server.
+pub type ServerStream = StreamOwned<ServerConnection, TcpStream>;
+
+/// The SHA-256 fingerprint of a certificate.
+pub type Fingerprint = [u8; 32];
+
+fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// Compute the SHA-256 fingerprint of a DER encoded certificate.
+pub fn fingerprint(der: &[u8]) -> Fingerprint {
+    let digest = ring::digest::digest(&ring::digest::SHA256, der);
+    let mut fingerprint = [0; 32];
+    fingerprint.copy_from_slice(digest.as_ref());
+    fingerprint
+}
+
+/// Parse a fingerprint written as 64 hexadecimal digits, optionally separated by colons.
+pub fn parse_fingerprint(text: &str) -> Result<Fingerprint, String> {
+    let digits: Vec<u8> = text.bytes().filter(|&b| b != b':').collect();
+    if digits.len() != 64 {
+        return Err(format!(
+            "expected a SHA-256 fingerprint of 64 hex digits, got '{}'",
+            text
+        ));
+    }
+    let mut fingerprint = [0; 32];
+    for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks(2)) {
+        let pair =
+            std::str::from_utf8(pair).map_err(|_| format!("invalid fingerprint '{}'", text))?;
+        *byte = u8::from_str_radix(pair, 16)
+            .map_err(|_| format!("invalid hex digits '{}' in fingerprint", pair))?;
+    }
+    Ok(fingerprint)
+}
+
+/// Build the server side configuration from a PEM certificate chain and a PEM private key.
+pub fn server_config_from_pem(cert_pem: &[u8], key_pem: &[u8]) -> io::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid_data)?;
+    if certs.is_empty() {
+        return Err(invalid_data("no certificate found in the PEM data"));
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(invalid_data)?;
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(invalid_data)?;
+    Ok(Arc::new(config))
+}
+
+/// Build the server side configuration from the PEM files at `cert_path` and `key_path`.
+pub fn server_config(cert_path: &Path, key_path: &Path) -> io::Result<Arc<ServerConfig>> {
+    server_config_from_pem(&fs::read(cert_path)?, &fs::read(key_path)?)
+}
+
+/// Start the server side of the handshake on an accepted connection.
+/// The handshake completes on the first read or write.
+pub fn accept(stream: TcpStream, config: Arc<ServerConfig>) -> io::Result<ServerStream> {
+    let connection = ServerConnection::new(config).map_err(invalid_data)?;
+    Ok(StreamOwned::new(connection, stream))
+}
+
+/// How a client decides to trust the server.
+#[derive(Debug, Clone)]
+pub struct ClientTls {
+    roots: RootCertStore,
+    pin: Option<Fingerprint>,
+}
+
+impl ClientTls {
+    /// Trust the certificates signed by the usual web authorities.
+    pub fn new() -> Self {
+        ClientTls {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+            pin: None,
+        }
+    }
+
+    /// Also trust the certificates in `pem`, for a server with its own authority.
+    pub fn with_root_pem(mut self, pem: &[u8]) -> io::Result<Self> {
+        for cert in CertificateDer::pem_slice_iter(pem) {
+            self.roots
+                .add(cert.map_err(invalid_data)?)
+                .map_err(invalid_data)?;
+        }
+        Ok(self)
+    }
+
+    /// Only accept a server whose certificate has this fingerprint, whoever signed it.
+    /// This allows self-signed certificates, without trusting any other server.
+    pub fn with_pinned_fingerprint(mut self, pin: Fingerprint) -> Self {
+        self.pin = Some(pin);
+        self
+    }
+
+    /// Build the rustls configuration.
+    pub fn config(&self) -> Arc<ClientConfig> {
+        let config = match self.pin {
+            Some(pin) => ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+                    pin,
+                    provider: crypto::ring::default_provider(),
+                }))
+                .with_no_client_auth(),
+            None => ClientConfig::builder()
+                .with_root_certificates(self.roots.clone())
+                .with_no_client_auth(),
+        };
+        Arc::new(config)
+    }
+
+    /// Connect to the server at `address`, checking its certificate against `server_name`.
+    pub fn connect(&self, address: &str, server_name: &str) -> io::Result<ClientStream> {
+        let name = ServerName::try_from(server_name.to_string()).map_err(invalid_data)?;
+        let connection = ClientConnection::new(self.config(), name).map_err(invalid_data)?;
+        Ok(StreamOwned::new(connection, TcpStream::connect(address)?))
+    }
+}
+
+impl Default for ClientTls {
+    fn default() -> Self {
+        ClientTls::new()
+    }
+}
+
+/// Accepts exactly the server certificate with the pinned fingerprint.
+#[derive(Debug)]
+struct PinnedVerifier {
+    pin: Fingerprint,
+    provider: CryptoProvider,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if fingerprint(end_entity) == self.pin {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "the server certificate does not match the pinned fingerprint".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// A self-signed certificate for localhost, as PEM certificate, PEM key and fingerprint.
+    fn self_signed() -> (String, String, Fingerprint) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        (
+            certified.cert.pem(),
+            certified.signing_key.serialize_pem(),
+            fingerprint(certified.cert.der()),
+        )
+    }
+
+    /// Start a server that echoes one line over TLS, and return its address.
+    fn echo_server(cert: &str, key: &str) -> String {
+        let config = server_config_from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut stream = accept(socket, config).unwrap();
+            let mut line = String::new();
+            if BufReader::new(&mut stream).read_line(&mut line).is_ok() {
+                let _ = stream.write_all(line.as_bytes());
+                let _ = stream.flush();
+            }
+        });
+        address
+    }
+
+    fn echo(tls: &ClientTls, address: &str) -> io::Result<String> {
+        let mut stream = tls.connect(address, "localhost")?;
+        writeln!(stream, "0 give 3")?;
+        stream.flush()?;
+        let mut line = String::new();
+        BufReader::new(&mut stream).read_line(&mut line)?;
+        Ok(line)
+    }
+
+    #[test]
+    fn test_parse_fingerprint() {
+        let hex = "00:11:22:33:44:55:66:77:88:99:aa:bb:cc:dd:ee:ff".repeat(2);
+        let parsed = parse_fingerprint(&hex.replacen("ff0", "ff:0", 1)).unwrap();
+        assert_eq!(parsed[0], 0x00);
+        assert_eq!(parsed[15], 0xff);
+        assert_eq!(parsed[17], 0x11);
+        assert!(parse_fingerprint("abcd").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_pinned_fingerprint_accepts_self_signed() {
+        let (cert, key, pin) = self_signed();
+        let address = echo_server(&cert, &key);
+        let tls = ClientTls::new().with_pinned_fingerprint(pin);
+        assert_eq!(echo(&tls, &address).unwrap(), "0 give 3\n");
+    }
+
+    #[test]
+    fn test_wrong_pin_is_rejected() {
+        let (cert, key, pin) = self_signed();
+        let address = echo_server(&cert, &key);
+        let mut wrong = pin;
+        wrong[0] ^= 1;
+        let tls = ClientTls::new().with_pinned_fingerprint(wrong);
+        assert!(echo(&tls, &address).is_err());
+    }
+
+    #[test]
+    fn test_trusted_root() {
+        let (cert, key, _) = self_signed();
+        let address = echo_server(&cert, &key);
+        assert!(echo(&ClientTls::new(), &address).is_err());
+        let address = echo_server(&cert, &key);
+        let tls = ClientTls::new().with_root_pem(cert.as_bytes()).unwrap();
+        assert_eq!(echo(&tls, &address).unwrap(), "0 give 3\n");
+    }
+
+    #[test]
+    fn test_server_config_needs_a_certificate() {
+        let (_, key, _) = self_signed();
+        assert!(server_config_from_pem(b"", key.as_bytes()).is_err());
+    }
+}
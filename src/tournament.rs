@@ -0,0 +1,213 @@
+// Matches between two strategies over a set of openings.
+// Every opening is played twice with the sides swapped, so neither strategy profits from a lucky opening or from moving first.
+
+use crate::{
+    board::Board,
+    game::{GameResult, QuartoGame},
+    player::Player,
+    random::SharedRng,
+    symmetry,
+};
+
+/// The placements of an opening, played in turn from an empty board.
+pub type Opening = Vec<(u8, u8)>;
+
+/// The key of a board that is the same for all boards that are symmetric to it.
+/// Takes the smallest canonical form over every attribute flip, as an opening has no piece in hand.
+fn symmetry_key(board: &Board) -> u128 {
+    (0..16)
+        .map(|mask| symmetry::canonical(board, mask).items())
+        .min()
+        .unwrap_or(board.items())
+}
+
+/// Generate `count` random openings of `plies` placements that are not over yet.
+/// No two openings lead to positions that are symmetric to each other, so the set does not test the same position twice.
+/// Returns fewer openings if there are not enough distinct ones, like for very short openings.
+/// The placements are drawn from `rng`, so a seed gives the same openings.
+pub fn balanced_openings(count: usize, plies: u8, rng: &SharedRng) -> Vec<Opening> {
+    let plies = plies.min(15);
+    let mut openings: Vec<Opening> = Vec::new();
+    let mut keys: Vec<u128> = Vec::new();
+    // Give up after many duplicates in a row, the generator may have found every distinct opening.
+    let mut attempts = 0;
+    while openings.len() < count && attempts < 1000 {
+        attempts += 1;
+        let mut board = Board::new();
+        let mut opening: Opening = Vec::new();
+        for _ in 0..plies {
+            let pieces = board.valid_pieces();
+            let spaces = board.empty_spaces();
+            let placement = (
+                pieces[rng.usize(..pieces.len())],
+                spaces[rng.usize(..spaces.len())],
+            );
+            if board.put_piece(placement.0, placement.1).is_err() || board.game_over() {
+                break;
+            }
+            opening.push(placement);
+        }
+        let key = symmetry_key(&board);
+        if opening.len() == plies as usize && !keys.contains(&key) {
+            keys.push(key);
+            openings.push(opening);
+            attempts = 0;
+        }
+    }
+    openings
+}
+
+/// The points of the player in `seat` for a game: 1 for a win of their team, also when an opponent ran out of time, a half for a draw.
+fn points(result: GameResult, seat: usize) -> f64 {
+    match (result, result.winning_team()) {
+        (_, Some(team)) if team == seat % 2 => 1.0,
+        (GameResult::Draw, _) => 0.5,
+        _ => 0.0,
+    }
+}
+
+/// The results of an opening played twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairedResult {
+    pub opening: Opening,
+    /// The result with strategy A as the first player.
+    pub first: GameResult,
+    /// The result with strategy B as the first player.
+    pub second: GameResult,
+}
+
+impl PairedResult {
+    /// The points of strategy A over both games.
+    pub fn score_a(&self) -> f64 {
+        points(self.first, 0) + points(self.second, 1)
+    }
+
+    /// The points of strategy B over both games.
+    pub fn score_b(&self) -> f64 {
+        points(self.first, 1) + points(self.second, 0)
+    }
+}
+
+/// The results of a paired match between strategy A and strategy B.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairedReport {
+    pub pairs: Vec<PairedResult>,
+}
+
+impl PairedReport {
+    /// The total points of strategy A.
+    pub fn score_a(&self) -> f64 {
+        self.pairs.iter().map(PairedResult::score_a).sum()
+    }
+
+    /// The total points of strategy B.
+    pub fn score_b(&self) -> f64 {
+        self.pairs.iter().map(PairedResult::score_b).sum()
+    }
+
+    /// The number of pairs won by A, won by B, and split evenly.
+    pub fn pair_outcomes(&self) -> (usize, usize, usize) {
+        let mut outcomes = (0, 0, 0);
+        for pair in self.pairs.iter() {
+            match pair.score_a().total_cmp(&pair.score_b()) {
+                std::cmp::Ordering::Greater => outcomes.0 += 1,
+                std::cmp::Ordering::Less => outcomes.1 += 1,
+                std::cmp::Ordering::Equal => outcomes.2 += 1,
+            }
+        }
+        outcomes
+    }
+
+    /// The number of games that ended with an error.
+    pub fn errors(&self) -> usize {
+        self.pairs
+            .iter()
+            .flat_map(|p| [p.first, p.second])
+            .filter(|&r| r == GameResult::Error)
+            .count()
+    }
+}
+
+/// Play every opening twice between the players built by `make_a` and `make_b`, once with each of them moving first.
+pub fn play_paired<A, B, FA, FB>(make_a: FA, make_b: FB, openings: &[Opening]) -> PairedReport
+where
+    A: Player + 'static,
+    B: Player + 'static,
+    FA: Fn() -> A,
+    FB: Fn() -> B,
+{
+    let play = |game: QuartoGame, opening: &Opening| match game.with_opening(opening) {
+        Ok(mut game) => game.play_without_call(),
+        Err(_) => GameResult::Error,
+    };
+    let pairs = openings
+        .iter()
+        .map(|opening| PairedResult {
+            opening: opening.clone(),
+            first: play(QuartoGame::new(make_a(), make_b()), opening),
+            second: play(QuartoGame::new(make_b(), make_a()), opening),
+        })
+        .collect();
+    PairedReport { pairs }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        player::ComputerPlayer,
+        strategy::{DeterministicStrategy, DumbStrategy, SmartStrategy},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_openings_are_distinct() {
+        let openings = balanced_openings(20, 3, &SharedRng::seeded(1));
+        assert_eq!(openings.len(), 20);
+        let mut keys: Vec<u128> = openings
+            .iter()
+            .map(|o| symmetry_key(&Board::new().apply_moves(o).unwrap()))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 20);
+    }
+
+    #[test]
+    fn test_single_ply_openings_run_out() {
+        // Every first placement is symmetric to piece 0 on a corner or on an edge.
+        assert!(balanced_openings(5, 1, &SharedRng::seeded(1)).len() <= 2);
+    }
+
+    #[test]
+    fn test_symmetry_key_of_mirrored_board() {
+        let board = Board::new().apply_moves(&[(3, 0), (6, 5)]).unwrap();
+        let mirrored = Board::new().apply_moves(&[(3, 3), (6, 6)]).unwrap();
+        assert_eq!(symmetry_key(&board), symmetry_key(&mirrored));
+    }
+
+    #[test]
+    fn test_paired_scores_add_up() {
+        let openings = balanced_openings(4, 2, &SharedRng::seeded(1));
+        let report = play_paired(
+            || ComputerPlayer::new(SmartStrategy::new(1)),
+            || ComputerPlayer::new(DumbStrategy::new()),
+            &openings,
+        );
+        assert_eq!(report.pairs.len(), 4);
+        assert_eq!(report.errors(), 0);
+        assert_eq!(report.score_a() + report.score_b(), 8.0);
+        let (a, b, split) = report.pair_outcomes();
+        assert_eq!(a + b + split, 4);
+    }
+
+    #[test]
+    fn test_identical_deterministic_players_split_pairs() {
+        let report = play_paired(
+            || ComputerPlayer::new(DeterministicStrategy),
+            || ComputerPlayer::new(DeterministicStrategy),
+            &balanced_openings(3, 4, &SharedRng::seeded(1)),
+        );
+        assert_eq!(report.pair_outcomes(), (0, 0, 3));
+    }
+}
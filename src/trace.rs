@@ -0,0 +1,371 @@
+// Traces of the last games of a batch run, kept to reproduce strategy bugs.
+// When a game goes wrong, the whole buffer is dumped with the seeds and configurations needed to replay it.
+
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    io::{self, Write},
+    mem,
+    path::PathBuf,
+};
+
+use crate::{
+    game::{GameResult, QuartoGame},
+    random::SharedRng,
+    record::{GameRecord, Turn},
+};
+
+/// Everything needed to replay a game: the record, the seed of the random generator, and how the players were set up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameTrace {
+    pub seed: u64,
+    pub configs: [String; 2],
+    pub record: GameRecord,
+}
+
+impl fmt::Display for GameTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "player 0 {}", self.configs[0])?;
+        writeln!(f, "player 1 {}", self.configs[1])?;
+        write!(f, "{}", self.record.to_text())
+    }
+}
+
+/// Something that should never happen in a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anomaly {
+    /// The game stopped with an error: a player gave no decision or an illegal one.
+    GameAborted,
+    /// The recorded turn cannot be played on the board of the turns before it.
+    IllegalMove { turn: usize },
+    /// The record breaks a rule of the game, like the players taking turns.
+    InvariantFailure(String),
+    /// Replaying the record gives another result than the game reported.
+    ResultMismatch {
+        recorded: Option<GameResult>,
+        replayed: Option<GameResult>,
+    },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::GameAborted => write!(f, "game aborted with an error"),
+            Anomaly::IllegalMove { turn } => write!(f, "turn {} is illegal", turn),
+            Anomaly::InvariantFailure(message) => write!(f, "invariant failed: {}", message),
+            Anomaly::ResultMismatch { recorded, replayed } => write!(
+                f,
+                "recorded result {:?} differs from replayed result {:?}",
+                recorded, replayed
+            ),
+        }
+    }
+}
+
+/// Replay a record and check it for anomalies.
+pub fn check(record: &GameRecord) -> Option<Anomaly> {
+    if record.result() == Some(GameResult::Error) {
+        return Some(Anomaly::GameAborted);
+    }
+    for (i, turn) in record.turns().iter().enumerate() {
+        if turn.giver == turn.placer || turn.giver != i % 2 {
+            return Some(Anomaly::InvariantFailure(format!(
+                "turn {} is given by player {} and placed by player {}",
+                i, turn.giver, turn.placer
+            )));
+        }
+        if record.board_after(i + 1).is_none() {
+            return Some(Anomaly::IllegalMove { turn: i });
+        }
+    }
+    let board = record.final_board()?;
+    let replayed = if board.has_winner() {
+        record.turns().last().map(|t| GameResult::Win(t.placer))
+    } else if board.board_full() {
+        Some(GameResult::Draw)
+    } else {
+        None
+    };
+    // A flag can only fall while the game is still going.
+    if let (Some(GameResult::Timeout(_)), None) = (record.result(), replayed) {
+        return None;
+    }
+    if replayed != record.result() {
+        return Some(Anomaly::ResultMismatch {
+            recorded: record.result(),
+            replayed,
+        });
+    }
+    None
+}
+
+/// A ring buffer with the traces of the last games.
+#[derive(Debug, Clone)]
+pub struct TraceBuffer {
+    capacity: usize,
+    traces: VecDeque<GameTrace>,
+}
+
+impl TraceBuffer {
+    /// The memory of the trace of a full game in bytes, roughly.
+    pub const GAME_SIZE: usize = mem::size_of::<GameTrace>() + 16 * mem::size_of::<Turn>() + 2 * 16;
+
+    /// Create an empty buffer that keeps the last `capacity` games, or none at all for 0.
+    pub fn new(capacity: usize) -> Self {
+        TraceBuffer {
+            capacity,
+            traces: VecDeque::new(),
+        }
+    }
+
+    /// Add a trace, forgetting the oldest one if the buffer is full.
+    pub fn push(&mut self, trace: GameTrace) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.traces.len() == self.capacity {
+            self.traces.pop_front();
+        }
+        self.traces.push_back(trace);
+    }
+
+    /// The number of traces in the buffer.
+    pub fn len(&self) -> usize {
+        self.traces.len()
+    }
+
+    /// Check if the buffer has no traces.
+    pub fn is_empty(&self) -> bool {
+        self.traces.is_empty()
+    }
+
+    /// The memory the traces take in bytes, roughly.
+    pub fn memory_usage(&self) -> usize {
+        self.traces
+            .iter()
+            .map(|trace| {
+                mem::size_of::<GameTrace>()
+                    + trace.record.len() * mem::size_of::<Turn>()
+                    + trace.configs.iter().map(String::capacity).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// The traces in the buffer, oldest first.
+    pub fn traces(&self) -> impl Iterator<Item = &GameTrace> {
+        self.traces.iter()
+    }
+
+    /// Write all traces, oldest first, separated by an empty line.
+    pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for trace in self.traces.iter() {
+            writeln!(out, "{}", trace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs games one after the other, checking each one and dumping the last traces when something is off.
+#[derive(Debug)]
+pub struct BatchRecorder {
+    buffer: TraceBuffer,
+    /// Where dumps are written, stderr if not set.
+    dump_dir: Option<PathBuf>,
+    dumps: Vec<PathBuf>,
+}
+
+impl BatchRecorder {
+    /// Create a recorder that keeps the last `capacity` games.
+    pub fn new(capacity: usize) -> Self {
+        BatchRecorder {
+            buffer: TraceBuffer::new(capacity),
+            dump_dir: None,
+            dumps: Vec::new(),
+        }
+    }
+
+    /// Write dumps as files in `dir` instead of to stderr.
+    pub fn with_dump_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dump_dir = Some(dir.into());
+        self
+    }
+
+    /// The traces of the last games.
+    pub fn buffer(&self) -> &TraceBuffer {
+        &self.buffer
+    }
+
+    /// The files dumps were written to.
+    pub fn dumps(&self) -> &[PathBuf] {
+        &self.dumps
+    }
+
+    /// Build a game with `make_game` from a random generator seeded with `seed`, and play it.
+    /// The players should draw from the generator, so the game is reproducible from the seed.
+    /// Returns the anomaly if the game had one, after dumping the buffer.
+    pub fn play<F>(
+        &mut self,
+        seed: u64,
+        configs: [String; 2],
+        make_game: F,
+    ) -> Result<GameResult, Anomaly>
+    where
+        F: FnOnce(&SharedRng) -> QuartoGame,
+    {
+        let mut game = make_game(&SharedRng::seeded(seed));
+        let result = game.play_without_call();
+        let record = game.history().clone();
+        let anomaly = check(&record);
+        self.buffer.push(GameTrace {
+            seed,
+            configs,
+            record,
+        });
+        match anomaly {
+            Some(anomaly) => {
+                // Failing to write the dump should not hide the anomaly itself.
+                let _ = self.dump(seed, &anomaly);
+                Err(anomaly)
+            }
+            None => Ok(result),
+        }
+    }
+
+    /// Write the buffer with the anomaly that caused the dump.
+    fn dump(&mut self, seed: u64, anomaly: &Anomaly) -> io::Result<()> {
+        match &self.dump_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                let path = dir.join(format!("anomaly-{}.txt", seed));
+                let mut file = fs::File::create(&path)?;
+                writeln!(file, "anomaly {}\n", anomaly)?;
+                self.buffer.dump(&mut file)?;
+                self.dumps.push(path);
+            }
+            None => {
+                let mut err = io::stderr().lock();
+                writeln!(err, "anomaly {}\n", anomaly)?;
+                self.buffer.dump(&mut err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{
+        board::Board,
+        player::{ComputerPlayer, Player},
+        record::Turn,
+        strategy::DumbStrategy,
+    };
+
+    use super::*;
+
+    fn dumb_game(rng: &SharedRng) -> QuartoGame {
+        QuartoGame::new(
+            ComputerPlayer::new(DumbStrategy::new().with_rng(rng.clone())),
+            ComputerPlayer::new(DumbStrategy::new().with_rng(rng.clone())),
+        )
+    }
+
+    fn configs() -> [String; 2] {
+        ["dumb".to_string(), "dumb".to_string()]
+    }
+
+    /// A player that always places on index 0, which is illegal from the second placement on.
+    struct StubbornPlayer;
+
+    impl Player for StubbornPlayer {
+        fn get_piece(&self, board: &Board) -> Option<u8> {
+            board.valid_pieces().first().copied()
+        }
+
+        fn get_move(&self, _: &Board, _: u8) -> Option<u8> {
+            Some(0)
+        }
+
+        fn quarto(&self, _: &Board) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_buffer_keeps_last_games() {
+        let mut recorder = BatchRecorder::new(3);
+        for seed in 0..5 {
+            assert!(recorder.play(seed, configs(), dumb_game).is_ok());
+        }
+        let seeds: Vec<u64> = recorder.buffer().traces().map(|t| t.seed).collect();
+        assert_eq!(seeds, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let mut recorder = BatchRecorder::new(2);
+        recorder.play(42, configs(), dumb_game).unwrap();
+        recorder.play(42, configs(), dumb_game).unwrap();
+        let traces: Vec<&GameTrace> = recorder.buffer().traces().collect();
+        assert_eq!(traces[0], traces[1]);
+    }
+
+    #[test]
+    fn test_check_result_mismatch() {
+        let mut record = GameRecord::new();
+        record.push(Turn {
+            giver: 0,
+            piece: 3,
+            placer: 1,
+            index: 0,
+            quarto_called: false,
+        });
+        record.set_result(GameResult::Draw);
+        assert_eq!(
+            check(&record),
+            Some(Anomaly::ResultMismatch {
+                recorded: Some(GameResult::Draw),
+                replayed: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_players_alternate() {
+        let mut record = GameRecord::new();
+        let turn = Turn {
+            giver: 0,
+            piece: 3,
+            placer: 1,
+            index: 0,
+            quarto_called: false,
+        };
+        record.push(turn);
+        record.push(Turn {
+            piece: 4,
+            index: 1,
+            ..turn
+        });
+        assert!(matches!(check(&record), Some(Anomaly::InvariantFailure(_))));
+    }
+
+    #[test]
+    fn test_anomaly_dumps_buffer() {
+        let dir = env::temp_dir().join(format!("quarto-trace-{}", std::process::id()));
+        let mut recorder = BatchRecorder::new(4).with_dump_dir(&dir);
+        recorder.play(1, configs(), dumb_game).unwrap();
+        let anomaly = recorder.play(2, ["stubborn".to_string(), "stubborn".to_string()], |_| {
+            QuartoGame::new(StubbornPlayer, StubbornPlayer)
+        });
+        assert_eq!(anomaly, Err(Anomaly::GameAborted));
+        assert_eq!(recorder.dumps().len(), 1);
+        let dump = fs::read_to_string(&recorder.dumps()[0]).unwrap();
+        assert!(dump.starts_with("anomaly game aborted"));
+        assert!(dump.contains("seed 1\n"));
+        assert!(dump.contains("player 0 stubborn"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,409 @@
+// A transposition table that remembers scores of positions the search has already seen.
+// Quarto positions are reached through many move orders, so the search would otherwise expand the same position over and over.
+// The solver has a table of its own for proven values, bounded in the same way so long runs stay within their memory.
+
+use crate::{board::Board, memory::MemoryBudget};
+
+/// Random keys for the piece that has to be placed, mixed into the board hash.
+/// The same position with a different piece in hand is a different search node.
+const PIECE_KEYS: [u64; 16] = piece_keys();
+
+/// Generate the `PIECE_KEYS` with SplitMix64 from a fixed seed.
+const fn piece_keys() -> [u64; 16] {
+    let mut keys = [0u64; 16];
+    let mut state: u64 = 0x7069_6563_6573_2121;
+    let mut i = 0;
+    while i < 16 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// The key of the search node where `piece` has to be placed on `board`.
+pub fn key(board: &Board, piece: u8) -> u64 {
+    board.hash() ^ PIECE_KEYS[(piece & 0b1111) as usize]
+}
+
+/// How a stored score relates to the real score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The real score is at least the stored score (the search failed high).
+    Lower,
+    /// The real score is at most the stored score (the search failed low).
+    Upper,
+}
+
+/// A stored search result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+}
+
+/// What to do when a new result maps to a slot that is already taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Always overwrite the old entry.
+    Always,
+    /// Only overwrite an entry of another position if the new result was searched at least as deep.
+    DepthPreferred,
+}
+
+/// A fixed-size table of search results, indexed by the lowest bits of the position key.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    policy: Replacement,
+    hits: u64,
+    probes: u64,
+}
+
+impl TranspositionTable {
+    /// The memory of a single slot in bytes.
+    pub const ENTRY_SIZE: usize = std::mem::size_of::<Option<Entry>>();
+
+    /// Create a table with room for `capacity` entries, rounded down to a power of two (at least 1).
+    pub fn new(capacity: usize, policy: Replacement) -> Self {
+        TranspositionTable {
+            entries: vec![None; Self::rounded_capacity(capacity)],
+            policy,
+            hits: 0,
+            probes: 0,
+        }
+    }
+
+    /// The number of slots of a table created with room for `capacity` entries.
+    pub fn rounded_capacity(capacity: usize) -> usize {
+        if capacity <= 1 {
+            1
+        } else {
+            1 << (usize::BITS - 1 - capacity.leading_zeros())
+        }
+    }
+
+    /// The number of slots in the table.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The memory the slots take in bytes, used or not.
+    pub fn memory_usage(&self) -> usize {
+        self.entries.capacity() * Self::ENTRY_SIZE
+    }
+
+    /// The number of slots in use.
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_some()).count()
+    }
+
+    /// Check if the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|e| e.is_none())
+    }
+
+    /// The fraction of probes that found an entry for their position.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.probes as f64
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    /// Look up the stored result for `key`, if there is one.
+    pub fn probe(&mut self, key: u64) -> Option<Entry> {
+        self.probes += 1;
+        let entry = self.entries[self.slot(key)].filter(|e| e.key == key);
+        if entry.is_some() {
+            self.hits += 1;
+        }
+        entry
+    }
+
+    /// Store a search result, following the replacement policy of the table.
+    pub fn store(&mut self, key: u64, depth: u8, score: i32, bound: Bound) {
+        let slot = self.slot(key);
+        let replace = match (self.policy, self.entries[slot]) {
+            (_, None) | (Replacement::Always, _) => true,
+            (Replacement::DepthPreferred, Some(old)) => old.key == key || depth >= old.depth,
+        };
+        if replace {
+            self.entries[slot] = Some(Entry {
+                key,
+                depth,
+                score,
+                bound,
+            });
+        }
+    }
+
+    /// Remove all entries, for example when starting a new game.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|e| *e = None);
+        self.hits = 0;
+        self.probes = 0;
+    }
+}
+
+impl Default for TranspositionTable {
+    /// A table in its share of the default `MemoryBudget`, which keeps the most valuable results.
+    fn default() -> Self {
+        MemoryBudget::default().table(Replacement::DepthPreferred)
+    }
+}
+
+/// A proven value of a position, as the solver stores it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Proof {
+    /// The canonical position.
+    pub key: u128,
+    /// The number of empty cells, how much work the proof took.
+    pub depth: u8,
+    pub value: i8,
+}
+
+/// A fixed-size table of proven positions, in power-of-two buckets indexed by a hash of the position.
+/// With two tiers a bucket has two slots: the first follows the replacement policy, the second takes what the first
+/// turns away or pushes out, so a deep proof is never lost to a shallow one and recent proofs are kept as well.
+#[derive(Debug, Clone)]
+pub struct ProofTable {
+    slots: Vec<Option<Proof>>,
+    ways: usize,
+    policy: Replacement,
+    stored: usize,
+}
+
+impl ProofTable {
+    /// The memory of a single slot in bytes.
+    pub const ENTRY_SIZE: usize = std::mem::size_of::<Option<Proof>>();
+
+    /// Create a table with room for `capacity` proofs, rounded down to a power of two, or none at all for 0.
+    pub fn new(capacity: usize, policy: Replacement) -> Self {
+        ProofTable {
+            slots: vec![None; Self::rounded_capacity(capacity)],
+            ways: 1,
+            policy,
+            stored: 0,
+        }
+    }
+
+    /// Create a table that takes at most `mib` mebibytes.
+    pub fn from_mib(mib: usize, policy: Replacement) -> Self {
+        ProofTable::new(mib.saturating_mul(1 << 20) / Self::ENTRY_SIZE, policy)
+    }
+
+    /// Split the slots into buckets of two, with a second tier that always takes the proofs the first turns away.
+    pub fn with_two_tiers(mut self) -> Self {
+        self.ways = 2.min(self.slots.len().max(1));
+        self
+    }
+
+    /// The number of slots of a table created with room for `capacity` proofs.
+    pub fn rounded_capacity(capacity: usize) -> usize {
+        match capacity {
+            0 => 0,
+            _ => 1 << (usize::BITS - 1 - capacity.leading_zeros()),
+        }
+    }
+
+    /// The number of slots in the table.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The number of proofs in the table.
+    pub fn len(&self) -> usize {
+        self.stored
+    }
+
+    /// Check if the table has no proofs.
+    pub fn is_empty(&self) -> bool {
+        self.stored == 0
+    }
+
+    /// The memory the slots take in bytes, used or not.
+    pub fn memory_usage(&self) -> usize {
+        self.slots.capacity() * Self::ENTRY_SIZE
+    }
+
+    /// The slots of the bucket of `key`.
+    fn bucket(&self, key: u128) -> std::ops::Range<usize> {
+        let buckets = self.slots.len() / self.ways;
+        if buckets == 0 {
+            return 0..0;
+        }
+        let hash = (key as u64) ^ ((key >> 64) as u64).rotate_left(32);
+        let first =
+            ((hash.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as usize & (buckets - 1)) * self.ways;
+        first..first + self.ways
+    }
+
+    /// Look up the proven value of `key`, if it is still in the table.
+    pub fn probe(&self, key: u128) -> Option<i8> {
+        self.slots[self.bucket(key)]
+            .iter()
+            .flatten()
+            .find(|proof| proof.key == key)
+            .map(|proof| proof.value)
+    }
+
+    /// Store the proven `value` of `key` that took `depth`, following the replacement policy of the table.
+    pub fn store(&mut self, key: u128, depth: u8, value: i8) {
+        let bucket = self.bucket(key);
+        let Some(first) = bucket.clone().next() else {
+            return;
+        };
+        let proof = Proof { key, depth, value };
+        // The same position is always updated, in whichever tier it is.
+        let mut slots = bucket.clone();
+        if let Some(slot) = slots.find(|&slot| self.slots[slot].is_some_and(|p| p.key == key)) {
+            self.slots[slot] = Some(proof);
+            return;
+        }
+        let replace = match (self.policy, self.slots[first]) {
+            (_, None) | (Replacement::Always, _) => true,
+            (Replacement::DepthPreferred, Some(old)) => depth >= old.depth,
+        };
+        // The proof that does not get the first slot, the old or the new one, moves down to the second tier.
+        let demoted = if replace {
+            self.slots[first].replace(proof)
+        } else {
+            Some(proof)
+        };
+        match demoted {
+            None => self.stored += 1,
+            Some(demoted) if bucket.len() > 1 => {
+                if self.slots[first + 1].replace(demoted).is_none() {
+                    self.stored += 1;
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Remove all proofs.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|p| *p = None);
+        self.stored = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity_power_of_two() {
+        assert_eq!(
+            TranspositionTable::new(1000, Replacement::Always).capacity(),
+            512
+        );
+        assert_eq!(
+            TranspositionTable::new(0, Replacement::Always).capacity(),
+            1
+        );
+        assert_eq!(
+            TranspositionTable::new(1024, Replacement::Always).capacity(),
+            1024
+        );
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut table = TranspositionTable::new(16, Replacement::Always);
+        assert!(table.is_empty());
+        table.store(42, 3, 7, Bound::Exact);
+        assert_eq!(
+            table.probe(42),
+            Some(Entry {
+                key: 42,
+                depth: 3,
+                score: 7,
+                bound: Bound::Exact
+            })
+        );
+        assert_eq!(table.probe(43), None);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_depth_preferred_keeps_deeper_entry() {
+        let mut table = TranspositionTable::new(1, Replacement::DepthPreferred);
+        table.store(1, 5, 0, Bound::Exact);
+        table.store(2, 2, 0, Bound::Exact);
+        assert!(table.probe(1).is_some());
+        assert!(table.probe(2).is_none());
+        // The same position is always updated.
+        table.store(1, 1, 9, Bound::Lower);
+        assert_eq!(table.probe(1).map(|e| e.score), Some(9));
+    }
+
+    #[test]
+    fn test_always_replaces() {
+        let mut table = TranspositionTable::new(1, Replacement::Always);
+        table.store(1, 5, 0, Bound::Exact);
+        table.store(2, 2, 0, Bound::Exact);
+        assert!(table.probe(1).is_none());
+        assert!(table.probe(2).is_some());
+    }
+
+    #[test]
+    fn test_proof_table_tiers() {
+        // One bucket, so every proof competes for the same slots.
+        let mut table = ProofTable::new(1, Replacement::DepthPreferred);
+        table.store(1, 9, 1);
+        table.store(2, 3, -1);
+        assert_eq!(
+            (table.probe(1), table.probe(2), table.len()),
+            (Some(1), None, 1)
+        );
+        let mut table = ProofTable::new(2, Replacement::DepthPreferred).with_two_tiers();
+        table.store(1, 9, 1);
+        table.store(2, 3, -1);
+        table.store(3, 4, 0);
+        // The deep proof stays on top, the second tier keeps the most recent shallow one.
+        assert_eq!(
+            (table.probe(1), table.probe(2), table.probe(3)),
+            (Some(1), None, Some(0))
+        );
+        table.store(4, 12, 1);
+        assert_eq!(
+            (table.probe(4), table.probe(1), table.len()),
+            (Some(1), Some(1), 2)
+        );
+        assert!(ProofTable::from_mib(1, Replacement::Always).memory_usage() <= 1 << 20);
+        assert_eq!(
+            ProofTable::new(0, Replacement::Always)
+                .with_two_tiers()
+                .probe(1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_key_depends_on_piece() {
+        let board = Board::new();
+        assert_ne!(key(&board, 0), key(&board, 1));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut table = TranspositionTable::new(4, Replacement::Always);
+        table.store(1, 1, 1, Bound::Exact);
+        table.clear();
+        assert!(table.is_empty());
+    }
+}
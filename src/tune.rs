@@ -0,0 +1,308 @@
+// Tuning of numeric strategy parameters by self-play, with simultaneous perturbation stochastic approximation (SPSA).
+// Every step plays two perturbed copies of the parameters against each other, and moves the parameters toward the winner.
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    board::Board,
+    evaluator::ThreatEvaluator,
+    game::{GameResult, QuartoGame},
+    mcts::MctsBudget,
+    player::{ComputerPlayer, Player},
+    random::SharedRng,
+    strategy::{MctsStrategy, SmartStrategy},
+};
+
+/// A numeric parameter of a strategy, kept between `min` and `max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Parameter {
+    /// Create a parameter `name` that starts at `value`, clamped between `min` and `max`.
+    pub fn new(name: &str, value: f64, min: f64, max: f64) -> Self {
+        Parameter {
+            name: name.to_string(),
+            value: value.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    /// The value as a fraction of the range, which the tuner works with so every parameter moves alike.
+    fn normalized(&self) -> f64 {
+        if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+
+    /// Set the value from a fraction of the range.
+    fn set_normalized(&mut self, fraction: f64) {
+        self.value = self.min + fraction.clamp(0.0, 1.0) * (self.max - self.min);
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {:.3}", self.name, self.value)
+    }
+}
+
+/// The parameters the `tune` command knows how to tune.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuneTarget {
+    /// The exploration constant of the MCTS strategy.
+    Mcts,
+    /// The weights of the `ThreatEvaluator` of the smart strategy.
+    Eval,
+}
+
+impl TuneTarget {
+    /// The parameters of the target, at their defaults.
+    pub fn parameters(self) -> Vec<Parameter> {
+        match self {
+            TuneTarget::Mcts => vec![Parameter::new(
+                "exploration",
+                std::f64::consts::SQRT_2,
+                0.1,
+                4.0,
+            )],
+            TuneTarget::Eval => {
+                let [pairs, threats, safe] = ThreatEvaluator::default().weights;
+                vec![
+                    Parameter::new("pairs", pairs, -50.0, 50.0),
+                    Parameter::new("threats", threats, -50.0, 50.0),
+                    Parameter::new("safe", safe, -50.0, 50.0),
+                ]
+            }
+        }
+    }
+
+    /// Make a player with the parameter `values`, searching `depth` turns or `iterations` playouts deep.
+    pub fn player(
+        self,
+        values: &[f64],
+        rng: &SharedRng,
+        depth: u8,
+        iterations: u32,
+    ) -> Box<dyn Player> {
+        match self {
+            TuneTarget::Mcts => Box::new(ComputerPlayer::new(
+                MctsStrategy::new(MctsBudget::Iterations(iterations))
+                    .with_exploration(values[0])
+                    .with_rng(rng.clone()),
+            )),
+            TuneTarget::Eval => {
+                let evaluator = ThreatEvaluator::new([values[0], values[1], values[2]]);
+                Box::new(ComputerPlayer::new(
+                    SmartStrategy::new(depth).with_evaluator(Arc::new(evaluator)),
+                ))
+            }
+        }
+    }
+}
+
+impl FromStr for TuneTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mcts" => Ok(TuneTarget::Mcts),
+            "eval" => Ok(TuneTarget::Eval),
+            _ => Err(format!("'{}' is not a target, use mcts or eval", s)),
+        }
+    }
+}
+
+/// Tunes parameters by playing perturbed copies of them against each other.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    parameters: Vec<Parameter>,
+    /// The number of pairs of games of every step, each pair from the same opening with both seats.
+    pairs: u32,
+    /// The number of random placements of the openings, so deterministic players play different games.
+    opening: usize,
+    /// How far the parameters move in the first step, as a fraction of their range.
+    step: f64,
+    /// How far the copies are perturbed in the first step, as a fraction of their range.
+    perturbation: f64,
+    rng: SharedRng,
+}
+
+impl Tuner {
+    /// Tune `parameters` with ten pairs of games per step, from openings of two placements.
+    pub fn new(parameters: Vec<Parameter>) -> Self {
+        Tuner {
+            parameters,
+            pairs: 10,
+            opening: 2,
+            step: 0.1,
+            perturbation: 0.1,
+            rng: SharedRng::new(),
+        }
+    }
+
+    /// Play `pairs` pairs of games per step, more games give steadier steps.
+    pub fn with_pairs(mut self, pairs: u32) -> Self {
+        self.pairs = pairs;
+        self
+    }
+
+    /// Start every pair of games from `placements` random placements.
+    pub fn with_opening(mut self, placements: usize) -> Self {
+        self.opening = placements;
+        self
+    }
+
+    /// Draw the perturbations, the openings and the players from `rng`, so a seed repeats the tuning.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// The parameters as tuned so far.
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Run `steps` steps, with `make` making a player from the parameter values and a random generator.
+    /// `report` hears every step with the score of the copy that was perturbed upwards, from 0 to 1.
+    pub fn tune<F>(&mut self, steps: u32, make: F, mut report: impl FnMut(u32, &[Parameter], f64))
+    where
+        F: Fn(&[f64], &SharedRng) -> Box<dyn Player>,
+    {
+        for step in 0..steps {
+            // The gains of SPSA shrink over the steps, so the parameters settle.
+            let k = f64::from(step + 1);
+            let gain = self.step / (k + 0.1 * f64::from(steps)).powf(0.602);
+            let width = self.perturbation / k.powf(0.101);
+            let signs: Vec<f64> = self
+                .parameters
+                .iter()
+                .map(|_| if self.rng.usize(..2) == 0 { -1.0 } else { 1.0 })
+                .collect();
+            let perturbed = |sign: f64| -> Vec<f64> {
+                self.parameters
+                    .iter()
+                    .zip(&signs)
+                    .map(|(parameter, delta)| {
+                        let mut moved = parameter.clone();
+                        moved.set_normalized(parameter.normalized() + sign * width * delta);
+                        moved.value
+                    })
+                    .collect()
+            };
+            let (up, down) = (perturbed(1.0), perturbed(-1.0));
+            let score = self.match_score(&up, &down, &make);
+            for (parameter, delta) in self.parameters.iter_mut().zip(&signs) {
+                let gradient = (2.0 * score - 1.0) / (2.0 * width * delta);
+                parameter.set_normalized(parameter.normalized() + gain * gradient);
+            }
+            report(step + 1, &self.parameters, score);
+        }
+    }
+
+    /// Play the pairs of games between the players of `first` and `second`, and return the score of `first`.
+    fn match_score<F>(&self, first: &[f64], second: &[f64], make: &F) -> f64
+    where
+        F: Fn(&[f64], &SharedRng) -> Box<dyn Player>,
+    {
+        let mut score = 0.0;
+        for _ in 0..self.pairs {
+            let opening = self.random_opening();
+            for seat in 0..2 {
+                let rng = SharedRng::seeded(self.rng.usize(..) as u64);
+                let (a, b) = (make(first, &rng), make(second, &rng));
+                let players = if seat == 0 { (a, b) } else { (b, a) };
+                let game = QuartoGame::new(players.0, players.1).with_opening(&opening);
+                let Ok(mut game) = game else {
+                    continue;
+                };
+                // A game that ended in an error counts as lost, so broken parameters are not rewarded.
+                score += match game.play_without_call() {
+                    GameResult::Win(winner) if winner == seat => 1.0,
+                    GameResult::Timeout(player) if player != seat => 1.0,
+                    GameResult::Draw => 0.5,
+                    _ => 0.0,
+                };
+            }
+        }
+        score / f64::from(2 * self.pairs.max(1))
+    }
+
+    /// Random placements that do not win and leave a piece that does not lose at once.
+    fn random_opening(&self) -> Vec<(u8, u8)> {
+        let mut board = Board::new();
+        let mut moves = Vec::new();
+        for _ in 0..self.opening {
+            let Some(piece) = self.rng.choose(&board.safe_pieces()) else {
+                break;
+            };
+            let spaces: Vec<u8> = board
+                .empty_spaces()
+                .into_iter()
+                .filter(|&index| {
+                    let mut next = board;
+                    next.put_piece(piece, index).is_ok() && !next.has_winner()
+                })
+                .collect();
+            let Some(index) = self.rng.choose(&spaces) else {
+                break;
+            };
+            board.put_piece(piece, index).unwrap_or_default();
+            moves.push((piece, index));
+        }
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuning_lowers_blunders() {
+        let mut tuner = Tuner::new(vec![Parameter::new("blunder", 0.8, 0.0, 1.0)])
+            .with_pairs(10)
+            .with_rng(SharedRng::seeded(2804));
+        let mut steps = 0;
+        tuner.tune(
+            8,
+            |values, rng| {
+                Box::new(ComputerPlayer::new(
+                    SmartStrategy::new(1)
+                        .with_blunder_probability(values[0])
+                        .with_rng(rng.clone()),
+                ))
+            },
+            |step, parameters, score| {
+                steps = step;
+                assert!((0.0..=1.0).contains(&score));
+                assert!((0.0..=1.0).contains(&parameters[0].value));
+            },
+        );
+        assert_eq!(steps, 8);
+        assert!(tuner.parameters()[0].value < 0.5);
+        assert_eq!(
+            tuner.parameters()[0].to_string(),
+            format!("blunder = {:.3}", tuner.parameters()[0].value)
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        assert_eq!("eval".parse(), Ok(TuneTarget::Eval));
+        assert!("weights".parse::<TuneTarget>().is_err());
+        let parameters = TuneTarget::Eval.parameters();
+        assert_eq!(parameters.len(), 3);
+        let values: Vec<f64> = parameters.iter().map(|p| p.value).collect();
+        let player = TuneTarget::Eval.player(&values, &SharedRng::seeded(1), 1, 100);
+        assert!(player.get_piece(&Board::new()).is_some());
+    }
+}
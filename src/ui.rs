@@ -1,7 +1,17 @@
+use std::time::Duration;
+
 use crate::board::Board;
 
+pub mod terminal;
+pub mod thinking;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod wizard;
+
 /// Any interface for the `HumanPlayer` should implement these functions.
 pub trait PlayerInterface {
+    /// Show the current state of the board to the player.
+    fn show_board(&self, board: &Board);
     /// Get the piece to play from the interface.
     /// This function **must** return a number.
     fn prompt_for_piece(&self, board: &Board) -> u8;
@@ -10,4 +20,13 @@ pub trait PlayerInterface {
     fn prompt_for_move(&self, board: &Board, piece: u8) -> u8;
     /// Ask if the player wants to call Quarto via the interface.
     fn ask_quarto(&self, board: &Board) -> bool;
-}
\ No newline at end of file
+    /// Warn the player that their last input was invalid.
+    fn warn_player(&self, message: &str);
+    /// Check if the player is gone, for example because the input was closed.
+    /// The `HumanPlayer` stops asking when this is true.
+    fn closed(&self) -> bool {
+        false
+    }
+    /// Show the time the player has for the decision they are asked next.
+    fn show_clock(&self, _time: Duration) {}
+}
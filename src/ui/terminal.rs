@@ -0,0 +1,332 @@
+// A text interface for a human player in the terminal.
+// Reads answers line by line, and asks again until the answer is a number.
+
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, BufRead, StdinLock, Stdout, Write},
+    time::Duration,
+};
+
+use crate::{
+    board::Board,
+    card,
+    clock::format_time,
+    fog::{self, Fog},
+    notation,
+    printable::{Glyphs, Piece, PrintableBoard},
+    ui::PlayerInterface,
+};
+
+/// A `PlayerInterface` that prints to `output` and reads answers from `input`.
+pub struct TerminalInterface<R: BufRead, W: Write> {
+    input: RefCell<R>,
+    output: RefCell<W>,
+    /// Set when reading from `input` fails or reaches the end.
+    closed: Cell<bool>,
+    /// Hides the pieces on the board in the fog variant.
+    fog: Option<RefCell<Fog>>,
+    /// The board last shown to the player.
+    shown: Cell<Option<Board>>,
+    /// The board after the player's last placement, which is still their turn.
+    placed: Cell<Option<Board>>,
+    /// Color the pieces with ANSI escapes.
+    color: bool,
+    /// Describe pieces as the pieces of a physical set.
+    physical: bool,
+}
+
+impl TerminalInterface<StdinLock<'static>, Stdout> {
+    /// Create an interface on stdin and stdout.
+    pub fn stdio() -> Self {
+        TerminalInterface::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> TerminalInterface<R, W> {
+    /// Create an interface on the given input and output.
+    pub fn new(input: R, output: W) -> Self {
+        TerminalInterface {
+            input: RefCell::new(input),
+            output: RefCell::new(output),
+            closed: Cell::new(false),
+            fog: None,
+            shown: Cell::new(None),
+            placed: Cell::new(None),
+            color: false,
+            physical: false,
+        }
+    }
+
+    /// Play the fog variant: the pieces on the board are hidden, and `fog` limits the lines to inspect per turn.
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(RefCell::new(fog));
+        self
+    }
+
+    /// Color dark and light pieces, and highlight the winning line once there is one.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Describe the pieces as in a physical set, like `tall dark square hollow`, for players with a real board.
+    pub fn with_physical(mut self, physical: bool) -> Self {
+        self.physical = physical;
+        self
+    }
+
+    /// The description of `piece` in the tray and prompts.
+    fn describe(&self, piece: Piece) -> String {
+        if self.physical {
+            card::physical(piece)
+        } else {
+            piece.to_string()
+        }
+    }
+
+    /// Draw the board, with the hidden pieces of the fog variant as `????`.
+    fn draw(&self, board: &Board) {
+        let hidden = self.fog.as_ref().map_or(0, |fog| fog.borrow().hidden());
+        let _ = writeln!(
+            self.output.borrow_mut(),
+            "\n{}\n",
+            PrintableBoard::new(board)
+                .with_hidden(hidden)
+                .with_color(self.color)
+        );
+    }
+
+    /// List the pieces that are left with their number, glyph and attributes.
+    fn draw_tray(&self, board: &Board) {
+        let glyphs = Glyphs::new().with_color(self.color);
+        let mut output = self.output.borrow_mut();
+        let _ = writeln!(output, "Pieces left:");
+        for number in board.valid_pieces() {
+            let piece = Piece::from_number(number);
+            let _ = writeln!(
+                output,
+                "  {:>2}  {} {:04b}  {}  {}",
+                number,
+                glyphs.render(piece),
+                number,
+                piece.code(),
+                self.describe(piece)
+            );
+        }
+    }
+
+    /// Handle `answer` as an inspection of the fog variant, and show the inspected line.
+    /// Returns false if the answer is no inspection.
+    fn inspect(&self, answer: &str) -> bool {
+        let (Some(fog), Some(line), Some(board)) = (
+            self.fog.as_ref(),
+            fog::parse_inspection(answer),
+            self.shown.get(),
+        ) else {
+            return false;
+        };
+        let inspected = fog.borrow_mut().inspect(line);
+        match inspected {
+            Ok(()) => self.draw(&board),
+            Err(message) => self.warn_player(&message),
+        }
+        true
+    }
+
+    /// Print `message` without a newline and read the answer.
+    /// Returns `None` if the input is closed.
+    fn ask(&self, message: &str) -> Option<String> {
+        let mut output = self.output.borrow_mut();
+        // Failing to write to the terminal leaves nothing to report the error to.
+        let _ = write!(output, "{}", message);
+        let _ = output.flush();
+        let mut line = String::new();
+        match self.input.borrow_mut().read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                self.closed.set(true);
+                None
+            }
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    /// Ask until `parse` reads a number from the answer, `expected` describes the answers it reads.
+    /// Returns `u8::MAX`, which is never a valid piece or index, if the input is closed.
+    fn ask_number(&self, message: &str, parse: fn(&str) -> Option<u8>, expected: &str) -> u8 {
+        loop {
+            match self.ask(message) {
+                None => return u8::MAX,
+                Some(answer) if self.inspect(&answer) => {}
+                Some(answer) => match parse(&answer) {
+                    Some(number) => return number,
+                    None => self.warn_player(&format!("'{}' is not {}.", answer, expected)),
+                },
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> PlayerInterface for TerminalInterface<R, W> {
+    /// In the fog variant a new turn starts when the opponent changed the board.
+    fn show_board(&self, board: &Board) {
+        if let Some(fog) = &self.fog
+            && self.shown.get() != Some(*board)
+            && self.placed.get() != Some(*board)
+        {
+            let mut fog = fog.borrow_mut();
+            fog.new_turn();
+            let _ = writeln!(
+                self.output.borrow_mut(),
+                "Lines to inspect this turn: {} (r0-r3, c0-c3, dd or du).",
+                fog.left()
+            );
+        }
+        self.shown.set(Some(*board));
+        self.draw(board);
+    }
+
+    /// Show the tray of pieces that are left, and ask until the answer is one of them.
+    fn prompt_for_piece(&self, board: &Board) -> u8 {
+        self.draw_tray(board);
+        loop {
+            let piece = self.ask_number(
+                "Pick a piece for your opponent: ",
+                notation::parse_piece,
+                "a piece code or a number",
+            );
+            if board.valid_piece(piece) || self.closed() {
+                return piece;
+            }
+            self.warn_player(&format!(
+                "Piece {} is not in the tray, pick another one!",
+                piece
+            ));
+        }
+    }
+
+    fn prompt_for_move(&self, board: &Board, piece: u8) -> u8 {
+        let message = match self.physical {
+            true => format!(
+                "Where do you place piece {} ({})? ",
+                Piece::from_number(piece).code(),
+                self.describe(Piece::from_number(piece))
+            ),
+            false => format!(
+                "Where do you place piece {} ({:04b})? ",
+                Piece::from_number(piece).code(),
+                piece
+            ),
+        };
+        let index = self.ask_number(&message, notation::parse_index, "a cell or a number");
+        let mut placed = *board;
+        if placed.put_piece(piece, index).is_ok() {
+            self.placed.set(Some(placed));
+        }
+        index
+    }
+
+    fn ask_quarto(&self, _: &Board) -> bool {
+        self.ask("Call Quarto? [y/N] ")
+            .is_some_and(|answer| answer.eq_ignore_ascii_case("y"))
+    }
+
+    fn warn_player(&self, message: &str) {
+        let _ = writeln!(self.output.borrow_mut(), "{}", message);
+    }
+
+    fn closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    fn show_clock(&self, time: Duration) {
+        let _ = writeln!(self.output.borrow_mut(), "Time left: {}", format_time(time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asks_again_until_number() {
+        let interface = TerminalInterface::new("x\n\n7\n".as_bytes(), Vec::new());
+        assert_eq!(interface.prompt_for_move(&Board::new(), 3), 7);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert_eq!(output.matches("Where do you place").count(), 3);
+        assert!(output.contains("'x' is not a cell or a number."));
+    }
+
+    #[test]
+    fn test_move_as_cell() {
+        let interface = TerminalInterface::new("B3\n".as_bytes(), Vec::new());
+        assert_eq!(interface.prompt_for_move(&Board::new(), 15), 9);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert!(output.contains("Where do you place piece TDSH (1111)?"));
+    }
+
+    #[test]
+    fn test_physical_descriptions() {
+        let interface =
+            TerminalInterface::new("0\nA1\n".as_bytes(), Vec::new()).with_physical(true);
+        interface.prompt_for_piece(&Board::new());
+        interface.prompt_for_move(&Board::new(), 15);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert!(output.contains("0000  SLRS  short light round solid\n"));
+        assert!(output.contains("Where do you place piece TDSH (tall dark square hollow)?"));
+    }
+
+    #[test]
+    fn test_tray_lists_pieces_left() {
+        let board = Board::new().apply_moves(&[(5, 0), (10, 15)]).unwrap();
+        let interface = TerminalInterface::new("5\n16\nslsh\n".as_bytes(), Vec::new());
+        assert_eq!(interface.prompt_for_piece(&board), 12);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert!(output.contains("   0  \u{2022}  0000  SLRS  solid round low light\n"));
+        assert!(output.contains("  15  \u{25a1}' 1111  TDSH  hole square high dark\n"));
+        assert!(!output.contains("0101"));
+        assert!(!output.contains("1010"));
+        assert!(output.contains("Piece 5 is not in the tray"));
+        assert!(output.contains("Piece 16 is not in the tray"));
+    }
+
+    #[test]
+    fn test_closed_input() {
+        let interface = TerminalInterface::new("".as_bytes(), Vec::new());
+        assert_eq!(interface.prompt_for_piece(&Board::new()), u8::MAX);
+        assert!(!interface.ask_quarto(&Board::new()));
+        assert!(interface.closed());
+    }
+
+    #[test]
+    fn test_fog_hides_and_inspects() {
+        let board = Board::new().apply_moves(&[(5, 0), (10, 15)]).unwrap();
+        let interface =
+            TerminalInterface::new("r0\nr3\nc1\n3\n".as_bytes(), Vec::new()).with_fog(Fog::new(2));
+        interface.show_board(&board);
+        assert_eq!(interface.prompt_for_move(&board, 3), 3);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert!(output.contains("???? ---- ---- ----"));
+        assert!(output.contains("0101 ---- ---- ----"));
+        assert!(output.contains("---- ---- ---- 1010"));
+        assert!(output.contains("No inspections left this turn."));
+    }
+
+    #[test]
+    fn test_fog_turns() {
+        let board = Board::new().apply_moves(&[(5, 0)]).unwrap();
+        let interface =
+            TerminalInterface::new("r0\n4\nr1\n".as_bytes(), Vec::new()).with_fog(Fog::new(1));
+        interface.show_board(&board);
+        assert_eq!(interface.prompt_for_move(&board, 3), 4);
+        // Handing over a piece after placing one is still the same turn.
+        let placed = board.apply_moves(&[(3, 4)]).unwrap();
+        interface.show_board(&placed);
+        interface.prompt_for_piece(&placed);
+        let next = placed.apply_moves(&[(7, 9)]).unwrap();
+        interface.show_board(&next);
+        assert_eq!(interface.fog.as_ref().unwrap().borrow().left(), 1);
+        let output = String::from_utf8(interface.output.into_inner()).unwrap();
+        assert_eq!(output.matches("Lines to inspect this turn: 1").count(), 2);
+        assert!(output.contains("No inspections left this turn."));
+    }
+}
@@ -0,0 +1,244 @@
+// A "thinking" indicator for computer players.
+// Instant answers are held back for a minimum time, and long searches show a spinner so the interface does not look frozen.
+
+use std::{
+    io::Write,
+    sync::{Mutex, mpsc::Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{board::Board, player::Player, search::SearchProgress};
+
+/// The frames of the spinner, shown one after the other.
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+/// The time between two frames of the spinner.
+const FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// The minimum and maximum time the interface should show a computer player thinking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyEnvelope {
+    /// Answers that arrive sooner are held back until this much time has passed.
+    pub min: Duration,
+    /// After this much time the indicator tells the user that the search takes longer than expected.
+    pub max: Duration,
+}
+
+impl LatencyEnvelope {
+    /// Create a new envelope, `max` is raised to `min` if it is smaller.
+    pub fn new(min: Duration, max: Duration) -> Self {
+        LatencyEnvelope {
+            min,
+            max: max.max(min),
+        }
+    }
+
+    /// The time an answer that took `elapsed` should still be held back.
+    pub fn hold(&self, elapsed: Duration) -> Duration {
+        self.min.saturating_sub(elapsed)
+    }
+
+    /// Check if a search that has been running for `elapsed` is taking longer than expected.
+    pub fn overdue(&self, elapsed: Duration) -> bool {
+        elapsed > self.max
+    }
+
+    /// The line the indicator shows after `elapsed`, with the last status reported by the search.
+    pub fn status_line(&self, elapsed: Duration, status: &str) -> String {
+        let frame = FRAMES[(elapsed.as_millis() / FRAME_TIME.as_millis()) as usize % FRAMES.len()];
+        let mut line = format!("{} thinking...", frame);
+        if !status.is_empty() {
+            line.push_str(&format!(" {}", status));
+        }
+        if self.overdue(elapsed) {
+            line.push_str(&format!(
+                " (taking longer than expected, {}s)",
+                elapsed.as_secs()
+            ));
+        }
+        line
+    }
+
+    /// Run `compute` on a separate thread while animating the indicator on `out`.
+    /// `poll` is asked for news from the search every frame, which is shown next to the spinner.
+    /// The answer is returned no sooner than the minimum time of the envelope.
+    pub fn think<T, F, P, W>(&self, out: &mut W, mut poll: P, compute: F) -> T
+    where
+        T: Send,
+        F: FnOnce() -> T + Send,
+        P: FnMut() -> Option<String>,
+        W: Write,
+    {
+        let start = Instant::now();
+        let result = thread::scope(|scope| {
+            let handle = scope.spawn(compute);
+            let mut status = String::new();
+            while !handle.is_finished() || start.elapsed() < self.min {
+                if let Some(update) = poll() {
+                    status = update;
+                }
+                // The indicator is cosmetic, failing to draw it should not stop the game.
+                let _ = write!(
+                    out,
+                    "\r\x1b[2K{}",
+                    self.status_line(start.elapsed(), &status)
+                );
+                let _ = out.flush();
+                thread::sleep(
+                    FRAME_TIME.min(self.hold(start.elapsed()).max(Duration::from_millis(1))),
+                );
+            }
+            handle.join()
+        });
+        let _ = write!(out, "\r\x1b[2K");
+        let _ = out.flush();
+        match result {
+            Ok(value) => value,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl Default for LatencyEnvelope {
+    /// Think for at least half a second, and warn after ten seconds.
+    fn default() -> Self {
+        LatencyEnvelope::new(Duration::from_millis(500), Duration::from_secs(10))
+    }
+}
+
+/// A `Player` wrapper that shows a thinking indicator on the terminal while the inner player decides.
+pub struct ThinkingPlayer<P: Player + Sync> {
+    player: P,
+    envelope: LatencyEnvelope,
+    /// Progress reported by the search of the inner player, if it reports any.
+    progress: Option<Mutex<Receiver<SearchProgress>>>,
+}
+
+impl<P: Player + Sync> ThinkingPlayer<P> {
+    /// Wrap `player` in the given latency envelope.
+    pub fn new(player: P, envelope: LatencyEnvelope) -> Self {
+        ThinkingPlayer {
+            player,
+            envelope,
+            progress: None,
+        }
+    }
+
+    /// Show the progress received on `progress` next to the spinner.
+    /// The sending side is usually given to the strategy of the inner player.
+    pub fn with_progress(mut self, progress: Receiver<SearchProgress>) -> Self {
+        self.progress = Some(Mutex::new(progress));
+        self
+    }
+
+    /// Get the latest progress report, if there is a new one.
+    fn poll(&self) -> Option<String> {
+        let progress = self.progress.as_ref()?.lock().ok()?;
+        progress.try_iter().last().map(|p| p.to_string())
+    }
+
+    /// Throw away progress of earlier searches, so it is not shown for the next one.
+    fn drain(&self) {
+        self.poll();
+    }
+}
+
+impl<P: Player + Sync> Player for ThinkingPlayer<P> {
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        self.drain();
+        self.envelope.think(
+            &mut std::io::stdout(),
+            || self.poll(),
+            || self.player.get_piece(board),
+        )
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        self.drain();
+        self.envelope.think(
+            &mut std::io::stdout(),
+            || self.poll(),
+            || self.player.get_move(board, piece),
+        )
+    }
+
+    fn quarto(&self, board: &Board) -> bool {
+        self.player.quarto(board)
+    }
+
+    fn forfeited(&self) -> bool {
+        self.player.forfeited()
+    }
+
+    fn time_left(&self, time: Duration) {
+        self.player.time_left(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::{player::ComputerPlayer, strategy::SmartStrategy};
+
+    use super::*;
+
+    #[test]
+    fn test_hold_until_minimum() {
+        let envelope = LatencyEnvelope::new(Duration::from_millis(300), Duration::from_secs(1));
+        assert_eq!(
+            envelope.hold(Duration::from_millis(100)),
+            Duration::from_millis(200)
+        );
+        assert_eq!(envelope.hold(Duration::from_millis(400)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_max_not_below_min() {
+        let envelope = LatencyEnvelope::new(Duration::from_secs(2), Duration::from_secs(1));
+        assert_eq!(envelope.max, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_status_line_overdue() {
+        let envelope = LatencyEnvelope::new(Duration::ZERO, Duration::from_secs(1));
+        assert!(
+            !envelope
+                .status_line(Duration::from_millis(500), "")
+                .contains("longer")
+        );
+        assert!(
+            envelope
+                .status_line(Duration::from_secs(3), "depth 2")
+                .contains("depth 2")
+        );
+        assert!(
+            envelope
+                .status_line(Duration::from_secs(3), "")
+                .contains("longer")
+        );
+    }
+
+    #[test]
+    fn test_think_waits_minimum_and_shows_status() {
+        let envelope = LatencyEnvelope::new(Duration::from_millis(150), Duration::from_secs(1));
+        let mut out: Vec<u8> = Vec::new();
+        let start = Instant::now();
+        let mut news = Some("depth 1".to_string());
+        let answer = envelope.think(&mut out, || news.take(), || 42);
+        assert_eq!(answer, 42);
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert!(String::from_utf8_lossy(&out).contains("depth 1"));
+    }
+
+    #[test]
+    fn test_thinking_player_answers() {
+        let (tx, rx) = mpsc::channel();
+        let player = ThinkingPlayer::new(
+            ComputerPlayer::new(SmartStrategy::new(0).with_reporter(tx)),
+            LatencyEnvelope::new(Duration::ZERO, Duration::from_secs(1)),
+        )
+        .with_progress(rx);
+        assert!(player.get_piece(&Board::new()).is_some());
+    }
+}
@@ -0,0 +1,716 @@
+// A full-screen interface for a human player, with the board, a tray of the pieces that are left and a status bar.
+// Cells and pieces are picked with the arrow keys and enter, so the whole game stays in view while choosing.
+// In a game with a chat, what is said shows in a sidebar, and tab starts a line to say.
+
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Stdout},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    Frame, Terminal,
+    backend::{Backend, CrosstermBackend},
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+};
+
+use crate::{
+    board::Board,
+    clock::format_time,
+    printable::{Glyphs, Piece},
+    puzzle::Puzzle,
+    replay::Replay,
+    ui::PlayerInterface,
+};
+
+/// How long the terminal waits for a key before the screen is drawn again.
+const TICK: Duration = Duration::from_millis(250);
+
+/// What the player is asked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    /// Nothing, the opponent is thinking.
+    Wait,
+    Piece,
+    /// Where to place the piece in hand.
+    Move(u8),
+    Quarto,
+}
+
+/// An answer of the player to a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Answer {
+    /// A cell or a piece.
+    Select(u8),
+    Quarto(bool),
+    /// A line for the chat, the prompt stays.
+    Say(String),
+    Quit,
+}
+
+/// Everything on screen.
+#[derive(Debug, Clone)]
+struct View {
+    board: Board,
+    prompt: Prompt,
+    /// The highlighted cell of the board.
+    cell: u8,
+    /// The highlighted piece of the tray.
+    piece: u8,
+    /// The last warning for the player.
+    message: String,
+    /// When the time for the current decision runs out, in a game with a clock.
+    deadline: Option<Instant>,
+    /// The lines of the chat, in a game with one.
+    chat: Option<Vec<String>>,
+    /// The line the player is typing for the chat.
+    draft: Option<String>,
+}
+
+impl View {
+    fn new() -> Self {
+        View {
+            board: Board::new(),
+            prompt: Prompt::Wait,
+            cell: 0,
+            piece: 0,
+            message: String::new(),
+            deadline: None,
+            chat: None,
+            draft: None,
+        }
+    }
+
+    /// Move the highlight of the current prompt with a key, or answer the prompt.
+    fn handle(&mut self, key: KeyCode) -> Option<Answer> {
+        if let Some(draft) = &mut self.draft {
+            match key {
+                KeyCode::Enter => return self.draft.take().map(Answer::Say),
+                KeyCode::Esc => self.draft = None,
+                KeyCode::Backspace => {
+                    draft.pop();
+                }
+                KeyCode::Char(c) => draft.push(c),
+                _ => {}
+            }
+            return None;
+        }
+        match (self.prompt, key) {
+            (_, KeyCode::Tab) if self.chat.is_some() => {
+                self.draft = Some(String::new());
+                None
+            }
+            (_, KeyCode::Esc | KeyCode::Char('q')) => Some(Answer::Quit),
+            (Prompt::Quarto, KeyCode::Char('y' | 'Y')) => Some(Answer::Quarto(true)),
+            (Prompt::Quarto, KeyCode::Char('n' | 'N') | KeyCode::Enter) => {
+                Some(Answer::Quarto(false))
+            }
+            (Prompt::Piece, KeyCode::Enter | KeyCode::Char(' ')) => {
+                Some(Answer::Select(self.piece))
+            }
+            (Prompt::Move(_), KeyCode::Enter | KeyCode::Char(' ')) => {
+                Some(Answer::Select(self.cell))
+            }
+            (Prompt::Piece, key) => {
+                self.piece = step(self.piece, key);
+                None
+            }
+            (Prompt::Move(_), key) => {
+                self.cell = step(self.cell, key);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Draw the board and the tray side by side, and the chat next to them if there is one, with the status bar below.
+    fn render(&self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(6), Constraint::Length(4)]).areas(frame.area());
+        let [board, tray, sidebar] = Layout::horizontal([
+            Constraint::Length(30),
+            Constraint::Length(30),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+        if let Some(chat) = &self.chat {
+            // The last lines that fit, above the line being typed.
+            let mut lines: Vec<Line> = chat.iter().map(|line| Line::from(line.clone())).collect();
+            if let Some(draft) = &self.draft {
+                lines.push(Line::from(format!("> {}_", draft)));
+            }
+            let shown = lines
+                .len()
+                .saturating_sub(sidebar.height.saturating_sub(2) as usize);
+            frame.render_widget(
+                Paragraph::new(lines.split_off(shown)).block(Block::bordered().title(" Chat ")),
+                sidebar,
+            );
+        }
+        let title = match self.prompt {
+            Prompt::Move(piece) => {
+                format!(
+                    " Board, place {} ",
+                    Glyphs::shape(Piece::from_number(piece))
+                )
+            }
+            _ => " Board ".to_string(),
+        };
+        let cells = grid(|index| {
+            let selected = matches!(self.prompt, Prompt::Move(_)) && index == self.cell;
+            (self.board.piece_at(index), selected)
+        });
+        frame.render_widget(
+            Paragraph::new(cells).block(Block::bordered().title(title)),
+            board,
+        );
+        let available = self.board.valid_pieces();
+        let pieces = grid(|piece| {
+            let left = available.contains(&piece).then_some(piece);
+            (left, self.prompt == Prompt::Piece && piece == self.piece)
+        });
+        frame.render_widget(
+            Paragraph::new(pieces).block(Block::bordered().title(" Pieces ")),
+            tray,
+        );
+        frame.render_widget(
+            Paragraph::new(self.status()).block(Block::bordered()),
+            status,
+        );
+    }
+
+    /// The question, the highlighted piece or the last warning, and the keys.
+    fn status(&self) -> Vec<Line<'static>> {
+        let question = match self.prompt {
+            Prompt::Wait => "Waiting for the opponent.".to_string(),
+            Prompt::Piece => format!(
+                "Pick a piece for your opponent: {}.",
+                Piece::from_number(self.piece)
+            ),
+            Prompt::Move(piece) => format!(
+                "Where do you place the {} piece?",
+                Piece::from_number(piece)
+            ),
+            Prompt::Quarto => "Call Quarto? [y/N]".to_string(),
+        };
+        let question = match self.deadline {
+            Some(deadline) if self.prompt != Prompt::Wait => format!(
+                "{} Time left: {}.",
+                question,
+                format_time(deadline.saturating_duration_since(Instant::now()))
+            ),
+            _ => question,
+        };
+        let help = if self.draft.is_some() {
+            "Type a line, enter to say it, escape to stop.".to_string()
+        } else if self.message.is_empty() && self.chat.is_some() {
+            "Arrows or hjkl to move, enter to pick, tab to chat, q to quit.".to_string()
+        } else if self.message.is_empty() {
+            "Arrows or hjkl to move, enter to pick, q to quit.".to_string()
+        } else {
+            self.message.clone()
+        };
+        vec![Line::from(question), Line::from(help)]
+    }
+}
+
+/// Lay out 16 pieces in 4 rows as glyphs, dark pieces in blue and light ones in yellow, with the selected one reversed.
+fn grid(item: impl Fn(u8) -> (Option<u8>, bool)) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::default()];
+    for row in 0..4 {
+        let mut spans = vec![Span::raw(" ")];
+        for column in 0..4 {
+            let (piece, selected) = item(row * 4 + column);
+            let (text, mut style) = match piece.map(Piece::from_number) {
+                Some(piece) => (
+                    format!(" {} ", Glyphs::shape(piece)),
+                    Style::new().fg(if piece.dark {
+                        Color::Blue
+                    } else {
+                        Color::Yellow
+                    }),
+                ),
+                None => (" \u{b7} ".to_string(), Style::new()),
+            };
+            if selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::raw("   "));
+            spans.push(Span::styled(text, style));
+        }
+        lines.push(Line::from(spans));
+        lines.push(Line::default());
+    }
+    lines
+}
+
+/// Move `index` on a 4x4 grid one step in the direction of `key`, staying on the grid.
+fn step(index: u8, key: KeyCode) -> u8 {
+    let (row, column) = (index / 4, index % 4);
+    let (row, column) = match key {
+        KeyCode::Up | KeyCode::Char('k') => (row.saturating_sub(1), column),
+        KeyCode::Down | KeyCode::Char('j') => ((row + 1).min(3), column),
+        KeyCode::Left | KeyCode::Char('h') => (row, column.saturating_sub(1)),
+        KeyCode::Right | KeyCode::Char('l') => (row, (column + 1).min(3)),
+        _ => (row, column),
+    };
+    row * 4 + column
+}
+
+/// The keys pressed in the terminal, ending when reading fails.
+/// Ctrl-C arrives as a key in raw mode, and quits like escape.
+pub struct Keys;
+
+impl Iterator for Keys {
+    type Item = KeyCode;
+
+    fn next(&mut self) -> Option<KeyCode> {
+        loop {
+            match event::read().ok()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && key.code == KeyCode::Char('c')
+                    {
+                        return Some(KeyCode::Esc);
+                    }
+                    return Some(key.code);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The keys pressed in the terminal like `Keys`, with `KeyCode::Null` when none is pressed for a while,
+/// so the screen is drawn again with the time and the chat meanwhile.
+pub struct Ticks(pub Duration);
+
+impl Iterator for Ticks {
+    type Item = KeyCode;
+
+    fn next(&mut self) -> Option<KeyCode> {
+        if event::poll(self.0).ok()? {
+            Keys.next()
+        } else {
+            Some(KeyCode::Null)
+        }
+    }
+}
+
+/// Says a line in a chat, or tells why it cannot.
+type Say = Box<dyn Fn(&str) -> Result<(), String>>;
+
+/// The lines of a chat, and the way to say something in it.
+struct ChatPanel {
+    lines: Arc<Mutex<Vec<String>>>,
+    say: Say,
+}
+
+/// A `PlayerInterface` that draws the game full-screen on `terminal` and reads `keys`.
+pub struct TuiInterface<B: Backend, K: Iterator<Item = KeyCode>> {
+    terminal: RefCell<Terminal<B>>,
+    keys: RefCell<K>,
+    view: RefCell<View>,
+    chat: Option<ChatPanel>,
+    /// Set when the keys end or the player quits.
+    closed: Cell<bool>,
+    /// Give the terminal back when dropped, set when this interface took it over.
+    restore: bool,
+}
+
+impl TuiInterface<CrosstermBackend<Stdout>, Ticks> {
+    /// Take over the terminal of this process, until the interface is dropped.
+    pub fn stdio() -> io::Result<Self> {
+        let mut interface = TuiInterface::new(ratatui::try_init()?, Ticks(TICK));
+        interface.restore = true;
+        Ok(interface)
+    }
+}
+
+impl<B: Backend, K: Iterator<Item = KeyCode>> TuiInterface<B, K> {
+    /// Create an interface that draws on `terminal` and reads `keys`.
+    pub fn new(terminal: Terminal<B>, keys: K) -> Self {
+        TuiInterface {
+            terminal: RefCell::new(terminal),
+            keys: RefCell::new(keys),
+            view: RefCell::new(View::new()),
+            chat: None,
+            closed: Cell::new(false),
+            restore: false,
+        }
+    }
+
+    /// Show the `lines` of a chat in a sidebar, and `say` what the player types after tab.
+    /// An error of `say` is shown as a warning.
+    pub fn with_chat(
+        mut self,
+        lines: Arc<Mutex<Vec<String>>>,
+        say: impl Fn(&str) -> Result<(), String> + 'static,
+    ) -> Self {
+        self.view.get_mut().chat = Some(Vec::new());
+        self.chat = Some(ChatPanel {
+            lines,
+            say: Box::new(say),
+        });
+        self
+    }
+
+    /// Draw the view with the chat so far, failing to draw leaves nothing to report the error to.
+    fn draw(&self) {
+        if let Some(chat) = &self.chat {
+            let lines = chat.lines.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            self.view.borrow_mut().chat = Some(lines);
+        }
+        let view = self.view.borrow();
+        let _ = self.terminal.borrow_mut().draw(|frame| view.render(frame));
+    }
+
+    /// Draw the view, then read and handle one key, which redraws the clock and the chat on a tick.
+    /// Returns the answer to the prompt if the key gave one, `Answer::Quit` if the keys end.
+    fn tick(&self) -> Option<Answer> {
+        self.draw();
+        let Some(key) = self.keys.borrow_mut().next() else {
+            return Some(Answer::Quit);
+        };
+        let answer = self.view.borrow_mut().handle(key);
+        match (answer, &self.chat) {
+            (Some(Answer::Say(text)), Some(chat)) => {
+                if let Err(e) = (chat.say)(&text) {
+                    self.view.borrow_mut().message = e;
+                }
+                None
+            }
+            (answer, _) => answer,
+        }
+    }
+
+    /// Show `prompt` and handle keys until the player answers it.
+    /// Returns `Answer::Quit` if the keys end.
+    fn ask(&self, prompt: Prompt) -> Answer {
+        self.view.borrow_mut().prompt = prompt;
+        let answer = loop {
+            if let Some(answer) = self.tick() {
+                break answer;
+            }
+        };
+        let mut view = self.view.borrow_mut();
+        view.prompt = Prompt::Wait;
+        view.message.clear();
+        view.deadline = None;
+        drop(view);
+        self.draw();
+        if answer == Answer::Quit {
+            self.closed.set(true);
+        }
+        answer
+    }
+
+    /// Ask for a cell or a piece, `u8::MAX` if the player quits.
+    fn ask_number(&self, prompt: Prompt) -> u8 {
+        match self.ask(prompt) {
+            Answer::Select(number) => number,
+            _ => u8::MAX,
+        }
+    }
+}
+
+impl<B: Backend, K: Iterator<Item = KeyCode>> Drop for TuiInterface<B, K> {
+    fn drop(&mut self) {
+        if self.restore {
+            ratatui::restore();
+        }
+    }
+}
+
+/// Step through `replay` on `terminal` with `keys`, until q or escape, or until the keys end.
+/// Left and right (or h and l) go a move back and forth, home and end to the start and the end,
+/// and a move number followed by enter jumps to that move.
+pub fn replay<B: Backend>(
+    terminal: &mut Terminal<B>,
+    keys: impl Iterator<Item = KeyCode>,
+    replay: &mut Replay,
+) -> Result<(), B::Error> {
+    let mut number = String::new();
+    terminal.draw(|frame| render_replay(frame, replay, &number))?;
+    for key in keys {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => break,
+            KeyCode::Right | KeyCode::Char('l' | 'n' | ' ') => {
+                replay.forward();
+            }
+            KeyCode::Left | KeyCode::Char('h' | 'p') | KeyCode::Backspace if number.is_empty() => {
+                replay.back();
+            }
+            KeyCode::Backspace => {
+                number.pop();
+            }
+            KeyCode::Home => replay.jump(0),
+            KeyCode::End => replay.jump(replay.len()),
+            KeyCode::Char(digit) if digit.is_ascii_digit() => number.push(digit),
+            KeyCode::Enter => replay.jump(number.parse().unwrap_or(replay.ply())),
+            _ => {}
+        }
+        if !matches!(key, KeyCode::Char('0'..='9') | KeyCode::Backspace) {
+            number.clear();
+        }
+        terminal.draw(|frame| render_replay(frame, replay, &number))?;
+    }
+    Ok(())
+}
+
+/// Ask for the winning placement of every puzzle on `interface`, with the verdict on the last one below the next.
+/// Returns the number of puzzles solved, and stops when the player quits.
+pub fn puzzles<B: Backend, K: Iterator<Item = KeyCode>>(
+    interface: &TuiInterface<B, K>,
+    puzzles: &[Puzzle],
+) -> usize {
+    let mut solved = 0;
+    let mut verdict = String::new();
+    for puzzle in puzzles {
+        interface.show_board(&puzzle.board);
+        interface.warn_player(format!("{} {}", verdict, puzzle.describe()).trim_start());
+        let index = interface.prompt_for_move(&puzzle.board, puzzle.piece);
+        if interface.closed() {
+            break;
+        }
+        solved += usize::from(puzzle.is_solution(index));
+        verdict = puzzle.verdict(index);
+    }
+    solved
+}
+
+/// Draw the board of `replay` with the last placed piece reversed and the pieces that are left,
+/// with what happened in the move and the move number typed so far below.
+fn render_replay(frame: &mut Frame, replay: &Replay, number: &str) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(6), Constraint::Length(4)]).areas(frame.area());
+    let [board, tray] =
+        Layout::horizontal([Constraint::Length(30), Constraint::Length(30)]).areas(main);
+    let placed = replay.turn().map(|turn| turn.index);
+    let cells = grid(|index| (replay.board().piece_at(index), Some(index) == placed));
+    let title = format!(" Move {}/{} ", replay.ply(), replay.len());
+    frame.render_widget(
+        Paragraph::new(cells).block(Block::bordered().title(title)),
+        board,
+    );
+    let available = replay.board().valid_pieces();
+    let pieces = grid(|piece| (available.contains(&piece).then_some(piece), false));
+    frame.render_widget(
+        Paragraph::new(pieces).block(Block::bordered().title(" Pieces ")),
+        tray,
+    );
+    let help = if number.is_empty() {
+        "Left/right to step, home/end, a number and enter to jump, q to quit.".to_string()
+    } else {
+        format!("Jump to move {}", number)
+    };
+    frame.render_widget(
+        Paragraph::new(vec![Line::from(replay.describe()), Line::from(help)])
+            .block(Block::bordered()),
+        status,
+    );
+}
+
+impl<B: Backend, K: Iterator<Item = KeyCode>> PlayerInterface for TuiInterface<B, K> {
+    fn show_board(&self, board: &Board) {
+        self.view.borrow_mut().board = *board;
+        self.draw();
+    }
+
+    /// Start on the first piece that is left.
+    fn prompt_for_piece(&self, board: &Board) -> u8 {
+        let mut view = self.view.borrow_mut();
+        if !board.valid_piece(view.piece) {
+            view.piece = board.valid_pieces().first().copied().unwrap_or(0);
+        }
+        drop(view);
+        self.ask_number(Prompt::Piece)
+    }
+
+    /// Start on the first empty cell.
+    fn prompt_for_move(&self, board: &Board, piece: u8) -> u8 {
+        let mut view = self.view.borrow_mut();
+        if !board.empty_index(view.cell) {
+            view.cell = board.empty_spaces().first().copied().unwrap_or(0);
+        }
+        drop(view);
+        self.ask_number(Prompt::Move(piece))
+    }
+
+    fn ask_quarto(&self, board: &Board) -> bool {
+        self.view.borrow_mut().board = *board;
+        self.ask(Prompt::Quarto) == Answer::Quarto(true)
+    }
+
+    fn warn_player(&self, message: &str) {
+        self.view.borrow_mut().message = message.to_string();
+        self.draw();
+    }
+
+    fn closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    fn show_clock(&self, time: Duration) {
+        self.view.borrow_mut().deadline = Some(Instant::now() + time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    fn interface(keys: Vec<KeyCode>) -> TuiInterface<TestBackend, vec::IntoIter<KeyCode>> {
+        TuiInterface::new(
+            Terminal::new(TestBackend::new(64, 16)).unwrap(),
+            keys.into_iter(),
+        )
+    }
+
+    fn screen(interface: &TuiInterface<TestBackend, vec::IntoIter<KeyCode>>) -> String {
+        let terminal = interface.terminal.borrow();
+        let buffer = terminal.backend().buffer();
+        buffer.content.iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_navigate_to_cell() {
+        let board = Board::new().apply_moves(&[(3, 0)]).unwrap();
+        let interface = interface(vec![KeyCode::Down, KeyCode::Char('l'), KeyCode::Enter]);
+        interface.show_board(&board);
+        // The highlight starts on the first empty cell, index 1.
+        assert_eq!(interface.prompt_for_move(&board, 5), 6);
+        assert!(screen(&interface).contains("●"));
+    }
+
+    #[test]
+    fn test_tray_starts_on_piece_left() {
+        let board = Board::new().apply_moves(&[(0, 0)]).unwrap();
+        let interface = interface(vec![KeyCode::Right, KeyCode::Enter]);
+        interface.show_board(&board);
+        assert_eq!(interface.prompt_for_piece(&board), 2);
+        let screen = screen(&interface);
+        assert!(screen.contains("Pieces"));
+        assert!(screen.contains("Waiting for the opponent."));
+    }
+
+    #[test]
+    fn test_warning_and_quarto() {
+        let interface = interface(vec![KeyCode::Char('y'), KeyCode::Enter]);
+        interface.warn_player("Space 0 is not empty, pick another one!");
+        assert!(screen(&interface).contains("Space 0 is not empty"));
+        assert!(interface.ask_quarto(&Board::new()));
+        assert!(!interface.ask_quarto(&Board::new()));
+    }
+
+    #[test]
+    fn test_replay_keys() {
+        let mut record = crate::record::GameRecord::new();
+        for (ply, (piece, index)) in [(15, 9), (0, 0), (3, 5)].into_iter().enumerate() {
+            record.push(crate::record::Turn {
+                giver: ply % 2,
+                piece,
+                placer: 1 - ply % 2,
+                index,
+                quarto_called: false,
+            });
+        }
+        let mut game = Replay::new(record).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        let keys = vec![
+            KeyCode::Right,
+            KeyCode::End,
+            KeyCode::Left,
+            KeyCode::Char('1'),
+        ];
+        replay(&mut terminal, keys.into_iter(), &mut game).unwrap();
+        assert_eq!(game.ply(), 2);
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Move 2/3"));
+        assert!(screen.contains("Jump to move 1"));
+        let keys = vec![KeyCode::Enter, KeyCode::Char('q'), KeyCode::Right];
+        replay(&mut terminal, keys.into_iter(), &mut game).unwrap();
+        assert_eq!(game.ply(), 2);
+    }
+
+    #[test]
+    fn test_puzzle_mode() {
+        let puzzle: Puzzle = "89a./..../..../...0 b 1; win in 1; D1".parse().unwrap();
+        // The highlight starts on the first empty cell, D1.
+        let interface = interface(vec![KeyCode::Enter, KeyCode::Down, KeyCode::Enter]);
+        assert_eq!(puzzles(&interface, &[puzzle, puzzle, puzzle]), 1);
+        assert!(interface.closed());
+        let interface = self::interface(vec![KeyCode::Char('q')]);
+        assert_eq!(puzzles(&interface, &[puzzle]), 0);
+    }
+
+    #[test]
+    fn test_clock_in_status() {
+        let interface = interface(vec![KeyCode::Enter]);
+        interface.show_clock(Duration::from_secs(95));
+        interface.prompt_for_piece(&Board::new());
+        assert!(!screen(&interface).contains("Time left"));
+        // The ticks without a key redraw the clock, which runs down meanwhile.
+        let terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        let interface = TuiInterface::new(terminal, vec![KeyCode::Null; 2].into_iter());
+        interface.view.borrow_mut().prompt = Prompt::Piece;
+        interface.show_clock(Duration::from_millis(2900));
+        assert_eq!(interface.tick(), None);
+        assert!(screen(&interface).contains("Time left: 0:02."));
+        std::thread::sleep(Duration::from_secs(1));
+        assert_eq!(interface.tick(), None);
+        assert!(screen(&interface).contains("Time left: 0:01."));
+        let view = View {
+            prompt: Prompt::Piece,
+            deadline: Some(Instant::now() + Duration::from_millis(95_500)),
+            ..View::new()
+        };
+        assert!(view.status()[0].to_string().ends_with("Time left: 1:35."));
+    }
+
+    #[test]
+    fn test_chat_in_sidebar() {
+        let lines = Arc::new(Mutex::new(vec!["bob: good luck".to_string()]));
+        let said = lines.clone();
+        let mut keys = vec![KeyCode::Tab];
+        keys.extend("hi q".chars().map(KeyCode::Char));
+        keys.extend([KeyCode::Enter, KeyCode::Tab, KeyCode::Esc, KeyCode::Enter]);
+        let terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        let interface =
+            TuiInterface::new(terminal, keys.into_iter()).with_chat(lines, move |text| {
+                said.lock().unwrap().push(format!("alice: {}", text));
+                Ok(())
+            });
+        // Typing q and escape in the chat does not quit.
+        assert_eq!(interface.prompt_for_piece(&Board::new()), 0);
+        let screen = screen(&interface);
+        assert!(screen.contains("Chat"));
+        assert!(screen.contains("bob: good luck"));
+        assert!(screen.contains("alice: hi q"));
+    }
+
+    #[test]
+    fn test_quit_and_closed_keys() {
+        let interface = interface(vec![KeyCode::Char('q')]);
+        assert_eq!(interface.prompt_for_piece(&Board::new()), u8::MAX);
+        assert!(interface.closed());
+        let interface = self::interface(vec![]);
+        assert!(!interface.ask_quarto(&Board::new()));
+        assert!(interface.closed());
+    }
+}
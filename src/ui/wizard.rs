@@ -0,0 +1,185 @@
+// The first-run wizard, which asks a few questions in the terminal to set up the profile.
+// Every question has a default, so pressing enter all the way through gives a working setup.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{profile::Profile, strategy::Difficulty};
+
+/// Asks the questions of the wizard on `output` and reads the answers from `input`.
+struct Wizard<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    /// Set when the input is closed, the remaining questions take their default.
+    closed: bool,
+}
+
+impl<R: BufRead, W: Write> Wizard<R, W> {
+    /// Print `question` with its `hint` and read the answer.
+    /// Returns `None` for an empty answer, or when the input is closed.
+    fn read(&mut self, question: &str, hint: &str) -> io::Result<Option<String>> {
+        if self.closed {
+            return Ok(None);
+        }
+        write!(self.output, "{} [{}] ", question, hint)?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            self.closed = true;
+            writeln!(self.output)?;
+        }
+        let answer = line.trim();
+        Ok((!answer.is_empty()).then(|| answer.to_string()))
+    }
+
+    /// Ask `question` until `parse` accepts the answer, an empty answer picks `default`.
+    fn ask<T>(
+        &mut self,
+        question: &str,
+        hint: &str,
+        default: T,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> io::Result<T> {
+        loop {
+            let Some(answer) = self.read(question, hint)? else {
+                return Ok(default);
+            };
+            match parse(&answer.to_lowercase()) {
+                Some(value) => return Ok(value),
+                None => writeln!(self.output, "Please answer {}.", hint)?,
+            }
+        }
+    }
+
+    /// Ask a yes or no question.
+    fn confirm(&mut self, question: &str, default: bool) -> io::Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        self.ask(question, hint, default, |answer| match answer {
+            "y" | "yes" => Some(true),
+            "n" | "no" => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Ask for a line of free text.
+    fn text(&mut self, question: &str, default: &str) -> io::Result<String> {
+        Ok(self
+            .read(question, default)?
+            .unwrap_or_else(|| default.to_string()))
+    }
+}
+
+/// Run the wizard, asking for a name, the difficulty, colors and saving games.
+/// `color` is the detected color support, offered as the default answer.
+pub fn run<R: BufRead, W: Write>(input: R, output: W, color: bool) -> io::Result<Profile> {
+    let mut wizard = Wizard {
+        input,
+        output,
+        closed: false,
+    };
+    let defaults = Profile::default();
+    writeln!(
+        wizard.output,
+        "Welcome to Quarto! Answer a few questions to get started, or press enter to keep the default."
+    )?;
+    let name = wizard.text("What is your name?", &defaults.name)?;
+    let difficulty = wizard.ask(
+        "How strong should the computer play?",
+        "easy/medium/hard/expert",
+        defaults.difficulty,
+        |answer| answer.parse::<Difficulty>().ok(),
+    )?;
+    let color = wizard.confirm("Use colors?", color)?;
+    let autosave = wizard.confirm(
+        "Save games in progress, so they can be resumed?",
+        defaults.autosave,
+    )?;
+    let history = wizard.confirm("Keep a history of finished games?", defaults.history)?;
+    Ok(Profile {
+        name,
+        difficulty,
+        color,
+        autosave,
+        history,
+        ..Profile::default()
+    })
+}
+
+/// Ask if the game that was interrupted last time should be resumed, yes by default.
+pub fn ask_resume<R: BufRead, W: Write>(input: R, output: W) -> io::Result<bool> {
+    let mut wizard = Wizard {
+        input,
+        output,
+        closed: false,
+    };
+    wizard.confirm("The last game was interrupted. Resume it?", true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(input: &str, color: bool) -> (Profile, String) {
+        let mut output: Vec<u8> = Vec::new();
+        let profile = run(input.as_bytes(), &mut output, color).unwrap();
+        (profile, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_answers() {
+        let (profile, _) = answer("Ada\nhard\nn\nno\ny\n", true);
+        assert_eq!(
+            profile,
+            Profile {
+                name: "Ada".to_string(),
+                difficulty: Difficulty::Hard,
+                color: false,
+                autosave: false,
+                history: true,
+                ..Profile::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_defaults_on_empty_answers() {
+        let (profile, _) = answer("\n\n\n\n\n", true);
+        assert_eq!(
+            profile,
+            Profile {
+                color: true,
+                ..Profile::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_answer_asks_again() {
+        let (profile, output) = answer("Ada\nimpossible\neasy\n", false);
+        assert_eq!(profile.difficulty, Difficulty::Easy);
+        assert!(output.contains("Please answer easy/medium/hard/expert."));
+    }
+
+    #[test]
+    fn test_closed_input_keeps_defaults() {
+        let (profile, _) = answer("Ada\n", false);
+        assert_eq!(
+            profile,
+            Profile {
+                name: "Ada".to_string(),
+                ..Profile::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_ask_resume() {
+        let mut output: Vec<u8> = Vec::new();
+        assert!(ask_resume("\n".as_bytes(), &mut output).unwrap());
+        assert!(!ask_resume("maybe\nn\n".as_bytes(), &mut output).unwrap());
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("Resume it? [Y/n]")
+        );
+    }
+}
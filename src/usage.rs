@@ -0,0 +1,275 @@
+// Usage statistics of the player at this computer, kept in the profile: games per mode, results per difficulty and time played.
+// They never leave the computer, unless the player exports a report and shares it themselves.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{simulate::SimulationStats, strategy::Difficulty};
+
+/// The kinds of games a person can play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mode {
+    /// Against a computer player.
+    Computer,
+    /// Two people at the same terminal.
+    Local,
+    /// The fog variant, where the pieces on the board are hidden.
+    Fog,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Computer => write!(f, "computer"),
+            Mode::Local => write!(f, "local"),
+            Mode::Fog => write!(f, "fog"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "computer" => Ok(Mode::Computer),
+            "local" => Ok(Mode::Local),
+            "fog" => Ok(Mode::Fog),
+            _ => Err(format!("expected computer, local or fog, got '{}'", s)),
+        }
+    }
+}
+
+/// The results of the player against one opponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Record {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+}
+
+impl Record {
+    /// The number of finished games.
+    pub fn games(&self) -> u64 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The share of finished games that were won, between 0 and 1.
+    pub fn win_rate(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => self.wins as f64 / games as f64,
+        }
+    }
+}
+
+/// Aggregate statistics over all games the player played on this computer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Usage {
+    /// The number of games by mode.
+    pub games: BTreeMap<Mode, u64>,
+    /// The results against the computer by difficulty.
+    pub versus: BTreeMap<Difficulty, Record>,
+    /// The time spent in games.
+    pub time_played: Duration,
+    /// The first game that was counted, in seconds since the Unix epoch.
+    pub since: Option<u64>,
+}
+
+impl Usage {
+    /// Count a batch of games in `mode`, with the results in `stats`.
+    /// `versus` is the difficulty of the computer opponent, with the player as the first player in `stats`.
+    pub fn record(&mut self, mode: Mode, versus: Option<Difficulty>, stats: &SimulationStats) {
+        if stats.games == 0 {
+            return;
+        }
+        *self.games.entry(mode).or_default() += stats.games as u64;
+        if let Some(difficulty) = versus {
+            let record = self.versus.entry(difficulty).or_default();
+            record.wins += stats.wins as u64;
+            record.draws += stats.draws as u64;
+            record.losses += stats.losses as u64;
+        }
+        self.time_played += stats.elapsed;
+        if self.since.is_none() {
+            self.since = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|now| now.as_secs());
+        }
+    }
+
+    /// The number of games over all modes.
+    pub fn total_games(&self) -> u64 {
+        self.games.values().sum()
+    }
+
+    /// Write the statistics as lines of the profile.
+    pub(crate) fn write(&self, body: &mut String) {
+        for (mode, games) in &self.games {
+            body.push_str(&format!("games {} {}\n", mode, games));
+        }
+        for (difficulty, record) in &self.versus {
+            body.push_str(&format!(
+                "versus {} {} {} {}\n",
+                difficulty, record.wins, record.draws, record.losses
+            ));
+        }
+        if !self.time_played.is_zero() {
+            body.push_str(&format!("time {}\n", self.time_played.as_secs()));
+        }
+        if let Some(since) = self.since {
+            body.push_str(&format!("since {}\n", since));
+        }
+    }
+
+    /// Read a line of the profile written by `write`, or return `false` if `key` is not a statistic.
+    pub(crate) fn read(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        let number = |text: &str| {
+            text.parse::<u64>()
+                .map_err(|_| format!("expected a number, got '{}'", text))
+        };
+        let values: Vec<&str> = value.split_whitespace().collect();
+        match (key, &values[..]) {
+            ("games", &[mode, games]) => {
+                self.games.insert(mode.parse()?, number(games)?);
+            }
+            ("versus", &[difficulty, wins, draws, losses]) => {
+                let record = Record {
+                    wins: number(wins)?,
+                    draws: number(draws)?,
+                    losses: number(losses)?,
+                };
+                self.versus.insert(difficulty.parse()?, record);
+            }
+            ("time", &[seconds]) => self.time_played = Duration::from_secs(number(seconds)?),
+            ("since", &[seconds]) => self.since = Some(number(seconds)?),
+            ("games" | "versus" | "time" | "since", _) => {
+                return Err(format!("malformed statistic '{} {}'", key, value));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// A summary to share, with the games per mode and the win rate against every difficulty.
+    pub fn report(&self, name: &str) -> String {
+        let mut report = format!("Quarto statistics of {}\n", name);
+        let minutes = self.time_played.as_secs() / 60;
+        report.push_str(&format!(
+            "{} games in {}h {:02}m\n",
+            self.total_games(),
+            minutes / 60,
+            minutes % 60
+        ));
+        if let Some(since) = self.since {
+            report.push_str(&format!("Tracked since {}\n", format_date(since)));
+        }
+        report.push('\n');
+        for (mode, games) in &self.games {
+            report.push_str(&format!("{:<12}{:>8} games\n", mode.to_string(), games));
+        }
+        if !self.versus.is_empty() {
+            report.push_str(&format!(
+                "\n{:<12}{:>8}{:>8}{:>8}{:>8}{:>10}\n",
+                "Versus", "Games", "Wins", "Draws", "Losses", "Win rate"
+            ));
+        }
+        for (difficulty, record) in &self.versus {
+            report.push_str(&format!(
+                "{:<12}{:>8}{:>8}{:>8}{:>8}{:>9.1}%\n",
+                difficulty.to_string(),
+                record.games(),
+                record.wins,
+                record.draws,
+                record.losses,
+                record.win_rate() * 100.0
+            ));
+        }
+        report
+    }
+}
+
+/// Format `seconds` since the Unix epoch as a `year-month-day` date in UTC.
+pub(crate) fn format_date(seconds: u64) -> String {
+    // Count in eras of 400 years from March 1st of year 0, so leap days fall at the end of a year.
+    let days = (seconds / 86400) as i64 + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(wins: u32, draws: u32, losses: u32) -> SimulationStats {
+        SimulationStats {
+            games: wins + draws + losses,
+            wins,
+            draws,
+            losses,
+            elapsed: Duration::from_secs(90),
+            ..SimulationStats::default()
+        }
+    }
+
+    #[test]
+    fn test_record_accumulates() {
+        let mut usage = Usage::default();
+        usage.record(Mode::Computer, Some(Difficulty::Hard), &stats(1, 0, 2));
+        usage.record(Mode::Computer, Some(Difficulty::Hard), &stats(1, 1, 0));
+        usage.record(Mode::Local, None, &stats(0, 1, 0));
+        assert_eq!(usage.total_games(), 6);
+        assert_eq!(usage.games[&Mode::Computer], 5);
+        let record = usage.versus[&Difficulty::Hard];
+        assert_eq!((record.wins, record.draws, record.losses), (2, 1, 2));
+        assert_eq!(record.win_rate(), 0.4);
+        assert_eq!(usage.time_played, Duration::from_secs(270));
+        assert!(usage.since.is_some());
+    }
+
+    #[test]
+    fn test_lines_round_trip() {
+        let mut usage = Usage::default();
+        usage.record(Mode::Fog, Some(Difficulty::Easy), &stats(3, 0, 1));
+        let mut body = String::new();
+        usage.write(&mut body);
+        let mut read = Usage::default();
+        for line in body.lines() {
+            let (key, value) = line.split_once(' ').unwrap();
+            assert_eq!(read.read(key, value), Ok(true));
+        }
+        assert_eq!(read, usage);
+        assert_eq!(read.read("name", "Ada"), Ok(false));
+        assert!(read.read("games", "chess 3").is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let mut usage = Usage::default();
+        assert!(usage.report("Ada").contains("0 games"));
+        usage.record(Mode::Computer, Some(Difficulty::Medium), &stats(1, 0, 1));
+        let report = usage.report("Ada");
+        assert!(report.starts_with("Quarto statistics of Ada"));
+        assert!(report.contains("medium"));
+        assert!(report.contains("50.0%"));
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_791_158_400), "2026-10-05");
+    }
+}
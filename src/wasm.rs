@@ -0,0 +1,157 @@
+// Bindings for JavaScript with wasm-bindgen, so a browser front-end can embed the engine and its rules.
+// Only built with the `wasm` feature, the pieces and cells are the numbers of `Board`.
+// Build the module with `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    board::Board,
+    player::Player,
+    random::SharedRng,
+    registry::{PlayerOptions, Registry},
+};
+
+/// A board for JavaScript, with the moves that are allowed on it.
+#[wasm_bindgen(js_name = Board)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmBoard {
+    board: Board,
+}
+
+#[wasm_bindgen(js_class = Board)]
+impl WasmBoard {
+    /// Create an empty board.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmBoard::default()
+    }
+
+    /// Read the board of a position in the notation of `Board::to_fen`.
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: &str) -> Result<WasmBoard, String> {
+        Board::from_fen(fen).map(|(board, _, _)| WasmBoard { board })
+    }
+
+    /// Write the position with `in_hand` handed over and `mover` to place it.
+    #[wasm_bindgen(js_name = toFen)]
+    pub fn to_fen(&self, in_hand: Option<u8>, mover: usize) -> String {
+        self.board.to_fen(in_hand, mover)
+    }
+
+    /// Place `piece` on `index`, or tell why that is not allowed.
+    #[wasm_bindgen(js_name = putPiece)]
+    pub fn put_piece(&mut self, piece: u8, index: u8) -> Result<(), String> {
+        self.board
+            .put_piece(piece, index)
+            .map_err(|e| e.to_string())
+    }
+
+    /// The piece on `index`, if any.
+    #[wasm_bindgen(js_name = pieceAt)]
+    pub fn piece_at(&self, index: u8) -> Option<u8> {
+        self.board.piece_at(index)
+    }
+
+    /// The empty cells.
+    #[wasm_bindgen(js_name = emptySpaces)]
+    pub fn empty_spaces(&self) -> Vec<u8> {
+        self.board.empty_spaces()
+    }
+
+    /// The pieces that are not on the board yet.
+    #[wasm_bindgen(js_name = validPieces)]
+    pub fn valid_pieces(&self) -> Vec<u8> {
+        self.board.valid_pieces()
+    }
+
+    /// The pieces that do not let the opponent win at once.
+    #[wasm_bindgen(js_name = safePieces)]
+    pub fn safe_pieces(&self) -> Vec<u8> {
+        self.board.safe_pieces()
+    }
+
+    /// Check if a line is won.
+    #[wasm_bindgen(js_name = hasWinner)]
+    pub fn has_winner(&self) -> bool {
+        self.board.has_winner()
+    }
+
+    /// Check if the game is won or the board is full.
+    #[wasm_bindgen(js_name = gameOver)]
+    pub fn game_over(&self) -> bool {
+        self.board.game_over()
+    }
+
+    /// The winning lines and their shared attributes, like `row 0: all hole`.
+    #[wasm_bindgen(js_name = winningLines)]
+    pub fn winning_lines(&self) -> Vec<String> {
+        self.board
+            .winning_lines()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+/// A computer player of the registry, asked for its decisions on a `Board`.
+#[wasm_bindgen]
+pub struct Engine {
+    player: Box<dyn Player>,
+}
+
+#[wasm_bindgen]
+impl Engine {
+    /// Create the computer player `name` of the registry, like `smart` or `mcts`, searching `depth` turns
+    /// or `iterations` playouts ahead. A `seed` makes its random decisions repeat.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        name: &str,
+        depth: u8,
+        iterations: u32,
+        seed: Option<u64>,
+    ) -> Result<Engine, String> {
+        let options = PlayerOptions {
+            depth,
+            iterations,
+            rng: seed.map_or_else(SharedRng::new, SharedRng::seeded),
+            ..PlayerOptions::default()
+        };
+        Ok(Engine {
+            player: Registry::default().create_computer(name, &options)?,
+        })
+    }
+
+    /// The piece to hand over to the opponent.
+    #[wasm_bindgen(js_name = choosePiece)]
+    pub fn choose_piece(&self, board: &WasmBoard) -> Option<u8> {
+        self.player.get_piece(&board.board)
+    }
+
+    /// The cell to place `piece` on.
+    #[wasm_bindgen(js_name = chooseMove)]
+    pub fn choose_move(&self, board: &WasmBoard, piece: u8) -> Option<u8> {
+        self.player.get_move(&board.board, piece)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_plays_on_board() {
+        let mut board = WasmBoard::from_fen("89a./..../..../...0 b 1").unwrap();
+        assert!(board.put_piece(8, 3).is_err());
+        assert_eq!(board.empty_spaces().len(), 12);
+        let engine = Engine::new("smart", 1, 100, Some(2805)).unwrap();
+        assert_eq!(engine.choose_move(&board, 11), Some(3));
+        board.put_piece(11, 3).unwrap();
+        assert!(board.has_winner());
+        assert_eq!(
+            board.winning_lines(),
+            vec!["row 0: all hole, round".to_string()]
+        );
+        assert!(Engine::new("human", 1, 100, None).is_err());
+        assert!(Engine::new("grandmaster", 1, 100, None).is_err());
+    }
+}
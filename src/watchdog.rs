@@ -0,0 +1,250 @@
+// A watchdog for players that stop answering, so a hung interface or a buggy bot cannot freeze a game.
+// The player runs on a thread of its own, a call that misses the deadline is abandoned and the player forfeits the game.
+
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{board::Board, player::Player};
+
+/// A call of the player, run on the thread that owns it.
+type Job = Box<dyn FnOnce(&dyn Player) + Send>;
+
+/// Wraps a `Player` and gives every call a hard timeout.
+/// The player lives on a thread of its own that runs its calls one by one, so it does not need to be `Sync`.
+/// In a game with a clock the next decision gets no more than the time the player has left, so a hung player loses on time.
+/// A call that times out or panics is abandoned: its thread keeps running in the background, but the game goes on.
+/// From then on the player stalled, so it does not decide anymore and forfeits the game.
+#[derive(Debug)]
+pub struct Watchdog {
+    jobs: Sender<Job>,
+    timeout: Duration,
+    /// The time left on the clock for the next decision, when the game told it.
+    time_left: Mutex<Option<Duration>>,
+    stalled: AtomicBool,
+}
+
+impl Watchdog {
+    /// Guard `player` with a timeout of a minute per call.
+    pub fn new<P: Player + Send + 'static>(player: P) -> Self {
+        Watchdog::spawn(move || Ok(Box::new(player) as Box<dyn Player>))
+            .expect("a player that is already built cannot fail to build")
+    }
+
+    /// Guard the player that `make` builds on the thread of the player, with a timeout of a minute per call.
+    /// Players that cannot be sent to another thread, like those of the registry, are guarded this way.
+    /// Returns the error of `make` if it cannot build the player.
+    pub fn spawn(
+        make: impl FnOnce() -> Result<Box<dyn Player>, String> + Send + 'static,
+    ) -> Result<Self, String> {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (built, outcome) = mpsc::channel();
+        thread::spawn(move || {
+            let player = match make() {
+                Ok(player) => player,
+                Err(e) => {
+                    let _ = built.send(Err(e));
+                    return;
+                }
+            };
+            let _ = built.send(Ok(()));
+            for job in queue {
+                job(player.as_ref());
+            }
+        });
+        outcome
+            .recv()
+            .map_err(|_| "the player panicked while it was built".to_string())??;
+        Ok(Watchdog {
+            jobs,
+            timeout: Duration::from_secs(60),
+            time_left: Mutex::new(None),
+            stalled: AtomicBool::new(false),
+        })
+    }
+
+    /// Abandon calls that take longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Check if a call of the player timed out or panicked.
+    pub fn stalled(&self) -> bool {
+        self.stalled.load(Ordering::Relaxed)
+    }
+
+    /// The time a decision may take: the timeout, or the time left on the clock if that is shorter.
+    fn deadline(&self) -> Duration {
+        match self
+            .time_left
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            Some(time) => time.min(self.timeout),
+            None => self.timeout,
+        }
+    }
+
+    /// Run `call` on the thread of the player, and wait for its answer until `timeout`.
+    fn guard<T: Send + 'static>(
+        &self,
+        timeout: Duration,
+        call: impl FnOnce(&dyn Player) -> T + Send + 'static,
+    ) -> Option<T> {
+        if self.stalled() {
+            return None;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move |player| {
+            let _ = sender.send(call(player));
+        });
+        // A panic ends the thread of the player, which drops the job and ends the wait like a timeout.
+        let answer = match self.jobs.send(job) {
+            Ok(()) => receiver.recv_timeout(timeout).ok(),
+            Err(_) => None,
+        };
+        if answer.is_none() {
+            self.stalled.store(true, Ordering::Relaxed);
+        }
+        answer
+    }
+}
+
+impl Player for Watchdog {
+    fn get_piece(&self, board: &Board) -> Option<u8> {
+        let board = *board;
+        self.guard(self.deadline(), move |player| player.get_piece(&board))
+            .flatten()
+    }
+
+    fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+        let board = *board;
+        self.guard(self.deadline(), move |player| {
+            player.get_move(&board, piece)
+        })
+        .flatten()
+    }
+
+    /// A call that does not come in time is no call.
+    fn quarto(&self, board: &Board) -> bool {
+        let board = *board;
+        self.guard(self.deadline(), move |player| player.quarto(&board))
+            .unwrap_or(false)
+    }
+
+    fn forfeited(&self) -> bool {
+        self.stalled()
+            || self
+                .guard(self.timeout, |player| player.forfeited())
+                .unwrap_or(true)
+    }
+
+    fn time_left(&self, time: Duration) {
+        *self.time_left.lock().unwrap_or_else(|e| e.into_inner()) = Some(time);
+        // The player hears it before its next call, without waiting for it.
+        let _ = self
+            .jobs
+            .send(Box::new(move |player: &dyn Player| player.time_left(time)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        QuartoGame,
+        clock::TimeControl,
+        driver::GameDriver,
+        game::GameResult,
+        player::ComputerPlayer,
+        strategy::{DeterministicStrategy, Strategy},
+    };
+
+    use super::*;
+
+    /// Plays like the deterministic strategy, but hangs or panics after handing over `after` pieces it hands over.
+    struct Faulty {
+        after: usize,
+        panics: bool,
+        given: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Faulty {
+        fn new(after: usize, panics: bool) -> Self {
+            Faulty {
+                after,
+                panics,
+                given: Default::default(),
+            }
+        }
+    }
+
+    impl Player for Faulty {
+        fn get_piece(&self, board: &Board) -> Option<u8> {
+            if self.given.fetch_add(1, Ordering::Relaxed) >= self.after {
+                if self.panics {
+                    panic!("a buggy bot");
+                }
+                thread::sleep(Duration::from_secs(3600));
+            }
+            DeterministicStrategy.get_piece(board)
+        }
+
+        fn get_move(&self, board: &Board, piece: u8) -> Option<u8> {
+            DeterministicStrategy.get_move(board, piece)
+        }
+
+        fn quarto(&self, board: &Board) -> bool {
+            board.has_winner()
+        }
+    }
+
+    #[test]
+    fn test_stalled_player_forfeits() {
+        for (seat, panics) in [(0, false), (1, false), (0, true)] {
+            let faulty =
+                Watchdog::new(Faulty::new(1, panics)).with_timeout(Duration::from_millis(50));
+            let honest = Box::new(ComputerPlayer::new(DeterministicStrategy));
+            let mut game = if seat == 0 {
+                QuartoGame::new(Box::new(faulty), honest)
+            } else {
+                QuartoGame::new(honest, Box::new(faulty))
+            };
+            assert_eq!(game.play(), GameResult::Win(1 - seat));
+            assert_eq!(game.history().len(), 2 + seat);
+        }
+    }
+
+    #[test]
+    fn test_hung_player_loses_on_time() {
+        let hung = Watchdog::new(Faulty::new(1, false));
+        let mut game = QuartoGame::new(ComputerPlayer::new(DeterministicStrategy), hung)
+            .with_clock(TimeControl::new().with_per_move(Duration::from_millis(50)));
+        assert_eq!(game.play(), GameResult::Timeout(1));
+        assert_eq!(game.history().len(), 3);
+    }
+
+    #[test]
+    fn test_answers_in_time_pass_through() {
+        let first = Watchdog::new(ComputerPlayer::new(DeterministicStrategy));
+        let second = Watchdog::new(ComputerPlayer::new(DeterministicStrategy));
+        let mut driver = GameDriver::new();
+        let result = driver.play([&first, &second]);
+        let mut game = QuartoGame::new(
+            ComputerPlayer::new(DeterministicStrategy),
+            ComputerPlayer::new(DeterministicStrategy),
+        );
+        assert_eq!(result, game.play());
+        assert!(!first.stalled() && !second.stalled());
+        let stalled = Watchdog::new(Faulty::new(0, false)).with_timeout(Duration::from_millis(10));
+        let mut driver = GameDriver::new();
+        assert_eq!(driver.play([&stalled, &first]), GameResult::Win(1));
+    }
+}